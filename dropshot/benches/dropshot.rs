@@ -0,0 +1,273 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Benchmarks for Dropshot's request handling path
+//!
+//! The scenarios here are meant to stay fixed from release to release so that
+//! results can be compared across versions.  To check a change for
+//! regressions, save a baseline before making it and compare against that
+//! baseline afterwards:
+//!
+//! ```text
+//! $ cargo bench -p dropshot -- --save-baseline before
+//! $ (make changes)
+//! $ cargo bench -p dropshot -- --baseline before
+//! ```
+//!
+//! Scenarios are grouped by the part of request handling they exercise:
+//!
+//! * "routing": looking up the handler for a request path in routers of
+//!   different sizes
+//! * "serialization": producing a response from a handler's return value
+//! * "round_trip": complete requests from an in-process client to a running
+//!   server over loopback, including extraction of path, query, and body
+//!   parameters
+
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BatchSize;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use criterion::Throughput;
+use dropshot::endpoint;
+use dropshot::ApiDescription;
+use dropshot::ApiEndpoint;
+use dropshot::ConfigDropshot;
+use dropshot::HttpError;
+use dropshot::HttpResponse;
+use dropshot::HttpResponseOk;
+use dropshot::HttpServer;
+use dropshot::HttpServerStarter;
+use dropshot::Path;
+use dropshot::Query;
+use dropshot::RequestContext;
+use dropshot::TypedBody;
+use dropshot::CONTENT_TYPE_JSON;
+use http::Method;
+use http::Request;
+use http::StatusCode;
+use hyper::client::HttpConnector;
+use hyper::Body;
+use hyper::Client;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use std::hint::black_box;
+
+/// Number of items in the responses used by the serialization and round trip
+/// scenarios
+const LIST_SIZES: &[usize] = &[1, 100, 1000];
+
+/// Number of endpoints in the routers used by the routing scenarios
+const ROUTER_SIZES: &[usize] = &[10, 100, 1000];
+
+#[derive(Clone, Deserialize, JsonSchema, Serialize)]
+struct Item {
+    id: u64,
+    name: String,
+    description: String,
+    tags: Vec<String>,
+}
+
+fn items(count: usize) -> Vec<Item> {
+    (0..count as u64)
+        .map(|id| Item {
+            id,
+            name: format!("item-{}", id),
+            description: String::from("an item used for benchmarking"),
+            tags: vec![String::from("alpha"), String::from("beta")],
+        })
+        .collect()
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct ItemPath {
+    id: u64,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct PartPath {
+    thing: String,
+    part: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct ListQuery {
+    count: usize,
+}
+
+#[endpoint {
+    method = GET,
+    path = "/ping",
+}]
+async fn bench_ping(
+    _rqctx: RequestContext<()>,
+) -> Result<HttpResponseOk<()>, HttpError> {
+    Ok(HttpResponseOk(()))
+}
+
+#[endpoint {
+    method = GET,
+    path = "/items",
+}]
+async fn bench_list_items(
+    _rqctx: RequestContext<()>,
+    query: Query<ListQuery>,
+) -> Result<HttpResponseOk<Vec<Item>>, HttpError> {
+    Ok(HttpResponseOk(items(query.into_inner().count)))
+}
+
+#[endpoint {
+    method = PUT,
+    path = "/items/{id}",
+}]
+async fn bench_put_item(
+    _rqctx: RequestContext<()>,
+    path: Path<ItemPath>,
+    body: TypedBody<Item>,
+) -> Result<HttpResponseOk<Item>, HttpError> {
+    let mut item = body.into_inner();
+    item.id = path.into_inner().id;
+    Ok(HttpResponseOk(item))
+}
+
+/// Handler for the endpoints in the routing scenarios.  This is registered
+/// many times, so it isn't declared with `#[endpoint]`.
+async fn get_part(
+    _rqctx: RequestContext<()>,
+    path: Path<PartPath>,
+) -> Result<HttpResponseOk<()>, HttpError> {
+    let PartPath { thing, part } = path.into_inner();
+    black_box((thing, part));
+    Ok(HttpResponseOk(()))
+}
+
+fn bench_routing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("routing");
+    for &size in ROUTER_SIZES {
+        let mut api = ApiDescription::new();
+        for i in 0..size {
+            let path = format!("/things{}/{{thing}}/parts/{{part}}", i);
+            let endpoint = ApiEndpoint::new(
+                format!("get_part_{}", i),
+                get_part,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                &path,
+            );
+            api.register(endpoint).unwrap();
+        }
+        let router = api.into_router();
+        // Look up a route near the end of the table so that the cost of a
+        // large router isn't hidden by an early match.
+        let path = format!("/things{}/widget/parts/sprocket", size - 1);
+
+        group.bench_with_input(
+            BenchmarkId::new("lookup", size),
+            &path,
+            |b, path| {
+                b.iter(|| {
+                    let result = router
+                        .lookup_route(&Method::GET, path.as_str().into())
+                        .unwrap();
+                    black_box(result.variables);
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_serialization(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serialization");
+    for &size in LIST_SIZES {
+        let list = items(size);
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(
+            BenchmarkId::new("list", size),
+            &list,
+            |b, list| {
+                b.iter_batched(
+                    || list.clone(),
+                    |list| black_box(HttpResponseOk(list).to_result().unwrap()),
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+/// A server and client used for the round trip scenarios
+struct RoundTrip {
+    server: HttpServer<()>,
+    client: Client<HttpConnector>,
+}
+
+impl RoundTrip {
+    fn new() -> RoundTrip {
+        let mut api = ApiDescription::new();
+        api.register(bench_ping).unwrap();
+        api.register(bench_list_items).unwrap();
+        api.register(bench_put_item).unwrap();
+
+        // Keep logging quiet so that it doesn't dominate the measurements.
+        let log = slog::Logger::root(slog::Discard, slog::o!());
+        let server =
+            HttpServerStarter::new(&ConfigDropshot::default(), api, (), &log)
+                .unwrap()
+                .start();
+        RoundTrip { server, client: Client::new() }
+    }
+
+    async fn request(&self, method: Method, path: &str, body: Body) {
+        let uri = format!("http://{}{}", self.server.local_addr(), path);
+        let request =
+            Request::builder().method(method).uri(uri).body(body).unwrap();
+        let response = self.client.request(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        black_box(hyper::body::to_bytes(response.into_body()).await.unwrap());
+    }
+}
+
+fn bench_round_trip(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let round_trip = runtime.block_on(async {
+        let round_trip = RoundTrip::new();
+        round_trip.server.wait_for_ready().await.unwrap();
+        round_trip
+    });
+
+    let mut group = c.benchmark_group("round_trip");
+    group.bench_function("ping", |b| {
+        b.to_async(&runtime)
+            .iter(|| round_trip.request(Method::GET, "/ping", Body::empty()))
+    });
+
+    for &size in LIST_SIZES {
+        let path = format!("/items?count={}", size);
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(
+            BenchmarkId::new("list", size),
+            &path,
+            |b, path| {
+                b.to_async(&runtime).iter(|| {
+                    round_trip.request(Method::GET, path, Body::empty())
+                })
+            },
+        );
+    }
+
+    let item = serde_json::to_vec(&items(1)[0]).unwrap();
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("put_item", |b| {
+        b.to_async(&runtime).iter(|| {
+            round_trip.request(Method::PUT, "/items/7", item.clone().into())
+        })
+    });
+    group.finish();
+
+    runtime.block_on(round_trip.server.close()).unwrap();
+}
+
+criterion_group!(benches, bench_routing, bench_serialization, bench_round_trip);
+criterion_main!(benches);
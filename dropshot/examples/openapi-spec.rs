@@ -0,0 +1,79 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Example of a server that can also print its OpenAPI document without
+//! starting.  Building the `ApiDescription` doesn't require binding a socket or
+//! creating a logger, so the same function can construct the API for both
+//! purposes.  This is handy for checking a spec into the repository and
+//! verifying in CI that it's up to date:
+//!
+//! ```text
+//! cargo run --example openapi-spec -- openapi             # print JSON
+//! cargo run --example openapi-spec -- openapi spec.yaml   # write YAML
+//! cargo run --example openapi-spec                        # run the server
+//! ```
+
+use dropshot::{
+    endpoint, ApiDescription, ConfigLogging, ConfigLoggingLevel, HttpError,
+    HttpResponseOk, HttpServerStarter, RequestContext,
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+/// The greeting returned by the server
+#[derive(Serialize, JsonSchema)]
+struct Greeting {
+    message: String,
+}
+
+/// Say hello
+#[endpoint {
+    method = GET,
+    path = "/hello",
+}]
+async fn get_hello(
+    _rqctx: RequestContext<()>,
+) -> Result<HttpResponseOk<Greeting>, HttpError> {
+    Ok(HttpResponseOk(Greeting { message: "hello".to_string() }))
+}
+
+/// Construct the description of the API, shared by the server and by the
+/// `openapi` subcommand.
+fn api() -> Result<ApiDescription<()>, String> {
+    let mut api = ApiDescription::new();
+    api.register(get_hello)?;
+    Ok(api)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), String> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    match args[..] {
+        ["openapi"] => {
+            return api()?
+                .openapi("Hello", "1.0.0")
+                .write(&mut std::io::stdout())
+                .map_err(|error| error.to_string());
+        }
+        ["openapi", path] => {
+            return api()?
+                .openapi("Hello", "1.0.0")
+                .write_file(path)
+                .map_err(|error| format!("{}: {}", path, error));
+        }
+        [] => (),
+        _ => return Err("usage: openapi-spec [openapi [FILE]]".to_string()),
+    }
+
+    let config_dropshot = Default::default();
+    let config_logging =
+        ConfigLogging::StderrTerminal { level: ConfigLoggingLevel::Info };
+    let log = config_logging
+        .to_logger("example-openapi-spec")
+        .map_err(|error| format!("failed to create logger: {}", error))?;
+
+    let server = HttpServerStarter::new(&config_dropshot, api()?, (), &log)
+        .map_err(|error| format!("failed to create server: {}", error))?
+        .start();
+    server.await
+}
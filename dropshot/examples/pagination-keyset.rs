@@ -0,0 +1,138 @@
+// Copyright 2023 Oxide Computer Company
+//! Example showing keyset pagination with `KeysetBounds`
+//!
+//! This is like the "pagination-basic" example, except that the client can also
+//! list projects in descending order, and the endpoint uses `KeysetBounds`
+//! rather than interpreting the page selector itself.  The projects are stored
+//! in a BTreeMap, but the endpoint also logs the SQL query that would fetch the
+//! same page from a database table.
+//!
+//! When you run this program, it will start an HTTP server on an available local
+//! port.  See the log entry to see what port it ran on.  Then use curl to use
+//! it, like this:
+//!
+//! ```ignore
+//! $ curl localhost:50568/projects?order=descending
+//! ```
+//!
+//! (Replace 50568 with whatever port your server is listening on.)
+
+use dropshot::endpoint;
+use dropshot::ApiDescription;
+use dropshot::ConfigDropshot;
+use dropshot::ConfigLogging;
+use dropshot::ConfigLoggingLevel;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::HttpServerStarter;
+use dropshot::KeysetBounds;
+use dropshot::KeysetPage;
+use dropshot::KeysetScanParams;
+use dropshot::PaginationOrder;
+use dropshot::PaginationParams;
+use dropshot::Query;
+use dropshot::RequestContext;
+use dropshot::ResultsPage;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+
+#[macro_use]
+extern crate slog;
+
+/// Object returned by our paginated endpoint
+#[derive(Clone, JsonSchema, Serialize)]
+struct Project {
+    name: String,
+    // lots more fields
+}
+
+/// Parameters describing the scan requested by the client
+#[derive(Deserialize, JsonSchema)]
+struct ProjectScanParams {
+    /// order in which to list projects (ascending by default)
+    order: Option<PaginationOrder>,
+}
+
+impl KeysetScanParams for ProjectScanParams {
+    fn order(&self) -> PaginationOrder {
+        self.order.unwrap_or(PaginationOrder::Ascending)
+    }
+}
+
+/// API endpoint for listing projects by name
+#[endpoint {
+    method = GET,
+    path = "/projects"
+}]
+async fn example_list_projects(
+    rqctx: RequestContext<BTreeMap<String, Project>>,
+    query: Query<PaginationParams<ProjectScanParams, KeysetPage<String>>>,
+) -> Result<HttpResponseOk<ResultsPage<Project>>, HttpError> {
+    let pag_params = query.into_inner();
+    let bounds = KeysetBounds::new(&pag_params, rqctx.page_limit(&pag_params)?);
+    debug!(rqctx.log, "equivalent query"; "sql" => sql_query(&bounds));
+
+    let projects: Vec<Project> =
+        bounds.select_from(rqctx.context()).into_iter().cloned().collect();
+    Ok(HttpResponseOk(bounds.results_page(projects, |p| p.name.clone())?))
+}
+
+/// Returns the SQL query that fetches the page described by `bounds` from a
+/// "projects" table.  A real implementation would bind the last-seen name as a
+/// parameter, of course, rather than quoting it.
+fn sql_query(bounds: &KeysetBounds<String>) -> String {
+    let mut sql = String::from("SELECT * FROM projects");
+    if let Some(name) = &bounds.after {
+        sql.push_str(&format!(
+            " WHERE name {} '{}'",
+            bounds.comparison().sql_operator(),
+            name.replace('\'', "''")
+        ));
+    }
+    sql.push_str(&format!(
+        " ORDER BY name {} LIMIT {}",
+        bounds.sql_order(),
+        bounds.limit
+    ));
+    sql
+}
+
+#[tokio::main]
+async fn main() -> Result<(), String> {
+    let port = std::env::args()
+        .nth(1)
+        .map(|p| p.parse::<u16>())
+        .transpose()
+        .map_err(|e| format!("failed to parse \"port\" argument: {}", e))?
+        .unwrap_or(0);
+
+    // Create 1000 projects up front.
+    let mut tree = BTreeMap::new();
+    for n in 1..1000 {
+        let name = format!("project{:03}", n);
+        let project = Project { name: name.clone() };
+        tree.insert(name, project);
+    }
+
+    // Run the Dropshot server.
+    let ctx = tree;
+    let config_dropshot = ConfigDropshot {
+        bind_address: SocketAddr::from((Ipv4Addr::LOCALHOST, port)),
+        ..Default::default()
+    };
+    let config_logging =
+        ConfigLogging::StderrTerminal { level: ConfigLoggingLevel::Debug };
+    let log = config_logging
+        .to_logger("example-pagination-keyset")
+        .map_err(|error| format!("failed to create logger: {}", error))?;
+    let mut api = ApiDescription::new();
+    api.register(example_list_projects).unwrap();
+    let server = HttpServerStarter::new(&config_dropshot, api, ctx, &log)
+        .map_err(|error| format!("failed to create server: {}", error))?
+        .start();
+    server.await
+}
@@ -92,6 +92,7 @@ use chrono::offset::TimeZone;
 use chrono::DateTime;
 use chrono::Utc;
 use dropshot::endpoint;
+use dropshot::paginate_iter;
 use dropshot::ApiDescription;
 use dropshot::ConfigDropshot;
 use dropshot::ConfigLogging;
@@ -106,7 +107,7 @@ use dropshot::PaginationParams;
 use dropshot::Query;
 use dropshot::RequestContext;
 use dropshot::ResultsPage;
-use dropshot::WhichPage;
+use dropshot::ScanMode;
 use hyper::Uri;
 use schemars::JsonSchema;
 use serde::Deserialize;
@@ -152,6 +153,13 @@ fn default_project_sort() -> ProjectSort {
     ProjectSort::ByNameAscending
 }
 
+impl ScanMode for ProjectScanParams {
+    type Mode = ProjectSort;
+    fn scan_mode(&self) -> Option<ProjectSort> {
+        Some(self.sort.clone())
+    }
+}
+
 #[derive(Deserialize, Clone, JsonSchema, Serialize)]
 #[serde(rename_all = "kebab-case")]
 enum ProjectSort {
@@ -185,6 +193,28 @@ enum ProjectScanPageSelector {
     MtimeName(PaginationOrder, DateTime<Utc>, String),
 }
 
+/// Recovers the scan mode from a page selector so that handlers can use
+/// `PaginationParams::current_scan_mode` to find the mode of any request
+impl ScanMode for ProjectScanPageSelector {
+    type Mode = ProjectSort;
+    fn scan_mode(&self) -> Option<ProjectSort> {
+        Some(match self {
+            ProjectScanPageSelector::Name(Ascending, ..) => {
+                ProjectSort::ByNameAscending
+            }
+            ProjectScanPageSelector::Name(Descending, ..) => {
+                ProjectSort::ByNameDescending
+            }
+            ProjectScanPageSelector::MtimeName(Ascending, ..) => {
+                ProjectSort::ByMtimeAscending
+            }
+            ProjectScanPageSelector::MtimeName(Descending, ..) => {
+                ProjectSort::ByMtimeDescending
+            }
+        })
+    }
+}
+
 /// Given a project (typically representing the last project in a page of
 /// results) and scan mode, return a page selector that can be sent to the client
 /// to request the next page of results.
@@ -225,59 +255,39 @@ async fn example_list_projects(
     query: Query<PaginationParams<ProjectScanParams, ProjectScanPageSelector>>,
 ) -> Result<HttpResponseOk<ResultsPage<Project>>, HttpError> {
     let pag_params = query.into_inner();
-    let limit = rqctx.page_limit(&pag_params)?.get() as usize;
+    let limit = rqctx.page_limit(&pag_params)?;
     let data = rqctx.context();
     let scan_params = ProjectScanParams {
-        sort: match &pag_params.page {
-            WhichPage::First(ProjectScanParams { sort }) => sort.clone(),
-
-            WhichPage::Next(ProjectScanPageSelector::Name(Ascending, ..)) => {
-                ProjectSort::ByNameAscending
-            }
-            WhichPage::Next(ProjectScanPageSelector::Name(Descending, ..)) => {
-                ProjectSort::ByNameDescending
-            }
-            WhichPage::Next(ProjectScanPageSelector::MtimeName(
-                Ascending,
-                ..,
-            )) => ProjectSort::ByMtimeAscending,
-            WhichPage::Next(ProjectScanPageSelector::MtimeName(
-                Descending,
-                ..,
-            )) => ProjectSort::ByMtimeDescending,
-        },
+        sort: pag_params.current_scan_mode(default_project_sort()),
     };
 
-    let iter = match &pag_params.page {
-        WhichPage::First(..) => match scan_params.sort {
+    let iter = match pag_params.page_selector() {
+        None => match scan_params.sort {
             ProjectSort::ByNameAscending => data.iter_by_name_asc(),
             ProjectSort::ByNameDescending => data.iter_by_name_desc(),
             ProjectSort::ByMtimeAscending => data.iter_by_mtime_asc(),
             ProjectSort::ByMtimeDescending => data.iter_by_mtime_desc(),
         },
 
-        WhichPage::Next(ProjectScanPageSelector::Name(Ascending, name)) => {
+        Some(ProjectScanPageSelector::Name(Ascending, name)) => {
             data.iter_by_name_asc_from(name)
         }
-        WhichPage::Next(ProjectScanPageSelector::Name(Descending, name)) => {
+        Some(ProjectScanPageSelector::Name(Descending, name)) => {
             data.iter_by_name_desc_from(name)
         }
-        WhichPage::Next(ProjectScanPageSelector::MtimeName(
-            Ascending,
-            mtime,
-            name,
-        )) => data.iter_by_mtime_asc_from(mtime, name),
-        WhichPage::Next(ProjectScanPageSelector::MtimeName(
-            Descending,
-            mtime,
-            name,
-        )) => data.iter_by_mtime_desc_from(mtime, name),
+        Some(ProjectScanPageSelector::MtimeName(Ascending, mtime, name)) => {
+            data.iter_by_mtime_asc_from(mtime, name)
+        }
+        Some(ProjectScanPageSelector::MtimeName(Descending, mtime, name)) => {
+            data.iter_by_mtime_desc_from(mtime, name)
+        }
     };
 
-    let projects = iter.take(limit).map(|p| (*p).clone()).collect();
-    Ok(HttpResponseOk(ResultsPage::new(
+    let projects = iter.map(|p| (*p).clone());
+    Ok(HttpResponseOk(paginate_iter(
         projects,
         &scan_params,
+        limit,
         page_selector_for,
     )?))
 }
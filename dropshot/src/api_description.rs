@@ -1,30 +1,64 @@
 // Copyright 2023 Oxide Computer Company
 //! Describes the endpoints and handler functions in your API
 
+use crate::clock::Clock;
+use crate::clock::SystemClock;
+use crate::extractor::CookieKey;
+use crate::extractor::Path;
+use crate::extractor::Preconditions;
+use crate::extractor::RangeRequest;
 use crate::extractor::RequestExtractor;
+use crate::handler::string_header;
+use crate::handler::ConcurrencyLimitedHandler;
 use crate::handler::HttpHandlerFunc;
 use crate::handler::HttpResponse;
 use crate::handler::HttpRouteHandler;
+use crate::handler::RequestContext;
+use crate::handler::RequestLogHook;
 use crate::handler::RouteHandler;
+use crate::openapi_docs::openapi_get;
+use crate::openapi_docs::OpenApiDocument;
+use crate::openapi_lint::unreferenced_schemas;
+use crate::openapi_lint::ApiProblem;
+use crate::openapi_lint::ApiProblemKind;
+use crate::openapi_version::convert_to_3_1;
+use crate::openapi_version::OpenApiVersion;
+use crate::pagination::PageTokenEncoding;
+use crate::pagination::PageTokenKey;
+use crate::pagination::PageTokenSettings;
 use crate::router::route_path_to_segments;
 use crate::router::HttpRouter;
 use crate::router::PathSegment;
+use crate::router::VariableSet;
 use crate::schema_util::j2oas_schema;
 use crate::server::ServerContext;
+use crate::static_dir::static_dir_get;
+use crate::static_dir::StaticDirPath;
 use crate::type_util::type_is_scalar;
+use crate::type_util::type_is_scalar_or_array;
 use crate::type_util::type_is_string_enum;
 use crate::HttpErrorResponseBody;
+use crate::CONTENT_TYPE_CBOR;
 use crate::CONTENT_TYPE_JSON;
+use crate::CONTENT_TYPE_MSGPACK;
+use crate::CONTENT_TYPE_MULTIPART_FORM_DATA;
 use crate::CONTENT_TYPE_OCTET_STREAM;
 use crate::CONTENT_TYPE_URL_ENCODED;
 
+use chrono::DateTime;
+use chrono::Utc;
 use http::Method;
 use http::StatusCode;
 use serde::Deserialize;
 use serde::Serialize;
+use std::borrow::Cow;
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// ApiEndpoint represents a single API endpoint associated with an
 /// ApiDescription. It has a handler, HTTP method (e.g. GET, POST), and a path--
@@ -33,18 +67,50 @@ use std::collections::HashSet;
 #[derive(Debug)]
 pub struct ApiEndpoint<Context: ServerContext> {
     pub operation_id: String,
-    pub handler: Box<dyn RouteHandler<Context>>,
+    pub handler: Arc<dyn RouteHandler<Context>>,
     pub method: Method,
     pub path: String,
     pub parameters: Vec<ApiEndpointParameter>,
+    pub security: Vec<ApiEndpointSecurity>,
     pub body_content_type: ApiEndpointBodyContentType,
+    pub encodings: Vec<ApiEndpointBodyContentType>,
+    /// MIME types passed to [`ApiEndpoint::encoding`] that aren't supported,
+    /// reported as an error when the endpoint is registered
+    pub unsupported_encodings: Vec<String>,
     pub response: ApiEndpointResponse,
+    /// media type of a freeform response body
+    pub response_content_type: Option<String>,
+    /// error statuses documented as this endpoint's own responses
+    pub errors: Vec<StatusCode>,
     pub summary: Option<String>,
     pub description: Option<String>,
     pub tags: Vec<String>,
     pub extension_mode: ExtensionMode,
     pub visible: bool,
     pub deprecated: bool,
+    /// when the endpoint was deprecated, sent in the `Deprecation` header
+    pub deprecated_since: Option<DateTime<Utc>>,
+    /// when the endpoint will stop working, sent in the `Sunset` header
+    pub sunset: Option<DateTime<Utc>>,
+    pub parameter_case: Option<ParameterCase>,
+    pub strict_query: Option<bool>,
+    pub aliases: Vec<String>,
+    pub max_concurrency: Option<usize>,
+    pub request_body_max_bytes: Option<usize>,
+    pub request_timeout: Option<Duration>,
+    /// security schemes declared with [`ApiDescription::security_scheme`]
+    /// that callers must satisfy, with the scopes each requires; these are
+    /// added to `security` when the endpoint is registered
+    pub security_requirements: BTreeMap<String, Vec<String>>,
+    /// named examples of the request body
+    pub request_examples: BTreeMap<String, serde_json::Value>,
+    /// examples of path, query, header, and cookie parameters, by parameter
+    pub parameter_examples: BTreeMap<String, serde_json::Value>,
+    /// named examples of the body of successful responses
+    pub response_examples: BTreeMap<String, serde_json::Value>,
+    /// vendor extensions (`x-` fields) of the operation
+    pub extensions: BTreeMap<String, serde_json::Value>,
+    pub attributes: BTreeMap<String, String>,
 }
 
 impl<'a, Context: ServerContext> ApiEndpoint<Context> {
@@ -71,14 +137,33 @@ impl<'a, Context: ServerContext> ApiEndpoint<Context> {
             method,
             path: path.to_string(),
             parameters: func_parameters.parameters,
+            security: func_parameters.security,
             body_content_type,
+            encodings: vec![],
+            unsupported_encodings: vec![],
             response,
+            response_content_type: None,
+            errors: vec![],
             summary: None,
             description: None,
             tags: vec![],
             extension_mode: func_parameters.extension_mode,
             visible: true,
             deprecated: false,
+            deprecated_since: None,
+            sunset: None,
+            parameter_case: None,
+            strict_query: None,
+            aliases: vec![],
+            max_concurrency: None,
+            request_body_max_bytes: None,
+            request_timeout: None,
+            security_requirements: BTreeMap::new(),
+            request_examples: BTreeMap::new(),
+            parameter_examples: BTreeMap::new(),
+            response_examples: BTreeMap::new(),
+            extensions: BTreeMap::new(),
+            attributes: BTreeMap::new(),
         }
     }
 
@@ -106,12 +191,354 @@ impl<'a, Context: ServerContext> ApiEndpoint<Context> {
         self.deprecated = deprecated;
         self
     }
+
+    /// Mark this endpoint as deprecated as of `time`, which its responses
+    /// report in a `Deprecation` header (RFC 9745) so that clients can notice
+    /// that they are using a route that is being phased out.
+    pub fn deprecated_since(mut self, time: DateTime<Utc>) -> Self {
+        self.deprecated = true;
+        self.deprecated_since = Some(time);
+        self.document_header(string_header(
+            "Deprecation",
+            "when the endpoint was deprecated, as \"@\" followed by seconds \
+             since the Unix epoch",
+        ));
+        self
+    }
+
+    /// Announce that this endpoint will stop working at `time`, which its
+    /// responses report in a `Sunset` header (RFC 8594).
+    pub fn sunset(mut self, time: DateTime<Utc>) -> Self {
+        self.sunset = Some(time);
+        self.document_header(string_header(
+            "Sunset",
+            "when the endpoint will stop working, as an HTTP date",
+        ));
+        self
+    }
+
+    /// Document a header sent with the endpoint's successful responses, unless
+    /// it's already documented.
+    fn document_header(&mut self, header: ApiEndpointHeader) {
+        let headers = &mut self.response.headers;
+        if !headers.iter().any(|h| h.name.eq_ignore_ascii_case(&header.name)) {
+            headers.push(header);
+        }
+    }
+
+    /// Override the naming convention used for this endpoint's path and query
+    /// parameters.  See [`ApiDescription::parameter_case`].
+    pub fn parameter_case(mut self, parameter_case: ParameterCase) -> Self {
+        self.parameter_case = Some(parameter_case);
+        self
+    }
+
+    /// Reject requests whose query strings contain parameters that this
+    /// endpoint's [`Query`](crate::Query) extractor doesn't accept, rather than
+    /// ignoring them.  See [`ApiDescription::strict_query`].
+    pub fn strict_query(mut self, strict_query: bool) -> Self {
+        self.strict_query = Some(strict_query);
+        self
+    }
+
+    /// Also serve this endpoint at `path`, e.g. to keep a legacy path working
+    /// while clients move to a renamed route.  Each alias appears in the
+    /// OpenAPI document as a separate, deprecated operation whose operation ID
+    /// is derived from that of the canonical endpoint.
+    pub fn alias<T: ToString>(mut self, path: T) -> Self {
+        self.aliases.push(path.to_string());
+        self
+    }
+
+    /// Offer request and response bodies in the encoding with MIME type
+    /// `mime_type`, alongside any others offered this way.  A [`TypedBody`]
+    /// parameter then accepts a body in any of the endpoint's encodings, as
+    /// labelled by the request's `Content-Type`, and a [`Negotiated`]
+    /// response is sent in whichever the request's `Accept` header prefers.
+    /// The OpenAPI document lists each encoding for both.
+    ///
+    /// JSON is always supported; CBOR and MessagePack require the "cbor" and
+    /// "msgpack" features respectively.  If `mime_type` is not a supported
+    /// encoding, registering the endpoint fails.
+    ///
+    /// [`TypedBody`]: crate::TypedBody
+    /// [`Negotiated`]: crate::Negotiated
+    pub fn encoding(mut self, mime_type: &str) -> Self {
+        match ApiEndpointBodyContentType::from_mime_type(mime_type)
+            .ok()
+            .filter(ApiEndpointBodyContentType::is_negotiable)
+        {
+            Some(encoding) => {
+                if !self.encodings.contains(&encoding) {
+                    self.encodings.push(encoding);
+                }
+            }
+            None => self.unsupported_encodings.push(mime_type.to_string()),
+        }
+        self
+    }
+
+    /// Label the endpoint's successful responses with the media type
+    /// `mime_type` (e.g., "text/plain") when their body is a
+    /// [`FreeformBody`](crate::FreeformBody), which is otherwise sent as
+    /// "application/octet-stream" and documented as any type at all.  The
+    /// OpenAPI document then lists the response under that media type.
+    /// Panics if `mime_type` is not a valid header value.
+    pub fn response_content_type<T: ToString>(mut self, mime_type: T) -> Self {
+        let mime_type = mime_type.to_string();
+        if let Err(e) = http::HeaderValue::try_from(&mime_type) {
+            panic!("invalid response content type {:?}: {}", mime_type, e);
+        }
+        self.response_content_type = Some(mime_type);
+        self
+    }
+
+    /// Document `status` as an error this endpoint may return.  Every
+    /// operation's OpenAPI description covers 4xx and 5xx responses in
+    /// general; declared errors also get entries of their own, so that
+    /// clients know which to expect.  Panics if `status` is not a client or
+    /// server error.
+    pub fn error(mut self, status: StatusCode) -> Self {
+        assert!(
+            status.is_client_error() || status.is_server_error(),
+            "not an error status: {}",
+            status
+        );
+        if !self.errors.contains(&status) {
+            self.errors.push(status);
+        }
+        self
+    }
+
+    /// Limit the number of requests to this endpoint that may be handled
+    /// concurrently.  Requests that arrive while `max_concurrency` requests are
+    /// already in progress are rejected with a 503 ("Service Unavailable").
+    /// This limit is independent of any other endpoint's.
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = Some(max_concurrency);
+        self
+    }
+
+    /// Override the server's
+    /// [`request_body_max_bytes`](crate::ConfigDropshot::request_body_max_bytes)
+    /// for requests to this endpoint, e.g. to accept large uploads on one
+    /// endpoint without raising the limit for all of them.
+    pub fn request_body_max_bytes(
+        mut self,
+        request_body_max_bytes: usize,
+    ) -> Self {
+        self.request_body_max_bytes = Some(request_body_max_bytes);
+        self
+    }
+
+    /// Override the server's
+    /// [`request_timeout_ms`](crate::ConfigDropshot::request_timeout_ms) for
+    /// requests to this endpoint.  A request whose handler takes longer than
+    /// `request_timeout` is cancelled and answered with a 503 ("Service
+    /// Unavailable").
+    pub fn request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = Some(request_timeout);
+        self
+    }
+
+    /// Require callers of this endpoint to satisfy the security scheme `name`,
+    /// which must have been declared with [`ApiDescription::security_scheme`]
+    /// by the time the endpoint is registered.  `scopes` lists the OAuth2 or
+    /// OpenID Connect scopes required, if any.  This only affects the OpenAPI
+    /// document: checking credentials remains up to the handler.
+    pub fn security_requirement<N: ToString>(
+        mut self,
+        name: N,
+        scopes: Vec<String>,
+    ) -> Self {
+        self.security_requirements.insert(name.to_string(), scopes);
+        self
+    }
+
+    /// Document `value` as an example of this endpoint's request body, under
+    /// `name` in the OpenAPI document.  Examples appear in generated
+    /// documentation and can be served by mock servers.  An endpoint defined
+    /// with the `endpoint` attribute can be given examples before it is
+    /// registered:
+    ///
+    /// ```ignore
+    /// api.register(
+    ///     ApiEndpoint::from(project_create)
+    ///         .request_example("minimal", &ProjectCreate::new("web")),
+    /// )?;
+    /// ```
+    ///
+    /// Panics if `value` cannot be serialized as JSON.
+    pub fn request_example<N: ToString, T: Serialize>(
+        mut self,
+        name: N,
+        value: &T,
+    ) -> Self {
+        self.request_examples.insert(name.to_string(), example_value(value));
+        self
+    }
+
+    /// Document `value` as an example of the path, query, header, or cookie
+    /// parameter called `name` (as it appears in the OpenAPI document).
+    /// Panics if `value` cannot be serialized as JSON.
+    pub fn parameter_example<N: ToString, T: Serialize>(
+        mut self,
+        name: N,
+        value: &T,
+    ) -> Self {
+        self.parameter_examples.insert(name.to_string(), example_value(value));
+        self
+    }
+
+    /// Document `value` as an example of the body of this endpoint's
+    /// successful responses, under `name` in the OpenAPI document.  Panics if
+    /// `value` cannot be serialized as JSON.
+    pub fn response_example<N: ToString, T: Serialize>(
+        mut self,
+        name: N,
+        value: &T,
+    ) -> Self {
+        self.response_examples.insert(name.to_string(), example_value(value));
+        self
+    }
+
+    /// Attach a vendor extension to the endpoint's operation in the OpenAPI
+    /// document, such as `x-internal`.  The name must begin with `x-`, and
+    /// names beginning with `x-dropshot-` are reserved for Dropshot's own
+    /// extensions; `ApiDescription::register()` rejects the endpoint
+    /// otherwise.  Endpoints defined with the `endpoint` macro can be given
+    /// extensions by registering e.g.
+    /// `ApiEndpoint::from(handler).extension("x-internal", json!(true))`.
+    pub fn extension<N: ToString>(
+        mut self,
+        name: N,
+        value: serde_json::Value,
+    ) -> Self {
+        self.extensions.insert(name.to_string(), value);
+        self
+    }
+
+    /// Attach a custom attribute to this endpoint.  Attributes don't affect
+    /// how the endpoint is served or documented.  Handlers and hooks can read
+    /// them from [`RequestContext::endpoint`] to make per-endpoint decisions,
+    /// like which metrics labels or authorization policy to apply.
+    pub fn attribute<K: ToString, V: ToString>(
+        mut self,
+        name: K,
+        value: V,
+    ) -> Self {
+        self.attributes.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Returns the metadata made available to handlers of this endpoint.
+    pub(crate) fn metadata(&self) -> EndpointMetadata {
+        EndpointMetadata {
+            operation_id: self.operation_id.clone(),
+            method: self.method.clone(),
+            path: self.path.clone(),
+            tags: self.tags.clone(),
+            deprecated: self.deprecated,
+            deprecated_since: self.deprecated_since,
+            sunset: self.sunset,
+            encodings: self.encodings.clone(),
+            strict_query: self.strict_query.unwrap_or_default(),
+            query_parameters: self
+                .parameters
+                .iter()
+                .filter_map(|param| match &param.metadata {
+                    ApiEndpointParameterMetadata::Query(name) => {
+                        Some(name.clone())
+                    }
+                    _ => None,
+                })
+                .collect(),
+            request_body_max_bytes: self.request_body_max_bytes,
+            request_timeout: self.request_timeout,
+            response_content_type: self.response_content_type.clone(),
+            attributes: self.attributes.clone(),
+        }
+    }
+
+    /// Construct the endpoint registered for the alias at `index`.
+    fn alias_endpoint(&self, index: usize) -> Self {
+        ApiEndpoint {
+            operation_id: format!("{}_alias{}", self.operation_id, index + 1),
+            handler: Arc::clone(&self.handler),
+            method: self.method.clone(),
+            path: self.aliases[index].clone(),
+            parameters: self.parameters.clone(),
+            security: self.security.clone(),
+            body_content_type: self.body_content_type.clone(),
+            encodings: self.encodings.clone(),
+            unsupported_encodings: self.unsupported_encodings.clone(),
+            response: self.response.clone(),
+            response_content_type: self.response_content_type.clone(),
+            errors: self.errors.clone(),
+            summary: self.summary.clone(),
+            description: self.description.clone(),
+            tags: self.tags.clone(),
+            extension_mode: self.extension_mode.clone(),
+            visible: self.visible,
+            deprecated: true,
+            deprecated_since: self.deprecated_since,
+            sunset: self.sunset,
+            parameter_case: self.parameter_case,
+            strict_query: self.strict_query,
+            aliases: vec![],
+            max_concurrency: self.max_concurrency,
+            request_body_max_bytes: self.request_body_max_bytes,
+            request_timeout: self.request_timeout,
+            security_requirements: self.security_requirements.clone(),
+            request_examples: self.request_examples.clone(),
+            parameter_examples: self.parameter_examples.clone(),
+            response_examples: self.response_examples.clone(),
+            extensions: self.extensions.clone(),
+            attributes: self.attributes.clone(),
+        }
+    }
+}
+
+/// Describes the endpoint handling a request.  This is available to handlers
+/// and hooks as [`RequestContext::endpoint`].
+#[derive(Clone, Debug)]
+pub struct EndpointMetadata {
+    pub operation_id: String,
+    pub method: Method,
+    /// path template with which the endpoint was registered (e.g.,
+    /// "/projects/{project}").  For an alias, this is the alias's path.
+    pub path: String,
+    pub tags: Vec<String>,
+    pub deprecated: bool,
+    /// when the endpoint was deprecated (see [`ApiEndpoint::deprecated_since`])
+    pub deprecated_since: Option<DateTime<Utc>>,
+    /// when the endpoint will stop working (see [`ApiEndpoint::sunset`])
+    pub sunset: Option<DateTime<Utc>>,
+    /// encodings offered with [`ApiEndpoint::encoding`], if any
+    pub encodings: Vec<ApiEndpointBodyContentType>,
+    /// whether unknown query parameters are rejected (see
+    /// [`ApiEndpoint::strict_query`])
+    pub strict_query: bool,
+    /// names of the query parameters the endpoint accepts, as they appear in
+    /// requests
+    pub query_parameters: BTreeSet<String>,
+    /// limit on the size of request bodies, if the endpoint overrides the
+    /// server's
+    pub request_body_max_bytes: Option<usize>,
+    /// time after which requests are abandoned, if the endpoint overrides the
+    /// server's
+    pub request_timeout: Option<Duration>,
+    /// media type of freeform response bodies (see
+    /// [`ApiEndpoint::response_content_type`])
+    pub response_content_type: Option<String>,
+    /// custom attributes attached with [`ApiEndpoint::attribute`]
+    pub attributes: BTreeMap<String, String>,
 }
 
 /// ApiEndpointParameter represents the discrete path and query parameters for a
 /// given API endpoint. These are typically derived from the members of stucts
 /// used as parameters to handler functions.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ApiEndpointParameter {
     pub metadata: ApiEndpointParameterMetadata,
     pub description: Option<String>,
@@ -137,6 +564,12 @@ impl ApiEndpointParameter {
                 ApiEndpointParameterLocation::Query => {
                     ApiEndpointParameterMetadata::Query(name)
                 }
+                ApiEndpointParameterLocation::Header => {
+                    ApiEndpointParameterMetadata::Header(name)
+                }
+                ApiEndpointParameterLocation::Cookie => {
+                    ApiEndpointParameterMetadata::Cookie(name)
+                }
             },
             description,
             required,
@@ -165,16 +598,20 @@ impl ApiEndpointParameter {
 pub enum ApiEndpointParameterLocation {
     Path,
     Query,
+    Header,
+    Cookie,
 }
 
 #[derive(Debug, Clone)]
 pub enum ApiEndpointParameterMetadata {
     Path(String),
     Query(String),
+    Header(String),
+    Cookie(String),
     Body(ApiEndpointBodyContentType),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ApiEndpointBodyContentType {
     /// application/octet-stream
     Bytes,
@@ -182,6 +619,12 @@ pub enum ApiEndpointBodyContentType {
     Json,
     /// application/x-www-form-urlencoded
     UrlEncoded,
+    /// multipart/form-data
+    MultipartFormData,
+    /// application/cbor
+    Cbor,
+    /// application/msgpack
+    MsgPack,
 }
 
 impl Default for ApiEndpointBodyContentType {
@@ -191,11 +634,14 @@ impl Default for ApiEndpointBodyContentType {
 }
 
 impl ApiEndpointBodyContentType {
-    pub fn mime_type(&self) -> &str {
+    pub fn mime_type(&self) -> &'static str {
         match self {
             Self::Bytes => CONTENT_TYPE_OCTET_STREAM,
             Self::Json => CONTENT_TYPE_JSON,
             Self::UrlEncoded => CONTENT_TYPE_URL_ENCODED,
+            Self::MultipartFormData => CONTENT_TYPE_MULTIPART_FORM_DATA,
+            Self::Cbor => CONTENT_TYPE_CBOR,
+            Self::MsgPack => CONTENT_TYPE_MSGPACK,
         }
     }
 
@@ -204,29 +650,212 @@ impl ApiEndpointBodyContentType {
             CONTENT_TYPE_OCTET_STREAM => Ok(Self::Bytes),
             CONTENT_TYPE_JSON => Ok(Self::Json),
             CONTENT_TYPE_URL_ENCODED => Ok(Self::UrlEncoded),
+            CONTENT_TYPE_MULTIPART_FORM_DATA => Ok(Self::MultipartFormData),
+            CONTENT_TYPE_CBOR => Ok(Self::Cbor),
+            CONTENT_TYPE_MSGPACK => Ok(Self::MsgPack),
             _ => Err(mime_type.to_string()),
         }
     }
+
+    /// Returns whether values of any serializable type can be encoded this
+    /// way, so that endpoints can offer it as one of several encodings.
+    pub(crate) fn is_negotiable(&self) -> bool {
+        match self {
+            Self::Json => true,
+            Self::Cbor => cfg!(feature = "cbor"),
+            Self::MsgPack => cfg!(feature = "msgpack"),
+            _ => false,
+        }
+    }
 }
 
-#[derive(Debug)]
+/// Naming convention used for path and query parameter names on the wire
+///
+/// Parameter types are Rust structs whose fields are conventionally
+/// `snake_case`.  Rather than annotating each of those structs with
+/// `#[serde(rename_all = "...")]`, consumers can select a convention for an
+/// entire API (see [`ApiDescription::parameter_case`]) or for a single endpoint
+/// (see [`ApiEndpoint::parameter_case`]).  Parameter names in the OpenAPI
+/// document and in path templates use the selected convention, and incoming
+/// names are translated back to `snake_case` before being deserialized.  Only
+/// the spelling in the selected convention is accepted: with
+/// [`ParameterCase::Camel`], `?pageToken=...` sets the `page_token` field, but
+/// `?page_token=...` is treated like any other unknown parameter.
+///
+/// Registering an endpoint fails if one of its parameter names would not
+/// survive the translation, as with `page_2` (which would become `page2` in
+/// camelCase, and then `page2` again in `snake_case`).
+///
+/// This applies only to the names of path and query parameters.  Request and
+/// response bodies are serialized exactly as their types specify, and neither
+/// their schemas nor those of the parameters themselves are renamed; use
+/// `#[serde(rename_all = "...")]` on those types to change their field names.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize,
+)]
+pub enum ParameterCase {
+    /// `snake_case`: names are used exactly as they appear in the Rust type
+    #[default]
+    Snake,
+    /// `camelCase`
+    Camel,
+    /// `PascalCase`
+    Pascal,
+    /// `kebab-case`
+    Kebab,
+}
+
+impl ParameterCase {
+    /// Translate a `snake_case` name into this naming convention.
+    pub fn to_wire(&self, name: &str) -> String {
+        match self {
+            ParameterCase::Snake => name.to_string(),
+            ParameterCase::Kebab => name.replace('_', "-"),
+            ParameterCase::Camel | ParameterCase::Pascal => {
+                let mut out = String::with_capacity(name.len());
+                let mut capitalize = *self == ParameterCase::Pascal;
+                for c in name.chars() {
+                    if c == '_' {
+                        // Leading underscores are dropped, and the first
+                        // letter of a camelCase name is left alone.
+                        capitalize |= !out.is_empty();
+                    } else if capitalize {
+                        out.push(c.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        out.push(c);
+                    }
+                }
+                out
+            }
+        }
+    }
+
+    /// Translate a name in this naming convention into `snake_case`.
+    pub fn from_wire(&self, name: &str) -> String {
+        match self {
+            ParameterCase::Snake => name.to_string(),
+            ParameterCase::Kebab => name.replace('-', "_"),
+            ParameterCase::Camel | ParameterCase::Pascal => {
+                let mut out = String::with_capacity(name.len() + 4);
+                for c in name.chars() {
+                    if c.is_ascii_uppercase() {
+                        if !out.is_empty() {
+                            out.push('_');
+                        }
+                        out.push(c.to_ascii_lowercase());
+                    } else {
+                        out.push(c);
+                    }
+                }
+                out
+            }
+        }
+    }
+
+    /// Returns whether `name` is spelled exactly as this naming convention
+    /// would spell it, so that translating it to `snake_case` and back yields
+    /// `name` again.
+    fn is_wire_name(&self, name: &str) -> bool {
+        self.to_wire(&self.from_wire(name)) == name
+    }
+
+    /// Rewrite the names in a query string from this naming convention into
+    /// `snake_case`.  Names not spelled in this convention are dropped, so
+    /// that they're ignored like any other unknown parameter.
+    pub(crate) fn query_from_wire<'a>(&self, query: &'a str) -> Cow<'a, str> {
+        if *self == ParameterCase::Snake {
+            return Cow::Borrowed(query);
+        }
+
+        Cow::Owned(
+            form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(
+                    form_urlencoded::parse(query.as_bytes())
+                        .filter(|(name, _)| self.is_wire_name(name))
+                        .map(|(name, value)| (self.from_wire(&name), value)),
+                )
+                .finish(),
+        )
+    }
+
+    /// Rewrite the names of path variables from this naming convention into
+    /// `snake_case`.
+    pub(crate) fn variables_from_wire<'a>(
+        &self,
+        variables: &'a VariableSet,
+    ) -> Cow<'a, VariableSet> {
+        if *self == ParameterCase::Snake {
+            return Cow::Borrowed(variables);
+        }
+
+        Cow::Owned(
+            variables
+                .iter()
+                .map(|(name, value)| (self.from_wire(name), value.clone()))
+                .collect(),
+        )
+    }
+}
+
+/// A security scheme that callers of an endpoint must satisfy, as documented in
+/// the OpenAPI document.  Extractors that authenticate the caller describe the
+/// scheme they check in [`ExtractorMetadata::security`].
+///
+/// [`ExtractorMetadata::security`]: crate::ExtractorMetadata::security
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApiEndpointSecurity {
+    /// name under which the scheme appears in the document's components
+    pub name: String,
+    pub scheme: openapiv3::SecurityScheme,
+    /// OAuth2 or OpenID Connect scopes required, if any
+    pub scopes: Vec<String>,
+}
+
+impl ApiEndpointSecurity {
+    /// Describes HTTP bearer authentication, as checked by
+    /// [`BearerToken`](crate::BearerToken).
+    pub fn bearer() -> Self {
+        ApiEndpointSecurity {
+            name: String::from("bearerAuth"),
+            scheme: openapiv3::SecurityScheme::HTTP {
+                scheme: String::from("bearer"),
+                bearer_format: None,
+                description: None,
+                extensions: Default::default(),
+            },
+            scopes: Vec::new(),
+        }
+    }
+}
+
+/// Metadata for a header sent with an API endpoint's response
+#[derive(Clone, Debug)]
 pub struct ApiEndpointHeader {
     pub name: String,
     pub description: Option<String>,
+    /// schema of the header's value (typically a string or number)
     pub schema: ApiSchemaGenerator,
+    /// whether the header is always sent
     pub required: bool,
 }
 
 /// Metadata for an API endpoint response: type information and status code.
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct ApiEndpointResponse {
     pub schema: Option<ApiSchemaGenerator>,
+    /// MIME types in which the body may be produced (if empty, the
+    /// endpoint's encodings, or JSON if it offers none)
+    pub content_types: Vec<&'static str>,
     pub headers: Vec<ApiEndpointHeader>,
     pub success: Option<StatusCode>,
+    /// Statuses other than `success` with which the same body may be sent
+    pub other_statuses: Vec<StatusCode>,
     pub description: Option<String>,
 }
 
 /// Wrapper for both dynamically generated and pre-generated schemas.
+#[derive(Clone)]
 pub enum ApiSchemaGenerator {
     Gen {
         name: fn() -> String,
@@ -257,6 +886,23 @@ pub struct ApiDescription<Context: ServerContext> {
     /// In practice, all the information we need is encoded in the router.
     router: HttpRouter<Context>,
     tag_config: TagConfig,
+    parameter_case: ParameterCase,
+    strict_query: bool,
+    request_log_hook: Option<RequestLogHook<Context>>,
+    clock: Arc<dyn Clock>,
+    cookie_key: Option<CookieKey>,
+    page_tokens: PageTokenSettings,
+    security_schemes: indexmap::IndexMap<String, openapiv3::SecurityScheme>,
+    openapi_documents: Vec<OpenApiDocument>,
+}
+
+/// Everything the server needs from an [`ApiDescription`] to run it
+pub(crate) struct ServerParts<Context: ServerContext> {
+    pub router: HttpRouter<Context>,
+    pub request_log_hook: Option<RequestLogHook<Context>>,
+    pub clock: Arc<dyn Clock>,
+    pub cookie_key: Option<CookieKey>,
+    pub page_tokens: PageTokenSettings,
 }
 
 impl<Context: ServerContext> ApiDescription<Context> {
@@ -264,6 +910,14 @@ impl<Context: ServerContext> ApiDescription<Context> {
         ApiDescription {
             router: HttpRouter::new(),
             tag_config: TagConfig::default(),
+            parameter_case: ParameterCase::default(),
+            strict_query: false,
+            request_log_hook: None,
+            clock: Arc::new(SystemClock),
+            cookie_key: None,
+            page_tokens: PageTokenSettings::default(),
+            security_schemes: indexmap::IndexMap::new(),
+            openapi_documents: Vec::new(),
         }
     }
 
@@ -272,6 +926,154 @@ impl<Context: ServerContext> ApiDescription<Context> {
         self
     }
 
+    /// Select the naming convention used for path and query parameters of
+    /// endpoints subsequently registered with this description.  Endpoints may
+    /// override this with [`ApiEndpoint::parameter_case`].
+    pub fn parameter_case(mut self, parameter_case: ParameterCase) -> Self {
+        self.parameter_case = parameter_case;
+        self
+    }
+
+    /// Make the [`Query`](crate::Query) extractor of endpoints subsequently
+    /// registered with this description reject requests containing query
+    /// parameters that it doesn't accept, with a 400 ("Bad Request") that
+    /// lists them.  By default such parameters are ignored, so a typo like
+    /// `?limt=10` goes unnoticed.  Endpoints may override this with
+    /// [`ApiEndpoint::strict_query`].
+    pub fn strict_query(mut self, strict_query: bool) -> Self {
+        self.strict_query = strict_query;
+        self
+    }
+
+    /// Install a hook that produces the logger for each request.
+    ///
+    /// The hook is invoked once the request has been routed, and the logger it
+    /// returns replaces `rqctx.log`.  It would typically derive a child of
+    /// `rqctx.log` carrying identifiers found in the request (such as the
+    /// tenant it's for) so that every log message emitted while handling it
+    /// includes them:
+    ///
+    /// ```
+    /// use dropshot::ApiDescription;
+    /// use slog::o;
+    ///
+    /// let api = ApiDescription::<()>::new().request_log_hook(|rqctx| {
+    ///     let tenant = rqctx
+    ///         .request
+    ///         .headers()
+    ///         .get("x-tenant-id")
+    ///         .and_then(|value| value.to_str().ok())
+    ///         .unwrap_or("unknown")
+    ///         .to_string();
+    ///     rqctx.log.new(o!("tenant_id" => tenant))
+    /// });
+    /// ```
+    ///
+    /// The hook runs before the handler's extractors, so that their messages
+    /// are covered too.  It therefore sees only the raw request (its headers,
+    /// path, and the endpoint's metadata), none of which has been validated
+    /// or authenticated yet: identifiers taken from it are only what the
+    /// client claims.  Handlers that authenticate the caller can derive a
+    /// logger carrying the verified identity from `rqctx.log` themselves.
+    pub fn request_log_hook<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&RequestContext<Context>) -> slog::Logger + Send + Sync + 'static,
+    {
+        self.request_log_hook = Some(RequestLogHook::new(hook));
+        self
+    }
+
+    /// Use `clock` as the source of time for the server and its handlers,
+    /// in place of the system's clock.  This is intended for tests of
+    /// time-dependent behavior; see
+    /// [`test_util::MockClock`](crate::test_util::MockClock).
+    pub fn clock<C: Clock>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Use `key` to sign and encrypt cookies; see
+    /// [`SignedCookie`](crate::SignedCookie) and
+    /// [`EncryptedCookie`](crate::EncryptedCookie).  Handlers can get the key
+    /// with `rqctx.server.cookie_key()` to sign or encrypt the cookies they
+    /// set.
+    pub fn cookie_key(mut self, key: CookieKey) -> Self {
+        self.cookie_key = Some(key);
+        self
+    }
+
+    /// Use `key` to sign the page tokens of paginated endpoints and to reject
+    /// tokens that clients have forged or modified; see [`PageTokenKey`].
+    pub fn page_token_key(mut self, key: PageTokenKey) -> Self {
+        self.page_tokens.key = Some(key);
+        self
+    }
+
+    /// Record `version` in the page tokens of paginated endpoints, and reject
+    /// tokens recording any other version with a 400 ("Bad Request") telling
+    /// the client to restart its scan.  Change the version whenever the
+    /// `PageSelector` type of any endpoint changes incompatibly, so that
+    /// clients in the middle of a scan get that error rather than one saying
+    /// that their token is corrupted.
+    pub fn page_token_version(mut self, version: u32) -> Self {
+        self.page_tokens.version = Some(version);
+        self
+    }
+
+    /// Make the page tokens of paginated endpoints expire `lifetime` after
+    /// they are created (to the second, according to the API's
+    /// [`clock`](Self::clock)).  Requests with expired tokens fail with a 400
+    /// ("Bad Request") telling the client to restart its scan.  Tokens created
+    /// without a lifetime do not expire.
+    pub fn page_token_lifetime(mut self, lifetime: Duration) -> Self {
+        self.page_tokens.lifetime = Some(lifetime);
+        self
+    }
+
+    /// Limit page tokens to `max_length` bytes (512 by default).  Requests with
+    /// longer tokens fail with a 400 ("Bad Request"), and a handler whose page
+    /// selector makes for a longer token fails with a 500 ("Internal Server
+    /// Error").  Raising the limit allows larger page selectors, but page
+    /// tokens appear in URLs, which many clients and proxies limit in length.
+    pub fn page_token_max_length(mut self, max_length: usize) -> Self {
+        self.page_tokens.max_length = Some(max_length);
+        self
+    }
+
+    /// Encode the page tokens of paginated endpoints with `encoding`; see
+    /// [`PageTokenEncoding`].
+    pub fn page_token_encoding(mut self, encoding: PageTokenEncoding) -> Self {
+        self.page_tokens.encoding = encoding;
+        self
+    }
+
+    /// Declare a security scheme under `name`, for endpoints to require with
+    /// [`ApiEndpoint::security_requirement`] (or the `security` parameter of
+    /// the `endpoint` attribute).  Declared schemes appear in the components
+    /// of the OpenAPI document whether or not any endpoint requires them.
+    ///
+    /// ```
+    /// use dropshot::ApiDescription;
+    ///
+    /// let api = ApiDescription::<()>::new().security_scheme(
+    ///     "api_key",
+    ///     openapiv3::SecurityScheme::APIKey {
+    ///         location: openapiv3::APIKeyLocation::Header,
+    ///         name: String::from("x-api-key"),
+    ///         description: None,
+    ///         extensions: Default::default(),
+    ///     },
+    /// );
+    /// ```
+    pub fn security_scheme<N: ToString>(
+        mut self,
+        name: N,
+        scheme: openapiv3::SecurityScheme,
+    ) -> Self {
+        self.security_schemes.insert(name.to_string(), scheme);
+        self
+    }
+
     /// Register a new API endpoint.
     pub fn register<T>(&mut self, endpoint: T) -> Result<(), String>
     where
@@ -282,13 +1084,35 @@ impl<Context: ServerContext> ApiDescription<Context> {
         // manually outline, see https://matklad.github.io/2021/09/04/fast-rust-builds.html#Keeping-Instantiations-In-Check
         fn _register<C: ServerContext>(
             s: &mut ApiDescription<C>,
-            e: ApiEndpoint<C>,
+            mut e: ApiEndpoint<C>,
         ) -> Result<(), String> {
-            s.validate_tags(&e)?;
-            s.validate_path_parameters(&e)?;
-            s.validate_named_parameters(&e)?;
+            if let Some(mime_type) = e.unsupported_encodings.first() {
+                return Err(format!(
+                    "endpoint \"{}\": unsupported encoding: {}",
+                    e.operation_id, mime_type
+                ));
+            }
+            s.apply_parameter_case(&mut e)?;
+            e.strict_query.get_or_insert(s.strict_query);
+            s.apply_max_concurrency(&mut e)?;
+            s.apply_security_requirements(&mut e)?;
+            let aliases = (0..e.aliases.len())
+                .map(|index| e.alias_endpoint(index))
+                .collect::<Vec<_>>();
+
+            for endpoint in std::iter::once(&e).chain(&aliases) {
+                s.validate_operation_id(endpoint)?;
+                s.validate_tags(endpoint)?;
+                s.validate_path_parameters(endpoint)?;
+                s.validate_named_parameters(endpoint)?;
+                s.validate_examples(endpoint)?;
+                validate_extension_names(endpoint.extensions.keys())?;
+            }
 
             s.router.insert(e);
+            for alias in aliases {
+                s.router.insert(alias);
+            }
 
             Ok(())
         }
@@ -298,61 +1122,490 @@ impl<Context: ServerContext> ApiDescription<Context> {
         Ok(())
     }
 
-    /// Validate that the tags conform to the tags policy.
-    fn validate_tags(&self, e: &ApiEndpoint<Context>) -> Result<(), String> {
-        // Don't care about endpoints that don't appear in the OpenAPI
-        if !e.visible {
-            return Ok(());
+    /// Mount the endpoints of `other` beneath `prefix`, so that an endpoint
+    /// that `other` describes at `/projects` is served at `/v1/projects` after
+    /// `api.merge("/v1", other)`.  This allows an API to be assembled from
+    /// descriptions built separately, e.g. by different modules.  `prefix`
+    /// may be empty, but must not contain path variables.
+    ///
+    /// The tag definitions and security schemes of `other` are added to this
+    /// description's, and its endpoints must conform to this description's
+    /// tag policy.  This fails, merging nothing, if a route or operation ID of
+    /// `other` conflicts with one already registered or if both descriptions
+    /// define a tag or security scheme differently.  Settings of `other` that
+    /// apply to the server as a whole, like its request log hook, are
+    /// discarded.
+    pub fn merge(
+        &mut self,
+        prefix: &str,
+        other: ApiDescription<Context>,
+    ) -> Result<(), String> {
+        let prefix_ok = prefix.is_empty()
+            || (prefix.starts_with('/')
+                && prefix.split('/').skip(1).all(|segment| {
+                    !segment.is_empty() && !segment.contains(['{', '}'])
+                }));
+        if !prefix_ok {
+            return Err(format!(
+                "path prefix \"{}\" must be empty or begin with a '/', \
+                 without empty segments, a trailing '/', or variables",
+                prefix
+            ));
         }
 
-        match (&self.tag_config.endpoint_tag_policy, e.tags.len()) {
-            (EndpointTagPolicy::AtLeastOne, 0) => {
-                return Err("At least one tag is required".to_string())
-            }
-            (EndpointTagPolicy::ExactlyOne, n) if n != 1 => {
-                return Err("Exactly one tag is required".to_string())
-            }
-            _ => (),
+        let tags = &self.tag_config.tag_definitions;
+        if let Some((name, _)) =
+            other.tag_config.tag_definitions.iter().find(|(name, details)| {
+                tags.get(*name).map_or(false, |existing| existing != *details)
+            })
+        {
+            return Err(format!("tag \"{}\" is defined differently", name));
+        }
+        let schemes = &self.security_schemes;
+        if let Some((name, _)) =
+            other.security_schemes.iter().find(|(name, scheme)| {
+                schemes.get(*name).map_or(false, |existing| existing != *scheme)
+            })
+        {
+            return Err(format!(
+                "security scheme \"{}\" is defined differently",
+                name
+            ));
         }
 
-        if !self.tag_config.allow_other_tags {
-            for tag in &e.tags {
-                if !self.tag_config.tag_definitions.contains_key(tag) {
-                    return Err(format!("Invalid tag: {}", tag));
-                }
+        let mut endpoints = other.router.into_endpoints();
+        for e in &mut endpoints {
+            e.path = format!("{}{}", prefix, e.path);
+        }
+
+        // The tags that `other` defines may be needed to satisfy our tag
+        // policy, so add them before validating its endpoints and remove them
+        // again if that fails.
+        let new_tags = other
+            .tag_config
+            .tag_definitions
+            .into_iter()
+            .filter(|(name, _)| {
+                !self.tag_config.tag_definitions.contains_key(name)
+            })
+            .collect::<Vec<_>>();
+        let new_tag_names =
+            new_tags.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+        self.tag_config.tag_definitions.extend(new_tags);
+        if let Err(error) = self.validate_merged(&endpoints) {
+            for name in &new_tag_names {
+                self.tag_config.tag_definitions.remove(name);
             }
+            return Err(error);
+        }
+
+        for (name, scheme) in other.security_schemes {
+            self.security_schemes.entry(name).or_insert(scheme);
+        }
+        self.openapi_documents.extend(other.openapi_documents);
+        for e in endpoints {
+            self.router.insert(e);
         }
 
         Ok(())
     }
 
-    /// Validate that the parameters specified in the path match the parameters
-    /// specified by the path parameter arguments to the handler function.
-    fn validate_path_parameters(
-        &self,
-        e: &ApiEndpoint<Context>,
+    /// Serve the files in the directory `dir` at paths beginning with
+    /// `path_prefix`, which is useful for serving a web console alongside the
+    /// API.  For example, after `api.register_static_dir("/console", "ui")`,
+    /// a request for `/console/js/app.js` is served the file `ui/js/app.js`.
+    ///
+    /// A request that names a directory is served the directory's
+    /// `index.html`.  Files are sent as with [`HttpResponseFile`], with a weak
+    /// `ETag` derived from the file's size and modification time, and
+    /// conditional requests are answered with a 304 ("Not Modified") when the
+    /// client's copy is current (see [`Preconditions`]).  Paths that don't
+    /// name a file within `dir`, including via symbolic links that lead out
+    /// of it, produce a 404 ("Not Found").
+    ///
+    /// The endpoint handles `GET` requests and isn't included in the OpenAPI
+    /// document.  This fails if `dir` isn't a directory.
+    ///
+    /// [`HttpResponseFile`]: crate::HttpResponseFile
+    /// [`Preconditions`]: crate::Preconditions
+    pub fn register_static_dir<P: AsRef<std::path::Path>>(
+        &mut self,
+        path_prefix: &str,
+        dir: P,
     ) -> Result<(), String> {
-        // Gather up the path parameters and the path variable components, and
-        // make sure they're identical.
-        let path = route_path_to_segments(&e.path)
-            .iter()
-            .filter_map(|segment| match PathSegment::from(segment) {
-                PathSegment::VarnameSegment(v) => Some(v),
-                PathSegment::VarnameWildcard(v) => Some(v),
-                PathSegment::Literal(_) => None,
-            })
-            .collect::<HashSet<_>>();
-        let vars = e
-            .parameters
-            .iter()
-            .filter_map(|p| match &p.metadata {
-                ApiEndpointParameterMetadata::Path(name) => Some(name.clone()),
-                _ => None,
-            })
-            .collect::<HashSet<_>>();
+        let dir = dir.as_ref();
+        let root = std::fs::canonicalize(dir)
+            .map_err(|e| format!("static directory {:?}: {}", dir, e))?;
+        if !root.is_dir() {
+            return Err(format!("static directory {:?}: not a directory", dir));
+        }
 
-        if path != vars {
-            let mut p = path.difference(&vars).collect::<Vec<_>>();
+        let root = Arc::new(root);
+        let handler = move |_rqctx: RequestContext<Context>,
+                            path: Path<StaticDirPath>,
+                            preconditions: Preconditions,
+                            range: RangeRequest| {
+            let root = Arc::clone(&root);
+            async move {
+                static_dir_get(
+                    &root,
+                    path.into_inner().path,
+                    preconditions,
+                    range,
+                )
+                .await
+            }
+        };
+        let path = format!("{}/{{path:.*}}", path_prefix.trim_end_matches('/'));
+        self.register(
+            ApiEndpoint::new(
+                String::from("static_dir"),
+                handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                &path,
+            )
+            .visible(false),
+        )
+    }
+
+    /// Serve the OpenAPI definition of this API as JSON in response to `GET`
+    /// requests for `path`, such as `/openapi.json`, so that the server
+    /// describes itself.  The document is generated from the endpoints
+    /// registered by the time the server is created, including those
+    /// registered after this, and has the given title and version.  The
+    /// endpoint itself isn't included in the document.
+    pub fn register_openapi<S1: AsRef<str>, S2: AsRef<str>>(
+        &mut self,
+        path: &str,
+        title: S1,
+        version: S2,
+    ) -> Result<(), String> {
+        let contents = Arc::new(tokio::sync::OnceCell::new());
+        let document = OpenApiDocument {
+            title: title.as_ref().to_string(),
+            version: version.as_ref().to_string(),
+            contents: Arc::clone(&contents),
+        };
+        let handler = move |_rqctx: RequestContext<Context>| {
+            let contents = Arc::clone(&contents);
+            async move { openapi_get(&contents) }
+        };
+        self.register(
+            ApiEndpoint::new(
+                String::from("openapi"),
+                handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                path,
+            )
+            .visible(false),
+        )?;
+        self.openapi_documents.push(document);
+        Ok(())
+    }
+
+    /// Serve an HTML page at `path` that renders the OpenAPI document at
+    /// `spec_url` as browsable documentation using
+    /// [ReDoc](https://github.com/Redocly/redoc).  `spec_url` is typically the
+    /// path given to [`ApiDescription::register_openapi`].  This requires the
+    /// `"redoc"` feature.
+    ///
+    /// Dropshot doesn't ship ReDoc itself, and the page doesn't load it from a
+    /// third-party CDN.  Instead, `script` is the contents of ReDoc's
+    /// `redoc.standalone.js` bundle (found in the `bundles` directory of the
+    /// `redoc` npm package), which the consumer vendors and embeds with
+    /// `include_bytes!`.  It's served at `redoc.standalone.js` under `path`.
+    ///
+    /// ```ignore
+    /// api.register_redoc(
+    ///     "/docs",
+    ///     "My API",
+    ///     "/openapi.json",
+    ///     include_bytes!("../vendor/redoc.standalone.js"),
+    /// )?;
+    /// ```
+    #[cfg(feature = "redoc")]
+    pub fn register_redoc(
+        &mut self,
+        path: &str,
+        title: &str,
+        spec_url: &str,
+        script: &'static [u8],
+    ) -> Result<(), String> {
+        let script_path = format!(
+            "{}/{}",
+            path.trim_end_matches('/'),
+            crate::openapi_docs::REDOC_SCRIPT_NAME
+        );
+        let page = Arc::new(crate::openapi_docs::redoc_page(
+            title,
+            spec_url,
+            &script_path,
+        ));
+        let handler = move |_rqctx: RequestContext<Context>| {
+            let page = Arc::clone(&page);
+            async move { crate::openapi_docs::redoc_get(&page) }
+        };
+        self.register(
+            ApiEndpoint::new(
+                String::from("redoc"),
+                handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                path,
+            )
+            .visible(false),
+        )?;
+
+        let handler = move |_rqctx: RequestContext<Context>| async move {
+            crate::openapi_docs::redoc_script_get(script)
+        };
+        self.register(
+            ApiEndpoint::new(
+                String::from("redoc_script"),
+                handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                &script_path,
+            )
+            .visible(false),
+        )
+    }
+
+    /// Resolve the endpoint's parameter naming convention and rename its path
+    /// and query parameters accordingly.  This fails if a parameter's name in
+    /// that convention would not be translated back into its original name.
+    fn apply_parameter_case(
+        &self,
+        e: &mut ApiEndpoint<Context>,
+    ) -> Result<(), String> {
+        let case = *e.parameter_case.get_or_insert(self.parameter_case);
+        if case == ParameterCase::Snake {
+            return Ok(());
+        }
+
+        for param in &mut e.parameters {
+            match &mut param.metadata {
+                ApiEndpointParameterMetadata::Path(name)
+                | ApiEndpointParameterMetadata::Query(name) => {
+                    let wire_name = case.to_wire(name);
+                    if case.from_wire(&wire_name) != *name {
+                        return Err(format!(
+                            "endpoint \"{}\": parameter \"{}\" cannot be \
+                             named in {:?} case (\"{}\" would be read as \
+                             \"{}\")",
+                            e.operation_id,
+                            name,
+                            case,
+                            wire_name,
+                            case.from_wire(&wire_name)
+                        ));
+                    }
+                    *name = wire_name;
+                }
+                // Header and cookie names follow HTTP's own conventions.
+                ApiEndpointParameterMetadata::Header(_)
+                | ApiEndpointParameterMetadata::Cookie(_)
+                | ApiEndpointParameterMetadata::Body(_) => (),
+            }
+        }
+
+        // The pagination extension lists the names of query parameters that
+        // are required for the first page.
+        if let ExtensionMode::Paginated(first_page) = &mut e.extension_mode {
+            if let Some(serde_json::Value::Array(required)) =
+                first_page.get_mut("required")
+            {
+                for name in required.iter_mut() {
+                    if let serde_json::Value::String(s) = name {
+                        *s = case.to_wire(s);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Wrap the endpoint's handler to enforce its concurrency limit, if it has
+    /// one.  This happens before aliases are constructed so that they share
+    /// the limit.
+    fn apply_max_concurrency(
+        &self,
+        e: &mut ApiEndpoint<Context>,
+    ) -> Result<(), String> {
+        match e.max_concurrency {
+            None => Ok(()),
+            Some(0) => {
+                Err("max_concurrency must be greater than 0".to_string())
+            }
+            Some(limit) => {
+                e.handler = ConcurrencyLimitedHandler::new(
+                    Arc::clone(&e.handler),
+                    limit,
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Add the security schemes that the endpoint requires by name to those
+    /// contributed by its extractors.
+    fn apply_security_requirements(
+        &self,
+        e: &mut ApiEndpoint<Context>,
+    ) -> Result<(), String> {
+        for (name, scopes) in &e.security_requirements {
+            let scheme = self.security_schemes.get(name).ok_or_else(|| {
+                format!("security scheme \"{}\" has not been declared", name)
+            })?;
+            match e.security.iter_mut().find(|s| &s.name == name) {
+                Some(security) => {
+                    for scope in scopes {
+                        if !security.scopes.contains(scope) {
+                            security.scopes.push(scope.clone());
+                        }
+                    }
+                }
+                None => e.security.push(ApiEndpointSecurity {
+                    name: name.clone(),
+                    scheme: scheme.clone(),
+                    scopes: scopes.clone(),
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate that the endpoint's examples describe parts of the request
+    /// that it has.
+    fn validate_examples(
+        &self,
+        e: &ApiEndpoint<Context>,
+    ) -> Result<(), String> {
+        if !e.request_examples.is_empty()
+            && !e.parameters.iter().any(|param| {
+                matches!(param.metadata, ApiEndpointParameterMetadata::Body(_))
+            })
+        {
+            return Err(format!(
+                "request examples given for operation \"{}\", which has no \
+                 request body",
+                e.operation_id
+            ));
+        }
+        for name in e.parameter_examples.keys() {
+            let found =
+                e.parameters.iter().any(|param| match &param.metadata {
+                    ApiEndpointParameterMetadata::Path(n)
+                    | ApiEndpointParameterMetadata::Query(n)
+                    | ApiEndpointParameterMetadata::Header(n)
+                    | ApiEndpointParameterMetadata::Cookie(n) => n == name,
+                    ApiEndpointParameterMetadata::Body(_) => false,
+                });
+            if !found {
+                return Err(format!(
+                    "example given for parameter \"{}\", which operation \
+                     \"{}\" does not have",
+                    name, e.operation_id
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate that endpoints being merged from another description don't
+    /// conflict with those already registered.
+    fn validate_merged(
+        &self,
+        endpoints: &[ApiEndpoint<Context>],
+    ) -> Result<(), String> {
+        for e in endpoints {
+            if let Some(conflict) = self.router.conflict(&e.method, &e.path) {
+                return Err(conflict);
+            }
+            self.validate_operation_id(e)?;
+            self.validate_tags(e)?;
+        }
+        Ok(())
+    }
+
+    /// Validate that no other endpoint in the OpenAPI document has the same
+    /// operation ID, since client generators name operations after them.
+    fn validate_operation_id(
+        &self,
+        e: &ApiEndpoint<Context>,
+    ) -> Result<(), String> {
+        if !e.visible {
+            return Ok(());
+        }
+        match (&self.router).into_iter().find(|(_, _, other)| {
+            other.visible && other.operation_id == e.operation_id
+        }) {
+            Some((path, method, _)) => Err(format!(
+                "operation ID \"{}\" is already used by {} {}",
+                e.operation_id, method, path
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Validate that the tags conform to the tags policy.
+    fn validate_tags(&self, e: &ApiEndpoint<Context>) -> Result<(), String> {
+        // Don't care about endpoints that don't appear in the OpenAPI
+        if !e.visible {
+            return Ok(());
+        }
+
+        match (&self.tag_config.endpoint_tag_policy, e.tags.len()) {
+            (EndpointTagPolicy::AtLeastOne, 0) => {
+                return Err("At least one tag is required".to_string())
+            }
+            (EndpointTagPolicy::ExactlyOne, n) if n != 1 => {
+                return Err("Exactly one tag is required".to_string())
+            }
+            _ => (),
+        }
+
+        if !self.tag_config.allow_other_tags {
+            for tag in &e.tags {
+                if !self.tag_config.tag_definitions.contains_key(tag) {
+                    return Err(format!("Invalid tag: {}", tag));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validate that the parameters specified in the path match the parameters
+    /// specified by the path parameter arguments to the handler function.
+    fn validate_path_parameters(
+        &self,
+        e: &ApiEndpoint<Context>,
+    ) -> Result<(), String> {
+        // Gather up the path parameters and the path variable components, and
+        // make sure they're identical.
+        let path = route_path_to_segments(&e.path)
+            .iter()
+            .filter_map(|segment| match PathSegment::from(segment) {
+                PathSegment::VarnameSegment(v) => Some(v),
+                PathSegment::VarnameWildcard(v) => Some(v),
+                PathSegment::Literal(_) => None,
+            })
+            .collect::<HashSet<_>>();
+        let vars = e
+            .parameters
+            .iter()
+            .filter_map(|p| match &p.metadata {
+                ApiEndpointParameterMetadata::Path(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+
+        if path != vars {
+            let mut p = path.difference(&vars).collect::<Vec<_>>();
             let mut v = vars.difference(&path).collect::<Vec<_>>();
             p.sort();
             v.sort();
@@ -386,7 +1639,8 @@ impl<Context: ServerContext> ApiDescription<Context> {
 
     /// Validate that named parameters have appropriate types and there are no
     /// duplicates. Parameters must have scalar types except in the case of the
-    /// received for a wildcard path which must be an array of String.
+    /// received for a wildcard path which must be an array of String, and of
+    /// query parameters which may also be arrays of scalars.
     fn validate_named_parameters(
         &self,
         e: &ApiEndpoint<Context>,
@@ -412,10 +1666,13 @@ impl<Context: ServerContext> ApiDescription<Context> {
             .collect::<BTreeMap<_, _>>();
 
         for param in &e.parameters {
-            // Skip anything that's not a path or query parameter (i.e. body)
+            // Skip anything that's not a path, query, header, or cookie
+            // parameter (i.e. body)
             match &param.metadata {
                 ApiEndpointParameterMetadata::Path(_)
-                | ApiEndpointParameterMetadata::Query(_) => (),
+                | ApiEndpointParameterMetadata::Query(_)
+                | ApiEndpointParameterMetadata::Header(_)
+                | ApiEndpointParameterMetadata::Cookie(_) => (),
                 _ => continue,
             }
             // Only body parameters should have unresolved schemas
@@ -458,6 +1715,15 @@ impl<Context: ServerContext> ApiDescription<Context> {
                             name
                         ));
                     }
+                    type_is_scalar_or_array(
+                        &e.operation_id,
+                        name,
+                        schema,
+                        dependencies,
+                    )?;
+                }
+                ApiEndpointParameterMetadata::Header(ref name)
+                | ApiEndpointParameterMetadata::Cookie(ref name) => {
                     type_is_scalar(
                         &e.operation_id,
                         name,
@@ -472,6 +1738,73 @@ impl<Context: ServerContext> ApiDescription<Context> {
         Ok(())
     }
 
+    /// Check the whole API for problems that make for a poor OpenAPI
+    /// document, returning every problem found.  This is meant to be called
+    /// once all endpoints have been registered, e.g. before starting the
+    /// server or in a test, and reports:
+    ///
+    /// * published operations with neither a summary nor a description
+    /// * operations whose path parameters don't match the variables in their
+    ///   paths
+    /// * operation IDs used by more than one published operation
+    /// * schemas included in the document that no operation uses
+    ///
+    /// `ApiDescription::register()` already rejects endpoints with mismatched
+    /// path parameters or duplicate operation IDs; they're reported here too
+    /// so that a successful validation covers the API as a whole.
+    pub fn validate(&self) -> Result<(), Vec<ApiProblem>> {
+        let mut problems = Vec::new();
+        let mut operation_ids = HashMap::new();
+        for (path, method, e) in &self.router {
+            let location = format!("{} {}", method, path);
+            if let Err(message) = self.validate_path_parameters(e) {
+                problems.push(ApiProblem {
+                    kind: ApiProblemKind::PathParameters,
+                    location: location.clone(),
+                    message,
+                });
+            }
+            if !e.visible {
+                continue;
+            }
+            if e.summary.is_none() && e.description.is_none() {
+                problems.push(ApiProblem {
+                    kind: ApiProblemKind::MissingDescription,
+                    location: location.clone(),
+                    message: "operation has no summary or description"
+                        .to_string(),
+                });
+            }
+            if let Some(other) =
+                operation_ids.insert(e.operation_id.as_str(), location.clone())
+            {
+                problems.push(ApiProblem {
+                    kind: ApiProblemKind::DuplicateOperationId,
+                    location,
+                    message: format!(
+                        "operation ID \"{}\" is also used by {}",
+                        e.operation_id, other
+                    ),
+                });
+            }
+        }
+
+        match self.openapi("", "").json() {
+            Ok(spec) => problems.extend(unreferenced_schemas(&spec)),
+            Err(error) => problems.push(ApiProblem {
+                kind: ApiProblemKind::InvalidDocument,
+                location: String::from("#"),
+                message: error.to_string(),
+            }),
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
     /// Build the OpenAPI definition describing this API.  Returns an
     /// [`OpenApiDefinition`] which can be used to specify the contents of the
     /// definition and select an output format.
@@ -493,7 +1826,10 @@ impl<Context: ServerContext> ApiDescription<Context> {
 
     /// Internal routine for constructing the OpenAPI definition describing this
     /// API in its JSON form.
-    fn gen_openapi(&self, info: openapiv3::Info) -> openapiv3::OpenAPI {
+    fn gen_openapi(
+        &self,
+        info: openapiv3::Info,
+    ) -> Result<openapiv3::OpenAPI, String> {
         let mut openapi = openapiv3::OpenAPI::default();
 
         openapi.openapi = "3.0.3".to_string();
@@ -537,8 +1873,21 @@ impl<Context: ServerContext> ApiDescription<Context> {
 
         let settings = schemars::gen::SchemaSettings::openapi3();
         let mut generator = schemars::gen::SchemaGenerator::new(settings);
-        let mut definitions =
-            indexmap::IndexMap::<String, schemars::schema::Schema>::new();
+        let error_schema = j2oas_schema(
+            None,
+            &generator.subschema_for::<HttpErrorResponseBody>(),
+        );
+        let mut definitions = SchemaDefinitions::default();
+        let mut security_schemes = indexmap::IndexMap::<
+            String,
+            openapiv3::ReferenceOr<openapiv3::SecurityScheme>,
+        >::new();
+        for (name, scheme) in &self.security_schemes {
+            security_schemes.insert(
+                name.clone(),
+                openapiv3::ReferenceOr::Item(scheme.clone()),
+            );
+        }
 
         for (path, method, endpoint) in &self.router {
             if !endpoint.visible {
@@ -571,6 +1920,24 @@ impl<Context: ServerContext> ApiDescription<Context> {
             operation.tags = endpoint.tags.clone();
             operation.deprecated = endpoint.deprecated;
 
+            // Every scheme the endpoint's extractors check must be satisfied,
+            // so they make up a single security requirement.
+            if !endpoint.security.is_empty() {
+                let mut requirement = indexmap::IndexMap::new();
+                for security in &endpoint.security {
+                    requirement
+                        .insert(security.name.clone(), security.scopes.clone());
+                    security_schemes
+                        .entry(security.name.clone())
+                        .or_insert_with(|| {
+                            openapiv3::ReferenceOr::Item(
+                                security.scheme.clone(),
+                            )
+                        });
+                }
+                operation.security = Some(vec![requirement]);
+            }
+
             operation.parameters = endpoint
                 .parameters
                 .iter()
@@ -583,11 +1950,17 @@ impl<Context: ServerContext> ApiDescription<Context> {
                         ApiEndpointParameterMetadata::Query(name) => {
                             (name, ApiEndpointParameterLocation::Query)
                         }
+                        ApiEndpointParameterMetadata::Header(name) => {
+                            (name, ApiEndpointParameterLocation::Header)
+                        }
+                        ApiEndpointParameterMetadata::Cookie(name) => {
+                            (name, ApiEndpointParameterLocation::Cookie)
+                        }
                     };
 
                     let schema = match &param.schema {
                         ApiSchemaGenerator::Static { schema, dependencies } => {
-                            definitions.extend(dependencies.clone());
+                            definitions.extend(dependencies);
                             j2oas_schema(None, schema)
                         }
                         _ => {
@@ -603,10 +1976,16 @@ impl<Context: ServerContext> ApiDescription<Context> {
                         format: openapiv3::ParameterSchemaOrContent::Schema(
                             schema,
                         ),
-                        example: None,
+                        example: endpoint.parameter_examples.get(name).cloned(),
                         examples: indexmap::IndexMap::new(),
                         extensions: indexmap::IndexMap::new(),
-                        explode: None,
+                        // Query parameters are deserialized from repeated
+                        // `name=value` pairs, i.e. the "form" style with
+                        // "explode" set.
+                        explode: match location {
+                            ApiEndpointParameterLocation::Query => Some(true),
+                            _ => None,
+                        },
                     };
                     match location {
                         ApiEndpointParameterLocation::Query => {
@@ -627,6 +2006,22 @@ impl<Context: ServerContext> ApiDescription<Context> {
                                 },
                             ))
                         }
+                        ApiEndpointParameterLocation::Header => {
+                            Some(openapiv3::ReferenceOr::Item(
+                                openapiv3::Parameter::Header {
+                                    parameter_data: parameter_data,
+                                    style: openapiv3::HeaderStyle::Simple,
+                                },
+                            ))
+                        }
+                        ApiEndpointParameterLocation::Cookie => {
+                            Some(openapiv3::ReferenceOr::Item(
+                                openapiv3::Parameter::Cookie {
+                                    parameter_data: parameter_data,
+                                    style: openapiv3::CookieStyle::Form,
+                                },
+                            ))
+                        }
                     }
                 })
                 .collect::<Vec<_>>();
@@ -635,41 +2030,61 @@ impl<Context: ServerContext> ApiDescription<Context> {
                 .parameters
                 .iter()
                 .filter_map(|param| {
-                    let mime_type = match &param.metadata {
+                    // A body of the endpoint's own content type is parsed
+                    // in any of its encodings, if it offers several.
+                    let content_types = match &param.metadata {
+                        ApiEndpointParameterMetadata::Body(ct)
+                            if *ct == endpoint.body_content_type
+                                && !endpoint.encodings.is_empty() =>
+                        {
+                            endpoint.encodings.clone()
+                        }
                         ApiEndpointParameterMetadata::Body(ct) => {
-                            ct.mime_type()
+                            vec![ct.clone()]
                         }
                         _ => return None,
                     };
 
                     let (name, js) = match &param.schema {
-                        ApiSchemaGenerator::Gen { name, schema } => {
-                            (Some(name()), schema(&mut generator))
-                        }
+                        ApiSchemaGenerator::Gen { name, schema } => (
+                            Some(name()),
+                            definitions.generate(&mut generator, *schema),
+                        ),
                         ApiSchemaGenerator::Static { schema, dependencies } => {
-                            definitions.extend(dependencies.clone());
+                            definitions.extend(dependencies);
                             (None, schema.as_ref().clone())
                         }
                     };
                     let schema = j2oas_schema(name.as_ref(), &js);
 
                     let mut content = indexmap::IndexMap::new();
-                    content.insert(
-                        mime_type.to_string(),
-                        openapiv3::MediaType {
-                            schema: Some(schema),
-                            ..Default::default()
-                        },
-                    );
+                    for content_type in content_types {
+                        content.insert(
+                            content_type.mime_type().to_string(),
+                            openapiv3::MediaType {
+                                schema: Some(schema.clone()),
+                                examples: openapi_examples(
+                                    &endpoint.request_examples,
+                                ),
+                                ..Default::default()
+                            },
+                        );
+                    }
 
                     Some(openapiv3::ReferenceOr::Item(openapiv3::RequestBody {
                         content: content,
-                        required: true,
+                        required: param.required,
                         ..Default::default()
                     }))
                 })
                 .next();
 
+            operation.extensions.extend(
+                endpoint
+                    .extensions
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.clone())),
+            );
             match &endpoint.extension_mode {
                 ExtensionMode::None => {}
                 ExtensionMode::Paginated(first_page_schema) => {
@@ -686,65 +2101,80 @@ impl<Context: ServerContext> ApiDescription<Context> {
                 }
             }
 
+            let headers: indexmap::IndexMap<_, _> = endpoint
+                .response
+                .headers
+                .iter()
+                .map(|header| {
+                    let schema = match &header.schema {
+                        ApiSchemaGenerator::Gen { name, schema } => {
+                            let schema =
+                                definitions.generate(&mut generator, *schema);
+                            j2oas_schema(Some(&name()), &schema)
+                        }
+                        ApiSchemaGenerator::Static { schema, dependencies } => {
+                            definitions.extend(dependencies);
+                            j2oas_schema(None, schema)
+                        }
+                    };
+
+                    (
+                        header.name.clone(),
+                        openapiv3::ReferenceOr::Item(openapiv3::Header {
+                            description: header.description.clone(),
+                            style: openapiv3::HeaderStyle::Simple,
+                            required: header.required,
+                            deprecated: None,
+                            format: openapiv3::ParameterSchemaOrContent::Schema(
+                                schema,
+                            ),
+                            example: None,
+                            examples: indexmap::IndexMap::new(),
+                            extensions: indexmap::IndexMap::new(),
+                        }),
+                    )
+                })
+                .collect();
+
             let response = if let Some(schema) = &endpoint.response.schema {
                 let (name, js) = match schema {
-                    ApiSchemaGenerator::Gen { name, schema } => {
-                        (Some(name()), schema(&mut generator))
-                    }
+                    ApiSchemaGenerator::Gen { name, schema } => (
+                        Some(name()),
+                        definitions.generate(&mut generator, *schema),
+                    ),
                     ApiSchemaGenerator::Static { schema, dependencies } => {
-                        definitions.extend(dependencies.clone());
+                        definitions.extend(dependencies);
                         (None, schema.as_ref().clone())
                     }
                 };
                 let mut content = indexmap::IndexMap::new();
                 if !is_empty(&js) {
-                    content.insert(
-                        CONTENT_TYPE_JSON.to_string(),
-                        openapiv3::MediaType {
-                            schema: Some(j2oas_schema(name.as_ref(), &js)),
-                            ..Default::default()
-                        },
-                    );
-                }
-
-                let headers = endpoint
-                    .response
-                    .headers
-                    .iter()
-                    .map(|header| {
-                        let schema = match &header.schema {
-                            ApiSchemaGenerator::Static {
-                                schema,
-                                dependencies,
-                            } => {
-                                definitions.extend(dependencies.clone());
-                                j2oas_schema(None, schema)
-                            }
-                            _ => {
-                                unimplemented!(
-                                    "this may happen for complex types"
-                                )
-                            }
+                    let schema = j2oas_schema(name.as_ref(), &js);
+                    let content_types =
+                        if !endpoint.response.content_types.is_empty() {
+                            endpoint.response.content_types.clone()
+                        } else if !endpoint.encodings.is_empty() {
+                            endpoint
+                                .encodings
+                                .iter()
+                                .map(ApiEndpointBodyContentType::mime_type)
+                                .collect()
+                        } else {
+                            vec![CONTENT_TYPE_JSON]
                         };
-
-                        (
-                            header.name.clone(),
-                            openapiv3::ReferenceOr::Item(openapiv3::Header {
-                                description: header.description.clone(),
-                                style: openapiv3::HeaderStyle::Simple,
-                                required: header.required,
-                                deprecated: None,
-                                format:
-                                    openapiv3::ParameterSchemaOrContent::Schema(
-                                        schema,
-                                    ),
-                                example: None,
-                                examples: indexmap::IndexMap::new(),
-                                extensions: indexmap::IndexMap::new(),
-                            }),
-                        )
-                    })
-                    .collect();
+                    for content_type in content_types {
+                        content.insert(
+                            content_type.to_string(),
+                            openapiv3::MediaType {
+                                schema: Some(schema.clone()),
+                                examples: openapi_examples(
+                                    &endpoint.response_examples,
+                                ),
+                                ..Default::default()
+                            },
+                        );
+                    }
+                }
 
                 let response = openapiv3::Response {
                     description: if let Some(description) =
@@ -765,11 +2195,15 @@ impl<Context: ServerContext> ApiDescription<Context> {
             } else {
                 // If no schema was specified, the response is hand-rolled. In
                 // this case we'll fall back to the default response type which
-                // we assume to be inclusive of errors. The media type and
-                // and schema will similarly be maximally permissive.
+                // we assume to be inclusive of errors. The media type (unless
+                // the endpoint declares one) and schema will similarly be
+                // maximally permissive.
                 let mut content = indexmap::IndexMap::new();
                 content.insert(
-                    "*/*".to_string(),
+                    endpoint
+                        .response_content_type
+                        .clone()
+                        .unwrap_or_else(|| "*/*".to_string()),
                     openapiv3::MediaType {
                         schema: Some(openapiv3::ReferenceOr::Item(
                             openapiv3::Schema {
@@ -779,6 +2213,7 @@ impl<Context: ServerContext> ApiDescription<Context> {
                                 ),
                             },
                         )),
+                        examples: openapi_examples(&endpoint.response_examples),
                         ..Default::default()
                     },
                 );
@@ -788,15 +2223,45 @@ impl<Context: ServerContext> ApiDescription<Context> {
                     // by OpenAPI.
                     description: "".to_string(),
                     content,
+                    headers,
                     ..Default::default()
                 }
             };
 
-            if let Some(code) = &endpoint.response.success {
-                operation.responses.responses.insert(
-                    openapiv3::StatusCode::Code(code.as_u16()),
-                    openapiv3::ReferenceOr::Item(response),
+            // Declared errors carry the same information as any other error
+            // response, but are described by their status.
+            let error_responses = endpoint.errors.iter().map(|code| {
+                let mut content = indexmap::IndexMap::new();
+                content.insert(
+                    CONTENT_TYPE_JSON.to_string(),
+                    openapiv3::MediaType {
+                        schema: Some(error_schema.clone()),
+                        ..Default::default()
+                    },
                 );
+                (
+                    openapiv3::StatusCode::Code(code.as_u16()),
+                    openapiv3::ReferenceOr::Item(openapiv3::Response {
+                        description: code
+                            .canonical_reason()
+                            .unwrap_or("Error")
+                            .to_string(),
+                        content,
+                        ..Default::default()
+                    }),
+                )
+            });
+
+            if let Some(code) = &endpoint.response.success {
+                let codes = std::iter::once(code)
+                    .chain(&endpoint.response.other_statuses);
+                for code in codes {
+                    operation.responses.responses.insert(
+                        openapiv3::StatusCode::Code(code.as_u16()),
+                        openapiv3::ReferenceOr::Item(response.clone()),
+                    );
+                }
+                operation.responses.responses.extend(error_responses);
 
                 // 4xx and 5xx responses all use the same error information
                 let err_ref = openapiv3::ReferenceOr::ref_(
@@ -811,6 +2276,7 @@ impl<Context: ServerContext> ApiDescription<Context> {
                     .responses
                     .insert(openapiv3::StatusCode::Range(5), err_ref);
             } else {
+                operation.responses.responses.extend(error_responses);
                 operation.responses.default =
                     Some(openapiv3::ReferenceOr::Item(response))
             }
@@ -846,21 +2312,35 @@ impl<Context: ServerContext> ApiDescription<Context> {
             }),
         );
 
+        components.security_schemes.extend(security_schemes);
+
         // Add the schemas for which we generated references.
-        let schemas = &mut components.schemas;
-
         let root_schema = generator.into_root_schema_for::<()>();
+        definitions.extend(&root_schema.definitions);
+        if !definitions.conflicts.is_empty() {
+            return Err(format!(
+                "different schemas have the same name ({}); give the types \
+                 distinct names, e.g. with #[schemars(rename = \"...\")]",
+                definitions
+                    .conflicts
+                    .into_iter()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        let schemas = &mut components.schemas;
         root_schema.definitions.iter().for_each(|(key, schema)| {
             schemas.insert(key.clone(), j2oas_schema(None, schema));
         });
 
-        definitions.into_iter().for_each(|(key, schema)| {
+        definitions.schemas.into_iter().for_each(|(key, schema)| {
             if !schemas.contains_key(&key) {
                 schemas.insert(key, j2oas_schema(None, &schema));
             }
         });
 
-        openapi
+        Ok(openapi)
     }
 
     // TODO-cleanup is there a way to make this available only within this
@@ -869,6 +2349,121 @@ impl<Context: ServerContext> ApiDescription<Context> {
     pub fn into_router(self) -> HttpRouter<Context> {
         self.router
     }
+
+    /// Consumes the description, returning everything the server needs to
+    /// run it.
+    pub(crate) fn into_server_parts(mut self) -> ServerParts<Context> {
+        for document in std::mem::take(&mut self.openapi_documents) {
+            let contents = self
+                .openapi(&document.title, &document.version)
+                .json()
+                .map(|json| json.to_string())
+                .map_err(|error| error.to_string());
+            // Each document belongs to exactly one description, so this is
+            // the only place its contents are set.
+            let _ = document.contents.set(contents);
+        }
+
+        ServerParts {
+            router: self.router,
+            request_log_hook: self.request_log_hook,
+            clock: self.clock,
+            cookie_key: self.cookie_key,
+            page_tokens: self.page_tokens,
+        }
+    }
+}
+
+/// The schemas collected for the components of an OpenAPI document, keyed by
+/// the names with which they are referenced.
+#[derive(Default)]
+struct SchemaDefinitions {
+    schemas: indexmap::IndexMap<String, schemars::schema::Schema>,
+    /// names used for more than one schema, such as those of two types with
+    /// the same name in different modules
+    conflicts: BTreeSet<String>,
+}
+
+impl SchemaDefinitions {
+    fn extend<'a, I>(&mut self, schemas: I)
+    where
+        I: IntoIterator<Item = (&'a String, &'a schemars::schema::Schema)>,
+    {
+        for (name, schema) in schemas {
+            match self.schemas.get(name) {
+                Some(existing) if existing != schema => {
+                    self.conflicts.insert(name.clone());
+                }
+                Some(_) => (),
+                None => {
+                    self.schemas.insert(name.clone(), schema.clone());
+                }
+            }
+        }
+    }
+
+    /// Generate a schema with `generator`, checking the schemas that it
+    /// references against those already collected.  (The generator itself
+    /// silently reuses the first schema it generated under a given name.)
+    fn generate(
+        &mut self,
+        generator: &mut schemars::gen::SchemaGenerator,
+        schema: fn(
+            &mut schemars::gen::SchemaGenerator,
+        ) -> schemars::schema::Schema,
+    ) -> schemars::schema::Schema {
+        let mut own =
+            schemars::gen::SchemaGenerator::new(generator.settings().clone());
+        schema(&mut own);
+        self.extend(own.definitions());
+        schema(generator)
+    }
+}
+
+/// Serialize an example attached to an endpoint.
+fn example_value<T: Serialize>(value: &T) -> serde_json::Value {
+    serde_json::to_value(value).expect("failed to serialize example")
+}
+
+/// Describe the examples attached to an endpoint as those of a media type.
+fn openapi_examples(
+    examples: &BTreeMap<String, serde_json::Value>,
+) -> indexmap::IndexMap<String, openapiv3::ReferenceOr<openapiv3::Example>> {
+    examples
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.clone(),
+                openapiv3::ReferenceOr::Item(openapiv3::Example {
+                    value: Some(value.clone()),
+                    ..Default::default()
+                }),
+            )
+        })
+        .collect()
+}
+
+/// Validate the names of vendor extensions: OpenAPI requires them to begin
+/// with `x-`, and those beginning with `x-dropshot-` are Dropshot's own.
+fn validate_extension_names<'a, I>(names: I) -> Result<(), String>
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    for name in names {
+        if !name.starts_with("x-") {
+            return Err(format!(
+                "extension \"{}\" must begin with \"x-\"",
+                name
+            ));
+        }
+        if name.starts_with("x-dropshot-") {
+            return Err(format!(
+                "extension \"{}\" uses the reserved prefix \"x-dropshot-\"",
+                name
+            ));
+        }
+    }
+    Ok(())
 }
 
 /// Returns true iff the schema represents the void schema that matches no data.
@@ -930,11 +2525,23 @@ fn is_empty(schema: &schemars::schema::Schema) -> bool {
 /// This object is used to specify configuration for building an OpenAPI
 /// definition document.  It is constructed using [`ApiDescription::openapi()`].
 /// Additional optional properties may be added and then the OpenAPI definition
-/// document may be generated via [`write()`](`OpenApiDefinition::write`) or
+/// document may be generated via [`write()`](`OpenApiDefinition::write`),
+/// [`write_yaml()`](`OpenApiDefinition::write_yaml`), or
 /// [`json()`](`OpenApiDefinition::json`).
 pub struct OpenApiDefinition<'a, Context: ServerContext> {
     api: &'a ApiDescription<Context>,
     info: openapiv3::Info,
+    servers: Vec<openapiv3::Server>,
+    extensions: indexmap::IndexMap<String, serde_json::Value>,
+    version: OpenApiVersion,
+}
+
+/// An OpenAPI document in the version requested of an [`OpenApiDefinition`]
+#[derive(Serialize)]
+#[serde(untagged)]
+enum OpenApiDocumentVersion {
+    V3_0(openapiv3::OpenAPI),
+    V3_1(serde_json::Value),
 }
 
 impl<'a, Context: ServerContext> OpenApiDefinition<'a, Context> {
@@ -948,7 +2555,24 @@ impl<'a, Context: ServerContext> OpenApiDefinition<'a, Context> {
             version: version.to_string(),
             ..Default::default()
         };
-        OpenApiDefinition { api, info }
+        OpenApiDefinition {
+            api,
+            info,
+            servers: Vec::new(),
+            extensions: indexmap::IndexMap::new(),
+            version: OpenApiVersion::default(),
+        }
+    }
+
+    /// Select the version of the OpenAPI specification to which the document
+    /// conforms.  The default is OpenAPI 3.0; with [`OpenApiVersion::V3_1`],
+    /// schemas follow JSON Schema 2020-12 as OpenAPI 3.1 requires, e.g.
+    /// marking nullable values with `type: [T, "null"]`.
+    ///
+    /// This routine will set the `openapi` field of the OpenAPI definition.
+    pub fn version(&mut self, version: OpenApiVersion) -> &mut Self {
+        self.version = version;
+        self
     }
 
     /// Provide a short description of the API.  CommonMark syntax may be
@@ -1040,9 +2664,78 @@ impl<'a, Context: ServerContext> OpenApiDefinition<'a, Context> {
         self
     }
 
+    /// Add a server at which the API is available, with a description of it
+    /// (e.g., "production").  `url` may be relative to the location of the
+    /// OpenAPI definition.
+    ///
+    /// This routine will add a `Server` object to the `servers` field of the
+    /// OpenAPI definition.  Servers appear in the order in which they are
+    /// added.
+    pub fn server<S1, S2>(&mut self, url: S1, description: S2) -> &mut Self
+    where
+        S1: AsRef<str>,
+        S2: AsRef<str>,
+    {
+        self.servers.push(openapiv3::Server {
+            url: url.as_ref().to_string(),
+            description: Some(description.as_ref().to_string()),
+            ..Default::default()
+        });
+        self
+    }
+
+    /// Add a server at which the API is available.
+    ///
+    /// This routine will add a `Server` object to the `servers` field of the
+    /// OpenAPI definition.
+    pub fn server_url<S: AsRef<str>>(&mut self, url: S) -> &mut Self {
+        self.servers.push(openapiv3::Server {
+            url: url.as_ref().to_string(),
+            ..Default::default()
+        });
+        self
+    }
+
+    /// Attach a vendor extension to the document, such as `x-logo`.  The name
+    /// must begin with `x-` and must not begin with `x-dropshot-`, which is
+    /// reserved for Dropshot's own extensions; otherwise, building the
+    /// document fails.
+    ///
+    /// This routine will add the field to the top-level object of the OpenAPI
+    /// definition.
+    pub fn extension<S: AsRef<str>>(
+        &mut self,
+        name: S,
+        value: serde_json::Value,
+    ) -> &mut Self {
+        self.extensions.insert(name.as_ref().to_string(), value);
+        self
+    }
+
+    fn build<E: serde::ser::Error>(&self) -> Result<OpenApiDocumentVersion, E> {
+        validate_extension_names(self.extensions.keys()).map_err(E::custom)?;
+        let mut openapi =
+            self.api.gen_openapi(self.info.clone()).map_err(E::custom)?;
+        openapi.servers = self.servers.clone();
+        openapi.extensions = self.extensions.clone();
+        match self.version {
+            OpenApiVersion::V3_0 => Ok(OpenApiDocumentVersion::V3_0(openapi)),
+            OpenApiVersion::V3_1 => {
+                let mut document =
+                    serde_json::to_value(&openapi).map_err(E::custom)?;
+                convert_to_3_1(&mut document);
+                Ok(OpenApiDocumentVersion::V3_1(document))
+            }
+        }
+    }
+
     /// Build a JSON object containing the OpenAPI definition for this API.
+    ///
+    /// This fails if two different schemas would be included in the document
+    /// under the same name, as when types in different modules have the same
+    /// name.
     pub fn json(&self) -> serde_json::Result<serde_json::Value> {
-        serde_json::to_value(&self.api.gen_openapi(self.info.clone()))
+        serde_json::to_value(&self.build()?)
     }
 
     /// Build a JSON object containing the OpenAPI definition for this API and
@@ -1051,10 +2744,40 @@ impl<'a, Context: ServerContext> OpenApiDefinition<'a, Context> {
         &self,
         out: &mut dyn std::io::Write,
     ) -> serde_json::Result<()> {
-        serde_json::to_writer_pretty(
-            out,
-            &self.api.gen_openapi(self.info.clone()),
-        )
+        serde_json::to_writer_pretty(out, &self.build()?)
+    }
+
+    /// Build the OpenAPI definition for this API and write it to the provided
+    /// stream as YAML.
+    pub fn write_yaml(
+        &self,
+        out: &mut dyn std::io::Write,
+    ) -> serde_yaml::Result<()> {
+        serde_yaml::to_writer(out, &self.build()?)
+    }
+
+    /// Write the OpenAPI definition for this API to the file at `path`, as
+    /// YAML if its extension is `.yaml` or `.yml` and as JSON otherwise.
+    ///
+    /// Together with [`ApiDescription::openapi`], this allows a program (or
+    /// a test) to produce the document at build or CI time without starting
+    /// a server.  The file is only written once the definition has been
+    /// built, so a failure leaves any existing file alone.
+    pub fn write_file<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let openapi = self.build::<serde_json::Error>()?;
+        let contents = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml" | "yml") => {
+                serde_yaml::to_string(&openapi).map_err(|error| {
+                    std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+                })?
+            }
+            _ => serde_json::to_string_pretty(&openapi)?,
+        };
+        std::fs::write(path, contents)
     }
 }
 
@@ -1091,14 +2814,14 @@ pub enum EndpointTagPolicy {
 }
 
 /// Details for a named tag
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct TagDetails {
     pub description: Option<String>,
     pub external_docs: Option<TagExternalDocs>,
 }
 
 /// External docs description
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct TagExternalDocs {
     pub description: Option<String>,
     pub url: String,
@@ -1120,11 +2843,14 @@ mod test {
     use crate::handler::RequestContext;
     use crate::ApiDescription;
     use crate::ApiEndpoint;
+    use crate::ApiProblemKind;
     use crate::EndpointTagPolicy;
+    use crate::ParameterCase;
     use crate::Path;
     use crate::Query;
     use crate::TagConfig;
     use crate::TagDetails;
+    use crate::TypedBody;
     use crate::CONTENT_TYPE_JSON;
     use http::Method;
     use hyper::Body;
@@ -1341,7 +3067,7 @@ mod test {
         .unwrap();
         api.register(
             ApiEndpoint::new(
-                "test_badpath_handler".to_string(),
+                "test_badpath_handler_yy".to_string(),
                 test_badpath_handler,
                 Method::GET,
                 CONTENT_TYPE_JSON,
@@ -1370,4 +3096,852 @@ mod test {
                 .collect::<HashSet<_>>()
         )
     }
+
+    #[test]
+    fn test_parameter_case_names() {
+        let cases = [
+            (ParameterCase::Snake, "page_token"),
+            (ParameterCase::Camel, "pageToken"),
+            (ParameterCase::Pascal, "PageToken"),
+            (ParameterCase::Kebab, "page-token"),
+        ];
+        for (case, wire) in cases {
+            assert_eq!(case.to_wire("page_token"), wire);
+            assert_eq!(case.from_wire(wire), "page_token");
+            assert_eq!(case.from_wire(&case.to_wire("limit")), "limit");
+        }
+
+        // Only the spelling in the selected convention is accepted.
+        let query = "pageToken=a&page_token=b&page-token=c&limit=1";
+        assert_eq!(
+            ParameterCase::Camel.query_from_wire(query),
+            "page_token=a&limit=1"
+        );
+        assert_eq!(
+            ParameterCase::Kebab.query_from_wire(query),
+            "page_token=c&limit=1"
+        );
+        assert_eq!(ParameterCase::Snake.query_from_wire(query), query);
+
+        // Some names don't survive the translation.
+        assert_eq!(ParameterCase::Camel.to_wire("page_2"), "page2");
+        assert_eq!(ParameterCase::Camel.from_wire("page2"), "page2");
+    }
+
+    #[test]
+    fn test_parameter_case_spec() {
+        #[allow(dead_code)]
+        #[derive(Deserialize, JsonSchema)]
+        struct CasePath {
+            project_name: String,
+        }
+
+        #[allow(dead_code)]
+        #[derive(Deserialize, JsonSchema)]
+        struct CaseQuery {
+            page_size: Option<u32>,
+        }
+
+        async fn test_case_handler(
+            _: RequestContext<()>,
+            _: Path<CasePath>,
+            _: Query<CaseQuery>,
+        ) -> Result<Response<Body>, HttpError> {
+            unimplemented!();
+        }
+
+        // The path template must use the wire name of the parameter.
+        let mut api =
+            ApiDescription::new().parameter_case(ParameterCase::Camel);
+        let error = api
+            .register(ApiEndpoint::new(
+                "test_case_handler".to_string(),
+                test_case_handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                "/projects/{project_name}",
+            ))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            "path parameters are not consumed (project_name) and specified \
+             parameters do not appear in the path (projectName)"
+        );
+
+        api.register(ApiEndpoint::new(
+            "test_case_handler".to_string(),
+            test_case_handler,
+            Method::GET,
+            CONTENT_TYPE_JSON,
+            "/projects/{projectName}",
+        ))
+        .unwrap();
+
+        // An endpoint may override the API-wide convention.
+        api.register(
+            ApiEndpoint::new(
+                "test_case_handler_kebab".to_string(),
+                test_case_handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                "/kebab/{project-name}",
+            )
+            .parameter_case(ParameterCase::Kebab),
+        )
+        .unwrap();
+
+        let mut out = Vec::new();
+        api.openapi("", "").write(&mut out).unwrap();
+        let out = from_utf8(&out).unwrap();
+        let spec = serde_json::from_str::<OpenAPI>(out).unwrap();
+
+        let names = |path: &str| {
+            spec.paths.paths[path]
+                .as_item()
+                .unwrap()
+                .get
+                .as_ref()
+                .unwrap()
+                .parameters
+                .iter()
+                .map(|p| p.as_item().unwrap().parameter_data_ref().name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names("/projects/{projectName}"),
+            ["projectName", "pageSize"]
+        );
+        assert_eq!(
+            names("/kebab/{project-name}"),
+            ["project-name", "page-size"]
+        );
+    }
+
+    #[test]
+    fn test_parameter_case_lossy() {
+        #[allow(dead_code)]
+        #[derive(Deserialize, JsonSchema)]
+        struct LossyQuery {
+            page_2: Option<u32>,
+        }
+
+        async fn test_lossy_handler(
+            _: RequestContext<()>,
+            _: Query<LossyQuery>,
+        ) -> Result<Response<Body>, HttpError> {
+            unimplemented!();
+        }
+
+        let endpoint = || {
+            ApiEndpoint::new(
+                "test_lossy_handler".to_string(),
+                test_lossy_handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                "/lossy",
+            )
+        };
+        let mut api =
+            ApiDescription::new().parameter_case(ParameterCase::Camel);
+        let error = api.register(endpoint()).unwrap_err();
+        assert_eq!(
+            error,
+            "endpoint \"test_lossy_handler\": parameter \"page_2\" cannot be \
+             named in Camel case (\"page2\" would be read as \"page2\")"
+        );
+
+        // Kebab case preserves the name.
+        api.register(endpoint().parameter_case(ParameterCase::Kebab)).unwrap();
+    }
+
+    #[test]
+    fn test_body_any_method() {
+        #[allow(dead_code)]
+        #[derive(Deserialize, JsonSchema)]
+        struct SearchBody {
+            term: String,
+        }
+
+        async fn test_body_handler(
+            _: RequestContext<()>,
+            _: TypedBody<SearchBody>,
+        ) -> Result<Response<Body>, HttpError> {
+            unimplemented!();
+        }
+
+        let mut api = ApiDescription::new();
+        for method in [Method::GET, Method::DELETE] {
+            api.register(ApiEndpoint::new(
+                format!("test_body_handler_{}", method.as_str().to_lowercase()),
+                test_body_handler,
+                method,
+                CONTENT_TYPE_JSON,
+                "/search",
+            ))
+            .unwrap();
+        }
+
+        let mut out = Vec::new();
+        api.openapi("", "").write(&mut out).unwrap();
+        let out = from_utf8(&out).unwrap();
+        let spec = serde_json::from_str::<OpenAPI>(out).unwrap();
+
+        let item = spec.paths.paths["/search"].as_item().unwrap();
+        assert!(item.get.as_ref().unwrap().request_body.is_some());
+        assert!(item.delete.as_ref().unwrap().request_body.is_some());
+    }
+
+    #[test]
+    fn test_untyped_body_content_type() {
+        async fn test_upload_handler(
+            _: RequestContext<()>,
+            _: crate::StreamingBody,
+        ) -> Result<Response<Body>, HttpError> {
+            unimplemented!();
+        }
+
+        let mut api = ApiDescription::new();
+        for (path, content_type) in [
+            ("/raw", CONTENT_TYPE_JSON),
+            ("/upload", crate::CONTENT_TYPE_MULTIPART_FORM_DATA),
+        ] {
+            api.register(ApiEndpoint::new(
+                path.to_string(),
+                test_upload_handler,
+                Method::POST,
+                content_type,
+                path,
+            ))
+            .unwrap();
+        }
+
+        let spec = api.openapi("", "").json().unwrap();
+        let content = |path: &str| {
+            spec["paths"][path]["post"]["requestBody"]["content"].clone()
+        };
+        // Without a declared content type, the body is arbitrary bytes.
+        assert_eq!(
+            content("/raw"),
+            serde_json::json!({
+                "application/octet-stream": {
+                    "schema": { "type": "string", "format": "binary" }
+                }
+            })
+        );
+        assert_eq!(
+            content("/upload"),
+            serde_json::json!({
+                "multipart/form-data": { "schema": { "type": "object" } }
+            })
+        );
+    }
+
+    #[test]
+    fn test_unsupported_encoding() {
+        let mut api = ApiDescription::new();
+        let ret = api.register(
+            ApiEndpoint::new(
+                "test_badpath_handler".to_string(),
+                test_badpath_handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                "/{a}/{b}",
+            )
+            .encoding(CONTENT_TYPE_JSON)
+            .encoding("text/plain"),
+        );
+        assert_eq!(
+            ret,
+            Err("endpoint \"test_badpath_handler\": unsupported encoding: \
+                 text/plain"
+                .to_string())
+        );
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut api = ApiDescription::new();
+        let error = api
+            .register(
+                ApiEndpoint::new(
+                    "test_badpath_handler".to_string(),
+                    test_badpath_handler,
+                    Method::GET,
+                    CONTENT_TYPE_JSON,
+                    "/new/{a}/{b}",
+                )
+                .alias("/old/{a}"),
+            )
+            .unwrap_err();
+        assert_eq!(error, "specified parameters do not appear in the path (b)");
+
+        api.register(
+            ApiEndpoint::new(
+                "test_badpath_handler".to_string(),
+                test_badpath_handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                "/new/{a}/{b}",
+            )
+            .alias("/old/{a}/{b}"),
+        )
+        .unwrap();
+
+        let mut out = Vec::new();
+        api.openapi("", "").write(&mut out).unwrap();
+        let out = from_utf8(&out).unwrap();
+        let spec = serde_json::from_str::<OpenAPI>(out).unwrap();
+
+        let operation = |path: &str| {
+            spec.paths.paths[path].as_item().unwrap().get.clone().unwrap()
+        };
+        let canonical = operation("/new/{a}/{b}");
+        assert_eq!(
+            canonical.operation_id.as_deref(),
+            Some("test_badpath_handler")
+        );
+        assert!(!canonical.deprecated);
+        let alias = operation("/old/{a}/{b}");
+        assert_eq!(
+            alias.operation_id.as_deref(),
+            Some("test_badpath_handler_alias1")
+        );
+        assert!(alias.deprecated);
+    }
+
+    #[test]
+    fn test_merge() {
+        let tag_config = |names: &[&str]| TagConfig {
+            allow_other_tags: false,
+            endpoint_tag_policy: EndpointTagPolicy::ExactlyOne,
+            tag_definitions: names
+                .iter()
+                .map(|name| (name.to_string(), TagDetails::default()))
+                .collect(),
+        };
+        let endpoint = |operation_id: &str, path: &str, tag: &str| {
+            ApiEndpoint::new(
+                operation_id.to_string(),
+                test_badpath_handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                path,
+            )
+            .tag(tag)
+        };
+
+        let mut api = ApiDescription::new().tag_config(tag_config(&["root"]));
+        api.register(endpoint("root_op", "/xx/{a}/{b}", "root")).unwrap();
+
+        // The tags that the merged description defines satisfy our policy.
+        let mut projects =
+            ApiDescription::new().tag_config(tag_config(&["projects"]));
+        projects
+            .register(endpoint("projects_op", "/{a}/{b}", "projects"))
+            .unwrap();
+        api.merge("/v1", projects).unwrap();
+
+        let mut conflicting = ApiDescription::new();
+        conflicting.register(endpoint("other_op", "/{a}/{b}", "root")).unwrap();
+        let error = api.merge("/v1", conflicting).unwrap_err();
+        assert_eq!(
+            error,
+            "URI path \"/v1/{a}/{b}\": attempted to create duplicate route \
+             for method \"GET\""
+        );
+
+        let mut duplicate_id = ApiDescription::new();
+        duplicate_id.register(endpoint("root_op", "/{a}/{b}", "root")).unwrap();
+        let error = api.merge("/v2", duplicate_id).unwrap_err();
+        assert_eq!(
+            error,
+            "operation ID \"root_op\" is already used by GET /xx/{a}/{b}"
+        );
+
+        let error = api.merge("/v2/", ApiDescription::new()).unwrap_err();
+        assert!(error.starts_with("path prefix \"/v2/\" must be empty"));
+
+        let mut out = Vec::new();
+        api.openapi("", "").write(&mut out).unwrap();
+        let out = from_utf8(&out).unwrap();
+        let spec = serde_json::from_str::<OpenAPI>(out).unwrap();
+
+        let operation = |path: &str| {
+            spec.paths.paths[path].as_item().unwrap().get.clone().unwrap()
+        };
+        assert_eq!(
+            operation("/v1/{a}/{b}").operation_id.as_deref(),
+            Some("projects_op")
+        );
+        assert_eq!(
+            operation("/xx/{a}/{b}").operation_id.as_deref(),
+            Some("root_op")
+        );
+        let tags = spec
+            .tags
+            .iter()
+            .map(|tag| tag.name.as_str())
+            .collect::<HashSet<_>>();
+        assert_eq!(tags, vec!["projects", "root"].into_iter().collect());
+    }
+
+    #[test]
+    fn test_extensions() {
+        let endpoint = |name: &str| {
+            ApiEndpoint::new(
+                "test_badpath_handler".to_string(),
+                test_badpath_handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                "/{a}/{b}",
+            )
+            .extension(name, serde_json::json!(true))
+        };
+
+        let mut api = ApiDescription::new();
+        let error = api.register(endpoint("internal")).unwrap_err();
+        assert_eq!(error, "extension \"internal\" must begin with \"x-\"");
+        let error = api.register(endpoint("x-dropshot-internal")).unwrap_err();
+        assert_eq!(
+            error,
+            "extension \"x-dropshot-internal\" uses the reserved prefix \
+             \"x-dropshot-\""
+        );
+        api.register(endpoint("x-internal")).unwrap();
+
+        let spec = api
+            .openapi("", "")
+            .extension("x-logo", serde_json::json!({ "url": "logo.png" }))
+            .json()
+            .unwrap();
+        assert_eq!(spec["x-logo"], serde_json::json!({ "url": "logo.png" }));
+        assert_eq!(
+            spec["paths"]["/{a}/{b}"]["get"]["x-internal"],
+            serde_json::json!(true)
+        );
+
+        let error = api
+            .openapi("", "")
+            .extension("logo", serde_json::json!(null))
+            .json()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "extension \"logo\" must begin with \"x-\""
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let endpoint = |operation_id: &str, path: &str| {
+            ApiEndpoint::new(
+                operation_id.to_string(),
+                test_badpath_handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                path,
+            )
+        };
+
+        let mut api = ApiDescription::new();
+        api.register(endpoint("documented", "/xx/{a}/{b}").summary("xx"))
+            .unwrap();
+        api.register(endpoint("hidden", "/zz/{a}/{b}").visible(false)).unwrap();
+        assert_eq!(api.validate(), Ok(()));
+
+        api.register(endpoint("undocumented", "/yy/{a}/{b}")).unwrap();
+        let problems = api.validate().unwrap_err();
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].kind, ApiProblemKind::MissingDescription);
+        assert_eq!(
+            problems[0].to_string(),
+            "GET /yy/{a}/{b}: operation has no summary or description"
+        );
+    }
+
+    #[test]
+    fn test_operation_id_unique() {
+        let mut api = ApiDescription::new();
+        api.register(
+            ApiEndpoint::new(
+                "test_badpath_handler".to_string(),
+                test_badpath_handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                "/new/{a}/{b}",
+            )
+            .alias("/old/{a}/{b}"),
+        )
+        .unwrap();
+
+        let error = api
+            .register(ApiEndpoint::new(
+                "test_badpath_handler".to_string(),
+                test_badpath_handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                "/other/{a}/{b}",
+            ))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            "operation ID \"test_badpath_handler\" is already used by GET \
+             /new/{a}/{b}"
+        );
+        let error = api
+            .register(ApiEndpoint::new(
+                "test_badpath_handler_alias1".to_string(),
+                test_badpath_handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                "/other/{a}/{b}",
+            ))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            "operation ID \"test_badpath_handler_alias1\" is already used by \
+             GET /old/{a}/{b}"
+        );
+
+        // Endpoints that aren't in the OpenAPI document may share IDs.
+        api.register(
+            ApiEndpoint::new(
+                "test_badpath_handler".to_string(),
+                test_badpath_handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                "/hidden/{a}/{b}",
+            )
+            .visible(false),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_max_concurrency_zero() {
+        let mut api = ApiDescription::new();
+        let error = api
+            .register(
+                ApiEndpoint::new(
+                    "test_badpath_handler".to_string(),
+                    test_badpath_handler,
+                    Method::GET,
+                    CONTENT_TYPE_JSON,
+                    "/{a}/{b}",
+                )
+                .max_concurrency(0),
+            )
+            .unwrap_err();
+        assert_eq!(error, "max_concurrency must be greater than 0");
+    }
+
+    #[test]
+    fn test_security_requirements() {
+        let api_key = openapiv3::SecurityScheme::APIKey {
+            location: openapiv3::APIKeyLocation::Header,
+            name: String::from("x-api-key"),
+            description: None,
+            extensions: Default::default(),
+        };
+        let basic = openapiv3::SecurityScheme::HTTP {
+            scheme: String::from("basic"),
+            bearer_format: None,
+            description: None,
+            extensions: Default::default(),
+        };
+        let mut api = ApiDescription::new()
+            .security_scheme("api_key", api_key.clone())
+            .security_scheme("basic", basic.clone());
+
+        let error = api
+            .register(
+                ApiEndpoint::new(
+                    "test_badpath_handler".to_string(),
+                    test_badpath_handler,
+                    Method::GET,
+                    CONTENT_TYPE_JSON,
+                    "/{a}/{b}",
+                )
+                .security_requirement("oauth", vec!["read".to_string()]),
+            )
+            .unwrap_err();
+        assert_eq!(error, "security scheme \"oauth\" has not been declared");
+
+        api.register(
+            ApiEndpoint::new(
+                "test_badpath_handler".to_string(),
+                test_badpath_handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                "/{a}/{b}",
+            )
+            .security_requirement("api_key", vec![]),
+        )
+        .unwrap();
+
+        let mut out = Vec::new();
+        api.openapi("", "").write(&mut out).unwrap();
+        let out = from_utf8(&out).unwrap();
+        let spec = serde_json::from_str::<OpenAPI>(out).unwrap();
+
+        // Declared schemes are documented even if no endpoint requires them.
+        let schemes = &spec.components.unwrap().security_schemes;
+        assert_eq!(schemes.len(), 2);
+        assert_eq!(schemes["api_key"].as_item(), Some(&api_key));
+        assert_eq!(schemes["basic"].as_item(), Some(&basic));
+
+        let operation = spec.paths.paths["/{a}/{b}"]
+            .as_item()
+            .unwrap()
+            .get
+            .as_ref()
+            .unwrap();
+        let security = operation.security.as_ref().unwrap();
+        assert_eq!(security.len(), 1);
+        assert_eq!(security[0].len(), 1);
+        assert_eq!(security[0]["api_key"], Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_examples() {
+        #[allow(dead_code)]
+        #[derive(Deserialize, JsonSchema)]
+        struct ExampleQuery {
+            name: Option<String>,
+        }
+
+        #[allow(dead_code)]
+        #[derive(Deserialize, JsonSchema)]
+        struct ExampleBody {
+            size: u32,
+        }
+
+        async fn test_example_handler(
+            _: RequestContext<()>,
+            _: Query<ExampleQuery>,
+            _: TypedBody<ExampleBody>,
+        ) -> Result<Response<Body>, HttpError> {
+            unimplemented!();
+        }
+
+        let mut api = ApiDescription::new();
+        let error = api
+            .register(
+                ApiEndpoint::new(
+                    "test_badpath_handler".to_string(),
+                    test_badpath_handler,
+                    Method::GET,
+                    CONTENT_TYPE_JSON,
+                    "/{a}/{b}",
+                )
+                .request_example("small", &serde_json::json!({ "size": 1 })),
+            )
+            .unwrap_err();
+        assert_eq!(
+            error,
+            "request examples given for operation \"test_badpath_handler\", \
+             which has no request body"
+        );
+        let error = api
+            .register(
+                ApiEndpoint::new(
+                    "test_badpath_handler".to_string(),
+                    test_badpath_handler,
+                    Method::GET,
+                    CONTENT_TYPE_JSON,
+                    "/{a}/{b}",
+                )
+                .parameter_example("c", &"abc"),
+            )
+            .unwrap_err();
+        assert_eq!(
+            error,
+            "example given for parameter \"c\", which operation \
+             \"test_badpath_handler\" does not have"
+        );
+
+        api.register(
+            ApiEndpoint::new(
+                "test_example_handler".to_string(),
+                test_example_handler,
+                Method::POST,
+                CONTENT_TYPE_JSON,
+                "/examples",
+            )
+            .parameter_example("name", &"abc")
+            .request_example("small", &serde_json::json!({ "size": 1 }))
+            .request_example("large", &serde_json::json!({ "size": 1000 }))
+            .response_example("done", &"ok"),
+        )
+        .unwrap();
+
+        let mut out = Vec::new();
+        api.openapi("", "").write(&mut out).unwrap();
+        let out = from_utf8(&out).unwrap();
+        let spec = serde_json::from_str::<OpenAPI>(out).unwrap();
+        let operation = spec.paths.paths["/examples"]
+            .as_item()
+            .unwrap()
+            .post
+            .as_ref()
+            .unwrap();
+
+        let parameter = operation.parameters[0].as_item().unwrap();
+        assert_eq!(
+            parameter.parameter_data_ref().example,
+            Some(serde_json::json!("abc"))
+        );
+
+        let body = operation.request_body.as_ref().unwrap().as_item().unwrap();
+        let examples = &body.content[CONTENT_TYPE_JSON].examples;
+        assert_eq!(examples.keys().collect::<Vec<_>>(), ["large", "small"]);
+        assert_eq!(
+            examples["small"].as_item().unwrap().value,
+            Some(serde_json::json!({ "size": 1 }))
+        );
+
+        let response = operation.responses.default.as_ref().unwrap();
+        let examples = &response.as_item().unwrap().content["*/*"].examples;
+        assert_eq!(
+            examples["done"].as_item().unwrap().value,
+            Some(serde_json::json!("ok"))
+        );
+    }
+
+    #[test]
+    fn test_deprecation_headers_spec() {
+        let since = "2023-06-01T00:00:00Z".parse().unwrap();
+        let sunset = "2024-01-01T00:00:00Z".parse().unwrap();
+        let mut api = ApiDescription::new();
+        api.register(
+            ApiEndpoint::new(
+                "test_badpath_handler".to_string(),
+                test_badpath_handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                "/{a}/{b}",
+            )
+            .deprecated_since(since)
+            .sunset(sunset)
+            .sunset(sunset),
+        )
+        .unwrap();
+
+        let mut out = Vec::new();
+        api.openapi("", "").write(&mut out).unwrap();
+        let out = from_utf8(&out).unwrap();
+        let spec = serde_json::from_str::<OpenAPI>(out).unwrap();
+        let operation = spec.paths.paths["/{a}/{b}"]
+            .as_item()
+            .unwrap()
+            .get
+            .as_ref()
+            .unwrap();
+        assert!(operation.deprecated);
+        let response = operation.responses.default.as_ref().unwrap();
+        let headers = &response.as_item().unwrap().headers;
+        assert_eq!(
+            headers.keys().collect::<Vec<_>>(),
+            ["Deprecation", "Sunset"]
+        );
+    }
+
+    #[test]
+    fn test_schema_name_conflict() {
+        mod a {
+            #[derive(serde::Deserialize, schemars::JsonSchema)]
+            pub enum Color {
+                Red,
+            }
+        }
+
+        mod b {
+            #[derive(serde::Deserialize, schemars::JsonSchema)]
+            pub enum Color {
+                Blue,
+            }
+        }
+
+        #[allow(dead_code)]
+        #[derive(Deserialize, JsonSchema)]
+        struct ColorQuery {
+            color: a::Color,
+        }
+
+        async fn query_handler(
+            _: RequestContext<()>,
+            _: Query<ColorQuery>,
+        ) -> Result<Response<Body>, HttpError> {
+            unimplemented!();
+        }
+
+        async fn red_handler(
+            _: RequestContext<()>,
+            _: TypedBody<a::Color>,
+        ) -> Result<Response<Body>, HttpError> {
+            unimplemented!();
+        }
+
+        async fn blue_handler(
+            _: RequestContext<()>,
+            _: TypedBody<b::Color>,
+        ) -> Result<Response<Body>, HttpError> {
+            unimplemented!();
+        }
+
+        let query = || {
+            ApiEndpoint::new(
+                "query_handler".to_string(),
+                query_handler,
+                Method::GET,
+                CONTENT_TYPE_JSON,
+                "/query",
+            )
+        };
+        let red = || {
+            ApiEndpoint::new(
+                "red_handler".to_string(),
+                red_handler,
+                Method::POST,
+                CONTENT_TYPE_JSON,
+                "/red",
+            )
+        };
+        let blue = || {
+            ApiEndpoint::new(
+                "blue_handler".to_string(),
+                blue_handler,
+                Method::POST,
+                CONTENT_TYPE_JSON,
+                "/blue",
+            )
+        };
+        let expected = "different schemas have the same name (Color); give \
+                        the types distinct names, e.g. with \
+                        #[schemars(rename = \"...\")]";
+
+        // The same type may be used in any number of places.
+        let mut api = ApiDescription::new();
+        api.register(query()).unwrap();
+        api.register(red()).unwrap();
+        let spec = api.openapi("", "").json().unwrap();
+        assert_eq!(
+            spec["components"]["schemas"]["Color"]["enum"],
+            serde_json::json!(["Red"])
+        );
+
+        // A type used as a parameter conflicts with one used as a body.
+        let mut api = ApiDescription::new();
+        api.register(query()).unwrap();
+        api.register(blue()).unwrap();
+        let error = api.openapi("", "").json().unwrap_err();
+        assert_eq!(error.to_string(), expected);
+
+        // So do two types used as bodies.
+        let mut api = ApiDescription::new();
+        api.register(red()).unwrap();
+        api.register(blue()).unwrap();
+        let error = api.openapi("", "").json().unwrap_err();
+        assert_eq!(error.to_string(), expected);
+    }
 }
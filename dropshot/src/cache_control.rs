@@ -0,0 +1,219 @@
+// Copyright 2023 Oxide Computer Company
+//! Cache-Control directives for responses
+//!
+//! [`CacheControl`] builds the value of a `Cache-Control` response header
+//! (RFC 9111 section 5.2, with the extensions of RFC 5861 and RFC 8246) from
+//! individual directives.  It can be attached to any response with
+//! [`HttpResponseCached`](crate::HttpResponseCached), or used as a field of
+//! the named headers of an [`HttpResponseHeaders`](crate::HttpResponseHeaders).
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::InstanceType;
+use schemars::schema::Schema;
+use schemars::schema::SchemaObject;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde::Serializer;
+use std::fmt;
+use std::time::Duration;
+
+/// Value of a `Cache-Control` response header
+///
+/// ```
+/// use dropshot::CacheControl;
+/// use std::time::Duration;
+///
+/// let cache_control = CacheControl::new()
+///     .public()
+///     .max_age(Duration::from_secs(60))
+///     .stale_while_revalidate(Duration::from_secs(30));
+/// assert_eq!(
+///     cache_control.to_string(),
+///     "public, max-age=60, stale-while-revalidate=30",
+/// );
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CacheControl {
+    public: bool,
+    private: bool,
+    no_cache: bool,
+    no_store: bool,
+    no_transform: bool,
+    must_revalidate: bool,
+    proxy_revalidate: bool,
+    immutable: bool,
+    max_age: Option<u64>,
+    s_maxage: Option<u64>,
+    stale_while_revalidate: Option<u64>,
+    stale_if_error: Option<u64>,
+}
+
+impl CacheControl {
+    /// Returns a value with no directives, to which directives are added.
+    pub fn new() -> Self {
+        CacheControl::default()
+    }
+
+    /// Any cache may store the response, even one that would otherwise not
+    /// be allowed to (e.g., for a request with an `Authorization` header).
+    pub fn public(mut self) -> Self {
+        self.public = true;
+        self
+    }
+
+    /// Only the client's own cache may store the response, not a shared one.
+    pub fn private(mut self) -> Self {
+        self.private = true;
+        self
+    }
+
+    /// Caches must revalidate the response with the server before each use.
+    pub fn no_cache(mut self) -> Self {
+        self.no_cache = true;
+        self
+    }
+
+    /// Caches must not store the response at all.
+    pub fn no_store(mut self) -> Self {
+        self.no_store = true;
+        self
+    }
+
+    /// Intermediaries must not transform the response's content.
+    pub fn no_transform(mut self) -> Self {
+        self.no_transform = true;
+        self
+    }
+
+    /// Caches must not use the response once it's stale without first
+    /// revalidating it.
+    pub fn must_revalidate(mut self) -> Self {
+        self.must_revalidate = true;
+        self
+    }
+
+    /// Like `must_revalidate`, but only for shared caches.
+    pub fn proxy_revalidate(mut self) -> Self {
+        self.proxy_revalidate = true;
+        self
+    }
+
+    /// The response won't change while it's fresh, so clients needn't
+    /// revalidate it even when the user reloads.
+    pub fn immutable(mut self) -> Self {
+        self.immutable = true;
+        self
+    }
+
+    /// The response stays fresh for `age` after it's generated.
+    pub fn max_age(mut self, age: Duration) -> Self {
+        self.max_age = Some(age.as_secs());
+        self
+    }
+
+    /// Overrides `max_age` for shared caches.
+    pub fn s_maxage(mut self, age: Duration) -> Self {
+        self.s_maxage = Some(age.as_secs());
+        self
+    }
+
+    /// Caches may use the response for up to `age` after it becomes stale
+    /// while they revalidate it in the background.
+    pub fn stale_while_revalidate(mut self, age: Duration) -> Self {
+        self.stale_while_revalidate = Some(age.as_secs());
+        self
+    }
+
+    /// Caches may use the response for up to `age` after it becomes stale if
+    /// revalidating it fails with an error.
+    pub fn stale_if_error(mut self, age: Duration) -> Self {
+        self.stale_if_error = Some(age.as_secs());
+        self
+    }
+}
+
+impl fmt::Display for CacheControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags = [
+            (self.public, "public"),
+            (self.private, "private"),
+            (self.no_cache, "no-cache"),
+            (self.no_store, "no-store"),
+            (self.no_transform, "no-transform"),
+            (self.must_revalidate, "must-revalidate"),
+            (self.proxy_revalidate, "proxy-revalidate"),
+            (self.immutable, "immutable"),
+        ];
+        let ages = [
+            (self.max_age, "max-age"),
+            (self.s_maxage, "s-maxage"),
+            (self.stale_while_revalidate, "stale-while-revalidate"),
+            (self.stale_if_error, "stale-if-error"),
+        ];
+
+        let directives = flags
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, name)| name.to_string())
+            .chain(ages.iter().filter_map(|(age, name)| {
+                age.map(|seconds| format!("{}={}", name, seconds))
+            }))
+            .collect::<Vec<_>>();
+        write!(f, "{}", directives.join(", "))
+    }
+}
+
+impl Serialize for CacheControl {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl JsonSchema for CacheControl {
+    fn schema_name() -> String {
+        String::from("CacheControl")
+    }
+
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CacheControl;
+    use std::time::Duration;
+
+    #[test]
+    fn test_cache_control() {
+        assert_eq!(CacheControl::new().to_string(), "");
+        assert_eq!(CacheControl::new().no_store().to_string(), "no-store");
+        assert_eq!(
+            CacheControl::new()
+                .stale_if_error(Duration::from_secs(600))
+                .max_age(Duration::from_millis(1500))
+                .immutable()
+                .private()
+                .to_string(),
+            "private, immutable, max-age=1, stale-if-error=600"
+        );
+        assert_eq!(
+            serde_json::to_string(
+                &CacheControl::new().public().s_maxage(Duration::ZERO)
+            )
+            .unwrap(),
+            "\"public, s-maxage=0\""
+        );
+    }
+}
@@ -0,0 +1,309 @@
+// Copyright 2023 Oxide Computer Company
+//! Checksums of request and response bodies
+//!
+//! Checksums are exchanged in the `Content-Digest` field defined by RFC 9530.
+//! Its value is a dictionary mapping algorithm names to base64-encoded digests,
+//! as in `sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:`.  The checksum
+//! of a streaming body is only known once the whole body has been produced, so
+//! the field is normally sent as a trailer.  Clients that cannot send trailers
+//! (including any HTTP/1.1 client, since hyper does not yet support HTTP/1.1
+//! trailers) may send it as a header instead.
+
+use crate::error::HttpError;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use http::HeaderMap;
+use http::HeaderValue;
+use hyper::body::HttpBody;
+use hyper::Body;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// Name of the field carrying body checksums
+pub const HEADER_CONTENT_DIGEST: &str = "content-digest";
+
+/// Algorithm used to compute a body checksum
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ChecksumAlgorithm {
+    #[serde(rename = "sha-256")]
+    Sha256,
+    #[serde(rename = "sha-512")]
+    Sha512,
+}
+
+impl ChecksumAlgorithm {
+    /// Returns the name of the algorithm as it appears in `Content-Digest`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "sha-256",
+            ChecksumAlgorithm::Sha512 => "sha-512",
+        }
+    }
+
+    pub(crate) fn checksummer(&self) -> Checksummer {
+        match self {
+            ChecksumAlgorithm::Sha256 => {
+                Checksummer::Sha256(sha2::Sha256::new())
+            }
+            ChecksumAlgorithm::Sha512 => {
+                Checksummer::Sha512(sha2::Sha512::new())
+            }
+        }
+    }
+}
+
+/// Incrementally computes the checksum of a body.
+pub(crate) enum Checksummer {
+    Sha256(sha2::Sha256),
+    Sha512(sha2::Sha512),
+}
+
+impl Checksummer {
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        match self {
+            Checksummer::Sha256(hasher) => hasher.update(data),
+            Checksummer::Sha512(hasher) => hasher.update(data),
+        }
+    }
+
+    pub(crate) fn finish(self) -> Vec<u8> {
+        match self {
+            Checksummer::Sha256(hasher) => hasher.finalize().to_vec(),
+            Checksummer::Sha512(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+/// Returns the `Content-Digest` value reporting `digest`.
+pub(crate) fn content_digest_value(
+    algorithm: ChecksumAlgorithm,
+    digest: &[u8],
+) -> HeaderValue {
+    let value = format!("{}=:{}:", algorithm.name(), STANDARD.encode(digest));
+    HeaderValue::from_str(&value).unwrap()
+}
+
+/// Returns the digest for `algorithm` reported in a `Content-Digest` value, if
+/// there is one.  Digests for other algorithms are ignored.
+pub(crate) fn content_digest_find(
+    value: &HeaderValue,
+    algorithm: ChecksumAlgorithm,
+) -> Result<Option<Vec<u8>>, HttpError> {
+    let bad_value = || {
+        HttpError::for_bad_request(
+            None,
+            format!("invalid {} field", HEADER_CONTENT_DIGEST),
+        )
+    };
+    let value = value.to_str().map_err(|_| bad_value())?;
+    for member in value.split(',') {
+        let mut parts = member.trim().splitn(2, '=');
+        let name = parts.next().unwrap_or("");
+        if !name.eq_ignore_ascii_case(algorithm.name()) {
+            continue;
+        }
+        let encoded = parts
+            .next()
+            .and_then(|v| v.strip_prefix(':'))
+            .and_then(|v| v.strip_suffix(':'))
+            .ok_or_else(bad_value)?;
+        return STANDARD.decode(encoded).map(Some).map_err(|_| bad_value());
+    }
+    Ok(None)
+}
+
+/// Checks `actual` against the digest reported in `field`.
+pub(crate) fn content_digest_verify(
+    field: Option<&HeaderValue>,
+    algorithm: ChecksumAlgorithm,
+    actual: &[u8],
+) -> Result<(), HttpError> {
+    let expected = match field {
+        Some(value) => content_digest_find(value, algorithm)?,
+        None => None,
+    };
+    match expected {
+        None => Err(HttpError::for_bad_request(
+            None,
+            format!(
+                "request has no {} checksum in {}",
+                algorithm.name(),
+                HEADER_CONTENT_DIGEST
+            ),
+        )),
+        Some(expected) if expected != actual => {
+            Err(HttpError::for_bad_request(
+                None,
+                format!(
+                    "request body does not match its {} checksum",
+                    algorithm.name()
+                ),
+            ))
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+/// Holds the trailers of a request body once it has been read.
+///
+/// [`StreamingBody::into_stream`](crate::StreamingBody::into_stream) consumes
+/// the body, so it stashes the trailers here for the checksum to be verified
+/// against once the stream ends.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RequestTrailers(Arc<Mutex<Option<HeaderMap>>>);
+
+impl RequestTrailers {
+    pub(crate) fn set(&self, trailers: HeaderMap) {
+        *self.0.lock().unwrap() = Some(trailers);
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<HeaderValue> {
+        self.0
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|trailers| trailers.get(name))
+            .cloned()
+    }
+}
+
+/// Returns a body with the contents of `body`, followed by a `Content-Digest`
+/// trailer reporting its checksum.
+pub(crate) fn body_with_checksum_trailer(
+    mut body: Body,
+    algorithm: ChecksumAlgorithm,
+) -> Body {
+    let (mut sender, checksummed_body) = Body::channel();
+    tokio::spawn(async move {
+        let mut checksummer = algorithm.checksummer();
+        while let Some(chunk) = body.data().await {
+            match chunk {
+                Ok(chunk) => {
+                    checksummer.update(&chunk);
+                    if sender.send_data(chunk).await.is_err() {
+                        // The client has gone away.
+                        return;
+                    }
+                }
+                Err(_) => {
+                    sender.abort();
+                    return;
+                }
+            }
+        }
+
+        let mut trailers = HeaderMap::new();
+        trailers.insert(
+            HEADER_CONTENT_DIGEST,
+            content_digest_value(algorithm, &checksummer.finish()),
+        );
+        let _ = sender.send_trailers(trailers).await;
+    });
+    checksummed_body
+}
+
+#[cfg(test)]
+mod test {
+    use super::body_with_checksum_trailer;
+    use super::content_digest_find;
+    use super::content_digest_value;
+    use super::content_digest_verify;
+    use super::ChecksumAlgorithm;
+    use super::HEADER_CONTENT_DIGEST;
+    use http::HeaderValue;
+    use hyper::body::HttpBody;
+    use hyper::Body;
+
+    // Digests of "hello world"
+    const SHA256_HELLO: &str = "uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=";
+    const SHA512_HELLO: &str = "MJ7MSJwS1utMxA9QyQLytNDtd+5RGnx6m808qG1M2G+Y\
+                                ndNbxf9JlnDaNCVbRbDP2DDoH2Bdz33FVC6TrpzXbw==";
+
+    fn checksum(algorithm: ChecksumAlgorithm, data: &[u8]) -> Vec<u8> {
+        let mut checksummer = algorithm.checksummer();
+        checksummer.update(&data[..4]);
+        checksummer.update(&data[4..]);
+        checksummer.finish()
+    }
+
+    #[test]
+    fn test_content_digest_value() {
+        let digest = checksum(ChecksumAlgorithm::Sha256, b"hello world");
+        assert_eq!(
+            content_digest_value(ChecksumAlgorithm::Sha256, &digest),
+            format!("sha-256=:{}:", SHA256_HELLO)
+        );
+        let digest = checksum(ChecksumAlgorithm::Sha512, b"hello world");
+        assert_eq!(
+            content_digest_value(ChecksumAlgorithm::Sha512, &digest),
+            format!("sha-512=:{}:", SHA512_HELLO)
+        );
+    }
+
+    #[test]
+    fn test_content_digest_find() {
+        let sha256 = checksum(ChecksumAlgorithm::Sha256, b"hello world");
+        let value = HeaderValue::from_str(&format!(
+            "md5=:XrY7u+Ae7tCTyyK7j1rNww==:, sha-256=:{}:",
+            SHA256_HELLO
+        ))
+        .unwrap();
+        assert_eq!(
+            content_digest_find(&value, ChecksumAlgorithm::Sha256).unwrap(),
+            Some(sha256.clone())
+        );
+        assert_eq!(
+            content_digest_find(&value, ChecksumAlgorithm::Sha512).unwrap(),
+            None
+        );
+        content_digest_verify(Some(&value), ChecksumAlgorithm::Sha256, &sha256)
+            .unwrap();
+
+        let error = content_digest_verify(
+            Some(&value),
+            ChecksumAlgorithm::Sha256,
+            &checksum(ChecksumAlgorithm::Sha256, b"goodbye world"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.internal_message,
+            "request body does not match its sha-256 checksum"
+        );
+        let error =
+            content_digest_verify(None, ChecksumAlgorithm::Sha256, &sha256)
+                .unwrap_err();
+        assert_eq!(
+            error.internal_message,
+            "request has no sha-256 checksum in content-digest"
+        );
+
+        let value = HeaderValue::from_static("sha-256=not-base64");
+        let error =
+            content_digest_find(&value, ChecksumAlgorithm::Sha256).unwrap_err();
+        assert_eq!(error.internal_message, "invalid content-digest field");
+    }
+
+    #[tokio::test]
+    async fn test_body_with_checksum_trailer() {
+        let chunks: Vec<Result<_, std::io::Error>> =
+            vec![Ok("hello"), Ok(" "), Ok("world")];
+        let body = Body::wrap_stream(futures::stream::iter(chunks));
+        let mut body =
+            body_with_checksum_trailer(body, ChecksumAlgorithm::Sha256);
+
+        let mut contents = Vec::new();
+        while let Some(chunk) = body.data().await {
+            contents.extend_from_slice(&chunk.unwrap());
+        }
+        assert_eq!(contents, b"hello world");
+
+        let trailers = body.trailers().await.unwrap().unwrap();
+        assert_eq!(
+            trailers.get(HEADER_CONTENT_DIGEST).unwrap(),
+            &format!("sha-256=:{}:", SHA256_HELLO)
+        );
+    }
+}
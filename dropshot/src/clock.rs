@@ -0,0 +1,40 @@
+// Copyright 2023 Oxide Computer Company
+//! Source of time for the server and its handlers
+//!
+//! Dropshot reads the time through a [`Clock`] rather than directly so that
+//! tests can substitute one they control (such as
+//! [`test_util::MockClock`](crate::test_util::MockClock)) and exercise
+//! time-dependent behavior deterministically.  Handlers should use the clock
+//! available from [`RequestContext::clock`](crate::RequestContext::clock) for
+//! the same reason.
+
+use chrono::DateTime;
+use chrono::Utc;
+use std::fmt::Debug;
+use std::time::Instant;
+
+/// Provides the current time
+///
+/// The clock used by a server is configured with
+/// [`ApiDescription::clock`](crate::ApiDescription::clock).
+pub trait Clock: Debug + Send + Sync + 'static {
+    /// Returns the current monotonic time, as used to measure durations.
+    fn now(&self) -> Instant;
+
+    /// Returns the current wall-clock time.
+    fn now_utc(&self) -> DateTime<Utc>;
+}
+
+/// [`Clock`] that reports the system's time
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn now_utc(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
@@ -1,6 +1,7 @@
 // Copyright 2020 Oxide Computer Company
 //! Configuration for Dropshot
 
+use ipnet::IpNet;
 use serde::Deserialize;
 use serde::Serialize;
 use std::net::SocketAddr;
@@ -50,11 +51,123 @@ use std::path::PathBuf;
 pub struct ConfigDropshot {
     /// IP address and TCP port to which to bind for accepting connections
     pub bind_address: SocketAddr,
+    /// further IP addresses and TCP ports on which the same server accepts
+    /// connections, e.g., to listen on both an IPv4 and an IPv6 address
+    pub additional_bind_addresses: Vec<SocketAddr>,
+    /// If present, the server accepts connections on the given Unix domain
+    /// socket instead of `bind_address`
+    pub unix_socket: Option<ConfigUnixSocket>,
     /// maximum allowed size of a request body, defaults to 1024
     pub request_body_max_bytes: usize,
+    /// If present, the number of milliseconds a request's handler may take
+    /// before the request is abandoned: the handler is cancelled and the
+    /// client gets a 503 ("Service Unavailable").  Endpoints may override this
+    /// with [`crate::ApiEndpoint::request_timeout`].
+    pub request_timeout_ms: Option<u64>,
 
     /// If present, enables TLS with the given configuration
     pub tls: Option<ConfigTls>,
+    /// If present along with `tls`, clients are asked for certificates, which
+    /// are verified with the given configuration
+    pub tls_client_auth: Option<ConfigTlsClientAuth>,
+
+    /// HTTP/2 settings, which by default accept HTTP/2 connections
+    pub http2: ConfigHttp2,
+
+    /// If present, enables adaptive load shedding with the given configuration
+    pub load_shedding: Option<ConfigLoadShedding>,
+
+    /// Base URL at which clients reach this server (e.g.,
+    /// "https://api.example.com/v1"), used to build absolute URLs in responses.
+    /// This is useful when the server sits behind a proxy.
+    pub external_url: Option<String>,
+    /// networks (e.g., "10.0.0.0/8") containing the proxies and load
+    /// balancers that front the server, whose Forwarded and X-Forwarded-For
+    /// headers are trusted to identify the client (see
+    /// [`RemoteAddr`](crate::RemoteAddr)), and whose Forwarded,
+    /// X-Forwarded-Proto, and X-Forwarded-Host headers are trusted when
+    /// building absolute URLs, defaults to empty
+    pub trusted_proxies: Vec<IpNet>,
+}
+
+/// Configuration for adaptive load shedding.
+///
+/// The server tracks the latency of requests to each endpoint.  When even the
+/// fastest requests to an endpoint over an `interval_ms` window take longer
+/// than `target_latency_ms`, requests are queueing up behind one another and
+/// the server begins rejecting a growing fraction of that endpoint's requests
+/// with a 503 ("Service Unavailable").  The fraction shrinks again once
+/// latency falls back under the target.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct ConfigLoadShedding {
+    /// latency above which an endpoint is considered overloaded, defaults to
+    /// 100
+    pub target_latency_ms: u64,
+    /// length of the window over which latency is measured, defaults to 1000
+    pub interval_ms: u64,
+}
+
+impl Default for ConfigLoadShedding {
+    fn default() -> Self {
+        ConfigLoadShedding { target_latency_ms: 100, interval_ms: 1000 }
+    }
+}
+
+/// Configuration for accepting connections on a Unix domain socket
+///
+/// Unix domain sockets are only supported on Unix-like systems, and not with
+/// TLS.  The socket file is removed when the server shuts down.  Requests made
+/// on the socket have the unspecified address "0.0.0.0:0" as their remote
+/// address, and so does the server as its local address.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ConfigUnixSocket {
+    /// path at which to create the socket
+    pub path: PathBuf,
+    /// permissions to give the socket file (e.g., 0o660), which determine who
+    /// may connect.  By default, the process's umask applies.
+    #[serde(default)]
+    pub mode: Option<u32>,
+    /// whether to remove a socket already at `path` (e.g., one left behind by
+    /// a server that didn't shut down), defaults to false.  Files other than
+    /// sockets are never removed.
+    #[serde(default)]
+    pub remove_existing: bool,
+}
+
+/// Configuration for HTTP/2
+///
+/// HTTP/2 is negotiated with ALPN on TLS connections.  On cleartext
+/// connections, it's used by clients that begin with the HTTP/2 connection
+/// preface ("prior knowledge").  Settings that aren't given use hyper's
+/// defaults.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct ConfigHttp2 {
+    /// whether to accept HTTP/2 connections, defaults to true
+    pub enabled: bool,
+    /// maximum number of concurrent streams on each connection
+    pub max_concurrent_streams: Option<u32>,
+    /// initial flow control window of each stream, in bytes
+    pub initial_stream_window_size: Option<u32>,
+    /// initial flow control window of each connection, in bytes
+    pub initial_connection_window_size: Option<u32>,
+    /// whether to size flow control windows from the connection's measured
+    /// bandwidth-delay product instead of the initial window sizes, defaults
+    /// to false
+    pub adaptive_window: bool,
+}
+
+impl Default for ConfigHttp2 {
+    fn default() -> Self {
+        ConfigHttp2 {
+            enabled: true,
+            max_concurrent_streams: None,
+            initial_stream_window_size: None,
+            initial_connection_window_size: None,
+            adaptive_window: false,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
@@ -71,11 +184,34 @@ pub enum ConfigTls {
         key_file: PathBuf,
     },
     AsBytes {
+        /// PEM-encoded certificate chain, in the same order as `cert_file`
         certs: Vec<u8>,
+        /// PEM-encoded PKCS #8 private key
+        key: Vec<u8>,
+    },
+    /// A certificate chain and private key held in memory in DER form, as
+    /// they would come from a secret store or be generated at startup
+    AsDer {
+        /// DER-encoded certificates, starting with the end-entity
+        /// certificate followed by any intermediate certificates
+        certs: Vec<Vec<u8>>,
+        /// DER-encoded PKCS #8 private key
         key: Vec<u8>,
     },
 }
 
+/// Verification of the certificates that clients of a TLS server present
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ConfigTlsClientAuth {
+    /// Path to a PEM file containing the certificates of the CAs trusted to
+    /// issue client certificates
+    pub ca_file: PathBuf,
+    /// whether clients may connect without presenting a certificate, defaults
+    /// to false.  A certificate that is presented is always verified.
+    #[serde(default)]
+    pub optional: bool,
+}
+
 impl ConfigTls {
     pub(crate) fn cert_reader(
         &self,
@@ -97,6 +233,10 @@ impl ConfigTls {
             ConfigTls::AsBytes { certs, .. } => {
                 Ok(Box::new(std::io::BufReader::new(certs.as_slice())))
             }
+            ConfigTls::AsDer { .. } => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "DER-encoded TLS configuration is not PEM",
+            )),
         }
     }
 
@@ -116,6 +256,10 @@ impl ConfigTls {
             ConfigTls::AsBytes { key, .. } => {
                 Ok(Box::new(std::io::BufReader::new(key.as_slice())))
             }
+            ConfigTls::AsDer { .. } => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "DER-encoded TLS configuration is not PEM",
+            )),
         }
     }
 }
@@ -124,8 +268,16 @@ impl Default for ConfigDropshot {
     fn default() -> Self {
         ConfigDropshot {
             bind_address: "127.0.0.1:0".parse().unwrap(),
+            additional_bind_addresses: Vec::new(),
+            unix_socket: None,
             request_body_max_bytes: 1024,
+            request_timeout_ms: None,
             tls: None,
+            tls_client_auth: None,
+            http2: ConfigHttp2::default(),
+            load_shedding: None,
+            external_url: None,
+            trusted_proxies: Vec::new(),
         }
     }
 }
@@ -18,6 +18,9 @@ pub(crate) struct ResponseInfo {
     pub remote_addr: std::net::SocketAddr,
     pub status_code: u16,
     pub message: String,
+    pub request_body_bytes: u64,
+    pub response_body_bytes: Option<u64>,
+    pub time_to_first_byte_us: u64,
 }
 
 #[cfg(feature = "usdt-probes")]
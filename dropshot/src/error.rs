@@ -42,10 +42,15 @@
 //! way.  Consumers can provide a `From` implementation that converts these
 //! errors into HttpErrors.
 
+use crate::http_util::http_date;
+use crate::EntityTag;
+use chrono::DateTime;
+use chrono::Utc;
 use hyper::Error as HyperError;
 use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt;
 
@@ -93,6 +98,12 @@ pub struct HttpError {
     pub external_message: String,
     /// Error message recorded in the log for this error
     pub internal_message: String,
+    /// Messages describing problems with particular fields of the request,
+    /// keyed by field name (see [`ValidationErrors`](crate::ValidationErrors))
+    pub field_errors: BTreeMap<String, Vec<String>>,
+    /// Additional headers to send with the response for this error, such as
+    /// the validators of a 304 ("Not Modified") response
+    pub headers: http::HeaderMap,
 }
 
 /// Body of an HTTP response for an `HttpError`.  This type can be used to
@@ -111,6 +122,8 @@ pub struct HttpErrorResponseBody {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error_code: Option<String>,
     pub message: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub field_errors: BTreeMap<String, Vec<String>>,
 }
 
 impl From<HyperError> for HttpError {
@@ -151,6 +164,8 @@ impl HttpError {
             error_code,
             internal_message: message.clone(),
             external_message: message,
+            field_errors: BTreeMap::new(),
+            headers: http::HeaderMap::new(),
         }
     }
 
@@ -166,6 +181,8 @@ impl HttpError {
                 .unwrap()
                 .to_string(),
             internal_message,
+            field_errors: BTreeMap::new(),
+            headers: http::HeaderMap::new(),
         }
     }
 
@@ -184,6 +201,8 @@ impl HttpError {
                 .unwrap()
                 .to_string(),
             internal_message,
+            field_errors: BTreeMap::new(),
+            headers: http::HeaderMap::new(),
         }
     }
 
@@ -229,6 +248,46 @@ impl HttpError {
             error_code,
             internal_message,
             external_message,
+            field_errors: BTreeMap::new(),
+            headers: http::HeaderMap::new(),
+        }
+    }
+
+    /// Generates an `HttpError` for a 304 "Not Modified" response, which tells
+    /// the client that its cached copy of the resource is current.  This isn't
+    /// really an error, but returning it as one lets a handler stop early (see
+    /// [`Preconditions::evaluate`](crate::Preconditions::evaluate)), and
+    /// Dropshot reports it as a successful response.  The response has no
+    /// body, but carries the resource's `etag` and `last_modified` time, if
+    /// known, as RFC 9110 requires.  Handlers that send other headers with the
+    /// full response, like `Cache-Control` or `Vary`, should add them to
+    /// `headers` too.
+    pub fn for_not_modified(
+        etag: Option<&EntityTag>,
+        last_modified: Option<DateTime<Utc>>,
+    ) -> Self {
+        let status_code = http::StatusCode::NOT_MODIFIED;
+        let message = status_code.canonical_reason().unwrap().to_string();
+        let mut headers = http::HeaderMap::new();
+        let etag = etag.map(|etag| etag.to_string());
+        let last_modified = last_modified.map(http_date);
+        for (name, value) in [
+            (http::header::ETAG, etag),
+            (http::header::LAST_MODIFIED, last_modified),
+        ] {
+            if let Some(Ok(value)) =
+                value.map(|v| http::HeaderValue::from_str(&v))
+            {
+                headers.insert(name, value);
+            }
+        }
+        HttpError {
+            status_code,
+            error_code: None,
+            internal_message: message.clone(),
+            external_message: message,
+            field_errors: BTreeMap::new(),
+            headers,
         }
     }
 
@@ -246,8 +305,15 @@ impl HttpError {
         // there's only one possible set of input and we can test it.  We'll
         // probably have to use unwrap() there and make sure we've tested that
         // code at least once!)
-        hyper::Response::builder()
-            .status(self.status_code)
+        let mut builder = hyper::Response::builder().status(self.status_code);
+        builder.headers_mut().unwrap().extend(self.headers);
+        if self.status_code == http::StatusCode::NOT_MODIFIED {
+            return builder
+                .header(super::http_util::HEADER_REQUEST_ID, request_id)
+                .body(hyper::Body::empty())
+                .unwrap();
+        }
+        builder
             .header(
                 http::header::CONTENT_TYPE,
                 super::http_util::CONTENT_TYPE_JSON,
@@ -258,6 +324,7 @@ impl HttpError {
                     request_id: request_id.to_string(),
                     message: self.external_message,
                     error_code: self.error_code,
+                    field_errors: self.field_errors,
                 })
                 .unwrap()
                 .into(),
@@ -281,6 +348,7 @@ impl Error for HttpError {
 #[cfg(test)]
 mod test {
     use crate::HttpErrorResponseBody;
+    use std::collections::BTreeMap;
 
     #[test]
     fn test_serialize_error_response_body() {
@@ -288,6 +356,7 @@ mod test {
             request_id: "123".to_string(),
             error_code: None,
             message: "oy!".to_string(),
+            field_errors: BTreeMap::new(),
         };
         let out = serde_json::to_string(&err).unwrap();
         assert_eq!(out, r#"{"request_id":"123","message":"oy!"}"#);
@@ -296,6 +365,7 @@ mod test {
             request_id: "123".to_string(),
             error_code: Some("err".to_string()),
             message: "oy!".to_string(),
+            field_errors: BTreeMap::new(),
         };
         let out = serde_json::to_string(&err).unwrap();
         assert_eq!(
@@ -0,0 +1,166 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Accept-Language extractor
+
+use crate::api_description::ApiEndpointBodyContentType;
+use crate::error::HttpError;
+use crate::server::ServerContext;
+use crate::ExtractorMetadata;
+use crate::RequestContext;
+use crate::SharedExtractor;
+use async_trait::async_trait;
+
+/// A language range from an `Accept-Language` header, such as "en-US" or "*",
+/// with its quality weight.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LanguagePreference {
+    pub tag: String,
+    pub quality: f32,
+}
+
+/// `AcceptLanguage` is an extractor providing the languages the client
+/// prefers, from the `Accept-Language` header, ordered from most to least
+/// preferred.  Languages the client has ruled out (with a quality of 0) and
+/// malformed entries are omitted.  A request without the header accepts any
+/// language, and the list is empty.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AcceptLanguage {
+    languages: Vec<LanguagePreference>,
+}
+
+impl AcceptLanguage {
+    pub fn languages(&self) -> &[LanguagePreference] {
+        &self.languages
+    }
+
+    /// Returns whichever of `available` the client most prefers, or `None` if
+    /// the client accepts none of them.  A language range matches a tag that
+    /// is equal to it or begins with it followed by "-" (ignoring case), so
+    /// "en" matches "en-GB", and "*" matches anything.  If the request had no
+    /// `Accept-Language` header, the first of `available` is returned.
+    pub fn negotiate<'a>(&self, available: &[&'a str]) -> Option<&'a str> {
+        if self.languages.is_empty() {
+            return available.first().copied();
+        }
+        self.languages.iter().find_map(|preference| {
+            available
+                .iter()
+                .copied()
+                .find(|tag| language_matches(&preference.tag, tag))
+        })
+    }
+}
+
+fn language_matches(range: &str, tag: &str) -> bool {
+    range == "*"
+        || tag.eq_ignore_ascii_case(range)
+        || (tag.len() > range.len()
+            && tag.as_bytes()[range.len()] == b'-'
+            && tag[..range.len()].eq_ignore_ascii_case(range))
+}
+
+#[async_trait]
+impl SharedExtractor for AcceptLanguage {
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+    ) -> Result<AcceptLanguage, HttpError> {
+        Ok(http_request_load_accept_language(rqctx.request.headers()))
+    }
+
+    fn metadata(
+        _body_content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        ExtractorMetadata::default()
+    }
+}
+
+fn http_request_load_accept_language(
+    headers: &http::HeaderMap,
+) -> AcceptLanguage {
+    let mut languages = headers
+        .get_all(http::header::ACCEPT_LANGUAGE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|range| {
+            let mut params = range.split(';');
+            let tag = params.next().unwrap_or("").trim();
+            let valid = tag == "*" || tag.split('-').all(valid_subtag);
+            let quality = params
+                .filter_map(|param| param.split_once('='))
+                .find(|(key, _)| key.trim().eq_ignore_ascii_case("q"))
+                .map_or(Some(1.0), |(_, value)| {
+                    value.trim().parse::<f32>().ok()
+                })
+                .filter(|quality| (0.0..=1.0).contains(quality))?;
+            (valid && quality > 0.0)
+                .then(|| LanguagePreference { tag: tag.to_string(), quality })
+        })
+        .collect::<Vec<_>>();
+    // The sort is stable, so languages of equal quality keep the client's
+    // order.
+    languages.sort_by(|a, b| b.quality.total_cmp(&a.quality));
+    AcceptLanguage { languages }
+}
+
+/// Language tags consist of subtags of 1 to 8 letters or digits.
+fn valid_subtag(subtag: &str) -> bool {
+    (1..=8).contains(&subtag.len())
+        && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+#[cfg(test)]
+mod test {
+    use super::http_request_load_accept_language;
+    use super::AcceptLanguage;
+    use http::HeaderMap;
+    use http::HeaderValue;
+
+    fn load(values: &[&'static str]) -> AcceptLanguage {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append("accept-language", HeaderValue::from_static(value));
+        }
+        http_request_load_accept_language(&headers)
+    }
+
+    fn tags(accept: &AcceptLanguage) -> Vec<(&str, f32)> {
+        accept.languages().iter().map(|l| (l.tag.as_str(), l.quality)).collect()
+    }
+
+    #[test]
+    fn test_accept_language() {
+        let accept = load(&["fr-CH, fr;q=0.9, en;q=0.8", "de;q=0.9, *;q=0.5"]);
+        assert_eq!(
+            tags(&accept),
+            vec![
+                ("fr-CH", 1.0),
+                ("fr", 0.9),
+                ("de", 0.9),
+                ("en", 0.8),
+                ("*", 0.5)
+            ]
+        );
+
+        let accept =
+            load(&["en;q=0, x_y, toolongsubtag, es;q=2, pt;q=abc, it"]);
+        assert_eq!(tags(&accept), vec![("it", 1.0)]);
+
+        assert!(load(&[]).languages().is_empty());
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let accept = load(&["fr-CH, fr;q=0.9, en;q=0.8"]);
+        assert_eq!(accept.negotiate(&["en-GB", "fr"]), Some("fr"));
+        assert_eq!(accept.negotiate(&["en-GB", "de"]), Some("en-GB"));
+        assert_eq!(accept.negotiate(&["de", "english"]), None);
+
+        let accept = load(&["DE, *;q=0.1"]);
+        assert_eq!(accept.negotiate(&["fr", "de-AT"]), Some("de-AT"));
+        assert_eq!(accept.negotiate(&["fr", "es"]), Some("fr"));
+
+        assert_eq!(load(&[]).negotiate(&["ja", "ko"]), Some("ja"));
+        assert_eq!(load(&[]).negotiate(&[]), None);
+    }
+}
@@ -0,0 +1,132 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Bearer token extractor
+
+use crate::api_description::ApiEndpointBodyContentType;
+use crate::api_description::ApiEndpointSecurity;
+use crate::error::HttpError;
+use crate::server::ServerContext;
+use crate::ExtractorMetadata;
+use crate::RequestContext;
+use crate::SharedExtractor;
+use async_trait::async_trait;
+use std::fmt;
+
+/// `BearerToken` is an extractor providing the token from an `Authorization:
+/// Bearer <token>` header (RFC 6750).  The token is checked only for syntax;
+/// verifying it is up to the handler.  A request without such a header fails
+/// with a 401 ("Unauthorized"), and one whose token is malformed fails with a
+/// 400 ("Bad Request").
+///
+/// Endpoints using this extractor are documented in the OpenAPI document as
+/// requiring HTTP bearer authentication (the "bearerAuth" security scheme).
+#[derive(Clone)]
+pub struct BearerToken {
+    token: String,
+}
+
+impl BearerToken {
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    pub fn into_inner(self) -> String {
+        self.token
+    }
+}
+
+/// The token is a credential, so keep it out of logs.
+impl fmt::Debug for BearerToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BearerToken(..)")
+    }
+}
+
+#[async_trait]
+impl SharedExtractor for BearerToken {
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+    ) -> Result<BearerToken, HttpError> {
+        http_request_load_bearer_token(rqctx.request.headers())
+    }
+
+    fn metadata(
+        _body_content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        ExtractorMetadata {
+            security: vec![ApiEndpointSecurity::bearer()],
+            ..Default::default()
+        }
+    }
+}
+
+fn http_request_load_bearer_token(
+    headers: &http::HeaderMap,
+) -> Result<BearerToken, HttpError> {
+    let credentials = headers
+        .get(http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+        .map(|(_, token)| token.trim())
+        .ok_or_else(|| {
+            HttpError::for_client_error(
+                None,
+                http::StatusCode::UNAUTHORIZED,
+                String::from("a bearer token is required"),
+            )
+        })?;
+
+    // token68 = 1*( ALPHA / DIGIT / "-" / "." / "_" / "~" / "+" / "/" ) *"="
+    let token = credentials.trim_end_matches('=');
+    let valid = !token.is_empty()
+        && token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-._~+/".contains(c));
+    if !valid {
+        return Err(HttpError::for_bad_request(
+            None,
+            String::from("malformed bearer token"),
+        ));
+    }
+
+    Ok(BearerToken { token: credentials.to_string() })
+}
+
+#[cfg(test)]
+mod test {
+    use super::http_request_load_bearer_token;
+    use http::HeaderMap;
+    use http::HeaderValue;
+    use http::StatusCode;
+
+    fn load(value: Option<&'static str>) -> Result<String, StatusCode> {
+        let mut headers = HeaderMap::new();
+        if let Some(value) = value {
+            headers.insert("authorization", HeaderValue::from_static(value));
+        }
+        http_request_load_bearer_token(&headers)
+            .map(|token| token.into_inner())
+            .map_err(|error| error.status_code)
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        assert_eq!(
+            load(Some("Bearer abc.DEF-123_~+/==")).unwrap(),
+            "abc.DEF-123_~+/=="
+        );
+        assert_eq!(load(Some("bearer  xyz")).unwrap(), "xyz");
+
+        assert_eq!(load(None), Err(StatusCode::UNAUTHORIZED));
+        assert_eq!(
+            load(Some("Basic dXNlcjpwYXNz")),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(load(Some("Bearer")), Err(StatusCode::UNAUTHORIZED));
+
+        assert_eq!(load(Some("Bearer ===")), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(load(Some("Bearer a=b")), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(load(Some("Bearer a b")), Err(StatusCode::BAD_REQUEST));
+    }
+}
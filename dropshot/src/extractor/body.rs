@@ -5,19 +5,27 @@
 use crate::api_description::ApiEndpointParameter;
 use crate::api_description::ApiSchemaGenerator;
 use crate::api_description::{ApiEndpointBodyContentType, ExtensionMode};
+use crate::checksum::content_digest_verify;
+use crate::checksum::ChecksumAlgorithm;
+use crate::checksum::RequestTrailers;
+use crate::checksum::HEADER_CONTENT_DIGEST;
 use crate::error::HttpError;
+use crate::http_util::http_content_length;
 use crate::http_util::http_dump_body;
+use crate::http_util::CountingBody;
 use crate::http_util::CONTENT_TYPE_JSON;
-use crate::schema_util::make_subschema_for;
 use crate::server::ServerContext;
 use crate::ExclusiveExtractor;
 use crate::ExtractorMetadata;
 use crate::RequestContext;
+use crate::Validate;
+use crate::Validated;
 use async_trait::async_trait;
 use bytes::BufMut;
 use bytes::Bytes;
 use bytes::BytesMut;
 use futures::Stream;
+use futures::StreamExt;
 use futures::TryStreamExt;
 use hyper::body::HttpBody;
 use schemars::schema::InstanceType;
@@ -28,7 +36,7 @@ use std::convert::Infallible;
 use std::fmt::Debug;
 
 // TypedBody: body extractor for formats that can be deserialized to a specific
-// type.  Only JSON is currently supported.
+// type.
 
 /// `TypedBody<BodyType>` is an extractor used to deserialize an instance of
 /// `BodyType` from an HTTP request body.  `BodyType` is any structure of yours
@@ -48,26 +56,15 @@ impl<BodyType: JsonSchema + DeserializeOwned + Send + Sync>
     }
 }
 
-/// Given an HTTP request, attempt to read the body, parse it according
-/// to the content type, and deserialize it to an instance of `BodyType`.
-async fn http_request_load_body<Context: ServerContext, BodyType>(
-    rqctx: &RequestContext<Context>,
-    request: hyper::Request<hyper::Body>,
-) -> Result<TypedBody<BodyType>, HttpError>
-where
-    BodyType: JsonSchema + DeserializeOwned + Send + Sync,
-{
-    let server = &rqctx.server;
-    let (parts, body) = request.into_parts();
-    let body = StreamingBody::new(body, server.config.request_body_max_bytes)
-        .into_bytes_mut()
-        .await?;
-
+/// Returns the content type of a request body from the request's
+/// `Content-Type` header.  Bodies without one are assumed to be JSON.
+fn http_request_content_type(
+    headers: &http::HeaderMap,
+) -> Result<ApiEndpointBodyContentType, HttpError> {
     // RFC 7231 §3.1.1.1: media types are case insensitive and may
     // be followed by whitespace and/or a parameter (e.g., charset),
     // which we currently ignore.
-    let content_type = parts
-        .headers
+    let content_type = headers
         .get(http::header::CONTENT_TYPE)
         .map(|hv| {
             hv.to_str().map_err(|e| {
@@ -80,10 +77,30 @@ where
         .unwrap_or(Ok(CONTENT_TYPE_JSON))?;
     let end = content_type.find(';').unwrap_or_else(|| content_type.len());
     let mime_type = content_type[..end].trim_end().to_lowercase();
-    let body_content_type =
-        ApiEndpointBodyContentType::from_mime_type(&mime_type)
-            .map_err(|e| HttpError::for_bad_request(None, e))?;
-    let expected_content_type = rqctx.body_content_type.clone();
+    ApiEndpointBodyContentType::from_mime_type(&mime_type)
+        .map_err(|e| HttpError::for_bad_request(None, e))
+}
+
+/// Given an HTTP request, attempt to read the body, parse it according
+/// to the content type, and deserialize it to an instance of `BodyType`.  The
+/// request's content type must match `expected_content_type`.
+async fn http_request_load_body<Context: ServerContext, BodyType>(
+    rqctx: &RequestContext<Context>,
+    request: hyper::Request<hyper::Body>,
+    expected_content_type: ApiEndpointBodyContentType,
+) -> Result<BodyType, HttpError>
+where
+    BodyType: JsonSchema + DeserializeOwned + Send + Sync,
+{
+    let (parts, body) = CountingBody::from_request(request);
+    let body = StreamingBody::new(
+        body,
+        rqctx.request_body_max_bytes(),
+        http_content_length(&parts.headers)?,
+    )
+    .into_bytes_mut()
+    .await?;
+    let body_content_type = http_request_content_type(&parts.headers)?;
 
     use ApiEndpointBodyContentType::*;
 
@@ -108,6 +125,20 @@ where
                 )
             })?
         }
+        #[cfg(feature = "cbor")]
+        (Cbor, Cbor) => ciborium::de::from_reader(&body[..]).map_err(|e| {
+            HttpError::for_bad_request(
+                None,
+                format!("unable to parse CBOR body: {}", e),
+            )
+        })?,
+        #[cfg(feature = "msgpack")]
+        (MsgPack, MsgPack) => rmp_serde::from_slice(&body).map_err(|e| {
+            HttpError::for_bad_request(
+                None,
+                format!("unable to parse MessagePack body: {}", e),
+            )
+        })?,
         (expected, requested) => {
             return Err(HttpError::for_bad_request(
                 None,
@@ -119,7 +150,7 @@ where
             ))
         }
     };
-    Ok(TypedBody { inner: content })
+    Ok(content)
 }
 
 // The `ExclusiveExtractor` implementation for TypedBody<BodyType> describes how
@@ -137,33 +168,234 @@ where
         rqctx: &RequestContext<Context>,
         request: hyper::Request<hyper::Body>,
     ) -> Result<TypedBody<BodyType>, HttpError> {
-        http_request_load_body(rqctx, request).await
+        // If the endpoint offers several encodings, accept any of them.
+        let encodings = &rqctx.endpoint.encodings;
+        let expected_content_type =
+            http_request_content_type(request.headers())
+                .ok()
+                .filter(|content_type| encodings.contains(content_type))
+                .unwrap_or_else(|| rqctx.body_content_type.clone());
+        let inner =
+            http_request_load_body(rqctx, request, expected_content_type)
+                .await?;
+        Ok(TypedBody { inner })
     }
 
     fn metadata(content_type: ApiEndpointBodyContentType) -> ExtractorMetadata {
-        let body = ApiEndpointParameter::new_body(
-            content_type,
-            true,
-            ApiSchemaGenerator::Gen {
-                name: BodyType::schema_name,
-                schema: make_subschema_for::<BodyType>,
-            },
-            vec![],
-        );
-        ExtractorMetadata {
-            extension_mode: ExtensionMode::None,
-            parameters: vec![body],
+        ExtractorMetadata::for_body::<BodyType>(content_type)
+    }
+}
+
+/// Returns whether `request` has no body.  An HTTP/1 request has a body only if
+/// it has a non-zero Content-Length or a Transfer-Encoding (RFC 9112 section
+/// 6.3).  HTTP/2 requests needn't say, so for those we rely on hyper knowing
+/// whether the stream ended with the request's headers.
+fn http_request_has_no_body(
+    request: &hyper::Request<hyper::Body>,
+) -> Result<bool, HttpError> {
+    let headers = request.headers();
+    match request.version() {
+        http::Version::HTTP_09
+        | http::Version::HTTP_10
+        | http::Version::HTTP_11 => Ok(match http_content_length(headers)? {
+            Some(content_length) => content_length == 0,
+            None => !headers.contains_key(http::header::TRANSFER_ENCODING),
+        }),
+        _ => Ok(request.body().is_end_stream()),
+    }
+}
+
+// An `Option<TypedBody<BodyType>>` is `None` for a request without a body.
+#[async_trait]
+impl<BodyType> ExclusiveExtractor for Option<TypedBody<BodyType>>
+where
+    BodyType: JsonSchema + DeserializeOwned + Send + Sync + 'static,
+{
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+        request: hyper::Request<hyper::Body>,
+    ) -> Result<Option<TypedBody<BodyType>>, HttpError> {
+        if http_request_has_no_body(&request)? {
+            return Ok(None);
+        }
+        TypedBody::<BodyType>::from_request(rqctx, request).await.map(Some)
+    }
+
+    fn metadata(content_type: ApiEndpointBodyContentType) -> ExtractorMetadata {
+        let mut metadata = TypedBody::<BodyType>::metadata(content_type);
+        for parameter in &mut metadata.parameters {
+            parameter.required = false;
         }
+        metadata
+    }
+}
+
+#[async_trait]
+impl<BodyType> ExclusiveExtractor for Validated<TypedBody<BodyType>>
+where
+    BodyType: JsonSchema + DeserializeOwned + Validate + Send + Sync + 'static,
+{
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+        request: hyper::Request<hyper::Body>,
+    ) -> Result<Validated<TypedBody<BodyType>>, HttpError> {
+        let body = TypedBody::<BodyType>::from_request(rqctx, request).await?;
+        body.inner.validate()?;
+        Ok(Validated { inner: body })
+    }
+
+    fn metadata(content_type: ApiEndpointBodyContentType) -> ExtractorMetadata {
+        TypedBody::<BodyType>::metadata(content_type)
+    }
+}
+
+// FormBody: body extractor for URL-encoded forms.
+
+/// `FormBody<BodyType>` is an extractor used to deserialize an instance of
+/// `BodyType` from an HTTP request body encoded as
+/// `application/x-www-form-urlencoded`, as submitted by HTML forms.  Unlike
+/// [`TypedBody`], it expects that content type whatever the endpoint's
+/// `content_type` says, and it rejects requests with any other content type.
+#[derive(Debug)]
+pub struct FormBody<BodyType: JsonSchema + DeserializeOwned + Send + Sync> {
+    inner: BodyType,
+}
+
+impl<BodyType: JsonSchema + DeserializeOwned + Send + Sync> FormBody<BodyType> {
+    pub fn into_inner(self) -> BodyType {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<BodyType> ExclusiveExtractor for FormBody<BodyType>
+where
+    BodyType: JsonSchema + DeserializeOwned + Send + Sync + 'static,
+{
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+        request: hyper::Request<hyper::Body>,
+    ) -> Result<FormBody<BodyType>, HttpError> {
+        let inner = http_request_load_body(
+            rqctx,
+            request,
+            ApiEndpointBodyContentType::UrlEncoded,
+        )
+        .await?;
+        Ok(FormBody { inner })
+    }
+
+    fn metadata(
+        _content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        TypedBody::<BodyType>::metadata(ApiEndpointBodyContentType::UrlEncoded)
+    }
+}
+
+// CborBody: body extractor for CBOR-encoded bodies.
+
+/// `CborBody<BodyType>` is an extractor used to deserialize an instance of
+/// `BodyType` from an HTTP request body encoded as `application/cbor`, a
+/// compact binary alternative to JSON for constrained clients.  Like
+/// [`FormBody`], it expects that content type whatever the endpoint's
+/// `content_type` says.
+#[cfg(feature = "cbor")]
+#[derive(Debug)]
+pub struct CborBody<BodyType: JsonSchema + DeserializeOwned + Send + Sync> {
+    inner: BodyType,
+}
+
+#[cfg(feature = "cbor")]
+impl<BodyType: JsonSchema + DeserializeOwned + Send + Sync> CborBody<BodyType> {
+    pub fn into_inner(self) -> BodyType {
+        self.inner
+    }
+}
+
+#[cfg(feature = "cbor")]
+#[async_trait]
+impl<BodyType> ExclusiveExtractor for CborBody<BodyType>
+where
+    BodyType: JsonSchema + DeserializeOwned + Send + Sync + 'static,
+{
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+        request: hyper::Request<hyper::Body>,
+    ) -> Result<CborBody<BodyType>, HttpError> {
+        let inner = http_request_load_body(
+            rqctx,
+            request,
+            ApiEndpointBodyContentType::Cbor,
+        )
+        .await?;
+        Ok(CborBody { inner })
+    }
+
+    fn metadata(
+        _content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        TypedBody::<BodyType>::metadata(ApiEndpointBodyContentType::Cbor)
+    }
+}
+
+// MsgPackBody: body extractor for MessagePack-encoded bodies.
+
+/// `MsgPackBody<BodyType>` is an extractor used to deserialize an instance of
+/// `BodyType` from an HTTP request body encoded as `application/msgpack`.  Like
+/// [`FormBody`], it expects that content type whatever the endpoint's
+/// `content_type` says.  The body is documented with `BodyType`'s JSON schema,
+/// which describes the shape of the MessagePack data just as well.
+#[cfg(feature = "msgpack")]
+#[derive(Debug)]
+pub struct MsgPackBody<BodyType: JsonSchema + DeserializeOwned + Send + Sync> {
+    inner: BodyType,
+}
+
+#[cfg(feature = "msgpack")]
+impl<BodyType: JsonSchema + DeserializeOwned + Send + Sync>
+    MsgPackBody<BodyType>
+{
+    pub fn into_inner(self) -> BodyType {
+        self.inner
+    }
+}
+
+#[cfg(feature = "msgpack")]
+#[async_trait]
+impl<BodyType> ExclusiveExtractor for MsgPackBody<BodyType>
+where
+    BodyType: JsonSchema + DeserializeOwned + Send + Sync + 'static,
+{
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+        request: hyper::Request<hyper::Body>,
+    ) -> Result<MsgPackBody<BodyType>, HttpError> {
+        let inner = http_request_load_body(
+            rqctx,
+            request,
+            ApiEndpointBodyContentType::MsgPack,
+        )
+        .await?;
+        Ok(MsgPackBody { inner })
+    }
+
+    fn metadata(
+        _content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        TypedBody::<BodyType>::metadata(ApiEndpointBodyContentType::MsgPack)
     }
 }
 
 // UntypedBody: body extractor for a plain array of bytes of a body.
 
 /// `UntypedBody` is an extractor for reading in the contents of the HTTP request
-/// body and making the raw bytes directly available to the consumer.
+/// body and making the raw bytes directly available to the consumer.  This is
+/// useful when the bytes themselves matter, as when verifying a signature
+/// computed over the body before parsing it.
 #[derive(Debug)]
 pub struct UntypedBody {
     content: Bytes,
+    content_type: Option<String>,
 }
 
 impl UntypedBody {
@@ -183,6 +415,17 @@ impl UntypedBody {
             )
         })
     }
+
+    /// Returns the request's `Content-Type` header, if it had one that is
+    /// valid ASCII.
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    /// Consumes the extractor, returning the body content.
+    pub fn into_bytes(self) -> Bytes {
+        self.content
+    }
 }
 
 #[async_trait]
@@ -191,19 +434,24 @@ impl ExclusiveExtractor for UntypedBody {
         rqctx: &RequestContext<Context>,
         request: hyper::Request<hyper::Body>,
     ) -> Result<UntypedBody, HttpError> {
-        let server = &rqctx.server;
-        let body = request.into_body();
-        let body_bytes =
-            StreamingBody::new(body, server.config.request_body_max_bytes)
-                .into_bytes_mut()
-                .await?;
-        Ok(UntypedBody { content: body_bytes.freeze() })
+        let (parts, body) = CountingBody::from_request(request);
+        let content_type = parts
+            .headers
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        let body_bytes = StreamingBody::new(
+            body,
+            rqctx.request_body_max_bytes(),
+            http_content_length(&parts.headers)?,
+        )
+        .into_bytes_mut()
+        .await?;
+        Ok(UntypedBody { content: body_bytes.freeze(), content_type })
     }
 
-    fn metadata(
-        _content_type: ApiEndpointBodyContentType,
-    ) -> ExtractorMetadata {
-        untyped_metadata()
+    fn metadata(content_type: ApiEndpointBodyContentType) -> ExtractorMetadata {
+        untyped_metadata(content_type)
     }
 }
 
@@ -213,13 +461,29 @@ impl ExclusiveExtractor for UntypedBody {
 /// raw bytes available to the consumer.
 #[derive(Debug)]
 pub struct StreamingBody {
-    body: hyper::Body,
+    body: CountingBody,
     cap: usize,
+    /// length declared by the request's Content-Length header, if any
+    content_length: Option<usize>,
+    /// checksums declared by the request's Content-Digest header, if any
+    content_digest: Option<http::HeaderValue>,
+    /// trailers of the request, available once the body has been read
+    trailers: RequestTrailers,
 }
 
 impl StreamingBody {
-    fn new(body: hyper::Body, cap: usize) -> Self {
-        Self { body, cap }
+    fn new(
+        body: CountingBody,
+        cap: usize,
+        content_length: Option<usize>,
+    ) -> Self {
+        Self {
+            body,
+            cap,
+            content_length,
+            content_digest: None,
+            trailers: RequestTrailers::default(),
+        }
     }
 
     /// Not part of the public API. Used only for doctests.
@@ -228,7 +492,7 @@ impl StreamingBody {
         let cap = data.len();
         let stream = futures::stream::iter([Ok::<_, Infallible>(data)]);
         let body = hyper::Body::wrap_stream(stream);
-        Self { body, cap }
+        Self::new(body.into(), cap, None)
     }
 
     /// Converts `self` into a stream.
@@ -240,7 +504,10 @@ impl StreamingBody {
     /// The stream produces an [`HttpError`] if any of the following cases occur:
     ///
     /// * A network error occurred.
-    /// * `request_body_max_bytes` was exceeded for this request.
+    /// * The request body limit (see
+    ///   [`RequestContext::request_body_max_bytes`]) was exceeded.
+    /// * The number of bytes received did not match the request's
+    ///   Content-Length header.
     ///
     /// # Examples
     ///
@@ -307,6 +574,17 @@ impl StreamingBody {
         mut self,
     ) -> impl Stream<Item = Result<Bytes, HttpError>> + Send {
         async_stream::try_stream! {
+            // If the client has told us up front that the body is too large,
+            // reject it without reading any of it.
+            if let Some(content_length) = self.content_length {
+                if content_length > self.cap {
+                    Err(HttpError::for_bad_request(
+                        None,
+                        format!("request body exceeded maximum size of {} bytes", self.cap),
+                    ))?;
+                }
+            }
+
             let mut bytes_read: usize = 0;
             while let Some(buf_res) = self.body.data().await {
                 let buf = buf_res?;
@@ -325,9 +603,60 @@ impl StreamingBody {
                 yield buf;
             }
 
-            // Read the trailers as well, even though we're not going to do anything
-            // with them.
-            self.body.trailers().await?;
+            if let Some(content_length) = self.content_length {
+                if bytes_read != content_length {
+                    Err(HttpError::for_bad_request(
+                        None,
+                        format!(
+                            "request body length ({} bytes) does not match \
+                             Content-Length header ({} bytes)",
+                            bytes_read, content_length
+                        ),
+                    ))?;
+                }
+            }
+
+            // Read the trailers as well, keeping them for
+            // `into_stream_with_checksum`.
+            if let Some(trailers) = self.body.trailers().await? {
+                self.trailers.set(trailers);
+            }
+        }
+    }
+
+    /// Converts `self` into a stream, like [`StreamingBody::into_stream`], that
+    /// also verifies the body against the checksum sent by the client.
+    ///
+    /// The checksum is taken from the request's `Content-Digest` trailer (see
+    /// RFC 9530), or from its `Content-Digest` header if it has no such
+    /// trailer.  Either must include a digest computed with `algorithm`.  Once
+    /// the whole body has been read, the stream produces a 400-level
+    /// [`HttpError`] if the checksum is missing or does not match the body.
+    /// Since a mismatch can only be detected at the end, consumers should not
+    /// commit to the data until the stream has completed successfully.
+    pub fn into_stream_with_checksum(
+        self,
+        algorithm: ChecksumAlgorithm,
+    ) -> impl Stream<Item = Result<Bytes, HttpError>> + Send {
+        let content_digest = self.content_digest.clone();
+        let trailers = self.trailers.clone();
+        let stream = self.into_stream();
+        async_stream::try_stream! {
+            tokio::pin!(stream);
+            let mut checksummer = algorithm.checksummer();
+            while let Some(buf_res) = stream.next().await {
+                let buf = buf_res?;
+                checksummer.update(&buf);
+                yield buf;
+            }
+
+            let field =
+                trailers.get(HEADER_CONTENT_DIGEST).or(content_digest);
+            content_digest_verify(
+                field.as_ref(),
+                algorithm,
+                &checksummer.finish(),
+            )?;
         }
     }
 
@@ -350,39 +679,58 @@ impl ExclusiveExtractor for StreamingBody {
         rqctx: &RequestContext<Context>,
         request: hyper::Request<hyper::Body>,
     ) -> Result<Self, HttpError> {
-        let server = &rqctx.server;
+        let (parts, body) = CountingBody::from_request(request);
+        let content_length = http_content_length(&parts.headers)?;
+        let content_digest = parts.headers.get(HEADER_CONTENT_DIGEST).cloned();
 
         Ok(Self {
-            body: request.into_body(),
-            cap: server.config.request_body_max_bytes,
+            body,
+            cap: rqctx.request_body_max_bytes(),
+            content_length,
+            content_digest,
+            trailers: RequestTrailers::default(),
         })
     }
 
-    fn metadata(
-        _content_type: ApiEndpointBodyContentType,
-    ) -> ExtractorMetadata {
-        untyped_metadata()
+    fn metadata(content_type: ApiEndpointBodyContentType) -> ExtractorMetadata {
+        untyped_metadata(content_type)
     }
 }
 
-fn untyped_metadata() -> ExtractorMetadata {
+/// Describes a body whose raw bytes are made available to the handler.  The
+/// body is documented with the endpoint's content type, except that JSON, the
+/// default, is taken to mean that the endpoint doesn't say, and the body is
+/// then documented as arbitrary bytes.
+fn untyped_metadata(
+    content_type: ApiEndpointBodyContentType,
+) -> ExtractorMetadata {
+    let schema = match content_type {
+        ApiEndpointBodyContentType::UrlEncoded
+        | ApiEndpointBodyContentType::MultipartFormData => SchemaObject {
+            instance_type: Some(InstanceType::Object.into()),
+            ..Default::default()
+        },
+        _ => SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            format: Some(String::from("binary")),
+            ..Default::default()
+        },
+    };
+    let content_type = match content_type {
+        ApiEndpointBodyContentType::Json => ApiEndpointBodyContentType::Bytes,
+        content_type => content_type,
+    };
     ExtractorMetadata {
         parameters: vec![ApiEndpointParameter::new_body(
-            ApiEndpointBodyContentType::Bytes,
+            content_type,
             true,
             ApiSchemaGenerator::Static {
-                schema: Box::new(
-                    SchemaObject {
-                        instance_type: Some(InstanceType::String.into()),
-                        format: Some(String::from("binary")),
-                        ..Default::default()
-                    }
-                    .into(),
-                ),
+                schema: Box::new(schema.into()),
                 dependencies: indexmap::IndexMap::default(),
             },
             vec![],
         )],
         extension_mode: ExtensionMode::None,
+        security: vec![],
     }
 }
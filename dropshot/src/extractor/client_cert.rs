@@ -0,0 +1,201 @@
+// Copyright 2023 Oxide Computer Company
+
+//! TLS client certificate extractor
+
+use crate::api_description::ApiEndpointBodyContentType;
+use crate::error::HttpError;
+use crate::server::ServerContext;
+use crate::ExtractorMetadata;
+use crate::RequestContext;
+use crate::SharedExtractor;
+use async_trait::async_trait;
+use std::convert::TryFrom;
+use std::net::IpAddr;
+use std::sync::Arc;
+use x509_parser::extensions::GeneralName;
+use x509_parser::parse_x509_certificate;
+
+/// `ClientCert` is an extractor providing the certificate chain that the
+/// client presented during the TLS handshake, along with fields parsed from
+/// the client's own certificate.  Clients are asked for certificates only when
+/// the server is configured with
+/// [`ConfigDropshot::tls_client_auth`](crate::ConfigDropshot::tls_client_auth),
+/// which names the CA bundle that the chain has been verified against by the
+/// time a request is handled.
+///
+/// Requests made without a client certificate (including all requests to a
+/// server not using TLS) fail with a 401 ("Unauthorized").  Use
+/// `Option<ClientCert>` for endpoints that accept such requests, or see
+/// [`RequestInfo::client_cert`](crate::RequestInfo::client_cert).
+#[derive(Clone, Debug)]
+pub struct ClientCert {
+    inner: Arc<ClientCertInner>,
+}
+
+#[derive(Debug)]
+struct ClientCertInner {
+    chain: Vec<Vec<u8>>,
+    subject: String,
+    dns_names: Vec<String>,
+    email_addresses: Vec<String>,
+    uris: Vec<String>,
+    ip_addresses: Vec<IpAddr>,
+}
+
+impl ClientCert {
+    /// Parses a certificate chain presented by a client, whose first
+    /// certificate is the client's own.  Returns `None` if the chain is empty
+    /// or the first certificate can't be parsed.
+    pub(crate) fn from_chain(chain: &[rustls::Certificate]) -> Option<Self> {
+        let (_, cert) = parse_x509_certificate(&chain.first()?.0).ok()?;
+        let mut inner = ClientCertInner {
+            chain: chain.iter().map(|cert| cert.0.clone()).collect(),
+            subject: cert.subject().to_string(),
+            dns_names: Vec::new(),
+            email_addresses: Vec::new(),
+            uris: Vec::new(),
+            ip_addresses: Vec::new(),
+        };
+
+        if let Ok(Some(san)) = cert.subject_alternative_name() {
+            for name in &san.value.general_names {
+                match name {
+                    GeneralName::DNSName(name) => {
+                        inner.dns_names.push(name.to_string())
+                    }
+                    GeneralName::RFC822Name(address) => {
+                        inner.email_addresses.push(address.to_string())
+                    }
+                    GeneralName::URI(uri) => inner.uris.push(uri.to_string()),
+                    GeneralName::IPAddress(bytes) => {
+                        if let Ok(octets) = <[u8; 4]>::try_from(*bytes) {
+                            inner.ip_addresses.push(IpAddr::from(octets));
+                        } else if let Ok(octets) = <[u8; 16]>::try_from(*bytes)
+                        {
+                            inner.ip_addresses.push(IpAddr::from(octets));
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        Some(ClientCert { inner: Arc::new(inner) })
+    }
+
+    /// Returns the DER encoding of each certificate in the chain, starting
+    /// with the client's own.
+    pub fn chain(&self) -> &[Vec<u8>] {
+        &self.inner.chain
+    }
+
+    /// Returns the subject of the client's certificate as a distinguished
+    /// name, e.g., "CN=client.example.com, O=Example".
+    pub fn subject(&self) -> &str {
+        &self.inner.subject
+    }
+
+    /// Returns the DNS names among the certificate's subject alternative
+    /// names.
+    pub fn dns_names(&self) -> &[String] {
+        &self.inner.dns_names
+    }
+
+    /// Returns the email addresses among the certificate's subject
+    /// alternative names.
+    pub fn email_addresses(&self) -> &[String] {
+        &self.inner.email_addresses
+    }
+
+    /// Returns the URIs (such as SPIFFE IDs) among the certificate's subject
+    /// alternative names.
+    pub fn uris(&self) -> &[String] {
+        &self.inner.uris
+    }
+
+    /// Returns the IP addresses among the certificate's subject alternative
+    /// names.
+    pub fn ip_addresses(&self) -> &[IpAddr] {
+        &self.inner.ip_addresses
+    }
+}
+
+#[async_trait]
+impl SharedExtractor for ClientCert {
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+    ) -> Result<ClientCert, HttpError> {
+        rqctx.request.client_cert().cloned().ok_or_else(|| {
+            HttpError::for_client_error(
+                None,
+                http::StatusCode::UNAUTHORIZED,
+                String::from("a client certificate is required"),
+            )
+        })
+    }
+
+    fn metadata(
+        _body_content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        ExtractorMetadata::default()
+    }
+}
+
+#[async_trait]
+impl SharedExtractor for Option<ClientCert> {
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+    ) -> Result<Option<ClientCert>, HttpError> {
+        Ok(rqctx.request.client_cert().cloned())
+    }
+
+    fn metadata(
+        _body_content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        ExtractorMetadata::default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ClientCert;
+    use std::net::IpAddr;
+
+    #[test]
+    fn test_from_chain() {
+        assert!(ClientCert::from_chain(&[]).is_none());
+        assert!(ClientCert::from_chain(&[rustls::Certificate(vec![1, 2, 3])])
+            .is_none());
+
+        let mut params = rcgen::CertificateParams::new(vec![]);
+        params.distinguished_name = rcgen::DistinguishedName::new();
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "client.example.com");
+        params.subject_alt_names = vec![
+            rcgen::SanType::DnsName(String::from("client.example.com")),
+            rcgen::SanType::Rfc822Name(String::from("ops@example.com")),
+            rcgen::SanType::IpAddress("192.0.2.7".parse().unwrap()),
+            rcgen::SanType::IpAddress("2001:db8::7".parse().unwrap()),
+        ];
+        let der = rcgen::Certificate::from_params(params)
+            .unwrap()
+            .serialize_der()
+            .unwrap();
+
+        let cert = ClientCert::from_chain(&[rustls::Certificate(der.clone())])
+            .unwrap();
+        assert_eq!(cert.chain(), &[der]);
+        assert_eq!(cert.subject(), "CN=client.example.com");
+        assert_eq!(cert.dns_names(), &["client.example.com"]);
+        assert_eq!(cert.email_addresses(), &["ops@example.com"]);
+        assert!(cert.uris().is_empty());
+        assert_eq!(
+            cert.ip_addresses(),
+            &[
+                "192.0.2.7".parse::<IpAddr>().unwrap(),
+                "2001:db8::7".parse::<IpAddr>().unwrap()
+            ]
+        );
+    }
+}
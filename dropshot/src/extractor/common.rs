@@ -1,18 +1,64 @@
 // Copyright 2023 Oxide Computer Company
 
+use super::metadata::get_metadata;
 use crate::api_description::ApiEndpointParameter;
+use crate::api_description::ApiEndpointParameterLocation;
+use crate::api_description::ApiEndpointSecurity;
+use crate::api_description::ApiSchemaGenerator;
 use crate::api_description::{ApiEndpointBodyContentType, ExtensionMode};
 use crate::error::HttpError;
+use crate::schema_util::make_subschema_for;
 use crate::server::ServerContext;
 use crate::RequestContext;
 
 use async_trait::async_trait;
+use schemars::JsonSchema;
 
 /// Metadata associated with an extractor including parameters and whether or not
 /// the associated endpoint is paginated.
+///
+/// The default value describes no parameters at all, which suits extractors
+/// (like one that authenticates the caller) whose inputs shouldn't appear in
+/// the OpenAPI document.
+#[derive(Default)]
 pub struct ExtractorMetadata {
     pub extension_mode: ExtensionMode,
     pub parameters: Vec<ApiEndpointParameter>,
+    /// security schemes the extractor checks, which the OpenAPI document
+    /// lists as requirements of the endpoint
+    pub security: Vec<ApiEndpointSecurity>,
+}
+
+impl ExtractorMetadata {
+    /// Describes each member of the struct `ParamType` as a parameter at
+    /// `location`, as [`Query`](crate::Query), [`Path`](crate::Path), and
+    /// [`Header`](crate::Header) do.
+    pub fn for_parameters<ParamType: JsonSchema>(
+        location: ApiEndpointParameterLocation,
+    ) -> Self {
+        get_metadata::<ParamType>(&location)
+    }
+
+    /// Describes a request body of type `BodyType` encoded as `content_type`,
+    /// as [`TypedBody`](crate::TypedBody) does.
+    pub fn for_body<BodyType: JsonSchema>(
+        content_type: ApiEndpointBodyContentType,
+    ) -> Self {
+        let body = ApiEndpointParameter::new_body(
+            content_type,
+            true,
+            ApiSchemaGenerator::Gen {
+                name: BodyType::schema_name,
+                schema: make_subschema_for::<BodyType>,
+            },
+            vec![],
+        );
+        ExtractorMetadata {
+            extension_mode: ExtensionMode::None,
+            parameters: vec![body],
+            security: vec![],
+        }
+    }
 }
 
 /// Extractors that require exclusive access to the underyling `hyper::Request`
@@ -120,6 +166,7 @@ impl RequestExtractor for () {
         ExtractorMetadata {
             extension_mode: ExtensionMode::None,
             parameters: vec![],
+            security: vec![],
         }
     }
 }
@@ -176,6 +223,8 @@ macro_rules! impl_rqextractor_for_tuple {
             let mut extension_mode = ExtensionMode::None;
             #[allow(unused_mut)]
             let mut parameters = vec![];
+            #[allow(unused_mut)]
+            let mut security = vec![];
             $(
                 let mut metadata = $S::metadata(_body_content_type.clone());
                 extension_mode = match (extension_mode, metadata.extension_mode) {
@@ -186,6 +235,7 @@ macro_rules! impl_rqextractor_for_tuple {
                     (_, x) => x,
                 };
                 parameters.append(&mut metadata.parameters);
+                security.append(&mut metadata.security);
             )+
 
             let mut metadata = X::metadata(_body_content_type.clone());
@@ -197,8 +247,9 @@ macro_rules! impl_rqextractor_for_tuple {
                 (_, x) => x,
             };
             parameters.append(&mut metadata.parameters);
+            security.append(&mut metadata.security);
 
-            ExtractorMetadata { extension_mode, parameters }
+            ExtractorMetadata { extension_mode, parameters, security }
         }
     }
 }}
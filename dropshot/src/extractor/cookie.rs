@@ -0,0 +1,435 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Cookie-related extractor(s)
+
+use super::metadata::get_metadata;
+use crate::api_description::ApiEndpointBodyContentType;
+use crate::api_description::ApiEndpointParameterLocation;
+use crate::error::HttpError;
+use crate::from_map::from_map;
+use crate::hmac::hmac_sha256;
+use crate::hmac::hmac_sha256_verify;
+use crate::server::ServerContext;
+use crate::ExtractorMetadata;
+use crate::RequestContext;
+use crate::SharedExtractor;
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::Aead;
+use aes_gcm::aead::AeadCore;
+use aes_gcm::aead::KeyInit;
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::Payload;
+use aes_gcm::Aes256Gcm;
+use async_trait::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use http::header::COOKIE;
+use http::HeaderMap;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Debug;
+
+/// `Cookie<CookieType>` is an extractor used to deserialize an instance of
+/// `CookieType` from the cookies sent with an HTTP request.  `CookieType` is
+/// any structure of yours that implements `serde::Deserialize`, with one field
+/// per cookie.  A request that lacks a required cookie or includes one that
+/// cannot be parsed fails with a 400 error.
+///
+/// Clients can set cookies to whatever they like.  Use [`SignedCookie`] for
+/// cookies whose values the server must be able to trust, and
+/// [`EncryptedCookie`] for those whose values clients must not see either.
+#[derive(Debug)]
+pub struct Cookie<CookieType: JsonSchema + Send + Sync> {
+    inner: CookieType,
+}
+
+impl<CookieType: JsonSchema + Send + Sync> Cookie<CookieType> {
+    pub fn into_inner(self) -> CookieType {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<CookieType> SharedExtractor for Cookie<CookieType>
+where
+    CookieType: DeserializeOwned + JsonSchema + Send + Sync + 'static,
+{
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+    ) -> Result<Cookie<CookieType>, HttpError> {
+        let cookies = http_request_cookies(rqctx.request.headers())?;
+        let inner = cookies_deserialize(&cookies)?;
+        Ok(Cookie { inner })
+    }
+
+    fn metadata(
+        _body_content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        get_metadata::<CookieType>(&ApiEndpointParameterLocation::Cookie)
+    }
+}
+
+/// `SignedCookie<CookieType>` is like [`Cookie`], except that it only accepts
+/// cookies whose values were signed with the server's [`CookieKey`] (see
+/// [`ApiDescription::cookie_key`](crate::ApiDescription::cookie_key)).  Other
+/// cookies are treated as though they were not sent, so a request whose
+/// required cookie has been tampered with fails with a 400 error.  The values
+/// that `CookieType` sees have had their signatures removed.
+///
+/// Signing makes cookies tamper-proof, not secret: clients can still read
+/// their values.
+#[derive(Debug)]
+pub struct SignedCookie<CookieType: JsonSchema + Send + Sync> {
+    inner: CookieType,
+}
+
+impl<CookieType: JsonSchema + Send + Sync> SignedCookie<CookieType> {
+    pub fn into_inner(self) -> CookieType {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<CookieType> SharedExtractor for SignedCookie<CookieType>
+where
+    CookieType: DeserializeOwned + JsonSchema + Send + Sync + 'static,
+{
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+    ) -> Result<SignedCookie<CookieType>, HttpError> {
+        let cookies = http_request_cookies_with_key(
+            rqctx,
+            "SignedCookie",
+            |key, n, v| key.verify(n, v),
+        )?;
+        let inner = cookies_deserialize(&cookies)?;
+        Ok(SignedCookie { inner })
+    }
+
+    fn metadata(
+        _body_content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        get_metadata::<CookieType>(&ApiEndpointParameterLocation::Cookie)
+    }
+}
+
+/// `EncryptedCookie<CookieType>` is like [`Cookie`], except that it only
+/// accepts cookies whose values were encrypted with the server's [`CookieKey`]
+/// (see [`ApiDescription::cookie_key`](crate::ApiDescription::cookie_key)).
+/// As with [`SignedCookie`], other cookies are treated as though they were not
+/// sent, and the values that `CookieType` sees have been decrypted.
+///
+/// Encryption makes cookies both tamper-proof and secret: clients see only
+/// opaque values.
+#[derive(Debug)]
+pub struct EncryptedCookie<CookieType: JsonSchema + Send + Sync> {
+    inner: CookieType,
+}
+
+impl<CookieType: JsonSchema + Send + Sync> EncryptedCookie<CookieType> {
+    pub fn into_inner(self) -> CookieType {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<CookieType> SharedExtractor for EncryptedCookie<CookieType>
+where
+    CookieType: DeserializeOwned + JsonSchema + Send + Sync + 'static,
+{
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+    ) -> Result<EncryptedCookie<CookieType>, HttpError> {
+        let cookies = http_request_cookies_with_key(
+            rqctx,
+            "EncryptedCookie",
+            |key, n, v| key.decrypt(n, v),
+        )?;
+        let inner = cookies_deserialize(&cookies)?;
+        Ok(EncryptedCookie { inner })
+    }
+
+    fn metadata(
+        _body_content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        get_metadata::<CookieType>(&ApiEndpointParameterLocation::Cookie)
+    }
+}
+
+/// Secret used to sign, verify, encrypt, and decrypt cookie values
+///
+/// A signed value is the original value followed by a `.` and a signature (an
+/// HMAC-SHA256 of the cookie's name and value), so it can be sent in a
+/// `Set-Cookie` header wherever the original value could.  An encrypted value
+/// is the original value sealed with AES-256-GCM under a key derived from the
+/// secret, with the cookie's name as associated data, and encoded as base64.
+#[derive(Clone)]
+pub struct CookieKey {
+    secret: Vec<u8>,
+    cipher: Aes256Gcm,
+}
+
+impl CookieKey {
+    /// Creates a key from `secret`, which should be randomly generated and
+    /// kept private to the servers that share it.  This fails if `secret` is
+    /// shorter than 32 bytes.
+    pub fn new(secret: impl Into<Vec<u8>>) -> Result<Self, String> {
+        let secret = secret.into();
+        if secret.len() < 32 {
+            return Err(format!(
+                "cookie key must be at least 32 bytes, got {}",
+                secret.len()
+            ));
+        }
+        // Use a separate key for encryption, so that a value signed for one
+        // purpose can't be confused with one encrypted for another.
+        let encryption_key =
+            hmac_sha256(&secret, &[&b"dropshot cookie encryption"[..]]);
+        let cipher = Aes256Gcm::new(&encryption_key.into());
+        Ok(CookieKey { secret, cipher })
+    }
+
+    /// Returns `value` signed for use as the value of the cookie `name`.
+    pub fn sign(&self, name: &str, value: &str) -> String {
+        let signature = self.signature(name, value);
+        format!("{}.{}", value, URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    /// Returns the original value of the cookie `name` if `signed` carries a
+    /// valid signature, or `None` otherwise.
+    pub fn verify(&self, name: &str, signed: &str) -> Option<String> {
+        let (value, signature) = signed.rsplit_once('.')?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        let message: [&[u8]; 3] = [name.as_bytes(), b"=", value.as_bytes()];
+        hmac_sha256_verify(&self.secret, &message, &signature)
+            .then(|| value.to_string())
+    }
+
+    /// HMAC-SHA256 of `name=value`
+    fn signature(&self, name: &str, value: &str) -> [u8; 32] {
+        hmac_sha256(&self.secret, &[name.as_bytes(), b"=", value.as_bytes()])
+    }
+
+    /// Returns `value` encrypted for use as the value of the cookie `name`.
+    /// Each call uses a fresh random nonce, so encrypting the same value twice
+    /// gives different results.
+    pub fn encrypt(&self, name: &str, value: &str) -> String {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let payload = Payload { msg: value.as_bytes(), aad: name.as_bytes() };
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, payload)
+            .expect("encrypting a cookie value cannot fail");
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        URL_SAFE_NO_PAD.encode(sealed)
+    }
+
+    /// Returns the original value of the cookie `name` if `encrypted` was
+    /// produced by [`CookieKey::encrypt`] with this key for a cookie of that
+    /// name, or `None` otherwise.
+    pub fn decrypt(&self, name: &str, encrypted: &str) -> Option<String> {
+        let sealed = URL_SAFE_NO_PAD.decode(encrypted).ok()?;
+        const NONCE_SIZE: usize = 12;
+        if sealed.len() < NONCE_SIZE {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_SIZE);
+        let payload = Payload { msg: ciphertext, aad: name.as_bytes() };
+        let value = self
+            .cipher
+            .decrypt(GenericArray::from_slice(nonce), payload)
+            .ok()?;
+        String::from_utf8(value).ok()
+    }
+}
+
+impl Debug for CookieKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CookieKey { .. }")
+    }
+}
+
+/// Parses the `Cookie` headers of a request into a map from each cookie's
+/// name to its value.  If a name appears more than once, the first value is
+/// used, since clients send the cookie with the most specific path first.
+/// Pairs without a `=` are ignored, as RFC 6265 section 5.2 does: clients send
+/// every cookie set for the domain, including ones that aren't ours to judge.
+fn http_request_cookies(
+    headers: &HeaderMap,
+) -> Result<BTreeMap<String, String>, HttpError> {
+    let mut cookies = BTreeMap::new();
+    for header in headers.get_all(COOKIE) {
+        let header = header.to_str().map_err(|_| {
+            HttpError::for_bad_request(
+                None,
+                String::from("value of header \"cookie\" is not valid ASCII"),
+            )
+        })?;
+        for pair in header.split(';') {
+            let pair = pair.trim();
+            if pair.is_empty() {
+                continue;
+            }
+            let (name, value) = match pair.split_once('=') {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            cookies
+                .entry(name.trim().to_string())
+                .or_insert_with(|| value.to_string());
+        }
+    }
+    Ok(cookies)
+}
+
+/// Parses the `Cookie` headers of a request like [`http_request_cookies`],
+/// keeping only the cookies that `open` accepts with the server's
+/// [`CookieKey`], with the values that it returns.  `extractor` names the
+/// extractor for the error reported when the server has no key.
+fn http_request_cookies_with_key<Context: ServerContext>(
+    rqctx: &RequestContext<Context>,
+    extractor: &str,
+    open: impl Fn(&CookieKey, &str, &str) -> Option<String>,
+) -> Result<BTreeMap<String, String>, HttpError> {
+    let key = rqctx.server.cookie_key().ok_or_else(|| {
+        HttpError::for_internal_error(format!(
+            "{} used without a cookie key configured",
+            extractor
+        ))
+    })?;
+    Ok(http_request_cookies(rqctx.request.headers())?
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let value = open(key, &name, &value)?;
+            Some((name, value))
+        })
+        .collect())
+}
+
+fn cookies_deserialize<CookieType: DeserializeOwned>(
+    cookies: &BTreeMap<String, String>,
+) -> Result<CookieType, HttpError> {
+    from_map(cookies).map_err(|message| {
+        HttpError::for_bad_request(
+            None,
+            format!("unable to parse cookies: {}", message),
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::http_request_cookies;
+    use super::CookieKey;
+    use http::header::COOKIE;
+    use http::HeaderMap;
+    use http::HeaderValue;
+
+    #[test]
+    fn test_cookies_parse() {
+        let mut headers = HeaderMap::new();
+        headers.append(
+            COOKIE,
+            HeaderValue::from_static("session=abc; theme=\"dark\";;"),
+        );
+        headers
+            .append(COOKIE, HeaderValue::from_static("session=def; lang=en"));
+        let cookies = http_request_cookies(&headers).unwrap();
+        assert_eq!(cookies.len(), 3);
+        assert_eq!(cookies["session"], "abc");
+        assert_eq!(cookies["theme"], "dark");
+        assert_eq!(cookies["lang"], "en");
+
+        // Malformed pairs are skipped rather than spoiling the rest.
+        let mut headers = HeaderMap::new();
+        headers.insert(COOKIE, HeaderValue::from_static("stray; session=abc"));
+        let cookies = http_request_cookies(&headers).unwrap();
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies["session"], "abc");
+    }
+
+    #[test]
+    fn test_cookie_key() {
+        let key = CookieKey::new([7u8; 32]).unwrap();
+        let signed = key.sign("session", "user.1234");
+        assert!(signed.starts_with("user.1234."));
+        assert_eq!(
+            key.verify("session", &signed).as_deref(),
+            Some("user.1234")
+        );
+
+        // The signature covers the name and the value, and only the right key
+        // produces it.
+        assert_eq!(key.verify("other", &signed), None);
+        let tampered = signed.replacen("1234", "1235", 1);
+        assert_eq!(key.verify("session", &tampered), None);
+        assert_eq!(key.verify("session", "user"), None);
+        let other_key = CookieKey::new([8u8; 32]).unwrap();
+        assert_eq!(other_key.verify("session", &signed), None);
+    }
+
+    #[test]
+    fn test_cookie_key_hmac() {
+        // Check the signatures against another HMAC-SHA256 implementation,
+        // with keys both shorter and longer than the block size.
+        let key = CookieKey::new([7u8; 32]).unwrap();
+        let expected = [
+            0x08, 0x42, 0xa3, 0x5e, 0xe7, 0xc7, 0xf8, 0xa1, 0x88, 0xc7, 0x98,
+            0x89, 0x58, 0x48, 0xbb, 0xbc, 0x6c, 0x75, 0x4b, 0xac, 0x46, 0xe2,
+            0xa4, 0x8a, 0x70, 0x6c, 0x42, 0x16, 0x5f, 0xd6, 0x92, 0xa9,
+        ];
+        assert_eq!(key.signature("session", "user.1234"), expected);
+
+        let key = CookieKey::new([0xaa; 131]).unwrap();
+        let expected = [
+            0xeb, 0x87, 0x8e, 0x30, 0x26, 0x7a, 0x7d, 0x11, 0x65, 0x94, 0xa0,
+            0xea, 0xb5, 0x14, 0xaf, 0x1d, 0xdc, 0x60, 0xee, 0xa2, 0x11, 0x23,
+            0x67, 0x22, 0xdb, 0xc1, 0x3c, 0xb6, 0x52, 0x51, 0xec, 0x87,
+        ];
+        assert_eq!(key.signature("session", "user.1234"), expected);
+    }
+
+    #[test]
+    fn test_cookie_key_short() {
+        let error = CookieKey::new([7u8; 31]).unwrap_err();
+        assert_eq!(error, "cookie key must be at least 32 bytes, got 31");
+    }
+
+    #[test]
+    fn test_cookie_key_encrypt() {
+        let key = CookieKey::new([7u8; 32]).unwrap();
+        let encrypted = key.encrypt("session", "user.1234");
+        assert!(!encrypted.contains("1234"));
+        assert_ne!(encrypted, key.encrypt("session", "user.1234"));
+        assert_eq!(
+            key.decrypt("session", &encrypted).as_deref(),
+            Some("user.1234")
+        );
+
+        // The value is bound to the cookie's name, can't be altered, and
+        // can only be decrypted with the right key.
+        assert_eq!(key.decrypt("other", &encrypted), None);
+        let mut tampered = encrypted.into_bytes();
+        let last = tampered.len() - 1;
+        tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+        let tampered = String::from_utf8(tampered).unwrap();
+        assert_eq!(key.decrypt("session", &tampered), None);
+        assert_eq!(key.decrypt("session", "user"), None);
+        let encrypted = key.encrypt("session", "user.1234");
+        let other_key = CookieKey::new([8u8; 32]).unwrap();
+        assert_eq!(other_key.decrypt("session", &encrypted), None);
+
+        // Signed and encrypted values aren't interchangeable.
+        let signed = key.sign("session", "user.1234");
+        assert_eq!(key.decrypt("session", &signed), None);
+        assert_eq!(key.verify("session", &encrypted), None);
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Header-related extractor(s)
+
+use super::metadata::get_metadata;
+use crate::api_description::ApiEndpointBodyContentType;
+use crate::api_description::ApiEndpointParameterLocation;
+use crate::api_description::ApiEndpointParameterMetadata;
+use crate::error::HttpError;
+use crate::from_map::from_map;
+use crate::server::ServerContext;
+use crate::ExtractorMetadata;
+use crate::RequestContext;
+use crate::SharedExtractor;
+use async_trait::async_trait;
+use http::HeaderMap;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+/// `Header<HeaderType>` is an extractor used to deserialize an instance of
+/// `HeaderType` from an HTTP request's headers.  `HeaderType` is any structure
+/// of yours that implements `serde::Deserialize`, with one field per header.
+///
+/// Header names are case-insensitive, and Dropshot presents them in lowercase,
+/// so fields should be named accordingly.  Most header names contain hyphens,
+/// which you can accommodate with `#[serde(rename_all = "kebab-case")]`.  As
+/// with other extractors, a header is optional if its field is an `Option`; a
+/// request that lacks a required header or includes one that cannot be parsed
+/// fails with a 400 error.
+#[derive(Debug)]
+pub struct Header<HeaderType: JsonSchema + Send + Sync> {
+    inner: HeaderType,
+}
+
+impl<HeaderType: JsonSchema + Send + Sync> Header<HeaderType> {
+    pub fn into_inner(self) -> HeaderType {
+        self.inner
+    }
+}
+
+/// Given the headers of an HTTP request, attempt to deserialize them as an
+/// instance of `HeaderType`.
+fn http_request_load_headers<HeaderType>(
+    headers: &HeaderMap,
+) -> Result<Header<HeaderType>, HttpError>
+where
+    HeaderType: DeserializeOwned + JsonSchema + Send + Sync,
+{
+    // Combine the values of any header that appears more than once, as
+    // permitted by RFC 9110.
+    let mut values = BTreeMap::<String, String>::new();
+    for (name, value) in headers {
+        let value = match value.to_str() {
+            Ok(value) => value,
+            // Requests carry plenty of headers that `HeaderType` doesn't
+            // care about (e.g., a browser's Referer), and those may contain
+            // any bytes at all.
+            Err(_) if !header_declared::<HeaderType>(name.as_str()) => continue,
+            Err(_) => {
+                return Err(HttpError::for_bad_request(
+                    None,
+                    format!("value of header \"{}\" is not valid ASCII", name),
+                ))
+            }
+        };
+        values
+            .entry(name.as_str().to_string())
+            .and_modify(|existing| {
+                existing.push_str(", ");
+                existing.push_str(value);
+            })
+            .or_insert_with(|| value.to_string());
+    }
+
+    from_map(&values).map(|inner| Header { inner }).map_err(|message| {
+        HttpError::for_bad_request(
+            None,
+            format!("unable to parse headers: {}", message),
+        )
+    })
+}
+
+/// Returns whether `HeaderType` has a field for the header called `name`.
+fn header_declared<HeaderType: JsonSchema>(name: &str) -> bool {
+    get_metadata::<HeaderType>(&ApiEndpointParameterLocation::Header)
+        .parameters
+        .iter()
+        .any(|parameter| match &parameter.metadata {
+            ApiEndpointParameterMetadata::Header(declared) => {
+                declared.eq_ignore_ascii_case(name)
+            }
+            _ => false,
+        })
+}
+
+// The `SharedExtractor` implementation for Header<HeaderType> describes how to
+// construct an instance of `Header<HeaderType>` from an HTTP request: namely,
+// by deserializing the request's headers to an instance of `HeaderType`.
+#[async_trait]
+impl<HeaderType> SharedExtractor for Header<HeaderType>
+where
+    HeaderType: DeserializeOwned + JsonSchema + Send + Sync + 'static,
+{
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+    ) -> Result<Header<HeaderType>, HttpError> {
+        http_request_load_headers(rqctx.request.headers())
+    }
+
+    fn metadata(
+        _body_content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        get_metadata::<HeaderType>(&ApiEndpointParameterLocation::Header)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::http_request_load_headers;
+    use http::HeaderMap;
+    use http::HeaderValue;
+    use http::StatusCode;
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, JsonSchema)]
+    #[serde(rename_all = "kebab-case")]
+    struct Headers {
+        x_api_version: u16,
+        accept_language: Option<String>,
+    }
+
+    #[test]
+    fn test_load_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Api-Version", HeaderValue::from_static("3"));
+        headers.append("accept-language", HeaderValue::from_static("en"));
+        headers.append("accept-language", HeaderValue::from_static("fr"));
+        let loaded = http_request_load_headers::<Headers>(&headers)
+            .unwrap()
+            .into_inner();
+        assert_eq!(loaded.x_api_version, 3);
+        assert_eq!(loaded.accept_language.as_deref(), Some("en, fr"));
+
+        headers.remove("accept-language");
+        let loaded = http_request_load_headers::<Headers>(&headers)
+            .unwrap()
+            .into_inner();
+        assert_eq!(loaded.accept_language, None);
+    }
+
+    #[test]
+    fn test_load_headers_bad() {
+        let headers = HeaderMap::new();
+        let error = http_request_load_headers::<Headers>(&headers).unwrap_err();
+        assert_eq!(error.status_code, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            error.external_message,
+            "unable to parse headers: missing field `x-api-version`"
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-version", HeaderValue::from_static("three"));
+        let error = http_request_load_headers::<Headers>(&headers).unwrap_err();
+        assert_eq!(error.status_code, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_load_headers_not_ascii() {
+        // A header that isn't declared may hold anything.
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-version", HeaderValue::from_static("3"));
+        headers.insert(
+            "referer",
+            HeaderValue::from_bytes(b"https://example.com/caf\xe9").unwrap(),
+        );
+        let loaded = http_request_load_headers::<Headers>(&headers)
+            .unwrap()
+            .into_inner();
+        assert_eq!(loaded.x_api_version, 3);
+
+        // A declared header must be valid.
+        headers.insert(
+            "accept-language",
+            HeaderValue::from_bytes(b"fran\xe7ais").unwrap(),
+        );
+        let error = http_request_load_headers::<Headers>(&headers).unwrap_err();
+        assert_eq!(error.status_code, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            error.external_message,
+            "value of header \"accept-language\" is not valid ASCII"
+        );
+    }
+}
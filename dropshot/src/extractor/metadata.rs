@@ -1,7 +1,9 @@
 // Copyright 2023 Oxide Computer Company
 
 use crate::api_description::ApiSchemaGenerator;
+use crate::pagination::PAGINATION_PARAMS;
 use crate::pagination::PAGINATION_PARAM_SENTINEL;
+use crate::pagination::SCAN_PARAM_NOTE;
 use crate::schema_util::schema2struct;
 use crate::schema_util::schema_extensions;
 use crate::schema_util::ReferenceVisitor;
@@ -55,10 +57,27 @@ where
             let mut visitor = ReferenceVisitor::new(&generator);
             schemars::visit::visit_schema(&mut visitor, &mut s);
 
+            // The scan parameters of a paginated endpoint are only used to
+            // begin a scan; say so, since the schema can't express it.
+            let description = match &extension_mode {
+                ExtensionMode::Paginated(_)
+                    if !PAGINATION_PARAMS
+                        .contains(&struct_member.name.as_str()) =>
+                {
+                    Some(match struct_member.description {
+                        Some(description) => {
+                            format!("{}\n\n{}", description, SCAN_PARAM_NOTE)
+                        }
+                        None => SCAN_PARAM_NOTE.to_string(),
+                    })
+                }
+                _ => struct_member.description,
+            };
+
             ApiEndpointParameter::new_named(
                 loc,
                 struct_member.name,
-                struct_member.description,
+                description,
                 struct_member.required,
                 ApiSchemaGenerator::Static {
                     schema: Box::new(s),
@@ -69,7 +88,7 @@ where
         })
         .collect::<Vec<_>>();
 
-    ExtractorMetadata { extension_mode, parameters }
+    ExtractorMetadata { extension_mode, parameters, security: vec![] }
 }
 
 #[cfg(test)]
@@ -84,6 +103,7 @@ mod test {
 
     use super::get_metadata;
     use super::ExtractorMetadata;
+    use crate::pagination::SCAN_PARAM_NOTE;
 
     #[derive(Deserialize, Serialize, JsonSchema)]
     #[allow(dead_code)]
@@ -195,4 +215,33 @@ mod test {
             expected,
         );
     }
+
+    #[test]
+    fn test_metadata_pagination_descriptions() {
+        let params = get_metadata::<PaginationParams<A, A>>(
+            &ApiEndpointParameterLocation::Query,
+        );
+        let descriptions = params
+            .parameters
+            .iter()
+            .map(|param| param.description.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            descriptions,
+            vec![
+                Some(SCAN_PARAM_NOTE),
+                Some(SCAN_PARAM_NOTE),
+                Some(SCAN_PARAM_NOTE),
+                Some("Maximum number of items returned by a single call"),
+                Some(
+                    "Token returned by previous call to retrieve the \
+                     subsequent page"
+                ),
+            ]
+        );
+
+        // Parameters of endpoints that aren't paginated are unaffected.
+        let params = get_metadata::<A>(&ApiEndpointParameterLocation::Query);
+        assert!(params.parameters.iter().all(|p| p.description.is_none()));
+    }
 }
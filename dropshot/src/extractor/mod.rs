@@ -10,18 +10,70 @@ pub use common::ExtractorMetadata;
 pub use common::RequestExtractor;
 pub use common::SharedExtractor;
 
+mod accept_language;
+pub use accept_language::AcceptLanguage;
+pub use accept_language::LanguagePreference;
+
+mod bearer_token;
+pub use bearer_token::BearerToken;
+
 mod body;
+#[cfg(feature = "cbor")]
+pub use body::CborBody;
+pub use body::FormBody;
+#[cfg(feature = "msgpack")]
+pub use body::MsgPackBody;
 pub use body::StreamingBody;
 pub use body::TypedBody;
 pub use body::UntypedBody;
 
+mod client_cert;
+pub use client_cert::ClientCert;
+
+mod cookie;
+pub use cookie::Cookie;
+pub use cookie::CookieKey;
+pub use cookie::EncryptedCookie;
+pub use cookie::SignedCookie;
+
+mod header;
+pub use header::Header;
+
 mod metadata;
 
+mod multipart;
+pub use multipart::Multipart;
+pub use multipart::MultipartPart;
+
 mod path;
 pub use path::Path;
 
+mod preconditions;
+pub(crate) use preconditions::http_request_if_none_match;
+pub use preconditions::EntityTag;
+pub use preconditions::EntityTagMatch;
+pub use preconditions::Preconditions;
+
 mod query;
 pub use query::Query;
 
+mod range;
+pub use range::ByteRange;
+pub use range::RangeRequest;
+
+mod remote_addr;
+pub(crate) use remote_addr::forwarded_param;
+pub(crate) use remote_addr::is_trusted_proxy;
+pub(crate) use remote_addr::trusted_forwarded_element;
+pub use remote_addr::RemoteAddr;
+
+mod raw_query;
+pub use raw_query::RawQuery;
+
 mod raw_request;
 pub use raw_request::RawRequest;
+
+mod validated;
+pub use validated::Validate;
+pub use validated::Validated;
+pub use validated::ValidationErrors;
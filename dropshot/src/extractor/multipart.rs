@@ -0,0 +1,728 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Multipart form-data extractor
+
+use crate::api_description::ApiEndpointBodyContentType;
+use crate::api_description::ApiEndpointParameter;
+use crate::api_description::ApiSchemaGenerator;
+use crate::api_description::ExtensionMode;
+use crate::error::HttpError;
+use crate::http_util::http_content_length;
+use crate::http_util::http_dump_body;
+use crate::http_util::CountingBody;
+use crate::http_util::CONTENT_TYPE_MULTIPART_FORM_DATA;
+use crate::server::ServerContext;
+use crate::ExclusiveExtractor;
+use crate::ExtractorMetadata;
+use crate::RequestContext;
+use async_trait::async_trait;
+use bytes::Buf;
+use bytes::Bytes;
+use bytes::BytesMut;
+use http::header::HeaderName;
+use http::header::CONTENT_DISPOSITION;
+use http::header::CONTENT_TYPE;
+use http::HeaderMap;
+use http::HeaderValue;
+use hyper::body::HttpBody;
+use percent_encoding::percent_decode_str;
+use schemars::schema::InstanceType;
+use schemars::schema::SchemaObject;
+
+/// largest block of headers accepted for a single part
+const PART_HEADERS_MAX_BYTES: usize = 8 * 1024;
+
+/// `Multipart` is an extractor for `multipart/form-data` request bodies, as
+/// submitted by HTML forms that upload files.  It reads the body incrementally:
+/// [`Multipart::next_part`] returns each part in turn, and the contents of a
+/// part can be read a chunk at a time, so that large uploads need not be
+/// buffered in memory.
+///
+/// The whole body is subject to the request body limit (see
+/// [`RequestContext::request_body_max_bytes`]), and each part may be further
+/// limited with [`Multipart::part_max_bytes`].
+///
+/// ```no_run
+/// use dropshot::HttpError;
+/// use dropshot::Multipart;
+///
+/// async fn save_uploads(multipart: Multipart) -> Result<(), HttpError> {
+///     let mut multipart = multipart.part_max_bytes(1024 * 1024);
+///     while let Some(mut part) = multipart.next_part().await? {
+///         let name = part.name().unwrap_or_default().to_string();
+///         let mut nbytes = 0;
+///         while let Some(chunk) = part.chunk().await? {
+///             // write `chunk` somewhere
+///             nbytes += chunk.len();
+///         }
+///         println!("part {:?}: {} bytes", name, nbytes);
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Multipart {
+    body: CountingBody,
+    cap: usize,
+    /// length declared by the request's Content-Length header, if any
+    content_length: Option<usize>,
+    bytes_read: usize,
+    /// "\r\n--" followed by the boundary
+    delimiter: Vec<u8>,
+    /// data read from the body but not yet consumed
+    buffer: BytesMut,
+    eof: bool,
+    state: MultipartState,
+    part_max_bytes: usize,
+}
+
+#[derive(Debug)]
+enum MultipartState {
+    /// before the first delimiter
+    Preamble,
+    /// after a delimiter, at the headers of a part
+    Headers,
+    /// within the body of a part, having read this many bytes of it
+    Body(usize),
+    /// after the final delimiter
+    Done,
+}
+
+impl Multipart {
+    fn new(
+        body: CountingBody,
+        boundary: &str,
+        cap: usize,
+        content_length: Option<usize>,
+    ) -> Self {
+        // The first delimiter need not be preceded by a line break, so start
+        // with one in the buffer.
+        let mut buffer = BytesMut::new();
+        buffer.extend_from_slice(b"\r\n");
+        Multipart {
+            body,
+            cap,
+            content_length,
+            bytes_read: 0,
+            delimiter: format!("\r\n--{}", boundary).into_bytes(),
+            buffer,
+            eof: false,
+            state: MultipartState::Preamble,
+            part_max_bytes: usize::MAX,
+        }
+    }
+
+    /// Limits the size of the contents of each part.  Reading more than
+    /// `part_max_bytes` from a part produces a 413 ("Payload Too Large")
+    /// error.
+    pub fn part_max_bytes(mut self, part_max_bytes: usize) -> Self {
+        self.part_max_bytes = part_max_bytes;
+        self
+    }
+
+    /// Returns the next part of the body, or `None` after the last one.  Any
+    /// contents of the previous part that have not been read are skipped.
+    pub async fn next_part(
+        &mut self,
+    ) -> Result<Option<MultipartPart<'_>>, HttpError> {
+        loop {
+            match self.state {
+                MultipartState::Preamble | MultipartState::Body(_) => {
+                    self.skip_to_delimiter().await?;
+                }
+                MultipartState::Headers => {
+                    let headers = self.read_headers().await?;
+                    self.state = MultipartState::Body(0);
+                    return MultipartPart::new(self, headers).map(Some);
+                }
+                MultipartState::Done => return Ok(None),
+            }
+        }
+    }
+
+    /// Discards data up to and including the next delimiter, and determines
+    /// from what follows it whether another part follows.
+    async fn skip_to_delimiter(&mut self) -> Result<(), HttpError> {
+        loop {
+            if let Some(index) = find(&self.buffer, &self.delimiter) {
+                // A delimiter is followed by "--" if it's the last one, or
+                // optional whitespace and a line break if not.
+                let after = index + self.delimiter.len();
+                let rest = &self.buffer[after..];
+                if rest.starts_with(b"--") {
+                    self.buffer.clear();
+                    self.state = MultipartState::Done;
+                    // Read (and ignore) the epilogue, so that the body's
+                    // length and size limit are checked.
+                    while self.fill().await? {
+                        self.buffer.clear();
+                    }
+                    return Ok(());
+                }
+                let padding = rest
+                    .iter()
+                    .take_while(|b| **b == b' ' || **b == b'\t')
+                    .count();
+                let rest = &rest[padding..];
+                if rest.starts_with(b"\r\n") {
+                    self.buffer.advance(after + padding + 2);
+                    self.state = MultipartState::Headers;
+                    return Ok(());
+                }
+                if rest.len() < 2 {
+                    // Not enough data yet to tell.
+                    if !self.fill().await? {
+                        return Err(bad_multipart("unexpected end of body"));
+                    }
+                    continue;
+                }
+                return Err(bad_multipart("malformed delimiter"));
+            }
+
+            // Keep what could be the start of a delimiter.
+            let keep = self.delimiter.len() - 1;
+            if self.buffer.len() > keep {
+                self.buffer.advance(self.buffer.len() - keep);
+            }
+            if !self.fill().await? {
+                return Err(bad_multipart("unexpected end of body"));
+            }
+        }
+    }
+
+    async fn read_headers(&mut self) -> Result<HeaderMap, HttpError> {
+        let end = loop {
+            if self.buffer.starts_with(b"\r\n") {
+                break 0;
+            }
+            if let Some(index) = find(&self.buffer, b"\r\n\r\n") {
+                break index + 2;
+            }
+            if self.buffer.len() > PART_HEADERS_MAX_BYTES {
+                return Err(bad_multipart("part headers too large"));
+            }
+            if !self.fill().await? {
+                return Err(bad_multipart("unexpected end of body"));
+            }
+        };
+        let block = self.buffer.split_to(end);
+        self.buffer.advance(2);
+
+        let mut headers = HeaderMap::new();
+        for line in block.split(|b| *b == b'\n') {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            if line.is_empty() {
+                continue;
+            }
+            let colon = line
+                .iter()
+                .position(|b| *b == b':')
+                .ok_or_else(|| bad_multipart("malformed part header"))?;
+            let name = HeaderName::from_bytes(&line[..colon])
+                .map_err(|_| bad_multipart("malformed part header"))?;
+            let value = HeaderValue::from_bytes(trim(&line[colon + 1..]))
+                .map_err(|_| bad_multipart("malformed part header"))?;
+            headers.append(name, value);
+        }
+        Ok(headers)
+    }
+
+    /// Returns the next chunk of the current part's contents, or `None` at the
+    /// end of the part.
+    async fn part_chunk(&mut self) -> Result<Option<Bytes>, HttpError> {
+        let read = match self.state {
+            MultipartState::Body(read) => read,
+            _ => return Ok(None),
+        };
+        loop {
+            let available = match find(&self.buffer, &self.delimiter) {
+                Some(0) => return Ok(None),
+                Some(index) => index,
+                // Hold back what could be the start of a delimiter.
+                None => {
+                    self.buffer.len().saturating_sub(self.delimiter.len() - 1)
+                }
+            };
+            if available > 0 {
+                let chunk = self.buffer.split_to(available).freeze();
+                let read = read + chunk.len();
+                if read > self.part_max_bytes {
+                    return Err(HttpError::for_client_error(
+                        None,
+                        http::StatusCode::PAYLOAD_TOO_LARGE,
+                        format!(
+                            "multipart part exceeded maximum size of {} bytes",
+                            self.part_max_bytes
+                        ),
+                    ));
+                }
+                self.state = MultipartState::Body(read);
+                return Ok(Some(chunk));
+            }
+            if !self.fill().await? {
+                return Err(bad_multipart("unexpected end of body"));
+            }
+        }
+    }
+
+    /// Reads more of the body into the buffer.  Returns `false` at the end of
+    /// the body.
+    async fn fill(&mut self) -> Result<bool, HttpError> {
+        if self.eof {
+            return Ok(false);
+        }
+        match self.body.data().await {
+            Some(buf) => {
+                let buf = buf?;
+                if self.bytes_read + buf.len() > self.cap {
+                    http_dump_body(&mut self.body).await?;
+                    self.eof = true;
+                    return Err(HttpError::for_bad_request(
+                        None,
+                        format!(
+                            "request body exceeded maximum size of {} bytes",
+                            self.cap
+                        ),
+                    ));
+                }
+                self.bytes_read += buf.len();
+                self.buffer.extend_from_slice(&buf);
+                Ok(true)
+            }
+            None => {
+                self.eof = true;
+                if let Some(content_length) = self.content_length {
+                    if self.bytes_read != content_length {
+                        return Err(HttpError::for_bad_request(
+                            None,
+                            format!(
+                                "request body length ({} bytes) does not match \
+                                 Content-Length header ({} bytes)",
+                                self.bytes_read, content_length
+                            ),
+                        ));
+                    }
+                }
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// One part of a `multipart/form-data` body, returned by
+/// [`Multipart::next_part`]
+#[derive(Debug)]
+pub struct MultipartPart<'a> {
+    multipart: &'a mut Multipart,
+    headers: HeaderMap,
+    name: Option<String>,
+    file_name: Option<String>,
+}
+
+impl<'a> MultipartPart<'a> {
+    fn new(
+        multipart: &'a mut Multipart,
+        headers: HeaderMap,
+    ) -> Result<Self, HttpError> {
+        let mut name = None;
+        let mut file_name = None;
+        let mut ext_file_name = None;
+        if let Some(disposition) = headers.get(CONTENT_DISPOSITION) {
+            // Clients commonly send names in UTF-8, which `to_str` rejects.
+            let params = std::str::from_utf8(disposition.as_bytes())
+                .ok()
+                .and_then(disposition_params)
+                .ok_or_else(|| {
+                    bad_multipart("malformed Content-Disposition header")
+                })?;
+            for (key, value) in params {
+                match key.as_str() {
+                    "name" => name = Some(value),
+                    "filename" => file_name = Some(value),
+                    "filename*" => ext_file_name = decode_ext_value(&value),
+                    _ => (),
+                }
+            }
+        }
+        // RFC 6266 prefers the extended form of the file name, if understood.
+        let file_name = ext_file_name.or(file_name);
+        Ok(MultipartPart { multipart, headers, name, file_name })
+    }
+
+    /// Returns the headers of this part.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Returns the name of the form field, from the part's
+    /// `Content-Disposition` header.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the name of the uploaded file, if the part is a file upload.
+    /// This comes from the client and should not be trusted as a path.
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
+    /// Returns the part's `Content-Type`, if it has one.
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok())
+    }
+
+    /// Returns the next chunk of the part's contents, or `None` once they have
+    /// all been read.
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, HttpError> {
+        self.multipart.part_chunk().await
+    }
+
+    /// Reads the rest of the part's contents into memory.
+    pub async fn bytes(mut self) -> Result<Bytes, HttpError> {
+        let mut contents = BytesMut::new();
+        while let Some(chunk) = self.chunk().await? {
+            contents.extend_from_slice(&chunk);
+        }
+        Ok(contents.freeze())
+    }
+
+    /// Reads the rest of the part's contents as a UTF-8 string.
+    pub async fn text(self) -> Result<String, HttpError> {
+        let bytes = self.bytes().await?;
+        String::from_utf8(bytes.to_vec()).map_err(|e| {
+            HttpError::for_bad_request(
+                None,
+                format!(
+                    "failed to parse multipart part as UTF-8 string: {}",
+                    e
+                ),
+            )
+        })
+    }
+}
+
+#[async_trait]
+impl ExclusiveExtractor for Multipart {
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+        request: hyper::Request<hyper::Body>,
+    ) -> Result<Multipart, HttpError> {
+        let content_type = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("");
+        let mut params = content_type.split(';');
+        let mime_type = params.next().unwrap_or("").trim().to_lowercase();
+        if mime_type != CONTENT_TYPE_MULTIPART_FORM_DATA {
+            return Err(HttpError::for_bad_request(
+                None,
+                format!(
+                    "expected content type \"{}\", got \"{}\"",
+                    CONTENT_TYPE_MULTIPART_FORM_DATA, mime_type
+                ),
+            ));
+        }
+        let boundary = params
+            .filter_map(|param| param.split_once('='))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case("boundary"))
+            .map(|(_, value)| {
+                let value = value.trim();
+                value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(value)
+                    .to_string()
+            })
+            .filter(|boundary| !boundary.is_empty() && boundary.len() <= 70)
+            .ok_or_else(|| {
+                bad_multipart("missing or invalid boundary in content type")
+            })?;
+
+        let content_length = http_content_length(request.headers())?;
+        let cap = rqctx.request_body_max_bytes();
+        if let Some(content_length) = content_length {
+            if content_length > cap {
+                return Err(HttpError::for_bad_request(
+                    None,
+                    format!(
+                        "request body exceeded maximum size of {} bytes",
+                        cap
+                    ),
+                ));
+            }
+        }
+        let (_, body) = CountingBody::from_request(request);
+        Ok(Multipart::new(body, &boundary, cap, content_length))
+    }
+
+    fn metadata(
+        _content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        ExtractorMetadata {
+            parameters: vec![ApiEndpointParameter::new_body(
+                ApiEndpointBodyContentType::MultipartFormData,
+                true,
+                ApiSchemaGenerator::Static {
+                    schema: Box::new(
+                        SchemaObject {
+                            instance_type: Some(InstanceType::Object.into()),
+                            ..Default::default()
+                        }
+                        .into(),
+                    ),
+                    dependencies: indexmap::IndexMap::default(),
+                },
+                vec![],
+            )],
+            extension_mode: ExtensionMode::None,
+            security: vec![],
+        }
+    }
+}
+
+fn bad_multipart(message: &str) -> HttpError {
+    HttpError::for_bad_request(
+        None,
+        format!("unable to parse multipart body: {}", message),
+    )
+}
+
+/// Returns the parameters of a `Content-Disposition` header value (RFC 6266,
+/// as used by RFC 7578), with names lowercased and quoted-string values
+/// unquoted, or `None` if the value is malformed.  Unlike a naive split on
+/// `;`, this allows quoted values to contain `;`, `=`, and escaped quotes.
+fn disposition_params(value: &str) -> Option<Vec<(String, String)>> {
+    let is_token_char =
+        |c: char| c.is_ascii_graphic() && !"()<>@,;:\\\"/[]?={}".contains(c);
+
+    let mut params = Vec::new();
+    let rest = value.trim_start();
+    let type_len = rest.find(|c| !is_token_char(c)).unwrap_or(rest.len());
+    if type_len == 0 {
+        return None;
+    }
+    let mut rest = rest[type_len..].trim_start();
+    while !rest.is_empty() {
+        rest = rest.strip_prefix(';')?.trim_start();
+        if rest.is_empty() {
+            // Tolerate a trailing ";".
+            break;
+        }
+        let name_len = rest.find(|c| !is_token_char(c)).unwrap_or(rest.len());
+        if name_len == 0 {
+            return None;
+        }
+        let name = rest[..name_len].to_ascii_lowercase();
+        rest = rest[name_len..].trim_start().strip_prefix('=')?.trim_start();
+
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            let end = loop {
+                match chars.next()? {
+                    (i, '"') => break i,
+                    (_, '\\') => value.push(chars.next()?.1),
+                    (_, c) => value.push(c),
+                }
+            };
+            rest = &quoted[end + 1..];
+            value
+        } else {
+            let value_len =
+                rest.find(|c| !is_token_char(c)).unwrap_or(rest.len());
+            if value_len == 0 {
+                return None;
+            }
+            let value = rest[..value_len].to_string();
+            rest = &rest[value_len..];
+            value
+        };
+        params.push((name, value));
+        rest = rest.trim_start();
+    }
+    Some(params)
+}
+
+/// Decodes an RFC 8187 extended parameter value like `UTF-8''a%20b.txt`,
+/// returning `None` if it's malformed or in a character set other than UTF-8.
+fn decode_ext_value(value: &str) -> Option<String> {
+    let (charset, rest) = value.split_once('\'')?;
+    let (_language, encoded) = rest.split_once('\'')?;
+    if !charset.eq_ignore_ascii_case("utf-8") {
+        return None;
+    }
+    percent_decode_str(encoded).decode_utf8().ok().map(|s| s.into_owned())
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+fn trim(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    let end = bytes
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map_or(start, |i| i + 1);
+    &bytes[start..end]
+}
+
+#[cfg(test)]
+mod test {
+    use super::disposition_params;
+    use super::Multipart;
+    use futures::stream;
+    use http::StatusCode;
+    use std::convert::Infallible;
+
+    const BODY: &str = "preamble\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\
+        \r\n\
+        hello\r\n\
+        --XyZ  \r\n\
+        Content-Disposition: form-data; name=\"upload\"; filename=\"a.txt\"\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        line one\r\n--Xy\r\n\
+        \r\n\
+        --XyZ--\r\n\
+        epilogue";
+
+    /// Returns a `Multipart` that receives `body` in chunks of `chunk_size`
+    /// bytes, to exercise delimiters that span chunks.
+    fn multipart(body: &str, chunk_size: usize) -> Multipart {
+        let chunks = body
+            .as_bytes()
+            .chunks(chunk_size)
+            .map(|c| Ok::<_, Infallible>(c.to_vec()))
+            .collect::<Vec<_>>();
+        let body = hyper::Body::wrap_stream(stream::iter(chunks));
+        Multipart::new(body.into(), "XyZ", 4096, None)
+    }
+
+    #[tokio::test]
+    async fn test_multipart() {
+        for chunk_size in [1, 2, 3, 7, 16, 1024] {
+            let mut multipart = multipart(BODY, chunk_size);
+
+            let part = multipart.next_part().await.unwrap().unwrap();
+            assert_eq!(part.name(), Some("title"));
+            assert_eq!(part.file_name(), None);
+            assert_eq!(part.text().await.unwrap(), "hello");
+
+            let part = multipart.next_part().await.unwrap().unwrap();
+            assert_eq!(part.name(), Some("upload"));
+            assert_eq!(part.file_name(), Some("a.txt"));
+            assert_eq!(part.content_type(), Some("text/plain"));
+            assert_eq!(part.text().await.unwrap(), "line one\r\n--Xy\r\n");
+
+            assert!(multipart.next_part().await.unwrap().is_none());
+            assert!(multipart.next_part().await.unwrap().is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multipart_skip_part() {
+        let mut multipart = multipart(BODY, 5);
+        let part = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!(part.name(), Some("title"));
+        drop(part);
+        // The unread contents of the first part are skipped.
+        let part = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!(part.name(), Some("upload"));
+        drop(part);
+        assert!(multipart.next_part().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_multipart_limits() {
+        let mut multipart = multipart(BODY, 1024).part_max_bytes(8);
+        let part = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!(part.text().await.unwrap(), "hello");
+        let part = multipart.next_part().await.unwrap().unwrap();
+        let error = part.bytes().await.unwrap_err();
+        assert_eq!(error.status_code, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(
+            error.external_message,
+            "multipart part exceeded maximum size of 8 bytes"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_multipart_truncated() {
+        let truncated = &BODY[..BODY.find("--XyZ--").unwrap()];
+        let mut multipart = multipart(truncated, 16);
+        multipart.next_part().await.unwrap().unwrap();
+        let part = multipart.next_part().await.unwrap().unwrap();
+        let error = part.bytes().await.unwrap_err();
+        assert_eq!(
+            error.external_message,
+            "unable to parse multipart body: unexpected end of body"
+        );
+    }
+
+    #[test]
+    fn test_disposition_params() {
+        let params = |value: &str| {
+            disposition_params(value).map(|params| {
+                params
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(
+            params("form-data; name=\"title\"").unwrap(),
+            ["name=title"]
+        );
+        assert_eq!(
+            params("form-data;NAME=title ; filename = \"a.txt\";").unwrap(),
+            ["name=title", "filename=a.txt"]
+        );
+        // Quoted values may contain separators and escaped quotes.
+        assert_eq!(
+            params(r#"form-data; name="a;b"; filename="x=\"y\".txt""#).unwrap(),
+            ["name=a;b", r#"filename=x="y".txt"#]
+        );
+        assert_eq!(
+            params("form-data; filename*=UTF-8''%C3%A9.txt").unwrap(),
+            ["filename*=UTF-8''%C3%A9.txt"]
+        );
+
+        assert_eq!(params(""), None);
+        assert_eq!(params("form-data; name"), None);
+        assert_eq!(params("form-data; name=\"title"), None);
+        assert_eq!(params("form-data; name=\"a\" b"), None);
+        assert_eq!(params("form-data; name=a b"), None);
+    }
+
+    #[tokio::test]
+    async fn test_multipart_disposition() {
+        let body = "--XyZ\r\n\
+            Content-Disposition: form-data; name=\"a;b\"; \
+            filename=\"x.txt\"; filename*=UTF-8''%C3%A9.txt\r\n\
+            \r\n\
+            hello\r\n\
+            --XyZ\r\n\
+            Content-Disposition: form-data; name=\"unterminated\r\n\
+            \r\n\
+            hello\r\n\
+            --XyZ--\r\n";
+        let mut multipart = multipart(body, 1024);
+        let part = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!(part.name(), Some("a;b"));
+        assert_eq!(part.file_name(), Some("\u{e9}.txt"));
+        drop(part);
+        let error = multipart.next_part().await.unwrap_err();
+        assert_eq!(
+            error.external_message,
+            "unable to parse multipart body: malformed Content-Disposition \
+             header"
+        );
+    }
+}
@@ -11,6 +11,8 @@ use crate::server::ServerContext;
 use crate::ExtractorMetadata;
 use crate::RequestContext;
 use crate::SharedExtractor;
+use crate::Validate;
+use crate::Validated;
 use async_trait::async_trait;
 use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
@@ -33,8 +35,8 @@ impl<PathType: JsonSchema + Send + Sync> Path<PathType> {
 }
 
 // The `SharedExtractor` implementation for Path<PathType> describes how to
-// construct an instance of `Path<QueryType>` from an HTTP request: namely, by
-// extracting parameters from the query string.
+// construct an instance of `Path<PathType>` from an HTTP request: namely, by
+// extracting parameters from the path variables matched by the router.
 #[async_trait]
 impl<PathType> SharedExtractor for Path<PathType>
 where
@@ -43,7 +45,9 @@ where
     async fn from_request<Context: ServerContext>(
         rqctx: &RequestContext<Context>,
     ) -> Result<Path<PathType>, HttpError> {
-        let params: PathType = http_extract_path_params(&rqctx.path_variables)?;
+        let variables =
+            rqctx.parameter_case.variables_from_wire(&rqctx.path_variables);
+        let params: PathType = http_extract_path_params(&variables)?;
         Ok(Path { inner: params })
     }
 
@@ -53,3 +57,23 @@ where
         get_metadata::<PathType>(&ApiEndpointParameterLocation::Path)
     }
 }
+
+#[async_trait]
+impl<PathType> SharedExtractor for Validated<Path<PathType>>
+where
+    PathType: DeserializeOwned + JsonSchema + Validate + Send + Sync + 'static,
+{
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+    ) -> Result<Validated<Path<PathType>>, HttpError> {
+        let path = Path::<PathType>::from_request(rqctx).await?;
+        path.inner.validate()?;
+        Ok(Validated { inner: path })
+    }
+
+    fn metadata(
+        body_content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        <Path<PathType> as SharedExtractor>::metadata(body_content_type)
+    }
+}
@@ -0,0 +1,413 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Conditional request extractor
+
+use crate::api_description::ApiEndpointBodyContentType;
+use crate::error::HttpError;
+use crate::server::ServerContext;
+use crate::ExtractorMetadata;
+use crate::RequestContext;
+use crate::SharedExtractor;
+use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::Utc;
+use std::fmt;
+use std::str::FromStr;
+
+/// An entity tag identifying one version of a resource, as found in `ETag`,
+/// `If-Match`, and `If-None-Match` headers.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EntityTag {
+    weak: bool,
+    tag: String,
+}
+
+impl EntityTag {
+    /// Returns a strong entity tag, which changes whenever the resource's
+    /// representation changes in any way.  `tag` must not contain `"`.
+    pub fn strong<S: Into<String>>(tag: S) -> Self {
+        EntityTag { weak: false, tag: tag.into() }
+    }
+
+    /// Returns a weak entity tag, which may be shared by representations that
+    /// are semantically equivalent.  `tag` must not contain `"`.
+    pub fn weak<S: Into<String>>(tag: S) -> Self {
+        EntityTag { weak: true, tag: tag.into() }
+    }
+
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    /// Returns the tag itself, without quotes or the weakness indicator.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Two tags match under the strong comparison if neither is weak and
+    /// their tags are identical.
+    pub fn strong_eq(&self, other: &EntityTag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    /// Two tags match under the weak comparison if their tags are identical,
+    /// regardless of whether either is weak.
+    pub fn weak_eq(&self, other: &EntityTag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+impl fmt::Display for EntityTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            write!(f, "W/\"{}\"", self.tag)
+        } else {
+            write!(f, "\"{}\"", self.tag)
+        }
+    }
+}
+
+impl FromStr for EntityTag {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_entity_tags(s)?.as_slice() {
+            [etag] => Ok(etag.clone()),
+            _ => Err(format!("expected a single entity tag: {}", s)),
+        }
+    }
+}
+
+/// The value of an `If-Match` or `If-None-Match` header: either `*`, which
+/// matches any current representation of the resource, or a list of entity
+/// tags.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EntityTagMatch {
+    Any,
+    Tags(Vec<EntityTag>),
+}
+
+impl EntityTagMatch {
+    pub(crate) fn matches(
+        &self,
+        current: &EntityTag,
+        eq: fn(&EntityTag, &EntityTag) -> bool,
+    ) -> bool {
+        match self {
+            EntityTagMatch::Any => true,
+            EntityTagMatch::Tags(tags) => tags.iter().any(|t| eq(t, current)),
+        }
+    }
+}
+
+/// `Preconditions` is an extractor providing the conditional request headers
+/// `If-Match`, `If-None-Match`, and `If-Modified-Since`.  Handlers call
+/// [`Preconditions::evaluate`] with the current version of the resource to
+/// decide whether to proceed.  A request with a malformed `If-Match` or
+/// `If-None-Match` header fails with a 400 ("Bad Request"); a malformed
+/// `If-Modified-Since` header is ignored, as RFC 9110 requires.
+#[derive(Clone, Debug)]
+pub struct Preconditions {
+    method: http::Method,
+    pub if_match: Option<EntityTagMatch>,
+    pub if_none_match: Option<EntityTagMatch>,
+    pub if_modified_since: Option<DateTime<Utc>>,
+}
+
+impl Preconditions {
+    /// Evaluates the preconditions against the current version of the
+    /// resource, identified by its entity tag and last modification time
+    /// (either of which may be unknown), following the order of RFC 9110
+    /// section 13.2.2.
+    ///
+    /// Returns `Ok(())` if the request should proceed.  Otherwise, returns an
+    /// error to be returned from the handler: a 412 ("Precondition Failed"),
+    /// or for a `GET` or `HEAD` request whose client already has the current
+    /// version, a 304 ("Not Modified") carrying the resource's `ETag` and
+    /// `Last-Modified` headers (see [`HttpError::for_not_modified`]).
+    pub fn evaluate(
+        &self,
+        etag: Option<&EntityTag>,
+        last_modified: Option<DateTime<Utc>>,
+    ) -> Result<(), HttpError> {
+        let safe = self.method == http::Method::GET
+            || self.method == http::Method::HEAD;
+
+        if let Some(if_match) = &self.if_match {
+            let matched = etag.map_or(false, |etag| {
+                if_match.matches(etag, EntityTag::strong_eq)
+            });
+            if !matched {
+                return Err(HttpError::for_status(
+                    None,
+                    http::StatusCode::PRECONDITION_FAILED,
+                ));
+            }
+        }
+
+        if let Some(if_none_match) = &self.if_none_match {
+            let matched = etag.map_or(false, |etag| {
+                if_none_match.matches(etag, EntityTag::weak_eq)
+            });
+            if matched {
+                return Err(if safe {
+                    HttpError::for_not_modified(etag, last_modified)
+                } else {
+                    HttpError::for_status(
+                        None,
+                        http::StatusCode::PRECONDITION_FAILED,
+                    )
+                });
+            }
+        } else if let (true, Some(since), Some(modified)) =
+            (safe, self.if_modified_since, last_modified)
+        {
+            // HTTP dates have a resolution of one second.
+            if modified.timestamp() <= since.timestamp() {
+                return Err(HttpError::for_not_modified(etag, last_modified));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SharedExtractor for Preconditions {
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+    ) -> Result<Preconditions, HttpError> {
+        http_request_load_preconditions(
+            rqctx.request.method(),
+            rqctx.request.headers(),
+        )
+    }
+
+    fn metadata(
+        _body_content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        ExtractorMetadata::default()
+    }
+}
+
+fn http_request_load_preconditions(
+    method: &http::Method,
+    headers: &http::HeaderMap,
+) -> Result<Preconditions, HttpError> {
+    Ok(Preconditions {
+        method: method.clone(),
+        if_match: etag_match(headers, http::header::IF_MATCH)?,
+        if_none_match: etag_match(headers, http::header::IF_NONE_MATCH)?,
+        if_modified_since: headers
+            .get(http::header::IF_MODIFIED_SINCE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+            .map(|date| date.with_timezone(&Utc)),
+    })
+}
+
+/// Returns the `If-None-Match` header of a `GET` or `HEAD` request, against
+/// which a response that knows its entity tag is compared to decide whether to
+/// send a 304 ("Not Modified") instead.  The header is ignored for other
+/// methods, and if it's malformed.
+pub(crate) fn http_request_if_none_match(
+    method: &http::Method,
+    headers: &http::HeaderMap,
+) -> Option<EntityTagMatch> {
+    if method != http::Method::GET && method != http::Method::HEAD {
+        return None;
+    }
+    etag_match(headers, http::header::IF_NONE_MATCH).ok().flatten()
+}
+
+/// Parses the `If-Match` or `If-None-Match` header `name`, which may be
+/// repeated.
+fn etag_match(
+    headers: &http::HeaderMap,
+    name: http::header::HeaderName,
+) -> Result<Option<EntityTagMatch>, HttpError> {
+    let values = headers
+        .get_all(&name)
+        .iter()
+        .map(|value| value.to_str().map_err(|e| e.to_string()))
+        .collect::<Result<Vec<_>, _>>();
+    values
+        .and_then(|values| {
+            if values.is_empty() {
+                Ok(None)
+            } else if values.iter().all(|v| v.trim() == "*") {
+                Ok(Some(EntityTagMatch::Any))
+            } else {
+                parse_entity_tags(&values.join(","))
+                    .map(|tags| Some(EntityTagMatch::Tags(tags)))
+            }
+        })
+        .map_err(|message| {
+            HttpError::for_bad_request(
+                None,
+                format!("invalid {} header: {}", name, message),
+            )
+        })
+}
+
+/// Parses a comma-separated list of entity tags, as in
+/// `"xyzzy", W/"r2d2xxxx"`.
+fn parse_entity_tags(s: &str) -> Result<Vec<EntityTag>, String> {
+    let mut tags = Vec::new();
+    let mut rest = s;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        if rest.is_empty() {
+            return Ok(tags);
+        }
+        let weak = rest.starts_with("W/");
+        if weak {
+            rest = &rest[2..];
+        }
+        let end = rest
+            .strip_prefix('"')
+            .and_then(|quoted| quoted.find('"'))
+            .ok_or_else(|| format!("malformed entity tag: {}", rest))?;
+        tags.push(EntityTag { weak, tag: rest[1..end + 1].to_string() });
+        rest = &rest[end + 2..];
+        if !rest.trim_start().is_empty() && !rest.trim_start().starts_with(',')
+        {
+            return Err(format!("malformed entity tag list: {}", s));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::http_request_if_none_match;
+    use super::http_request_load_preconditions;
+    use super::parse_entity_tags;
+    use super::EntityTag;
+    use super::EntityTagMatch;
+    use http::HeaderMap;
+    use http::HeaderValue;
+    use http::Method;
+    use http::StatusCode;
+
+    #[test]
+    fn test_parse_entity_tags() {
+        assert_eq!(
+            parse_entity_tags("\"xyzzy\", W/\"r2d2,xxxx\",\"\"").unwrap(),
+            vec![
+                EntityTag::strong("xyzzy"),
+                EntityTag::weak("r2d2,xxxx"),
+                EntityTag::strong(""),
+            ]
+        );
+        assert_eq!(parse_entity_tags("").unwrap(), vec![]);
+        assert!(parse_entity_tags("xyzzy").is_err());
+        assert!(parse_entity_tags("\"xyzzy").is_err());
+        assert!(parse_entity_tags("\"a\" \"b\"").is_err());
+
+        assert_eq!(EntityTag::weak("abc").to_string(), "W/\"abc\"");
+        assert_eq!(
+            "\"abc\"".parse::<EntityTag>(),
+            Ok(EntityTag::strong("abc"))
+        );
+        assert!("\"a\", \"b\"".parse::<EntityTag>().is_err());
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let current = EntityTag::strong("v2");
+        let mut headers = HeaderMap::new();
+        headers.insert("if-none-match", HeaderValue::from_static("\"v1\""));
+        headers.append("if-none-match", HeaderValue::from_static("W/\"v2\""));
+
+        let get =
+            http_request_load_preconditions(&Method::GET, &headers).unwrap();
+        assert_eq!(
+            get.if_none_match,
+            Some(EntityTagMatch::Tags(vec![
+                EntityTag::strong("v1"),
+                EntityTag::weak("v2"),
+            ]))
+        );
+        let error = get.evaluate(Some(&current), None).unwrap_err();
+        assert_eq!(error.status_code, StatusCode::NOT_MODIFIED);
+        assert_eq!(error.headers.get("etag").unwrap(), "\"v2\"");
+        assert!(error.headers.get("last-modified").is_none());
+        get.evaluate(Some(&EntityTag::strong("v3")), None).unwrap();
+
+        let put =
+            http_request_load_preconditions(&Method::PUT, &headers).unwrap();
+        let error = put.evaluate(Some(&current), None).unwrap_err();
+        assert_eq!(error.status_code, StatusCode::PRECONDITION_FAILED);
+
+        // If-Match uses the strong comparison.
+        let mut headers = HeaderMap::new();
+        headers.insert("if-match", HeaderValue::from_static("W/\"v2\""));
+        let put =
+            http_request_load_preconditions(&Method::PUT, &headers).unwrap();
+        let error = put.evaluate(Some(&current), None).unwrap_err();
+        assert_eq!(error.status_code, StatusCode::PRECONDITION_FAILED);
+
+        headers.insert("if-match", HeaderValue::from_static("*"));
+        let put =
+            http_request_load_preconditions(&Method::PUT, &headers).unwrap();
+        put.evaluate(Some(&current), None).unwrap();
+        let error = put.evaluate(None, None).unwrap_err();
+        assert_eq!(error.status_code, StatusCode::PRECONDITION_FAILED);
+
+        headers.insert("if-match", HeaderValue::from_static("v2"));
+        let error = http_request_load_preconditions(&Method::PUT, &headers)
+            .unwrap_err();
+        assert_eq!(error.status_code, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(http_request_if_none_match(&Method::GET, &headers), None);
+
+        headers.insert("if-none-match", HeaderValue::from_static("W/\"v2\""));
+        assert_eq!(
+            http_request_if_none_match(&Method::HEAD, &headers),
+            Some(EntityTagMatch::Tags(vec![EntityTag::weak("v2")]))
+        );
+        assert_eq!(http_request_if_none_match(&Method::PUT, &headers), None);
+
+        headers.insert("if-none-match", HeaderValue::from_static("v2"));
+        assert_eq!(http_request_if_none_match(&Method::GET, &headers), None);
+    }
+
+    #[test]
+    fn test_evaluate_modified_since() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "if-modified-since",
+            HeaderValue::from_static("Sun, 06 Nov 1994 08:49:37 GMT"),
+        );
+        let get =
+            http_request_load_preconditions(&Method::GET, &headers).unwrap();
+        let since = get.if_modified_since.unwrap();
+
+        let error = get.evaluate(None, Some(since)).unwrap_err();
+        assert_eq!(error.status_code, StatusCode::NOT_MODIFIED);
+        assert_eq!(
+            error.headers.get("last-modified").unwrap(),
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+        get.evaluate(None, Some(since + chrono::Duration::seconds(1))).unwrap();
+        get.evaluate(None, None).unwrap();
+
+        // If-None-Match takes precedence over If-Modified-Since.
+        headers.insert("if-none-match", HeaderValue::from_static("\"v1\""));
+        let get =
+            http_request_load_preconditions(&Method::GET, &headers).unwrap();
+        get.evaluate(Some(&EntityTag::strong("v2")), Some(since)).unwrap();
+
+        // A malformed date is ignored.
+        let mut headers = HeaderMap::new();
+        headers
+            .insert("if-modified-since", HeaderValue::from_static("yesterday"));
+        let get =
+            http_request_load_preconditions(&Method::GET, &headers).unwrap();
+        assert_eq!(get.if_modified_since, None);
+    }
+}
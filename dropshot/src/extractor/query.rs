@@ -5,21 +5,41 @@
 use super::metadata::get_metadata;
 use crate::api_description::ApiEndpointBodyContentType;
 use crate::api_description::ApiEndpointParameterLocation;
+use crate::api_description::ParameterCase;
 use crate::error::HttpError;
+use crate::from_map::from_map;
+use crate::from_map::MapError;
+use crate::from_map::MapValue;
 use crate::server::ServerContext;
 use crate::ExtractorMetadata;
 use crate::RequestContext;
 use crate::RequestInfo;
 use crate::SharedExtractor;
+use crate::Validate;
+use crate::Validated;
 use async_trait::async_trait;
 use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::fmt::Debug;
+use std::fmt::Display;
 
 /// `Query<QueryType>` is an extractor used to deserialize an instance of
 /// `QueryType` from an HTTP request's query string.  `QueryType` is any
 /// structure of yours that implements `serde::Deserialize`.  See this module's
 /// documentation for more information.
+///
+/// A field of type `Vec<T>` collects every occurrence of a repeated parameter,
+/// so `?id=1&id=2` produces `vec![1, 2]`.  Such parameters are described in
+/// the OpenAPI document with the `form` style and `explode: true`.  Fields of
+/// structs included with `#[serde(flatten)]` are received as strings, so they
+/// should be `String`s or types that deserialize from strings.
+///
+/// Query parameters that `QueryType` doesn't accept are ignored unless the
+/// endpoint opts into rejecting them with
+/// [`ApiEndpoint::strict_query`](crate::ApiEndpoint::strict_query) or
+/// [`ApiDescription::strict_query`](crate::ApiDescription::strict_query).
 #[derive(Debug)]
 pub struct Query<QueryType: DeserializeOwned + JsonSchema + Send + Sync> {
     inner: QueryType,
@@ -36,13 +56,27 @@ impl<QueryType: DeserializeOwned + JsonSchema + Send + Sync> Query<QueryType> {
 /// it as an instance of `QueryType`.
 fn http_request_load_query<QueryType>(
     request: &RequestInfo,
+    parameter_case: ParameterCase,
 ) -> Result<Query<QueryType>, HttpError>
 where
     QueryType: DeserializeOwned + JsonSchema + Send + Sync,
 {
     let raw_query_string = request.uri().query().unwrap_or("");
+    let query_string = parameter_case.query_from_wire(raw_query_string);
     // TODO-correctness: are query strings defined to be urlencoded in this way?
-    match serde_urlencoded::from_str(raw_query_string) {
+    let mut params = BTreeMap::<String, QueryValue>::new();
+    for (name, value) in form_urlencoded::parse(query_string.as_bytes()) {
+        params
+            .entry(name.to_string())
+            .or_insert_with(|| QueryValue {
+                name: name.to_string(),
+                values: Vec::new(),
+            })
+            .values
+            .push(value.into_owned());
+    }
+
+    match from_map(&params) {
         Ok(q) => Ok(Query { inner: q }),
         Err(e) => Err(HttpError::for_bad_request(
             None,
@@ -51,6 +85,60 @@ where
     }
 }
 
+/// Returns an error listing the parameters in the request's query string that
+/// aren't among `known`, if there are any.
+fn http_request_check_query(
+    request: &RequestInfo,
+    known: &BTreeSet<String>,
+) -> Result<(), HttpError> {
+    let raw_query_string = request.uri().query().unwrap_or("");
+    let mut unknown = Vec::new();
+    for (name, _) in form_urlencoded::parse(raw_query_string.as_bytes()) {
+        if !known.contains(name.as_ref()) && !unknown.contains(&name) {
+            unknown.push(name);
+        }
+    }
+
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(HttpError::for_bad_request(
+            None,
+            format!("unknown query parameters: {}", unknown.join(", ")),
+        ))
+    }
+}
+
+/// The values given for a single query parameter, in the order in which they
+/// appeared in the query string.
+#[derive(Debug, Clone)]
+struct QueryValue {
+    name: String,
+    values: Vec<String>,
+}
+
+impl MapValue for QueryValue {
+    fn as_value(&self) -> Result<&str, MapError> {
+        match self.values.as_slice() {
+            [value] => Ok(value.as_str()),
+            _ => Err(MapError(format!("duplicate field `{}`", self.name))),
+        }
+    }
+
+    fn as_seq(&self) -> Result<Box<dyn Iterator<Item = Self>>, MapError> {
+        let name = self.name.clone();
+        Ok(Box::new(self.values.clone().into_iter().map(move |value| {
+            QueryValue { name: name.clone(), values: vec![value] }
+        })))
+    }
+
+    // Report the underlying parse error, as in "invalid digit found in
+    // string".
+    fn parse_error(&self, _type_name: &str, error: &dyn Display) -> MapError {
+        MapError(error.to_string())
+    }
+}
+
 // The `SharedExtractor` implementation for Query<QueryType> describes how to
 // construct an instance of `Query<QueryType>` from an HTTP request: namely, by
 // parsing the query string to an instance of `QueryType`.
@@ -65,7 +153,13 @@ where
     async fn from_request<Context: ServerContext>(
         rqctx: &RequestContext<Context>,
     ) -> Result<Query<QueryType>, HttpError> {
-        http_request_load_query(&rqctx.request)
+        if rqctx.endpoint.strict_query {
+            http_request_check_query(
+                &rqctx.request,
+                &rqctx.endpoint.query_parameters,
+            )?;
+        }
+        http_request_load_query(&rqctx.request, rqctx.parameter_case)
     }
 
     fn metadata(
@@ -74,3 +168,52 @@ where
         get_metadata::<QueryType>(&ApiEndpointParameterLocation::Query)
     }
 }
+
+// An `Option<Query<QueryType>>` is `None` for a request without a query
+// string, which lets an endpoint accept its query parameters as a group or not
+// at all.
+#[async_trait]
+impl<QueryType> SharedExtractor for Option<Query<QueryType>>
+where
+    QueryType: JsonSchema + DeserializeOwned + Send + Sync + 'static,
+{
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+    ) -> Result<Option<Query<QueryType>>, HttpError> {
+        match rqctx.request.uri().query() {
+            None | Some("") => Ok(None),
+            Some(_) => Query::<QueryType>::from_request(rqctx).await.map(Some),
+        }
+    }
+
+    fn metadata(
+        body_content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        let mut metadata =
+            <Query<QueryType> as SharedExtractor>::metadata(body_content_type);
+        for parameter in &mut metadata.parameters {
+            parameter.required = false;
+        }
+        metadata
+    }
+}
+
+#[async_trait]
+impl<QueryType> SharedExtractor for Validated<Query<QueryType>>
+where
+    QueryType: JsonSchema + DeserializeOwned + Validate + Send + Sync + 'static,
+{
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+    ) -> Result<Validated<Query<QueryType>>, HttpError> {
+        let query = Query::<QueryType>::from_request(rqctx).await?;
+        query.inner.validate()?;
+        Ok(Validated { inner: query })
+    }
+
+    fn metadata(
+        body_content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        <Query<QueryType> as SharedExtractor>::metadata(body_content_type)
+    }
+}
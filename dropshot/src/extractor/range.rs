@@ -0,0 +1,184 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Byte range request extractor
+
+use crate::api_description::ApiEndpointBodyContentType;
+use crate::error::HttpError;
+use crate::server::ServerContext;
+use crate::ExtractorMetadata;
+use crate::RequestContext;
+use crate::SharedExtractor;
+use async_trait::async_trait;
+use std::ops::Range;
+
+/// One range of bytes from a `Range` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteRange {
+    /// `first-last`: bytes `first` through `last`, inclusive
+    FromTo(u64, u64),
+    /// `first-`: bytes from `first` to the end
+    From(u64),
+    /// `-length`: the final `length` bytes
+    Suffix(u64),
+}
+
+impl ByteRange {
+    /// Returns the offsets of the bytes this range selects from a
+    /// representation `len` bytes long, or `None` if it selects none of them.
+    pub fn resolve(&self, len: u64) -> Option<Range<u64>> {
+        match *self {
+            ByteRange::FromTo(first, last) if first < len => {
+                Some(first..last.saturating_add(1).min(len))
+            }
+            ByteRange::From(first) if first < len => Some(first..len),
+            ByteRange::Suffix(length) if length > 0 && len > 0 => {
+                Some(len.saturating_sub(length)..len)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// `RangeRequest` is an extractor providing the byte ranges requested by the
+/// `Range` header, for endpoints that serve large representations and want to
+/// support resumable downloads.  Pair it with
+/// [`HttpResponsePartialContent`](crate::HttpResponsePartialContent).
+///
+/// As HTTP permits, a `Range` header that's malformed or uses a unit other
+/// than bytes is ignored, as though the request had none.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RangeRequest {
+    ranges: Vec<ByteRange>,
+}
+
+impl RangeRequest {
+    /// Returns the requested ranges, which is empty if the request has no
+    /// (usable) `Range` header.
+    pub fn ranges(&self) -> &[ByteRange] {
+        &self.ranges
+    }
+
+    /// Resolves the request against a representation `len` bytes long for
+    /// servers that only send a single range.  Returns `Ok(None)` if the whole
+    /// representation should be sent, either because no range was requested or
+    /// because several were, and `Ok(Some(range))` if only `range` should be
+    /// sent.  If no requested bytes exist, returns a 416 ("Range Not
+    /// Satisfiable") error.
+    pub fn single_range(
+        &self,
+        len: u64,
+    ) -> Result<Option<Range<u64>>, HttpError> {
+        match self.ranges.as_slice() {
+            [range] => range.resolve(len).map(Some).ok_or_else(|| {
+                HttpError::for_client_error(
+                    None,
+                    http::StatusCode::RANGE_NOT_SATISFIABLE,
+                    format!(
+                        "requested range is not satisfiable for length {}",
+                        len
+                    ),
+                )
+            }),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl SharedExtractor for RangeRequest {
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+    ) -> Result<RangeRequest, HttpError> {
+        let ranges = rqctx
+            .request
+            .headers()
+            .get(http::header::RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_range_header)
+            .unwrap_or_default();
+        Ok(RangeRequest { ranges })
+    }
+
+    fn metadata(
+        _body_content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        ExtractorMetadata::default()
+    }
+}
+
+/// Parses a `Range` header value like "bytes=0-499, 1000-, -500".
+fn parse_range_header(value: &str) -> Option<Vec<ByteRange>> {
+    let (unit, specs) = value.split_once('=')?;
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+        return None;
+    }
+    specs
+        .split(',')
+        .map(str::trim)
+        .filter(|spec| !spec.is_empty())
+        .map(|spec| {
+            let (first, last) = spec.split_once('-')?;
+            match (first.is_empty(), last.is_empty()) {
+                (true, false) => Some(ByteRange::Suffix(last.parse().ok()?)),
+                (false, true) => Some(ByteRange::From(first.parse().ok()?)),
+                (false, false) => {
+                    let first = first.parse().ok()?;
+                    let last = last.parse().ok()?;
+                    (first <= last).then_some(ByteRange::FromTo(first, last))
+                }
+                (true, true) => None,
+            }
+        })
+        .collect::<Option<Vec<_>>>()
+        .filter(|ranges| !ranges.is_empty())
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_range_header;
+    use super::ByteRange;
+    use super::RangeRequest;
+    use http::StatusCode;
+
+    #[test]
+    fn test_parse_range_header() {
+        assert_eq!(
+            parse_range_header("bytes=0-499, 1000-,-500"),
+            Some(vec![
+                ByteRange::FromTo(0, 499),
+                ByteRange::From(1000),
+                ByteRange::Suffix(500),
+            ])
+        );
+        assert_eq!(parse_range_header("bytes=5-4"), None);
+        assert_eq!(parse_range_header("bytes=-"), None);
+        assert_eq!(parse_range_header("bytes=a-b"), None);
+        assert_eq!(parse_range_header("bytes="), None);
+        assert_eq!(parse_range_header("items=0-4"), None);
+        assert_eq!(parse_range_header("0-4"), None);
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(ByteRange::FromTo(0, 9).resolve(100), Some(0..10));
+        assert_eq!(ByteRange::FromTo(90, 200).resolve(100), Some(90..100));
+        assert_eq!(ByteRange::FromTo(100, 200).resolve(100), None);
+        assert_eq!(ByteRange::From(10).resolve(100), Some(10..100));
+        assert_eq!(ByteRange::From(100).resolve(100), None);
+        assert_eq!(ByteRange::Suffix(10).resolve(100), Some(90..100));
+        assert_eq!(ByteRange::Suffix(500).resolve(100), Some(0..100));
+        assert_eq!(ByteRange::Suffix(0).resolve(100), None);
+        assert_eq!(ByteRange::Suffix(10).resolve(0), None);
+
+        let request = RangeRequest { ranges: vec![ByteRange::From(50)] };
+        assert_eq!(request.single_range(100).unwrap(), Some(50..100));
+        let error = request.single_range(10).unwrap_err();
+        assert_eq!(error.status_code, StatusCode::RANGE_NOT_SATISFIABLE);
+
+        let request = RangeRequest {
+            ranges: vec![ByteRange::From(50), ByteRange::Suffix(5)],
+        };
+        assert_eq!(request.single_range(100).unwrap(), None);
+        assert_eq!(RangeRequest::default().single_range(100).unwrap(), None);
+    }
+}
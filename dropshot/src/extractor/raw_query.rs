@@ -0,0 +1,51 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Raw query string extractor
+
+use crate::api_description::ApiEndpointBodyContentType;
+use crate::error::HttpError;
+use crate::server::ServerContext;
+use crate::ExtractorMetadata;
+use crate::RequestContext;
+use crate::SharedExtractor;
+use async_trait::async_trait;
+
+/// `RawQuery` is an extractor providing the request's query string exactly as
+/// it appeared in the request URI: undecoded, in its original order, and
+/// without the leading "?".  This is useful for endpoints that must check a
+/// signature computed over the query string, which the decoded parameters
+/// from [`Query`](crate::Query) can't reproduce byte-for-byte.
+///
+/// `RawQuery` can be used alongside `Query`.  It adds nothing to the
+/// endpoint's OpenAPI description.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RawQuery {
+    query: Option<String>,
+}
+
+impl RawQuery {
+    /// Returns the query string, or `None` if the request URI had no "?".
+    pub fn as_str(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    pub fn into_inner(self) -> Option<String> {
+        self.query
+    }
+}
+
+#[async_trait]
+impl SharedExtractor for RawQuery {
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+    ) -> Result<RawQuery, HttpError> {
+        let query = rqctx.request.uri().query().map(str::to_string);
+        Ok(RawQuery { query })
+    }
+
+    fn metadata(
+        _body_content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        ExtractorMetadata::default()
+    }
+}
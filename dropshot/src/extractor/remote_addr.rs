@@ -0,0 +1,288 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Effective client address extractor
+
+use crate::api_description::ApiEndpointBodyContentType;
+use crate::error::HttpError;
+use crate::server::ServerContext;
+use crate::ExtractorMetadata;
+use crate::RequestContext;
+use crate::SharedExtractor;
+use async_trait::async_trait;
+use ipnet::IpNet;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+
+/// `RemoteAddr` is an extractor providing the IP address of the client that
+/// made the request.
+///
+/// Without further configuration, this is the address of the peer on the
+/// other end of the connection.  When the server sits behind proxies or load
+/// balancers, list their addresses in
+/// [`ConfigDropshot::trusted_proxies`](crate::ConfigDropshot::trusted_proxies).
+/// For a request that arrives from one of those proxies, the client address is
+/// taken from the `Forwarded` header (or, if that's missing, the
+/// `X-Forwarded-For` header), skipping over any other trusted proxies the
+/// request passed through.  These headers are ignored in requests from any
+/// other peer, since the peer could have put anything in them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RemoteAddr {
+    ip: IpAddr,
+}
+
+impl RemoteAddr {
+    /// Returns the client's IP address.
+    pub fn ip(&self) -> IpAddr {
+        self.ip
+    }
+}
+
+#[async_trait]
+impl SharedExtractor for RemoteAddr {
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+    ) -> Result<RemoteAddr, HttpError> {
+        Ok(RemoteAddr {
+            ip: resolve_client_ip(
+                rqctx.request.headers(),
+                rqctx.request.remote_addr().ip(),
+                &rqctx.server.config.trusted_proxies,
+            ),
+        })
+    }
+
+    fn metadata(
+        _body_content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        ExtractorMetadata::default()
+    }
+}
+
+/// Determines the client's address for a request received from `peer`.
+///
+/// Each proxy appends the address it received the request from to the
+/// forwarding headers, so the addresses are walked from last to first.  The
+/// first address not belonging to a trusted proxy is the client.  If an
+/// address can't be parsed (e.g., "unknown" or an obfuscated identifier), the
+/// walk stops at the proxy that reported it.
+fn resolve_client_ip(
+    headers: &http::HeaderMap,
+    peer: IpAddr,
+    trusted_proxies: &[IpNet],
+) -> IpAddr {
+    let is_trusted = |ip: &IpAddr| is_trusted_proxy(trusted_proxies, ip);
+
+    let mut client = peer;
+    if !is_trusted(&client) {
+        return client;
+    }
+
+    let forwarded = forwarded_for(headers, "forwarded", |element| {
+        forwarded_param(element, "for")
+    });
+    let hops = if forwarded.is_empty() {
+        forwarded_for(headers, "x-forwarded-for", Some)
+    } else {
+        forwarded
+    };
+
+    for hop in hops.iter().rev() {
+        match parse_node(hop) {
+            Some(ip) => client = ip,
+            None => break,
+        }
+        if !is_trusted(&client) {
+            break;
+        }
+    }
+    client
+}
+
+/// Returns whether `ip` belongs to one of the `trusted_proxies`.
+pub(crate) fn is_trusted_proxy(trusted_proxies: &[IpNet], ip: &IpAddr) -> bool {
+    trusted_proxies.iter().any(|net| net.contains(ip))
+}
+
+/// Returns the element of the `Forwarded` header added by the trusted proxy
+/// furthest from the server, for a request received from `peer`, which must
+/// be a trusted proxy.  Elements before it could have been written by the
+/// client.
+pub(crate) fn trusted_forwarded_element<'a>(
+    headers: &'a http::HeaderMap,
+    peer: IpAddr,
+    trusted_proxies: &[IpNet],
+) -> Option<&'a str> {
+    debug_assert!(is_trusted_proxy(trusted_proxies, &peer));
+    let elements = headers
+        .get_all("forwarded")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect::<Vec<_>>();
+
+    let mut trusted = None;
+    for element in elements.into_iter().rev() {
+        trusted = Some(element);
+        // The proxy that added this element received the request from the
+        // node it names in "for".
+        match forwarded_param(element, "for").and_then(parse_node) {
+            Some(ip) if is_trusted_proxy(trusted_proxies, &ip) => (),
+            _ => break,
+        }
+    }
+    trusted
+}
+
+/// Returns the value of parameter `name` (e.g., "for" or "proto") in
+/// `element`, one element of a `Forwarded` header, without quotes.
+pub(crate) fn forwarded_param<'a>(
+    element: &'a str,
+    name: &str,
+) -> Option<&'a str> {
+    element.split(';').find_map(|pair| {
+        let mut kv = pair.splitn(2, '=');
+        let key = kv.next().unwrap_or("").trim();
+        let value = kv.next().unwrap_or("").trim().trim_matches('"');
+        (key.eq_ignore_ascii_case(name) && !value.is_empty()).then_some(value)
+    })
+}
+
+/// Collects the client identifier from each comma-separated element of every
+/// instance of header `name`, in order.
+fn forwarded_for<'a, F>(
+    headers: &'a http::HeaderMap,
+    name: &str,
+    node: F,
+) -> Vec<&'a str>
+where
+    F: Fn(&'a str) -> Option<&'a str>,
+{
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(node)
+        .map(str::trim)
+        .collect()
+}
+
+/// Parses an address that may be quoted, bracketed, or include a port, as in
+/// "192.0.2.43", "192.0.2.43:47011", or "\"[2001:db8::1]:4711\"".
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim_matches('"');
+    if let Some(rest) = node.strip_prefix('[') {
+        return rest.split(']').next()?.parse().ok();
+    }
+    node.parse()
+        .ok()
+        .or_else(|| node.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::resolve_client_ip;
+    use super::trusted_forwarded_element;
+    use http::HeaderMap;
+    use http::HeaderValue;
+    use ipnet::IpNet;
+    use std::net::IpAddr;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn trusted() -> Vec<IpNet> {
+        vec!["10.0.0.0/8".parse().unwrap(), "fd00::/8".parse().unwrap()]
+    }
+
+    #[test]
+    fn test_untrusted_peer() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_static("1.2.3.4"));
+        headers.insert("forwarded", HeaderValue::from_static("for=1.2.3.4"));
+        let peer = ip("192.0.2.1");
+        assert_eq!(resolve_client_ip(&headers, peer, &trusted()), peer);
+        assert_eq!(resolve_client_ip(&headers, peer, &[]), peer);
+    }
+
+    #[test]
+    fn test_x_forwarded_for() {
+        let mut headers = HeaderMap::new();
+        let peer = ip("10.0.0.1");
+        assert_eq!(resolve_client_ip(&headers, peer, &trusted()), peer);
+
+        // A client can put whatever it likes at the start of the header, so
+        // only the entries added by trusted proxies are believed.
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("6.6.6.6, 192.0.2.7, 10.1.1.1"),
+        );
+        headers.append("x-forwarded-for", HeaderValue::from_static("10.2.2.2"));
+        assert_eq!(
+            resolve_client_ip(&headers, peer, &trusted()),
+            ip("192.0.2.7")
+        );
+
+        // If every hop is trusted, the first one is the best we have.
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_static("10.3.3.3"));
+        assert_eq!(
+            resolve_client_ip(&headers, peer, &trusted()),
+            ip("10.3.3.3")
+        );
+    }
+
+    #[test]
+    fn test_forwarded() {
+        let peer = ip("fd00::1");
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "forwarded",
+            HeaderValue::from_static(
+                "for=192.0.2.43:47011, for=\"[2001:db8::1]:4711\";proto=https, \
+                 by=10.0.0.1;for=10.0.0.2",
+            ),
+        );
+        // Forwarded takes precedence over X-Forwarded-For.
+        headers.insert("x-forwarded-for", HeaderValue::from_static("1.2.3.4"));
+        assert_eq!(
+            resolve_client_ip(&headers, peer, &trusted()),
+            ip("2001:db8::1")
+        );
+
+        // An unparseable hop ends the walk at the proxy that reported it.
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "forwarded",
+            HeaderValue::from_static(
+                "for=192.0.2.43, for=unknown, for=10.0.0.2",
+            ),
+        );
+        assert_eq!(
+            resolve_client_ip(&headers, peer, &trusted()),
+            ip("10.0.0.2")
+        );
+    }
+
+    #[test]
+    fn test_trusted_forwarded_element() {
+        let peer = ip("10.0.0.1");
+        let mut headers = HeaderMap::new();
+        assert_eq!(trusted_forwarded_element(&headers, peer, &trusted()), None);
+
+        // The client's own element isn't believed.
+        headers.insert(
+            "forwarded",
+            HeaderValue::from_static(
+                "host=evil.example, for=192.0.2.43;host=api.example, \
+                 for=10.0.0.2;host=internal",
+            ),
+        );
+        assert_eq!(
+            trusted_forwarded_element(&headers, peer, &trusted()),
+            Some("for=192.0.2.43;host=api.example")
+        );
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Validation of extracted parameters
+
+use crate::error::HttpError;
+use std::collections::BTreeMap;
+
+/// Checks constraints on a value extracted from a request that its type alone
+/// can't express, like numeric ranges or relationships between fields.  Wrap
+/// an extractor in [`Validated`] to have its value checked before the handler
+/// runs.
+pub trait Validate {
+    fn validate(&self) -> Result<(), ValidationErrors>;
+}
+
+/// Problems found by [`Validate::validate`], as messages keyed by the name of
+/// the offending field.  A request that fails validation receives a 400 ("Bad
+/// Request") whose body lists these messages in its `field_errors` property.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationErrors {
+    fields: BTreeMap<String, Vec<String>>,
+}
+
+impl ValidationErrors {
+    pub fn new() -> Self {
+        ValidationErrors::default()
+    }
+
+    /// Record `message` as a problem with the field `name`.
+    pub fn add<N: ToString, M: ToString>(&mut self, name: N, message: M) {
+        self.fields
+            .entry(name.to_string())
+            .or_default()
+            .push(message.to_string());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Returns the messages recorded for each field.
+    pub fn fields(&self) -> &BTreeMap<String, Vec<String>> {
+        &self.fields
+    }
+
+    /// Returns `Err(self)` if any problems have been recorded, which is
+    /// convenient at the end of a [`Validate::validate`] implementation.
+    pub fn into_result(self) -> Result<(), ValidationErrors> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl From<ValidationErrors> for HttpError {
+    fn from(errors: ValidationErrors) -> Self {
+        let summary = errors
+            .fields
+            .iter()
+            .map(|(name, messages)| {
+                format!("{}: {}", name, messages.join(", "))
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+        let mut error = HttpError::for_bad_request(
+            None,
+            format!("request failed validation: {}", summary),
+        );
+        error.field_errors = errors.fields;
+        error
+    }
+}
+
+/// `Validated<E>` wraps one of the extractors [`Query`](crate::Query),
+/// [`Path`](crate::Path), or [`TypedBody`](crate::TypedBody) whose value
+/// implements [`Validate`].  After the wrapped extractor deserializes its
+/// value, the value is validated, and the request fails with a 400 ("Bad
+/// Request") listing the problems if it's invalid.
+#[derive(Debug)]
+pub struct Validated<E> {
+    pub(crate) inner: E,
+}
+
+impl<E> Validated<E> {
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+}
@@ -16,7 +16,8 @@ use std::fmt::Display;
 
 /// Deserialize a BTreeMap<String, MapValue> into a type, invoking
 /// String::parse() for all values according to the required type. MapValue may
-/// be either a single String or a sequence of Strings.
+/// be either a single String or a sequence of Strings; each element of a
+/// sequence is itself a MapValue.
 pub(crate) fn from_map<'a, T, Z>(
     map: &'a BTreeMap<String, Z>,
 ) -> Result<T, String>
@@ -28,9 +29,19 @@ where
     T::deserialize(&mut deserializer).map_err(|e| e.0)
 }
 
-pub(crate) trait MapValue {
+pub(crate) trait MapValue: Sized {
     fn as_value(&self) -> Result<&str, MapError>;
-    fn as_seq(&self) -> Result<Box<dyn Iterator<Item = String>>, MapError>;
+    fn as_seq(&self) -> Result<Box<dyn Iterator<Item = Self>>, MapError>;
+
+    /// Describe a failure to parse this value as `type_name`. `error` is the
+    /// error reported by the type's `FromStr` implementation.
+    fn parse_error(&self, type_name: &str, _error: &dyn Display) -> MapError {
+        MapError(format!(
+            "unable to parse '{}' as {}",
+            self.as_value().unwrap_or_default(),
+            type_name
+        ))
+    }
 }
 
 impl MapValue for String {
@@ -38,7 +49,7 @@ impl MapValue for String {
         Ok(self.as_str())
     }
 
-    fn as_seq(&self) -> Result<Box<dyn Iterator<Item = String>>, MapError> {
+    fn as_seq(&self) -> Result<Box<dyn Iterator<Item = Self>>, MapError> {
         Err(MapError(
             "a string may not be used in place of a sequence of values"
                 .to_string(),
@@ -126,11 +137,9 @@ macro_rules! de_value {
             {
                 self.value(|raw_value| match raw_value.as_value()?.parse::<$i>() {
                     Ok(value) => visitor.[<visit_ $i>](value),
-                    Err(_) => Err(MapError(format!(
-                        "unable to parse '{}' as {}",
-                        raw_value.as_value()?,
-                        type_name::<$i>()
-                    ))),
+                    Err(error) => {
+                        Err(raw_value.parse_error(type_name::<$i>(), &error))
+                    }
                 })
             }
         }
@@ -229,6 +238,8 @@ where
         visitor.visit_enum(self)
     }
 
+    // The value is being discarded so there's no need to interpret it; in
+    // particular, an ignored query parameter may be repeated.
     fn deserialize_ignored_any<V>(
         self,
         visitor: V,
@@ -236,7 +247,7 @@ where
     where
         V: Visitor<'de>,
     {
-        self.value(|raw_value| visitor.visit_str(raw_value.as_value()?))
+        visitor.visit_unit()
     }
 
     // We really shouldn't have to implement this, and we can't actually do so
@@ -33,7 +33,14 @@
 
 use super::error::HttpError;
 use super::extractor::RequestExtractor;
+#[cfg(feature = "cbor")]
+use super::http_util::CONTENT_TYPE_CBOR;
+#[cfg(feature = "csv")]
+use super::http_util::CONTENT_TYPE_CSV;
 use super::http_util::CONTENT_TYPE_JSON;
+#[cfg(feature = "msgpack")]
+use super::http_util::CONTENT_TYPE_MSGPACK;
+use super::http_util::CONTENT_TYPE_NDJSON;
 use super::http_util::CONTENT_TYPE_OCTET_STREAM;
 use super::server::DropshotState;
 use super::server::ServerContext;
@@ -41,7 +48,26 @@ use crate::api_description::ApiEndpointBodyContentType;
 use crate::api_description::ApiEndpointHeader;
 use crate::api_description::ApiEndpointResponse;
 use crate::api_description::ApiSchemaGenerator;
+use crate::api_description::EndpointMetadata;
+use crate::api_description::ParameterCase;
+use crate::cache_control::CacheControl;
+use crate::checksum::body_with_checksum_trailer;
+use crate::checksum::ChecksumAlgorithm;
+use crate::clock::Clock;
+use crate::extractor::forwarded_param;
+use crate::extractor::http_request_if_none_match;
+use crate::extractor::is_trusted_proxy;
+use crate::extractor::trusted_forwarded_element;
+use crate::extractor::ClientCert;
+use crate::extractor::EntityTag;
+use crate::extractor::EntityTagMatch;
+use crate::extractor::RangeRequest;
+use crate::http_util::http_content_disposition_attachment;
+use crate::http_util::http_date;
+use crate::pagination::OffsetPaginationParams;
+use crate::pagination::PageTokenScope;
 use crate::pagination::PaginationParams;
+use crate::pagination::ResultsPage;
 use crate::router::VariableSet;
 use crate::schema_util::make_subschema_for;
 use crate::schema_util::schema2struct;
@@ -49,6 +75,9 @@ use crate::schema_util::ReferenceVisitor;
 use crate::to_map::to_map;
 
 use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::Utc;
+use futures::StreamExt;
 use http::HeaderMap;
 use http::StatusCode;
 use hyper::Body;
@@ -63,9 +92,13 @@ use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
 use std::future::Future;
+use std::io::Seek;
 use std::marker::PhantomData;
 use std::num::NonZeroU32;
+use std::ops::Range;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
 
 /// Type alias for the result returned by HTTP handler functions.
 pub type HttpHandlerResult = Result<Response<Body>, HttpError>;
@@ -77,8 +110,12 @@ pub struct RequestContext<Context: ServerContext> {
     pub server: Arc<DropshotState<Context>>,
     /// HTTP request routing variables
     pub path_variables: VariableSet,
+    /// metadata of the endpoint handling this request
+    pub endpoint: Arc<EndpointMetadata>,
     /// expected request body mime type
     pub body_content_type: ApiEndpointBodyContentType,
+    /// naming convention used for path and query parameters
+    pub parameter_case: ParameterCase,
     /// unique id assigned to this request
     pub request_id: String,
     /// logger for this specific request
@@ -97,6 +134,7 @@ pub struct RequestInfo {
     version: http::Version,
     headers: http::HeaderMap<http::HeaderValue>,
     remote_addr: std::net::SocketAddr,
+    client_cert: Option<ClientCert>,
 }
 
 impl RequestInfo {
@@ -110,6 +148,7 @@ impl RequestInfo {
             version: request.version(),
             headers: request.headers().clone(),
             remote_addr,
+            client_cert: request.extensions().get::<ClientCert>().cloned(),
         }
     }
 }
@@ -135,6 +174,12 @@ impl RequestInfo {
         self.remote_addr
     }
 
+    /// Returns the certificate the client presented during the TLS
+    /// handshake, if any.  See [`ClientCert`].
+    pub fn client_cert(&self) -> Option<&ClientCert> {
+        self.client_cert.as_ref()
+    }
+
     /// Returns a reference to the `RequestInfo` itself
     ///
     /// This is provided for source compatibility.  In previous versions of
@@ -174,6 +219,28 @@ impl<Context: ServerContext> RequestContext<Context> {
         &self.server.private
     }
 
+    /// Returns the clock that handlers should use to read the time, so that
+    /// tests can control it.  See
+    /// [`ApiDescription::clock`](crate::ApiDescription::clock).
+    pub fn clock(&self) -> &dyn Clock {
+        self.server.clock.as_ref()
+    }
+
+    /// Returns the maximum number of bytes allowed in the body of this
+    /// request: the endpoint's limit if it has one, or else the server's.
+    pub fn request_body_max_bytes(&self) -> usize {
+        self.endpoint
+            .request_body_max_bytes
+            .unwrap_or(self.server.config.request_body_max_bytes)
+    }
+
+    /// Returns how long the handler may take to handle this request before
+    /// the request is abandoned: the endpoint's timeout if it has one, or else
+    /// the server's.
+    pub fn request_timeout(&self) -> Option<Duration> {
+        self.endpoint.request_timeout.or(self.server.config.request_timeout)
+    }
+
     /// Returns the appropriate count of items to return for a paginated request
     ///
     /// This first looks at any client-requested limit and clamps it based on the
@@ -188,16 +255,149 @@ impl<Context: ServerContext> RequestContext<Context> {
         ScanParams: DeserializeOwned,
         PageSelector: DeserializeOwned + Serialize,
     {
+        Ok(self.clamp_page_limit(pag_params.limit))
+    }
+
+    /// Returns the appropriate count of items to return for a request
+    /// paginated by offset, as [`page_limit`](Self::page_limit) does for a
+    /// request paginated by page token
+    pub fn offset_page_limit<ScanParams>(
+        &self,
+        pag_params: &OffsetPaginationParams<ScanParams>,
+    ) -> Result<NonZeroU32, HttpError>
+    where
+        ScanParams: DeserializeOwned,
+    {
+        Ok(self.clamp_page_limit(pag_params.limit))
+    }
+
+    /// Returns the page token settings of this request, so that work the
+    /// handler runs on another task can create and parse page tokens as the
+    /// handler itself would.  See [`PageTokenScope`].
+    pub fn page_token_scope(&self) -> PageTokenScope {
+        PageTokenScope::new(&self.server.page_tokens, &self.server.clock)
+    }
+
+    fn clamp_page_limit(&self, limit: Option<NonZeroU32>) -> NonZeroU32 {
         let server_config = &self.server.config;
 
-        Ok(pag_params
-            .limit
+        limit
             // Compare the client-provided limit to the configured max for the
             // server and take the smaller one.
             .map(|limit| min(limit, server_config.page_max_nitems))
             // If no limit was provided by the client, use the configured
             // default.
-            .unwrap_or(server_config.page_default_nitems))
+            .unwrap_or(server_config.page_default_nitems)
+    }
+
+    /// Returns an absolute URL for `path_and_query` (which should begin with
+    /// "/") as a client of this server would see it, e.g., for use in a
+    /// Location header or a link to the next page of results.
+    ///
+    /// If the server is configured with an `external_url`, that is used as the
+    /// base URL, including any path prefix.  Otherwise, if the request came
+    /// from one of the server's `trusted_proxies`, the scheme and host are
+    /// taken from the `Forwarded` header or else the `X-Forwarded-Proto` and
+    /// `X-Forwarded-Host` headers.  Failing that, the scheme is that of the
+    /// connection and the host comes from the request's `Host` header (or URI
+    /// authority), falling back to the server's local address.
+    pub fn absolute_url(&self, path_and_query: &str) -> String {
+        let server_config = &self.server.config;
+        if let Some(external_url) = &server_config.external_url {
+            return format!(
+                "{}{}",
+                external_url.trim_end_matches('/'),
+                path_and_query
+            );
+        }
+
+        let headers = self.request.headers();
+        let header_str = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                // Proxies may append to these headers; the first value was set
+                // by the proxy closest to the client.
+                .and_then(|value| value.split(',').next())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        // The headers set by proxies are believed only in requests that come
+        // from one, as for `RemoteAddr`.
+        let (mut scheme, mut host) = (None, None);
+        let peer = self.request.remote_addr().ip();
+        let trusted_proxies = &server_config.trusted_proxies;
+        if is_trusted_proxy(trusted_proxies, &peer) {
+            if let Some(element) =
+                trusted_forwarded_element(headers, peer, trusted_proxies)
+            {
+                scheme = forwarded_param(element, "proto").map(String::from);
+                host = forwarded_param(element, "host").map(String::from);
+            }
+            scheme = scheme.or_else(|| header_str("x-forwarded-proto"));
+            host = host.or_else(|| header_str("x-forwarded-host"));
+        }
+
+        let scheme = scheme.unwrap_or_else(|| {
+            String::from(if self.server.using_tls() { "https" } else { "http" })
+        });
+        let host = host
+            .or_else(|| header_str(http::header::HOST.as_str()))
+            .or_else(|| self.request.uri().authority().map(|a| a.to_string()))
+            .unwrap_or_else(|| self.server.local_addr.to_string());
+        format!("{}://{}{}", scheme, host, path_and_query)
+    }
+
+    /// Returns the path of the resource served by the endpoint with operation
+    /// ID `operation_id` when its path parameters have the values in `params`,
+    /// a struct like the one taken by that endpoint's [`Path`](crate::Path)
+    /// extractor (but with a wildcard parameter given as a single
+    /// "/"-separated string).  Unlike a path assembled with `format!`, the
+    /// result is guaranteed to be routed back to that endpoint, with those
+    /// parameters.  See also [`HttpResponseCreated::with_location`].
+    ///
+    /// A failure here is a bug in the server, so it's reported as a 500
+    /// ("Internal Server Error").
+    pub fn path_for<P: Serialize>(
+        &self,
+        operation_id: &str,
+        params: &P,
+    ) -> Result<String, HttpError> {
+        let error = |message: String| {
+            HttpError::for_internal_error(format!(
+                "building path for endpoint \"{}\": {}",
+                operation_id, message
+            ))
+        };
+        let variables = to_map(params).map_err(|e| error(e.0))?;
+        self.server.router.path_for(operation_id, &variables).map_err(error)
+    }
+}
+
+/// Callback that produces the logger for each request.  See
+/// [`ApiDescription::request_log_hook`](crate::ApiDescription::request_log_hook).
+pub(crate) struct RequestLogHook<Context: ServerContext>(
+    Box<dyn Fn(&RequestContext<Context>) -> Logger + Send + Sync>,
+);
+
+impl<Context: ServerContext> RequestLogHook<Context> {
+    pub(crate) fn new<F>(hook: F) -> Self
+    where
+        F: Fn(&RequestContext<Context>) -> Logger + Send + Sync + 'static,
+    {
+        RequestLogHook(Box::new(hook))
+    }
+
+    /// Replaces the request's logger with the one produced by the hook.
+    pub(crate) fn apply(&self, rqctx: &mut RequestContext<Context>) {
+        rqctx.log = (self.0)(rqctx);
+    }
+}
+
+impl<Context: ServerContext> Debug for RequestLogHook<Context> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str("RequestLogHook")
     }
 }
 
@@ -304,7 +504,8 @@ where
 //            | ResponseType that implements HttpResponse.  We'll end up
 //            | invoking:
 //            v
-//      3. ResponseType::to_result()
+//      3. ResponseType::to_result() (or to_conditional_result(), for a GET
+//         or HEAD request with an If-None-Match header)
 //            |
 //            | This is a type-specific conversion from `ResponseType` into
 //            | `Response<Body>` that's allowed to fail with an `HttpError`.
@@ -346,9 +547,17 @@ macro_rules! impl_HttpHandlerFunc_for_func_with_params {
             _param_tuple: ($($T,)*)
         ) -> HttpHandlerResult
         {
+            let if_none_match = http_request_if_none_match(
+                rqctx.request.method(),
+                rqctx.request.headers(),
+            );
             let response: ResponseType =
                 (self)(rqctx, $(_param_tuple.$i,)*).await?;
-            response.to_result()
+            match if_none_match {
+                Some(if_none_match) =>
+                    response.to_conditional_result(&if_none_match),
+                None => response.to_result(),
+            }
         }
     }
 }}
@@ -378,7 +587,7 @@ pub trait RouteHandler<Context: ServerContext>: Debug + Send + Sync {
     ) -> HttpHandlerResult;
 }
 
-/// `HttpRouteHandler` is the only type that implements `RouteHandler`.  The
+/// `HttpRouteHandler` is the main type that implements `RouteHandler`.  The
 /// reason both exist is that we need `HttpRouteHandler::new()` to consume an
 /// arbitrary kind of `HttpHandlerFunc<FuncParams>` and return an object that's
 /// _not_ parametrized by `FuncParams`.  In fact, the resulting
@@ -457,8 +666,97 @@ where
         // resolved statically.
         let funcparams =
             RequestExtractor::from_request(&rqctx, request).await?;
+        let content_type = rqctx.endpoint.response_content_type.clone();
         let future = self.handler.handle_request(rqctx, funcparams);
-        future.await
+        let mut response = future.await?;
+        if let Some(content_type) = content_type {
+            label_freeform_response(&mut response, &content_type)?;
+        }
+        Ok(response)
+    }
+}
+
+/// Labels a successful response with the endpoint's declared media type if
+/// its body is freeform, which [`FreeformBody`] sends as
+/// "application/octet-stream".
+fn label_freeform_response(
+    response: &mut Response<Body>,
+    content_type: &str,
+) -> Result<(), HttpError> {
+    let is_success = response.status().is_success();
+    let headers = response.headers_mut();
+    if is_success
+        && headers
+            .get(http::header::CONTENT_TYPE)
+            .map_or(false, |value| value == CONTENT_TYPE_OCTET_STREAM)
+    {
+        let value = http::HeaderValue::try_from(content_type).map_err(|e| {
+            HttpError::for_internal_error(format!(
+                "invalid response content type {:?}: {}",
+                content_type, e
+            ))
+        })?;
+        headers.insert(http::header::CONTENT_TYPE, value);
+    }
+    Ok(())
+}
+
+/// `ConcurrencyLimitedHandler` wraps another `RouteHandler` to bound the
+/// number of requests it handles at once.  Requests beyond the limit are
+/// rejected immediately with a 503 rather than queued so that one expensive
+/// endpoint cannot tie up resources needed by the rest of the server.
+pub(crate) struct ConcurrencyLimitedHandler<Context: ServerContext> {
+    /// the handler that services requests admitted under the limit
+    inner: Arc<dyn RouteHandler<Context>>,
+    /// maximum number of concurrent requests
+    limit: usize,
+    /// permits for requests currently being handled
+    permits: tokio::sync::Semaphore,
+}
+
+impl<Context: ServerContext> ConcurrencyLimitedHandler<Context> {
+    pub(crate) fn new(
+        inner: Arc<dyn RouteHandler<Context>>,
+        limit: usize,
+    ) -> Arc<dyn RouteHandler<Context>> {
+        Arc::new(ConcurrencyLimitedHandler {
+            inner,
+            limit,
+            permits: tokio::sync::Semaphore::new(limit),
+        })
+    }
+}
+
+impl<Context: ServerContext> Debug for ConcurrencyLimitedHandler<Context> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{:?} (max_concurrency: {})", self.inner, self.limit)
+    }
+}
+
+#[async_trait]
+impl<Context: ServerContext> RouteHandler<Context>
+    for ConcurrencyLimitedHandler<Context>
+{
+    fn label(&self) -> &str {
+        self.inner.label()
+    }
+
+    async fn handle_request(
+        &self,
+        rqctx: RequestContext<Context>,
+        request: hyper::Request<hyper::Body>,
+    ) -> HttpHandlerResult {
+        let _permit = self.permits.try_acquire().map_err(|_| {
+            HttpError::for_unavail(
+                None,
+                format!(
+                    "endpoint is already handling the maximum of {} \
+                     concurrent requests",
+                    self.limit
+                ),
+            )
+        })?;
+        self.inner.handle_request(rqctx, request).await
     }
 }
 
@@ -475,7 +773,7 @@ where
     /// Given a function matching one of the supported API handler function
     /// signatures, return a RouteHandler that can be used to respond to HTTP
     /// requests using this function.
-    pub fn new(handler: HandlerType) -> Box<dyn RouteHandler<Context>> {
+    pub fn new(handler: HandlerType) -> Arc<dyn RouteHandler<Context>> {
         HttpRouteHandler::new_with_name(handler, "<unlabeled handler>")
     }
 
@@ -485,8 +783,8 @@ where
     pub fn new_with_name(
         handler: HandlerType,
         label: &str,
-    ) -> Box<dyn RouteHandler<Context>> {
-        Box::new(HttpRouteHandler {
+    ) -> Arc<dyn RouteHandler<Context>> {
+        Arc::new(HttpRouteHandler {
             label: label.to_string(),
             handler,
             phantom: PhantomData,
@@ -502,10 +800,70 @@ where
 /// HttpResponse must produce a `Result<Response<Body>, HttpError>` and generate
 /// the response metadata.  Typically one should use `Response<Body>` or an
 /// implementation of `HttpTypedResponse`.
+///
+/// Consumers can implement this trait for their own response types, choosing
+/// the status code, headers, and body of the response and how it's described
+/// in the OpenAPI document.  (A response that's just a status code and body
+/// can implement [`HttpCodedResponse`] instead, and one that adds headers to
+/// another response can use [`HttpResponseHeaders`].)
+///
+/// ```
+/// use dropshot::{ApiEndpointHeader, ApiEndpointResponse, ApiSchemaGenerator};
+/// use dropshot::{HttpHandlerResult, HttpResponse};
+/// use http::StatusCode;
+///
+/// /// A 202 ("Accepted") response for a job that's been started, naming the
+/// /// URL at which to monitor its progress
+/// struct JobStarted {
+///     monitor: String,
+/// }
+///
+/// impl HttpResponse for JobStarted {
+///     fn to_result(self) -> HttpHandlerResult {
+///         Ok(hyper::Response::builder()
+///             .status(StatusCode::ACCEPTED)
+///             .header("x-job-monitor", self.monitor)
+///             .body(hyper::Body::empty())?)
+///     }
+///
+///     fn response_metadata() -> ApiEndpointResponse {
+///         ApiEndpointResponse {
+///             success: Some(StatusCode::ACCEPTED),
+///             description: Some(String::from("job started")),
+///             headers: vec![ApiEndpointHeader {
+///                 name: String::from("x-job-monitor"),
+///                 description: Some(String::from("where to monitor the job")),
+///                 schema: ApiSchemaGenerator::Gen {
+///                     name: || String::from("String"),
+///                     schema: |gen| gen.subschema_for::<String>(),
+///                 },
+///                 required: true,
+///             }],
+///             ..Default::default()
+///         }
+///     }
+/// }
+/// ```
 pub trait HttpResponse {
     /// Generate the response to the HTTP call.
     fn to_result(self) -> HttpHandlerResult;
 
+    /// Generate the response to a `GET` or `HEAD` request with the
+    /// `If-None-Match` header `if_none_match`.  Responses that know their
+    /// entity tag without producing their body, like [`HttpResponseTagged`],
+    /// send a 304 ("Not Modified") instead if the tag matches.  By default,
+    /// this is just `to_result()`.
+    fn to_conditional_result(
+        self,
+        if_none_match: &EntityTagMatch,
+    ) -> HttpHandlerResult
+    where
+        Self: Sized,
+    {
+        let _ = if_none_match;
+        self.to_result()
+    }
+
     /// Extract status code and structure metadata for the non-error response.
     /// Type information for errors is handled generically across all endpoints.
     fn response_metadata() -> ApiEndpointResponse;
@@ -532,6 +890,82 @@ impl From<Body> for FreeformBody {
     }
 }
 
+impl FreeformBody {
+    /// Size of the buffer used to read each chunk of an `AsyncRead` source.
+    const READ_CHUNK_BYTES: usize = 64 * 1024;
+
+    /// Sends the chunks produced by `stream` as the body, using chunked
+    /// transfer encoding, so that arbitrarily large responses (such as log
+    /// tails or exports) can be sent without buffering them.  The stream is
+    /// only polled for another chunk once the previous one has been written
+    /// to the connection, so a slow client holds back the producer.  If the
+    /// stream yields an error, the response is aborted.
+    ///
+    /// ```
+    /// use dropshot::{FreeformBody, HttpError, HttpResponseOk};
+    /// use futures::StreamExt;
+    ///
+    /// async fn export() -> Result<HttpResponseOk<FreeformBody>, HttpError> {
+    ///     let rows = futures::stream::iter(0..1000).map(|i| {
+    ///         Ok::<_, std::io::Error>(bytes::Bytes::from(format!("{}\n", i)))
+    ///     });
+    ///     Ok(HttpResponseOk(FreeformBody::from_stream(rows)))
+    /// }
+    /// ```
+    pub fn from_stream<S, E>(stream: S) -> Self
+    where
+        S: futures::Stream<Item = Result<bytes::Bytes, E>> + Send + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>> + 'static,
+    {
+        Self(Body::wrap_stream(stream))
+    }
+
+    /// Streams the contents of `reader` as the body, a chunk at a time, rather
+    /// than reading it into memory first.  Chunks are only read as the client
+    /// consumes them.  If reading fails partway through, the response is
+    /// aborted.
+    ///
+    /// ```
+    /// use dropshot::{FreeformBody, HttpError, HttpResponseOk};
+    ///
+    /// async fn artifact(
+    ///     path: &std::path::Path,
+    /// ) -> Result<HttpResponseOk<FreeformBody>, HttpError> {
+    ///     let file = tokio::fs::File::open(path).await.map_err(|e| {
+    ///         HttpError::for_not_found(None, format!("open: {}", e))
+    ///     })?;
+    ///     Ok(HttpResponseOk(FreeformBody::from_async_read(file)))
+    /// }
+    /// ```
+    pub fn from_async_read<R>(reader: R) -> Self
+    where
+        R: tokio::io::AsyncRead + Send + 'static,
+    {
+        let stream = async_stream::try_stream! {
+            let mut reader = Box::pin(reader);
+            loop {
+                let mut buf =
+                    bytes::BytesMut::with_capacity(Self::READ_CHUNK_BYTES);
+                if reader.read_buf(&mut buf).await? == 0 {
+                    break;
+                }
+                yield buf.freeze();
+            }
+        };
+        Self::from_stream::<_, std::io::Error>(stream)
+    }
+
+    /// Follows the body with a `Content-Digest` trailer (see RFC 9530)
+    /// reporting its checksum, computed with `algorithm` as the body is sent.
+    /// This allows clients to verify the integrity of streaming responses
+    /// whose checksum isn't known up front.  Note that trailers are only
+    /// delivered to clients that support them on the connection's protocol;
+    /// hyper does not currently send trailers over HTTP/1.1.
+    pub fn with_checksum_trailer(self, algorithm: ChecksumAlgorithm) -> Self {
+        Self(body_with_checksum_trailer(self.0, algorithm))
+    }
+}
+
 /// An "empty" type used to represent responses that have no associated data
 /// payload. This isn't intended for general use, but must be pub since it's
 /// used as the Body type for certain responses.
@@ -558,6 +992,12 @@ pub trait HttpResponseContent {
     // with multiple, explicitly enumerated mime types.
     // TODO the ApiSchemaGenerator type is particularly inelegant.
     fn content_metadata() -> Option<ApiSchemaGenerator>;
+
+    /// MIME types in which the content may be produced, used to describe the
+    /// response in the OpenAPI document
+    fn content_types() -> Vec<&'static str> {
+        vec![CONTENT_TYPE_JSON]
+    }
 }
 
 impl HttpResponseContent for FreeformBody {
@@ -614,6 +1054,261 @@ where
     }
 }
 
+/// Wraps a serializable object so that coded response types such as
+/// [HttpResponseOk] send it encoded as CBOR (`application/cbor`) rather than
+/// as JSON.
+#[cfg(feature = "cbor")]
+pub struct Cbor<T>(pub T);
+
+#[cfg(feature = "cbor")]
+impl<T> HttpResponseContent for Cbor<T>
+where
+    T: JsonSchema + Serialize + Send + Sync + 'static,
+{
+    fn to_response(
+        self,
+        builder: http::response::Builder,
+    ) -> HttpHandlerResult {
+        let mut serialized = Vec::new();
+        ciborium::ser::into_writer(&self.0, &mut serialized)
+            .map_err(|e| HttpError::for_internal_error(e.to_string()))?;
+        Ok(builder
+            .header(http::header::CONTENT_TYPE, CONTENT_TYPE_CBOR)
+            .body(serialized.into())?)
+    }
+
+    fn content_metadata() -> Option<ApiSchemaGenerator> {
+        <T as HttpResponseContent>::content_metadata()
+    }
+
+    fn content_types() -> Vec<&'static str> {
+        vec![CONTENT_TYPE_CBOR]
+    }
+}
+
+/// Wraps a serializable object so that coded response types such as
+/// [HttpResponseOk] send it encoded as MessagePack (`application/msgpack`)
+/// rather than as JSON.  The OpenAPI document describes the body with the
+/// object's JSON schema.
+#[cfg(feature = "msgpack")]
+pub struct MsgPack<T>(pub T);
+
+#[cfg(feature = "msgpack")]
+impl<T> HttpResponseContent for MsgPack<T>
+where
+    T: JsonSchema + Serialize + Send + Sync + 'static,
+{
+    fn to_response(
+        self,
+        builder: http::response::Builder,
+    ) -> HttpHandlerResult {
+        // Encode structs as maps, not arrays, so that clients can decode
+        // fields by name.
+        let serialized = rmp_serde::to_vec_named(&self.0)
+            .map_err(|e| HttpError::for_internal_error(e.to_string()))?;
+        Ok(builder
+            .header(http::header::CONTENT_TYPE, CONTENT_TYPE_MSGPACK)
+            .body(serialized.into())?)
+    }
+
+    fn content_metadata() -> Option<ApiSchemaGenerator> {
+        <T as HttpResponseContent>::content_metadata()
+    }
+
+    fn content_types() -> Vec<&'static str> {
+        vec![CONTENT_TYPE_MSGPACK]
+    }
+}
+
+/// Wraps a serializable object so that it is sent as CBOR to clients whose
+/// `Accept` header prefers `application/cbor` over `application/json`, and as
+/// JSON otherwise.
+///
+/// ```
+/// use dropshot::{HttpError, HttpResponseOk, JsonOrCbor, RequestContext};
+///
+/// async fn get_count(
+///     rqctx: RequestContext<()>,
+/// ) -> Result<HttpResponseOk<JsonOrCbor<u64>>, HttpError> {
+///     Ok(HttpResponseOk(JsonOrCbor::new(&rqctx.request, 42)))
+/// }
+/// ```
+#[cfg(feature = "cbor")]
+pub struct JsonOrCbor<T> {
+    value: T,
+    cbor: bool,
+}
+
+#[cfg(feature = "cbor")]
+impl<T> JsonOrCbor<T> {
+    /// Wraps `value`, choosing its encoding from the `Accept` header of
+    /// `request`.
+    pub fn new(request: &RequestInfo, value: T) -> Self {
+        let encoding = preferred_encoding(
+            request.headers(),
+            &[
+                ApiEndpointBodyContentType::Json,
+                ApiEndpointBodyContentType::Cbor,
+            ],
+        );
+        let cbor = encoding == Some(ApiEndpointBodyContentType::Cbor);
+        JsonOrCbor { value, cbor }
+    }
+}
+
+#[cfg(feature = "cbor")]
+impl<T> HttpResponseContent for JsonOrCbor<T>
+where
+    T: JsonSchema + Serialize + Send + Sync + 'static,
+{
+    fn to_response(
+        self,
+        builder: http::response::Builder,
+    ) -> HttpHandlerResult {
+        if self.cbor {
+            Cbor(self.value).to_response(builder)
+        } else {
+            HttpResponseContent::to_response(self.value, builder)
+        }
+    }
+
+    fn content_metadata() -> Option<ApiSchemaGenerator> {
+        <T as HttpResponseContent>::content_metadata()
+    }
+
+    fn content_types() -> Vec<&'static str> {
+        vec![CONTENT_TYPE_JSON, CONTENT_TYPE_CBOR]
+    }
+}
+
+/// Wraps a serializable object so that it is sent in whichever of the
+/// endpoint's encodings (see [`ApiEndpoint::encoding`]) the request's `Accept`
+/// header prefers.  Endpoints that offer no particular encodings send JSON.
+///
+/// ```
+/// use dropshot::{endpoint, HttpError, HttpResponseOk};
+/// use dropshot::{Negotiated, RequestContext};
+///
+/// #[endpoint {
+///     method = GET,
+///     path = "/count",
+///     encodings = [ "application/json" ],
+/// }]
+/// async fn get_count(
+///     rqctx: RequestContext<()>,
+/// ) -> Result<HttpResponseOk<Negotiated<u64>>, HttpError> {
+///     Ok(HttpResponseOk(Negotiated::new(&rqctx, 42)))
+/// }
+/// ```
+///
+/// [`ApiEndpoint::encoding`]: crate::ApiEndpoint::encoding
+pub struct Negotiated<T> {
+    value: T,
+    encoding: ApiEndpointBodyContentType,
+}
+
+impl<T> Negotiated<T> {
+    /// Wraps `value`, choosing its encoding for the request `rqctx`.
+    pub fn new<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+        value: T,
+    ) -> Self {
+        let encoding = preferred_encoding(
+            rqctx.request.headers(),
+            &rqctx.endpoint.encodings,
+        )
+        .unwrap_or(ApiEndpointBodyContentType::Json);
+        Negotiated { value, encoding }
+    }
+}
+
+impl<T> HttpResponseContent for Negotiated<T>
+where
+    T: JsonSchema + Serialize + Send + Sync + 'static,
+{
+    fn to_response(
+        self,
+        builder: http::response::Builder,
+    ) -> HttpHandlerResult {
+        match self.encoding {
+            #[cfg(feature = "cbor")]
+            ApiEndpointBodyContentType::Cbor => {
+                Cbor(self.value).to_response(builder)
+            }
+            #[cfg(feature = "msgpack")]
+            ApiEndpointBodyContentType::MsgPack => {
+                MsgPack(self.value).to_response(builder)
+            }
+            // `ApiEndpoint::encoding` only offers the encodings above and JSON.
+            _ => HttpResponseContent::to_response(self.value, builder),
+        }
+    }
+
+    fn content_metadata() -> Option<ApiSchemaGenerator> {
+        <T as HttpResponseContent>::content_metadata()
+    }
+
+    fn content_types() -> Vec<&'static str> {
+        // The endpoint's encodings are documented instead.
+        vec![]
+    }
+}
+
+/// Returns whichever of `encodings` the `Accept` headers in `headers` rank
+/// highest, preferring earlier ones in a tie.  This is the first encoding if
+/// there are no `Accept` headers or none of the encodings is acceptable, and
+/// `None` only if `encodings` is empty.
+fn preferred_encoding(
+    headers: &HeaderMap,
+    encodings: &[ApiEndpointBodyContentType],
+) -> Option<ApiEndpointBodyContentType> {
+    // For each encoding, the quality of the most specific media range that
+    // matches it.
+    let mut matches: Vec<Option<(u8, f32)>> = vec![None; encodings.len()];
+    let ranges = headers
+        .get_all(http::header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','));
+    for range in ranges {
+        let mut params = range.split(';');
+        let media_range = params.next().unwrap_or("").trim().to_lowercase();
+        let quality = params
+            .filter_map(|param| param.split_once('='))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case("q"))
+            .and_then(|(_, value)| value.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        for (encoding, best) in encodings.iter().zip(matches.iter_mut()) {
+            let mime_type = encoding.mime_type();
+            let specificity = if media_range == mime_type {
+                2
+            } else if media_range == "*/*" {
+                0
+            } else if media_range
+                .strip_suffix("/*")
+                .map_or(false, |t| mime_type.split('/').next() == Some(t))
+            {
+                1
+            } else {
+                continue;
+            };
+            if best.map_or(true, |(s, _)| specificity > s) {
+                *best = Some((specificity, quality));
+            }
+        }
+    }
+
+    let mut preferred: Option<(usize, f32)> = None;
+    for (index, best) in matches.iter().enumerate() {
+        let quality = best.map_or(0.0, |(_, quality)| quality);
+        if quality > 0.0 && preferred.map_or(true, |(_, q)| quality > q) {
+            preferred = Some((index, quality));
+        }
+    }
+    let index = preferred.map_or(0, |(index, _)| index);
+    encodings.get(index).cloned()
+}
+
 /// The `HttpCodedResponse` trait is used for all of the specific response types
 /// that we provide. We use it in particular to encode the success status code
 /// and the type information of the return value.
@@ -644,6 +1339,7 @@ where
     fn response_metadata() -> ApiEndpointResponse {
         ApiEndpointResponse {
             schema: T::Body::content_metadata(),
+            content_types: T::Body::content_types(),
             success: Some(T::STATUS_CODE),
             description: Some(T::DESCRIPTION.to_string()),
             ..Default::default()
@@ -671,10 +1367,76 @@ impl<T: HttpResponseContent + Send + Sync + 'static>
     From<HttpResponseCreated<T>> for HttpHandlerResult
 {
     fn from(response: HttpResponseCreated<T>) -> HttpHandlerResult {
-        // TODO-correctness (or polish?): add Location header
         HttpResponseCreated::for_object(response.0)
     }
 }
+impl<T: HttpResponseContent + Send + Sync + 'static> HttpResponseCreated<T> {
+    /// Adds a `Location` header identifying the new resource by the path to
+    /// the endpoint `operation_id` (typically the one that fetches it) with
+    /// path parameters `params`.  The path is built by
+    /// [`RequestContext::path_for`], so it always names a registered route.
+    ///
+    /// ```
+    /// use dropshot::{endpoint, HttpError, HttpResponseCreated};
+    /// use dropshot::{HttpResponseCreatedAt, HttpResponseOk, Path};
+    /// use dropshot::{RequestContext, TypedBody};
+    /// use schemars::JsonSchema;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Deserialize, JsonSchema, Serialize)]
+    /// struct Project {
+    ///     name: String,
+    /// }
+    ///
+    /// #[derive(Deserialize, JsonSchema, Serialize)]
+    /// struct ProjectPath {
+    ///     name: String,
+    /// }
+    ///
+    /// #[endpoint { method = GET, path = "/projects/{name}" }]
+    /// async fn project_get(
+    ///     _rqctx: RequestContext<()>,
+    ///     path: Path<ProjectPath>,
+    /// ) -> Result<HttpResponseOk<Project>, HttpError> {
+    ///     Ok(HttpResponseOk(Project { name: path.into_inner().name }))
+    /// }
+    ///
+    /// #[endpoint { method = POST, path = "/projects" }]
+    /// async fn project_create(
+    ///     rqctx: RequestContext<()>,
+    ///     body: TypedBody<Project>,
+    /// ) -> Result<HttpResponseCreatedAt<Project>, HttpError> {
+    ///     let project = body.into_inner();
+    ///     let params = ProjectPath { name: project.name.clone() };
+    ///     HttpResponseCreated(project).with_location(
+    ///         &rqctx,
+    ///         "project_get",
+    ///         &params,
+    ///     )
+    /// }
+    /// ```
+    pub fn with_location<Context: ServerContext, P: Serialize>(
+        self,
+        rqctx: &RequestContext<Context>,
+        operation_id: &str,
+        params: &P,
+    ) -> Result<HttpResponseCreatedAt<T>, HttpError> {
+        let location = rqctx.path_for(operation_id, params)?;
+        Ok(HttpResponseHeaders::new(self, CreatedHeaders { location }))
+    }
+}
+
+/// Describes the headers of a 201 response that identifies the new resource.
+#[derive(JsonSchema, Serialize)]
+#[doc(hidden)]
+pub struct CreatedHeaders {
+    /// HTTP "Location" header, identifying the new resource
+    location: String,
+}
+
+/// See [`HttpResponseCreated::with_location`]
+pub type HttpResponseCreatedAt<T> =
+    HttpResponseHeaders<HttpResponseCreated<T>, CreatedHeaders>;
 
 /// `HttpResponseAccepted<T: Serialize>` wraps an object of any
 /// serializable type.  It denotes an HTTP 202 "Accepted" response whose body is
@@ -749,38 +1511,860 @@ impl From<HttpResponseUpdatedNoContent> for HttpHandlerResult {
     }
 }
 
-/// Describes headers associated with a 300-level response.
-#[derive(JsonSchema, Serialize)]
-#[doc(hidden)]
-pub struct RedirectHeaders {
-    /// HTTP "Location" header
-    // What type should we use to represent header values?
-    //
-    // It's tempting to use `http::HeaderValue` here.  But in HTTP, header
-    // values can contain bytes that aren't valid Rust strings.  See
-    // `http::header::HeaderValue`.  We could propagate this nonsense all the
-    // way to the OpenAPI spec, encoding the Location header as, say,
-    // base64-encoded bytes.  This sounds really annoying to consumers.  It's
-    // also a fair bit more work to implement.  We'd need to create a separate
-    // type for this field so that we can impl `Serialize` and `JsonSchema` on
-    // it, and we'd need to also impl serialization of byte sequences in
-    // `MapSerializer`.  Ugh.
-    //
-    // We just use `String`.  This might contain values that aren't valid in
-    // HTTP response headers.  But we can at least validate that at runtime, and
-    // it sure is easier to implement!
-    location: String,
+/// `HttpResponsePartialContent<T>` wraps a response body that may be just a
+/// part of the resource's content, for endpoints that support range requests
+/// (see [`RangeRequest`](crate::RangeRequest)).  A partial response has status
+/// 206 "Partial Content" and a `Content-Range` header identifying the part;
+/// otherwise, the status is 200 "OK".  Either way, the `Accept-Ranges` header
+/// advertises support for byte ranges.
+pub struct HttpResponsePartialContent<
+    T: HttpResponseContent + Send + Sync + 'static,
+> {
+    body: T,
+    content_range: Option<(Range<u64>, u64)>,
 }
+impl<T: HttpResponseContent + Send + Sync + 'static>
+    HttpResponsePartialContent<T>
+{
+    /// Returns a 200 "OK" response whose `body` is the entire content.
+    pub fn full(body: T) -> Self {
+        HttpResponsePartialContent { body, content_range: None }
+    }
 
-/// See `http_response_found()`
-pub type HttpResponseFound =
-    HttpResponseHeaders<HttpResponseFoundStatus, RedirectHeaders>;
-
-/// `http_response_found` returns an HTTP 302 "Found" response with no response
-/// body.
-///
-/// The sole argument will become the value of the `Location` header.  This is
-/// where you want to redirect the client to.
+    /// Returns a 206 "Partial Content" response whose `body` is the bytes at
+    /// offsets `range` of content `total_len` bytes long, or a 500 error if
+    /// `range` is empty or extends past the end of the content.
+    pub fn partial(
+        body: T,
+        range: Range<u64>,
+        total_len: u64,
+    ) -> Result<Self, HttpError> {
+        if range.start >= range.end || range.end > total_len {
+            return Err(HttpError::for_internal_error(format!(
+                "invalid range {}..{} of content {} bytes long",
+                range.start, range.end, total_len
+            )));
+        }
+        Ok(HttpResponsePartialContent {
+            body,
+            content_range: Some((range, total_len)),
+        })
+    }
+}
+impl<T: HttpResponseContent + Send + Sync + 'static> HttpResponse
+    for HttpResponsePartialContent<T>
+{
+    fn to_result(self) -> HttpHandlerResult {
+        let builder =
+            Response::builder().header(http::header::ACCEPT_RANGES, "bytes");
+        let builder = match self.content_range {
+            None => builder.status(StatusCode::OK),
+            Some((range, total_len)) => {
+                builder.status(StatusCode::PARTIAL_CONTENT).header(
+                    http::header::CONTENT_RANGE,
+                    content_range(&range, total_len),
+                )
+            }
+        };
+        self.body.to_response(builder)
+    }
+
+    fn response_metadata() -> ApiEndpointResponse {
+        ApiEndpointResponse {
+            schema: T::content_metadata(),
+            content_types: T::content_types(),
+            success: Some(StatusCode::OK),
+            other_statuses: vec![StatusCode::PARTIAL_CONTENT],
+            description: Some(String::from(
+                "successful operation, or part of the content (206) in \
+                 response to a range request",
+            )),
+            ..Default::default()
+        }
+    }
+}
+
+/// Declares the status codes with which an [`HttpResponseWithStatus`] may be
+/// sent, so that they can be documented in the OpenAPI document.
+pub trait ResponseStatuses: Send + Sync + 'static {
+    /// The permitted status codes.  The first is described as the endpoint's
+    /// success status.
+    const STATUS_CODES: &'static [StatusCode];
+    const DESCRIPTION: &'static str = "successful operation";
+}
+
+/// `HttpResponseWithStatus<T, S>` wraps a body (like the other response types,
+/// a serializable object or a [`FreeformBody`]) sent with a status code chosen
+/// by the handler, for responses that the fixed wrappers like
+/// [`HttpResponseOk`] can't express, such as a 409 ("Conflict") that carries
+/// the conflicting resource.  The status must be one of those declared by
+/// `S`, each of which is documented as producing the body.
+///
+/// ```
+/// use dropshot::{HttpError, HttpResponseWithStatus, ResponseStatuses};
+/// use http::StatusCode;
+///
+/// struct CreatedOrConflict;
+/// impl ResponseStatuses for CreatedOrConflict {
+///     const STATUS_CODES: &'static [StatusCode] =
+///         &[StatusCode::CREATED, StatusCode::CONFLICT];
+/// }
+///
+/// fn create(
+///     name: String,
+///     exists: bool,
+/// ) -> Result<HttpResponseWithStatus<String, CreatedOrConflict>, HttpError> {
+///     let status =
+///         if exists { StatusCode::CONFLICT } else { StatusCode::CREATED };
+///     HttpResponseWithStatus::new(status, name)
+/// }
+/// ```
+pub struct HttpResponseWithStatus<
+    T: HttpResponseContent + Send + Sync + 'static,
+    S: ResponseStatuses,
+> {
+    status: StatusCode,
+    body: T,
+    statuses: PhantomData<S>,
+}
+impl<T: HttpResponseContent + Send + Sync + 'static, S: ResponseStatuses>
+    HttpResponseWithStatus<T, S>
+{
+    /// Returns a response with status `status` and body `body`, or a 500 error
+    /// if `S` doesn't permit `status`.
+    pub fn new(status: StatusCode, body: T) -> Result<Self, HttpError> {
+        if !S::STATUS_CODES.contains(&status) {
+            return Err(HttpError::for_internal_error(format!(
+                "response status {} is not among those declared for the \
+                 endpoint",
+                status
+            )));
+        }
+        Ok(HttpResponseWithStatus { status, body, statuses: PhantomData })
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+impl<T: HttpResponseContent + Send + Sync + 'static, S: ResponseStatuses>
+    HttpResponse for HttpResponseWithStatus<T, S>
+{
+    fn to_result(self) -> HttpHandlerResult {
+        self.body.to_response(Response::builder().status(self.status))
+    }
+
+    fn response_metadata() -> ApiEndpointResponse {
+        let (success, other_statuses) = match S::STATUS_CODES {
+            [] => (None, Vec::new()),
+            [first, rest @ ..] => (Some(*first), rest.to_vec()),
+        };
+        ApiEndpointResponse {
+            schema: T::content_metadata(),
+            content_types: T::content_types(),
+            success,
+            other_statuses,
+            description: Some(S::DESCRIPTION.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Formats the value of a `Content-Range` header for the bytes at offsets
+/// `range` of content `total_len` bytes long.
+fn content_range(range: &Range<u64>, total_len: u64) -> String {
+    format!("bytes {}-{}/{}", range.start, range.end - 1, total_len)
+}
+
+/// `HttpResponseFile` sends the contents of a file on disk, read with async
+/// I/O as the client consumes them rather than buffered in memory.  The
+/// response has a `Content-Length`, a `Content-Type` guessed from the file's
+/// extension (`application/octet-stream` if it has no well-known one), and a
+/// `Last-Modified` header from the file's modification time.
+///
+/// Range requests are supported by passing the request's
+/// [`RangeRequest`](crate::RangeRequest) to
+/// [`with_range`](HttpResponseFile::with_range), as with
+/// [`HttpResponsePartialContent`].
+///
+/// ```
+/// use dropshot::{endpoint, HttpError, HttpResponseFile, RangeRequest};
+/// use dropshot::RequestContext;
+///
+/// #[endpoint { method = GET, path = "/artifact" }]
+/// async fn artifact(
+///     _rqctx: RequestContext<()>,
+///     range: RangeRequest,
+/// ) -> Result<HttpResponseFile, HttpError> {
+///     HttpResponseFile::open("/var/artifacts/latest.tar.gz")
+///         .await?
+///         .with_range(&range)
+/// }
+/// ```
+#[derive(Debug)]
+pub struct HttpResponseFile {
+    file: std::fs::File,
+    len: u64,
+    content_type: String,
+    last_modified: Option<DateTime<Utc>>,
+    etag: Option<EntityTag>,
+    range: Option<Range<u64>>,
+}
+
+impl HttpResponseFile {
+    /// Opens the file at `path` to be sent.  If there's no regular file there,
+    /// this fails with a 404 ("Not Found") error.
+    pub async fn open<P: AsRef<std::path::Path>>(
+        path: P,
+    ) -> Result<Self, HttpError> {
+        let path = path.as_ref();
+        let error = |e: std::io::Error| match e.kind() {
+            std::io::ErrorKind::NotFound => HttpError::for_not_found(
+                None,
+                format!("opening {:?}: {:#}", path, e),
+            ),
+            _ => HttpError::for_internal_error(format!(
+                "opening {:?}: {:#}",
+                path, e
+            )),
+        };
+        let file = tokio::fs::File::open(path).await.map_err(error)?;
+        let metadata = file.metadata().await.map_err(error)?;
+        if !metadata.is_file() {
+            return Err(HttpError::for_not_found(
+                None,
+                format!("opening {:?}: not a regular file", path),
+            ));
+        }
+        Ok(HttpResponseFile {
+            file: file.into_std().await,
+            len: metadata.len(),
+            content_type: mime_guess::from_path(path)
+                .first_or_octet_stream()
+                .to_string(),
+            last_modified: metadata.modified().ok().map(DateTime::from),
+            etag: None,
+            range: None,
+        })
+    }
+
+    /// Overrides the content type guessed from the file's extension.
+    pub fn with_content_type<S: Into<String>>(
+        mut self,
+        content_type: S,
+    ) -> Self {
+        self.content_type = content_type.into();
+        self
+    }
+
+    /// Sends `etag` in the response's `ETag` header.
+    pub fn with_etag(mut self, etag: EntityTag) -> Self {
+        self.etag = Some(etag);
+        self
+    }
+
+    /// Sends only the part of the file selected by the request's `Range`
+    /// header, if it has one, with status 206 ("Partial Content").  See
+    /// [`RangeRequest::single_range`](crate::RangeRequest::single_range),
+    /// whose 416 ("Range Not Satisfiable") error this returns if the range
+    /// lies beyond the end of the file.
+    pub fn with_range(
+        mut self,
+        range: &RangeRequest,
+    ) -> Result<Self, HttpError> {
+        self.range = range.single_range(self.len)?;
+        Ok(self)
+    }
+
+    /// Returns the size of the file, in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns whether the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the file's modification time, which can be passed to
+    /// [`Preconditions::evaluate`](crate::Preconditions::evaluate).
+    pub fn last_modified(&self) -> Option<DateTime<Utc>> {
+        self.last_modified
+    }
+}
+
+impl HttpResponse for HttpResponseFile {
+    fn to_result(self) -> HttpHandlerResult {
+        let mut file = self.file;
+        let range = self.range.clone().unwrap_or(0..self.len);
+        file.seek(std::io::SeekFrom::Start(range.start)).map_err(|e| {
+            HttpError::for_internal_error(format!("seeking file: {:#}", e))
+        })?;
+        let reader =
+            tokio::fs::File::from_std(file).take(range.end - range.start);
+
+        let mut builder = Response::builder()
+            .header(http::header::ACCEPT_RANGES, "bytes")
+            .header(http::header::CONTENT_TYPE, self.content_type)
+            .header(http::header::CONTENT_LENGTH, range.end - range.start);
+        if let Some(last_modified) = self.last_modified {
+            builder = builder
+                .header(http::header::LAST_MODIFIED, http_date(last_modified));
+        }
+        if let Some(etag) = self.etag {
+            builder = builder.header(http::header::ETAG, etag.to_string());
+        }
+        builder = match self.range {
+            None => builder.status(StatusCode::OK),
+            Some(range) => builder.status(StatusCode::PARTIAL_CONTENT).header(
+                http::header::CONTENT_RANGE,
+                content_range(&range, self.len),
+            ),
+        };
+        Ok(builder.body(FreeformBody::from_async_read(reader).0)?)
+    }
+
+    fn response_metadata() -> ApiEndpointResponse {
+        ApiEndpointResponse {
+            success: Some(StatusCode::OK),
+            description: Some(String::from(
+                "successful operation, or part of the content (206) in \
+                 response to a range request",
+            )),
+            ..Default::default()
+        }
+    }
+}
+
+/// `HttpResponseNdjson<T>` is a 200 ("OK") response whose body is a stream of
+/// items of type `T`, each sent as a line of JSON (`application/x-ndjson`) as
+/// soon as it's produced.  This lets a large collection be scanned in one
+/// request without buffering it all, as an alternative to pagination for
+/// clients that prefer it.  Items are only serialized as the client consumes
+/// them.  If the stream yields an error, the response is aborted, so clients
+/// can tell a truncated body from a complete one.
+///
+/// The OpenAPI document describes the body with the schema of a single item.
+///
+/// ```
+/// use dropshot::{endpoint, HttpError, HttpResponseNdjson, RequestContext};
+/// use futures::StreamExt;
+///
+/// #[endpoint { method = GET, path = "/events" }]
+/// async fn events(
+///     _rqctx: RequestContext<()>,
+/// ) -> Result<HttpResponseNdjson<u64>, HttpError> {
+///     let items = futures::stream::iter(0..100_000u64)
+///         .map(Ok::<_, std::convert::Infallible>);
+///     Ok(HttpResponseNdjson::new(items))
+/// }
+/// ```
+pub struct HttpResponseNdjson<T>
+where
+    T: JsonSchema + Serialize + Send + Sync + 'static,
+{
+    body: Body,
+    items: PhantomData<T>,
+}
+
+impl<T> HttpResponseNdjson<T>
+where
+    T: JsonSchema + Serialize + Send + Sync + 'static,
+{
+    pub fn new<S, E>(items: S) -> Self
+    where
+        S: futures::Stream<Item = Result<T, E>> + Send + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>> + 'static,
+    {
+        type BoxError = Box<dyn std::error::Error + Send + Sync>;
+        let lines = items.map(|item| {
+            let item = item.map_err(Into::<BoxError>::into)?;
+            let mut line = serde_json::to_vec(&item)?;
+            line.push(b'\n');
+            Ok::<_, BoxError>(bytes::Bytes::from(line))
+        });
+        HttpResponseNdjson {
+            body: Body::wrap_stream(lines),
+            items: PhantomData,
+        }
+    }
+}
+
+impl<T> HttpResponse for HttpResponseNdjson<T>
+where
+    T: JsonSchema + Serialize + Send + Sync + 'static,
+{
+    fn to_result(self) -> HttpHandlerResult {
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header(http::header::CONTENT_TYPE, CONTENT_TYPE_NDJSON)
+            .body(self.body)?)
+    }
+
+    fn response_metadata() -> ApiEndpointResponse {
+        ApiEndpointResponse {
+            schema: <T as HttpResponseContent>::content_metadata(),
+            content_types: vec![CONTENT_TYPE_NDJSON],
+            success: Some(StatusCode::OK),
+            description: Some(String::from(
+                "successful operation, streaming one item per line",
+            )),
+            ..Default::default()
+        }
+    }
+}
+
+/// `HttpResponseCsv<T>` is a 200 ("OK") response whose body is a table of rows
+/// of type `T` sent as comma-separated values (`text/csv`), as for a
+/// spreadsheet export.  `T` is typically a struct of scalar fields: the first
+/// row is preceded by a header row naming them, and values are quoted as
+/// needed (see the [`csv`] crate).  An empty table has an empty body.
+///
+/// Like [`HttpResponseNdjson`], rows are serialized and sent as the client
+/// consumes them, and an error from the stream aborts the response.  The
+/// OpenAPI document describes the body with the schema of a single row.  This
+/// requires the `"csv"` feature.
+///
+/// ```
+/// use dropshot::{endpoint, HttpError, HttpResponseCsv, RequestContext};
+/// use schemars::JsonSchema;
+/// use serde::Serialize;
+///
+/// #[derive(JsonSchema, Serialize)]
+/// struct Sale {
+///     region: String,
+///     total: u64,
+/// }
+///
+/// #[endpoint { method = GET, path = "/sales.csv" }]
+/// async fn sales(
+///     _rqctx: RequestContext<()>,
+/// ) -> Result<HttpResponseCsv<Sale>, HttpError> {
+///     let sales = vec![
+///         Sale { region: String::from("north, upper"), total: 12 },
+///         Sale { region: String::from("south"), total: 7 },
+///     ];
+///     Ok(HttpResponseCsv::from_rows(sales))
+/// }
+/// ```
+#[cfg(feature = "csv")]
+pub struct HttpResponseCsv<T>
+where
+    T: JsonSchema + Serialize + Send + Sync + 'static,
+{
+    body: Body,
+    rows: PhantomData<T>,
+}
+
+#[cfg(feature = "csv")]
+impl<T> HttpResponseCsv<T>
+where
+    T: JsonSchema + Serialize + Send + Sync + 'static,
+{
+    /// Sends the rows produced by `rows`.
+    pub fn new<S, E>(rows: S) -> Self
+    where
+        S: futures::Stream<Item = Result<T, E>> + Send + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>> + 'static,
+    {
+        type BoxError = Box<dyn std::error::Error + Send + Sync>;
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        let lines = rows.map(move |row| {
+            writer.serialize(row.map_err(Into::<BoxError>::into)?)?;
+            writer.flush()?;
+            let line = std::mem::take(writer.get_mut());
+            Ok::<_, BoxError>(bytes::Bytes::from(line))
+        });
+        HttpResponseCsv { body: Body::wrap_stream(lines), rows: PhantomData }
+    }
+
+    /// Sends the rows in `rows`, which may be a collection or a (`Send`)
+    /// iterator.
+    pub fn from_rows<I>(rows: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: Send + 'static,
+    {
+        let rows = rows.into_iter().map(Ok::<_, std::convert::Infallible>);
+        Self::new(futures::stream::iter(rows))
+    }
+}
+
+#[cfg(feature = "csv")]
+impl<T> HttpResponse for HttpResponseCsv<T>
+where
+    T: JsonSchema + Serialize + Send + Sync + 'static,
+{
+    fn to_result(self) -> HttpHandlerResult {
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header(http::header::CONTENT_TYPE, CONTENT_TYPE_CSV)
+            .body(self.body)?)
+    }
+
+    fn response_metadata() -> ApiEndpointResponse {
+        ApiEndpointResponse {
+            schema: <T as HttpResponseContent>::content_metadata(),
+            content_types: vec![CONTENT_TYPE_CSV],
+            success: Some(StatusCode::OK),
+            description: Some(String::from(
+                "successful operation, with one row per item",
+            )),
+            ..Default::default()
+        }
+    }
+}
+
+/// Describes a required response header with a string value.
+pub(crate) fn string_header(
+    name: &str,
+    description: &str,
+) -> ApiEndpointHeader {
+    ApiEndpointHeader {
+        name: name.to_string(),
+        description: Some(description.to_string()),
+        schema: ApiSchemaGenerator::Static {
+            schema: Box::new(
+                schemars::schema::SchemaObject {
+                    instance_type: Some(
+                        schemars::schema::InstanceType::String.into(),
+                    ),
+                    ..Default::default()
+                }
+                .into(),
+            ),
+            dependencies: indexmap::IndexMap::new(),
+        },
+        required: true,
+    }
+}
+
+/// `HttpResponseTagged` wraps a response with the entity tag identifying the
+/// current version of its content, which is sent in the `ETag` header.  When
+/// the `If-None-Match` header of a `GET` or `HEAD` request already names that
+/// tag (under the weak comparison), Dropshot answers with a 304 ("Not
+/// Modified") and no body, without serializing the wrapped response at all.
+///
+/// The tag must change whenever the wrapped response would, and is typically
+/// something cheaper to come by than the response itself, like a generation
+/// number or content hash kept alongside the resource.
+///
+/// ```
+/// use dropshot::{endpoint, EntityTag, HttpError, HttpResponseOk};
+/// use dropshot::{HttpResponseTagged, RequestContext};
+///
+/// #[endpoint { method = GET, path = "/catalog" }]
+/// async fn catalog(
+///     _rqctx: RequestContext<()>,
+/// ) -> Result<HttpResponseTagged<HttpResponseOk<Vec<String>>>, HttpError> {
+///     let generation = 3;
+///     let items = vec![String::from("widget")];
+///     Ok(HttpResponseTagged::new(
+///         EntityTag::strong(generation.to_string()),
+///         HttpResponseOk(items),
+///     ))
+/// }
+/// ```
+pub struct HttpResponseTagged<T: HttpResponse + Send + Sync + 'static> {
+    etag: EntityTag,
+    response: T,
+}
+
+impl<T: HttpResponse + Send + Sync + 'static> HttpResponseTagged<T> {
+    pub fn new(etag: EntityTag, response: T) -> Self {
+        HttpResponseTagged { etag, response }
+    }
+
+    pub fn etag(&self) -> &EntityTag {
+        &self.etag
+    }
+
+    fn etag_header(&self) -> Result<http::HeaderValue, HttpError> {
+        http::HeaderValue::try_from(self.etag.to_string()).map_err(|e| {
+            HttpError::for_internal_error(format!("invalid entity tag: {}", e))
+        })
+    }
+}
+
+impl<T: HttpResponse + Send + Sync + 'static> HttpResponse
+    for HttpResponseTagged<T>
+{
+    fn to_result(self) -> HttpHandlerResult {
+        let etag = self.etag_header()?;
+        let mut result = self.response.to_result()?;
+        result.headers_mut().insert(http::header::ETAG, etag);
+        Ok(result)
+    }
+
+    fn to_conditional_result(
+        self,
+        if_none_match: &EntityTagMatch,
+    ) -> HttpHandlerResult {
+        if !if_none_match.matches(&self.etag, EntityTag::weak_eq) {
+            return self.to_result();
+        }
+        Ok(Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header(http::header::ETAG, self.etag_header()?)
+            .body(Body::empty())?)
+    }
+
+    fn response_metadata() -> ApiEndpointResponse {
+        let mut metadata = T::response_metadata();
+        metadata.headers.push(string_header(
+            "ETag",
+            "entity tag identifying the current version of the content",
+        ));
+        metadata
+    }
+}
+
+/// `HttpResponseAttachment` wraps a response whose body the client should save
+/// as a file (a download), such as an [`HttpResponseFile`] or a streaming
+/// [`FreeformBody`], adding a `Content-Disposition: attachment` header with
+/// the suggested file name.  A name that isn't printable ASCII is encoded as
+/// RFC 6266 and RFC 8187 describe, along with an ASCII approximation for
+/// older clients.
+///
+/// ```
+/// use dropshot::{endpoint, HttpError, HttpResponseAttachment};
+/// use dropshot::{HttpResponseFile, RequestContext};
+///
+/// #[endpoint { method = GET, path = "/export" }]
+/// async fn export(
+///     _rqctx: RequestContext<()>,
+/// ) -> Result<HttpResponseAttachment<HttpResponseFile>, HttpError> {
+///     let file = HttpResponseFile::open("/var/exports/latest.csv").await?;
+///     Ok(HttpResponseAttachment::new("données-2023.csv", file))
+/// }
+/// ```
+pub struct HttpResponseAttachment<T: HttpResponse + Send + Sync + 'static> {
+    filename: String,
+    response: T,
+}
+
+impl<T: HttpResponse + Send + Sync + 'static> HttpResponseAttachment<T> {
+    pub fn new<S: Into<String>>(filename: S, response: T) -> Self {
+        HttpResponseAttachment { filename: filename.into(), response }
+    }
+
+    fn add_header(
+        filename: &str,
+        mut result: Response<Body>,
+    ) -> HttpHandlerResult {
+        let value = http_content_disposition_attachment(filename);
+        let value = http::HeaderValue::try_from(value).map_err(|e| {
+            HttpError::for_internal_error(format!(
+                "invalid attachment file name {:?}: {}",
+                filename, e
+            ))
+        })?;
+        result.headers_mut().insert(http::header::CONTENT_DISPOSITION, value);
+        Ok(result)
+    }
+}
+
+impl<T: HttpResponse + Send + Sync + 'static> HttpResponse
+    for HttpResponseAttachment<T>
+{
+    fn to_result(self) -> HttpHandlerResult {
+        Self::add_header(&self.filename, self.response.to_result()?)
+    }
+
+    fn to_conditional_result(
+        self,
+        if_none_match: &EntityTagMatch,
+    ) -> HttpHandlerResult {
+        let result = self.response.to_conditional_result(if_none_match)?;
+        Self::add_header(&self.filename, result)
+    }
+
+    fn response_metadata() -> ApiEndpointResponse {
+        let mut metadata = T::response_metadata();
+        metadata.headers.push(string_header(
+            "Content-Disposition",
+            "identifies the content as a file to be saved, and suggests its \
+             name",
+        ));
+        metadata
+    }
+}
+
+/// `HttpResponseCached` wraps a response with the [`CacheControl`] directives
+/// that say how caches may store and reuse it, sent in the `Cache-Control`
+/// header.  The header is also sent with the 304 ("Not Modified") response
+/// that a wrapped [`HttpResponseTagged`] may produce, so that revalidating a
+/// cached response refreshes it.
+///
+/// ```
+/// use dropshot::{endpoint, CacheControl, HttpError, HttpResponseCached};
+/// use dropshot::{HttpResponseOk, RequestContext};
+/// use std::time::Duration;
+///
+/// #[endpoint { method = GET, path = "/regions" }]
+/// async fn regions(
+///     _rqctx: RequestContext<()>,
+/// ) -> Result<HttpResponseCached<HttpResponseOk<Vec<String>>>, HttpError> {
+///     Ok(HttpResponseCached::new(
+///         CacheControl::new().public().max_age(Duration::from_secs(3600)),
+///         HttpResponseOk(vec![String::from("us-east-1")]),
+///     ))
+/// }
+/// ```
+pub struct HttpResponseCached<T: HttpResponse + Send + Sync + 'static> {
+    cache_control: CacheControl,
+    response: T,
+}
+
+impl<T: HttpResponse + Send + Sync + 'static> HttpResponseCached<T> {
+    pub fn new(cache_control: CacheControl, response: T) -> Self {
+        HttpResponseCached { cache_control, response }
+    }
+
+    fn add_header(
+        cache_control: &CacheControl,
+        mut result: Response<Body>,
+    ) -> HttpHandlerResult {
+        let value = http::HeaderValue::try_from(cache_control.to_string())
+            .map_err(|e| {
+                HttpError::for_internal_error(format!(
+                    "invalid cache control: {}",
+                    e
+                ))
+            })?;
+        result.headers_mut().insert(http::header::CACHE_CONTROL, value);
+        Ok(result)
+    }
+}
+
+impl<T: HttpResponse + Send + Sync + 'static> HttpResponse
+    for HttpResponseCached<T>
+{
+    fn to_result(self) -> HttpHandlerResult {
+        Self::add_header(&self.cache_control, self.response.to_result()?)
+    }
+
+    fn to_conditional_result(
+        self,
+        if_none_match: &EntityTagMatch,
+    ) -> HttpHandlerResult {
+        let result = self.response.to_conditional_result(if_none_match)?;
+        Self::add_header(&self.cache_control, result)
+    }
+
+    fn response_metadata() -> ApiEndpointResponse {
+        let mut metadata = T::response_metadata();
+        metadata.headers.push(string_header(
+            "Cache-Control",
+            "how caches may store and reuse the response",
+        ));
+        metadata
+    }
+}
+
+/// `HttpResponseOkPage` is a 200 ("OK") response with a page of results, like
+/// `HttpResponseOk<ResultsPage<T>>`, that also links to the next page of
+/// results (if there is one) in an RFC 8288 (formerly RFC 5988) `Link` header
+/// with `rel="next"`.  Generic HTTP clients can then follow the pages without
+/// parsing the body.
+///
+/// The link is the URL of the request being handled (made absolute with
+/// [`RequestContext::absolute_url`]) with its `page_token` query parameter
+/// replaced by the token for the next page.  Other query parameters, like
+/// `limit`, are preserved.
+pub struct HttpResponseOkPage<T: JsonSchema + Serialize + Send + Sync + 'static>
+{
+    page: ResultsPage<T>,
+    next_link: Option<String>,
+}
+
+impl<T: JsonSchema + Serialize + Send + Sync + 'static> HttpResponseOkPage<T> {
+    pub fn new<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+        page: ResultsPage<T>,
+    ) -> Self {
+        let next_link = page.next_page.as_ref().map(|token| {
+            let uri = rqctx.request.uri();
+            let query = uri.query().unwrap_or("");
+            let mut next_query =
+                form_urlencoded::Serializer::new(String::new());
+            for (name, value) in form_urlencoded::parse(query.as_bytes()) {
+                if name != "page_token" {
+                    next_query.append_pair(&name, &value);
+                }
+            }
+            next_query.append_pair("page_token", token);
+            rqctx.absolute_url(&format!(
+                "{}?{}",
+                uri.path(),
+                next_query.finish()
+            ))
+        });
+        HttpResponseOkPage { page, next_link }
+    }
+}
+
+impl<T: JsonSchema + Serialize + Send + Sync + 'static> HttpResponse
+    for HttpResponseOkPage<T>
+{
+    fn to_result(self) -> HttpHandlerResult {
+        let mut result = HttpResponseOk(self.page).to_result()?;
+        if let Some(next_link) = self.next_link {
+            let value = http::HeaderValue::try_from(format!(
+                "<{}>; rel=\"next\"",
+                next_link
+            ))
+            .map_err(|e| {
+                HttpError::for_internal_error(format!("invalid link: {}", e))
+            })?;
+            result.headers_mut().insert(http::header::LINK, value);
+        }
+        Ok(result)
+    }
+
+    fn response_metadata() -> ApiEndpointResponse {
+        let mut metadata =
+            HttpResponseOk::<ResultsPage<T>>::response_metadata();
+        metadata.headers.push(string_header(
+            "Link",
+            "link to the next page of results, if there is one",
+        ));
+        metadata
+    }
+}
+
+/// Describes headers associated with a 300-level response.
+#[derive(JsonSchema, Serialize)]
+#[doc(hidden)]
+pub struct RedirectHeaders {
+    /// HTTP "Location" header
+    // What type should we use to represent header values?
+    //
+    // It's tempting to use `http::HeaderValue` here.  But in HTTP, header
+    // values can contain bytes that aren't valid Rust strings.  See
+    // `http::header::HeaderValue`.  We could propagate this nonsense all the
+    // way to the OpenAPI spec, encoding the Location header as, say,
+    // base64-encoded bytes.  This sounds really annoying to consumers.  It's
+    // also a fair bit more work to implement.  We'd need to create a separate
+    // type for this field so that we can impl `Serialize` and `JsonSchema` on
+    // it, and we'd need to also impl serialization of byte sequences in
+    // `MapSerializer`.  Ugh.
+    //
+    // We just use `String`.  This might contain values that aren't valid in
+    // HTTP response headers.  But we can at least validate that at runtime, and
+    // it sure is easier to implement!
+    location: String,
+}
+
+/// See `http_response_found()`
+pub type HttpResponseFound =
+    HttpResponseHeaders<HttpResponseFoundStatus, RedirectHeaders>;
+
+/// `http_response_found` returns an HTTP 302 "Found" response with no response
+/// body.
+///
+/// The sole argument will become the value of the `Location` header.  This is
+/// where you want to redirect the client to.
 ///
 /// Per MDN and RFC 9110 S15.4.3, you might want to use 307 ("Temporary
 /// Redirect") or 303 ("See Other") instead.
@@ -901,26 +2485,101 @@ impl From<HttpResponseTemporaryRedirectStatus> for HttpHandlerResult {
     }
 }
 
+/// See `http_response_permanent_redirect()`
+pub type HttpResponsePermanentRedirect =
+    HttpResponseHeaders<HttpResponsePermanentRedirectStatus, RedirectHeaders>;
+
+/// `http_response_permanent_redirect` represents an HTTP 308 "Permanent
+/// Redirect" response with no response body.
+///
+/// The sole argument will become the value of the `Location` header.  This is
+/// where you want to redirect the client to.
+///
+/// Use this when the resource has moved for good, so that clients may update
+/// their links.  Like 307 "Temporary Redirect", this directs the client to use
+/// the same request method and body when it makes the follow-up request.
+pub fn http_response_permanent_redirect(
+    location: String,
+) -> Result<HttpResponsePermanentRedirect, HttpError> {
+    let _ = http::HeaderValue::from_str(&location)
+        .map_err(|e| http_redirect_error(e, &location))?;
+    Ok(HttpResponseHeaders::new(
+        HttpResponsePermanentRedirectStatus,
+        RedirectHeaders { location },
+    ))
+}
+
+/// This internal type impls HttpCodedResponse.  Consumers should use
+/// `HttpResponsePermanentRedirect` instead, which includes metadata about the
+/// `Location` header.
+#[doc(hidden)]
+pub struct HttpResponsePermanentRedirectStatus;
+impl HttpCodedResponse for HttpResponsePermanentRedirectStatus {
+    type Body = Empty;
+    const STATUS_CODE: StatusCode = StatusCode::PERMANENT_REDIRECT;
+    const DESCRIPTION: &'static str = "redirect (permanent redirect)";
+}
+impl From<HttpResponsePermanentRedirectStatus> for HttpHandlerResult {
+    fn from(_: HttpResponsePermanentRedirectStatus) -> HttpHandlerResult {
+        HttpResponsePermanentRedirectStatus::for_object(Empty)
+    }
+}
+
 #[derive(Serialize, JsonSchema)]
 pub struct NoHeaders {}
 
 /// `HttpResponseHeaders` is a wrapper for responses that include both
-/// structured and unstructured headers. The first type parameter is a
-/// `HttpTypedResponse` that provides the structure of the response body.
+/// structured and unstructured headers. The first type parameter is the
+/// response being wrapped (such as an [`HttpResponseOk`] or
+/// [`HttpResponseWithStatus`]), which provides the status and body.
 /// The second type parameter is an optional struct that enumerates named
 /// headers that are included in the response. In addition to those (optional)
 /// named headers, consumers may add additional headers via the `headers_mut`
 /// interface. Unnamed headers override named headers in the case of naming
 /// conflicts.
+///
+/// The named headers are documented in the OpenAPI document, using the
+/// struct's field names (so `#[serde(rename = "...")]` is typically needed)
+/// and doc comments.  Their values may be strings, numbers, booleans, or
+/// unit enum variants, and a field of type `Option` makes the header optional:
+///
+/// ```
+/// use dropshot::{HttpResponseHeaders, HttpResponseOk};
+/// use schemars::JsonSchema;
+/// use serde::Serialize;
+///
+/// #[derive(JsonSchema, Serialize)]
+/// struct RateLimitHeaders {
+///     /// requests remaining in the current window
+///     #[serde(rename = "x-rate-limit-remaining")]
+///     remaining: u32,
+///     /// seconds until the window resets, if it's been used up
+///     #[serde(rename = "retry-after")]
+///     retry_after: Option<u64>,
+/// }
+///
+/// fn rate_limited(
+///     body: String,
+///     remaining: u32,
+/// ) -> HttpResponseHeaders<HttpResponseOk<String>, RateLimitHeaders> {
+///     let retry_after = (remaining == 0).then_some(60);
+///     HttpResponseHeaders::new(
+///         HttpResponseOk(body),
+///         RateLimitHeaders { remaining, retry_after },
+///     )
+/// }
+/// ```
 pub struct HttpResponseHeaders<
-    T: HttpCodedResponse,
+    T: HttpResponse + Send + Sync + 'static,
     H: JsonSchema + Serialize + Send + Sync + 'static = NoHeaders,
 > {
     body: T,
     structured_headers: H,
     other_headers: HeaderMap,
 }
-impl<T: HttpCodedResponse> HttpResponseHeaders<T, NoHeaders> {
+impl<T: HttpResponse + Send + Sync + 'static>
+    HttpResponseHeaders<T, NoHeaders>
+{
     pub fn new_unnamed(body: T) -> Self {
         Self {
             body,
@@ -930,7 +2589,7 @@ impl<T: HttpCodedResponse> HttpResponseHeaders<T, NoHeaders> {
     }
 }
 impl<
-        T: HttpCodedResponse,
+        T: HttpResponse + Send + Sync + 'static,
         H: JsonSchema + Serialize + Send + Sync + 'static,
     > HttpResponseHeaders<T, H>
 {
@@ -945,18 +2604,14 @@ impl<
     pub fn headers_mut(&mut self) -> &mut HeaderMap {
         &mut self.other_headers
     }
-}
-impl<
-        T: HttpCodedResponse,
-        H: JsonSchema + Serialize + Send + Sync + 'static,
-    > HttpResponse for HttpResponseHeaders<T, H>
-{
-    fn to_result(self) -> HttpHandlerResult {
-        let HttpResponseHeaders { body, structured_headers, other_headers } =
-            self;
-        // Compute the body.
-        let mut result = body.into()?;
-        // Add in both the structured and other headers.
+
+    /// Adds the structured and other headers to `result`, the response
+    /// produced by the wrapped body.
+    fn add_headers(
+        mut result: Response<Body>,
+        structured_headers: H,
+        other_headers: HeaderMap,
+    ) -> HttpHandlerResult {
         let headers = result.headers_mut();
         let header_map = to_map(&structured_headers).map_err(|e| {
             HttpError::for_internal_error(format!(
@@ -977,6 +2632,30 @@ impl<
 
         Ok(result)
     }
+}
+impl<
+        T: HttpResponse + Send + Sync + 'static,
+        H: JsonSchema + Serialize + Send + Sync + 'static,
+    > HttpResponse for HttpResponseHeaders<T, H>
+{
+    fn to_result(self) -> HttpHandlerResult {
+        let HttpResponseHeaders { body, structured_headers, other_headers } =
+            self;
+        Self::add_headers(body.to_result()?, structured_headers, other_headers)
+    }
+
+    fn to_conditional_result(
+        self,
+        if_none_match: &EntityTagMatch,
+    ) -> HttpHandlerResult {
+        let HttpResponseHeaders { body, structured_headers, other_headers } =
+            self;
+        Self::add_headers(
+            body.to_conditional_result(if_none_match)?,
+            structured_headers,
+            other_headers,
+        )
+    }
 
     fn response_metadata() -> ApiEndpointResponse {
         let mut metadata = T::response_metadata();
@@ -1004,7 +2683,68 @@ impl<
             })
             .collect::<Vec<_>>();
 
-        metadata.headers = headers;
+        metadata.headers.extend(headers);
         metadata
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::preferred_encoding;
+    use crate::api_description::ApiEndpointBodyContentType;
+    use http::header::ACCEPT;
+    use http::HeaderMap;
+
+    fn preferred(
+        accept: &[&str],
+        encodings: &[ApiEndpointBodyContentType],
+    ) -> Option<ApiEndpointBodyContentType> {
+        let mut headers = HeaderMap::new();
+        for value in accept {
+            headers.append(ACCEPT, value.parse().unwrap());
+        }
+        preferred_encoding(&headers, encodings)
+    }
+
+    #[test]
+    fn test_preferred_encoding() {
+        use ApiEndpointBodyContentType::Cbor;
+        use ApiEndpointBodyContentType::Json;
+        use ApiEndpointBodyContentType::MsgPack;
+        let all = &[Json, Cbor, MsgPack];
+
+        assert_eq!(preferred(&[], &[]), None);
+        assert_eq!(preferred(&["application/cbor"], &[]), None);
+        assert_eq!(preferred(&[], all), Some(Json));
+        assert_eq!(preferred(&["*/*"], all), Some(Json));
+        assert_eq!(preferred(&["text/html"], all), Some(Json));
+        assert_eq!(preferred(&["application/json"], all), Some(Json));
+        assert_eq!(preferred(&["application/cbor"], all), Some(Cbor));
+        assert_eq!(preferred(&["Application/CBOR; q=0.5"], all), Some(Cbor));
+        assert_eq!(preferred(&["application/cbor;q=0"], all), Some(Json));
+        assert_eq!(
+            preferred(&["application/cbor, application/json"], all),
+            Some(Json)
+        );
+        assert_eq!(
+            preferred(&["application/json;q=0.8, application/cbor"], all),
+            Some(Cbor)
+        );
+        assert_eq!(
+            preferred(
+                &["application/json; q=0.1", "application/msgpack; q=0.9"],
+                all
+            ),
+            Some(MsgPack)
+        );
+        assert_eq!(
+            preferred(&["application/*;q=0.5, application/msgpack"], all),
+            Some(MsgPack)
+        );
+        // A more specific range overrides a general one.
+        assert_eq!(
+            preferred(&["application/json;q=0, */*"], &[Json, Cbor]),
+            Some(Cbor)
+        );
+    }
+}
@@ -0,0 +1,35 @@
+// Copyright 2023 Oxide Computer Company
+
+//! HMAC-SHA256 (RFC 2104), for signing values that the server hands to clients
+//! and needs to trust when they come back
+
+use hmac::Hmac;
+use hmac::Mac;
+use sha2::Sha256;
+
+/// Returns an HMAC-SHA256 under `secret` that has been fed the concatenation
+/// of `message`.
+fn hmac_sha256_of(secret: &[u8], message: &[&[u8]]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+        .expect("HMAC accepts keys of any length");
+    for part in message {
+        mac.update(part);
+    }
+    mac
+}
+
+/// Returns the HMAC-SHA256 under `secret` of the concatenation of `message`.
+pub(crate) fn hmac_sha256(secret: &[u8], message: &[&[u8]]) -> [u8; 32] {
+    hmac_sha256_of(secret, message).finalize().into_bytes().into()
+}
+
+/// Returns whether `signature` is the HMAC-SHA256 under `secret` of the
+/// concatenation of `message`.  The comparison takes constant time, so that
+/// it does not reveal how much of a forged signature is correct.
+pub(crate) fn hmac_sha256_verify(
+    secret: &[u8],
+    message: &[&[u8]],
+    signature: &[u8],
+) -> bool {
+    hmac_sha256_of(secret, message).verify_slice(signature).is_ok()
+}
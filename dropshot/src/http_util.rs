@@ -2,8 +2,20 @@
 //! General-purpose HTTP-related facilities
 
 use bytes::Bytes;
+use chrono::DateTime;
+use chrono::Utc;
 use hyper::body::HttpBody;
+use percent_encoding::utf8_percent_encode;
+use percent_encoding::AsciiSet;
+use percent_encoding::NON_ALPHANUMERIC;
 use serde::de::DeserializeOwned;
+use std::convert::TryFrom;
+use std::pin::Pin;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
 
 use super::error::HttpError;
 use crate::from_map::from_map;
@@ -11,6 +23,10 @@ use crate::router::VariableSet;
 
 /// header name for conveying request ids ("x-request-id")
 pub const HEADER_REQUEST_ID: &str = "x-request-id";
+/// header name announcing that an endpoint is deprecated (RFC 9745)
+pub(crate) const HEADER_DEPRECATION: &str = "deprecation";
+/// header name announcing when an endpoint will stop working (RFC 8594)
+pub(crate) const HEADER_SUNSET: &str = "sunset";
 /// MIME type for raw bytes
 pub const CONTENT_TYPE_OCTET_STREAM: &str = "application/octet-stream";
 /// MIME type for plain JSON data
@@ -19,6 +35,138 @@ pub const CONTENT_TYPE_JSON: &str = "application/json";
 pub const CONTENT_TYPE_NDJSON: &str = "application/x-ndjson";
 /// MIME type for form/urlencoded data
 pub const CONTENT_TYPE_URL_ENCODED: &str = "application/x-www-form-urlencoded";
+/// MIME type for comma-separated values
+pub const CONTENT_TYPE_CSV: &str = "text/csv";
+/// MIME type for CBOR data
+pub const CONTENT_TYPE_CBOR: &str = "application/cbor";
+/// MIME type for MessagePack data
+pub const CONTENT_TYPE_MSGPACK: &str = "application/msgpack";
+/// MIME type for multipart form data
+pub const CONTENT_TYPE_MULTIPART_FORM_DATA: &str = "multipart/form-data";
+
+/// Formats `time` as an HTTP date (RFC 9110 S5.6.7), as used in headers like
+/// `Last-Modified`.
+pub(crate) fn http_date(time: DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Characters that are percent-encoded in an RFC 8187 extended header
+/// parameter value: all but `attr-char`
+const EXT_VALUE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
+/// Returns a `Content-Disposition` header value (RFC 6266) telling the client
+/// to save the response body as a file named `filename`.  A name that isn't
+/// printable ASCII is also sent in the `filename*` parameter (RFC 8187), with
+/// an approximation of it in `filename` for clients that don't understand
+/// that.
+pub(crate) fn http_content_disposition_attachment(filename: &str) -> String {
+    let fallback = filename
+        .chars()
+        .map(|c| match c {
+            ' '..='~' if c != '"' && c != '\\' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    if fallback == filename {
+        format!("attachment; filename=\"{}\"", filename)
+    } else {
+        format!(
+            "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+            fallback,
+            utf8_percent_encode(filename, EXT_VALUE)
+        )
+    }
+}
+
+/// Counts the bytes read from a request's body, to be reported when the
+/// request completes.  The server adds one of these to each request's
+/// extensions.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RequestBodyBytes(Arc<AtomicU64>);
+
+impl RequestBodyBytes {
+    pub(crate) fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn add(&self, nbytes: usize) {
+        self.0.fetch_add(nbytes as u64, Ordering::Relaxed);
+    }
+}
+
+/// A request body that adds the bytes read from it to the request's
+/// [`RequestBodyBytes`].  Everything else, including the body's size hint and
+/// trailers, comes from the wrapped body.
+#[derive(Debug)]
+pub(crate) struct CountingBody {
+    inner: hyper::Body,
+    counter: RequestBodyBytes,
+}
+
+impl CountingBody {
+    /// Splits `request` into its parts and a body counted against the
+    /// request's [`RequestBodyBytes`], if it has one.
+    pub(crate) fn from_request(
+        request: hyper::Request<hyper::Body>,
+    ) -> (http::request::Parts, CountingBody) {
+        let (parts, inner) = request.into_parts();
+        let counter = parts
+            .extensions
+            .get::<RequestBodyBytes>()
+            .cloned()
+            .unwrap_or_default();
+        (parts, CountingBody { inner, counter })
+    }
+}
+
+impl From<hyper::Body> for CountingBody {
+    fn from(inner: hyper::Body) -> Self {
+        CountingBody { inner, counter: RequestBodyBytes::default() }
+    }
+}
+
+impl HttpBody for CountingBody {
+    type Data = Bytes;
+    type Error = hyper::Error;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let poll = Pin::new(&mut self.inner).poll_data(cx);
+        if let Poll::Ready(Some(Ok(buf))) = &poll {
+            self.counter.add(buf.len());
+        }
+        poll
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> hyper::body::SizeHint {
+        self.inner.size_hint()
+    }
+}
 
 /// Reads the rest of the body from the request, dropping all the bytes.  This is
 /// useful after encountering error conditions.
@@ -43,6 +191,105 @@ where
     Ok(nbytesread)
 }
 
+/// Returns the length of the request body declared by the Content-Length header
+/// in `headers`, if there is one.  A header that is not a valid length, or
+/// multiple headers that disagree, produce a 400-level error: we cannot reliably
+/// tell where such a body ends.
+pub fn http_content_length(
+    headers: &http::HeaderMap,
+) -> Result<Option<usize>, HttpError> {
+    let mut content_length = None;
+    for value in headers.get_all(http::header::CONTENT_LENGTH) {
+        let length = value
+            .to_str()
+            .ok()
+            .filter(|v| !v.is_empty() && v.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|v| v.parse::<usize>().ok())
+            .ok_or_else(|| {
+                HttpError::for_bad_request(
+                    None,
+                    String::from("invalid Content-Length header"),
+                )
+            })?;
+        if content_length.map_or(false, |l| l != length) {
+            return Err(HttpError::for_bad_request(
+                None,
+                String::from("conflicting Content-Length headers"),
+            ));
+        }
+        content_length = Some(length);
+    }
+    Ok(content_length)
+}
+
+/// Makes the framing headers of a response consistent with its status code and
+/// body before it's sent.
+///
+/// * 1xx, 204, and 304 responses carry no body, so any Content-Length or
+///   Transfer-Encoding header is removed.
+/// * A response with a Transfer-Encoding header is never also sent with a
+///   Content-Length header.
+/// * If the body's length is known up front, any Content-Length header must
+///   match it.  A mismatch is a bug in the handler and produces a 500 error
+///   rather than a response that clients and proxies would disagree about.
+/// * A response to a HEAD request whose body length is known reports that
+///   length and is sent without the body itself.
+pub(crate) fn http_response_framing(
+    response: &mut hyper::Response<hyper::Body>,
+    is_head: bool,
+) -> Result<(), HttpError> {
+    let status = response.status();
+    let headers = response.headers_mut();
+    if status.is_informational()
+        || status == http::StatusCode::NO_CONTENT
+        || status == http::StatusCode::NOT_MODIFIED
+    {
+        headers.remove(http::header::CONTENT_LENGTH);
+        headers.remove(http::header::TRANSFER_ENCODING);
+        return Ok(());
+    }
+
+    if headers.contains_key(http::header::TRANSFER_ENCODING) {
+        headers.remove(http::header::CONTENT_LENGTH);
+        return Ok(());
+    }
+
+    let declared = http_content_length(headers).map_err(|error| {
+        HttpError::for_internal_error(format!(
+            "response: {}",
+            error.internal_message
+        ))
+    })?;
+    let actual = response
+        .body()
+        .size_hint()
+        .exact()
+        .and_then(|length| usize::try_from(length).ok());
+
+    if is_head {
+        if let Some(actual) = actual {
+            if declared.is_none() {
+                response
+                    .headers_mut()
+                    .insert(http::header::CONTENT_LENGTH, actual.into());
+            }
+            *response.body_mut() = hyper::Body::empty();
+        }
+        return Ok(());
+    }
+
+    match (declared, actual) {
+        (Some(declared), Some(actual)) if declared != actual => {
+            Err(HttpError::for_internal_error(format!(
+                "response body length ({} bytes) does not match \
+                 Content-Length header ({} bytes)",
+                actual, declared
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
 /// Given a set of variables (most immediately from a RequestContext, likely
 /// generated by the HttpRouter when routing an incoming request), extract them
 /// into an instance of type T.  This is a convenience function that reports an
@@ -91,3 +338,157 @@ pub fn http_extract_path_params<T: DeserializeOwned>(
         )
     })
 }
+
+#[cfg(test)]
+mod test {
+    use super::http_content_disposition_attachment;
+    use super::http_content_length;
+    use super::http_date;
+    use super::http_response_framing;
+    use super::CountingBody;
+    use super::RequestBodyBytes;
+    use http::header::CONTENT_LENGTH;
+    use http::header::TRANSFER_ENCODING;
+    use http::HeaderMap;
+    use http::StatusCode;
+    use hyper::body::HttpBody;
+    use hyper::Body;
+    use hyper::Request;
+    use hyper::Response;
+
+    #[test]
+    fn test_http_date() {
+        let time = chrono::DateTime::parse_from_rfc3339("1994-11-06T08:49:37Z")
+            .unwrap()
+            .into();
+        assert_eq!(http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+    }
+
+    #[test]
+    fn test_content_disposition_attachment() {
+        assert_eq!(
+            http_content_disposition_attachment("report 2023.csv"),
+            "attachment; filename=\"report 2023.csv\""
+        );
+        assert_eq!(
+            http_content_disposition_attachment("résumé \"final\".pdf"),
+            "attachment; filename=\"r_sum_ _final_.pdf\"; \
+             filename*=UTF-8''r%C3%A9sum%C3%A9%20%22final%22.pdf"
+        );
+        assert_eq!(
+            http_content_disposition_attachment("a\\b\n"),
+            "attachment; filename=\"a_b_\"; filename*=UTF-8''a%5Cb%0A"
+        );
+    }
+
+    #[test]
+    fn test_content_length() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(http_content_length(&headers).unwrap(), None);
+
+        headers.insert(CONTENT_LENGTH, "12".parse().unwrap());
+        assert_eq!(http_content_length(&headers).unwrap(), Some(12));
+
+        // Repeating the same value is allowed; disagreeing values are not.
+        headers.append(CONTENT_LENGTH, "12".parse().unwrap());
+        assert_eq!(http_content_length(&headers).unwrap(), Some(12));
+        headers.append(CONTENT_LENGTH, "13".parse().unwrap());
+        let error = http_content_length(&headers).unwrap_err();
+        assert_eq!(error.status_code, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            error.internal_message,
+            "conflicting Content-Length headers"
+        );
+
+        for bad in ["", "-1", "+1", "1.0", "0x10", "12, 12"] {
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_LENGTH, bad.parse().unwrap());
+            let error = http_content_length(&headers).unwrap_err();
+            assert_eq!(error.internal_message, "invalid Content-Length header");
+        }
+    }
+
+    fn response(
+        status: StatusCode,
+        headers: &[(http::header::HeaderName, &str)],
+        body: &'static str,
+    ) -> Response<Body> {
+        let mut builder = Response::builder().status(status);
+        for (name, value) in headers {
+            builder = builder.header(name, *value);
+        }
+        builder.body(body.into()).unwrap()
+    }
+
+    #[test]
+    fn test_response_framing() {
+        // Matching or absent Content-Length is fine.
+        let mut r = response(StatusCode::OK, &[(CONTENT_LENGTH, "5")], "hello");
+        http_response_framing(&mut r, false).unwrap();
+        assert_eq!(r.headers()[CONTENT_LENGTH], "5");
+        let mut r = response(StatusCode::OK, &[], "hello");
+        http_response_framing(&mut r, false).unwrap();
+        assert!(r.headers().get(CONTENT_LENGTH).is_none());
+
+        // A mismatched Content-Length is a server error.
+        let mut r = response(StatusCode::OK, &[(CONTENT_LENGTH, "4")], "hello");
+        let error = http_response_framing(&mut r, false).unwrap_err();
+        assert_eq!(error.status_code, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            error.internal_message,
+            "response body length (5 bytes) does not match Content-Length \
+             header (4 bytes)"
+        );
+
+        // Transfer-Encoding takes precedence over Content-Length.
+        let mut r = response(
+            StatusCode::OK,
+            &[(CONTENT_LENGTH, "4"), (TRANSFER_ENCODING, "chunked")],
+            "hello",
+        );
+        http_response_framing(&mut r, false).unwrap();
+        assert!(r.headers().get(CONTENT_LENGTH).is_none());
+
+        // 204 responses have no framing headers at all.
+        let mut r = response(
+            StatusCode::NO_CONTENT,
+            &[(CONTENT_LENGTH, "0"), (TRANSFER_ENCODING, "chunked")],
+            "",
+        );
+        http_response_framing(&mut r, false).unwrap();
+        assert!(r.headers().is_empty());
+
+        // Neither do 304 responses, even to HEAD requests.
+        let mut r = response(StatusCode::NOT_MODIFIED, &[], "");
+        http_response_framing(&mut r, true).unwrap();
+        assert!(r.headers().is_empty());
+
+        // HEAD responses report the length of the body they omit.
+        let mut r = response(StatusCode::OK, &[], "hello");
+        http_response_framing(&mut r, true).unwrap();
+        assert_eq!(r.headers()[CONTENT_LENGTH], "5");
+        assert_eq!(r.body().size_hint().exact(), Some(0));
+        let mut r = response(StatusCode::OK, &[(CONTENT_LENGTH, "5")], "");
+        http_response_framing(&mut r, true).unwrap();
+        assert_eq!(r.headers()[CONTENT_LENGTH], "5");
+    }
+
+    #[tokio::test]
+    async fn test_counting_body() {
+        let counter = RequestBodyBytes::default();
+        let mut request = Request::new(Body::from("hello"));
+        request.extensions_mut().insert(counter.clone());
+        let (_, mut body) = CountingBody::from_request(request);
+        assert_eq!(body.size_hint().exact(), Some(5));
+        assert!(!body.is_end_stream());
+        while let Some(chunk) = body.data().await {
+            chunk.unwrap();
+        }
+        assert!(body.trailers().await.unwrap().is_none());
+        assert_eq!(counter.get(), 5);
+
+        // An empty body is known to be empty before it's read.
+        let (_, body) = CountingBody::from_request(Request::new(Body::empty()));
+        assert!(body.is_end_stream());
+    }
+}
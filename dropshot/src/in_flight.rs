@@ -0,0 +1,115 @@
+// Copyright 2023 Oxide Computer Company
+//! Tracking of the requests a server is currently handling
+//!
+//! Each request is registered when it arrives and removed once its handler
+//! has produced a response (or the request is abandoned, as when the client
+//! disconnects).  This is intended for debugging: when a server seems stuck,
+//! the list of in-flight requests shows what it's stuck on.
+
+use crate::clock::Clock;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Describes a request that the server is currently handling
+#[derive(Clone, Debug, JsonSchema, Serialize)]
+pub struct InFlightRequest {
+    /// unique id assigned to the request (as in the `x-request-id` header)
+    pub request_id: String,
+    pub method: String,
+    pub uri: String,
+    /// operation id of the endpoint handling the request, if it has been
+    /// routed yet
+    pub operation_id: Option<String>,
+    pub remote_addr: SocketAddr,
+    /// time since the request arrived
+    pub elapsed: Duration,
+}
+
+/// The set of requests a server is currently handling
+#[derive(Debug, Default)]
+pub(crate) struct InFlightRequests {
+    /// in-flight requests, keyed by request id
+    requests: Mutex<HashMap<String, InFlightEntry>>,
+}
+
+#[derive(Debug)]
+struct InFlightEntry {
+    method: String,
+    uri: String,
+    operation_id: Option<String>,
+    remote_addr: SocketAddr,
+    start_time: Instant,
+}
+
+impl InFlightRequests {
+    /// Registers a request that has just arrived.  It remains registered until
+    /// the returned guard is dropped.
+    pub(crate) fn start(
+        self: &Arc<Self>,
+        request_id: &str,
+        request: &hyper::Request<hyper::Body>,
+        remote_addr: SocketAddr,
+        start_time: Instant,
+    ) -> InFlightGuard {
+        self.requests.lock().unwrap().insert(
+            request_id.to_string(),
+            InFlightEntry {
+                method: request.method().to_string(),
+                uri: request.uri().to_string(),
+                operation_id: None,
+                remote_addr,
+                start_time,
+            },
+        );
+        InFlightGuard {
+            requests: Arc::clone(self),
+            request_id: request_id.to_string(),
+        }
+    }
+
+    /// Records the endpoint to which a request was routed.
+    pub(crate) fn routed(&self, request_id: &str, operation_id: &str) {
+        if let Some(entry) = self.requests.lock().unwrap().get_mut(request_id) {
+            entry.operation_id = Some(operation_id.to_string());
+        }
+    }
+
+    /// Returns the requests currently in flight, oldest first.
+    pub(crate) fn list(&self, clock: &dyn Clock) -> Vec<InFlightRequest> {
+        let now = clock.now();
+        let mut requests = self
+            .requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(request_id, entry)| InFlightRequest {
+                request_id: request_id.clone(),
+                method: entry.method.clone(),
+                uri: entry.uri.clone(),
+                operation_id: entry.operation_id.clone(),
+                remote_addr: entry.remote_addr,
+                elapsed: now.saturating_duration_since(entry.start_time),
+            })
+            .collect::<Vec<_>>();
+        requests.sort_by(|a, b| b.elapsed.cmp(&a.elapsed));
+        requests
+    }
+}
+
+/// Removes a request from the in-flight set when dropped
+pub(crate) struct InFlightGuard {
+    requests: Arc<InFlightRequests>,
+    request_id: String,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.requests.requests.lock().unwrap().remove(&self.request_id);
+    }
+}
@@ -68,8 +68,16 @@
 //!         HttpServerStarter::new(
 //!             &ConfigDropshot {
 //!                 bind_address: "127.0.0.1:0".parse().unwrap(),
+//!                 additional_bind_addresses: Vec::new(),
+//!                 unix_socket: None,
 //!                 request_body_max_bytes: 1024,
+//!                 request_timeout_ms: None,
 //!                 tls: None,
+//!                 tls_client_auth: None,
+//!                 http2: Default::default(),
+//!                 load_shedding: None,
+//!                 external_url: None,
+//!                 trusted_proxies: Vec::new(),
 //!             },
 //!             api,
 //!             Arc::new(()),
@@ -87,7 +95,23 @@
 //! provides a few resources using shared state.
 //!
 //! For a given `ApiDescription`, you can also print out an OpenAPI spec
-//! describing the API.  See [`ApiDescription::openapi`].
+//! describing the API.  See [`ApiDescription::openapi`].  Constructing an
+//! `ApiDescription` doesn't require a server or a logger, so a program can
+//! write its spec at build or CI time, e.g. from an `openapi` subcommand as in
+//! `examples/openapi-spec.rs`.  A server can also describe itself:
+//! [`ApiDescription::register_openapi`] serves the spec at a path like
+//! `/openapi.json`, and with the `"redoc"` feature,
+//! `ApiDescription::register_redoc` serves a page that renders it as
+//! documentation, using a copy of ReDoc's script that the consumer vendors
+//! (nothing is loaded from a CDN).  Specs follow OpenAPI 3.0 unless OpenAPI
+//! 3.1 is selected with [`OpenApiDefinition::version`].
+//!
+//! [`ApiDescription::validate`] checks the whole API for problems that make for
+//! a poor spec, like operations without descriptions or unused schemas.  To
+//! check that a new version of the API doesn't break clients of an old one,
+//! compare their specs with [`compare_openapi`].  Vendor extensions (`x-`
+//! fields) can be added to operations with [`ApiEndpoint::extension`] and to
+//! the whole document with [`OpenApiDefinition::extension`].
 //!
 //!
 //! ## API Handler Functions
@@ -168,7 +192,11 @@
 //!   `HttpResponseOk<Project>`.  This means that the function will
 //!   return an HTTP 200 status code ("OK") with an object of type `Project`.
 //! * The function itself has a Rustdoc comment that will be used to document
-//!   this _endpoint_ in the OpenAPI schema.
+//!   this _endpoint_ in the OpenAPI schema.  The first line of the comment
+//!   becomes the operation's summary and the rest its description.  Likewise,
+//!   Rustdoc comments on the fields of the types used with extractors like
+//!   `Query` and `Path` become the descriptions of those parameters, and
+//!   comments on body and response types become schema descriptions.
 //!
 //! From this information, Dropshot can generate an OpenAPI specification for
 //! this API that describes the endpoint (which OpenAPI calls an "operation"),
@@ -176,6 +204,11 @@
 //! for each type of response (which can also include documentation).  This is
 //! largely known statically, though generated at runtime.
 //!
+//! Larger APIs can be assembled from separately built descriptions:
+//! [`ApiDescription::merge`] mounts the endpoints of one description beneath a
+//! path prefix of another, along with its tag definitions and security schemes,
+//! and fails if their routes or operation IDs conflict.
+//!
 //!
 //! ### `#[endpoint { ... }]` attribute parameters
 //!
@@ -190,7 +223,22 @@
 //!     path = "/path/name/with/{named}/{variables}",
 //!
 //!     // Optional fields
+//!     aliases = [ "/old/path/with/{named}/{variables}" ],
+//!     operation_id = "my_operation",
+//!     deprecated = { true | false },
+//!     deprecated_since = "2023-06-01T00:00:00Z",
+//!     sunset = "2024-01-01T00:00:00Z",
+//!     unpublished = { true | false },
 //!     tags = [ "all", "your", "OpenAPI", "tags" ],
+//!     encodings = [ "application/json", "application/cbor" ],
+//!     response_content_type = "text/plain",
+//!     strict_query = { true | false },
+//!     max_concurrency = N,
+//!     request_body_max_bytes = N,
+//!     request_timeout_ms = N,
+//!     errors = [ 404, 409 ],
+//!     security = { oauth = [ "read", "write" ], api_key = [] },
+//!     attributes = { name = "value" },
 //! }]
 //! ```
 //!
@@ -198,9 +246,79 @@
 //! for the API endpoint. These are used as part of endpoint registration and
 //! appear in the OpenAPI spec output.
 //!
+//! The aliases field lists additional paths at which the same handler is
+//! served, which is useful when renaming a route.  Each alias must use the same
+//! path variables as the canonical path.  Aliases appear in the OpenAPI spec as
+//! deprecated operations.
+//!
+//! The operation_id field overrides the operation's ID in the OpenAPI spec,
+//! which is otherwise the name of the handler function.  Client generators
+//! typically name their methods after operation IDs, so
+//! [`ApiDescription::register`] rejects an endpoint whose operation ID is
+//! already used by another endpoint in the spec.
+//!
+//! The deprecated field marks the operation as deprecated in the OpenAPI spec.
+//! To also tell clients at runtime, deprecated_since gives the time (in RFC
+//! 3339 format) at which the endpoint was deprecated, which implies
+//! `deprecated = true` and is sent in a `Deprecation` header (RFC 9745) with
+//! each successful response.  Similarly, sunset gives the time at which the
+//! endpoint will stop working, sent in a `Sunset` header (RFC 8594).
+//!
+//! The unpublished field, when true, omits the operation from the OpenAPI spec
+//! while still serving it, which suits internal or debugging endpoints that
+//! shouldn't be part of the public API.  Unpublished endpoints are exempt from
+//! the tag policy and may share operation IDs.
+//!
 //! The tags field is used to categorize API endpoints and only impacts the
 //! OpenAPI spec output.
 //!
+//! The encodings field lists the media types in which the endpoint accepts and
+//! produces bodies: JSON, and with the "cbor" and "msgpack" features, CBOR and
+//! MessagePack.  A `TypedBody` is parsed according to the request's
+//! `Content-Type`, and a [`Negotiated`] response is sent in the encoding the
+//! request's `Accept` header prefers.  The OpenAPI spec lists every encoding.
+//!
+//! The response_content_type field gives the media type of the endpoint's
+//! successful responses when their body is a [`FreeformBody`], which is
+//! otherwise sent as `application/octet-stream` and described in the OpenAPI
+//! spec as any type at all.
+//!
+//! The strict_query field, when true, makes the endpoint's [`Query`] extractor
+//! reject requests that include query parameters it doesn't accept, with a 400
+//! ("Bad Request") listing them.  Otherwise such parameters are ignored.  It
+//! overrides [`ApiDescription::strict_query`] for this endpoint.
+//!
+//! The max_concurrency field bounds the number of requests to this endpoint
+//! that the server will handle at once.  Requests that arrive while the
+//! endpoint is at its limit are rejected with a 503 ("Service Unavailable").
+//!
+//! The request_body_max_bytes field overrides the server's
+//! `request_body_max_bytes` (from [`ConfigDropshot`]) for this endpoint, so that
+//! an endpoint accepting uploads can allow larger bodies than the rest of the
+//! API.
+//!
+//! The request_timeout_ms field overrides the server's `request_timeout_ms`
+//! (from [`ConfigDropshot`]) for this endpoint.  A request whose handler runs
+//! longer than this is abandoned: the handler is cancelled and the client gets
+//! a 503 ("Service Unavailable").
+//!
+//! The errors field lists the error statuses that the endpoint is expected to
+//! return.  Each gets its own entry, with the error body's schema, among the
+//! operation's responses in the OpenAPI spec, in addition to the general 4xx
+//! and 5xx entries that every operation has.
+//!
+//! The security field names the security schemes that callers of the
+//! endpoint must satisfy, each with the OAuth2 or OpenID Connect scopes it
+//! requires.  The schemes must be declared with
+//! [`ApiDescription::security_scheme`] before the endpoint is registered.  This
+//! only documents the operation's security requirement in the OpenAPI spec:
+//! the handler remains responsible for checking the caller's credentials.
+//!
+//! The attributes field attaches arbitrary metadata to the endpoint.  It has no
+//! effect on the endpoint itself, but handlers and hooks can read it from
+//! `RequestContext::endpoint` to make per-endpoint decisions, like which
+//! metrics labels or authorization policy to apply.
+//!
 //!
 //! ### Function parameters
 //!
@@ -211,9 +329,13 @@
 //!      rqctx: RequestContext<Context>,
 //!      [query_params: Query<Q>,]
 //!      [path_params: Path<P>,]
+//!      [header_params: Header<H>,]
+//!      [cookies: Cookie<C>,]
 //!      [body_param: TypedBody<J>,]
+//!      [body_param: FormBody<F>,]
 //!      [body_param: UntypedBody,]
 //!      [body_param: StreamingBody,]
+//!      [body_param: Multipart,]
 //!      [raw_request: RawRequest,]
 //! ) -> Result<HttpResponse*, HttpError>
 //! ```
@@ -221,31 +343,111 @@
 //! The `RequestContext` must appear first.  The `Context` type is
 //! caller-provided context which is provided when the server is created.
 //!
-//! The types `Query`, `Path`, `TypedBody`, `UntypedBody`, and `RawRequest` are
-//! called **Extractors** because they cause information to be pulled out of the
-//! request and made available to the handler function.
+//! The types `Query`, `Path`, `Header`, `Cookie`, `TypedBody`, `FormBody`,
+//! `UntypedBody`, `Multipart`, and `RawRequest` are called **Extractors**
+//! because they cause information to be pulled out of the request and made
+//! available to the handler function.
 //!
 //! * [`Query`]`<Q>` extracts parameters from a query string, deserializing them
 //!   into an instance of type `Q`. `Q` must implement `serde::Deserialize` and
-//!   `schemars::JsonSchema`.
+//!   `schemars::JsonSchema`.  A field of type `Vec<T>` receives every value of
+//!   a repeated parameter (e.g., `?id=1&id=2`).
 //! * [`Path`]`<P>` extracts parameters from HTTP path, deserializing them into
 //!   an instance of type `P`. `P` must implement `serde::Deserialize` and
 //!   `schemars::JsonSchema`.
+//! * [`Header`]`<H>` extracts request headers, deserializing them into an
+//!   instance of type `H`. `H` must implement `serde::Deserialize` and
+//!   `schemars::JsonSchema`.  Header names are presented in lowercase, so
+//!   `#[serde(rename_all = "kebab-case")]` is usually what you want.
+//! * [`Cookie`]`<C>` extracts the cookies sent with the request, deserializing
+//!   them into an instance of type `C`. `C` must implement `serde::Deserialize`
+//!   and `schemars::JsonSchema`.  [`SignedCookie`]`<C>` does the same, but only
+//!   accepts cookies signed with the key given to
+//!   [`ApiDescription::cookie_key`], and [`EncryptedCookie`]`<C>` only those
+//!   encrypted with it.
+//! * [`ClientCert`] provides the certificate chain the client presented during
+//!   the TLS handshake, along with the subject and subject alternative names
+//!   of the client's certificate.  Clients only present certificates when the
+//!   server's TLS configuration requests them.  Requests without one fail with
+//!   a 401, unless the extractor is wrapped in an `Option`.
+//! * [`RemoteAddr`] provides the IP address of the client.  For requests
+//!   forwarded by the proxies listed in
+//!   [`ConfigDropshot::trusted_proxies`], this comes from the `Forwarded` or
+//!   `X-Forwarded-For` header rather than the connection's peer address.
+//! * [`Preconditions`] provides the conditional request headers `If-Match`,
+//!   `If-None-Match`, and `If-Modified-Since`.  Its
+//!   [`evaluate`](Preconditions::evaluate) method compares them to the
+//!   resource's current [`EntityTag`] and modification time, producing the
+//!   304 ("Not Modified") or 412 ("Precondition Failed") error to return if
+//!   the request shouldn't proceed.
+//! * [`BearerToken`] provides the token from an `Authorization: Bearer`
+//!   header, and documents the endpoint as requiring bearer authentication in
+//!   the OpenAPI document.
+//! * [`RangeRequest`] provides the byte ranges requested by the `Range` header,
+//!   for use with [`HttpResponsePartialContent`] to support resumable
+//!   downloads.
+//! * [`AcceptLanguage`] provides the languages the client prefers, from the
+//!   `Accept-Language` header, and chooses among those a handler can produce.
+//! * [`RawQuery`] provides the query string exactly as it appeared in the
+//!   request URI, for endpoints that verify signed URLs.
 //! * [`TypedBody`]`<J>` extracts content from the request body by parsing the
 //!   body as JSON (or form/url-encoded) and deserializing it into an instance
 //!   of type `J`. `J` must implement `serde::Deserialize` and `schemars::JsonSchema`.
+//! * [`FormBody`]`<F>` is like `TypedBody`, but always parses the body as
+//!   form/url-encoded, regardless of the endpoint's `content_type`.
+//! * `CborBody<C>` is like `FormBody`, but parses the body as CBOR
+//!   (`application/cbor`).  It requires the `"cbor"` feature, which also
+//!   provides the `Cbor<T>` and `JsonOrCbor<T>` wrappers for sending response
+//!   bodies as CBOR.
+//! * `MsgPackBody<M>` likewise parses the body as MessagePack
+//!   (`application/msgpack`).  It requires the `"msgpack"` feature, which also
+//!   provides the `MsgPack<T>` wrapper for sending response bodies as
+//!   MessagePack.
 //! * [`UntypedBody`] extracts the raw bytes of the request body.
 //! * [`StreamingBody`] provides the raw bytes of the request body as a
-//!   [`Stream`](futures::Stream) of [`Bytes`](bytes::Bytes) chunks.
+//!   [`Stream`](futures::Stream) of [`Bytes`](bytes::Bytes) chunks.  The
+//!   OpenAPI spec documents these raw bodies as `application/octet-stream`
+//!   unless the endpoint specifies a `content_type`, such as
+//!   `multipart/form-data` for an upload that the handler parses itself.
+//! * [`Multipart`] reads a `multipart/form-data` body (such as an HTML form
+//!   with file uploads) one part at a time, without buffering it in memory.
 //! * [`RawRequest`] provides access to the underlying [`hyper::Request`].  The
 //!   hope is that this would generally not be needed.  It can be useful to
 //!   implement functionality not provided by Dropshot.
+//! * [`Validated`]`<E>` wraps a `Query`, `Path`, or `TypedBody` whose value
+//!   implements [`Validate`], and rejects the request with a 400 if the value
+//!   is invalid.  The response body's `field_errors` property lists the
+//!   problem with each field.
+//!
+//! `Query`, `Path`, `Header`, `Cookie`, `SignedCookie`, `EncryptedCookie`,
+//! `ClientCert`, `RemoteAddr`, `Preconditions`, `BearerToken`, `RangeRequest`,
+//! `AcceptLanguage`, and `RawQuery` impl `SharedExtractor`.  `TypedBody`,
+//! `FormBody`, `UntypedBody`, `StreamingBody`, `Multipart`, and `RawRequest`
+//! impl `ExclusiveExtractor`.  Your function may accept 0-3 extractors, but only
+//! one can be `ExclusiveExtractor`, and it must be the last one.  Otherwise,
+//! the order of extractor arguments does not matter.
+//!
+//! A `Query` or `TypedBody` may be wrapped in an `Option` to make the whole
+//! group of query parameters, or the body, optional: `Option<Query<Q>>` is
+//! `None` if the request has no query string and `Option<TypedBody<J>>` is
+//! `None` if it has no body.  The OpenAPI spec marks the parameters or body as
+//! not required.
+//!
+//! To accept path and query parameters as a single argument, derive
+//! [`RequestParams`](macro@RequestParams) for a struct and mark each field with
+//! `#[dropshot(path)]` or `#[dropshot(query)]`:
 //!
-//! `Query` and `Path` impl `SharedExtractor`.  `TypedBody`, `UntypedBody`,
-//! `StreamingBody`, and `RawRequest` impl `ExclusiveExtractor`.  Your function
-//! may accept 0-3 extractors, but only one can be `ExclusiveExtractor`, and it
-//! must be the last one.  Otherwise, the order of extractor arguments does not
-//! matter.
+//! ```
+//! use dropshot::RequestParams;
+//!
+//! #[derive(RequestParams)]
+//! struct WidgetParams {
+//!     #[dropshot(path)]
+//!     widget_id: u32,
+//!     #[dropshot(query)]
+//!     verbose: Option<bool>,
+//! }
+//! ```
 //!
 //! If the handler accepts any extractors and the corresponding extraction
 //! cannot be completed, the request fails with status code 400 and an error
@@ -291,6 +493,72 @@
 //! }
 //! ```
 //!
+//! ### Custom extractors
+//!
+//! Handler functions may also accept extractors of your own, by implementing
+//! [`SharedExtractor`] (or [`ExclusiveExtractor`], for one that consumes the
+//! request body) for a type.  `from_request` builds the value from the
+//! request, and may use the server context, e.g., to look something up in a
+//! database.  `metadata` describes what the extractor reads from the request
+//! for the OpenAPI spec: [`ExtractorMetadata::for_parameters`] and
+//! [`ExtractorMetadata::for_body`] describe parameters and bodies the way the
+//! built-in extractors do, and `ExtractorMetadata::default()` describes
+//! nothing.  Here's an extractor for the user making a request:
+//!
+//! ```
+//! use async_trait::async_trait;
+//! use dropshot::ApiEndpointBodyContentType;
+//! use dropshot::ApiEndpointParameterLocation;
+//! use dropshot::ExtractorMetadata;
+//! use dropshot::HttpError;
+//! use dropshot::RequestContext;
+//! use dropshot::ServerContext;
+//! use dropshot::SharedExtractor;
+//! use http::StatusCode;
+//! use schemars::JsonSchema;
+//!
+//! struct AuthenticatedUser {
+//!     id: String,
+//! }
+//!
+//! /// Describes the header that `AuthenticatedUser` reads.
+//! #[derive(JsonSchema)]
+//! #[allow(dead_code)]
+//! struct UserHeaders {
+//!     #[schemars(rename = "x-user-id")]
+//!     user_id: String,
+//! }
+//!
+//! #[async_trait]
+//! impl SharedExtractor for AuthenticatedUser {
+//!     async fn from_request<Context: ServerContext>(
+//!         rqctx: &RequestContext<Context>,
+//!     ) -> Result<AuthenticatedUser, HttpError> {
+//!         let id = rqctx
+//!             .request
+//!             .headers()
+//!             .get("x-user-id")
+//!             .and_then(|value| value.to_str().ok())
+//!             .ok_or_else(|| {
+//!                 HttpError::for_client_error(
+//!                     None,
+//!                     StatusCode::UNAUTHORIZED,
+//!                     String::from("missing user"),
+//!                 )
+//!             })?;
+//!         Ok(AuthenticatedUser { id: id.to_string() })
+//!     }
+//!
+//!     fn metadata(
+//!         _body_content_type: ApiEndpointBodyContentType,
+//!     ) -> ExtractorMetadata {
+//!         ExtractorMetadata::for_parameters::<UserHeaders>(
+//!             ApiEndpointParameterLocation::Header,
+//!         )
+//!     }
+//! }
+//! ```
+//!
 //! ### Endpoint function return types
 //!
 //! Endpoint handler functions are async, so they always return a `Future`.  When
@@ -329,6 +597,12 @@
 //! the OpenAPI spec will not include any status code or type information in
 //! this case.
 //!
+//! Example values of an endpoint's request body, parameters, and response
+//! body can be added to the OpenAPI spec with
+//! [`ApiEndpoint::request_example`], [`ApiEndpoint::parameter_example`], and
+//! [`ApiEndpoint::response_example`], applied to the endpoint (for example,
+//! `ApiEndpoint::from(project_create)`) before registering it.
+//!
 //! ## What about generic handlers that run on all requests?
 //!
 //! There's no mechanism in Dropshot for this.  Instead, it's recommended that
@@ -449,13 +723,30 @@
 //! (typically including the sort fields, sort order, and filter options) and
 //! `PageSelector` is a consumer-defined type describing the page token.  The
 //! PageSelector will be serialized to JSON and base64-encoded to construct the
-//! page token.  This will be automatically parsed on the way back in.
+//! page token.  This will be automatically parsed on the way back in.  Clients
+//! can decode and modify page tokens, so an API that needs to trust them can
+//! have them signed with a [`PageTokenKey`] (see
+//! [`ApiDescription::page_token_key`]).  An API can also version its page
+//! tokens ([`ApiDescription::page_token_version`]) or make them expire
+//! ([`ApiDescription::page_token_lifetime`]), so that clients holding stale
+//! tokens are told to restart their scans.  Tokens are limited to 512 bytes
+//! unless the API says otherwise ([`ApiDescription::page_token_max_length`]).
 //!
 //! For output, a paginated API endpoint's handler function can return
 //! `Result<`[`HttpResponseOk`]<[`ResultsPage`]`<T>, HttpError>` where `T:
 //! Serialize` is the item listed by the endpoint.  You can also use your own
 //! structure that contains a [`ResultsPage`] (possibly using
-//! `#[serde(flatten)]`), if that's the behavior you want.
+//! `#[serde(flatten)]`), if that's the behavior you want.  Handlers that know
+//! the size of the collection (or can estimate it) can report it with
+//! [`ResultsPage::with_total_count`].  If the handler can get an iterator (or
+//! a `Stream`) over the collection starting at the requested page,
+//! [`paginate_iter`] (or [`paginate_stream`]) takes the page's items from it
+//! and builds the [`ResultsPage`] in one step.
+//!
+//! Endpoints that need to let clients jump to an arbitrary position in a
+//! collection can instead paginate by offset, using [`OffsetPaginationParams`]
+//! and [`ResultsPage::new_offset`].  These produce the same [`ResultsPage`]
+//! envelope, but don't have the consistency guarantees of page tokens.
 //!
 //! There are several complete, documented examples in the "examples" directory.
 //!
@@ -527,11 +818,19 @@
 //! parameters that are mandatory if `page_token` is not specified (when
 //! fetching the first page of data).
 //!
+//! For readers of the document, the description of each of those scan
+//! parameters also notes that it applies only to the first page and is ignored
+//! if `page_token` is provided.  `limit` and `page_token` themselves are
+//! documented on every paginated operation, and the response schema for a
+//! `ResultsPage<T>` is named after the item type (e.g., `ProjectResultsPage`).
+//!
 //! ## DTrace probes
 //!
 //! Dropshot optionally exposes two DTrace probes, `request_start` and
 //! `request_finish`. These provide detailed information about each request,
-//! such as their ID, the local and remote IPs, and the response information.
+//! such as their ID, the local and remote IPs, and the response information,
+//! including the sizes of the request and response bodies and the time taken
+//! to produce the response headers.
 //! See the dropshot::dtrace::RequestInfo` and `dropshot::dtrace::ResponseInfo`
 //! types for a complete listing of what's available.
 //!
@@ -561,11 +860,11 @@
 //! ```text
 //! ## dtrace -Zq -n 'dropshot*:::request-* { printf("%s\n", copyinstr(arg0)); }'
 //! {"ok":{"id":"b793c62e-60e4-45c5-9274-198a04d9abb1","local_addr":"127.0.0.1:61028","remote_addr":"127.0.0.1:34286","method":"GET","path":"/counter","query":null}}
-//! {"ok":{"id":"b793c62e-60e4-45c5-9274-198a04d9abb1","local_addr":"127.0.0.1:61028","remote_addr":"127.0.0.1:34286","status_code":200,"message":""}}
+//! {"ok":{"id":"b793c62e-60e4-45c5-9274-198a04d9abb1","local_addr":"127.0.0.1:61028","remote_addr":"127.0.0.1:34286","status_code":200,"message":"","request_body_bytes":0,"response_body_bytes":1,"time_to_first_byte_us":112}}
 //! {"ok":{"id":"9050e30a-1ce3-4d6f-be1c-69a11c618800","local_addr":"127.0.0.1:61028","remote_addr":"127.0.0.1:41101","method":"PUT","path":"/counter","query":null}}
-//! {"ok":{"id":"9050e30a-1ce3-4d6f-be1c-69a11c618800","local_addr":"127.0.0.1:61028","remote_addr":"127.0.0.1:41101","status_code":400,"message":"do not like the number 10"}}
+//! {"ok":{"id":"9050e30a-1ce3-4d6f-be1c-69a11c618800","local_addr":"127.0.0.1:61028","remote_addr":"127.0.0.1:41101","status_code":400,"message":"do not like the number 10","request_body_bytes":15,"response_body_bytes":133,"time_to_first_byte_us":254}}
 //! {"ok":{"id":"a53696af-543d-452f-81b6-5a045dd9921d","local_addr":"127.0.0.1:61028","remote_addr":"127.0.0.1:57376","method":"PUT","path":"/counter","query":null}}
-//! {"ok":{"id":"a53696af-543d-452f-81b6-5a045dd9921d","local_addr":"127.0.0.1:61028","remote_addr":"127.0.0.1:57376","status_code":204,"message":""}}
+//! {"ok":{"id":"a53696af-543d-452f-81b6-5a045dd9921d","local_addr":"127.0.0.1:61028","remote_addr":"127.0.0.1:57376","status_code":204,"message":"","request_body_bytes":14,"response_body_bytes":0,"time_to_first_byte_us":187}}
 //! ```
 
 // Clippy's style advice is definitely valuable, but not worth the trouble for
@@ -584,17 +883,28 @@
 mod dtrace;
 
 mod api_description;
+mod cache_control;
+mod checksum;
+mod clock;
 mod config;
 mod error;
 mod extractor;
 mod from_map;
 mod handler;
+mod hmac;
 mod http_util;
+mod in_flight;
+mod load_shedding;
 mod logging;
+mod openapi_compat;
+mod openapi_docs;
+mod openapi_lint;
+mod openapi_version;
 mod pagination;
 mod router;
 mod schema_util;
 mod server;
+mod static_dir;
 mod to_map;
 mod type_util;
 mod websocket;
@@ -607,61 +917,148 @@ extern crate slog;
 pub use api_description::ApiDescription;
 pub use api_description::ApiEndpoint;
 pub use api_description::ApiEndpointBodyContentType;
+pub use api_description::ApiEndpointHeader;
 pub use api_description::ApiEndpointParameter;
 pub use api_description::ApiEndpointParameterLocation;
 pub use api_description::ApiEndpointResponse;
+pub use api_description::ApiEndpointSecurity;
+pub use api_description::ApiSchemaGenerator;
+pub use api_description::EndpointMetadata;
 pub use api_description::EndpointTagPolicy;
 pub use api_description::ExtensionMode;
 pub use api_description::OpenApiDefinition;
+pub use api_description::ParameterCase;
 pub use api_description::TagConfig;
 pub use api_description::TagDetails;
 pub use api_description::TagExternalDocs;
+pub use cache_control::CacheControl;
+pub use checksum::ChecksumAlgorithm;
+pub use checksum::HEADER_CONTENT_DIGEST;
+pub use clock::Clock;
+pub use clock::SystemClock;
 pub use config::ConfigDropshot;
+pub use config::ConfigHttp2;
+pub use config::ConfigLoadShedding;
 pub use config::ConfigTls;
+pub use config::ConfigTlsClientAuth;
+pub use config::ConfigUnixSocket;
 pub use dtrace::ProbeRegistration;
 pub use error::HttpError;
 pub use error::HttpErrorResponseBody;
+pub use extractor::AcceptLanguage;
+pub use extractor::BearerToken;
+pub use extractor::ByteRange;
+#[cfg(feature = "cbor")]
+pub use extractor::CborBody;
+pub use extractor::ClientCert;
+pub use extractor::Cookie;
+pub use extractor::CookieKey;
+pub use extractor::EncryptedCookie;
+pub use extractor::EntityTag;
+pub use extractor::EntityTagMatch;
 pub use extractor::ExclusiveExtractor;
 pub use extractor::ExtractorMetadata;
+pub use extractor::FormBody;
+pub use extractor::Header;
+pub use extractor::LanguagePreference;
+#[cfg(feature = "msgpack")]
+pub use extractor::MsgPackBody;
+pub use extractor::Multipart;
+pub use extractor::MultipartPart;
 pub use extractor::Path;
+pub use extractor::Preconditions;
 pub use extractor::Query;
+pub use extractor::RangeRequest;
+pub use extractor::RawQuery;
 pub use extractor::RawRequest;
+pub use extractor::RemoteAddr;
 pub use extractor::SharedExtractor;
+pub use extractor::SignedCookie;
 pub use extractor::StreamingBody;
 pub use extractor::TypedBody;
 pub use extractor::UntypedBody;
+pub use extractor::Validate;
+pub use extractor::Validated;
+pub use extractor::ValidationErrors;
 pub use handler::http_response_found;
+pub use handler::http_response_permanent_redirect;
 pub use handler::http_response_see_other;
 pub use handler::http_response_temporary_redirect;
+#[cfg(feature = "cbor")]
+pub use handler::Cbor;
 pub use handler::FreeformBody;
 pub use handler::HttpCodedResponse;
+pub use handler::HttpHandlerResult;
 pub use handler::HttpResponse;
 pub use handler::HttpResponseAccepted;
+pub use handler::HttpResponseAttachment;
+pub use handler::HttpResponseCached;
+pub use handler::HttpResponseContent;
 pub use handler::HttpResponseCreated;
+pub use handler::HttpResponseCreatedAt;
+#[cfg(feature = "csv")]
+pub use handler::HttpResponseCsv;
 pub use handler::HttpResponseDeleted;
+pub use handler::HttpResponseFile;
 pub use handler::HttpResponseFound;
 pub use handler::HttpResponseHeaders;
+pub use handler::HttpResponseNdjson;
 pub use handler::HttpResponseOk;
+pub use handler::HttpResponseOkPage;
+pub use handler::HttpResponsePartialContent;
+pub use handler::HttpResponsePermanentRedirect;
 pub use handler::HttpResponseSeeOther;
+pub use handler::HttpResponseTagged;
 pub use handler::HttpResponseTemporaryRedirect;
 pub use handler::HttpResponseUpdatedNoContent;
+pub use handler::HttpResponseWithStatus;
+#[cfg(feature = "cbor")]
+pub use handler::JsonOrCbor;
+#[cfg(feature = "msgpack")]
+pub use handler::MsgPack;
+pub use handler::Negotiated;
 pub use handler::NoHeaders;
 pub use handler::RequestContext;
 pub use handler::RequestInfo;
+pub use handler::ResponseStatuses;
+pub use http_util::CONTENT_TYPE_CBOR;
+pub use http_util::CONTENT_TYPE_CSV;
 pub use http_util::CONTENT_TYPE_JSON;
+pub use http_util::CONTENT_TYPE_MSGPACK;
+pub use http_util::CONTENT_TYPE_MULTIPART_FORM_DATA;
 pub use http_util::CONTENT_TYPE_NDJSON;
 pub use http_util::CONTENT_TYPE_OCTET_STREAM;
 pub use http_util::CONTENT_TYPE_URL_ENCODED;
 pub use http_util::HEADER_REQUEST_ID;
+pub use in_flight::InFlightRequest;
 pub use logging::ConfigLogging;
 pub use logging::ConfigLoggingIfExists;
 pub use logging::ConfigLoggingLevel;
+pub use openapi_compat::compare_openapi;
+pub use openapi_compat::BreakingChange;
+pub use openapi_lint::ApiProblem;
+pub use openapi_lint::ApiProblemKind;
+pub use openapi_version::OpenApiVersion;
+pub use pagination::paginate_iter;
+pub use pagination::paginate_stream;
 pub use pagination::EmptyScanParams;
+pub use pagination::KeysetBounds;
+pub use pagination::KeysetComparison;
+pub use pagination::KeysetPage;
+pub use pagination::KeysetPageSelector;
+pub use pagination::KeysetScanParams;
+pub use pagination::OffsetPaginationParams;
+pub use pagination::PageTokenEncoding;
+pub use pagination::PageTokenKey;
+pub use pagination::PageTokenScope;
+pub use pagination::PaginatedResource;
 pub use pagination::PaginationOrder;
 pub use pagination::PaginationParams;
 pub use pagination::ResultsPage;
+pub use pagination::ScanMode;
 pub use pagination::WhichPage;
 pub use server::ServerContext;
+pub use server::ShutdownHandle;
 pub use server::ShutdownWaitFuture;
 pub use server::{HttpServer, HttpServerStarter};
 pub use websocket::WebsocketChannelResult;
@@ -673,7 +1070,16 @@ pub use websocket::WebsocketUpgrade;
 // Users of the `endpoint` macro need the following macros:
 pub use handler::RequestContextArgument;
 pub use http::Method;
+pub use http::StatusCode;
 
 extern crate dropshot_endpoint;
 pub use dropshot_endpoint::channel;
 pub use dropshot_endpoint::endpoint;
+pub use dropshot_endpoint::PaginatedResource;
+pub use dropshot_endpoint::RequestParams;
+
+// Code generated by `#[derive(RequestParams)]` needs the following:
+#[doc(hidden)]
+pub mod __private {
+    pub use async_trait::async_trait;
+}
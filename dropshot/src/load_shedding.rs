@@ -0,0 +1,241 @@
+// Copyright 2023 Oxide Computer Company
+//! Adaptive load shedding based on endpoint latency
+//!
+//! This is loosely modeled on CoDel: rather than reacting to individual slow
+//! requests (which may simply be expensive), we look at the _minimum_ latency
+//! observed for an endpoint over an interval.  If even the fastest request in
+//! an interval exceeded the target, requests are waiting behind each other and
+//! the endpoint is overloaded.  While overloaded, we reject a fraction of the
+//! endpoint's requests, increasing that fraction additively for each interval
+//! that remains over the target and halving it for each interval that does
+//! not.
+//!
+//! Rejections are spread evenly over the endpoint's requests rather than chosen
+//! at random: each request adds the current shed percentage to an accumulator,
+//! and each request that pushes it to 100 is rejected.
+
+use crate::clock::Clock;
+use crate::config::ConfigLoadShedding;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Amount by which the shed percentage grows for each overloaded interval.
+const SHED_PERCENT_STEP: u32 = 10;
+/// Upper bound on the shed percentage.  We always admit some requests so that
+/// we can observe when the endpoint has recovered.
+const SHED_PERCENT_MAX: u32 = 90;
+
+/// Tracks the load on each endpoint and decides which requests to shed.
+#[derive(Debug)]
+pub(crate) struct LoadShedder {
+    target_latency: Duration,
+    interval: Duration,
+    /// per-endpoint state, keyed by operation id
+    endpoints: Mutex<HashMap<String, EndpointLoad>>,
+}
+
+#[derive(Debug)]
+struct EndpointLoad {
+    /// start of the current measurement interval
+    interval_start: Instant,
+    /// minimum latency observed during the current interval
+    interval_min: Option<Duration>,
+    /// percentage of requests currently being rejected
+    shed_percent: u32,
+    /// accumulated shed percentage since the last rejected request
+    credit: u32,
+}
+
+impl LoadShedder {
+    pub(crate) fn new(config: &ConfigLoadShedding) -> Self {
+        LoadShedder {
+            target_latency: Duration::from_millis(config.target_latency_ms),
+            interval: Duration::from_millis(config.interval_ms),
+            endpoints: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns whether a request for the given endpoint should be handled
+    /// (`true`) or rejected (`false`).
+    pub(crate) fn admit(&self, operation_id: &str) -> bool {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let load = match endpoints.get_mut(operation_id) {
+            Some(load) => load,
+            None => return true,
+        };
+        load.credit += load.shed_percent;
+        if load.credit >= 100 {
+            load.credit -= 100;
+            false
+        } else {
+            true
+        }
+    }
+
+    /// Records the latency of a request for the given endpoint that finished
+    /// handling at `now`.
+    pub(crate) fn record(
+        &self,
+        operation_id: &str,
+        latency: Duration,
+        now: Instant,
+    ) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        if !endpoints.contains_key(operation_id) {
+            endpoints.insert(operation_id.to_string(), EndpointLoad::new(now));
+        }
+        let load = endpoints.get_mut(operation_id).unwrap();
+
+        let interval_min = match load.interval_min {
+            Some(min) if min <= latency => min,
+            _ => latency,
+        };
+        load.interval_min = Some(interval_min);
+
+        if now.duration_since(load.interval_start) < self.interval {
+            return;
+        }
+
+        if interval_min > self.target_latency {
+            load.shed_percent =
+                (load.shed_percent + SHED_PERCENT_STEP).min(SHED_PERCENT_MAX);
+        } else {
+            load.shed_percent /= 2;
+            if load.shed_percent == 0 {
+                load.credit = 0;
+            }
+        }
+        load.interval_start = now;
+        load.interval_min = None;
+    }
+
+    /// Returns a guard that records the latency of a request for the given
+    /// endpoint, starting now, when it's dropped.
+    pub(crate) fn latency_guard<'a>(
+        &'a self,
+        operation_id: &'a str,
+        clock: &'a dyn Clock,
+    ) -> LatencyGuard<'a> {
+        LatencyGuard {
+            load_shedder: self,
+            operation_id,
+            clock,
+            start_time: clock.now(),
+        }
+    }
+}
+
+/// Records the latency of a request when dropped, so that requests whose
+/// handling is cancelled (e.g., because the client disconnected) are counted
+/// along with those that complete
+pub(crate) struct LatencyGuard<'a> {
+    load_shedder: &'a LoadShedder,
+    operation_id: &'a str,
+    clock: &'a dyn Clock,
+    start_time: Instant,
+}
+
+impl Drop for LatencyGuard<'_> {
+    fn drop(&mut self) {
+        let now = self.clock.now();
+        let latency = now.saturating_duration_since(self.start_time);
+        self.load_shedder.record(self.operation_id, latency, now);
+    }
+}
+
+impl EndpointLoad {
+    fn new(now: Instant) -> Self {
+        EndpointLoad {
+            interval_start: now,
+            interval_min: None,
+            shed_percent: 0,
+            credit: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LoadShedder;
+    use crate::config::ConfigLoadShedding;
+    use crate::test_util::MockClock;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    fn count_admitted(shedder: &LoadShedder, n: usize) -> usize {
+        (0..n).filter(|_| shedder.admit("op")).count()
+    }
+
+    #[test]
+    fn test_load_shedding() {
+        let shedder = LoadShedder::new(&ConfigLoadShedding {
+            target_latency_ms: 100,
+            interval_ms: 1000,
+        });
+        let slow = Duration::from_millis(150);
+        let fast = Duration::from_millis(10);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        // Nothing is shed for an endpoint we haven't seen, nor for one that
+        // has only been slow for part of an interval.
+        assert_eq!(count_admitted(&shedder, 10), 10);
+        shedder.record("op", slow, at(0));
+        shedder.record("op", slow, at(500));
+        assert_eq!(count_admitted(&shedder, 10), 10);
+
+        // A single fast request in the interval means there's no standing
+        // queue.
+        shedder.record("op", fast, at(600));
+        shedder.record("op", slow, at(1000));
+        assert_eq!(count_admitted(&shedder, 10), 10);
+
+        // Once a whole interval is over the target, we start shedding, and we
+        // shed more for each interval that remains over the target.
+        shedder.record("op", slow, at(1500));
+        shedder.record("op", slow, at(2000));
+        assert_eq!(count_admitted(&shedder, 10), 9);
+        shedder.record("op", slow, at(3000));
+        assert_eq!(count_admitted(&shedder, 10), 8);
+
+        // Other endpoints are unaffected.
+        assert!((0..10).all(|_| shedder.admit("other")));
+
+        // Shedding never exceeds the maximum.
+        for i in 4..20 {
+            shedder.record("op", slow, at(i * 1000));
+        }
+        assert_eq!(count_admitted(&shedder, 10), 1);
+
+        // Shedding backs off once latency recovers.
+        shedder.record("op", fast, at(20000));
+        assert_eq!(count_admitted(&shedder, 100), 55);
+        for i in 21..30 {
+            shedder.record("op", fast, at(i * 1000));
+        }
+        assert_eq!(count_admitted(&shedder, 10), 10);
+    }
+
+    #[tokio::test]
+    async fn test_latency_guard() {
+        let shedder = LoadShedder::new(&ConfigLoadShedding {
+            target_latency_ms: 100,
+            interval_ms: 1000,
+        });
+        let clock = MockClock::new(chrono::Utc::now());
+
+        // Latency is recorded even if the request's handling is cancelled
+        // (here, by dropping its future before it completes).
+        for _ in 0..2 {
+            let handle = async {
+                let _guard = shedder.latency_guard("op", &clock);
+                clock.advance(Duration::from_millis(1000));
+                std::future::pending::<()>().await;
+            };
+            let _ = tokio::time::timeout(Duration::ZERO, handle).await;
+        }
+        assert_eq!(count_admitted(&shedder, 10), 9);
+    }
+}
@@ -26,6 +26,10 @@ pub enum ConfigLogging {
         path: Utf8PathBuf,
         if_exists: ConfigLoggingIfExists,
     },
+    /// Output to each of several sinks, each with its own level filter.  For
+    /// example, this can be used to log to the terminal at level "info" and to
+    /// a file at level "debug".
+    Multiple { sinks: Vec<ConfigLogging> },
 }
 
 /// Log messages have a level that's used for filtering in the usual way.
@@ -71,12 +75,18 @@ impl ConfigLogging {
         &self,
         log_name: S,
     ) -> Result<Logger, io::Error> {
+        let drain = self.to_drain(log_name.as_ref())?;
+        Ok(async_root_logger(drain))
+    }
+
+    /// Create a drain for this configuration, including its level filter.
+    fn to_drain(&self, log_name: &str) -> Result<BoxedDrain, io::Error> {
         match self {
             ConfigLogging::StderrTerminal { level } => {
                 let decorator = slog_term::TermDecorator::new().build();
                 let drain =
                     slog_term::FullFormat::new(decorator).build().fuse();
-                Ok(async_root_logger(level, drain))
+                Ok(level_drain(level, drain))
             }
 
             ConfigLogging::File { level, path, if_exists } => {
@@ -99,26 +109,49 @@ impl ConfigLogging {
                 let drain = log_drain_for_file(
                     &open_options,
                     Path::new(path),
-                    log_name.as_ref().to_string(),
+                    log_name.to_string(),
                 )?;
-                Ok(async_root_logger(level, drain))
+                Ok(level_drain(level, drain))
+            }
+
+            ConfigLogging::Multiple { sinks } => {
+                // Each sink has already applied its own level filter, so every
+                // message is simply sent to all of them.
+                let mut drains = sinks
+                    .iter()
+                    .map(|sink| sink.to_drain(log_name))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .into_iter();
+                let first = match drains.next() {
+                    Some(drain) => drain,
+                    None => return Ok(Box::new(slog::Discard)),
+                };
+                Ok(drains.fold(first, |all, drain| {
+                    Box::new(slog::Duplicate::new(all, drain).fuse())
+                }))
             }
         }
     }
 }
 
+/// A level-filtered drain for one logging sink.
+type BoxedDrain = Box<dyn Drain<Ok = (), Err = slog::Never> + Send>;
+
+fn level_drain<T>(level: &ConfigLoggingLevel, drain: T) -> BoxedDrain
+where
+    T: slog::Drain + Send + 'static,
+    <T as slog::Drain>::Err: std::fmt::Debug,
+{
+    Box::new(slog::LevelFilter(drain, Level::from(level)).fuse())
+}
+
 // TODO-hardening We use an async drain to take care of synchronization.  That's
 // mainly because the other two documented options use a std::sync::Mutex, which
 // is not futures-aware and is likely to foul up our executor.  However, we have
 // not verified that the async implementation behaves reasonably under
 // backpressure, and it definitely makes things harder to debug.
-fn async_root_logger<T>(level: &ConfigLoggingLevel, drain: T) -> slog::Logger
-where
-    T: slog::Drain + Send + 'static,
-    <T as slog::Drain>::Err: std::fmt::Debug,
-{
-    let level_drain = slog::LevelFilter(drain, Level::from(level)).fuse();
-    let async_drain = slog_async::Async::new(level_drain).build().fuse();
+fn async_root_logger(drain: BoxedDrain) -> slog::Logger {
+    let async_drain = slog_async::Async::new(drain).build().fuse();
     slog::Logger::root(async_drain, o!())
 }
 
@@ -199,7 +232,8 @@ mod test {
             .to_string();
         println!("error: {}", error);
         assert!(error.contains(
-            "unknown variant `bonkers`, expected `stderr-terminal` or `file`"
+            "unknown variant `bonkers`, expected one of `stderr-terminal`, \
+             `file`, `multiple`"
         ));
     }
 
@@ -546,4 +580,69 @@ mod test {
         assert_eq!(log_records[1].msg, "message3_warn");
         assert_eq!(log_records[2].msg, "message3_error");
     }
+
+    // Working "mode = multiple" configuration.  Each sink gets the messages that
+    // pass its own level filter.
+
+    #[test]
+    fn test_config_multiple() {
+        let mut logtest = LogTest::setup("multiple_dir");
+        let debug_path = logtest.will_create_file("debug.out");
+        let warn_path = logtest.will_create_file("warn.out");
+
+        // Windows paths need to have \ turned into \\
+        let escaped_debug_path =
+            debug_path.display().to_string().escape_default().to_string();
+        let escaped_warn_path =
+            warn_path.display().to_string().escape_default().to_string();
+
+        let config = format!(
+            r#"
+            mode = "multiple"
+
+            [[sinks]]
+            mode = "file"
+            level = "debug"
+            if_exists = "fail"
+            path = "{}"
+
+            [[sinks]]
+            mode = "file"
+            level = "warn"
+            if_exists = "fail"
+            path = "{}"
+            "#,
+            escaped_debug_path, escaped_warn_path
+        );
+
+        {
+            // Construct the logger in a block so that it's flushed by the time
+            // we proceed.
+            let log =
+                read_config_and_create_logger("multiple", &config).unwrap();
+            trace!(log, "message_trace");
+            debug!(log, "message_debug");
+            warn!(log, "message_warn");
+        }
+
+        let log_records = read_bunyan_log(&debug_path);
+        assert_eq!(log_records.len(), 2);
+        assert_eq!(log_records[0].msg, "message_debug");
+        assert_eq!(log_records[1].msg, "message_warn");
+
+        let log_records = read_bunyan_log(&warn_path);
+        assert_eq!(log_records.len(), 1);
+        assert_eq!(log_records[0].msg, "message_warn");
+    }
+
+    #[test]
+    fn test_config_multiple_no_sinks() {
+        let config = r##"
+            mode = "multiple"
+            sinks = []
+        "##;
+        let log =
+            read_config_and_create_logger("multiple_no_sinks", config).unwrap();
+        warn!(log, "discarded");
+    }
 }
@@ -0,0 +1,798 @@
+// Copyright 2023 Oxide Computer Company
+//! Compatibility checks between versions of an OpenAPI document
+//!
+//! [`compare_openapi`] finds the differences between two versions of an API's
+//! OpenAPI document that could break existing clients: operations that were
+//! removed, requests that are no longer accepted, and responses that clients
+//! may not be prepared for.  Services can use it to gate releases on the
+//! compatibility of their APIs, by comparing the document generated for a
+//! release with the one checked in for the previous release.
+//!
+//! The comparison is structural and deliberately conservative.  Schemas are
+//! compared in the direction in which data flows: a request schema may accept
+//! more than it used to, but not less, and a response schema may promise more
+//! than it used to, but not less.  Schemas that combine alternatives (`oneOf`
+//! and `anyOf`) are compared variant by variant, in order.
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::fmt;
+
+static NULL: Value = Value::Null;
+
+const METHODS: &[&str] =
+    &["get", "put", "post", "delete", "options", "head", "patch", "trace"];
+
+/// A difference between two versions of an OpenAPI document that could break
+/// existing clients, as found by [`compare_openapi`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BreakingChange {
+    /// where the change was found, as the operation (e.g.,
+    /// "GET /projects/{project}") and the part of it that changed
+    pub location: String,
+    /// what changed
+    pub message: String,
+}
+
+impl fmt::Display for BreakingChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// Returns the changes from the OpenAPI document `old` to `new` that could
+/// break clients written against `old`.  An empty result means that `new` is
+/// backwards-compatible with `old`.
+///
+/// Documents are compared as JSON, as produced by
+/// [`OpenApiDefinition::json`](crate::OpenApiDefinition::json) or read from
+/// a file:
+///
+/// ```
+/// use dropshot::compare_openapi;
+/// use serde_json::json;
+///
+/// let old = json!({
+///     "paths": {
+///         "/projects": { "get": { "responses": {} } },
+///         "/health": { "get": { "responses": {} } }
+///     }
+/// });
+/// let new = json!({
+///     "paths": {
+///         "/projects": { "get": { "responses": {} } }
+///     }
+/// });
+/// let changes = compare_openapi(&old, &new);
+/// assert_eq!(changes.len(), 1);
+/// assert_eq!(changes[0].to_string(), "GET /health: operation was removed");
+/// ```
+pub fn compare_openapi(old: &Value, new: &Value) -> Vec<BreakingChange> {
+    let mut comparison =
+        Comparison { old, new, changes: Vec::new(), visited: HashSet::new() };
+    comparison.compare_paths();
+    comparison.changes
+}
+
+/// The direction in which data described by a schema flows
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Direction {
+    Request,
+    Response,
+}
+
+struct Comparison<'a> {
+    old: &'a Value,
+    new: &'a Value,
+    changes: Vec<BreakingChange>,
+    /// pairs of referenced schemas already compared, so that changes to types
+    /// shared by several operations are reported once and recursive types
+    /// don't compare forever
+    visited: HashSet<(String, String, Direction)>,
+}
+
+impl<'a> Comparison<'a> {
+    fn report<L: ToString, M: ToString>(&mut self, location: L, message: M) {
+        self.changes.push(BreakingChange {
+            location: location.to_string(),
+            message: message.to_string(),
+        });
+    }
+
+    fn compare_paths(&mut self) {
+        let (old, new) = (self.old, self.new);
+
+        // Paths that differ only in the names of their variables are the same
+        // to clients.
+        let new_paths = entries(&new["paths"])
+            .map(|(path, item)| (normalize_path(path), item))
+            .collect::<BTreeMap<_, _>>();
+
+        for (path, old_item) in entries(&old["paths"]) {
+            let new_item = new_paths.get(&normalize_path(path)).copied();
+            for method in METHODS {
+                let old_op = match old_item.get(*method) {
+                    Some(op) => op,
+                    None => continue,
+                };
+                let location = format!("{} {}", method.to_uppercase(), path);
+                match new_item.and_then(|item| item.get(*method)) {
+                    Some(new_op) => {
+                        self.compare_operation(&location, old_op, new_op)
+                    }
+                    None => self.report(location, "operation was removed"),
+                }
+            }
+        }
+    }
+
+    fn compare_operation(
+        &mut self,
+        location: &str,
+        old_op: &'a Value,
+        new_op: &'a Value,
+    ) {
+        self.compare_parameters(location, old_op, new_op);
+        self.compare_request_body(location, old_op, new_op);
+        self.compare_responses(location, old_op, new_op);
+    }
+
+    fn compare_parameters(
+        &mut self,
+        location: &str,
+        old_op: &'a Value,
+        new_op: &'a Value,
+    ) {
+        let old_params = parameters(self.old, old_op);
+        let new_params = parameters(self.new, new_op);
+
+        // Path parameters are matched by position, since they may be renamed
+        // along with the path's variables.
+        let old_path = old_params.iter().filter(|p| p["in"] == "path");
+        let new_path = new_params.iter().filter(|p| p["in"] == "path");
+        for (old_param, new_param) in old_path.zip(new_path) {
+            let location = format!(
+                "{} path parameter \"{}\"",
+                location,
+                new_param["name"].as_str().unwrap_or_default()
+            );
+            self.compare_schema(
+                &location,
+                &old_param["schema"],
+                &new_param["schema"],
+                Direction::Request,
+            );
+        }
+
+        for new_param in new_params.iter().filter(|p| p["in"] != "path") {
+            let name = new_param["name"].as_str().unwrap_or_default();
+            let kind = new_param["in"].as_str().unwrap_or_default();
+            let location =
+                format!("{} {} parameter \"{}\"", location, kind, name);
+            let old_param = old_params
+                .iter()
+                .find(|p| p["in"] == new_param["in"] && p["name"] == name);
+            let required = is_true(&new_param["required"]);
+            match old_param {
+                None if required => {
+                    self.report(location, "required parameter was added")
+                }
+                None => (),
+                Some(old_param) => {
+                    if required && !is_true(&old_param["required"]) {
+                        self.report(&location, "parameter is now required");
+                    }
+                    self.compare_schema(
+                        &location,
+                        &old_param["schema"],
+                        &new_param["schema"],
+                        Direction::Request,
+                    );
+                }
+            }
+        }
+    }
+
+    fn compare_request_body(
+        &mut self,
+        location: &str,
+        old_op: &'a Value,
+        new_op: &'a Value,
+    ) {
+        let location = format!("{} request body", location);
+        let new_body = match resolve(self.new, &new_op["requestBody"]) {
+            Value::Null => return,
+            body => body,
+        };
+        let old_body = match resolve(self.old, &old_op["requestBody"]) {
+            Value::Null => {
+                if is_true(&new_body["required"]) {
+                    self.report(location, "required request body was added");
+                }
+                return;
+            }
+            body => body,
+        };
+        if is_true(&new_body["required"]) && !is_true(&old_body["required"]) {
+            self.report(&location, "request body is now required");
+        }
+        for (media_type, old_content) in entries(&old_body["content"]) {
+            let location = format!("{} ({})", location, media_type);
+            match new_body["content"].get(media_type) {
+                Some(new_content) => self.compare_schema(
+                    &location,
+                    &old_content["schema"],
+                    &new_content["schema"],
+                    Direction::Request,
+                ),
+                None => {
+                    self.report(location, "media type is no longer accepted")
+                }
+            }
+        }
+    }
+
+    fn compare_responses(
+        &mut self,
+        location: &str,
+        old_op: &'a Value,
+        new_op: &'a Value,
+    ) {
+        // Clients are expected to cope with errors, but not with the loss of
+        // a successful response they rely on.
+        for (status, old_response) in entries(&old_op["responses"]) {
+            if !status.starts_with('2') && status != "default" {
+                continue;
+            }
+            let location = format!("{} response {}", location, status);
+            let new_response = match new_op["responses"].get(status) {
+                Some(response) => resolve(self.new, response),
+                None => {
+                    self.report(location, "response status was removed");
+                    continue;
+                }
+            };
+            let old_response = resolve(self.old, old_response);
+            for (media_type, old_content) in entries(&old_response["content"]) {
+                let location = format!("{} ({})", location, media_type);
+                match new_response["content"].get(media_type) {
+                    Some(new_content) => self.compare_schema(
+                        &location,
+                        &old_content["schema"],
+                        &new_content["schema"],
+                        Direction::Response,
+                    ),
+                    None => self.report(location, "media type was removed"),
+                }
+            }
+        }
+    }
+
+    fn compare_schema(
+        &mut self,
+        location: &str,
+        old: &'a Value,
+        new: &'a Value,
+        direction: Direction,
+    ) {
+        if let (Some(old_ref), Some(new_ref)) =
+            (old["$ref"].as_str(), new["$ref"].as_str())
+        {
+            let key = (old_ref.to_string(), new_ref.to_string(), direction);
+            if !self.visited.insert(key) {
+                return;
+            }
+        }
+        let old = unwrap_schema(self.old, old);
+        let new = unwrap_schema(self.new, new);
+        let request = direction == Direction::Request;
+
+        // A schema without a type accepts anything, and may be replaced by
+        // anything in a response.
+        if is_any(new) {
+            if !request && !is_any(old) {
+                self.report(location, "schema no longer constrains values");
+            }
+            return;
+        }
+        if is_any(old) {
+            if request {
+                self.report(location, "schema now constrains values");
+            }
+            return;
+        }
+
+        let old_type = old["type"].as_str();
+        let new_type = new["type"].as_str();
+        if old_type != new_type {
+            // Every integer is a number, so a request may accept numbers in
+            // place of integers, and a response may narrow numbers down to
+            // integers.
+            let widened = match (old_type, new_type) {
+                (Some("integer"), Some("number")) => request,
+                (Some("number"), Some("integer")) => !request,
+                _ => false,
+            };
+            if !widened {
+                self.report(
+                    location,
+                    format!(
+                        "type changed from {} to {}",
+                        type_name(old),
+                        type_name(new)
+                    ),
+                );
+                return;
+            }
+        }
+
+        let old_nullable = is_true(&old["nullable"]);
+        let new_nullable = is_true(&new["nullable"]);
+        if request && old_nullable && !new_nullable {
+            self.report(location, "null is no longer accepted");
+        } else if !request && new_nullable && !old_nullable {
+            self.report(location, "value may now be null");
+        }
+
+        self.compare_enums(location, old, new, direction);
+        if request {
+            self.compare_limits(location, old, new);
+        }
+        self.compare_properties(location, old, new, direction);
+        self.compare_alternatives(location, old, new, direction);
+
+        if let (Some(old_items), Some(new_items)) =
+            (old.get("items"), new.get("items"))
+        {
+            let location = format!("{}[]", location);
+            self.compare_schema(&location, old_items, new_items, direction);
+        }
+    }
+
+    fn compare_enums(
+        &mut self,
+        location: &str,
+        old: &Value,
+        new: &Value,
+        direction: Direction,
+    ) {
+        let old_values = old["enum"].as_array();
+        let new_values = new["enum"].as_array();
+        match (direction, old_values, new_values) {
+            (Direction::Request, None, Some(_)) => {
+                self.report(location, "values are now restricted to a set")
+            }
+            (Direction::Request, Some(old_values), Some(new_values)) => {
+                for value in old_values {
+                    if !new_values.contains(value) {
+                        self.report(
+                            location,
+                            format!("value {} is no longer accepted", value),
+                        );
+                    }
+                }
+            }
+            (Direction::Response, Some(_), None) => self
+                .report(location, "values are no longer restricted to a set"),
+            (Direction::Response, Some(old_values), Some(new_values)) => {
+                for value in new_values {
+                    if !old_values.contains(value) {
+                        self.report(
+                            location,
+                            format!("value {} may now be returned", value),
+                        );
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Compares the bounds of a request schema, which may be relaxed but not
+    /// tightened.
+    fn compare_limits(&mut self, location: &str, old: &Value, new: &Value) {
+        const MAXIMA: &[&str] = &["maximum", "maxLength", "maxItems"];
+        const MINIMA: &[&str] = &["minimum", "minLength", "minItems"];
+        let limits = MAXIMA
+            .iter()
+            .map(|name| (name, true))
+            .chain(MINIMA.iter().map(|name| (name, false)));
+        for (name, is_maximum) in limits {
+            let new_limit = match new[*name].as_f64() {
+                Some(limit) => limit,
+                None => continue,
+            };
+            let tightened = match old[*name].as_f64() {
+                None => true,
+                Some(old_limit) if is_maximum => new_limit < old_limit,
+                Some(old_limit) => new_limit > old_limit,
+            };
+            if tightened {
+                self.report(
+                    location,
+                    format!("{} tightened to {}", name, new[*name]),
+                );
+            }
+        }
+        if new["pattern"].is_string() && old["pattern"] != new["pattern"] {
+            self.report(location, "pattern changed");
+        }
+    }
+
+    fn compare_properties(
+        &mut self,
+        location: &str,
+        old: &'a Value,
+        new: &'a Value,
+        direction: Direction,
+    ) {
+        let old_required = required(old);
+        let new_required = required(new);
+        let new_properties = &new["properties"];
+
+        match direction {
+            Direction::Request => {
+                for name in &new_required {
+                    if !old_required.contains(name) {
+                        self.report(
+                            location,
+                            format!("property \"{}\" is now required", name),
+                        );
+                    }
+                }
+            }
+            Direction::Response => {
+                for (name, _) in entries(&old["properties"]) {
+                    if new_properties.get(name).is_none() {
+                        self.report(
+                            location,
+                            format!("property \"{}\" was removed", name),
+                        );
+                    } else if old_required.contains(&name.as_str())
+                        && !new_required.contains(&name.as_str())
+                    {
+                        self.report(
+                            location,
+                            format!("property \"{}\" is now optional", name),
+                        );
+                    }
+                }
+            }
+        }
+
+        for (name, old_property) in entries(&old["properties"]) {
+            if let Some(new_property) = new_properties.get(name) {
+                let location = format!("{}.{}", location, name);
+                self.compare_schema(
+                    &location,
+                    old_property,
+                    new_property,
+                    direction,
+                );
+            }
+        }
+    }
+
+    fn compare_alternatives(
+        &mut self,
+        location: &str,
+        old: &'a Value,
+        new: &'a Value,
+        direction: Direction,
+    ) {
+        for keyword in ["oneOf", "anyOf"] {
+            let (old_variants, new_variants) =
+                match (old[keyword].as_array(), new[keyword].as_array()) {
+                    (Some(old_variants), Some(new_variants)) => {
+                        (old_variants, new_variants)
+                    }
+                    _ => continue,
+                };
+            match direction {
+                Direction::Request
+                    if new_variants.len() < old_variants.len() =>
+                {
+                    self.report(location, "alternatives were removed")
+                }
+                Direction::Response
+                    if new_variants.len() > old_variants.len() =>
+                {
+                    self.report(location, "alternatives were added")
+                }
+                _ => (),
+            }
+            for (index, (old_variant, new_variant)) in
+                old_variants.iter().zip(new_variants).enumerate()
+            {
+                let location = format!("{} ({} {})", location, keyword, index);
+                self.compare_schema(
+                    &location,
+                    old_variant,
+                    new_variant,
+                    direction,
+                );
+            }
+        }
+    }
+}
+
+/// Replaces the variables of a path template with empty ones.
+fn normalize_path(path: &str) -> String {
+    let mut normalized = String::with_capacity(path.len());
+    let mut in_variable = false;
+    for c in path.chars() {
+        match c {
+            '{' => {
+                in_variable = true;
+                normalized.push(c);
+            }
+            '}' => {
+                in_variable = false;
+                normalized.push(c);
+            }
+            _ if in_variable => (),
+            _ => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// Follows a local reference (e.g., "#/components/schemas/Project") within
+/// `doc`, if `value` is one.
+fn resolve<'a>(doc: &'a Value, value: &'a Value) -> &'a Value {
+    let mut value = value;
+    // Bound the number of references followed, in case they form a cycle.
+    for _ in 0..16 {
+        match value["$ref"].as_str().and_then(|r| r.strip_prefix('#')) {
+            Some(pointer) => value = doc.pointer(pointer).unwrap_or(&NULL),
+            None => break,
+        }
+    }
+    value
+}
+
+/// Resolves a schema, looking through the `allOf` with a single member that
+/// describes a reference.
+fn unwrap_schema<'a>(doc: &'a Value, schema: &'a Value) -> &'a Value {
+    let schema = resolve(doc, schema);
+    match schema["allOf"].as_array() {
+        Some(all_of) if all_of.len() == 1 => resolve(doc, &all_of[0]),
+        _ => schema,
+    }
+}
+
+/// Returns the operation's parameters, with references resolved.
+fn parameters<'a>(doc: &'a Value, op: &'a Value) -> Vec<&'a Value> {
+    match &op["parameters"] {
+        Value::Array(params) => {
+            params.iter().map(|param| resolve(doc, param)).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the members of `value`, which are none if it isn't an object.
+fn entries(value: &Value) -> impl Iterator<Item = (&String, &Value)> {
+    value.as_object().into_iter().flatten()
+}
+
+fn required(schema: &Value) -> Vec<&str> {
+    schema["required"]
+        .as_array()
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default()
+}
+
+fn is_true(value: &Value) -> bool {
+    value.as_bool().unwrap_or(false)
+}
+
+/// Returns whether `schema` accepts any value.
+fn is_any(schema: &Value) -> bool {
+    ["type", "enum", "properties", "items", "oneOf", "anyOf", "allOf", "$ref"]
+        .iter()
+        .all(|keyword| schema.get(keyword).is_none())
+}
+
+fn type_name(schema: &Value) -> &str {
+    schema["type"].as_str().unwrap_or("an untyped schema")
+}
+
+#[cfg(test)]
+mod test {
+    use super::compare_openapi;
+    use serde_json::json;
+    use serde_json::Value;
+
+    fn changes(old: Value, new: Value) -> Vec<String> {
+        compare_openapi(&old, &new).iter().map(ToString::to_string).collect()
+    }
+
+    fn document(path: &str, operation: Value) -> Value {
+        json!({
+            "openapi": "3.0.3",
+            "paths": { path: { "post": operation } },
+            "components": {
+                "schemas": {
+                    "Project": {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string" },
+                            "size": { "type": "integer" }
+                        },
+                        "required": ["name", "size"]
+                    }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_compare_paths() {
+        let old = document("/projects/{project}", json!({}));
+        assert!(changes(old.clone(), old.clone()).is_empty());
+
+        // Renaming a path variable doesn't affect clients.
+        let new = document("/projects/{name}", json!({}));
+        assert!(changes(old.clone(), new).is_empty());
+
+        let new = document("/projects", json!({}));
+        assert_eq!(
+            changes(old, new),
+            ["POST /projects/{project}: operation was removed"]
+        );
+    }
+
+    #[test]
+    fn test_compare_parameters() {
+        let old = document(
+            "/projects",
+            json!({
+                "parameters": [{
+                    "in": "query",
+                    "name": "limit",
+                    "schema": { "type": "integer", "maximum": 100 }
+                }]
+            }),
+        );
+        let new = document(
+            "/projects",
+            json!({
+                "parameters": [
+                    {
+                        "in": "query",
+                        "name": "limit",
+                        "required": true,
+                        "schema": { "type": "number", "maximum": 50 }
+                    },
+                    {
+                        "in": "query",
+                        "name": "sort",
+                        "required": true,
+                        "schema": { "type": "string" }
+                    },
+                    {
+                        "in": "query",
+                        "name": "filter",
+                        "schema": { "type": "string" }
+                    }
+                ]
+            }),
+        );
+        assert_eq!(
+            changes(old, new),
+            [
+                "POST /projects query parameter \"limit\": parameter is now \
+                 required",
+                "POST /projects query parameter \"limit\": maximum tightened \
+                 to 50",
+                "POST /projects query parameter \"sort\": required parameter \
+                 was added",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare_bodies() {
+        let old = document(
+            "/projects",
+            json!({
+                "requestBody": {
+                    "content": {
+                        "application/json": {
+                            "schema": {
+                                "$ref": "#/components/schemas/Project"
+                            }
+                        }
+                    },
+                    "required": true
+                },
+                "responses": {
+                    "201": {
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "$ref": "#/components/schemas/Project"
+                                }
+                            }
+                        }
+                    }
+                }
+            }),
+        );
+
+        // The same change to a shared type affects requests and responses
+        // differently.
+        let mut new = old.clone();
+        new["components"]["schemas"]["Project"] = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "size": { "type": "integer" },
+                "color": { "type": "string", "enum": ["red", "blue"] }
+            },
+            "required": ["name", "color"]
+        });
+        assert_eq!(
+            changes(old.clone(), new),
+            [
+                "POST /projects request body (application/json): property \
+                 \"color\" is now required",
+                "POST /projects response 201 (application/json): property \
+                 \"size\" is now optional",
+            ]
+        );
+
+        let mut new = old.clone();
+        new["components"]["schemas"]["Project"]["properties"]["size"] =
+            json!({ "type": "number" });
+        assert_eq!(
+            changes(old.clone(), new),
+            ["POST /projects response 201 (application/json).size: type \
+                 changed from integer to number",]
+        );
+
+        let mut new = old.clone();
+        new["paths"]["/projects"]["post"]["responses"] = json!({ "200": {} });
+        assert_eq!(
+            changes(old, new),
+            ["POST /projects response 201: response status was removed"]
+        );
+    }
+
+    #[test]
+    fn test_compare_enums() {
+        let operation = |values: Value| {
+            json!({
+                "requestBody": {
+                    "content": {
+                        "application/json": {
+                            "schema": { "type": "string", "enum": values }
+                        }
+                    }
+                },
+                "responses": {
+                    "200": {
+                        "content": {
+                            "application/json": {
+                                "schema": { "type": "string", "enum": values }
+                            }
+                        }
+                    }
+                }
+            })
+        };
+        let old = document("/color", operation(json!(["red", "blue"])));
+        let new = document("/color", operation(json!(["red", "green"])));
+        assert_eq!(
+            changes(old, new),
+            [
+                "POST /color request body (application/json): value \"blue\" \
+                 is no longer accepted",
+                "POST /color response 200 (application/json): value \"green\" \
+                 may now be returned",
+            ]
+        );
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Serving an API's own OpenAPI document, and documentation generated from it
+
+use crate::error::HttpError;
+use crate::CONTENT_TYPE_JSON;
+use http::header;
+use http::StatusCode;
+use hyper::Body;
+use hyper::Response;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+/// An OpenAPI document served by the API that it describes.  The document
+/// is generated when the server is created, once every endpoint has been
+/// registered.
+pub(crate) struct OpenApiDocument {
+    pub title: String,
+    pub version: String,
+    /// the JSON document, or a description of why it couldn't be generated
+    pub contents: Arc<OnceCell<Result<String, String>>>,
+}
+
+/// Serves the OpenAPI document whose contents are in `contents`.
+pub(crate) fn openapi_get(
+    contents: &OnceCell<Result<String, String>>,
+) -> Result<Response<Body>, HttpError> {
+    match contents.get() {
+        Some(Ok(json)) => Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, CONTENT_TYPE_JSON)
+            .body(json.clone().into())?),
+        Some(Err(error)) => Err(HttpError::for_internal_error(format!(
+            "generating OpenAPI document: {}",
+            error
+        ))),
+        None => Err(HttpError::for_internal_error(
+            "OpenAPI document was not generated".to_string(),
+        )),
+    }
+}
+
+/// Name of the ReDoc script, served alongside the documentation page
+#[cfg(feature = "redoc")]
+pub(crate) const REDOC_SCRIPT_NAME: &str = "redoc.standalone.js";
+
+/// Returns the HTML page that renders the OpenAPI document at `spec_url`
+/// with the ReDoc script at `script_url`.
+#[cfg(feature = "redoc")]
+pub(crate) fn redoc_page(
+    title: &str,
+    spec_url: &str,
+    script_url: &str,
+) -> String {
+    format!(
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, \
+         initial-scale=1\">\n\
+         <title>{}</title>\n\
+         </head>\n\
+         <body>\n\
+         <redoc spec-url=\"{}\"></redoc>\n\
+         <script src=\"{}\"></script>\n\
+         </body>\n\
+         </html>\n",
+        html_escape(title),
+        html_escape(spec_url),
+        html_escape(script_url),
+    )
+}
+
+/// Serves the documentation page `page` produced by `redoc_page()`.
+#[cfg(feature = "redoc")]
+pub(crate) fn redoc_get(page: &str) -> Result<Response<Body>, HttpError> {
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .body(page.to_string().into())?)
+}
+
+/// Serves the ReDoc script bundle `script`.
+#[cfg(feature = "redoc")]
+pub(crate) fn redoc_script_get(
+    script: &'static [u8],
+) -> Result<Response<Body>, HttpError> {
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/javascript; charset=utf-8")
+        .body(script.into())?)
+}
+
+/// Escapes `text` for use in HTML content or a quoted attribute value.
+#[cfg(feature = "redoc")]
+fn html_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(all(test, feature = "redoc"))]
+mod test {
+    use super::redoc_page;
+
+    #[test]
+    fn test_redoc_page_escapes() {
+        let page = redoc_page(
+            "Cats & <Dogs>",
+            "/openapi.json?a=\"b\"",
+            "/docs/redoc.standalone.js",
+        );
+        assert!(page.contains("<title>Cats &amp; &lt;Dogs&gt;</title>"));
+        assert!(page.contains("spec-url=\"/openapi.json?a=&quot;b&quot;\""));
+        assert!(page.contains("<script src=\"/docs/redoc.standalone.js\">"));
+    }
+}
@@ -0,0 +1,177 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Checks of an API for problems that make for a poor OpenAPI document
+//!
+//! See [`ApiDescription::validate`](crate::ApiDescription::validate).
+
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// The kind of problem found by
+/// [`ApiDescription::validate`](crate::ApiDescription::validate)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ApiProblemKind {
+    /// an operation has neither a summary nor a description
+    MissingDescription,
+    /// the path parameters of an operation don't match the variables in its
+    /// path
+    PathParameters,
+    /// more than one operation has the same operation ID
+    DuplicateOperationId,
+    /// a schema is defined in the document's components but never used
+    UnreferencedSchema,
+    /// the document could not be generated at all
+    InvalidDocument,
+}
+
+/// A problem with an API found by
+/// [`ApiDescription::validate`](crate::ApiDescription::validate)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ApiProblem {
+    /// what kind of problem this is
+    pub kind: ApiProblemKind,
+    /// where the problem was found, as the operation (e.g.,
+    /// "GET /projects/{project}") or the JSON pointer of a schema
+    pub location: String,
+    /// a description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for ApiProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+const SCHEMA_REF_PREFIX: &str = "#/components/schemas/";
+
+/// Returns a problem for each schema in the components of the OpenAPI
+/// document `spec` that isn't referenced, directly or through other schemas,
+/// from elsewhere in the document.
+pub(crate) fn unreferenced_schemas(spec: &Value) -> Vec<ApiProblem> {
+    let schemas = match spec.pointer("/components/schemas") {
+        Some(Value::Object(schemas)) => schemas,
+        _ => return Vec::new(),
+    };
+
+    // Find the schemas referenced from outside the schemas themselves, then
+    // those that they reference in turn.
+    let mut roots = BTreeSet::new();
+    if let Value::Object(document) = spec {
+        for (name, value) in document {
+            match (name.as_str(), value) {
+                ("components", Value::Object(components)) => {
+                    for (name, value) in components {
+                        if name != "schemas" {
+                            find_refs(value, &mut roots);
+                        }
+                    }
+                }
+                _ => find_refs(value, &mut roots),
+            }
+        }
+    }
+    let mut referenced = BTreeSet::new();
+    let mut pending = roots.into_iter().collect::<Vec<_>>();
+    while let Some(name) = pending.pop() {
+        if !referenced.insert(name.clone()) {
+            continue;
+        }
+        if let Some(schema) = schemas.get(&name) {
+            let mut refs = BTreeSet::new();
+            find_refs(schema, &mut refs);
+            pending.extend(refs);
+        }
+    }
+
+    schemas
+        .keys()
+        .filter(|name| !referenced.contains(*name))
+        .map(|name| ApiProblem {
+            kind: ApiProblemKind::UnreferencedSchema,
+            location: format!("{}{}", SCHEMA_REF_PREFIX, name),
+            message: "schema is not used by any operation".to_string(),
+        })
+        .collect()
+}
+
+/// Adds the names of the component schemas referenced within `value` to
+/// `refs`.
+fn find_refs(value: &Value, refs: &mut BTreeSet<String>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                match (key.as_str(), value) {
+                    ("$ref", Value::String(reference)) => {
+                        if let Some(name) =
+                            reference.strip_prefix(SCHEMA_REF_PREFIX)
+                        {
+                            refs.insert(name.to_string());
+                        }
+                    }
+                    _ => find_refs(value, refs),
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                find_refs(value, refs);
+            }
+        }
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::unreferenced_schemas;
+    use super::ApiProblemKind;
+    use serde_json::json;
+
+    #[test]
+    fn test_unreferenced_schemas() {
+        let spec = json!({
+            "paths": {
+                "/projects": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "content": {
+                                    "application/json": {
+                                        "schema": {
+                                            "$ref": "#/components/schemas/Project"
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Project": {
+                        "properties": {
+                            "owner": { "$ref": "#/components/schemas/User" }
+                        }
+                    },
+                    "User": { "type": "object" },
+                    "Orphan": {
+                        "properties": {
+                            "me": { "$ref": "#/components/schemas/Orphan" }
+                        }
+                    }
+                }
+            }
+        });
+
+        let problems = unreferenced_schemas(&spec);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].kind, ApiProblemKind::UnreferencedSchema);
+        assert_eq!(
+            problems[0].to_string(),
+            "#/components/schemas/Orphan: schema is not used by any operation"
+        );
+    }
+}
@@ -0,0 +1,218 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Versions of the OpenAPI specification in which documents can be generated
+//!
+//! Dropshot builds documents according to OpenAPI 3.0.  For OpenAPI 3.1, the
+//! 3.0 document is converted: 3.1 aligns schemas with JSON Schema 2020-12,
+//! which expresses some of the same things differently.
+
+use serde_json::Value;
+
+/// Version of the OpenAPI specification to which a generated document
+/// conforms
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum OpenApiVersion {
+    /// OpenAPI 3.0.3
+    #[default]
+    V3_0,
+    /// OpenAPI 3.1.0
+    V3_1,
+}
+
+/// Keywords whose values are schemas
+const SCHEMA_KEYWORDS: &[&str] = &["items", "not", "additionalProperties"];
+/// Keywords whose values are arrays of schemas
+const SCHEMA_ARRAY_KEYWORDS: &[&str] = &["allOf", "anyOf", "oneOf"];
+
+/// Converts the OpenAPI 3.0 document `document` to OpenAPI 3.1.
+pub(crate) fn convert_to_3_1(document: &mut Value) {
+    document["openapi"] = Value::String(String::from("3.1.0"));
+    if let Some(Value::Object(schemas)) =
+        document.pointer_mut("/components/schemas")
+    {
+        schemas.values_mut().for_each(convert_schema);
+    }
+    if let Some(paths) = document.get_mut("paths") {
+        convert_nested_schemas(paths);
+    }
+    if let Some(Value::Object(components)) = document.get_mut("components") {
+        for (name, value) in components {
+            if name != "schemas" {
+                convert_nested_schemas(value);
+            }
+        }
+    }
+}
+
+/// Converts the schemas of parameters, headers, and media types (the values
+/// of "schema" members) anywhere within `value`.
+fn convert_nested_schemas(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                if key == "schema" {
+                    convert_schema(value);
+                } else {
+                    convert_nested_schemas(value);
+                }
+            }
+        }
+        Value::Array(values) => {
+            values.iter_mut().for_each(convert_nested_schemas)
+        }
+        _ => (),
+    }
+}
+
+/// Converts an OpenAPI 3.0 schema, and the schemas within it, to OpenAPI 3.1.
+fn convert_schema(schema: &mut Value) {
+    let object = match schema {
+        Value::Object(object) => object,
+        _ => return,
+    };
+
+    if let Some(Value::Object(properties)) = object.get_mut("properties") {
+        properties.values_mut().for_each(convert_schema);
+    }
+    for keyword in SCHEMA_KEYWORDS {
+        if let Some(value) = object.get_mut(*keyword) {
+            convert_schema(value);
+        }
+    }
+    for keyword in SCHEMA_ARRAY_KEYWORDS {
+        if let Some(Value::Array(values)) = object.get_mut(*keyword) {
+            values.iter_mut().for_each(convert_schema);
+        }
+    }
+
+    // An example is given as one of a list of examples.
+    if let Some(example) = object.remove("example") {
+        object.insert(String::from("examples"), Value::Array(vec![example]));
+    }
+
+    // Exclusive bounds are the bounds themselves rather than flags.
+    for (exclusive, inclusive) in
+        [("exclusiveMinimum", "minimum"), ("exclusiveMaximum", "maximum")]
+    {
+        if let Some(Value::Bool(flag)) = object.get(exclusive) {
+            let flag = *flag;
+            object.remove(exclusive);
+            if flag {
+                if let Some(bound) = object.remove(inclusive) {
+                    object.insert(exclusive.to_string(), bound);
+                }
+            }
+        }
+    }
+
+    // Null is a type of its own.
+    if let Some(Value::Bool(nullable)) = object.remove("nullable") {
+        if !nullable {
+            return;
+        }
+        if let Some(Value::Array(values)) = object.get_mut("enum") {
+            if !values.contains(&Value::Null) {
+                values.push(Value::Null);
+            }
+        }
+        match object.get_mut("type") {
+            Some(Value::String(instance_type)) => {
+                let instance_type = Value::String(instance_type.clone());
+                object.insert(
+                    String::from("type"),
+                    Value::Array(vec![instance_type, Value::from("null")]),
+                );
+            }
+            Some(_) => (),
+            None => {
+                let inner = std::mem::take(object);
+                object.insert(
+                    String::from("anyOf"),
+                    Value::Array(vec![
+                        Value::Object(inner),
+                        serde_json::json!({ "type": "null" }),
+                    ]),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::convert_to_3_1;
+    use serde_json::json;
+
+    #[test]
+    fn test_convert_to_3_1() {
+        let mut document = json!({
+            "openapi": "3.0.3",
+            "paths": {
+                "/things": {
+                    "get": {
+                        "parameters": [{
+                            "in": "query",
+                            "name": "limit",
+                            "schema": {
+                                "nullable": true,
+                                "type": "integer",
+                                "minimum": 1,
+                                "exclusiveMinimum": false
+                            }
+                        }]
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Thing": {
+                        "type": "object",
+                        "example": { "size": 2 },
+                        "properties": {
+                            "size": {
+                                "type": "number",
+                                "minimum": 0,
+                                "exclusiveMinimum": true
+                            },
+                            "color": {
+                                "nullable": true,
+                                "type": "string",
+                                "enum": ["red", "blue"]
+                            },
+                            "owner": {
+                                "nullable": true,
+                                "allOf": [{ "$ref": "#/components/schemas/User" }]
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        convert_to_3_1(&mut document);
+
+        assert_eq!(document["openapi"], "3.1.0");
+        assert_eq!(
+            document["paths"]["/things"]["get"]["parameters"][0]["schema"],
+            json!({ "type": ["integer", "null"], "minimum": 1 })
+        );
+        let thing = &document["components"]["schemas"]["Thing"];
+        assert_eq!(thing["examples"], json!([{ "size": 2 }]));
+        assert_eq!(
+            thing["properties"]["size"],
+            json!({ "type": "number", "exclusiveMinimum": 0 })
+        );
+        assert_eq!(
+            thing["properties"]["color"],
+            json!({ "type": ["string", "null"], "enum": ["red", "blue", null] })
+        );
+        assert_eq!(
+            thing["properties"]["owner"],
+            json!({
+                "anyOf": [
+                    { "allOf": [{ "$ref": "#/components/schemas/User" }] },
+                    { "type": "null" }
+                ]
+            })
+        );
+    }
+}
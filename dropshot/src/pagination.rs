@@ -99,8 +99,8 @@
 
 use crate::error::HttpError;
 use crate::from_map::from_map;
-use base64::engine::general_purpose::URL_SAFE;
-use base64::Engine;
+use futures::Stream;
+use futures::StreamExt;
 use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
@@ -110,6 +110,25 @@ use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::num::NonZeroU32;
 
+mod keyset;
+mod offset;
+mod resource;
+mod token_settings;
+
+pub use keyset::KeysetBounds;
+pub use keyset::KeysetComparison;
+pub use keyset::KeysetPage;
+pub use keyset::KeysetPageSelector;
+pub use keyset::KeysetScanParams;
+pub use offset::OffsetPaginationParams;
+pub use resource::PaginatedResource;
+pub use token_settings::PageTokenEncoding;
+pub use token_settings::PageTokenKey;
+pub use token_settings::PageTokenScope;
+
+pub(crate) use token_settings::with_page_tokens;
+pub(crate) use token_settings::PageTokenSettings;
+
 /// A page of results from a paginated API
 ///
 /// This structure is intended for use both on the server side (to generate the
@@ -120,6 +139,10 @@ pub struct ResultsPage<ItemType> {
     pub next_page: Option<String>,
     /// list of items on this page of results
     pub items: Vec<ItemType>,
+    /// total number of items in the collection, if the server provides it
+    /// (this may be an estimate)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_count: Option<u64>,
 }
 
 impl<ItemType> JsonSchema for ResultsPage<ItemType>
@@ -144,6 +167,9 @@ pub struct ResultsPageSchema<ItemType> {
     pub next_page: Option<String>,
     /// list of items on this page of results
     pub items: Vec<ItemType>,
+    /// total number of items in the collection, if the server provides it
+    /// (this may be an estimate)
+    pub total_count: Option<u64>,
 }
 
 impl<ItemType> ResultsPage<ItemType> {
@@ -168,10 +194,82 @@ impl<ItemType> ResultsPage<ItemType> {
             })
             .transpose()?;
 
-        Ok(ResultsPage { next_page, items })
+        Ok(ResultsPage { next_page, items, total_count: None })
+    }
+
+    /// Reports the total number of items in the collection (or an estimate of
+    /// it), so that clients can tell how many pages remain.  Counting can be
+    /// expensive for large collections, so this is optional.
+    pub fn with_total_count(mut self, total_count: u64) -> Self {
+        self.total_count = Some(total_count);
+        self
     }
 }
 
+/// Construct the page of results from `items`, an iterator over the collection
+/// positioned at the start of the requested page, in scan order.  At most
+/// `limit` items are taken from the iterator (typically, `limit` is the result
+/// of [`RequestContext::page_limit`]).  As with [`ResultsPage::new`],
+/// `get_page_selector` constructs the token for the next page from the last
+/// item on the page and `scan_params`.
+///
+/// This replaces the usual `take(limit)`, `collect()`, and
+/// [`ResultsPage::new`] sequence at the end of a handler:
+///
+/// ```
+/// use dropshot::paginate_iter;
+/// use dropshot::EmptyScanParams;
+/// use dropshot::HttpError;
+/// use dropshot::ResultsPage;
+/// use std::num::NonZeroU32;
+///
+/// fn list_after(
+///     last_seen: Option<u32>,
+///     limit: NonZeroU32,
+/// ) -> Result<ResultsPage<u32>, HttpError> {
+///     let start = last_seen.map(|n| n + 1).unwrap_or(0);
+///     paginate_iter(start..1000, &EmptyScanParams {}, limit, |n, _| *n)
+/// }
+///
+/// let page = list_after(Some(4), NonZeroU32::new(3).unwrap()).unwrap();
+/// assert_eq!(page.items, vec![5, 6, 7]);
+/// ```
+///
+/// [`RequestContext::page_limit`]: crate::RequestContext::page_limit
+pub fn paginate_iter<I, F, ScanParams, PageSelector>(
+    items: I,
+    scan_params: &ScanParams,
+    limit: NonZeroU32,
+    get_page_selector: F,
+) -> Result<ResultsPage<I::Item>, HttpError>
+where
+    I: IntoIterator,
+    F: Fn(&I::Item, &ScanParams) -> PageSelector,
+    PageSelector: Serialize,
+{
+    let items = items.into_iter().take(limit.get() as usize).collect();
+    ResultsPage::new(items, scan_params, get_page_selector)
+}
+
+/// Construct the page of results from `items`, a stream over the collection
+/// positioned at the start of the requested page, in scan order.  This is the
+/// asynchronous counterpart of [`paginate_iter`]: the stream is only polled for
+/// (at most) `limit` items.
+pub async fn paginate_stream<S, F, ScanParams, PageSelector>(
+    items: S,
+    scan_params: &ScanParams,
+    limit: NonZeroU32,
+    get_page_selector: F,
+) -> Result<ResultsPage<S::Item>, HttpError>
+where
+    S: Stream,
+    F: Fn(&S::Item, &ScanParams) -> PageSelector,
+    PageSelector: Serialize,
+{
+    let items: Vec<_> = items.take(limit.get() as usize).collect().await;
+    ResultsPage::new(items, scan_params, get_page_selector)
+}
+
 /// Querystring parameters provided by clients when scanning a paginated
 /// collection
 ///
@@ -222,13 +320,148 @@ where
     ///
     /// Consumers should use
     /// [`RequestContext`][crate::handler::RequestContext::page_limit()]
-    /// to access this value.
+    /// or [`PaginationParams::limit_or`] to access this value.
     pub(crate) limit: Option<NonZeroU32>,
 }
 
+impl<ScanParams, PageSelector> PaginationParams<ScanParams, PageSelector>
+where
+    ScanParams: DeserializeOwned,
+    PageSelector: DeserializeOwned + Serialize,
+{
+    /// Returns the number of items to return for this request: the
+    /// client-requested limit, or `default` if the client didn't specify one.
+    /// Unlike [`RequestContext::page_limit`], which silently clamps the
+    /// client's limit to the server-wide maximum, this fails with a 400 ("Bad
+    /// Request") if the client asks for more than `max` items, so that an
+    /// endpoint can enforce a page size of its own.  (`default` is itself
+    /// clamped to `max`.)
+    ///
+    /// [`RequestContext::page_limit`]: crate::RequestContext::page_limit
+    pub fn limit_or(
+        &self,
+        default: NonZeroU32,
+        max: NonZeroU32,
+    ) -> Result<NonZeroU32, HttpError> {
+        match self.limit {
+            None => Ok(default.min(max)),
+            Some(limit) if limit <= max => Ok(limit),
+            Some(limit) => Err(HttpError::for_bad_request(
+                None,
+                format!(
+                    "limit {} exceeds the maximum page size ({})",
+                    limit, max
+                ),
+            )),
+        }
+    }
+
+    /// Returns the page selector provided by the client if this request
+    /// resumes a scan, or `None` if it begins one
+    pub fn page_selector(&self) -> Option<&PageSelector> {
+        match &self.page {
+            WhichPage::First(_) => None,
+            WhichPage::Next(page_selector) => Some(page_selector),
+        }
+    }
+
+    /// Returns the mode of the scan that this request begins or resumes
+    /// (typically, how the collection is sorted), or `default_mode` if the
+    /// client began the scan without choosing one.  See [`ScanMode`].
+    pub fn current_scan_mode<Mode>(&self, default_mode: Mode) -> Mode
+    where
+        ScanParams: ScanMode<Mode = Mode>,
+        PageSelector: ScanMode<Mode = Mode>,
+    {
+        match &self.page {
+            WhichPage::First(scan_params) => scan_params.scan_mode(),
+            WhichPage::Next(page_selector) => page_selector.scan_mode(),
+        }
+        .unwrap_or(default_mode)
+    }
+}
+
+/// `ScanParams` or `PageSelector` that records the mode of a scan (typically,
+/// how the collection is sorted)
+///
+/// An endpoint that supports several modes generally has the client choose one
+/// in its `ScanParams` and records it in its `PageSelector` so that subsequent
+/// pages continue the same scan.  The handler then needs the mode regardless
+/// of which page is being requested.  When an endpoint's `ScanParams` and
+/// `PageSelector` both implement this trait with the same `Mode`,
+/// [`PaginationParams::current_scan_mode`] provides it, leaving the handler to
+/// match on the mode and on [`PaginationParams::page_selector`] only to find
+/// where to resume.
+///
+/// ```
+/// use dropshot::PaginationParams;
+/// use dropshot::ScanMode;
+/// # use serde::Deserialize;
+/// # use serde::Serialize;
+///
+/// # #[derive(Deserialize)]
+/// #[derive(Clone, Copy)]
+/// enum ProjectSort {
+///     ByName,
+///     ByMtime,
+/// }
+///
+/// # #[derive(Deserialize)]
+/// struct ProjectScanParams {
+///     sort: Option<ProjectSort>,
+/// }
+///
+/// impl ScanMode for ProjectScanParams {
+///     type Mode = ProjectSort;
+///     fn scan_mode(&self) -> Option<ProjectSort> {
+///         self.sort
+///     }
+/// }
+///
+/// # #[derive(Deserialize, Serialize)]
+/// enum ProjectPageSelector {
+///     Name(String),
+///     Mtime(u64, String),
+/// }
+///
+/// impl ScanMode for ProjectPageSelector {
+///     type Mode = ProjectSort;
+///     fn scan_mode(&self) -> Option<ProjectSort> {
+///         Some(match self {
+///             ProjectPageSelector::Name(..) => ProjectSort::ByName,
+///             ProjectPageSelector::Mtime(..) => ProjectSort::ByMtime,
+///         })
+///     }
+/// }
+///
+/// fn list_projects(
+///     pag_params: &PaginationParams<ProjectScanParams, ProjectPageSelector>,
+/// ) {
+///     match pag_params.current_scan_mode(ProjectSort::ByName) {
+///         ProjectSort::ByName => { /* ... */ }
+///         ProjectSort::ByMtime => { /* ... */ }
+///     }
+/// }
+/// ```
+pub trait ScanMode {
+    /// the endpoint's type describing the modes of a scan
+    type Mode;
+
+    /// Returns the mode of the scan, or `None` if the client left it to the
+    /// endpoint's default.
+    fn scan_mode(&self) -> Option<Self::Mode>;
+}
+
 pub(crate) const PAGINATION_PARAM_SENTINEL: &str =
     "x-dropshot-pagination-param";
 pub(crate) const PAGINATION_EXTENSION: &str = "x-dropshot-pagination";
+/// Query parameters of a paginated endpoint that apply to every page of a
+/// scan (as opposed to those of the consumer's `ScanParams`)
+pub(crate) const PAGINATION_PARAMS: &[&str] = &["limit", "page_token"];
+/// Added to the OpenAPI descriptions of `ScanParams` query parameters
+pub(crate) const SCAN_PARAM_NOTE: &str =
+    "Applies only to the first page of a scan (ignored if `page_token` is \
+     provided)";
 
 #[derive(Serialize)]
 struct PaginationParamSentinelValue {
@@ -353,9 +586,12 @@ pub enum WhichPage<ScanParams, PageSelector> {
 pub struct EmptyScanParams {}
 
 /// The order in which the client wants to page through the requested collection
-#[derive(Copy, Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[derive(
+    Copy, Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize,
+)]
 #[serde(rename_all = "lowercase")]
 pub enum PaginationOrder {
+    #[default]
     Ascending,
     Descending,
 }
@@ -369,10 +605,19 @@ pub enum PaginationOrder {
 // approach allows us to rev the serialized form if needed (see
 // `PaginationVersion`) and add other metadata in a backwards-compatiable way.
 // It also emphasizes to clients that the token should be treated as opaque,
-// though it's obviously not resistant to tampering.
-
-/// Maximum length of a page token once the consumer-provided type is serialized
-/// and the result is base64-encoded
+// though it's obviously not resistant to tampering unless the API has a
+// `PageTokenKey`, in which case a signature is appended to the encoded JSON.
+//
+// Depending on the API's settings, the token may also record the version of
+// the consumer's page selectors and the time at which the token expires.  Both
+// are checked before the consumer's PageSelector is deserialized so that a
+// client holding a stale token is told to restart its scan, rather than being
+// told that its token is corrupted.
+
+/// Default maximum length of a page token once the consumer-provided type is
+/// serialized and the result is base64-encoded (and signed, if the API has a
+/// [`PageTokenKey`]).  APIs can change this with
+/// `ApiDescription::page_token_max_length`.
 ///
 /// We impose a maximum length primarily to prevent a client from making us parse
 /// extremely large strings.  We apply this limit when we create tokens to avoid
@@ -407,6 +652,15 @@ enum PaginationVersion {
 #[derive(Debug, Deserialize, Serialize)]
 struct SerializedToken<PageSelector> {
     v: PaginationVersion,
+    /// version of the consumer's page selectors, if the API has one (see
+    /// `ApiDescription::page_token_version`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sv: Option<u32>,
+    /// time (in seconds since the Unix epoch) after which the token is no
+    /// longer accepted, if the API's tokens expire (see
+    /// `ApiDescription::page_token_lifetime`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    exp: Option<i64>,
     page_start: PageSelector,
 }
 
@@ -414,9 +668,14 @@ struct SerializedToken<PageSelector> {
 fn serialize_page_token<PageSelector: Serialize>(
     page_start: PageSelector,
 ) -> Result<String, HttpError> {
+    let page_tokens = token_settings::current_page_tokens();
     let token_bytes = {
-        let serialized_token =
-            SerializedToken { v: PaginationVersion::V1, page_start };
+        let serialized_token = SerializedToken {
+            v: PaginationVersion::V1,
+            sv: page_tokens.as_ref().and_then(|p| p.version()),
+            exp: page_tokens.as_ref().and_then(|p| p.expiration()),
+            page_start,
+        };
 
         let json_bytes =
             serde_json::to_vec(&serialized_token).map_err(|e| {
@@ -426,7 +685,15 @@ fn serialize_page_token<PageSelector: Serialize>(
                 ))
             })?;
 
-        URL_SAFE.encode(json_bytes)
+        let token = page_tokens
+            .as_ref()
+            .map(|p| p.encoding())
+            .unwrap_or_default()
+            .encode(&json_bytes);
+        match page_tokens.as_ref().and_then(|p| p.key()) {
+            Some(key) => key.sign(&token),
+            None => token,
+        }
     };
 
     // TODO-robustness is there a way for us to know at compile-time that
@@ -435,29 +702,44 @@ fn serialize_page_token<PageSelector: Serialize>(
     // mean that if it ever works, then it will always work?  But would that
     // interface be a pain to use, given that variable-length strings are
     // very common in the token?
-    if token_bytes.len() > MAX_TOKEN_LENGTH {
+    let max_length = max_token_length(&page_tokens);
+    if token_bytes.len() > max_length {
         return Err(HttpError::for_internal_error(format!(
             "serialized token is too large ({} bytes, max is {})",
             token_bytes.len(),
-            MAX_TOKEN_LENGTH
+            max_length
         )));
     }
 
     Ok(token_bytes)
 }
 
+/// Returns the maximum length of page tokens under the given settings.
+fn max_token_length(page_tokens: &Option<token_settings::PageTokens>) -> usize {
+    page_tokens
+        .as_ref()
+        .and_then(|p| p.max_length())
+        .unwrap_or(MAX_TOKEN_LENGTH)
+}
+
 /// Deserialize a token from the given string into the consumer's page selector
 /// type
 fn deserialize_page_token<PageSelector: DeserializeOwned>(
     token_str: &str,
 ) -> Result<PageSelector, String> {
-    if token_str.len() > MAX_TOKEN_LENGTH {
+    let page_tokens = token_settings::current_page_tokens();
+    if token_str.len() > max_token_length(&page_tokens) {
         return Err(String::from(
             "failed to parse pagination token: too large",
         ));
     }
-    let json_bytes = URL_SAFE
-        .decode(token_str.as_bytes())
+    let token_str = match page_tokens.as_ref().and_then(|p| p.key()) {
+        Some(key) => key.verify(token_str).ok_or_else(|| {
+            String::from("failed to parse pagination token: invalid signature")
+        })?,
+        None => token_str,
+    };
+    let json_bytes = PageTokenEncoding::decode(token_str)
         .map_err(|e| format!("failed to parse pagination token: {}", e))?;
 
     // TODO-debugging: we don't want the user to have to know about the
@@ -469,7 +751,7 @@ fn deserialize_page_token<PageSelector: DeserializeOwned>(
     // supports this, but it seems like serde only preserves the to_string()
     // output of the error anyway.  It's not clear how else we could
     // propagate this information out.
-    let deserialized: SerializedToken<PageSelector> =
+    let deserialized: SerializedToken<serde_json::Value> =
         serde_json::from_slice(&json_bytes).map_err(|_| {
             String::from("failed to parse pagination token: corrupted token")
         })?;
@@ -481,15 +763,36 @@ fn deserialize_page_token<PageSelector: DeserializeOwned>(
         ));
     }
 
-    Ok(deserialized.page_start)
+    if deserialized.sv != page_tokens.as_ref().and_then(|p| p.version()) {
+        return Err(String::from(
+            "failed to parse pagination token: token is for another version \
+             of the API; restart the scan",
+        ));
+    }
+
+    if let (Some(exp), Some(page_tokens)) = (deserialized.exp, &page_tokens) {
+        if page_tokens.now() > exp {
+            return Err(String::from(
+                "failed to parse pagination token: token has expired; \
+                 restart the scan",
+            ));
+        }
+    }
+
+    serde_json::from_value(deserialized.page_start).map_err(|_| {
+        String::from("failed to parse pagination token: corrupted token")
+    })
 }
 
 #[cfg(test)]
 mod test {
     use super::deserialize_page_token;
+    use super::paginate_iter;
+    use super::paginate_stream;
     use super::serialize_page_token;
     use super::PaginationParams;
     use super::ResultsPage;
+    use super::ScanMode;
     use super::WhichPage;
     use super::PAGINATION_PARAM_SENTINEL;
     use base64::engine::general_purpose::URL_SAFE;
@@ -499,6 +802,7 @@ mod test {
     use serde::Deserialize;
     use serde::Serialize;
     use serde_json::json;
+    use std::collections::BTreeMap;
     use std::{fmt::Debug, num::NonZeroU32};
 
     #[test]
@@ -764,6 +1068,83 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_pagparams_limit_or() {
+        let limit_or = |querystring: &str, default: u32, max: u32| {
+            let pagparams: PaginationParams<BTreeMap<String, String>, u32> =
+                serde_urlencoded::from_str(querystring).unwrap();
+            pagparams
+                .limit_or(
+                    NonZeroU32::new(default).unwrap(),
+                    NonZeroU32::new(max).unwrap(),
+                )
+                .map(NonZeroU32::get)
+        };
+
+        assert_eq!(limit_or("", 10, 50).unwrap(), 10);
+        assert_eq!(limit_or("", 100, 50).unwrap(), 50);
+        assert_eq!(limit_or("limit=7", 10, 50).unwrap(), 7);
+        assert_eq!(limit_or("limit=50", 10, 50).unwrap(), 50);
+        let error = limit_or("limit=51", 10, 50).unwrap_err();
+        assert_eq!(error.status_code, http::StatusCode::BAD_REQUEST);
+        assert_eq!(
+            error.external_message,
+            "limit 51 exceeds the maximum page size (50)"
+        );
+    }
+
+    #[test]
+    fn test_pagparams_scan_mode() {
+        #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Sort {
+            Name,
+            Mtime,
+        }
+        #[derive(Debug, Deserialize)]
+        struct Params {
+            sort: Option<Sort>,
+        }
+        impl ScanMode for Params {
+            type Mode = Sort;
+            fn scan_mode(&self) -> Option<Sort> {
+                self.sort
+            }
+        }
+        #[derive(Debug, Deserialize, Serialize)]
+        struct Selector {
+            sort: Sort,
+            last_seen: String,
+        }
+        impl ScanMode for Selector {
+            type Mode = Sort;
+            fn scan_mode(&self) -> Option<Sort> {
+                Some(self.sort)
+            }
+        }
+        let parse = |querystring: &str| {
+            serde_urlencoded::from_str::<PaginationParams<Params, Selector>>(
+                querystring,
+            )
+            .unwrap()
+        };
+
+        let pagparams = parse("");
+        assert_eq!(pagparams.current_scan_mode(Sort::Name), Sort::Name);
+        assert!(pagparams.page_selector().is_none());
+        let pagparams = parse("sort=mtime");
+        assert_eq!(pagparams.current_scan_mode(Sort::Name), Sort::Mtime);
+
+        let token = serialize_page_token(Selector {
+            sort: Sort::Mtime,
+            last_seen: String::from("proj"),
+        })
+        .unwrap();
+        let pagparams = parse(&format!("page_token={}", token));
+        assert_eq!(pagparams.current_scan_mode(Sort::Name), Sort::Mtime);
+        assert_eq!(pagparams.page_selector().unwrap().last_seen, "proj");
+    }
+
     #[test]
     fn test_results_page() {
         // It would be a neat paginated fibonacci API if the page selector was
@@ -795,6 +1176,53 @@ mod test {
         assert!(results.next_page.is_none());
     }
 
+    #[test]
+    fn test_results_page_total_count() {
+        let get_page = |item: &u32, _: &()| *item;
+
+        // The count is omitted unless the server provides one.
+        let results = ResultsPage::new(vec![1, 2], &(), get_page).unwrap();
+        let json = serde_json::to_value(&results).unwrap();
+        assert!(json.get("total_count").is_none());
+        let results: ResultsPage<u32> = serde_json::from_value(json).unwrap();
+        assert_eq!(results.total_count, None);
+
+        let results = ResultsPage::new(vec![1, 2], &(), get_page)
+            .unwrap()
+            .with_total_count(40);
+        let json = serde_json::to_value(&results).unwrap();
+        assert_eq!(json["total_count"], 40);
+        let results: ResultsPage<u32> = serde_json::from_value(json).unwrap();
+        assert_eq!(results.total_count, Some(40));
+    }
+
+    #[tokio::test]
+    async fn test_paginate_iter() {
+        let limit = NonZeroU32::new(3).unwrap();
+        let get_page = |item: &u32, _: &()| *item;
+
+        // Only "limit" items are consumed, even from an unbounded iterator.
+        let results = paginate_iter(5.., &(), limit, get_page).unwrap();
+        assert_eq!(results.items, vec![5, 6, 7]);
+        let token = results.next_page.unwrap();
+        assert_eq!(deserialize_page_token::<u32>(&token).unwrap(), 7);
+
+        let results = paginate_iter(vec![1], &(), limit, get_page).unwrap();
+        assert_eq!(results.items, vec![1]);
+        assert!(results.next_page.is_some());
+
+        let results = paginate_iter(None, &(), limit, get_page).unwrap();
+        assert!(results.items.is_empty());
+        assert!(results.next_page.is_none());
+
+        let stream = futures::stream::iter(5..);
+        let results =
+            paginate_stream(stream, &(), limit, get_page).await.unwrap();
+        assert_eq!(results.items, vec![5, 6, 7]);
+        let token = results.next_page.unwrap();
+        assert_eq!(deserialize_page_token::<u32>(&token).unwrap(), 7);
+    }
+
     #[derive(Deserialize, Serialize, JsonSchema)]
     struct Name {
         name: String,
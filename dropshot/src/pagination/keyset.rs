@@ -0,0 +1,358 @@
+// Copyright 2023 Oxide Computer Company
+//! Keyset pagination
+//!
+//! Most paginated collections are sorted by some unique key (or combination
+//! of keys) and resumed after the key of the last item the client saw.  The
+//! types here capture that pattern so that an endpoint only has to say how its
+//! scan parameters and page selector describe the order and the last-seen key.
+//! [`KeysetBounds::new`] turns [`PaginationParams`] into bounds that a backend
+//! can apply: the order in which to return items, the key that items must come
+//! after, and the maximum number of items.
+//!
+//! For a collection kept in a `BTreeMap`, [`KeysetBounds::select_from`] applies
+//! the bounds directly.  For a database, the bounds translate into an `ORDER
+//! BY`, a `WHERE` comparison on the key columns (see
+//! [`KeysetBounds::comparison`]), and a `LIMIT`.  With sqlx, that might look
+//! like this:
+//!
+//! ```ignore
+//! let bounds = KeysetBounds::new(&pag_params, rqctx.page_limit(&pag_params)?);
+//! let mut query = sqlx::QueryBuilder::new("SELECT * FROM projects");
+//! if let Some(name) = &bounds.after {
+//!     query.push(" WHERE name ");
+//!     query.push(bounds.comparison().sql_operator());
+//!     query.push_bind(name);
+//! }
+//! query.push(" ORDER BY name ");
+//! query.push(bounds.sql_order());
+//! query.push(" LIMIT ");
+//! query.push_bind(i64::from(bounds.limit.get()));
+//! let projects: Vec<Project> = query.build_query_as().fetch_all(&pool).await?;
+//! ```
+//!
+//! and with diesel:
+//!
+//! ```ignore
+//! let bounds = KeysetBounds::new(&pag_params, rqctx.page_limit(&pag_params)?);
+//! let mut query = projects::table.into_boxed();
+//! query = match (&bounds.after, bounds.comparison()) {
+//!     (None, _) => query,
+//!     (Some(name), KeysetComparison::GreaterThan) => {
+//!         query.filter(projects::name.gt(name))
+//!     }
+//!     (Some(name), KeysetComparison::LessThan) => {
+//!         query.filter(projects::name.lt(name))
+//!     }
+//! };
+//! query = match bounds.order {
+//!     PaginationOrder::Ascending => query.order(projects::name.asc()),
+//!     PaginationOrder::Descending => query.order(projects::name.desc()),
+//! };
+//! let projects = query
+//!     .limit(i64::from(bounds.limit.get()))
+//!     .load::<Project>(&mut conn)?;
+//! ```
+//!
+//! For a composite key, compare the key columns as a row value (e.g.,
+//! `WHERE (mtime, name) > ($1, $2)`), which most SQL databases support, or
+//! expand the comparison into `mtime > $1 OR (mtime = $1 AND name > $2)`.
+//! Either way, every key column must be sorted in the same direction.
+//!
+//! [`KeysetBounds::results_page`] then builds the page of results, including
+//! the token for the next page.  See `examples/pagination-keyset.rs` for a
+//! complete example.
+
+use super::EmptyScanParams;
+use super::PaginationOrder;
+use super::PaginationParams;
+use super::ResultsPage;
+use super::WhichPage;
+use crate::error::HttpError;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::num::NonZeroU32;
+use std::ops::Bound;
+
+/// `ScanParams` for a keyset-paginated collection
+pub trait KeysetScanParams {
+    /// Returns the order in which the client asked to list the collection.
+    fn order(&self) -> PaginationOrder;
+}
+
+/// A collection without scan parameters is listed in ascending order.
+impl KeysetScanParams for EmptyScanParams {
+    fn order(&self) -> PaginationOrder {
+        PaginationOrder::Ascending
+    }
+}
+
+/// `PageSelector` for a keyset-paginated collection
+///
+/// [`KeysetPage`] implements this for the common case where the order and the
+/// last-seen key are all that the page selector needs.
+pub trait KeysetPageSelector {
+    /// key by which the collection is sorted (a tuple for a composite key)
+    type Key: Clone;
+
+    /// Returns the order of the scan being resumed.
+    fn order(&self) -> PaginationOrder;
+
+    /// Returns the key of the last item the client has seen.
+    fn last_seen(&self) -> &Self::Key;
+}
+
+/// General-purpose `PageSelector` for a keyset-paginated collection
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+pub struct KeysetPage<K> {
+    pub order: PaginationOrder,
+    /// key of the last item on the previous page
+    pub last_seen: K,
+}
+
+impl<K: Clone> KeysetPageSelector for KeysetPage<K> {
+    type Key = K;
+
+    fn order(&self) -> PaginationOrder {
+        self.order
+    }
+
+    fn last_seen(&self) -> &K {
+        &self.last_seen
+    }
+}
+
+/// Comparison that the keys of the items on a page must satisfy relative to
+/// [`KeysetBounds::after`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeysetComparison {
+    GreaterThan,
+    LessThan,
+}
+
+impl KeysetComparison {
+    /// Returns the SQL operator for this comparison.
+    pub fn sql_operator(&self) -> &'static str {
+        match self {
+            KeysetComparison::GreaterThan => ">",
+            KeysetComparison::LessThan => "<",
+        }
+    }
+}
+
+/// Describes which items of a keyset-paginated collection belong on the
+/// requested page
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeysetBounds<K> {
+    /// order in which to return items
+    pub order: PaginationOrder,
+    /// key of the last item the client has seen, if any.  Only items that come
+    /// strictly after it (in `order`) belong on the page.
+    pub after: Option<K>,
+    /// maximum number of items to return
+    pub limit: NonZeroU32,
+}
+
+impl<K> KeysetBounds<K> {
+    /// Returns the bounds of the page requested by `pag_params`.  `limit` is
+    /// typically the result of
+    /// [`RequestContext::page_limit`](crate::RequestContext::page_limit).
+    pub fn new<ScanParams, PageSelector>(
+        pag_params: &PaginationParams<ScanParams, PageSelector>,
+        limit: NonZeroU32,
+    ) -> Self
+    where
+        ScanParams: KeysetScanParams + DeserializeOwned,
+        PageSelector:
+            KeysetPageSelector<Key = K> + DeserializeOwned + Serialize,
+        K: Clone,
+    {
+        match &pag_params.page {
+            WhichPage::First(scan_params) => {
+                KeysetBounds { order: scan_params.order(), after: None, limit }
+            }
+            WhichPage::Next(page_selector) => KeysetBounds {
+                order: page_selector.order(),
+                after: Some(page_selector.last_seen().clone()),
+                limit,
+            },
+        }
+    }
+
+    /// Returns the comparison that keys of items on the page must satisfy
+    /// relative to [`after`](Self::after).
+    pub fn comparison(&self) -> KeysetComparison {
+        match self.order {
+            PaginationOrder::Ascending => KeysetComparison::GreaterThan,
+            PaginationOrder::Descending => KeysetComparison::LessThan,
+        }
+    }
+
+    /// Returns the SQL keyword (`ASC` or `DESC`) for sorting by the key.
+    pub fn sql_order(&self) -> &'static str {
+        match self.order {
+            PaginationOrder::Ascending => "ASC",
+            PaginationOrder::Descending => "DESC",
+        }
+    }
+
+    /// Returns whether an item with key `key` may appear on the page (ignoring
+    /// the limit).
+    pub fn admits(&self, key: &K) -> bool
+    where
+        K: Ord,
+    {
+        match (&self.after, self.comparison()) {
+            (None, _) => true,
+            (Some(after), KeysetComparison::GreaterThan) => key > after,
+            (Some(after), KeysetComparison::LessThan) => key < after,
+        }
+    }
+
+    /// Returns the items of `map` that belong on the page, in order.
+    pub fn select_from<'a, V>(&self, map: &'a BTreeMap<K, V>) -> Vec<&'a V>
+    where
+        K: Ord,
+    {
+        let limit = self.limit.get() as usize;
+        match (self.order, &self.after) {
+            (PaginationOrder::Ascending, None) => {
+                map.values().take(limit).collect()
+            }
+            (PaginationOrder::Ascending, Some(after)) => map
+                .range((Bound::Excluded(after), Bound::Unbounded))
+                .map(|(_, value)| value)
+                .take(limit)
+                .collect(),
+            (PaginationOrder::Descending, None) => {
+                map.values().rev().take(limit).collect()
+            }
+            (PaginationOrder::Descending, Some(after)) => map
+                .range((Bound::Unbounded, Bound::Excluded(after)))
+                .rev()
+                .map(|(_, value)| value)
+                .take(limit)
+                .collect(),
+        }
+    }
+
+    /// Constructs the page of results from `items` (which should be the items
+    /// selected by these bounds), using `key` to get the key of each item.  The
+    /// token for the next page is a [`KeysetPage`].
+    pub fn results_page<T, F>(
+        &self,
+        items: Vec<T>,
+        key: F,
+    ) -> Result<ResultsPage<T>, HttpError>
+    where
+        F: Fn(&T) -> K,
+        K: Serialize,
+    {
+        ResultsPage::new(items, &self.order, |item, order| KeysetPage {
+            order: *order,
+            last_seen: key(item),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::KeysetBounds;
+    use super::KeysetComparison;
+    use super::KeysetPage;
+    use super::KeysetScanParams;
+    use crate::pagination::PaginationOrder;
+    use crate::pagination::PaginationParams;
+    use crate::pagination::WhichPage;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+    use std::num::NonZeroU32;
+
+    #[derive(Debug, Deserialize)]
+    struct ScanParams {
+        order: PaginationOrder,
+    }
+
+    impl KeysetScanParams for ScanParams {
+        fn order(&self) -> PaginationOrder {
+            self.order
+        }
+    }
+
+    fn bounds(
+        page: WhichPage<ScanParams, KeysetPage<u32>>,
+        limit: u32,
+    ) -> KeysetBounds<u32> {
+        let pag_params = PaginationParams { page, limit: None };
+        KeysetBounds::new(&pag_params, NonZeroU32::new(limit).unwrap())
+    }
+
+    #[test]
+    fn test_keyset_bounds() {
+        let map = (1..=10).map(|n| (n, n * 100)).collect::<BTreeMap<_, _>>();
+
+        let first = bounds(
+            WhichPage::First(ScanParams { order: PaginationOrder::Ascending }),
+            3,
+        );
+        assert_eq!(first.after, None);
+        assert_eq!(first.comparison(), KeysetComparison::GreaterThan);
+        assert_eq!(first.sql_order(), "ASC");
+        assert!(first.admits(&1));
+        assert_eq!(first.select_from(&map), [&100, &200, &300]);
+
+        let next = bounds(
+            WhichPage::Next(KeysetPage {
+                order: PaginationOrder::Ascending,
+                last_seen: 8,
+            }),
+            3,
+        );
+        assert_eq!(next.after, Some(8));
+        assert!(!next.admits(&8));
+        assert!(next.admits(&9));
+        assert_eq!(next.select_from(&map), [&900, &1000]);
+
+        let first = bounds(
+            WhichPage::First(ScanParams { order: PaginationOrder::Descending }),
+            2,
+        );
+        assert_eq!(first.comparison(), KeysetComparison::LessThan);
+        assert_eq!(first.comparison().sql_operator(), "<");
+        assert_eq!(first.sql_order(), "DESC");
+        assert_eq!(first.select_from(&map), [&1000, &900]);
+
+        let next = bounds(
+            WhichPage::Next(KeysetPage {
+                order: PaginationOrder::Descending,
+                last_seen: 3,
+            }),
+            5,
+        );
+        assert!(next.admits(&2));
+        assert!(!next.admits(&3));
+        assert_eq!(next.select_from(&map), [&200, &100]);
+    }
+
+    #[test]
+    fn test_keyset_results_page() {
+        let bounds = bounds(
+            WhichPage::First(ScanParams { order: PaginationOrder::Descending }),
+            2,
+        );
+        let page = bounds.results_page(vec![10, 9], |item| *item).unwrap();
+        assert_eq!(page.items, [10, 9]);
+        let token = page.next_page.unwrap();
+        let selector: KeysetPage<u32> =
+            crate::pagination::deserialize_page_token(&token).unwrap();
+        assert_eq!(
+            selector,
+            KeysetPage { order: PaginationOrder::Descending, last_seen: 9 }
+        );
+
+        let page = bounds.results_page(vec![], |item: &u32| *item).unwrap();
+        assert!(page.next_page.is_none());
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright 2023 Oxide Computer Company
+//! Offset pagination
+//!
+//! Some clients (typically internal tools and UIs with numbered pages) want to
+//! fetch a collection by position: skip `offset` items and return the next
+//! `limit`.  This is simpler than the token-based scheme described in the
+//! parent module but has its drawbacks: a client scanning the collection may
+//! miss or repeat items that are inserted or removed during the scan, and many
+//! backends must walk past all `offset` items to find the page.  Endpoints
+//! paginated by offset use the same [`ResultsPage`] envelope as other
+//! paginated endpoints, so both styles can coexist in one API.
+
+use super::ResultsPage;
+use crate::from_map::from_map;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Deserializer;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::num::NonZeroU32;
+
+/// Querystring parameters provided by clients when fetching a page of a
+/// collection by position
+///
+/// To build an API endpoint that paginates results by offset, have your
+/// handler function accept a `Query<OffsetPaginationParams<ScanParams>>` and
+/// return a [`ResultsPage`] built with [`ResultsPage::new_offset`].
+/// `ScanParams` describes the querystring parameters (typically, filters and
+/// sort options) that your endpoint accepts in addition to `offset` and
+/// `limit`.  Unlike the `ScanParams` of [`PaginationParams`], these are sent
+/// with every request, since there's no page token to carry them.  Use
+/// [`EmptyScanParams`] if there aren't any.
+///
+/// The [`ResultsPage`] for a request includes, as its `next_page`, the offset
+/// of the next page, if the page was full.  Use
+/// [`ResultsPage::with_total_count`] to tell clients how many pages there are.
+///
+/// [`PaginationParams`]: super::PaginationParams
+/// [`EmptyScanParams`]: super::EmptyScanParams
+#[derive(Debug, Deserialize)]
+pub struct OffsetPaginationParams<ScanParams>
+where
+    ScanParams: DeserializeOwned,
+{
+    /// Consumer-defined parameters of the request (e.g., filters)
+    #[serde(flatten, deserialize_with = "deserialize_scan_params")]
+    pub scan_params: ScanParams,
+
+    /// Number of items to skip before the first item of the page
+    #[serde(default)]
+    pub offset: u64,
+
+    /// Client-requested limit on page size (optional)
+    ///
+    /// Consumers should use
+    /// [`RequestContext`][crate::RequestContext::offset_page_limit()]
+    /// to access this value.
+    pub(crate) limit: Option<NonZeroU32>,
+}
+
+// The flattened scan parameters arrive as a map of strings, as the page
+// parameters of `PaginationParams` do.  See `deserialize_whichpage`.
+fn deserialize_scan_params<'de, D, ScanParams>(
+    deserializer: D,
+) -> Result<ScanParams, D::Error>
+where
+    D: Deserializer<'de>,
+    ScanParams: DeserializeOwned,
+{
+    let raw_params = BTreeMap::<String, String>::deserialize(deserializer)?;
+    from_map(&raw_params).map_err(serde::de::Error::custom)
+}
+
+impl<ScanParams> JsonSchema for OffsetPaginationParams<ScanParams>
+where
+    ScanParams: DeserializeOwned + JsonSchema,
+{
+    fn schema_name() -> String {
+        "OffsetPaginationParams".to_string()
+    }
+
+    fn json_schema(
+        gen: &mut schemars::gen::SchemaGenerator,
+    ) -> schemars::schema::Schema {
+        SchemaOffsetPaginationParams::<ScanParams>::json_schema(gen)
+    }
+}
+
+// This is the API consumer-visible interface for endpoints paginated by offset.
+// We use this solely to generate the schema.
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct SchemaOffsetPaginationParams<ScanParams> {
+    #[schemars(flatten)]
+    params: ScanParams,
+    /// Number of items to skip before the first item returned
+    offset: Option<u64>,
+    /// Maximum number of items returned by a single call
+    limit: Option<NonZeroU32>,
+}
+
+impl<ItemType> ResultsPage<ItemType> {
+    /// Construct the page of results for a request paginated by offset from
+    /// `items`, the (at most `limit`) items starting at the requested offset.
+    /// If the page is full, its `next_page` is the offset of the next page.
+    pub fn new_offset<ScanParams: DeserializeOwned>(
+        items: Vec<ItemType>,
+        pag_params: &OffsetPaginationParams<ScanParams>,
+        limit: NonZeroU32,
+    ) -> ResultsPage<ItemType> {
+        let nitems = u64::try_from(items.len()).unwrap_or(u64::MAX);
+        let next_page = (nitems >= u64::from(limit.get()))
+            .then(|| pag_params.offset.saturating_add(nitems).to_string());
+        ResultsPage { next_page, items, total_count: None }
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright 2023 Oxide Computer Company
+//! Items that know how to paginate themselves
+//!
+//! An endpoint that lists a collection sorted in one of several ways needs a
+//! `ScanParams` type naming the sorts, a `PageSelector` type recording the
+//! sort and the last-seen key, and a function that builds a page selector from
+//! the last item on a page.  [`PaginatedResource`] gathers these up on the
+//! item type, and `#[derive(PaginatedResource)]` generates all three from the
+//! fields by which the item can be sorted.
+
+use super::PaginationParams;
+use super::ResultsPage;
+use super::WhichPage;
+use crate::error::HttpError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// An item of a paginated collection, along with the types describing how the
+/// collection can be scanned
+///
+/// This is normally derived.  Each `#[paginate(...)]` attribute on the item
+/// type describes one sort: `sort_by` names the field by which items are
+/// sorted, and `then` names a field that breaks ties (and may be repeated).
+/// Together, the fields of each sort must identify an item uniquely.
+///
+/// ```
+/// use dropshot::PaginatedResource;
+/// use schemars::JsonSchema;
+/// use serde::Serialize;
+///
+/// #[derive(Clone, JsonSchema, PaginatedResource, Serialize)]
+/// #[paginate(sort_by = "name")]
+/// #[paginate(sort_by = "mtime", then = "name")]
+/// struct Project {
+///     name: String,
+///     mtime: u64,
+/// }
+/// ```
+///
+/// This generates three types named after the item:
+///
+/// * `ProjectSortBy`, an enum with a variant for each sort (`Name` and
+///   `MtimeName`, appearing in querystrings as "name" and "mtime_name").
+///   The first sort is the default.
+/// * `ProjectScanParams`, the `ScanParams`, with the optional querystring
+///   parameters `sort_by` and `order` (a [`PaginationOrder`], ascending by
+///   default).  It implements [`KeysetScanParams`].
+/// * `ProjectPageSelector`, the `PageSelector`, an enum with a variant for each
+///   sort having fields `order` and `last_seen`, the latter holding the value
+///   of the sort's field (or a tuple of the values of its fields).
+///
+/// An endpoint then accepts
+/// `Query<PaginationParams<ProjectScanParams, ProjectPageSelector>>`, uses
+/// [`scan_params`](Self::scan_params) to find the requested sort and
+/// [`WhichPage`] to find where to resume, and finishes with
+/// [`results_page`](Self::results_page).  The fields' types must implement
+/// `Clone`, `Serialize`, and `Deserialize`, and the crate must depend on
+/// `serde` and `schemars`.
+///
+/// [`PaginationOrder`]: crate::PaginationOrder
+/// [`KeysetScanParams`]: crate::KeysetScanParams
+pub trait PaginatedResource: Sized {
+    /// parameters of the first request of a scan
+    type ScanParams: Clone + DeserializeOwned;
+    /// position of a scan after the first page
+    type PageSelector: DeserializeOwned + Serialize;
+
+    /// Returns the page selector for resuming the scan described by
+    /// `scan_params` after `item`.
+    fn page_selector_for(
+        item: &Self,
+        scan_params: &Self::ScanParams,
+    ) -> Self::PageSelector;
+
+    /// Returns the parameters of the scan being resumed by `page_selector`.
+    fn scan_params_for(page_selector: &Self::PageSelector) -> Self::ScanParams;
+
+    /// Returns the parameters of the scan that `pag_params` starts or
+    /// resumes.
+    fn scan_params(
+        pag_params: &PaginationParams<Self::ScanParams, Self::PageSelector>,
+    ) -> Self::ScanParams {
+        match &pag_params.page {
+            WhichPage::First(scan_params) => scan_params.clone(),
+            WhichPage::Next(page_selector) => {
+                Self::scan_params_for(page_selector)
+            }
+        }
+    }
+
+    /// Constructs the page of results from `items`, which should be the items
+    /// requested by `pag_params`.
+    fn results_page(
+        items: Vec<Self>,
+        pag_params: &PaginationParams<Self::ScanParams, Self::PageSelector>,
+    ) -> Result<ResultsPage<Self>, HttpError> {
+        ResultsPage::new(
+            items,
+            &Self::scan_params(pag_params),
+            Self::page_selector_for,
+        )
+    }
+}
@@ -0,0 +1,270 @@
+// Copyright 2023 Oxide Computer Company
+//! API-wide settings for page tokens: signing, versioning, expiry, size, and
+//! encoding
+//!
+//! Tokens are created and parsed deep within serde and [`ResultsPage::new`],
+//! neither of which has access to the server.  So while Dropshot handles a
+//! request, the API's settings (and the server's clock) are made available to
+//! them in a task-local variable.  Work that a handler hands off to another
+//! task can be run with the same settings using a [`PageTokenScope`].
+//!
+//! [`ResultsPage::new`]: super::ResultsPage::new
+
+use crate::clock::Clock;
+use crate::hmac::hmac_sha256;
+use crate::hmac::hmac_sha256_verify;
+use base64::engine::general_purpose::URL_SAFE;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fmt::Debug;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Secret used to sign and verify page tokens
+///
+/// Page tokens carry the consumer's `PageSelector` in a form that clients can
+/// decode, modify, and send back.  For an API with a page token key (see
+/// [`ApiDescription::page_token_key`]), every token that Dropshot hands out is
+/// followed by a `.` and a signature (an HMAC-SHA256 of the rest of the token),
+/// and a request whose token does not carry a valid signature fails with a 400
+/// ("Bad Request") before the handler is invoked.  Handlers can then trust
+/// that the page selectors they receive are ones they produced.
+///
+/// Signing makes page tokens tamper-proof, not secret: clients can still decode
+/// them.  Tokens are signed only while Dropshot is handling a request, so
+/// results pages must be constructed on the handler's own task, or on a task
+/// that it runs within a [`PageTokenScope`].  A token constructed anywhere else
+/// is unsigned, and is rejected when a client presents it.
+///
+/// [`ApiDescription::page_token_key`]: crate::ApiDescription::page_token_key
+#[derive(Clone)]
+pub struct PageTokenKey {
+    secret: Vec<u8>,
+}
+
+impl PageTokenKey {
+    /// Creates a key from `secret`, which should be randomly generated and
+    /// kept private to the servers that share it.  This fails if `secret` is
+    /// shorter than 32 bytes.
+    pub fn new(secret: impl Into<Vec<u8>>) -> Result<Self, String> {
+        let secret = secret.into();
+        if secret.len() < 32 {
+            return Err(format!(
+                "page token key must be at least 32 bytes, got {}",
+                secret.len()
+            ));
+        }
+        Ok(PageTokenKey { secret })
+    }
+
+    /// Returns `token` followed by its signature.
+    pub(crate) fn sign(&self, token: &str) -> String {
+        let signature = hmac_sha256(&self.secret, &[token.as_bytes()]);
+        format!("{}.{}", token, URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    /// Returns the original token if `signed` carries a valid signature, or
+    /// `None` otherwise.
+    pub(crate) fn verify<'a>(&self, signed: &'a str) -> Option<&'a str> {
+        let (token, signature) = signed.rsplit_once('.')?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        hmac_sha256_verify(&self.secret, &[token.as_bytes()], &signature)
+            .then_some(token)
+    }
+}
+
+impl Debug for PageTokenKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PageTokenKey { .. }")
+    }
+}
+
+/// Text encoding of the serialized contents of page tokens
+///
+/// Either way, tokens use only characters that are safe in URLs (apart from
+/// the `=` padding, which clients may need to escape), and Dropshot accepts
+/// tokens in either encoding, so an API can switch encodings without breaking
+/// scans that are in progress.  See
+/// [`ApiDescription::page_token_encoding`].
+///
+/// [`ApiDescription::page_token_encoding`]:
+/// crate::ApiDescription::page_token_encoding
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PageTokenEncoding {
+    /// URL-safe base64 with `=` padding (RFC 4648 section 5)
+    #[default]
+    Base64Url,
+    /// URL-safe base64 without padding
+    Base64UrlNoPad,
+}
+
+impl PageTokenEncoding {
+    pub(crate) fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            PageTokenEncoding::Base64Url => URL_SAFE.encode(bytes),
+            PageTokenEncoding::Base64UrlNoPad => URL_SAFE_NO_PAD.encode(bytes),
+        }
+    }
+
+    /// Decodes `token`, which may have been encoded with any encoding.
+    pub(crate) fn decode(token: &str) -> Result<Vec<u8>, base64::DecodeError> {
+        URL_SAFE_NO_PAD.decode(token.trim_end_matches('='))
+    }
+}
+
+/// How an API's page tokens are created and checked
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PageTokenSettings {
+    /// key with which tokens are signed
+    pub key: Option<PageTokenKey>,
+    /// version of the API's page selectors, recorded in each token
+    pub version: Option<u32>,
+    /// how long tokens remain valid after they are created
+    pub lifetime: Option<Duration>,
+    /// maximum length of a token, if not the default
+    pub max_length: Option<usize>,
+    /// encoding of the tokens that the API hands out
+    pub encoding: PageTokenEncoding,
+}
+
+/// Page token settings of the request being handled, along with the clock by
+/// which tokens expire
+#[derive(Clone, Debug)]
+pub(crate) struct PageTokens {
+    settings: Arc<PageTokenSettings>,
+    clock: Arc<dyn Clock>,
+}
+
+impl PageTokens {
+    pub(crate) fn key(&self) -> Option<&PageTokenKey> {
+        self.settings.key.as_ref()
+    }
+
+    pub(crate) fn version(&self) -> Option<u32> {
+        self.settings.version
+    }
+
+    pub(crate) fn max_length(&self) -> Option<usize> {
+        self.settings.max_length
+    }
+
+    pub(crate) fn encoding(&self) -> PageTokenEncoding {
+        self.settings.encoding
+    }
+
+    /// Returns the time (in seconds since the Unix epoch) at which a token
+    /// created now expires, if tokens expire.
+    pub(crate) fn expiration(&self) -> Option<i64> {
+        let lifetime = self.settings.lifetime?;
+        let lifetime = i64::try_from(lifetime.as_secs()).unwrap_or(i64::MAX);
+        Some(self.now().saturating_add(lifetime))
+    }
+
+    /// Returns the current time in seconds since the Unix epoch.
+    pub(crate) fn now(&self) -> i64 {
+        self.clock.now_utc().timestamp()
+    }
+}
+
+tokio::task_local! {
+    /// page token settings of the request being handled
+    static PAGE_TOKENS: PageTokens;
+}
+
+/// Page token settings of a request, for work that the request's handler runs
+/// on another task
+///
+/// The settings are available only to the task handling the request, so page
+/// tokens created or parsed on a task that the handler spawns would otherwise
+/// be unsigned, unversioned, and never expire.  Get a scope from
+/// [`RequestContext::page_token_scope`] and run the spawned work within it:
+///
+/// ```ignore
+/// let scope = rqctx.page_token_scope();
+/// let page = tokio::spawn(scope.run(async move {
+///     ResultsPage::new(items, &EmptyScanParams {}, page_selector_for)
+/// }))
+/// .await
+/// .unwrap()?;
+/// ```
+///
+/// [`RequestContext::page_token_scope`]:
+/// crate::RequestContext::page_token_scope
+#[derive(Clone, Debug)]
+pub struct PageTokenScope {
+    page_tokens: PageTokens,
+}
+
+impl PageTokenScope {
+    pub(crate) fn new(
+        settings: &Arc<PageTokenSettings>,
+        clock: &Arc<dyn Clock>,
+    ) -> Self {
+        let page_tokens = PageTokens {
+            settings: Arc::clone(settings),
+            clock: Arc::clone(clock),
+        };
+        PageTokenScope { page_tokens }
+    }
+
+    /// Runs `future` so that the page tokens it creates and parses follow the
+    /// settings of the request from which this scope came.
+    pub async fn run<F: Future>(self, future: F) -> F::Output {
+        PAGE_TOKENS.scope(self.page_tokens, future).await
+    }
+}
+
+/// Runs `future` (the handling of a request) so that the page tokens it
+/// creates and parses follow `settings`.
+pub(crate) async fn with_page_tokens<F: Future>(
+    settings: &Arc<PageTokenSettings>,
+    clock: &Arc<dyn Clock>,
+    future: F,
+) -> F::Output {
+    PageTokenScope::new(settings, clock).run(future).await
+}
+
+/// Returns the page token settings of the request being handled, if any.
+pub(crate) fn current_page_tokens() -> Option<PageTokens> {
+    PAGE_TOKENS.try_with(PageTokens::clone).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::PageTokenEncoding;
+    use super::PageTokenKey;
+
+    #[test]
+    fn test_page_token_key() {
+        let key = PageTokenKey::new([7u8; 32]).unwrap();
+        let signed = key.sign("eyJ2IjoidjEifQ==");
+        assert!(signed.starts_with("eyJ2IjoidjEifQ==."));
+        assert_eq!(key.verify(&signed), Some("eyJ2IjoidjEifQ=="));
+
+        let tampered = signed.replacen("eyJ2", "eyJ3", 1);
+        assert_eq!(key.verify(&tampered), None);
+        assert_eq!(key.verify("eyJ2IjoidjEifQ=="), None);
+        let other_key = PageTokenKey::new([8u8; 32]).unwrap();
+        assert_eq!(other_key.verify(&signed), None);
+
+        assert_eq!(
+            PageTokenKey::new([7u8; 31]).unwrap_err(),
+            "page token key must be at least 32 bytes, got 31"
+        );
+    }
+
+    #[test]
+    fn test_page_token_encoding() {
+        let bytes = b"{\"v\":\"v1\"}";
+        let padded = PageTokenEncoding::Base64Url.encode(bytes);
+        let unpadded = PageTokenEncoding::Base64UrlNoPad.encode(bytes);
+        assert_eq!(padded, "eyJ2IjoidjEifQ==");
+        assert_eq!(unpadded, "eyJ2IjoidjEifQ");
+        assert_eq!(PageTokenEncoding::decode(&padded).unwrap(), bytes);
+        assert_eq!(PageTokenEncoding::decode(&unpadded).unwrap(), bytes);
+        assert!(PageTokenEncoding::decode("eyJ2I+oidjEifQ").is_err());
+    }
+}
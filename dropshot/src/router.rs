@@ -4,16 +4,22 @@
 use super::error::HttpError;
 use super::handler::RouteHandler;
 
+use crate::api_description::EndpointMetadata;
 use crate::from_map::MapError;
 use crate::from_map::MapValue;
 use crate::server::ServerContext;
 use crate::ApiEndpoint;
 use crate::ApiEndpointBodyContentType;
+use crate::ParameterCase;
 use http::Method;
 use http::StatusCode;
 use percent_encoding::percent_decode_str;
+use percent_encoding::utf8_percent_encode;
+use percent_encoding::AsciiSet;
+use percent_encoding::CONTROLS;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
+use std::sync::Arc;
 
 /// `HttpRouter` is a simple data structure for routing incoming HTTP requests to
 /// specific handler functions based on the request method and URI path.  For
@@ -81,6 +87,9 @@ pub struct HttpRouter<Context: ServerContext> {
 struct HttpRouterNode<Context: ServerContext> {
     /// Handlers, etc. for each of the HTTP methods defined for this node.
     methods: BTreeMap<String, ApiEndpoint<Context>>,
+    /// Metadata for the endpoint of each HTTP method, shared with the requests
+    /// that it handles
+    metadata: BTreeMap<String, Arc<EndpointMetadata>>,
     /// Edges linking to child nodes.
     edges: Option<HttpRouterEdges<Context>>,
 }
@@ -191,12 +200,14 @@ impl MapValue for VariableValue {
         }
     }
 
-    fn as_seq(&self) -> Result<Box<dyn Iterator<Item = String>>, MapError> {
+    fn as_seq(&self) -> Result<Box<dyn Iterator<Item = Self>>, MapError> {
         match self {
             VariableValue::String(_) => Err(MapError(
                 "cannot deserialize a single value as a sequence".to_string(),
             )),
-            VariableValue::Components(v) => Ok(Box::new(v.clone().into_iter())),
+            VariableValue::Components(v) => {
+                Ok(Box::new(v.clone().into_iter().map(VariableValue::String)))
+            }
         }
     }
 }
@@ -204,18 +215,25 @@ impl MapValue for VariableValue {
 /// `RouterLookupResult` represents the result of invoking
 /// `HttpRouter::lookup_route()`.  A successful route lookup includes
 /// the handler, a mapping of variables in the configured path to the
-/// corresponding values in the actual path, and the expected body
-/// content type.
+/// corresponding values in the actual path, the expected body content type,
+/// the naming convention used for parameters, and the endpoint's operation id.
 #[derive(Debug)]
 pub struct RouterLookupResult<'a, Context: ServerContext> {
     pub handler: &'a dyn RouteHandler<Context>,
+    pub operation_id: &'a str,
+    pub endpoint: Arc<EndpointMetadata>,
     pub variables: VariableSet,
     pub body_content_type: ApiEndpointBodyContentType,
+    pub parameter_case: ParameterCase,
 }
 
 impl<Context: ServerContext> HttpRouterNode<Context> {
     pub fn new() -> Self {
-        HttpRouterNode { methods: BTreeMap::new(), edges: None }
+        HttpRouterNode {
+            methods: BTreeMap::new(),
+            metadata: BTreeMap::new(),
+            edges: None,
+        }
     }
 }
 
@@ -392,9 +410,88 @@ impl<Context: ServerContext> HttpRouter<Context> {
             );
         }
 
+        node.metadata.insert(methodname.clone(), Arc::new(endpoint.metadata()));
         node.methods.insert(methodname, endpoint);
     }
 
+    /// Describe the conflict, if any, that would prevent an endpoint for
+    /// `method` at `path` from being inserted alongside the routes already
+    /// configured.  This detects the same conflicts for which
+    /// `HttpRouter::insert()` panics, but leaves the router unchanged.
+    pub fn conflict(&self, method: &Method, path: &str) -> Option<String> {
+        let mut node: &Box<HttpRouterNode<Context>> = &self.root;
+        for raw_segment in route_path_to_segments(path) {
+            node = match (&node.edges, PathSegment::from(raw_segment)) {
+                // Nothing has been registered beneath this node, so nothing
+                // can conflict.
+                (None, _) => return None,
+                (
+                    Some(HttpRouterEdges::Literals(literals)),
+                    PathSegment::Literal(lit),
+                ) => match literals.get(&lit) {
+                    Some(node) => node,
+                    None => return None,
+                },
+                (
+                    Some(HttpRouterEdges::VariableSingle(varname, node)),
+                    PathSegment::VarnameSegment(new_varname),
+                )
+                | (
+                    Some(HttpRouterEdges::VariableRest(varname, node)),
+                    PathSegment::VarnameWildcard(new_varname),
+                ) => {
+                    if new_varname != *varname {
+                        return Some(format!(
+                            "URI path \"{}\": attempted to use variable name \
+                             \"{}\", but a different name (\"{}\") has \
+                             already been used for this",
+                            path, new_varname, varname
+                        ));
+                    }
+                    node
+                }
+                (Some(_), _) => {
+                    return Some(format!(
+                        "URI path \"{}\": path segment \"{}\" conflicts \
+                         with the kind of path segment (literal, variable, \
+                         or wildcard) already registered in its place",
+                        path, raw_segment
+                    ))
+                }
+            };
+        }
+
+        let methodname = method.as_str().to_uppercase();
+        node.methods.contains_key(&methodname).then(|| {
+            format!(
+                "URI path \"{}\": attempted to create duplicate route for \
+                 method \"{}\"",
+                path, method,
+            )
+        })
+    }
+
+    /// Consume the router, returning the endpoints registered with it.
+    pub fn into_endpoints(self) -> Vec<ApiEndpoint<Context>> {
+        let mut endpoints = Vec::new();
+        let mut nodes = vec![self.root];
+        while let Some(node) = nodes.pop() {
+            let HttpRouterNode { methods, edges, .. } = *node;
+            endpoints.extend(methods.into_values());
+            match edges {
+                Some(HttpRouterEdges::Literals(literals)) => {
+                    nodes.extend(literals.into_values())
+                }
+                Some(HttpRouterEdges::VariableSingle(_, node))
+                | Some(HttpRouterEdges::VariableRest(_, node)) => {
+                    nodes.push(node)
+                }
+                None => (),
+            }
+        }
+        endpoints
+    }
+
     /// Look up the route handler for an HTTP request having method `method` and
     /// URI path `path`.  A successful lookup produces a `RouterLookupResult`,
     /// which includes both the handler that can process this request and a map
@@ -481,15 +578,147 @@ impl<Context: ServerContext> HttpRouter<Context> {
             .get(&methodname)
             .map(|handler| RouterLookupResult {
                 handler: &*handler.handler,
+                operation_id: &handler.operation_id,
+                endpoint: Arc::clone(&node.metadata[&methodname]),
                 variables,
                 body_content_type: handler.body_content_type.clone(),
+                parameter_case: handler.parameter_case.unwrap_or_default(),
             })
             .ok_or_else(|| {
                 HttpError::for_status(None, StatusCode::METHOD_NOT_ALLOWED)
             })
     }
+
+    /// Returns the path of the resource that the endpoint with operation ID
+    /// `operation_id` serves when its path variables take the values in
+    /// `variables` -- the inverse of `lookup_route()`.  `variables` is keyed
+    /// by the `snake_case` names of the variables, which are translated into
+    /// the endpoint's [`ParameterCase`].  Each value is percent-encoded,
+    /// except that the value of a wildcard variable may be several
+    /// "/"-separated segments.  This fails if there's no such endpoint or more
+    /// than one, if `variables` doesn't have exactly the endpoint's path
+    /// variables, or if some value wouldn't be routed back to the endpoint
+    /// (e.g., because it's empty).
+    pub fn path_for(
+        &self,
+        operation_id: &str,
+        variables: &BTreeMap<String, String>,
+    ) -> Result<String, String> {
+        // Operation IDs are unique only among visible endpoints.
+        let mut endpoints = self
+            .into_iter()
+            .map(|(_, _, endpoint)| endpoint)
+            .filter(|endpoint| endpoint.operation_id == operation_id);
+        let endpoint = endpoints
+            .next()
+            .ok_or_else(|| format!("no endpoint \"{}\"", operation_id))?;
+        if endpoints.next().is_some() {
+            return Err(format!(
+                "more than one endpoint has operation ID \"{}\"",
+                operation_id
+            ));
+        }
+
+        let case = endpoint.parameter_case.unwrap_or_default();
+        let variables = variables
+            .iter()
+            .map(|(varname, value)| (case.to_wire(varname), value.clone()))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut path = String::new();
+        let mut used = 0;
+        for raw_segment in route_path_to_segments(&endpoint.path) {
+            let (varname, value) = match PathSegment::from(raw_segment) {
+                PathSegment::Literal(lit) => {
+                    path.push('/');
+                    path.push_str(&lit);
+                    continue;
+                }
+                PathSegment::VarnameSegment(varname) => {
+                    let value = variables.get(&varname);
+                    if value.map_or(false, |v| v.contains('/')) {
+                        return Err(format!(
+                            "value of path variable \"{}\" contains \"/\"",
+                            varname
+                        ));
+                    }
+                    (varname, value)
+                }
+                PathSegment::VarnameWildcard(varname) => {
+                    let value = variables.get(&varname);
+                    (varname, value)
+                }
+            };
+            let value = value.ok_or_else(|| {
+                format!("missing value for path variable \"{}\"", varname)
+            })?;
+            used += 1;
+            for segment in value.split('/').filter(|s| !s.is_empty()) {
+                path.push('/');
+                path.extend(utf8_percent_encode(segment, PATH_SEGMENT));
+            }
+        }
+        if used != variables.len() {
+            return Err(format!(
+                "endpoint \"{}\" ({}) does not have all of the path \
+                 variables {:?}",
+                operation_id,
+                endpoint.path,
+                variables.keys().collect::<Vec<_>>()
+            ));
+        }
+        if path.is_empty() {
+            path.push('/');
+        }
+
+        // Make sure that the path really does lead back to this endpoint with
+        // these values.
+        let routed = self
+            .lookup_route(&endpoint.method, InputPath(&path))
+            .ok()
+            .filter(|result| {
+                result.operation_id == operation_id
+                    && variables.iter().all(|(varname, value)| {
+                        match result.variables.get(varname) {
+                            Some(VariableValue::String(s)) => s == value,
+                            Some(VariableValue::Components(c)) => {
+                                c.join("/") == value.trim_matches('/')
+                            }
+                            None => false,
+                        }
+                    })
+            });
+        if routed.is_none() {
+            return Err(format!(
+                "path \"{}\" would not be routed to endpoint \"{}\"",
+                path, operation_id
+            ));
+        }
+        Ok(path)
+    }
 }
 
+/// Characters that are percent-encoded in a path segment built by
+/// `HttpRouter::path_for()`: those that are not allowed in a path segment
+/// (RFC 3986 S3.3), plus "%" itself
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
 /// Insert a variable into the set after checking for duplicates.
 fn insert_var(
     path: &str,
@@ -732,12 +961,14 @@ mod test {
     use crate::router::VariableValue;
     use crate::ApiEndpoint;
     use crate::ApiEndpointResponse;
+    use crate::ParameterCase;
     use http::Method;
     use http::StatusCode;
     use hyper::Body;
     use hyper::Response;
     use serde::Deserialize;
     use std::collections::BTreeMap;
+    use std::sync::Arc;
 
     async fn test_handler(
         _: RequestContext<()>,
@@ -745,16 +976,16 @@ mod test {
         panic!("test handler is not supposed to run");
     }
 
-    fn new_handler() -> Box<dyn RouteHandler<()>> {
+    fn new_handler() -> Arc<dyn RouteHandler<()>> {
         HttpRouteHandler::new(test_handler)
     }
 
-    fn new_handler_named(name: &str) -> Box<dyn RouteHandler<()>> {
+    fn new_handler_named(name: &str) -> Arc<dyn RouteHandler<()>> {
         HttpRouteHandler::new_with_name(test_handler, name)
     }
 
     fn new_endpoint(
-        handler: Box<dyn RouteHandler<()>>,
+        handler: Arc<dyn RouteHandler<()>>,
         method: Method,
         path: &str,
     ) -> ApiEndpoint<()> {
@@ -764,14 +995,33 @@ mod test {
             method,
             path: path.to_string(),
             parameters: vec![],
+            security: vec![],
             body_content_type: ApiEndpointBodyContentType::default(),
+            encodings: vec![],
+            unsupported_encodings: vec![],
             response: ApiEndpointResponse::default(),
+            response_content_type: None,
+            errors: vec![],
             summary: None,
             description: None,
             tags: vec![],
             extension_mode: Default::default(),
             visible: true,
             deprecated: false,
+            deprecated_since: None,
+            sunset: None,
+            parameter_case: None,
+            strict_query: None,
+            aliases: vec![],
+            max_concurrency: None,
+            request_body_max_bytes: None,
+            request_timeout: None,
+            security_requirements: BTreeMap::new(),
+            request_examples: BTreeMap::new(),
+            parameter_examples: BTreeMap::new(),
+            response_examples: BTreeMap::new(),
+            extensions: BTreeMap::new(),
+            attributes: BTreeMap::new(),
         }
     }
 
@@ -828,6 +1078,39 @@ mod test {
         router.insert(new_endpoint(new_handler(), Method::GET, "/foo/bar/"));
     }
 
+    #[test]
+    fn test_conflict() {
+        let mut router = HttpRouter::new();
+        router.insert(new_endpoint(new_handler(), Method::GET, "/boo"));
+        router.insert(new_endpoint(new_handler(), Method::GET, "/foo/{id}"));
+
+        assert_eq!(router.conflict(&Method::PUT, "/boo"), None);
+        assert_eq!(router.conflict(&Method::GET, "/boo/bar"), None);
+        assert_eq!(router.conflict(&Method::GET, "/foo/{id}/bar"), None);
+        assert_eq!(
+            router.conflict(&Method::GET, "/boo/").unwrap(),
+            "URI path \"/boo/\": attempted to create duplicate route for \
+             method \"GET\""
+        );
+        assert_eq!(
+            router.conflict(&Method::GET, "/foo/{name}").unwrap(),
+            "URI path \"/foo/{name}\": attempted to use variable name \
+             \"name\", but a different name (\"id\") has already been used \
+             for this"
+        );
+        assert!(router.conflict(&Method::GET, "/foo/bar").is_some());
+        assert!(router.conflict(&Method::GET, "/{rest:.*}").is_some());
+
+        // Checking for conflicts doesn't register anything.
+        let mut paths = router
+            .into_endpoints()
+            .into_iter()
+            .map(|endpoint| endpoint.path)
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(paths, vec!["/boo", "/foo/{id}"]);
+    }
+
     #[test]
     #[should_panic(expected = "path segments may not be empty: '//'")]
     fn test_duplicate_route3() {
@@ -1380,6 +1663,85 @@ mod test {
         let _ = PathSegment::from("{varname:abc+}");
     }
 
+    #[test]
+    fn test_path_for() {
+        let mut router = HttpRouter::new();
+        let mut endpoint = new_endpoint(
+            new_handler(),
+            Method::GET,
+            "/projects/{project}/files/{path:.*}",
+        );
+        endpoint.operation_id = String::from("file_get");
+        router.insert(endpoint);
+        router.insert(new_endpoint(new_handler(), Method::GET, "/"));
+        let vars = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<BTreeMap<_, _>>()
+        };
+
+        assert_eq!(
+            router
+                .path_for(
+                    "file_get",
+                    &vars(&[("project", "my project"), ("path", "a/b.txt")])
+                )
+                .unwrap(),
+            "/projects/my%20project/files/a/b.txt"
+        );
+        assert_eq!(router.path_for("test_handler", &vars(&[])).unwrap(), "/");
+
+        assert!(router.path_for("file_get_v2", &vars(&[])).is_err());
+
+        // Variables are named as in Rust, whatever the endpoint's naming
+        // convention on the wire.
+        let mut endpoint = new_endpoint(
+            new_handler(),
+            Method::GET,
+            "/orgs/{orgName}/projects/{projectName}",
+        );
+        endpoint.operation_id = String::from("project_get");
+        endpoint.parameter_case = Some(ParameterCase::Camel);
+        router.insert(endpoint);
+        assert_eq!(
+            router
+                .path_for(
+                    "project_get",
+                    &vars(&[("org_name", "o"), ("project_name", "p")])
+                )
+                .unwrap(),
+            "/orgs/o/projects/p"
+        );
+        assert!(router
+            .path_for(
+                "project_get",
+                &vars(&[("orgName", "o"), ("projectName", "p")])
+            )
+            .is_err());
+
+        // An operation ID shared by invisible endpoints is ambiguous.
+        for path in ["/static/a/{path:.*}", "/static/b/{path:.*}"] {
+            let mut endpoint = new_endpoint(new_handler(), Method::GET, path);
+            endpoint.operation_id = String::from("static_get");
+            endpoint.visible = false;
+            router.insert(endpoint);
+        }
+        assert_eq!(
+            router.path_for("static_get", &vars(&[("path", "x")])).unwrap_err(),
+            "more than one endpoint has operation ID \"static_get\""
+        );
+        for bad in [
+            vars(&[("project", "p")]),
+            vars(&[("project", "p"), ("path", ""), ("extra", "x")]),
+            vars(&[("project", "a/b"), ("path", "")]),
+            vars(&[("project", ".."), ("path", "")]),
+            vars(&[("project", ""), ("path", "x")]),
+        ] {
+            assert!(router.path_for("file_get", &bad).is_err());
+        }
+    }
+
     #[test]
     fn test_map() {
         #[derive(Deserialize)]
@@ -2,14 +2,33 @@
 //! Generic server-wide state and facilities
 
 use super::api_description::ApiDescription;
-use super::config::{ConfigDropshot, ConfigTls};
+use super::api_description::ServerParts;
+#[cfg(unix)]
+use super::config::ConfigUnixSocket;
+use super::config::{
+    ConfigDropshot, ConfigHttp2, ConfigTls, ConfigTlsClientAuth,
+};
 #[cfg(feature = "usdt-probes")]
 use super::dtrace::probes;
 use super::error::HttpError;
 use super::handler::RequestContext;
+use super::handler::RequestLogHook;
+use super::http_util::http_date;
+use super::http_util::http_response_framing;
+use super::http_util::RequestBodyBytes;
+use super::http_util::HEADER_DEPRECATION;
 use super::http_util::HEADER_REQUEST_ID;
+use super::http_util::HEADER_SUNSET;
+use super::load_shedding::LoadShedder;
 use super::router::HttpRouter;
 use super::ProbeRegistration;
+use crate::clock::Clock;
+use crate::extractor::ClientCert;
+use crate::extractor::CookieKey;
+use crate::in_flight::InFlightRequest;
+use crate::in_flight::InFlightRequests;
+use crate::pagination::with_page_tokens;
+use crate::pagination::PageTokenSettings;
 
 use async_stream::stream;
 use futures::future::{
@@ -17,6 +36,7 @@ use futures::future::{
 };
 use futures::lock::Mutex;
 use futures::stream::{Stream, StreamExt};
+use hyper::body::HttpBody;
 use hyper::server::{
     conn::{AddrIncoming, AddrStream},
     Server,
@@ -25,14 +45,17 @@ use hyper::service::Service;
 use hyper::Body;
 use hyper::Request;
 use hyper::Response;
+use ipnet::IpNet;
 use rustls;
 use std::convert::TryFrom;
 use std::future::Future;
 use std::net::SocketAddr;
 use std::num::NonZeroU32;
+use std::path::Path;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 use tokio::io::ReadBuf;
 use tokio::net::{TcpListener, TcpStream};
 use tokio_rustls::{server::TlsStream, TlsAcceptor};
@@ -66,12 +89,42 @@ pub struct DropshotState<C: ServerContext> {
     pub local_addr: SocketAddr,
     /// Identifies how to accept TLS connections
     pub(crate) tls_acceptor: Option<Arc<Mutex<TlsAcceptor>>>,
+    /// How to verify client certificates, kept for refreshing the acceptor
+    pub(crate) tls_client_auth: Option<ConfigTlsClientAuth>,
+    /// whether HTTP/2 is offered when negotiating TLS
+    pub(crate) http2: bool,
+    /// Decides which requests to reject when endpoints are overloaded
+    pub(crate) load_shedder: Option<LoadShedder>,
+    /// Produces the logger for each request, if the API provided one
+    pub(crate) request_log_hook: Option<RequestLogHook<C>>,
+    /// source of time for the server and its handlers
+    pub(crate) clock: Arc<dyn Clock>,
+    /// key for signing and verifying cookies, if the API provided one
+    pub(crate) cookie_key: Option<CookieKey>,
+    /// how page tokens are created and checked
+    pub(crate) page_tokens: Arc<PageTokenSettings>,
+    /// requests currently being handled
+    pub(crate) in_flight: Arc<InFlightRequests>,
+    /// tasks serving the server's connections
+    pub(crate) connections: ConnectionTasks,
 }
 
 impl<C: ServerContext> DropshotState<C> {
     pub fn using_tls(&self) -> bool {
         self.tls_acceptor.is_some()
     }
+
+    /// Returns the requests currently being handled, oldest first.  A request
+    /// remains in flight until its handler has produced a response.
+    pub fn in_flight_requests(&self) -> Vec<InFlightRequest> {
+        self.in_flight.list(self.clock.as_ref())
+    }
+
+    /// Returns the key for signing and verifying cookies, if the API provided
+    /// one (see [`ApiDescription::cookie_key`]).
+    pub fn cookie_key(&self) -> Option<&CookieKey> {
+        self.cookie_key.as_ref()
+    }
 }
 
 /// Stores static configuration associated with the server
@@ -80,17 +133,24 @@ impl<C: ServerContext> DropshotState<C> {
 pub struct ServerConfig {
     /// maximum allowed size of a request body
     pub request_body_max_bytes: usize,
+    /// time after which requests are abandoned, if any
+    pub request_timeout: Option<Duration>,
     /// maximum size of any page of results
     pub page_max_nitems: NonZeroU32,
     /// default size for a page of results
     pub page_default_nitems: NonZeroU32,
+    /// base URL at which clients reach this server, if configured
+    pub external_url: Option<String>,
+    /// networks whose proxies are trusted to report the client's address and
+    /// the URL it used
+    pub trusted_proxies: Vec<IpNet>,
 }
 
 /// A thin wrapper around a Hyper Server object that exposes some interfaces that
 /// we find useful.
 pub struct HttpServerStarter<C: ServerContext> {
     app_state: Arc<DropshotState<C>>,
-    local_addr: SocketAddr,
+    local_addrs: Vec<SocketAddr>,
     wrapped: WrappedHttpServerStarter<C>,
 }
 
@@ -104,13 +164,32 @@ impl<C: ServerContext> HttpServerStarter<C> {
         let server_config = ServerConfig {
             // We start aggressively to ensure test coverage.
             request_body_max_bytes: config.request_body_max_bytes,
+            request_timeout: config
+                .request_timeout_ms
+                .map(Duration::from_millis),
             page_max_nitems: NonZeroU32::new(10000).unwrap(),
             page_default_nitems: NonZeroU32::new(100).unwrap(),
+            external_url: config.external_url.clone(),
+            trusted_proxies: config.trusted_proxies.clone(),
         };
 
+        if config.tls.is_none() && config.tls_client_auth.is_some() {
+            return Err("tls_client_auth requires tls to be configured".into());
+        }
+
+        if config.unix_socket.is_some() {
+            return HttpServerStarter::new_unix(
+                config,
+                server_config,
+                api,
+                private,
+                log,
+            );
+        }
+
         let starter = match config.tls {
             Some(_) => {
-                let (starter, app_state, local_addr) =
+                let (starter, app_state, local_addrs) =
                     InnerHttpsServerStarter::new(
                         config,
                         server_config,
@@ -120,12 +199,12 @@ impl<C: ServerContext> HttpServerStarter<C> {
                     )?;
                 HttpServerStarter {
                     app_state,
-                    local_addr,
+                    local_addrs,
                     wrapped: WrappedHttpServerStarter::Https(starter),
                 }
             }
             None => {
-                let (starter, app_state, local_addr) =
+                let (starter, app_state, local_addrs) =
                     InnerHttpServerStarter::new(
                         config,
                         server_config,
@@ -135,36 +214,89 @@ impl<C: ServerContext> HttpServerStarter<C> {
                     )?;
                 HttpServerStarter {
                     app_state,
-                    local_addr,
+                    local_addrs,
                     wrapped: WrappedHttpServerStarter::Http(starter),
                 }
             }
         };
 
-        for (path, method, _) in &starter.app_state.router {
-            debug!(starter.app_state.log, "registered endpoint";
+        starter.log_endpoints();
+        Ok(starter)
+    }
+
+    #[cfg(unix)]
+    fn new_unix(
+        config: &ConfigDropshot,
+        server_config: ServerConfig,
+        api: ApiDescription<C>,
+        private: C,
+        log: &Logger,
+    ) -> Result<HttpServerStarter<C>, GenericError> {
+        if config.tls.is_some() {
+            return Err("tls is not supported with unix_socket".into());
+        }
+        if !config.additional_bind_addresses.is_empty() {
+            return Err("additional_bind_addresses is not supported with \
+                unix_socket"
+                .into());
+        }
+
+        let (starter, app_state, local_addr) = InnerUnixServerStarter::new(
+            config,
+            server_config,
+            api,
+            private,
+            log,
+        )?;
+        let starter = HttpServerStarter {
+            app_state,
+            local_addrs: vec![local_addr],
+            wrapped: WrappedHttpServerStarter::Unix(starter),
+        };
+        starter.log_endpoints();
+        Ok(starter)
+    }
+
+    #[cfg(not(unix))]
+    fn new_unix(
+        _config: &ConfigDropshot,
+        _server_config: ServerConfig,
+        _api: ApiDescription<C>,
+        _private: C,
+        _log: &Logger,
+    ) -> Result<HttpServerStarter<C>, GenericError> {
+        Err("Unix domain sockets are not supported on this platform".into())
+    }
+
+    fn log_endpoints(&self) {
+        for (path, method, _) in &self.app_state.router {
+            debug!(self.app_state.log, "registered endpoint";
                 "method" => &method,
                 "path" => &path
             );
         }
-
-        Ok(starter)
     }
 
     pub fn start(self) -> HttpServer<C> {
         let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let (ready_tx, ready_rx) = tokio::sync::watch::channel(false);
         let log_close = self.app_state.log.new(o!());
         let join_handle = match self.wrapped {
-            WrappedHttpServerStarter::Http(http) => http.start(rx, log_close),
+            WrappedHttpServerStarter::Http(http) => {
+                http.start(rx, ready_tx, log_close)
+            }
             WrappedHttpServerStarter::Https(https) => {
-                https.start(rx, log_close)
+                https.start(rx, ready_tx, log_close)
+            }
+            #[cfg(unix)]
+            WrappedHttpServerStarter::Unix(unix) => {
+                unix.start(rx, ready_tx, log_close)
             }
         }
         .map(|r| {
             r.map_err(|e| format!("waiting for server: {e}"))?
                 .map_err(|e| format!("server stopped: {e}"))
         });
-        info!(self.app_state.log, "listening");
 
         #[cfg(feature = "usdt-probes")]
         let probe_registration = match usdt::register_probes() {
@@ -196,9 +328,12 @@ impl<C: ServerContext> HttpServerStarter<C> {
         HttpServer {
             probe_registration,
             app_state: self.app_state,
-            local_addr: self.local_addr,
-            closer: CloseHandle { close_channel: Some(tx) },
+            local_addrs: self.local_addrs,
+            closer: CloseHandle {
+                close_channel: Arc::new(std::sync::Mutex::new(Some(tx))),
+            },
             join_future: join_handle.boxed().shared(),
+            ready: ready_rx,
         }
     }
 }
@@ -206,30 +341,47 @@ impl<C: ServerContext> HttpServerStarter<C> {
 enum WrappedHttpServerStarter<C: ServerContext> {
     Http(InnerHttpServerStarter<C>),
     Https(InnerHttpsServerStarter<C>),
+    #[cfg(unix)]
+    Unix(InnerUnixServerStarter<C>),
 }
 
+/// The Hyper server for each address on which a Dropshot server listens,
+/// along with the logger for that address
 struct InnerHttpServerStarter<C: ServerContext>(
-    Server<AddrIncoming, ServerConnectionHandler<C>>,
+    Vec<(
+        Server<AddrIncoming, ServerConnectionHandler<C>, ConnectionTasks>,
+        Logger,
+    )>,
 );
 
 type InnerHttpServerStarterNewReturn<C> =
-    (InnerHttpServerStarter<C>, Arc<DropshotState<C>>, SocketAddr);
+    (InnerHttpServerStarter<C>, Arc<DropshotState<C>>, Vec<SocketAddr>);
 
 impl<C: ServerContext> InnerHttpServerStarter<C> {
-    /// Begins execution of the underlying Http server.
+    /// Begins execution of the underlying Http servers, which shut down
+    /// together.
     fn start(
         self,
         close_signal: tokio::sync::oneshot::Receiver<()>,
+        ready: tokio::sync::watch::Sender<bool>,
         log_close: Logger,
     ) -> tokio::task::JoinHandle<Result<(), hyper::Error>> {
-        let graceful = self.0.with_graceful_shutdown(async move {
-            close_signal.await.expect(
-                "dropshot server shutting down without invoking close()",
-            );
-            info!(log_close, "received request to begin graceful shutdown");
-        });
-
-        tokio::spawn(async { graceful.await })
+        let close = close_future(close_signal, log_close);
+        let graceful = self
+            .0
+            .into_iter()
+            .map(|(server, log)| {
+                info!(log, "listening");
+                server.with_graceful_shutdown(close.clone())
+            })
+            .collect::<Vec<_>>();
+
+        tokio::spawn(async move {
+            // The listening sockets were bound when the server was created, so
+            // the server is accepting connections as soon as it's running.
+            let _ = ready.send(true);
+            futures::future::try_join_all(graceful).await.map(|_| ())
+        })
     }
 
     /// Set up an HTTP server bound on the specified address that runs registered
@@ -245,41 +397,313 @@ impl<C: ServerContext> InnerHttpServerStarter<C> {
         private: C,
         log: &Logger,
     ) -> Result<InnerHttpServerStarterNewReturn<C>, hyper::Error> {
-        let incoming = AddrIncoming::bind(&config.bind_address)?;
-        let local_addr = incoming.local_addr();
+        let incomings = bind_addresses(config)
+            .map(AddrIncoming::bind)
+            .collect::<Result<Vec<_>, _>>()?;
+        let local_addrs =
+            incomings.iter().map(AddrIncoming::local_addr).collect::<Vec<_>>();
+        let local_addr = local_addrs[0];
 
         // TODO-cleanup too many Arcs?
+        let ServerParts {
+            router,
+            request_log_hook,
+            clock,
+            cookie_key,
+            page_tokens,
+        } = api.into_server_parts();
+        let app_state = Arc::new(DropshotState {
+            private,
+            config: server_config,
+            router,
+            log: log.new(o!("local_addr" => local_addr, "scheme" => "http")),
+            local_addr,
+            tls_acceptor: None,
+            tls_client_auth: None,
+            http2: config.http2.enabled,
+            load_shedder: config.load_shedding.as_ref().map(LoadShedder::new),
+            request_log_hook,
+            clock,
+            cookie_key,
+            page_tokens: Arc::new(page_tokens),
+            in_flight: Default::default(),
+            connections: Default::default(),
+        });
+
+        let servers = incomings
+            .into_iter()
+            .map(|incoming| {
+                let local_addr = incoming.local_addr();
+                let log =
+                    log.new(o!("local_addr" => local_addr, "scheme" => "http"));
+                let make_service = ServerConnectionHandler::new(
+                    app_state.clone(),
+                    log.clone(),
+                );
+                let builder = hyper::Server::builder(incoming)
+                    .executor(app_state.connections.clone());
+                let server =
+                    configure_http2(builder, &config.http2).serve(make_service);
+                (server, log)
+            })
+            .collect();
+        Ok((InnerHttpServerStarter(servers), app_state, local_addrs))
+    }
+}
+
+/// The address reported for both ends of connections on a Unix domain socket,
+/// which have no IP address
+#[cfg(unix)]
+const UNIX_SOCKET_ADDR: SocketAddr =
+    SocketAddr::new(std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED), 0);
+
+#[cfg(unix)]
+struct InnerUnixServerStarter<C: ServerContext> {
+    server: Server<UnixAcceptor, ServerConnectionHandler<C>, ConnectionTasks>,
+    path: std::path::PathBuf,
+}
+
+#[cfg(unix)]
+type InnerUnixServerStarterNewReturn<C> =
+    (InnerUnixServerStarter<C>, Arc<DropshotState<C>>, SocketAddr);
+
+#[cfg(unix)]
+impl<C: ServerContext> InnerUnixServerStarter<C> {
+    /// Begins execution of the underlying Http server, removing the socket
+    /// file once it has shut down.
+    fn start(
+        self,
+        close_signal: tokio::sync::oneshot::Receiver<()>,
+        ready: tokio::sync::watch::Sender<bool>,
+        log_close: Logger,
+    ) -> tokio::task::JoinHandle<Result<(), hyper::Error>> {
+        let path = self.path;
+        let log_remove = log_close.clone();
+        info!(log_close, "listening");
+        let graceful = self
+            .server
+            .with_graceful_shutdown(close_future(close_signal, log_close));
+
+        tokio::spawn(async move {
+            let _ = ready.send(true);
+            let result = graceful.await;
+            if let Err(error) = std::fs::remove_file(&path) {
+                warn!(log_remove, "failed to remove socket file";
+                    "path" => %path.display(),
+                    "error" => %error
+                );
+            }
+            result
+        })
+    }
+
+    fn new(
+        config: &ConfigDropshot,
+        server_config: ServerConfig,
+        api: ApiDescription<C>,
+        private: C,
+        log: &Logger,
+    ) -> Result<InnerUnixServerStarterNewReturn<C>, GenericError> {
+        // The caller checked that a Unix socket is configured.
+        let unix_socket = config.unix_socket.as_ref().unwrap();
+        let listener = bind_unix_socket(unix_socket)?;
+
+        let local_addr = UNIX_SOCKET_ADDR;
+        let ServerParts {
+            router,
+            request_log_hook,
+            clock,
+            cookie_key,
+            page_tokens,
+        } = api.into_server_parts();
         let app_state = Arc::new(DropshotState {
             private,
             config: server_config,
-            router: api.into_router(),
-            log: log.new(o!("local_addr" => local_addr)),
+            router,
+            log: log.new(o!(
+                "unix_socket" => unix_socket.path.display().to_string(),
+                "scheme" => "http"
+            )),
             local_addr,
             tls_acceptor: None,
+            tls_client_auth: None,
+            http2: config.http2.enabled,
+            load_shedder: config.load_shedding.as_ref().map(LoadShedder::new),
+            request_log_hook,
+            clock,
+            cookie_key,
+            page_tokens: Arc::new(page_tokens),
+            in_flight: Default::default(),
+            connections: Default::default(),
         });
 
-        let make_service = ServerConnectionHandler::new(app_state.clone());
-        let builder = hyper::Server::builder(incoming);
-        let server = builder.serve(make_service);
-        Ok((InnerHttpServerStarter(server), app_state, local_addr))
+        let make_service = ServerConnectionHandler::new(
+            Arc::clone(&app_state),
+            app_state.log.clone(),
+        );
+        let builder = Server::builder(UnixAcceptor(listener))
+            .executor(app_state.connections.clone());
+        let server =
+            configure_http2(builder, &config.http2).serve(make_service);
+        let starter =
+            InnerUnixServerStarter { server, path: unix_socket.path.clone() };
+        Ok((starter, app_state, local_addr))
+    }
+}
+
+/// Creates the socket file described by `config` and listens on it.
+#[cfg(unix)]
+fn bind_unix_socket(
+    config: &ConfigUnixSocket,
+) -> std::io::Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let path = &config.path;
+    if config.remove_existing {
+        match std::fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => {
+                std::fs::remove_file(path)?
+            }
+            Ok(_) => {
+                return Err(io_error(format!(
+                    "{} exists and is not a socket",
+                    path.display()
+                )))
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => (),
+            Err(error) => return Err(error),
+        }
+    }
+
+    let listener = match config.mode {
+        None => bind_unix_listener(path, path)?,
+        Some(mode) => bind_unix_socket_with_mode(path, mode)?,
+    };
+    let result = listener
+        .set_nonblocking(true)
+        .and_then(|()| tokio::net::UnixListener::from_std(listener));
+    if result.is_err() {
+        // Don't leave behind a socket file that nothing is listening on.
+        let _ = std::fs::remove_file(path);
+    }
+    result
+}
+
+/// Binds a Unix socket at `path` whose permissions are `mode`.  Changing the
+/// permissions of `path` after binding it would leave the socket open to
+/// anyone the umask allows in the meantime, so the socket is bound in a
+/// directory only we can reach, given its permissions there, and then linked
+/// into place.  Unlike a rename, linking fails if `path` already exists.
+#[cfg(unix)]
+fn bind_unix_socket_with_mode(
+    path: &Path,
+    mode: u32,
+) -> std::io::Result<std::os::unix::net::UnixListener> {
+    use std::os::unix::fs::DirBuilderExt;
+    use std::os::unix::fs::PermissionsExt;
+
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let private_dir = parent.join(format!(".dropshot-{}", Uuid::new_v4()));
+    std::fs::DirBuilder::new().mode(0o700).create(&private_dir)?;
+    let private_path = private_dir.join("socket");
+    let result = bind_unix_listener(&private_path, path).and_then(|listener| {
+        std::fs::set_permissions(
+            &private_path,
+            std::fs::Permissions::from_mode(mode),
+        )?;
+        std::fs::hard_link(&private_path, path).map_err(|e| {
+            io_error(format!("failed to bind {}: {}", path.display(), e))
+        })?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_file(&private_path);
+    let _ = std::fs::remove_dir(&private_dir);
+    result
+}
+
+/// Binds a Unix socket at `bind_path`, describing failures in terms of the
+/// configured `path`.
+#[cfg(unix)]
+fn bind_unix_listener(
+    bind_path: &Path,
+    path: &Path,
+) -> std::io::Result<std::os::unix::net::UnixListener> {
+    std::os::unix::net::UnixListener::bind(bind_path).map_err(|e| {
+        io_error(format!("failed to bind {}: {}", path.display(), e))
+    })
+}
+
+/// Accepts connections on a Unix domain socket for hyper
+#[cfg(unix)]
+struct UnixAcceptor(tokio::net::UnixListener);
+
+#[cfg(unix)]
+impl hyper::server::accept::Accept for UnixAcceptor {
+    type Conn = tokio::net::UnixStream;
+    type Error = std::io::Error;
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        ctx: &mut core::task::Context,
+    ) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+        self.0.poll_accept(ctx).map(|result| Some(result.map(|(conn, _)| conn)))
+    }
+}
+
+#[cfg(unix)]
+impl<C: ServerContext> Service<&tokio::net::UnixStream>
+    for ServerConnectionHandler<C>
+{
+    type Response = ServerRequestHandler<C>;
+    type Error = GenericError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        _ctx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _conn: &tokio::net::UnixStream) -> Self::Future {
+        let server = Arc::clone(&self.server);
+        let log = self.log.clone();
+        Box::pin(http_connection_handle(server, log, UNIX_SOCKET_ADDR, None))
     }
 }
 
 /// Wrapper for TlsStream<TcpStream> that also carries the remote SocketAddr
+/// and the certificate presented by the client, if any
 #[derive(Debug)]
 struct TlsConn {
     stream: TlsStream<TcpStream>,
     remote_addr: SocketAddr,
+    client_cert: Option<ClientCert>,
 }
 
 impl TlsConn {
     fn new(stream: TlsStream<TcpStream>, remote_addr: SocketAddr) -> TlsConn {
-        TlsConn { stream, remote_addr }
+        // The handshake has completed by now, so any certificate chain the
+        // client presented has already been verified.  Parse it once here
+        // rather than for each request on the connection.
+        let client_cert = stream
+            .get_ref()
+            .1
+            .peer_certificates()
+            .and_then(ClientCert::from_chain);
+        TlsConn { stream, remote_addr, client_cert }
     }
 
     fn remote_addr(&self) -> SocketAddr {
         self.remote_addr
     }
+
+    fn client_cert(&self) -> Option<ClientCert> {
+        self.client_cert.clone()
+    }
 }
 
 /// Forward AsyncRead to the underlying stream
@@ -428,8 +852,13 @@ impl hyper::server::accept::Accept for HttpsAcceptor {
     }
 }
 
+/// The Hyper server for each address on which a Dropshot server listens,
+/// along with the logger for that address
 struct InnerHttpsServerStarter<C: ServerContext>(
-    Server<HttpsAcceptor, ServerConnectionHandler<C>>,
+    Vec<(
+        Server<HttpsAcceptor, ServerConnectionHandler<C>, ConnectionTasks>,
+        Logger,
+    )>,
 );
 
 /// Create a TLS configuration from the Dropshot config structure.
@@ -439,41 +868,128 @@ impl TryFrom<&ConfigTls> for rustls::ServerConfig {
     type Error = std::io::Error;
 
     fn try_from(config: &ConfigTls) -> std::io::Result<Self> {
-        let certs = load_certs(&config)?;
-        let private_key = load_private_key(&config)?;
-        let mut cfg = rustls::ServerConfig::builder()
-            // TODO: We may want to expose protocol configuration in our
-            // config
-            .with_safe_default_cipher_suites()
-            .with_safe_default_kx_groups()
-            .with_safe_default_protocol_versions()
-            .unwrap()
-            .with_client_cert_verifier(rustls::server::NoClientAuth::boxed())
-            .with_single_cert(certs, private_key)
-            .expect("bad certificate/key");
-        cfg.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
-        Ok(cfg)
+        tls_server_config(config, None, true)
     }
 }
 
+/// Create a TLS configuration that also verifies the certificates presented
+/// by clients, if `client_auth` is given, and offers HTTP/2 if `http2` is set.
+fn tls_server_config(
+    config: &ConfigTls,
+    client_auth: Option<&ConfigTlsClientAuth>,
+    http2: bool,
+) -> std::io::Result<rustls::ServerConfig> {
+    let certs = load_certs(config)?;
+    let private_key = load_private_key(config)?;
+    let verifier = match client_auth {
+        None => rustls::server::NoClientAuth::boxed(),
+        Some(client_auth) => {
+            let roots = load_client_cas(&client_auth.ca_file)?;
+            if client_auth.optional {
+                rustls::server::AllowAnyAnonymousOrAuthenticatedClient::new(
+                    roots,
+                )
+                .boxed()
+            } else {
+                rustls::server::AllowAnyAuthenticatedClient::new(roots).boxed()
+            }
+        }
+    };
+    let mut cfg = rustls::ServerConfig::builder()
+        // TODO: We may want to expose protocol configuration in our
+        // config
+        .with_safe_default_cipher_suites()
+        .with_safe_default_kx_groups()
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(certs, private_key)
+        .map_err(|err| {
+            io_error(format!("invalid certificate or key: {err}"))
+        })?;
+    cfg.alpn_protocols = if http2 {
+        vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+    } else {
+        vec![b"http/1.1".to_vec()]
+    };
+    Ok(cfg)
+}
+
+/// Applies the HTTP/2 configuration `config` to a server under construction.
+fn configure_http2<I, E>(
+    builder: hyper::server::Builder<I, E>,
+    config: &ConfigHttp2,
+) -> hyper::server::Builder<I, E> {
+    let mut builder = builder.http1_only(!config.enabled);
+    if let Some(max) = config.max_concurrent_streams {
+        builder = builder.http2_max_concurrent_streams(max);
+    }
+    if let Some(size) = config.initial_stream_window_size {
+        builder = builder.http2_initial_stream_window_size(size);
+    }
+    if let Some(size) = config.initial_connection_window_size {
+        builder = builder.http2_initial_connection_window_size(size);
+    }
+    if config.adaptive_window {
+        builder = builder.http2_adaptive_window(true);
+    }
+    builder
+}
+
+/// Returns the addresses on which the server configured by `config` listens
+/// for TCP connections, starting with its primary address.
+fn bind_addresses(
+    config: &ConfigDropshot,
+) -> impl Iterator<Item = &SocketAddr> + '_ {
+    std::iter::once(&config.bind_address)
+        .chain(config.additional_bind_addresses.iter())
+}
+
+/// Returns a future that completes, and logs that the server is shutting
+/// down, once `close_signal` is received.  The future can be cloned to shut
+/// down each of the server's listeners.
+fn close_future(
+    close_signal: tokio::sync::oneshot::Receiver<()>,
+    log_close: Logger,
+) -> Shared<BoxFuture<'static, ()>> {
+    async move {
+        close_signal
+            .await
+            .expect("dropshot server shutting down without invoking close()");
+        info!(log_close, "received request to begin graceful shutdown");
+    }
+    .boxed()
+    .shared()
+}
+
 type InnerHttpsServerStarterNewReturn<C> =
-    (InnerHttpsServerStarter<C>, Arc<DropshotState<C>>, SocketAddr);
+    (InnerHttpsServerStarter<C>, Arc<DropshotState<C>>, Vec<SocketAddr>);
 
 impl<C: ServerContext> InnerHttpsServerStarter<C> {
-    /// Begins execution of the underlying Http server.
+    /// Begins execution of the underlying Http servers, which shut down
+    /// together.
     fn start(
         self,
         close_signal: tokio::sync::oneshot::Receiver<()>,
+        ready: tokio::sync::watch::Sender<bool>,
         log_close: Logger,
     ) -> tokio::task::JoinHandle<Result<(), hyper::Error>> {
-        let graceful = self.0.with_graceful_shutdown(async move {
-            close_signal.await.expect(
-                "dropshot server shutting down without invoking close()",
-            );
-            info!(log_close, "received request to begin graceful shutdown");
-        });
-
-        tokio::spawn(async { graceful.await })
+        let close = close_future(close_signal, log_close);
+        let graceful = self
+            .0
+            .into_iter()
+            .map(|(server, log)| {
+                info!(log, "listening");
+                server.with_graceful_shutdown(close.clone())
+            })
+            .collect::<Vec<_>>();
+
+        tokio::spawn(async move {
+            // The listening sockets were bound when the server was created, so
+            // the server is accepting connections as soon as it's running.
+            let _ = ready.send(true);
+            futures::future::try_join_all(graceful).await.map(|_| ())
+        })
     }
 
     fn new(
@@ -486,36 +1002,78 @@ impl<C: ServerContext> InnerHttpsServerStarter<C> {
         let acceptor = Arc::new(Mutex::new(TlsAcceptor::from(Arc::new(
             // Unwrap is safe here because we cannot enter this code path
             // without a TLS configuration
-            rustls::ServerConfig::try_from(config.tls.as_ref().unwrap())?,
+            tls_server_config(
+                config.tls.as_ref().unwrap(),
+                config.tls_client_auth.as_ref(),
+                config.http2.enabled,
+            )?,
         ))));
 
-        let tcp = {
-            let listener = std::net::TcpListener::bind(&config.bind_address)?;
+        let mut listeners = Vec::new();
+        for bind_address in bind_addresses(config) {
+            let listener = std::net::TcpListener::bind(bind_address)?;
             listener.set_nonblocking(true)?;
             // We use `from_std` instead of just calling `bind` here directly
             // to avoid invoking an async function, to match the interface
             // provided by `HttpServerStarter::new`.
-            TcpListener::from_std(listener)?
-        };
-
-        let local_addr = tcp.local_addr()?;
-        let logger = log.new(o!("local_addr" => local_addr));
-        let https_acceptor =
-            HttpsAcceptor::new(logger.clone(), acceptor.clone(), tcp);
-
+            let tcp = TcpListener::from_std(listener)?;
+            let local_addr = tcp.local_addr()?;
+            let logger =
+                log.new(o!("local_addr" => local_addr, "scheme" => "https"));
+            listeners.push((tcp, local_addr, logger));
+        }
+        let local_addrs = listeners
+            .iter()
+            .map(|(_, local_addr, _)| *local_addr)
+            .collect::<Vec<_>>();
+        let local_addr = local_addrs[0];
+
+        let ServerParts {
+            router,
+            request_log_hook,
+            clock,
+            cookie_key,
+            page_tokens,
+        } = api.into_server_parts();
         let app_state = Arc::new(DropshotState {
             private,
             config: server_config,
-            router: api.into_router(),
-            log: logger,
+            router,
+            log: listeners[0].2.clone(),
             local_addr,
-            tls_acceptor: Some(acceptor),
+            tls_acceptor: Some(Arc::clone(&acceptor)),
+            tls_client_auth: config.tls_client_auth.clone(),
+            http2: config.http2.enabled,
+            load_shedder: config.load_shedding.as_ref().map(LoadShedder::new),
+            request_log_hook,
+            clock,
+            cookie_key,
+            page_tokens: Arc::new(page_tokens),
+            in_flight: Default::default(),
+            connections: Default::default(),
         });
 
-        let make_service = ServerConnectionHandler::new(Arc::clone(&app_state));
-        let server = Server::builder(https_acceptor).serve(make_service);
-
-        Ok((InnerHttpsServerStarter(server), app_state, local_addr))
+        let servers = listeners
+            .into_iter()
+            .map(|(tcp, _, logger)| {
+                let https_acceptor = HttpsAcceptor::new(
+                    logger.clone(),
+                    Arc::clone(&acceptor),
+                    tcp,
+                );
+                let make_service = ServerConnectionHandler::new(
+                    Arc::clone(&app_state),
+                    logger.clone(),
+                );
+                let builder = Server::builder(https_acceptor)
+                    .executor(app_state.connections.clone());
+                let server =
+                    configure_http2(builder, &config.http2).serve(make_service);
+                (server, logger)
+            })
+            .collect();
+
+        Ok((InnerHttpsServerStarter(servers), app_state, local_addrs))
     }
 }
 
@@ -534,7 +1092,9 @@ impl<C: ServerContext> Service<&TlsConn> for ServerConnectionHandler<C> {
     fn call(&mut self, conn: &TlsConn) -> Self::Future {
         let server = Arc::clone(&self.server);
         let remote_addr = conn.remote_addr();
-        Box::pin(http_connection_handle(server, remote_addr))
+        let client_cert = conn.client_cert();
+        let log = self.log.clone();
+        Box::pin(http_connection_handle(server, log, remote_addr, client_cert))
     }
 }
 
@@ -564,19 +1124,60 @@ impl FusedFuture for ShutdownWaitFuture {
 pub struct HttpServer<C: ServerContext> {
     probe_registration: ProbeRegistration,
     app_state: Arc<DropshotState<C>>,
-    local_addr: SocketAddr,
+    local_addrs: Vec<SocketAddr>,
     closer: CloseHandle,
     join_future: SharedBoxFuture<Result<(), String>>,
+    ready: tokio::sync::watch::Receiver<bool>,
+}
+
+// Channel used to trigger the shutdown of an [HttpServer].  This is shared
+// between the server and any [ShutdownHandle]s, and whichever uses it first
+// takes the sender.
+type CloseChannel =
+    Arc<std::sync::Mutex<Option<tokio::sync::oneshot::Sender<()>>>>;
+
+fn send_close(close_channel: &CloseChannel) {
+    if let Some(c) = close_channel.lock().unwrap().take() {
+        // The server may have stopped on its own already, in which case
+        // there's nobody to tell.
+        let _ = c.send(());
+    }
 }
 
 // Handle used to trigger the shutdown of an [HttpServer].
 struct CloseHandle {
-    close_channel: Option<tokio::sync::oneshot::Sender<()>>,
+    close_channel: CloseChannel,
 }
 
 impl<C: ServerContext> HttpServer<C> {
+    /// Returns the address on which the server is listening.  This is valid as
+    /// soon as the server has been created, even if `bind_address` specified
+    /// port 0.
     pub fn local_addr(&self) -> SocketAddr {
-        self.local_addr
+        self.local_addrs[0]
+    }
+
+    /// Returns every address on which the server is listening: that of
+    /// `bind_address` (the same as [`HttpServer::local_addr`]) followed by
+    /// those of `additional_bind_addresses`.
+    pub fn local_addrs(&self) -> &[SocketAddr] {
+        &self.local_addrs
+    }
+
+    /// Waits until the server is accepting connections.
+    ///
+    /// Connections made after this completes will be serviced without delay,
+    /// so test harnesses and orchestration code can use this rather than
+    /// polling the server's port.  This returns an error if the server stopped
+    /// before it became ready.
+    pub async fn wait_for_ready(&self) -> Result<(), String> {
+        let mut ready = self.ready.clone();
+        while !*ready.borrow() {
+            ready.changed().await.map_err(|_| {
+                "server stopped before it was ready".to_string()
+            })?;
+        }
+        Ok(())
     }
 
     pub fn app_private(&self) -> &C {
@@ -587,7 +1188,20 @@ impl<C: ServerContext> HttpServer<C> {
         self.app_state.using_tls()
     }
 
+    /// Returns the requests currently being handled, oldest first.  This is
+    /// also available to handlers (e.g., to implement an administrative
+    /// endpoint) through `rqctx.server.in_flight_requests()`.
+    pub fn in_flight_requests(&self) -> Vec<InFlightRequest> {
+        self.app_state.in_flight_requests()
+    }
+
     /// Update TLS certificates for a running HTTPS server.
+    ///
+    /// Connections accepted from now on use the new certificate chain and
+    /// key, while existing connections carry on with the ones they were
+    /// negotiated with.  This allows short-lived certificates to be rotated
+    /// without interrupting clients.  If the new configuration can't be
+    /// loaded, the server keeps using its current certificates.
     pub async fn refresh_tls(&self, config: &ConfigTls) -> Result<(), String> {
         let acceptor = &self
             .app_state
@@ -595,9 +1209,13 @@ impl<C: ServerContext> HttpServer<C> {
             .as_ref()
             .ok_or_else(|| "Not configured for TLS".to_string())?;
 
-        *acceptor.lock().await = TlsAcceptor::from(Arc::new(
-            rustls::ServerConfig::try_from(config).unwrap(),
-        ));
+        let tls_config = tls_server_config(
+            config,
+            self.app_state.tls_client_auth.as_ref(),
+            self.app_state.http2,
+        )
+        .map_err(|e| format!("loading TLS configuration: {e}"))?;
+        *acceptor.lock().await = TlsAcceptor::from(Arc::new(tls_config));
         Ok(())
     }
 
@@ -613,21 +1231,66 @@ impl<C: ServerContext> HttpServer<C> {
     /// This function does not cause the server to shut down. It just waits for
     /// the shutdown to happen.
     ///
-    /// To trigger a shutdown, Call [HttpServer::close] (which also awaits shutdown).
+    /// To trigger a shutdown, Call [HttpServer::close] (which also awaits
+    /// shutdown) or use a [`ShutdownHandle`].
     pub fn wait_for_shutdown(&self) -> ShutdownWaitFuture {
         ShutdownWaitFuture(self.join_future.clone())
     }
 
+    /// Returns a handle with which the server can be shut down from elsewhere
+    /// (e.g., from a task handling signals) while this `HttpServer` is
+    /// awaited or held by someone else.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            close_channel: Arc::clone(&self.closer.close_channel),
+            connections: self.app_state.connections.clone(),
+            join_future: self.join_future.clone(),
+            log: self.app_state.log.clone(),
+        }
+    }
+
     /// Signals the currently running server to stop and waits for it to exit.
+    ///
+    /// The server stops accepting connections right away, but waits for
+    /// requests in progress to complete however long they take.  See
+    /// [`HttpServer::shutdown`] to limit the wait.
     pub async fn close(mut self) -> Result<(), String> {
-        self.closer
-            .close_channel
-            .take()
-            .expect("cannot close twice")
-            .send(())
-            .expect("failed to send close signal");
+        self.send_close();
         self.join_future.await
     }
+
+    /// Signals the currently running server to stop, waits up to
+    /// `drain_timeout` for its open connections to finish, and then aborts
+    /// any connections that remain.
+    ///
+    /// As with [`HttpServer::close`], the server stops accepting connections
+    /// right away, and [`HttpServer::wait_for_shutdown`] completes once the
+    /// server has stopped.  An aborted connection is closed without a
+    /// response to any request in progress on it, and the handlers of those
+    /// requests are cancelled.
+    pub async fn shutdown(
+        mut self,
+        drain_timeout: Duration,
+    ) -> Result<(), String> {
+        self.send_close();
+        let join_future = self.join_future.clone();
+        match tokio::time::timeout(drain_timeout, join_future).await {
+            Ok(result) => result,
+            Err(_) => {
+                warn!(
+                    self.app_state.log,
+                    "aborting connections that did not drain";
+                    "drain_timeout" => ?drain_timeout
+                );
+                self.app_state.connections.abort_all();
+                self.join_future.await
+            }
+        }
+    }
+
+    fn send_close(&mut self) {
+        send_close(&self.closer.close_channel);
+    }
 }
 
 // For graceful termination, the `close()` function is preferred, as it can
@@ -636,12 +1299,128 @@ impl<C: ServerContext> HttpServer<C> {
 // (e.g., from failing tests).
 impl Drop for CloseHandle {
     fn drop(&mut self) {
-        if let Some(c) = self.close_channel.take() {
-            c.send(()).expect("failed to send close signal")
+        send_close(&self.close_channel);
+    }
+}
+
+/// A cloneable handle for shutting down an [`HttpServer`], obtained with
+/// [`HttpServer::shutdown_handle`].
+///
+/// This is useful when the code that decides the server should stop (e.g., a
+/// signal handler or a supervisor) is not the code that owns the server.
+/// Holding a handle does not keep the server running: dropping the
+/// `HttpServer` still shuts it down.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    close_channel: CloseChannel,
+    connections: ConnectionTasks,
+    join_future: SharedBoxFuture<Result<(), String>>,
+    log: Logger,
+}
+
+impl ShutdownHandle {
+    /// Signals the server to stop, as [`HttpServer::close`] does, without
+    /// waiting for it to exit.  Requests in progress are allowed to complete.
+    /// This does nothing if the server has already been told to stop.
+    pub fn shutdown(&self) {
+        send_close(&self.close_channel);
+    }
+
+    /// Signals the server to stop and aborts all of its open connections.
+    /// Requests in progress get no response and their handlers are cancelled.
+    pub fn abort(&self) {
+        send_close(&self.close_channel);
+        self.connections.abort_all();
+    }
+
+    /// Returns a future which completes when the server has shut down, like
+    /// [`HttpServer::wait_for_shutdown`].
+    pub fn wait_for_shutdown(&self) -> ShutdownWaitFuture {
+        ShutdownWaitFuture(self.join_future.clone())
+    }
+
+    /// Wires the process's termination signals to this handle: the first
+    /// SIGTERM or SIGINT (Ctrl-C on other platforms) shuts the server down
+    /// gracefully, and a second one aborts the connections that remain.
+    ///
+    /// This spawns a task that runs until the server has shut down, and so
+    /// must be called from within a Tokio runtime.  It fails if the signal
+    /// handlers can't be installed.
+    pub fn shutdown_on_signals(
+        &self,
+    ) -> std::io::Result<tokio::task::JoinHandle<()>> {
+        let mut signals = TerminationSignals::new()?;
+        let handle = self.clone();
+        Ok(tokio::spawn(async move {
+            let mut shutdown = handle.wait_for_shutdown();
+            tokio::select! {
+                signal = signals.recv() => {
+                    info!(
+                        handle.log,
+                        "received signal, shutting down";
+                        "signal" => signal
+                    );
+                    handle.shutdown();
+                }
+                _ = &mut shutdown => return,
+            }
+            tokio::select! {
+                signal = signals.recv() => {
+                    warn!(
+                        handle.log,
+                        "received another signal, aborting connections";
+                        "signal" => signal
+                    );
+                    handle.abort();
+                }
+                _ = &mut shutdown => {}
+            }
+        }))
+    }
+}
+
+// The signals that ask a process to terminate.
+#[cfg(unix)]
+struct TerminationSignals {
+    terminate: tokio::signal::unix::Signal,
+    interrupt: tokio::signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl TerminationSignals {
+    fn new() -> std::io::Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+        Ok(TerminationSignals {
+            terminate: signal(SignalKind::terminate())?,
+            interrupt: signal(SignalKind::interrupt())?,
+        })
+    }
+
+    // Waits for the next signal, returning its name.
+    async fn recv(&mut self) -> &'static str {
+        tokio::select! {
+            _ = self.terminate.recv() => "SIGTERM",
+            _ = self.interrupt.recv() => "SIGINT",
         }
     }
 }
 
+#[cfg(not(unix))]
+struct TerminationSignals;
+
+#[cfg(not(unix))]
+impl TerminationSignals {
+    fn new() -> std::io::Result<Self> {
+        Ok(TerminationSignals)
+    }
+
+    // Waits for the next signal, returning its name.
+    async fn recv(&mut self) -> &'static str {
+        let _ = tokio::signal::ctrl_c().await;
+        "ctrl-c"
+    }
+}
+
 impl<C: ServerContext> Future for HttpServer<C> {
     type Output = Result<(), String>;
 
@@ -658,16 +1437,45 @@ impl<C: ServerContext> FusedFuture for HttpServer<C> {
     }
 }
 
+/// Runs the tasks that Hyper creates to serve connections, keeping track of
+/// them so that connections still open after a shutdown's drain timeout can
+/// be aborted.
+#[derive(Clone, Default)]
+pub(crate) struct ConnectionTasks(
+    Arc<std::sync::Mutex<Vec<tokio::task::JoinHandle<()>>>>,
+);
+
+impl ConnectionTasks {
+    fn abort_all(&self) {
+        for task in self.0.lock().unwrap().drain(..) {
+            task.abort();
+        }
+    }
+}
+
+impl<F> hyper::rt::Executor<F> for ConnectionTasks
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    fn execute(&self, future: F) {
+        let mut tasks = self.0.lock().unwrap();
+        tasks.retain(|task| !task.is_finished());
+        tasks.push(tokio::spawn(future));
+    }
+}
+
 /// Initial entry point for handling a new connection to the HTTP server.
 /// This is invoked by Hyper when a new connection is accepted.  This function
 /// must return a Hyper Service object that will handle requests for this
 /// connection.
 async fn http_connection_handle<C: ServerContext>(
     server: Arc<DropshotState<C>>,
+    log: Logger,
     remote_addr: SocketAddr,
+    client_cert: Option<ClientCert>,
 ) -> Result<ServerRequestHandler<C>, GenericError> {
-    info!(server.log, "accepted connection"; "remote_addr" => %remote_addr);
-    Ok(ServerRequestHandler::new(server, remote_addr))
+    info!(log, "accepted connection"; "remote_addr" => %remote_addr);
+    Ok(ServerRequestHandler::new(server, log, remote_addr, client_cert))
 }
 
 /// Initial entry point for handling a new request to the HTTP server.  This is
@@ -676,6 +1484,7 @@ async fn http_connection_handle<C: ServerContext>(
 /// also get turned into an HTTP response).
 async fn http_request_handle_wrap<C: ServerContext>(
     server: Arc<DropshotState<C>>,
+    log: Logger,
     remote_addr: SocketAddr,
     request: Request<Body>,
 ) -> Result<Response<Body>, GenericError> {
@@ -683,14 +1492,18 @@ async fn http_request_handle_wrap<C: ServerContext>(
     // straightforward, since the request handling code can simply return early
     // with an error and we'll treat it like an error from any of the endpoints
     // themselves.
+    let clock = Arc::clone(&server.clock);
+    let start_time = clock.now();
     let request_id = generate_request_id();
-    let request_log = server.log.new(o!(
+    let request_log = log.new(o!(
         "remote_addr" => remote_addr,
         "req_id" => request_id.clone(),
         "method" => request.method().as_str().to_string(),
         "uri" => format!("{}", request.uri()),
     ));
     trace!(request_log, "incoming request");
+    let _in_flight =
+        server.in_flight.start(&request_id, &request, remote_addr, start_time);
     #[cfg(feature = "usdt-probes")]
     probes::request__start!(|| {
         let uri = request.uri();
@@ -709,6 +1522,13 @@ async fn http_request_handle_wrap<C: ServerContext>(
     #[cfg(feature = "usdt-probes")]
     let local_addr = server.local_addr;
 
+    // Body extractors count the bytes they read from the request body here.
+    // Bodies that handlers read themselves (through `RawRequest`) go
+    // uncounted.
+    let request_body_bytes = RequestBodyBytes::default();
+    let mut request = request;
+    request.extensions_mut().insert(request_body_bytes.clone());
+
     let maybe_response = http_request_handle(
         server,
         request,
@@ -718,11 +1538,30 @@ async fn http_request_handle_wrap<C: ServerContext>(
     )
     .await;
 
+    // A 304 ("Not Modified") may arrive as an `HttpError` (see
+    // `HttpError::for_not_modified()`), but it's a successful response.
+    let maybe_response = maybe_response.or_else(|error| {
+        if error.status_code == http::StatusCode::NOT_MODIFIED {
+            Ok(error.into_response(&request_id))
+        } else {
+            Err(error)
+        }
+    });
+
+    // The response head is ready to be sent now, but its body may not have
+    // been produced yet.  We report the body's size only if it's known.
+    let time_to_first_byte_us = u64::try_from(
+        clock.now().saturating_duration_since(start_time).as_micros(),
+    )
+    .unwrap_or(u64::MAX);
+    let request_body_bytes = request_body_bytes.get();
+
     let response = match maybe_response {
         Err(error) => {
             let message_external = error.external_message.clone();
             let message_internal = error.internal_message.clone();
             let r = error.into_response(&request_id);
+            let response_body_bytes = r.body().size_hint().exact();
 
             #[cfg(feature = "usdt-probes")]
             probes::request__done!(|| {
@@ -732,6 +1571,9 @@ async fn http_request_handle_wrap<C: ServerContext>(
                     remote_addr,
                     status_code: r.status().as_u16(),
                     message: message_external.clone(),
+                    request_body_bytes,
+                    response_body_bytes,
+                    time_to_first_byte_us,
                 }
             });
 
@@ -740,15 +1582,23 @@ async fn http_request_handle_wrap<C: ServerContext>(
                 "response_code" => r.status().as_str().to_string(),
                 "error_message_internal" => message_internal,
                 "error_message_external" => message_external,
+                "request_body_bytes" => request_body_bytes,
+                "response_body_bytes" => response_body_bytes,
+                "time_to_first_byte_us" => time_to_first_byte_us,
             );
 
             r
         }
 
         Ok(response) => {
+            let response_body_bytes = response.body().size_hint().exact();
+
             // TODO-debug: add request and response headers here
             info!(request_log, "request completed";
-                "response_code" => response.status().as_str().to_string()
+                "response_code" => response.status().as_str().to_string(),
+                "request_body_bytes" => request_body_bytes,
+                "response_body_bytes" => response_body_bytes,
+                "time_to_first_byte_us" => time_to_first_byte_us,
             );
 
             #[cfg(feature = "usdt-probes")]
@@ -759,6 +1609,9 @@ async fn http_request_handle_wrap<C: ServerContext>(
                     remote_addr,
                     status_code: response.status().as_u16(),
                     message: "".to_string(),
+                    request_body_bytes,
+                    response_body_bytes,
+                    time_to_first_byte_us,
                 }
             });
 
@@ -783,23 +1636,97 @@ async fn http_request_handle<C: ServerContext>(
     // this to take forever.
     // TODO-correctness: Do we need to dump the body on errors?
     let method = request.method();
+    let is_head = method == http::Method::HEAD;
     let uri = request.uri();
     let lookup_result =
         server.router.lookup_route(&method, uri.path().into())?;
-    let rqctx = RequestContext {
+    let operation_id = lookup_result.operation_id;
+    let endpoint = Arc::clone(&lookup_result.endpoint);
+    server.in_flight.routed(request_id, operation_id);
+    if let Some(load_shedder) = &server.load_shedder {
+        if !load_shedder.admit(operation_id) {
+            return Err(HttpError::for_unavail(
+                None,
+                format!("shedding load for endpoint \"{}\"", operation_id),
+            ));
+        }
+    }
+    let mut rqctx = RequestContext {
         server: Arc::clone(&server),
         request: RequestInfo::new(&request, remote_addr),
         path_variables: lookup_result.variables,
+        endpoint: lookup_result.endpoint,
         body_content_type: lookup_result.body_content_type,
+        parameter_case: lookup_result.parameter_case,
         request_id: request_id.to_string(),
         log: request_log,
     };
-    let mut response =
-        lookup_result.handler.handle_request(rqctx, request).await?;
+    // This runs before any extractor, so the hook sees only the raw request.
+    if let Some(request_log_hook) = &server.request_log_hook {
+        request_log_hook.apply(&mut rqctx);
+    }
+    let request_timeout = rqctx.request_timeout();
+    let log = rqctx.log.clone();
+    // Latency is recorded when the guard is dropped, so that it's recorded
+    // even if this future is dropped (e.g., because the client disconnected)
+    // before the handler completes.
+    let latency_guard = server.load_shedder.as_ref().map(|load_shedder| {
+        load_shedder.latency_guard(operation_id, &*server.clock)
+    });
+    let handler_future = with_page_tokens(
+        &server.page_tokens,
+        &server.clock,
+        lookup_result.handler.handle_request(rqctx, request),
+    );
+    // Dropping the handler's future when the timeout expires cancels it.
+    let result = match request_timeout {
+        None => handler_future.await,
+        Some(timeout) => {
+            match tokio::time::timeout(timeout, handler_future).await {
+                Ok(result) => result,
+                Err(_) => {
+                    warn!(log, "request timed out"; "timeout" => ?timeout);
+                    Err(HttpError::for_unavail(
+                        None,
+                        format!("request timed out after {:?}", timeout),
+                    ))
+                }
+            }
+        }
+    };
+    drop(latency_guard);
+    // Clients learn that an endpoint is being phased out from its error
+    // responses as well as its successful ones.
+    let mut lifecycle_headers = http::HeaderMap::new();
+    if let Some(deprecated_since) = endpoint.deprecated_since {
+        lifecycle_headers.insert(
+            HEADER_DEPRECATION,
+            http::header::HeaderValue::from_str(&format!(
+                "@{}",
+                deprecated_since.timestamp()
+            ))
+            .unwrap(),
+        );
+    }
+    if let Some(sunset) = endpoint.sunset {
+        lifecycle_headers.insert(
+            HEADER_SUNSET,
+            http::header::HeaderValue::from_str(&http_date(sunset)).unwrap(),
+        );
+    }
+    let mut response = match result {
+        Ok(response) => response,
+        Err(mut error) => {
+            error.headers.extend(lifecycle_headers);
+            return Err(error);
+        }
+    };
+    http_response_framing(&mut response, is_head)?;
     response.headers_mut().insert(
         HEADER_REQUEST_ID,
         http::header::HeaderValue::from_str(&request_id).unwrap(),
     );
+    response.headers_mut().extend(lifecycle_headers);
     Ok(response)
 }
 
@@ -820,13 +1747,15 @@ fn generate_request_id() -> String {
 pub struct ServerConnectionHandler<C: ServerContext> {
     /// backend state that will be made available to the connection handler
     server: Arc<DropshotState<C>>,
+    /// log for the address on which connections are accepted
+    log: Logger,
 }
 
 impl<C: ServerContext> ServerConnectionHandler<C> {
     /// Create an ServerConnectionHandler with the given state object that
-    /// will be made available to the handler.
-    fn new(server: Arc<DropshotState<C>>) -> Self {
-        ServerConnectionHandler { server }
+    /// will be made available to the handler, logging to `log`.
+    fn new(server: Arc<DropshotState<C>>, log: Logger) -> Self {
+        ServerConnectionHandler { server, log }
     }
 }
 
@@ -859,7 +1788,8 @@ impl<T: ServerContext> Service<&AddrStream> for ServerConnectionHandler<T> {
         // address and any other per-connection state that we want to keep.
         let server = Arc::clone(&self.server);
         let remote_addr = conn.remote_addr();
-        Box::pin(http_connection_handle(server, remote_addr))
+        let log = self.log.clone();
+        Box::pin(http_connection_handle(server, log, remote_addr, None))
     }
 }
 
@@ -871,14 +1801,23 @@ impl<T: ServerContext> Service<&AddrStream> for ServerConnectionHandler<T> {
 pub struct ServerRequestHandler<C: ServerContext> {
     /// backend state that will be made available to the request handler
     server: Arc<DropshotState<C>>,
+    /// log for the address on which the connection was accepted
+    log: Logger,
     remote_addr: SocketAddr,
+    /// certificate presented by the client during the TLS handshake
+    client_cert: Option<ClientCert>,
 }
 
 impl<C: ServerContext> ServerRequestHandler<C> {
     /// Create a ServerRequestHandler object with the given state object that
     /// will be provided to the handler function.
-    fn new(server: Arc<DropshotState<C>>, remote_addr: SocketAddr) -> Self {
-        ServerRequestHandler { server, remote_addr }
+    fn new(
+        server: Arc<DropshotState<C>>,
+        log: Logger,
+        remote_addr: SocketAddr,
+        client_cert: Option<ClientCert>,
+    ) -> Self {
+        ServerRequestHandler { server, log, remote_addr, client_cert }
     }
 }
 
@@ -895,9 +1834,15 @@ impl<C: ServerContext> Service<Request<Body>> for ServerRequestHandler<C> {
         Poll::Ready(Ok(()))
     }
 
-    fn call(&mut self, req: Request<Body>) -> Self::Future {
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        // The certificate travels with the request so that it's available
+        // when `RequestInfo` is constructed.
+        if let Some(client_cert) = &self.client_cert {
+            req.extensions_mut().insert(client_cert.clone());
+        }
         Box::pin(http_request_handle_wrap(
             Arc::clone(&self.server),
+            self.log.clone(),
             self.remote_addr,
             req,
         ))
@@ -910,6 +1855,14 @@ fn io_error(err: String) -> std::io::Error {
 
 // Load public certificate from config.
 fn load_certs(config: &ConfigTls) -> std::io::Result<Vec<rustls::Certificate>> {
+    // DER certificates need no decoding.
+    if let ConfigTls::AsDer { certs, .. } = config {
+        if certs.is_empty() {
+            return Err(io_error("expected at least one certificate".into()));
+        }
+        return Ok(certs.iter().cloned().map(rustls::Certificate).collect());
+    }
+
     let mut reader = config.cert_reader()?;
 
     // Load and return certificate.
@@ -918,8 +1871,37 @@ fn load_certs(config: &ConfigTls) -> std::io::Result<Vec<rustls::Certificate>> {
         .map(|mut chain| chain.drain(..).map(rustls::Certificate).collect())
 }
 
+// Load the certificates of the CAs trusted to issue client certificates.
+fn load_client_cas(ca_file: &Path) -> std::io::Result<rustls::RootCertStore> {
+    let file = std::fs::File::open(ca_file).map_err(|e| {
+        io_error(format!("failed to open {}: {}", ca_file.display(), e))
+    })?;
+    let certs = rustls_pemfile::certs(&mut std::io::BufReader::new(file))
+        .map_err(|err| {
+            io_error(format!("failed to load client CA certificates: {err}"))
+        })?;
+
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in certs {
+        roots.add(&rustls::Certificate(cert)).map_err(|err| {
+            io_error(format!("invalid client CA certificate: {err}"))
+        })?;
+    }
+    if roots.is_empty() {
+        return Err(io_error(format!(
+            "no client CA certificates in {}",
+            ca_file.display()
+        )));
+    }
+    Ok(roots)
+}
+
 // Load private key from config.
 fn load_private_key(config: &ConfigTls) -> std::io::Result<rustls::PrivateKey> {
+    if let ConfigTls::AsDer { key, .. } = config {
+        return Ok(rustls::PrivateKey(key.clone()));
+    }
+
     let mut reader = config.key_reader()?;
 
     // Load and return a single private key.
@@ -1023,6 +2005,16 @@ mod test {
         assert!(server.close().await.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_server_wait_for_ready() {
+        let (server, config) = create_test_server();
+        server.wait_for_ready().await.unwrap();
+        // Waiting again once the server is ready completes immediately.
+        server.wait_for_ready().await.unwrap();
+        single_client_request(server.local_addr(), config.log()).await;
+        server.close().await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_drop_server_without_close_okay() {
         let (server, _) = create_test_server();
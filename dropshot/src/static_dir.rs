@@ -0,0 +1,84 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Serving a directory tree of static files
+
+use crate::error::HttpError;
+use crate::extractor::EntityTag;
+use crate::extractor::Preconditions;
+use crate::extractor::RangeRequest;
+use crate::handler::HttpResponseFile;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// File served for a request that names a directory
+pub(crate) const INDEX_FILE: &str = "index.html";
+
+/// Path parameters of the endpoint serving a static directory
+#[derive(Deserialize, JsonSchema)]
+pub(crate) struct StaticDirPath {
+    pub path: Vec<String>,
+}
+
+/// Serves the file named by the components `path` within the directory
+/// `root`, which must be canonical.
+pub(crate) async fn static_dir_get(
+    root: &Path,
+    path: Vec<String>,
+    preconditions: Preconditions,
+    range: RangeRequest,
+) -> Result<HttpResponseFile, HttpError> {
+    // The router rejects literal dot-segments, but it decodes each segment
+    // afterward, so "%2e%2e" and "a%2fb" arrive here intact.
+    let mut entry = root.to_path_buf();
+    for component in &path {
+        if component == "."
+            || component == ".."
+            || component.contains(['/', '\\', '\0'])
+        {
+            return Err(HttpError::for_not_found(
+                None,
+                format!("invalid path component {:?}", component),
+            ));
+        }
+        entry.push(component);
+    }
+
+    let mut entry = resolve(root, &entry).await?;
+    if tokio::fs::metadata(&entry).await.map_or(false, |m| m.is_dir()) {
+        entry = resolve(root, &entry.join(INDEX_FILE)).await?;
+    }
+
+    let file = HttpResponseFile::open(&entry).await?;
+    let etag = EntityTag::weak(match file.last_modified() {
+        Some(time) => format!(
+            "{:x}.{:x}-{:x}",
+            time.timestamp(),
+            time.timestamp_subsec_nanos(),
+            file.len()
+        ),
+        None => format!("{:x}", file.len()),
+    });
+    // A 304 carries the same `ETag` and `Last-Modified` as the file would.
+    preconditions.evaluate(Some(&etag), file.last_modified())?;
+    file.with_etag(etag).with_range(&range)
+}
+
+/// Resolves symbolic links in `entry`, making sure that the result is still
+/// within `root`.
+async fn resolve(root: &Path, entry: &Path) -> Result<PathBuf, HttpError> {
+    let resolved = tokio::fs::canonicalize(entry).await.map_err(|e| {
+        HttpError::for_not_found(
+            None,
+            format!("resolving {:?}: {:#}", entry, e),
+        )
+    })?;
+    if !resolved.starts_with(root) {
+        return Err(HttpError::for_not_found(
+            None,
+            format!("{:?} resolves outside of {:?}", entry, root),
+        ));
+    }
+    Ok(resolved)
+}
@@ -35,6 +35,27 @@ use crate::logging::ConfigLogging;
 use crate::pagination::ResultsPage;
 use crate::server::{HttpServer, HttpServerStarter, ServerContext};
 
+mod load_generator;
+mod mock_clock;
+mod recording;
+mod request_generator;
+
+pub use load_generator::LoadGenerator;
+pub use load_generator::LoadLimit;
+pub use load_generator::LoadReport;
+pub use mock_clock::MockClock;
+pub use recording::RecordedBody;
+pub use recording::RecordedExchange;
+pub use recording::RecordedRequest;
+pub use recording::RecordedResponse;
+pub use recording::Recorder;
+pub use recording::Recording;
+pub use recording::ReplayDifference;
+pub use recording::ReplayRules;
+pub use request_generator::ContractViolation;
+pub use request_generator::GeneratedRequest;
+pub use request_generator::RequestGenerator;
+
 enum AllowedValue<'a> {
     Any,
     OneOf(&'a [&'a str]),
@@ -56,11 +77,23 @@ pub const TEST_HEADER_2: &str = "x-dropshot-test-header-2";
 
 // List of allowed HTTP headers in responses.
 // Used to make sure we don't leak headers unexpectedly.
-const ALLOWED_HEADERS: [AllowedHeader<'static>; 8] = [
+const ALLOWED_HEADERS: [AllowedHeader<'static>; 17] = [
+    AllowedHeader {
+        name: "accept-ranges",
+        value: AllowedValue::OneOf(&["bytes"]),
+    },
+    AllowedHeader::new("cache-control"),
+    AllowedHeader::new("content-disposition"),
     AllowedHeader::new("content-length"),
+    AllowedHeader::new("content-range"),
     AllowedHeader::new("content-type"),
     AllowedHeader::new("date"),
+    AllowedHeader::new("deprecation"),
+    AllowedHeader::new("etag"),
+    AllowedHeader::new("last-modified"),
+    AllowedHeader::new("link"),
     AllowedHeader::new("location"),
+    AllowedHeader::new("sunset"),
     AllowedHeader::new("x-request-id"),
     AllowedHeader {
         name: "transfer-encoding",
@@ -79,6 +112,8 @@ pub struct ClientTestContext {
     pub client: Client<HttpConnector>,
     /// logger for the test suite HTTP client
     pub client_log: Logger,
+    /// records requests and responses, if enabled with `record()`
+    recorder: Option<Recorder>,
 }
 
 impl ClientTestContext {
@@ -88,9 +123,17 @@ impl ClientTestContext {
             bind_address: server_addr,
             client: Client::new(),
             client_log: log,
+            recorder: None,
         }
     }
 
+    /// Records each subsequent request made with
+    /// [`make_request_with_request`](Self::make_request_with_request) (and
+    /// the functions built on it), along with its response, into `recorder`.
+    pub fn record(&mut self, recorder: &Recorder) {
+        self.recorder = Some(recorder.clone());
+    }
+
     /// Given the path for an API endpoint (e.g., "/projects"), return a Uri that
     /// we can use to invoke this endpoint from the client.  This essentially
     /// appends the path to a base URL constructed from the server's IP address
@@ -242,11 +285,11 @@ impl ClientTestContext {
             "body" => ?&request.body(),
         );
 
-        let mut response = self
-            .client
-            .request(request)
-            .await
-            .expect("failed to make request to server");
+        let response = match &self.recorder {
+            Some(recorder) => recorder.send(&self.client, request).await,
+            None => self.client.request(request).await,
+        };
+        let mut response = response.expect("failed to make request to server");
 
         // Check that we got the expected response code.
         let status = response.status();
@@ -692,6 +735,30 @@ pub async fn iter_collection<T: Clone + DeserializeOwned>(
     (rv, npages)
 }
 
+/// Fetches all items of a paginated collection, following each page's
+/// `next_page` token until the server reports that there are no more pages.
+/// Any query parameters in `list_url` (e.g., `limit` or filters) are sent with
+/// every request.
+pub async fn objects_list_all<T: DeserializeOwned>(
+    client: &ClientTestContext,
+    list_url: &str,
+) -> Vec<T> {
+    let separator = if list_url.contains('?') { '&' } else { '?' };
+    let mut page = objects_list_page::<T>(client, list_url).await;
+    let mut rv = page.items;
+
+    while let Some(token) = page.next_page {
+        page = objects_list_page::<T>(
+            client,
+            &format!("{}{}page_token={}", list_url, separator, token),
+        )
+        .await;
+        rv.extend(page.items);
+    }
+
+    rv
+}
+
 static TEST_SUITE_LOGGER_ID: AtomicU32 = AtomicU32::new(0);
 
 /// Returns a unique path name in a temporary directory that includes the given
@@ -0,0 +1,287 @@
+// Copyright 2023 Oxide Computer Company
+//! Generation of load against a test server
+//!
+//! [`LoadGenerator`] sends the same request to a server over a number of
+//! concurrent connections, either for a fixed duration or until a fixed number
+//! of requests have been sent, and summarizes how the server responded in a
+//! [`LoadReport`].  It's intended for soak and performance regression tests
+//! that would otherwise need an external tool.
+
+use super::ClientTestContext;
+use http::Method;
+use hyper::body::to_bytes;
+use hyper::Body;
+use hyper::Client;
+use hyper::Request;
+use hyper::StatusCode;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Determines when a [`LoadGenerator`] stops sending requests
+#[derive(Clone, Copy, Debug)]
+pub enum LoadLimit {
+    /// send requests until this much time has elapsed
+    Duration(Duration),
+    /// send this many requests in total
+    Requests(u64),
+}
+
+/// Sends a request repeatedly over concurrent connections and reports the
+/// results.
+///
+/// ```no_run
+/// # async fn example(testctx: dropshot::test_util::TestContext<()>) {
+/// use dropshot::test_util::LoadGenerator;
+/// use http::Method;
+///
+/// let report = LoadGenerator::new(Method::GET, "/projects")
+///     .concurrency(8)
+///     .requests(10_000)
+///     .run(&testctx.client_testctx)
+///     .await;
+/// assert_eq!(report.failures(), 0);
+/// println!("{}", report);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct LoadGenerator {
+    method: Method,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Option<Vec<u8>>,
+    concurrency: usize,
+    limit: LoadLimit,
+}
+
+impl LoadGenerator {
+    /// Creates a generator that sends `method` requests for `path` over a
+    /// single connection for one second.
+    pub fn new(method: Method, path: &str) -> Self {
+        LoadGenerator {
+            method,
+            path: path.to_string(),
+            headers: Vec::new(),
+            body: None,
+            concurrency: 1,
+            limit: LoadLimit::Duration(Duration::from_secs(1)),
+        }
+    }
+
+    /// Adds a header to each request.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sets the body of each request.
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Sets the number of connections over which requests are sent
+    /// concurrently.  Each connection has at most one request outstanding.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        assert!(concurrency > 0, "concurrency must be at least 1");
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Sends requests until `duration` has elapsed.
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.limit = LoadLimit::Duration(duration);
+        self
+    }
+
+    /// Sends `count` requests in total.
+    pub fn requests(mut self, count: u64) -> Self {
+        self.limit = LoadLimit::Requests(count);
+        self
+    }
+
+    /// Sends requests to the server until the limit is reached and returns a
+    /// summary of the results.
+    pub async fn run(&self, client: &ClientTestContext) -> LoadReport {
+        let uri = client.url(&self.path);
+        let issued = Arc::new(AtomicU64::new(0));
+        let start = Instant::now();
+        let deadline = match self.limit {
+            LoadLimit::Duration(duration) => Some(start + duration),
+            LoadLimit::Requests(_) => None,
+        };
+
+        let workers = (0..self.concurrency)
+            .map(|_| {
+                // Each worker has its own client, and therefore its own
+                // connection to the server.
+                let http = Client::new();
+                let uri = uri.clone();
+                let method = self.method.clone();
+                let headers = self.headers.clone();
+                let body = self.body.clone();
+                let limit = self.limit;
+                let issued = Arc::clone(&issued);
+                tokio::spawn(async move {
+                    let mut results = WorkerResults::default();
+                    loop {
+                        let more = match limit {
+                            LoadLimit::Duration(_) => {
+                                Instant::now() < deadline.unwrap()
+                            }
+                            LoadLimit::Requests(count) => {
+                                issued.fetch_add(1, Ordering::SeqCst) < count
+                            }
+                        };
+                        if !more {
+                            break;
+                        }
+
+                        let mut builder = Request::builder()
+                            .method(method.clone())
+                            .uri(uri.clone());
+                        for (name, value) in &headers {
+                            builder = builder.header(name.as_str(), value);
+                        }
+                        let request = builder
+                            .body(match &body {
+                                Some(body) => Body::from(body.clone()),
+                                None => Body::empty(),
+                            })
+                            .expect("attempted to construct invalid request");
+
+                        let request_start = Instant::now();
+                        let result = match http.request(request).await {
+                            Ok(response) => {
+                                let status = response.status();
+                                to_bytes(response.into_body())
+                                    .await
+                                    .map(|_| status)
+                                    .map_err(|_| ())
+                            }
+                            Err(_) => Err(()),
+                        };
+                        let latency = request_start.elapsed();
+                        match result {
+                            Ok(status) => {
+                                *results.statuses.entry(status).or_insert(0) +=
+                                    1;
+                                results.latencies.push(latency);
+                            }
+                            Err(()) => results.failures += 1,
+                        }
+                    }
+                    results
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut statuses = BTreeMap::new();
+        let mut latencies = Vec::new();
+        let mut failures = 0;
+        for worker in workers {
+            let results = worker.await.expect("load generator worker panicked");
+            for (status, count) in results.statuses {
+                *statuses.entry(status).or_insert(0) += count;
+            }
+            latencies.extend(results.latencies);
+            failures += results.failures;
+        }
+        latencies.sort_unstable();
+
+        LoadReport { elapsed: start.elapsed(), statuses, latencies, failures }
+    }
+}
+
+#[derive(Default)]
+struct WorkerResults {
+    statuses: BTreeMap<StatusCode, u64>,
+    latencies: Vec<Duration>,
+    failures: u64,
+}
+
+/// Summary of the requests sent by a [`LoadGenerator`]
+#[derive(Debug)]
+pub struct LoadReport {
+    /// time from the first request being sent to the last response arriving
+    pub elapsed: Duration,
+    /// number of responses received with each status
+    pub statuses: BTreeMap<StatusCode, u64>,
+    /// latency of each request that received a response, in increasing order
+    latencies: Vec<Duration>,
+    failures: u64,
+}
+
+impl LoadReport {
+    /// Returns the number of requests that received a response.
+    pub fn responses(&self) -> u64 {
+        self.latencies.len() as u64
+    }
+
+    /// Returns the number of requests that failed without a complete
+    /// response, as when the connection is reset.
+    pub fn failures(&self) -> u64 {
+        self.failures
+    }
+
+    /// Returns the number of responses whose status was not a success.
+    pub fn errors(&self) -> u64 {
+        self.statuses
+            .iter()
+            .filter(|(status, _)| !status.is_success())
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    /// Returns the rate at which responses were received, per second.
+    pub fn throughput(&self) -> f64 {
+        let elapsed = self.elapsed.as_secs_f64();
+        if elapsed == 0.0 {
+            return 0.0;
+        }
+        self.responses() as f64 / elapsed
+    }
+
+    /// Returns the latency below which `percentile` percent of responses
+    /// arrived (using the nearest-rank method), or `None` if no responses were
+    /// received.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `percentile` is not in the range `(0, 100]`.
+    pub fn latency_percentile(&self, percentile: f64) -> Option<Duration> {
+        assert!(
+            percentile > 0.0 && percentile <= 100.0,
+            "percentile must be in (0, 100]"
+        );
+        if self.latencies.is_empty() {
+            return None;
+        }
+        let rank = (percentile / 100.0 * self.latencies.len() as f64).ceil();
+        Some(self.latencies[(rank as usize).max(1) - 1])
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} responses ({} errors), {} failures in {:?} ({:.1} req/s)",
+            self.responses(),
+            self.errors(),
+            self.failures,
+            self.elapsed,
+            self.throughput()
+        )?;
+        for percentile in &[50.0, 90.0, 99.0, 100.0] {
+            if let Some(latency) = self.latency_percentile(*percentile) {
+                write!(f, ", p{} {:?}", percentile, latency)?;
+            }
+        }
+        Ok(())
+    }
+}
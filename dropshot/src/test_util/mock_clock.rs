@@ -0,0 +1,68 @@
+// Copyright 2023 Oxide Computer Company
+//! [`Clock`] whose time is controlled by the test
+
+use crate::clock::Clock;
+use chrono::DateTime;
+use chrono::Utc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+/// [`Clock`] that only moves when the test advances it
+///
+/// Clones share the same time, so a test can keep one clone and give another
+/// to the server through
+/// [`ApiDescription::clock`](crate::ApiDescription::clock):
+///
+/// ```
+/// use dropshot::test_util::MockClock;
+/// use dropshot::ApiDescription;
+/// use std::time::Duration;
+///
+/// let clock = MockClock::new("2023-01-01T00:00:00Z".parse().unwrap());
+/// let api = ApiDescription::<()>::new().clock(clock.clone());
+/// // ... start a server with `api` ...
+/// clock.advance(Duration::from_secs(3600));
+/// ```
+#[derive(Clone, Debug)]
+pub struct MockClock {
+    time: Arc<Mutex<MockTime>>,
+}
+
+#[derive(Debug)]
+struct MockTime {
+    now: Instant,
+    now_utc: DateTime<Utc>,
+}
+
+impl MockClock {
+    /// Creates a clock whose wall-clock time starts at `start`.
+    pub fn new(start: DateTime<Utc>) -> Self {
+        MockClock {
+            time: Arc::new(Mutex::new(MockTime {
+                now: Instant::now(),
+                now_utc: start,
+            })),
+        }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let mut time = self.time.lock().unwrap();
+        time.now += duration;
+        time.now_utc = time.now_utc
+            + chrono::Duration::from_std(duration)
+                .expect("duration out of range");
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.time.lock().unwrap().now
+    }
+
+    fn now_utc(&self) -> DateTime<Utc> {
+        self.time.lock().unwrap().now_utc
+    }
+}
@@ -0,0 +1,503 @@
+// Copyright 2023 Oxide Computer Company
+//! Recording and replaying requests for contract testing
+//!
+//! A [`Recorder`] attached to a [`ClientTestContext`] (with
+//! [`ClientTestContext::record`]) captures each request made through that
+//! context along with the server's response.  The resulting [`Recording`] can
+//! be saved to a file and later replayed against another build of the server
+//! with [`Recording::replay`], which reports each response that differs from
+//! the recorded one.  [`ReplayRules`] determine which parts of the responses
+//! are expected to vary from run to run (like timestamps and generated ids) and
+//! so are not compared.
+
+use super::ClientTestContext;
+use base64::Engine;
+use camino::Utf8Path;
+use http::request::Parts as RequestParts;
+use http::response::Parts as ResponseParts;
+use http::HeaderMap;
+use hyper::body::to_bytes;
+use hyper::client::HttpConnector;
+use hyper::Body;
+use hyper::Client;
+use hyper::Request;
+use hyper::Response;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// Response headers that are never compared by default, because they vary
+/// from one request to the next or follow from the body (which is compared
+/// separately)
+const DEFAULT_IGNORED_HEADERS: [&str; 3] =
+    ["content-length", "date", crate::HEADER_REQUEST_ID];
+
+/// Response body fields that are never compared by default.  Dropshot includes
+/// the request id in error responses.
+const DEFAULT_IGNORED_FIELDS: [&str; 1] = ["/request_id"];
+
+/// Collects the requests made through a [`ClientTestContext`] and the
+/// responses to them
+///
+/// Clones share the same collection, so a test can keep one clone and attach
+/// another to a client:
+///
+/// ```no_run
+/// # async fn example(mut testctx: dropshot::test_util::TestContext<()>) {
+/// use dropshot::test_util::Recorder;
+///
+/// let recorder = Recorder::new();
+/// testctx.client_testctx.record(&recorder);
+/// // ... make requests with `testctx.client_testctx` ...
+/// recorder.recording().save("tests/recordings/projects.json").unwrap();
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Recorder {
+    exchanges: Arc<Mutex<Vec<RecordedExchange>>>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Recorder::default()
+    }
+
+    /// Returns everything recorded so far.
+    pub fn recording(&self) -> Recording {
+        Recording { exchanges: self.exchanges.lock().unwrap().clone() }
+    }
+
+    /// Sends `request` with `client` and records it along with the response.
+    /// Both bodies are read into memory in order to record them.
+    pub(crate) async fn send(
+        &self,
+        client: &Client<HttpConnector>,
+        request: Request<Body>,
+    ) -> Result<Response<Body>, hyper::Error> {
+        let (parts, body) = request.into_parts();
+        let body = to_bytes(body).await?;
+        let recorded_request = RecordedRequest::new(&parts, &body);
+
+        let response = client
+            .request(Request::from_parts(parts, Body::from(body)))
+            .await?;
+        let (parts, body) = response.into_parts();
+        let body = to_bytes(body).await?;
+        let recorded_response = RecordedResponse::new(&parts, &body);
+
+        self.exchanges.lock().unwrap().push(RecordedExchange {
+            request: recorded_request,
+            response: recorded_response,
+        });
+        Ok(Response::from_parts(parts, Body::from(body)))
+    }
+}
+
+/// Requests made to a server and the responses to them, in the order in which
+/// they were made
+///
+/// This is saved to and loaded from files as JSON.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Recording {
+    pub exchanges: Vec<RecordedExchange>,
+}
+
+/// A request and the response to it
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RecordedExchange {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// path and query string of the request
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: RecordedBody,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: RecordedBody,
+}
+
+/// Body of a recorded request or response, stored in the most readable form
+/// that represents it
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedBody {
+    Empty,
+    /// body that was valid JSON
+    Json(Value),
+    /// body that was valid UTF-8, but not JSON
+    Text(String),
+    /// any other body, base64-encoded
+    Base64(String),
+}
+
+impl RecordedRequest {
+    fn new(parts: &RequestParts, body: &[u8]) -> Self {
+        RecordedRequest {
+            method: parts.method.to_string(),
+            path: parts
+                .uri
+                .path_and_query()
+                .map(|path_and_query| path_and_query.to_string())
+                .unwrap_or_else(|| String::from("/")),
+            headers: record_headers(&parts.headers),
+            body: RecordedBody::new(body),
+        }
+    }
+}
+
+impl RecordedResponse {
+    fn new(parts: &ResponseParts, body: &[u8]) -> Self {
+        RecordedResponse {
+            status: parts.status.as_u16(),
+            headers: record_headers(&parts.headers),
+            body: RecordedBody::new(body),
+        }
+    }
+}
+
+fn record_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect()
+}
+
+impl RecordedBody {
+    fn new(body: &[u8]) -> Self {
+        if body.is_empty() {
+            RecordedBody::Empty
+        } else if let Ok(value) = serde_json::from_slice(body) {
+            RecordedBody::Json(value)
+        } else if let Ok(text) = std::str::from_utf8(body) {
+            RecordedBody::Text(text.to_string())
+        } else {
+            RecordedBody::Base64(
+                base64::engine::general_purpose::STANDARD.encode(body),
+            )
+        }
+    }
+
+    /// Returns the bytes of the body.  A JSON body is re-serialized, so it may
+    /// not be byte-for-byte identical to the original.
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            RecordedBody::Empty => Vec::new(),
+            RecordedBody::Json(value) => value.to_string().into_bytes(),
+            RecordedBody::Text(text) => text.clone().into_bytes(),
+            RecordedBody::Base64(encoded) => {
+                base64::engine::general_purpose::STANDARD
+                    .decode(encoded)
+                    .expect("recorded body is not valid base64")
+            }
+        }
+    }
+}
+
+impl Recording {
+    /// Loads a recording previously saved with [`Recording::save`].
+    pub fn load<P: AsRef<Utf8Path>>(path: P) -> io::Result<Self> {
+        let contents = fs::read(path.as_ref())?;
+        serde_json::from_slice(&contents)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    /// Saves the recording to `path`, replacing any file already there.
+    pub fn save<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<()> {
+        let mut contents = serde_json::to_string_pretty(self)?;
+        contents.push('\n');
+        fs::write(path.as_ref(), contents)
+    }
+
+    /// Sends each recorded request to the server (in order) and compares the
+    /// response to the recorded one, except for the parts ignored by `rules`.
+    /// Returns a description of each response that differs.
+    pub async fn replay(
+        &self,
+        client: &ClientTestContext,
+        rules: &ReplayRules,
+    ) -> Vec<ReplayDifference> {
+        let mut differences = Vec::new();
+        for (index, exchange) in self.exchanges.iter().enumerate() {
+            let recorded = &exchange.request;
+            let mut builder = Request::builder()
+                .method(recorded.method.as_str())
+                .uri(client.url(&recorded.path));
+            for (name, value) in &recorded.headers {
+                // The client supplies these for the new server.
+                if name == "host" || name == "content-length" {
+                    continue;
+                }
+                builder = builder.header(name.as_str(), value.as_str());
+            }
+            let request = builder
+                .body(Body::from(recorded.body.to_bytes()))
+                .expect("recorded request is invalid");
+
+            let problems = match client.client.request(request).await {
+                Ok(response) => {
+                    let (parts, body) = response.into_parts();
+                    match to_bytes(body).await {
+                        Ok(body) => rules.compare(
+                            &exchange.response,
+                            &RecordedResponse::new(&parts, &body),
+                        ),
+                        Err(error) => {
+                            vec![format!("failed to read response: {}", error)]
+                        }
+                    }
+                }
+                Err(error) => vec![format!("request failed: {}", error)],
+            };
+            if !problems.is_empty() {
+                differences.push(ReplayDifference {
+                    index,
+                    method: recorded.method.clone(),
+                    path: recorded.path.clone(),
+                    problems,
+                });
+            }
+        }
+        differences
+    }
+}
+
+/// Describes a replayed request whose response differed from the recorded one
+#[derive(Debug)]
+pub struct ReplayDifference {
+    /// position of the request in the recording
+    pub index: usize,
+    pub method: String,
+    pub path: String,
+    /// each way in which the response differed
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for ReplayDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "request {} ({} {}): {}",
+            self.index,
+            self.method,
+            self.path,
+            self.problems.join("; ")
+        )
+    }
+}
+
+/// Determines which parts of a response are compared when replaying a
+/// [`Recording`]
+///
+/// By default, everything is compared except the `Content-Length`, `Date`,
+/// and `x-request-id` headers and the `request_id` field of error bodies.
+///
+/// Fields of JSON bodies are identified by JSON pointers (RFC 6901), in which
+/// a `*` segment matches any object property or array element.  For example,
+/// `/items/*/time_created` ignores the `time_created` field of every element
+/// of `items`.  Ignoring a field ignores everything within it.
+#[derive(Clone, Debug)]
+pub struct ReplayRules {
+    ignored_headers: Vec<String>,
+    ignored_fields: Vec<Vec<String>>,
+}
+
+impl Default for ReplayRules {
+    fn default() -> Self {
+        let rules = ReplayRules {
+            ignored_headers: Vec::new(),
+            ignored_fields: Vec::new(),
+        };
+        let rules = DEFAULT_IGNORED_HEADERS
+            .iter()
+            .fold(rules, |rules, name| rules.ignore_header(name));
+        DEFAULT_IGNORED_FIELDS
+            .iter()
+            .fold(rules, |rules, pointer| rules.ignore_field(pointer))
+    }
+}
+
+impl ReplayRules {
+    pub fn new() -> Self {
+        ReplayRules::default()
+    }
+
+    /// Ignores the response header `name`.
+    pub fn ignore_header(mut self, name: &str) -> Self {
+        self.ignored_headers.push(name.to_lowercase());
+        self
+    }
+
+    /// Ignores the response body field identified by `pointer`, which may
+    /// contain `*` segments.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pointer` is neither empty nor begins with `/`.
+    pub fn ignore_field(mut self, pointer: &str) -> Self {
+        let segments = if pointer.is_empty() {
+            Vec::new()
+        } else {
+            pointer
+                .strip_prefix('/')
+                .unwrap_or_else(|| {
+                    panic!("JSON pointer must begin with \"/\": {:?}", pointer)
+                })
+                .split('/')
+                .map(String::from)
+                .collect()
+        };
+        self.ignored_fields.push(segments);
+        self
+    }
+
+    fn header_ignored(&self, name: &str) -> bool {
+        self.ignored_headers.iter().any(|ignored| ignored == name)
+    }
+
+    fn field_ignored(&self, path: &[String]) -> bool {
+        self.ignored_fields.iter().any(|pattern| {
+            pattern.len() == path.len()
+                && pattern.iter().zip(path).all(|(pattern, segment)| {
+                    pattern == "*" || pattern == segment
+                })
+        })
+    }
+
+    /// Returns a description of each way in which `actual` differs from
+    /// `expected`.
+    fn compare(
+        &self,
+        expected: &RecordedResponse,
+        actual: &RecordedResponse,
+    ) -> Vec<String> {
+        let mut problems = Vec::new();
+        if expected.status != actual.status {
+            problems.push(format!(
+                "status: expected {}, found {}",
+                expected.status, actual.status
+            ));
+        }
+
+        let headers = |response: &RecordedResponse| {
+            response
+                .headers
+                .iter()
+                .filter(|(name, _)| !self.header_ignored(name))
+                .cloned()
+                .collect::<BTreeSet<_>>()
+        };
+        let expected_headers = headers(expected);
+        let actual_headers = headers(actual);
+        for (name, value) in expected_headers.difference(&actual_headers) {
+            problems.push(format!("header {}: missing {:?}", name, value));
+        }
+        for (name, value) in actual_headers.difference(&expected_headers) {
+            problems.push(format!("header {}: unexpected {:?}", name, value));
+        }
+
+        match (&expected.body, &actual.body) {
+            (RecordedBody::Json(expected), RecordedBody::Json(actual)) => {
+                self.compare_json(
+                    expected,
+                    actual,
+                    &mut Vec::new(),
+                    &mut problems,
+                );
+            }
+            (expected, actual) if expected != actual => {
+                problems.push(format!(
+                    "body: expected {:?}, found {:?}",
+                    expected, actual
+                ));
+            }
+            _ => (),
+        }
+        problems
+    }
+
+    fn compare_json(
+        &self,
+        expected: &Value,
+        actual: &Value,
+        path: &mut Vec<String>,
+        problems: &mut Vec<String>,
+    ) {
+        if self.field_ignored(path) {
+            return;
+        }
+        let location = |path: &[String]| {
+            path.iter().fold(String::from("body"), |location, segment| {
+                location + "/" + segment
+            })
+        };
+        match (expected, actual) {
+            (Value::Object(expected), Value::Object(actual)) => {
+                let keys = expected.keys().chain(actual.keys());
+                for key in keys.collect::<BTreeSet<_>>() {
+                    path.push(key.replace('~', "~0").replace('/', "~1"));
+                    match (expected.get(key), actual.get(key)) {
+                        (Some(expected), Some(actual)) => {
+                            self.compare_json(expected, actual, path, problems)
+                        }
+                        (Some(expected), None) if !self.field_ignored(path) => {
+                            problems.push(format!(
+                                "{}: missing (expected {})",
+                                location(path.as_slice()),
+                                expected
+                            ));
+                        }
+                        (None, Some(actual)) if !self.field_ignored(path) => {
+                            problems.push(format!(
+                                "{}: unexpected {}",
+                                location(path.as_slice()),
+                                actual
+                            ));
+                        }
+                        _ => (),
+                    }
+                    path.pop();
+                }
+            }
+            (Value::Array(expected), Value::Array(actual))
+                if expected.len() == actual.len() =>
+            {
+                for (i, (expected, actual)) in
+                    expected.iter().zip(actual).enumerate()
+                {
+                    path.push(i.to_string());
+                    self.compare_json(expected, actual, path, problems);
+                    path.pop();
+                }
+            }
+            (expected, actual) if expected != actual => {
+                problems.push(format!(
+                    "{}: expected {}, found {}",
+                    location(path.as_slice()),
+                    expected,
+                    actual
+                ));
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,877 @@
+// Copyright 2023 Oxide Computer Company
+//! Generation of requests from the OpenAPI description of an API
+//!
+//! [`RequestGenerator`] walks the OpenAPI document that Dropshot produces for
+//! an [`ApiDescription`] and generates requests for each endpoint from the
+//! schemas of its parameters and body.  Most of the requests are valid
+//! according to those schemas.  The rest deliberately violate them in one
+//! respect each (e.g., an integer one past its maximum or a body missing a
+//! required property).  [`RequestGenerator::check`] sends these requests to a
+//! running server and reports each one that wasn't handled as described.
+//!
+//! Generation is deterministic for a given seed, so failures are reproducible.
+
+use super::ClientTestContext;
+use crate::api_description::ApiDescription;
+use crate::server::ServerContext;
+use crate::websocket::WEBSOCKET_EXTENSION;
+use crate::CONTENT_TYPE_JSON;
+use crate::CONTENT_TYPE_OCTET_STREAM;
+use crate::CONTENT_TYPE_URL_ENCODED;
+use http::Method;
+use hyper::body::to_bytes;
+use hyper::Body;
+use hyper::Request;
+use hyper::StatusCode;
+use percent_encoding::utf8_percent_encode;
+use percent_encoding::NON_ALPHANUMERIC;
+use serde_json::Map;
+use serde_json::Value;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Number of valid requests generated for each endpoint by default
+const DEFAULT_CASES_PER_ENDPOINT: usize = 16;
+
+/// Beyond this depth, generated values include only what their schemas require
+/// so that recursive schemas produce finite values.
+const MAX_DEPTH: usize = 6;
+
+/// Generates requests for the endpoints of an API from their descriptions and
+/// checks that a server handles them as described.
+///
+/// ```no_run
+/// # async fn example(api: dropshot::ApiDescription<()>) {
+/// use dropshot::test_util::RequestGenerator;
+/// use dropshot::test_util::TestContext;
+///
+/// let generator = RequestGenerator::new(&api);
+/// let log = slog::Logger::root(slog::Discard, slog::o!());
+/// let testctx =
+///     TestContext::new(api, (), &Default::default(), None, log);
+/// let violations = generator.check(&testctx.client_testctx).await;
+/// assert!(violations.is_empty(), "{:#?}", violations);
+/// testctx.teardown().await;
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RequestGenerator {
+    spec: Value,
+    seed: u64,
+    cases_per_endpoint: usize,
+}
+
+/// A request produced by a [`RequestGenerator`]
+#[derive(Debug)]
+pub struct GeneratedRequest {
+    /// operation id of the endpoint for which the request was generated
+    pub operation_id: String,
+    pub method: Method,
+    pub path_and_query: String,
+    pub headers: Vec<(String, String)>,
+    pub content_type: Option<String>,
+    pub body: Option<String>,
+    /// If this request deliberately violates the endpoint's description, a
+    /// description of how it does so
+    pub invalid_because: Option<String>,
+    /// statuses documented for the endpoint
+    documented: DocumentedStatuses,
+}
+
+/// Describes a generated request that wasn't handled as described
+#[derive(Debug)]
+pub struct ContractViolation {
+    pub request: GeneratedRequest,
+    /// status of the response, if one was received
+    pub status: Option<StatusCode>,
+    pub problem: String,
+}
+
+impl fmt::Display for ContractViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} (operation \"{}\"): {}",
+            self.request.method,
+            self.request.path_and_query,
+            self.request.operation_id,
+            self.problem
+        )?;
+        if let Some(reason) = &self.request.invalid_because {
+            write!(f, " (request was invalid: {})", reason)?;
+        }
+        if let Some(body) = &self.request.body {
+            write!(f, "; request body: {}", body)?;
+        }
+        Ok(())
+    }
+}
+
+impl RequestGenerator {
+    pub fn new<C: ServerContext>(api: &ApiDescription<C>) -> Self {
+        let spec = api
+            .openapi("generated requests", "0.0.0")
+            .json()
+            .expect("failed to generate OpenAPI document");
+        RequestGenerator {
+            spec,
+            seed: 0,
+            cases_per_endpoint: DEFAULT_CASES_PER_ENDPOINT,
+        }
+    }
+
+    /// Sets the seed from which requests are generated.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the number of valid requests generated for each endpoint.  Each
+    /// endpoint also gets one invalid request for each way in which its
+    /// parameters or body can be made invalid.
+    pub fn cases_per_endpoint(mut self, cases_per_endpoint: usize) -> Self {
+        self.cases_per_endpoint = cases_per_endpoint;
+        self
+    }
+
+    /// Generates requests for every endpoint in the API.
+    pub fn generate(&self) -> Vec<GeneratedRequest> {
+        let mut requests = Vec::new();
+        let paths = match self.spec.get("paths").and_then(Value::as_object) {
+            Some(paths) => paths,
+            None => return requests,
+        };
+        for (path, path_item) in paths {
+            let operations = match path_item.as_object() {
+                Some(operations) => operations,
+                None => continue,
+            };
+            for (method, operation) in operations {
+                let method = match method.to_uppercase().parse::<Method>() {
+                    Ok(method) => method,
+                    Err(_) => continue,
+                };
+                // Websocket endpoints can't be exercised with plain requests.
+                if operation.get(WEBSOCKET_EXTENSION).is_some() {
+                    continue;
+                }
+                self.generate_operation(&mut requests, path, method, operation);
+            }
+        }
+        requests
+    }
+
+    /// Sends every generated request to the server and returns those that
+    /// weren't handled as described.
+    ///
+    /// A valid request must produce one of the endpoint's documented success
+    /// statuses or a client error (which Dropshot documents for all
+    /// endpoints).  An invalid request must produce a client error.  In
+    /// either case, a server error or a failure to produce any response (as
+    /// happens when the handler panics) is a violation.  Dropshot documents
+    /// server errors for all endpoints, but they indicate bugs in the handler
+    /// rather than problems with the request.
+    pub async fn check(
+        &self,
+        client: &ClientTestContext,
+    ) -> Vec<ContractViolation> {
+        let mut violations = Vec::new();
+        for request in self.generate() {
+            let mut builder = Request::builder()
+                .method(request.method.clone())
+                .uri(client.url(&request.path_and_query));
+            for (name, value) in &request.headers {
+                builder = builder.header(name.as_str(), value.as_str());
+            }
+            if let Some(content_type) = &request.content_type {
+                builder =
+                    builder.header(http::header::CONTENT_TYPE, content_type);
+            }
+            let body = match &request.body {
+                Some(body) => Body::from(body.clone()),
+                None => Body::empty(),
+            };
+            let hyper_request =
+                builder.body(body).expect("generated an invalid request");
+
+            let status = match client.client.request(hyper_request).await {
+                Ok(response) => {
+                    let status = response.status();
+                    match to_bytes(response.into_body()).await {
+                        Ok(_) => Ok(status),
+                        Err(error) => Err(format!(
+                            "failed to read response body: {}",
+                            error
+                        )),
+                    }
+                }
+                Err(error) => Err(format!("request failed: {}", error)),
+            };
+
+            let (status, problem) = match status {
+                Ok(status) => (Some(status), request.check_status(status)),
+                Err(problem) => (None, Some(problem)),
+            };
+            if let Some(problem) = problem {
+                violations.push(ContractViolation { request, status, problem });
+            }
+        }
+        violations
+    }
+
+    fn generate_operation(
+        &self,
+        requests: &mut Vec<GeneratedRequest>,
+        path: &str,
+        method: Method,
+        operation: &Value,
+    ) {
+        let operation_id = operation
+            .get("operationId")
+            .and_then(Value::as_str)
+            .unwrap_or(path)
+            .to_string();
+        let mut gen = ValueGenerator {
+            spec: &self.spec,
+            rng: Rng::new(self.seed ^ fnv1a(operation_id.as_bytes())),
+        };
+
+        let parameters: Vec<&Value> = operation
+            .get("parameters")
+            .and_then(Value::as_array)
+            .map(|parameters| {
+                parameters.iter().map(|p| gen.resolve(p)).collect()
+            })
+            .unwrap_or_default();
+        let body = operation
+            .get("requestBody")
+            .map(|body| gen.resolve(body))
+            .and_then(|body| body.get("content"))
+            .and_then(Value::as_object)
+            .and_then(|content| content.iter().next())
+            .map(|(content_type, media)| {
+                (content_type.clone(), media.get("schema"))
+            });
+        let documented = DocumentedStatuses::new(operation);
+
+        let base = |gen: &mut ValueGenerator<'_>| {
+            let values: Vec<Option<Value>> = parameters
+                .iter()
+                .map(|parameter| gen.parameter_value(parameter))
+                .collect();
+            let body_value = body.as_ref().map(|(content_type, schema)| {
+                gen.body_value(content_type, *schema)
+            });
+            (values, body_value)
+        };
+
+        for _ in 0..self.cases_per_endpoint {
+            let (values, body_value) = base(&mut gen);
+            requests.push(build_request(
+                &operation_id,
+                &method,
+                path,
+                &parameters,
+                &values,
+                body.as_ref().map(|(content_type, _)| content_type.as_str()),
+                body_value.map(|value| render_body(&body, &value)),
+                None,
+                &documented,
+            ));
+        }
+
+        // Generate one invalid request for each parameter that can be made
+        // invalid, leaving everything else valid.
+        for (i, parameter) in parameters.iter().enumerate() {
+            let schema = match parameter.get("schema") {
+                Some(schema) => schema,
+                None => continue,
+            };
+            let (invalid, reason) = match gen.invalid_scalar(schema) {
+                Some(invalid) => invalid,
+                None => continue,
+            };
+            let (mut values, body_value) = base(&mut gen);
+            values[i] = Some(invalid);
+            let name =
+                parameter.get("name").and_then(Value::as_str).unwrap_or("");
+            requests.push(build_request(
+                &operation_id,
+                &method,
+                path,
+                &parameters,
+                &values,
+                body.as_ref().map(|(content_type, _)| content_type.as_str()),
+                body_value.map(|value| render_body(&body, &value)),
+                Some(format!("parameter \"{}\" {}", name, reason)),
+                &documented,
+            ));
+        }
+
+        // Generate invalid bodies.  We only know how to do this for JSON.
+        if let Some((content_type, schema)) = &body {
+            if content_type != CONTENT_TYPE_JSON {
+                return;
+            }
+            let (values, body_value) = base(&mut gen);
+            let mut invalid_bodies = vec![(
+                String::from("{"),
+                String::from("body is not valid JSON"),
+            )];
+            if let (Some(schema), Some(Value::Object(object))) =
+                (schema, &body_value)
+            {
+                let schema = gen.resolve(schema);
+                let required = schema
+                    .get("required")
+                    .and_then(Value::as_array)
+                    .and_then(|required| required.first())
+                    .and_then(Value::as_str);
+                if let Some(required) = required {
+                    let mut object = object.clone();
+                    object.remove(required);
+                    invalid_bodies.push((
+                        Value::Object(object).to_string(),
+                        format!("body is missing property \"{}\"", required),
+                    ));
+                }
+            }
+            for (invalid_body, reason) in invalid_bodies {
+                requests.push(build_request(
+                    &operation_id,
+                    &method,
+                    path,
+                    &parameters,
+                    &values,
+                    Some(content_type.as_str()),
+                    Some(invalid_body),
+                    Some(reason),
+                    &documented,
+                ));
+            }
+        }
+    }
+}
+
+impl GeneratedRequest {
+    /// Returns a description of the problem if `status` isn't appropriate for
+    /// this request.
+    fn check_status(&self, status: StatusCode) -> Option<String> {
+        if status.is_server_error() {
+            return Some(format!("server error {}", status));
+        }
+        if self.invalid_because.is_some() {
+            if status.is_client_error() {
+                return None;
+            }
+            return Some(format!(
+                "invalid request produced status {} rather than a client error",
+                status
+            ));
+        }
+        if status.is_client_error() || self.documented.includes(status) {
+            return None;
+        }
+        Some(format!("undocumented status {}", status))
+    }
+}
+
+/// Statuses documented for an endpoint's successful responses
+#[derive(Clone, Debug)]
+struct DocumentedStatuses {
+    /// the endpoint documents a default response, which may have any status
+    any: bool,
+    codes: Vec<StatusCode>,
+}
+
+impl DocumentedStatuses {
+    fn new(operation: &Value) -> Self {
+        let responses = operation
+            .get("responses")
+            .and_then(Value::as_object)
+            .cloned()
+            .unwrap_or_default();
+        DocumentedStatuses {
+            any: responses.contains_key("default"),
+            codes: responses
+                .keys()
+                .filter_map(|key| key.parse::<u16>().ok())
+                .filter_map(|code| StatusCode::from_u16(code).ok())
+                .collect(),
+        }
+    }
+
+    fn includes(&self, status: StatusCode) -> bool {
+        self.any || self.codes.contains(&status)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_request(
+    operation_id: &str,
+    method: &Method,
+    path: &str,
+    parameters: &[&Value],
+    values: &[Option<Value>],
+    content_type: Option<&str>,
+    body: Option<String>,
+    invalid_because: Option<String>,
+    documented: &DocumentedStatuses,
+) -> GeneratedRequest {
+    let mut path = path.to_string();
+    let mut query = form_urlencoded::Serializer::new(String::new());
+    let mut headers = Vec::new();
+    for (parameter, value) in parameters.iter().zip(values) {
+        let value = match value {
+            Some(value) => value,
+            None => continue,
+        };
+        let name = parameter.get("name").and_then(Value::as_str).unwrap_or("");
+        let location = parameter.get("in").and_then(Value::as_str);
+        let rendered = render_scalars(value);
+        match location {
+            Some("path") => {
+                let rendered = rendered.into_iter().next().unwrap_or_default();
+                let encoded = utf8_percent_encode(&rendered, NON_ALPHANUMERIC)
+                    .to_string();
+                path = path.replace(&format!("{{{}}}", name), &encoded);
+            }
+            Some("query") => {
+                for item in rendered {
+                    query.append_pair(name, &item);
+                }
+            }
+            Some("header") => {
+                for item in rendered {
+                    headers.push((name.to_string(), item));
+                }
+            }
+            _ => (),
+        }
+    }
+    let query = query.finish();
+    let path_and_query =
+        if query.is_empty() { path } else { format!("{}?{}", path, query) };
+
+    GeneratedRequest {
+        operation_id: operation_id.to_string(),
+        method: method.clone(),
+        path_and_query,
+        headers,
+        content_type: body
+            .as_ref()
+            .and(content_type)
+            .map(|content_type| content_type.to_string()),
+        body,
+        invalid_because,
+        documented: documented.clone(),
+    }
+}
+
+/// Renders a parameter value as it would appear in a path, query string, or
+/// header.  Arrays produce one item per element.
+fn render_scalars(value: &Value) -> Vec<String> {
+    match value {
+        Value::Null => vec![],
+        Value::String(s) => vec![s.clone()],
+        Value::Array(items) => items.iter().flat_map(render_scalars).collect(),
+        other => vec![other.to_string()],
+    }
+}
+
+fn render_body(
+    body: &Option<(String, Option<&Value>)>,
+    value: &Value,
+) -> String {
+    let content_type =
+        body.as_ref().map(|(content_type, _)| content_type.as_str());
+    match (content_type, value) {
+        (Some(CONTENT_TYPE_URL_ENCODED), Value::Object(object)) => {
+            let mut serializer =
+                form_urlencoded::Serializer::new(String::new());
+            for (key, value) in object {
+                for item in render_scalars(value) {
+                    serializer.append_pair(key, &item);
+                }
+            }
+            serializer.finish()
+        }
+        (Some(CONTENT_TYPE_JSON), value) => value.to_string(),
+        (_, Value::String(s)) => s.clone(),
+        (_, value) => value.to_string(),
+    }
+}
+
+/// Generates values from JSON schemas
+struct ValueGenerator<'a> {
+    spec: &'a Value,
+    rng: Rng,
+}
+
+impl<'a> ValueGenerator<'a> {
+    /// Follows `$ref`s within the OpenAPI document.
+    fn resolve<'b>(&self, mut schema: &'b Value) -> &'b Value
+    where
+        'a: 'b,
+    {
+        let spec: &'a Value = self.spec;
+        while let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            match reference
+                .strip_prefix('#')
+                .and_then(|pointer| spec.pointer(pointer))
+            {
+                Some(target) => schema = target,
+                None => break,
+            }
+        }
+        schema
+    }
+
+    fn parameter_value(&mut self, parameter: &Value) -> Option<Value> {
+        let required =
+            parameter.get("required").and_then(Value::as_bool).unwrap_or(false);
+        if !required && self.rng.bool() {
+            return None;
+        }
+        let schema = parameter.get("schema")?;
+        Some(self.value(schema, 0))
+    }
+
+    fn body_value(
+        &mut self,
+        content_type: &str,
+        schema: Option<&Value>,
+    ) -> Value {
+        match schema {
+            Some(schema) if content_type != CONTENT_TYPE_OCTET_STREAM => {
+                self.value(schema, 0)
+            }
+            _ => {
+                let len = self.rng.below(64) as usize;
+                Value::String(self.alphanumeric(len))
+            }
+        }
+    }
+
+    fn value(&mut self, schema: &Value, depth: usize) -> Value {
+        let schema = self.resolve(schema);
+        if schema.get("nullable").and_then(Value::as_bool) == Some(true)
+            && self.rng.below(4) == 0
+        {
+            return Value::Null;
+        }
+        if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+            if !values.is_empty() {
+                return values[self.rng.below(values.len() as u64) as usize]
+                    .clone();
+            }
+        }
+        if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+            let mut merged: Option<Value> = None;
+            for subschema in all {
+                let value = self.value(subschema, depth);
+                merged = Some(match (merged, value) {
+                    (Some(Value::Object(mut a)), Value::Object(b)) => {
+                        a.extend(b);
+                        Value::Object(a)
+                    }
+                    (_, value) => value,
+                });
+            }
+            return merged.unwrap_or(Value::Null);
+        }
+        for key in &["oneOf", "anyOf"] {
+            if let Some(choices) = schema.get(*key).and_then(Value::as_array) {
+                if !choices.is_empty() {
+                    let choice = self.rng.below(choices.len() as u64) as usize;
+                    return self.value(&choices[choice], depth);
+                }
+            }
+        }
+
+        match schema.get("type").and_then(Value::as_str) {
+            Some("boolean") => Value::Bool(self.rng.bool()),
+            Some("integer") => {
+                let (lo, hi) = integer_bounds(schema);
+                // Favor the bounds, which are most likely to expose bugs, and
+                // otherwise stick to modest values.
+                let start = lo.max(-1000).min(hi);
+                let end = hi.min(1000).max(start);
+                let value = match self.rng.below(4) {
+                    0 => lo,
+                    1 => hi,
+                    _ => self.rng.range(start, end),
+                };
+                integer_value(value).unwrap_or(Value::Null)
+            }
+            Some("number") => {
+                let lo = schema
+                    .get("minimum")
+                    .and_then(Value::as_f64)
+                    .unwrap_or(-1000.0);
+                let hi = schema
+                    .get("maximum")
+                    .and_then(Value::as_f64)
+                    .unwrap_or(1000.0);
+                let fraction = self.rng.below(1001) as f64 / 1000.0;
+                serde_json::Number::from_f64(lo + (hi - lo) * fraction)
+                    .map(Value::Number)
+                    .unwrap_or(Value::Null)
+            }
+            Some("string") => Value::String(self.string(schema)),
+            Some("array") => {
+                let min =
+                    schema.get("minItems").and_then(Value::as_u64).unwrap_or(0);
+                let max = schema
+                    .get("maxItems")
+                    .and_then(Value::as_u64)
+                    .unwrap_or(u64::MAX)
+                    .min(min.saturating_add(3))
+                    .max(min);
+                let len = if depth >= MAX_DEPTH {
+                    min
+                } else {
+                    min + self.rng.below(max - min + 1)
+                };
+                match schema.get("items") {
+                    Some(items) => Value::Array(
+                        (0..len)
+                            .map(|_| self.value(items, depth + 1))
+                            .collect(),
+                    ),
+                    None => Value::Array(vec![]),
+                }
+            }
+            Some("object") | None => self.object(schema, depth),
+            Some(_) => Value::Null,
+        }
+    }
+
+    fn object(&mut self, schema: &Value, depth: usize) -> Value {
+        let properties =
+            match schema.get("properties").and_then(Value::as_object) {
+                Some(properties) => properties,
+                None => {
+                    if schema.get("type").is_none() {
+                        // This schema permits any value.
+                        return Value::Null;
+                    }
+                    return Value::Object(Map::new());
+                }
+            };
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|required| required.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        let mut object = Map::new();
+        for (name, property) in properties {
+            let is_required = required.contains(&name.as_str());
+            if is_required || (depth < MAX_DEPTH && self.rng.bool()) {
+                object.insert(name.clone(), self.value(property, depth + 1));
+            }
+        }
+        Value::Object(object)
+    }
+
+    fn string(&mut self, schema: &Value) -> String {
+        let rng = &mut self.rng;
+        match schema.get("format").and_then(Value::as_str) {
+            Some("uuid") => {
+                let hex = format!("{:016x}{:016x}", rng.next(), rng.next());
+                return format!(
+                    "{}-{}-{}-{}-{}",
+                    &hex[0..8],
+                    &hex[8..12],
+                    &hex[12..16],
+                    &hex[16..20],
+                    &hex[20..32]
+                );
+            }
+            Some("date-time") => {
+                return format!(
+                    "2023-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+                    1 + rng.below(12),
+                    1 + rng.below(28),
+                    rng.below(24),
+                    rng.below(60),
+                    rng.below(60)
+                );
+            }
+            Some("date") => {
+                return format!(
+                    "2023-{:02}-{:02}",
+                    1 + rng.below(12),
+                    1 + rng.below(28)
+                );
+            }
+            Some("ip") | Some("ipv4") => {
+                return format!(
+                    "10.{}.{}.{}",
+                    rng.below(256),
+                    rng.below(256),
+                    rng.below(256)
+                );
+            }
+            Some("ipv6") => return format!("fd00::{:x}", rng.below(0x10000)),
+            _ => (),
+        }
+        let min = schema.get("minLength").and_then(Value::as_u64).unwrap_or(0);
+        let max = schema
+            .get("maxLength")
+            .and_then(Value::as_u64)
+            .unwrap_or(u64::MAX)
+            .min(min.saturating_add(16))
+            .max(min);
+        let len = min + self.rng.below(max - min + 1);
+        self.alphanumeric(len as usize)
+    }
+
+    fn alphanumeric(&mut self, len: usize) -> String {
+        const CHARS: &[u8] =
+            b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+        (0..len)
+            .map(|_| CHARS[self.rng.below(CHARS.len() as u64) as usize] as char)
+            .collect()
+    }
+
+    /// Returns a value that violates `schema`, along with a description of the
+    /// violation, if we know how to construct one.
+    fn invalid_scalar(&mut self, schema: &Value) -> Option<(Value, String)> {
+        let schema = self.resolve(schema);
+        if schema.get("enum").is_some() {
+            return Some((
+                Value::String(String::from("not-a-valid-variant")),
+                String::from("is not one of the allowed values"),
+            ));
+        }
+        match schema.get("type").and_then(Value::as_str)? {
+            "integer" => {
+                let (lo, hi) = integer_bounds(schema);
+                let choices = [
+                    integer_value(lo - 1).map(|v| (v, "is below its minimum")),
+                    integer_value(hi + 1).map(|v| (v, "is above its maximum")),
+                ];
+                let choices: Vec<_> = choices.iter().flatten().collect();
+                let (value, reason) = if choices.is_empty() {
+                    (Value::String(String::from("NaN")), "is not an integer")
+                } else {
+                    choices[self.rng.below(choices.len() as u64) as usize]
+                        .clone()
+                };
+                Some((value, reason.to_string()))
+            }
+            "number" => Some((
+                Value::String(String::from("not-a-number")),
+                String::from("is not a number"),
+            )),
+            "boolean" => Some((
+                Value::String(String::from("maybe")),
+                String::from("is not a boolean"),
+            )),
+            "string" => {
+                if schema.get("format").and_then(Value::as_str).map_or(
+                    false,
+                    |format| {
+                        ["uuid", "date-time", "date", "ip", "ipv4", "ipv6"]
+                            .contains(&format)
+                    },
+                ) {
+                    return Some((
+                        Value::String(String::from("not-well-formed")),
+                        String::from("does not match its format"),
+                    ));
+                }
+                if let Some(max) =
+                    schema.get("maxLength").and_then(Value::as_u64)
+                {
+                    return Some((
+                        Value::String("a".repeat(max as usize + 1)),
+                        String::from("is longer than its maximum length"),
+                    ));
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Returns the range of values allowed by an integer schema.
+fn integer_bounds(schema: &Value) -> (i128, i128) {
+    let (format_lo, format_hi): (i128, i128) =
+        match schema.get("format").and_then(Value::as_str) {
+            Some("int8") => (i8::MIN.into(), i8::MAX.into()),
+            Some("uint8") => (0, u8::MAX.into()),
+            Some("int16") => (i16::MIN.into(), i16::MAX.into()),
+            Some("uint16") => (0, u16::MAX.into()),
+            Some("int32") => (i32::MIN.into(), i32::MAX.into()),
+            Some("uint32") => (0, u32::MAX.into()),
+            Some("uint64") | Some("uint") => (0, u64::MAX.into()),
+            _ => (i64::MIN.into(), i64::MAX.into()),
+        };
+    // Bounds are only honored when they're integers.  `as_f64` would lose
+    // precision for large values.
+    let bound = |key: &str| {
+        schema.get(key).and_then(|v| {
+            v.as_i64().map(i128::from).or_else(|| v.as_u64().map(i128::from))
+        })
+    };
+    let lo = bound("minimum").map_or(format_lo, |min| min.max(format_lo));
+    let hi = bound("maximum").map_or(format_hi, |max| max.min(format_hi));
+    (lo, hi)
+}
+
+/// Converts `value` to JSON, if it can be represented exactly.
+fn integer_value(value: i128) -> Option<Value> {
+    if let Ok(value) = i64::try_from(value) {
+        Some(Value::from(value))
+    } else {
+        u64::try_from(value).ok().map(Value::from)
+    }
+}
+
+/// Pseudorandom number generator (splitmix64).  We use our own so that the
+/// requests generated for a given seed don't change with the version of some
+/// other crate.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a value in `[0, n)`, or 0 if `n` is 0.
+    fn below(&mut self, n: u64) -> u64 {
+        if n == 0 {
+            0
+        } else {
+            self.next() % n
+        }
+    }
+
+    /// Returns a value in `[lo, hi]`.
+    fn range(&mut self, lo: i128, hi: i128) -> i128 {
+        let span = (hi - lo + 1) as u128;
+        lo + (u128::from(self.next()) % span) as i128
+    }
+
+    fn bool(&mut self) -> bool {
+        self.next() & 1 == 1
+    }
+}
+
+/// FNV-1a hash, used to derive a distinct but stable seed for each endpoint
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
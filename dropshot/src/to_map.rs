@@ -52,6 +52,14 @@ macro_rules! ser_err {
 
 }
 
+macro_rules! ser_display {
+    ($i:ident, $t:ty) => {
+        fn $i(self, v: $t) -> Result<Self::Ok, Self::Error> {
+            Ok(Some(v.to_string()))
+        }
+    };
+}
+
 macro_rules! ser_t_err {
     ($i:ident $(, $p:ident : $t:ty )*) => {
         fn $i<T: ?Sized>(self $(, $p: $t)*) -> Result<Self::Ok, Self::Error>
@@ -190,8 +198,9 @@ impl SerializeStruct for MapSerializeStruct {
         T: Serialize,
     {
         let mut serializer = StringSerializer;
-        let value = value.serialize(&mut serializer)?;
-        self.output.insert(key.to_string(), value);
+        if let Some(value) = value.serialize(&mut serializer)? {
+            self.output.insert(key.to_string(), value);
+        }
         Ok(())
     }
 
@@ -200,12 +209,11 @@ impl SerializeStruct for MapSerializeStruct {
     }
 }
 
-/// A trivial `Serializer` used to extract a `String`. One could imagine
-/// extending this to convert other scalars into strings, but for now we'll just
-/// work with strings.
+/// A trivial `Serializer` used to convert a scalar into a `String`.  `None` is
+/// represented as `None`, so that the field is omitted.
 struct StringSerializer;
 impl<'a> Serializer for &'a mut StringSerializer {
-    type Ok = String;
+    type Ok = Option<String>;
     type Error = MapError;
 
     type SerializeSeq = Impossible<Self::Ok, Self::Error>;
@@ -217,32 +225,59 @@ impl<'a> Serializer for &'a mut StringSerializer {
     type SerializeStructVariant = Impossible<Self::Ok, Self::Error>;
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
-        Ok(v.to_string())
+        Ok(Some(v.to_string()))
+    }
+
+    ser_display!(serialize_bool, bool);
+    ser_display!(serialize_i8, i8);
+    ser_display!(serialize_i16, i16);
+    ser_display!(serialize_i32, i32);
+    ser_display!(serialize_i64, i64);
+    ser_display!(serialize_u8, u8);
+    ser_display!(serialize_u16, u16);
+    ser_display!(serialize_u32, u32);
+    ser_display!(serialize_u64, u64);
+    ser_display!(serialize_f32, f32);
+    ser_display!(serialize_f64, f64);
+    ser_display!(serialize_char, char);
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: ?Sized>(
+        self,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: Serialize,
+    {
+        value.serialize(self)
     }
 
-    ser_err!(serialize_bool, _v: bool);
-    ser_err!(serialize_i8, _v: i8);
-    ser_err!(serialize_i16, _v: i16);
-    ser_err!(serialize_i32, _v: i32);
-    ser_err!(serialize_i64, _v: i64);
-    ser_err!(serialize_u8, _v: u8);
-    ser_err!(serialize_u16, _v: u16);
-    ser_err!(serialize_u32, _v: u32);
-    ser_err!(serialize_u64, _v: u64);
-    ser_err!(serialize_f32, _v: f32);
-    ser_err!(serialize_f64, _v: f64);
-    ser_err!(serialize_char, _v: char);
     ser_err!(serialize_bytes, _v: &[u8]);
-    ser_err!(serialize_none);
     ser_err!(serialize_unit);
-    ser_t_err!(serialize_some, _value: &T);
     ser_err!(serialize_unit_struct, _name: &'static str);
-    ser_err!(
-        serialize_unit_variant,
-        _name: &'static str,
-        _variant_index: u32,
-        _variant: &'static str,
-    );
     ser_t_err!(
         serialize_newtype_variant,
         _name: &'static str,
@@ -250,7 +285,6 @@ impl<'a> Serializer for &'a mut StringSerializer {
         _variant: &'static str,
         _value: &T,
     );
-    ser_t_err!(serialize_newtype_struct, _name: &'static str, _value: &T);
 
     fn serialize_seq(
         self,
@@ -349,19 +383,43 @@ mod test {
     }
 
     #[test]
-    fn test_to_map_num() {
+    fn test_to_map_scalars() {
         #[derive(Serialize)]
-        struct Bad {
-            a: String,
-            b: u32,
+        #[serde(rename_all = "lowercase")]
+        enum Kind {
+            Widget,
         }
 
-        let bad = Bad { a: "a".to_string(), b: 0xb };
+        #[derive(Serialize)]
+        struct Id(u64);
 
-        assert_eq!(
-            to_map(&bad),
-            Err(MapError("invalid type: u32".to_string()))
-        );
+        #[derive(Serialize)]
+        struct Scalars {
+            a: u32,
+            b: bool,
+            c: Option<i64>,
+            d: Option<String>,
+            e: Kind,
+            f: Id,
+        }
+
+        let scalars = Scalars {
+            a: 0xb,
+            b: true,
+            c: Some(-1),
+            d: None,
+            e: Kind::Widget,
+            f: Id(7),
+        };
+
+        let map = to_map(&scalars).unwrap();
+
+        assert_eq!(map.get("a"), Some(&"11".to_string()));
+        assert_eq!(map.get("b"), Some(&"true".to_string()));
+        assert_eq!(map.get("c"), Some(&"-1".to_string()));
+        assert_eq!(map.get("d"), None);
+        assert_eq!(map.get("e"), Some(&"widget".to_string()));
+        assert_eq!(map.get("f"), Some(&"7".to_string()));
     }
 
     #[test]
@@ -6,7 +6,8 @@ use std::collections::HashSet;
 
 use indexmap::IndexMap;
 use schemars::schema::{
-    InstanceType, Schema, SchemaObject, SingleOrVec, SubschemaValidation,
+    ArrayValidation, InstanceType, Schema, SchemaObject, SingleOrVec,
+    SubschemaValidation,
 };
 
 /// Returns true iff the input schema is a boolean, floating-point number,
@@ -34,6 +35,52 @@ pub fn type_is_scalar(
     )
 }
 
+/// Returns true iff the input schema is a scalar (see [`type_is_scalar`]) or
+/// an array of scalars.
+pub fn type_is_scalar_or_array(
+    operation_id: &str,
+    name: &str,
+    schema: &Schema,
+    dependencies: &IndexMap<String, Schema>,
+) -> Result<(), String> {
+    match type_resolve(schema, dependencies) {
+        Schema::Object(SchemaObject {
+            instance_type: Some(SingleOrVec::Single(instance_type)),
+            subschemas: None,
+            array: Some(array_validation),
+            object: None,
+            reference: None,
+            ..
+        }) if instance_type.as_ref() == &InstanceType::Array => {
+            match array_validation.as_ref() {
+                ArrayValidation {
+                    items: Some(SingleOrVec::Single(item_schema)),
+                    additional_items: None,
+                    ..
+                } => type_is_scalar(
+                    operation_id,
+                    name,
+                    item_schema,
+                    dependencies,
+                )
+                .map_err(|_| {
+                    format!(
+                        "for endpoint {} the parameter '{}' must be an array \
+                         of scalars",
+                        operation_id, name
+                    )
+                }),
+                _ => Err(format!(
+                    "for endpoint {} the parameter '{}' must be an array of \
+                     scalars",
+                    operation_id, name
+                )),
+            }
+        }
+        _ => type_is_scalar(operation_id, name, schema, dependencies),
+    }
+}
+
 /// Returns true iff the input schema is a string.
 pub fn type_is_string(
     operation_id: &str,
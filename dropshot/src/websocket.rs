@@ -168,6 +168,7 @@ impl ExclusiveExtractor for WebsocketUpgrade {
         ExtractorMetadata {
             parameters: vec![],
             extension_mode: ExtensionMode::Websocket,
+            security: vec![],
         }
     }
 }
@@ -294,6 +295,8 @@ impl JsonSchema for WebsocketUpgrade {
 
 #[cfg(test)]
 mod tests {
+    use crate::api_description::EndpointMetadata;
+    use crate::clock::SystemClock;
     use crate::router::HttpRouter;
     use crate::server::{DropshotState, ServerConfig};
     use crate::{
@@ -323,8 +326,11 @@ mod tests {
                 private: (),
                 config: ServerConfig {
                     request_body_max_bytes: 0,
+                    request_timeout: None,
                     page_max_nitems: NonZeroU32::new(1).unwrap(),
                     page_default_nitems: NonZeroU32::new(1).unwrap(),
+                    external_url: None,
+                    trusted_proxies: Vec::new(),
                 },
                 router: HttpRouter::new(),
                 log: log.clone(),
@@ -333,10 +339,36 @@ mod tests {
                     8080,
                 ),
                 tls_acceptor: None,
+                tls_client_auth: None,
+                http2: true,
+                load_shedder: None,
+                request_log_hook: None,
+                clock: Arc::new(SystemClock),
+                cookie_key: None,
+                page_tokens: Default::default(),
+                in_flight: Default::default(),
+                connections: Default::default(),
             }),
             request: RequestInfo::new(&request, remote_addr),
             path_variables: Default::default(),
+            endpoint: Arc::new(EndpointMetadata {
+                operation_id: String::from("ws_upgrade"),
+                method: http::Method::GET,
+                path: String::from("/"),
+                tags: vec![],
+                deprecated: false,
+                deprecated_since: None,
+                sunset: None,
+                encodings: vec![],
+                strict_query: false,
+                query_parameters: Default::default(),
+                request_body_max_bytes: None,
+                request_timeout: None,
+                response_content_type: None,
+                attributes: Default::default(),
+            }),
             body_content_type: Default::default(),
+            parameter_case: Default::default(),
             request_id: "".to_string(),
             log: log.clone(),
         };
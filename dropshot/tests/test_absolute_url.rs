@@ -0,0 +1,151 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for building absolute URLs with `RequestContext::absolute_url`.
+
+use dropshot::endpoint;
+use dropshot::test_util::read_json;
+use dropshot::test_util::TestContext;
+use dropshot::ApiDescription;
+use dropshot::ConfigDropshot;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::RequestContext;
+use http::Method;
+use http::Request;
+use http::StatusCode;
+use hyper::Body;
+use slog::o;
+
+pub mod common;
+
+#[endpoint {
+    method = GET,
+    path = "/url",
+}]
+async fn api_url(
+    rqctx: RequestContext<usize>,
+) -> Result<HttpResponseOk<String>, HttpError> {
+    Ok(HttpResponseOk(rqctx.absolute_url("/things?page_token=abc")))
+}
+
+fn test_setup(name: &str, config: ConfigDropshot) -> TestContext<usize> {
+    let mut api = ApiDescription::new();
+    api.register(api_url).unwrap();
+    let logctx = common::create_log_context(name);
+    let log = logctx.log.new(o!());
+    TestContext::new(api, 0, &config, Some(logctx), log)
+}
+
+async fn get_url(
+    testctx: &TestContext<usize>,
+    headers: &[(&str, &str)],
+) -> String {
+    let client = &testctx.client_testctx;
+    let mut request =
+        Request::builder().method(Method::GET).uri(client.url("/url"));
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let mut response = client
+        .make_request_with_request(
+            request.body(Body::empty()).unwrap(),
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+    read_json(&mut response).await
+}
+
+#[tokio::test]
+async fn test_absolute_url_default() {
+    let testctx = test_setup("absolute_url_default", Default::default());
+    let addr = testctx.server.local_addr();
+
+    // By default, the URL is based on the Host header, and proxy headers are
+    // ignored.
+    assert_eq!(
+        get_url(&testctx, &[]).await,
+        format!("http://{}/things?page_token=abc", addr)
+    );
+    assert_eq!(
+        get_url(
+            &testctx,
+            &[
+                ("x-forwarded-proto", "https"),
+                ("x-forwarded-host", "api.example.com")
+            ]
+        )
+        .await,
+        format!("http://{}/things?page_token=abc", addr)
+    );
+
+    testctx.teardown().await;
+}
+
+#[tokio::test]
+async fn test_absolute_url_trusted_proxy() {
+    // Requests from the test client come from the loopback address.
+    let config = ConfigDropshot {
+        trusted_proxies: vec!["127.0.0.0/8".parse().unwrap()],
+        ..Default::default()
+    };
+    let testctx = test_setup("absolute_url_trusted_proxy", config);
+
+    assert_eq!(
+        get_url(
+            &testctx,
+            &[
+                ("x-forwarded-proto", "https"),
+                ("x-forwarded-host", "api.example.com, internal.example.com")
+            ]
+        )
+        .await,
+        "https://api.example.com/things?page_token=abc"
+    );
+
+    // The Forwarded header takes precedence.
+    assert_eq!(
+        get_url(
+            &testctx,
+            &[
+                ("forwarded", "for=192.0.2.60;proto=https;host=\"a.example\""),
+                ("x-forwarded-host", "b.example")
+            ]
+        )
+        .await,
+        "https://a.example/things?page_token=abc"
+    );
+
+    // Elements of the Forwarded header that the client wrote are ignored.
+    assert_eq!(
+        get_url(
+            &testctx,
+            &[(
+                "forwarded",
+                "host=evil.example, for=192.0.2.60;proto=https;host=a.example"
+            )]
+        )
+        .await,
+        "https://a.example/things?page_token=abc"
+    );
+
+    testctx.teardown().await;
+}
+
+#[tokio::test]
+async fn test_absolute_url_external_url() {
+    let config = ConfigDropshot {
+        external_url: Some("https://api.example.com/v1/".to_string()),
+        trusted_proxies: vec!["127.0.0.0/8".parse().unwrap()],
+        ..Default::default()
+    };
+    let testctx = test_setup("absolute_url_external_url", config);
+
+    // The configured URL wins over everything else.
+    assert_eq!(
+        get_url(&testctx, &[("x-forwarded-host", "b.example")]).await,
+        "https://api.example.com/v1/things?page_token=abc"
+    );
+
+    testctx.teardown().await;
+}
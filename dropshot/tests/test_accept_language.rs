@@ -0,0 +1,84 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for the Accept-Language extractor.
+
+use dropshot::endpoint;
+use dropshot::test_util::read_json;
+use dropshot::AcceptLanguage;
+use dropshot::ApiDescription;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::RequestContext;
+use http::Method;
+use http::StatusCode;
+use hyper::Body;
+use hyper::Request;
+
+pub mod common;
+
+#[endpoint {
+    method = GET,
+    path = "/greeting",
+}]
+async fn greeting(
+    _rqctx: RequestContext<usize>,
+    accept_language: AcceptLanguage,
+) -> Result<HttpResponseOk<String>, HttpError> {
+    let greeting = match accept_language.negotiate(&["en", "fr", "de"]) {
+        Some("en") => "hello",
+        Some("fr") => "bonjour",
+        Some("de") => "hallo",
+        _ => {
+            return Err(HttpError::for_client_error(
+                None,
+                StatusCode::NOT_ACCEPTABLE,
+                String::from("no supported language"),
+            ))
+        }
+    };
+    Ok(HttpResponseOk(greeting.to_string()))
+}
+
+#[tokio::test]
+async fn test_accept_language() {
+    let mut api = ApiDescription::new();
+    api.register(greeting).unwrap();
+    let testctx = common::test_setup("accept_language", api);
+    let client = &testctx.client_testctx;
+    let request = |accept_language: &str| {
+        Request::builder()
+            .method(Method::GET)
+            .uri(client.url("/greeting"))
+            .header(http::header::ACCEPT_LANGUAGE, accept_language)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let mut response = client
+        .make_request_with_request(
+            request("fr-CH, de;q=0.9, en;q=0.8"),
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+    let greeting: String = read_json(&mut response).await;
+    assert_eq!(greeting, "hallo");
+
+    let mut response = client
+        .make_request_no_body(Method::GET, "/greeting", StatusCode::OK)
+        .await
+        .unwrap();
+    let greeting: String = read_json(&mut response).await;
+    assert_eq!(greeting, "hello");
+
+    let error = client
+        .make_request_with_request(
+            request("ja, en;q=0"),
+            StatusCode::NOT_ACCEPTABLE,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(error.message, "no supported language");
+
+    testctx.teardown().await;
+}
@@ -0,0 +1,98 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for the bearer token extractor.
+
+use dropshot::endpoint;
+use dropshot::test_util::read_json;
+use dropshot::ApiDescription;
+use dropshot::BearerToken;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::RequestContext;
+use http::Method;
+use http::StatusCode;
+use hyper::Body;
+use hyper::Request;
+
+pub mod common;
+
+#[endpoint {
+    method = GET,
+    path = "/whoami",
+}]
+async fn whoami(
+    _rqctx: RequestContext<usize>,
+    token: BearerToken,
+) -> Result<HttpResponseOk<String>, HttpError> {
+    Ok(HttpResponseOk(token.into_inner()))
+}
+
+#[endpoint {
+    method = GET,
+    path = "/public",
+}]
+async fn public(
+    _rqctx: RequestContext<usize>,
+) -> Result<HttpResponseOk<String>, HttpError> {
+    Ok(HttpResponseOk(String::from("hello")))
+}
+
+fn api() -> ApiDescription<usize> {
+    let mut api = ApiDescription::new();
+    api.register(whoami).unwrap();
+    api.register(public).unwrap();
+    api
+}
+
+#[tokio::test]
+async fn test_bearer_token() {
+    let testctx = common::test_setup("bearer_token", api());
+    let client = &testctx.client_testctx;
+    let request = |authorization: &str| {
+        Request::builder()
+            .method(Method::GET)
+            .uri(client.url("/whoami"))
+            .header(http::header::AUTHORIZATION, authorization)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let mut response = client
+        .make_request_with_request(request("Bearer s3cr3t"), StatusCode::OK)
+        .await
+        .unwrap();
+    let token: String = read_json(&mut response).await;
+    assert_eq!(token, "s3cr3t");
+
+    let error = client
+        .make_request_error(Method::GET, "/whoami", StatusCode::UNAUTHORIZED)
+        .await;
+    assert_eq!(error.message, "a bearer token is required");
+
+    let error = client
+        .make_request_with_request(
+            request("Bearer not a token"),
+            StatusCode::BAD_REQUEST,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(error.message, "malformed bearer token");
+
+    testctx.teardown().await;
+}
+
+#[test]
+fn test_bearer_token_openapi() {
+    let api = api();
+    let spec = api.openapi("test", "1.0.0").json().unwrap();
+
+    assert_eq!(
+        spec["paths"]["/whoami"]["get"]["security"],
+        serde_json::json!([{ "bearerAuth": [] }])
+    );
+    assert!(spec["paths"]["/public"]["get"].get("security").is_none());
+
+    let scheme = &spec["components"]["securitySchemes"]["bearerAuth"];
+    assert_eq!(scheme["type"], "http");
+    assert_eq!(scheme["scheme"], "bearer");
+}
@@ -0,0 +1,188 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for CBOR request and response bodies.
+
+#![cfg(feature = "cbor")]
+
+use dropshot::endpoint;
+use dropshot::test_util::read_json;
+use dropshot::test_util::ClientTestContext;
+use dropshot::ApiDescription;
+use dropshot::Cbor;
+use dropshot::CborBody;
+use dropshot::HttpError;
+use dropshot::HttpErrorResponseBody;
+use dropshot::HttpResponseOk;
+use dropshot::JsonOrCbor;
+use dropshot::RequestContext;
+use dropshot::CONTENT_TYPE_CBOR;
+use dropshot::CONTENT_TYPE_JSON;
+use http::header::ACCEPT;
+use http::header::CONTENT_TYPE;
+use http::Method;
+use http::StatusCode;
+use hyper::Body;
+use hyper::Request;
+use hyper::Response;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+pub mod common;
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
+struct Reading {
+    sensor: String,
+    value: i64,
+}
+
+#[endpoint {
+    method = POST,
+    path = "/readings",
+}]
+async fn readings_post(
+    _rqctx: RequestContext<usize>,
+    body: CborBody<Reading>,
+) -> Result<HttpResponseOk<Cbor<Reading>>, HttpError> {
+    let mut reading = body.into_inner();
+    reading.value += 1;
+    Ok(HttpResponseOk(Cbor(reading)))
+}
+
+#[endpoint {
+    method = GET,
+    path = "/readings/latest",
+}]
+async fn readings_latest(
+    rqctx: RequestContext<usize>,
+) -> Result<HttpResponseOk<JsonOrCbor<Reading>>, HttpError> {
+    let reading = Reading { sensor: String::from("t0"), value: 21 };
+    Ok(HttpResponseOk(JsonOrCbor::new(&rqctx.request, reading)))
+}
+
+fn api() -> ApiDescription<usize> {
+    let mut api = ApiDescription::new();
+    api.register(readings_post).unwrap();
+    api.register(readings_latest).unwrap();
+    api
+}
+
+fn to_cbor(reading: &Reading) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::ser::into_writer(reading, &mut bytes).unwrap();
+    bytes
+}
+
+async fn read_cbor(response: &mut Response<Body>) -> Reading {
+    assert_eq!(response.headers()[CONTENT_TYPE], CONTENT_TYPE_CBOR);
+    let bytes = hyper::body::to_bytes(response.body_mut()).await.unwrap();
+    ciborium::de::from_reader(&bytes[..]).unwrap()
+}
+
+async fn post_reading(
+    client: &ClientTestContext,
+    content_type: &str,
+    body: Vec<u8>,
+    expected_status: StatusCode,
+) -> Result<Response<Body>, HttpErrorResponseBody> {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(client.url("/readings"))
+        .header(CONTENT_TYPE, content_type)
+        .body(Body::from(body))
+        .unwrap();
+    client.make_request_with_request(request, expected_status).await
+}
+
+async fn get_latest(
+    client: &ClientTestContext,
+    accept: &str,
+) -> Response<Body> {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(client.url("/readings/latest"))
+        .header(ACCEPT, accept)
+        .body(Body::empty())
+        .unwrap();
+    client.make_request_with_request(request, StatusCode::OK).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_cbor_body() {
+    let testctx = common::test_setup("cbor_body", api());
+    let client = &testctx.client_testctx;
+
+    let reading = Reading { sensor: String::from("t1"), value: 7 };
+    let mut response = post_reading(
+        client,
+        CONTENT_TYPE_CBOR,
+        to_cbor(&reading),
+        StatusCode::OK,
+    )
+    .await
+    .unwrap();
+    let echoed = read_cbor(&mut response).await;
+    assert_eq!(echoed, Reading { sensor: String::from("t1"), value: 8 });
+
+    // Error case: not CBOR at all
+    let error = post_reading(
+        client,
+        CONTENT_TYPE_CBOR,
+        vec![0xff, 0xff],
+        StatusCode::BAD_REQUEST,
+    )
+    .await
+    .unwrap_err();
+    assert!(error.message.starts_with("unable to parse CBOR body:"));
+
+    // Error case: wrong content type
+    let error = post_reading(
+        client,
+        CONTENT_TYPE_JSON,
+        br#"{"sensor":"t1","value":7}"#.to_vec(),
+        StatusCode::BAD_REQUEST,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        error.message,
+        "expected content type \"application/cbor\", got \"application/json\""
+    );
+
+    testctx.teardown().await;
+}
+
+#[tokio::test]
+async fn test_cbor_negotiation() {
+    let testctx = common::test_setup("cbor_negotiation", api());
+    let client = &testctx.client_testctx;
+    let expected = Reading { sensor: String::from("t0"), value: 21 };
+
+    let mut response = get_latest(client, "application/cbor").await;
+    assert_eq!(read_cbor(&mut response).await, expected);
+
+    let mut response = get_latest(client, "*/*").await;
+    let reading: Reading = read_json(&mut response).await;
+    assert_eq!(reading, expected);
+
+    let mut response =
+        get_latest(client, "application/json;q=0.5, application/cbor").await;
+    assert_eq!(read_cbor(&mut response).await, expected);
+
+    testctx.teardown().await;
+}
+
+#[test]
+fn test_cbor_openapi() {
+    let spec = api().openapi("CBOR", "1.0").json().unwrap();
+    let post = &spec["paths"]["/readings"]["post"];
+    assert!(post["requestBody"]["content"]["application/cbor"].is_object());
+    let content = &post["responses"]["200"]["content"];
+    assert!(content["application/cbor"].is_object());
+    assert!(content["application/json"].is_null());
+
+    let get = &spec["paths"]["/readings/latest"]["get"];
+    let content = &get["responses"]["200"]["content"];
+    assert!(content["application/json"].is_object());
+    assert!(content["application/cbor"].is_object());
+}
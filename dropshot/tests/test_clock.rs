@@ -0,0 +1,57 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for replacing the server's clock with
+//! `ApiDescription::clock`.
+
+use chrono::DateTime;
+use chrono::Utc;
+use dropshot::endpoint;
+use dropshot::test_util::read_json;
+use dropshot::test_util::MockClock;
+use dropshot::ApiDescription;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::RequestContext;
+use http::Method;
+use http::StatusCode;
+use std::time::Duration;
+
+pub mod common;
+
+#[endpoint {
+    method = GET,
+    path = "/now",
+}]
+async fn api_now(
+    rqctx: RequestContext<usize>,
+) -> Result<HttpResponseOk<DateTime<Utc>>, HttpError> {
+    Ok(HttpResponseOk(rqctx.clock().now_utc()))
+}
+
+#[tokio::test]
+async fn test_mock_clock() {
+    let start: DateTime<Utc> = "2023-01-01T00:00:00Z".parse().unwrap();
+    let clock = MockClock::new(start);
+    let mut api = ApiDescription::new().clock(clock.clone());
+    api.register(api_now).unwrap();
+    let testctx = common::test_setup("mock_clock", api);
+    let client = &testctx.client_testctx;
+
+    let mut response = client
+        .make_request_no_body(Method::GET, "/now", StatusCode::OK)
+        .await
+        .unwrap();
+    let now: DateTime<Utc> = read_json(&mut response).await;
+    assert_eq!(now, start);
+
+    // Time stands still until the test moves it.
+    clock.advance(Duration::from_secs(90));
+    let mut response = client
+        .make_request_no_body(Method::GET, "/now", StatusCode::OK)
+        .await
+        .unwrap();
+    let now: DateTime<Utc> = read_json(&mut response).await;
+    assert_eq!(now, start + chrono::Duration::seconds(90));
+
+    testctx.teardown().await;
+}
@@ -0,0 +1,104 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for per-endpoint concurrency limits.
+
+use dropshot::endpoint;
+use dropshot::test_util::TestContext;
+use dropshot::ApiDescription;
+use dropshot::ConfigDropshot;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::RequestContext;
+use http::Method;
+use http::StatusCode;
+use slog::o;
+use tokio::sync::Notify;
+use tokio::sync::Semaphore;
+
+pub mod common;
+
+/// Server context that lets the test control when a request to the limited
+/// endpoint completes.
+struct Gate {
+    /// notified when a request has entered the handler
+    entered: Notify,
+    /// handlers wait for a permit before returning
+    release: Semaphore,
+}
+
+#[endpoint {
+    method = GET,
+    path = "/limited",
+    max_concurrency = 1,
+}]
+async fn api_limited(
+    rqctx: RequestContext<Gate>,
+) -> Result<HttpResponseOk<()>, HttpError> {
+    let gate = rqctx.context();
+    gate.entered.notify_one();
+    gate.release.acquire().await.unwrap().forget();
+    Ok(HttpResponseOk(()))
+}
+
+#[endpoint {
+    method = GET,
+    path = "/unlimited",
+}]
+async fn api_unlimited(
+    _rqctx: RequestContext<Gate>,
+) -> Result<HttpResponseOk<()>, HttpError> {
+    Ok(HttpResponseOk(()))
+}
+
+#[tokio::test]
+async fn test_max_concurrency() {
+    let mut api = ApiDescription::new();
+    api.register(api_limited).unwrap();
+    api.register(api_unlimited).unwrap();
+
+    let logctx = common::create_log_context("max_concurrency");
+    let log = logctx.log.new(o!());
+    let gate = Gate { entered: Notify::new(), release: Semaphore::new(0) };
+    let testctx = TestContext::new(
+        api,
+        gate,
+        &ConfigDropshot::default(),
+        Some(logctx),
+        log,
+    );
+    let client = &testctx.client_testctx;
+
+    let gate = testctx.server.app_private();
+
+    // While the first request occupies the only slot for the limited endpoint,
+    // a second request to it is turned away but other endpoints are
+    // unaffected.
+    let first =
+        client.make_request_no_body(Method::GET, "/limited", StatusCode::OK);
+    let others = async {
+        gate.entered.notified().await;
+        let error = client
+            .make_request_error(
+                Method::GET,
+                "/limited",
+                StatusCode::SERVICE_UNAVAILABLE,
+            )
+            .await;
+        assert_eq!(error.message, "Service Unavailable");
+        client
+            .make_request_no_body(Method::GET, "/unlimited", StatusCode::OK)
+            .await
+            .expect("expected success");
+        gate.release.add_permits(2);
+    };
+    let (first, ()) = tokio::join!(first, others);
+    first.expect("expected success");
+
+    // Once the first request completes, the endpoint accepts requests again.
+    client
+        .make_request_no_body(Method::GET, "/limited", StatusCode::OK)
+        .await
+        .expect("expected success");
+
+    testctx.teardown().await;
+}
@@ -155,8 +155,16 @@ fn make_config(
             std::net::IpAddr::from_str(bind_ip_str).unwrap(),
             bind_port,
         ),
+        additional_bind_addresses: Vec::new(),
+        unix_socket: None,
         request_body_max_bytes: 1024,
+        request_timeout_ms: None,
         tls,
+        tls_client_auth: None,
+        http2: Default::default(),
+        load_shedding: None,
+        external_url: None,
+        trusted_proxies: Vec::new(),
     }
 }
 
@@ -404,3 +412,35 @@ async fn test_config_bind_address_https_buffer() {
 
     logctx.cleanup_successful();
 }
+
+#[tokio::test]
+async fn test_config_additional_bind_addresses() {
+    let logctx = create_log_context("config_additional_bind_addresses");
+    let log = logctx.log.new(o!());
+
+    let mut config = make_config("127.0.0.1", 0, None);
+    config.additional_bind_addresses = vec!["127.0.0.1:0".parse().unwrap()];
+    let server = make_server(&config, &log).start();
+    let local_addrs = server.local_addrs().to_vec();
+    assert_eq!(local_addrs.len(), 2);
+    assert_eq!(local_addrs[0], server.local_addr());
+    assert_ne!(local_addrs[0].port(), local_addrs[1].port());
+
+    // The one server is reachable at each of its addresses.
+    let client = hyper::Client::new();
+    for local_addr in &local_addrs {
+        let uri = format!("http://{}/", local_addr).parse().unwrap();
+        client.get(uri).await.unwrap();
+    }
+
+    // Closing the server stops all of its listeners.
+    server.close().await.unwrap();
+    let client = hyper::Client::new();
+    for local_addr in &local_addrs {
+        let uri = format!("http://{}/", local_addr).parse().unwrap();
+        let error = client.get(uri).await.unwrap_err();
+        assert!(error.is_connect());
+    }
+
+    logctx.cleanup_successful();
+}
@@ -0,0 +1,275 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for the `Cookie`, `SignedCookie`, and `EncryptedCookie`
+//! extractors.
+
+use dropshot::endpoint;
+use dropshot::test_util::read_json;
+use dropshot::test_util::ClientTestContext;
+use dropshot::ApiDescription;
+use dropshot::Cookie;
+use dropshot::CookieKey;
+use dropshot::EncryptedCookie;
+use dropshot::HttpError;
+use dropshot::HttpErrorResponseBody;
+use dropshot::HttpResponseHeaders;
+use dropshot::HttpResponseOk;
+use dropshot::HttpResponseUpdatedNoContent;
+use dropshot::RequestContext;
+use dropshot::SignedCookie;
+use http::header::COOKIE;
+use http::header::SET_COOKIE;
+use http::HeaderValue;
+use http::Method;
+use http::StatusCode;
+use hyper::Body;
+use hyper::Request;
+use hyper::Response;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+pub mod common;
+
+const KEY: [u8; 32] = [42; 32];
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct Prefs {
+    theme: String,
+    font_size: Option<u8>,
+}
+
+#[endpoint {
+    method = GET,
+    path = "/prefs",
+}]
+async fn prefs_get(
+    _rqctx: RequestContext<usize>,
+    cookies: Cookie<Prefs>,
+) -> Result<HttpResponseOk<Prefs>, HttpError> {
+    Ok(HttpResponseOk(cookies.into_inner()))
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct Session {
+    session: String,
+}
+
+#[endpoint {
+    method = POST,
+    path = "/login",
+}]
+async fn login(
+    rqctx: RequestContext<usize>,
+) -> Result<HttpResponseHeaders<HttpResponseUpdatedNoContent>, HttpError> {
+    let key = rqctx.server.cookie_key().unwrap();
+    let mut response =
+        HttpResponseHeaders::new_unnamed(HttpResponseUpdatedNoContent());
+    let cookie = format!("session={}; HttpOnly", key.sign("session", "alice"));
+    response
+        .headers_mut()
+        .insert(SET_COOKIE, HeaderValue::from_str(&cookie).unwrap());
+    Ok(response)
+}
+
+#[endpoint {
+    method = GET,
+    path = "/whoami",
+}]
+async fn whoami(
+    _rqctx: RequestContext<usize>,
+    cookies: SignedCookie<Session>,
+) -> Result<HttpResponseOk<String>, HttpError> {
+    Ok(HttpResponseOk(cookies.into_inner().session))
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct Secret {
+    secret: String,
+}
+
+#[endpoint {
+    method = GET,
+    path = "/secret",
+}]
+async fn secret_get(
+    _rqctx: RequestContext<usize>,
+    cookies: EncryptedCookie<Secret>,
+) -> Result<HttpResponseOk<String>, HttpError> {
+    Ok(HttpResponseOk(cookies.into_inner().secret))
+}
+
+fn api() -> ApiDescription<usize> {
+    let mut api =
+        ApiDescription::new().cookie_key(CookieKey::new(KEY).unwrap());
+    api.register(prefs_get).unwrap();
+    api.register(login).unwrap();
+    api.register(whoami).unwrap();
+    api.register(secret_get).unwrap();
+    api
+}
+
+async fn get_with_cookie(
+    client: &ClientTestContext,
+    path: &str,
+    cookie: &str,
+    expected_status: StatusCode,
+) -> Result<Response<Body>, HttpErrorResponseBody> {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(client.url(path))
+        .header(COOKIE, cookie)
+        .body(Body::empty())
+        .unwrap();
+    client.make_request_with_request(request, expected_status).await
+}
+
+#[tokio::test]
+async fn test_cookie() {
+    let testctx = common::test_setup("cookie", api());
+    let client = &testctx.client_testctx;
+
+    let mut response = get_with_cookie(
+        client,
+        "/prefs",
+        "theme=dark; other=1",
+        StatusCode::OK,
+    )
+    .await
+    .unwrap();
+    let prefs: Prefs = read_json(&mut response).await;
+    assert_eq!(prefs.theme, "dark");
+    assert_eq!(prefs.font_size, None);
+
+    // Someone else's malformed cookie doesn't get in the way.
+    let mut response =
+        get_with_cookie(client, "/prefs", "stray; theme=light", StatusCode::OK)
+            .await
+            .unwrap();
+    let prefs: Prefs = read_json(&mut response).await;
+    assert_eq!(prefs.theme, "light");
+
+    let error = client
+        .make_request_error(Method::GET, "/prefs", StatusCode::BAD_REQUEST)
+        .await;
+    assert_eq!(error.message, "unable to parse cookies: missing field `theme`");
+
+    let error = get_with_cookie(
+        client,
+        "/prefs",
+        "theme=dark; font_size=huge",
+        StatusCode::BAD_REQUEST,
+    )
+    .await
+    .unwrap_err();
+    assert!(error.message.starts_with("unable to parse cookies:"));
+
+    testctx.teardown().await;
+}
+
+#[tokio::test]
+async fn test_signed_cookie() {
+    let testctx = common::test_setup("signed_cookie", api());
+    let client = &testctx.client_testctx;
+
+    let response = client
+        .make_request_no_body(Method::POST, "/login", StatusCode::NO_CONTENT)
+        .await
+        .unwrap();
+    let set_cookie = response.headers()[SET_COOKIE].to_str().unwrap();
+    let cookie = set_cookie.split(';').next().unwrap();
+
+    let mut response =
+        get_with_cookie(client, "/whoami", cookie, StatusCode::OK)
+            .await
+            .unwrap();
+    let user: String = read_json(&mut response).await;
+    assert_eq!(user, "alice");
+
+    // Neither an unsigned nor a tampered cookie is accepted.
+    let error = get_with_cookie(
+        client,
+        "/whoami",
+        "session=alice",
+        StatusCode::BAD_REQUEST,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        error.message,
+        "unable to parse cookies: missing field `session`"
+    );
+    let tampered = cookie.replacen("alice", "mallory", 1);
+    get_with_cookie(client, "/whoami", &tampered, StatusCode::BAD_REQUEST)
+        .await
+        .unwrap_err();
+
+    testctx.teardown().await;
+}
+
+#[tokio::test]
+async fn test_signed_cookie_no_key() {
+    let mut api = ApiDescription::new();
+    api.register(whoami).unwrap();
+    let testctx = common::test_setup("signed_cookie_no_key", api);
+
+    let signed = CookieKey::new(KEY).unwrap().sign("session", "alice");
+    let cookie = format!("session={}", signed);
+    get_with_cookie(
+        &testctx.client_testctx,
+        "/whoami",
+        &cookie,
+        StatusCode::INTERNAL_SERVER_ERROR,
+    )
+    .await
+    .unwrap_err();
+
+    testctx.teardown().await;
+}
+
+#[tokio::test]
+async fn test_encrypted_cookie() {
+    let testctx = common::test_setup("encrypted_cookie", api());
+    let client = &testctx.client_testctx;
+    let key = CookieKey::new(KEY).unwrap();
+
+    let encrypted = key.encrypt("secret", "swordfish");
+    let cookie = format!("secret={}", encrypted);
+    let mut response =
+        get_with_cookie(client, "/secret", &cookie, StatusCode::OK)
+            .await
+            .unwrap();
+    let secret: String = read_json(&mut response).await;
+    assert_eq!(secret, "swordfish");
+
+    // Neither a plain nor a signed value is accepted.
+    for value in ["swordfish".to_string(), key.sign("secret", "swordfish")] {
+        let cookie = format!("secret={}", value);
+        let error = get_with_cookie(
+            client,
+            "/secret",
+            &cookie,
+            StatusCode::BAD_REQUEST,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            error.message,
+            "unable to parse cookies: missing field `secret`"
+        );
+    }
+
+    testctx.teardown().await;
+}
+
+#[test]
+fn test_cookie_openapi() {
+    let spec = api().openapi("Cookies", "1.0").json().unwrap();
+    let parameters = &spec["paths"]["/prefs"]["get"]["parameters"];
+    assert_eq!(parameters[0]["in"], "cookie");
+    assert_eq!(parameters[0]["name"], "font_size");
+    assert_eq!(parameters[0]["required"], false);
+    assert_eq!(parameters[1]["in"], "cookie");
+    assert_eq!(parameters[1]["name"], "theme");
+    assert_eq!(parameters[1]["required"], true);
+}
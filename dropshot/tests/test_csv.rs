@@ -0,0 +1,97 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for CSV responses.
+
+#![cfg(feature = "csv")]
+
+use dropshot::endpoint;
+use dropshot::test_util::read_string;
+use dropshot::ApiDescription;
+use dropshot::HttpError;
+use dropshot::HttpResponseCsv;
+use dropshot::RequestContext;
+use dropshot::CONTENT_TYPE_CSV;
+use http::header::CONTENT_TYPE;
+use http::Method;
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+pub mod common;
+
+#[derive(JsonSchema, Serialize)]
+struct Sale {
+    /// where the sale was made
+    region: String,
+    total: u64,
+    note: Option<String>,
+}
+
+#[endpoint {
+    method = GET,
+    path = "/sales",
+}]
+async fn sales_get(
+    _rqctx: RequestContext<usize>,
+) -> Result<HttpResponseCsv<Sale>, HttpError> {
+    Ok(HttpResponseCsv::from_rows(vec![
+        Sale {
+            region: String::from("north, upper"),
+            total: 12,
+            note: Some(String::from("said \"hi\"")),
+        },
+        Sale { region: String::from("south"), total: 7, note: None },
+    ]))
+}
+
+#[endpoint {
+    method = GET,
+    path = "/sales/none",
+}]
+async fn sales_none_get(
+    _rqctx: RequestContext<usize>,
+) -> Result<HttpResponseCsv<Sale>, HttpError> {
+    Ok(HttpResponseCsv::from_rows(Vec::new()))
+}
+
+fn api() -> ApiDescription<usize> {
+    let mut api = ApiDescription::new();
+    api.register(sales_get).unwrap();
+    api.register(sales_none_get).unwrap();
+    api
+}
+
+#[tokio::test]
+async fn test_csv_response() {
+    let testctx = common::test_setup("csv_response", api());
+    let client = &testctx.client_testctx;
+
+    let mut response = client
+        .make_request_no_body(Method::GET, "/sales", StatusCode::OK)
+        .await
+        .unwrap();
+    assert_eq!(response.headers()[CONTENT_TYPE], CONTENT_TYPE_CSV);
+    assert_eq!(
+        read_string(&mut response).await,
+        "region,total,note\n\
+         \"north, upper\",12,\"said \"\"hi\"\"\"\n\
+         south,7,\n"
+    );
+
+    let mut response = client
+        .make_request_no_body(Method::GET, "/sales/none", StatusCode::OK)
+        .await
+        .unwrap();
+    assert_eq!(read_string(&mut response).await, "");
+
+    testctx.teardown().await;
+}
+
+#[test]
+fn test_csv_openapi() {
+    let spec = api().openapi("test", "1.0.0").json().unwrap();
+    let content =
+        &spec["paths"]["/sales"]["get"]["responses"]["200"]["content"];
+    assert!(content.get(CONTENT_TYPE_CSV).is_some());
+    assert!(content.get("application/json").is_none());
+}
@@ -0,0 +1,128 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for extractors defined outside of Dropshot.
+
+use async_trait::async_trait;
+use dropshot::endpoint;
+use dropshot::test_util::read_json;
+use dropshot::ApiDescription;
+use dropshot::ApiEndpointBodyContentType;
+use dropshot::ApiEndpointParameterLocation;
+use dropshot::ExtractorMetadata;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::RequestContext;
+use dropshot::ServerContext;
+use dropshot::SharedExtractor;
+use http::Method;
+use http::StatusCode;
+use hyper::Body;
+use hyper::Request;
+use schemars::JsonSchema;
+
+pub mod common;
+
+/// The user making a request, identified by its "x-user-id" header
+struct AuthenticatedUser {
+    id: String,
+}
+
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct UserHeaders {
+    /// identifies the user making the request
+    #[schemars(rename = "x-user-id")]
+    user_id: String,
+}
+
+#[async_trait]
+impl SharedExtractor for AuthenticatedUser {
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+    ) -> Result<AuthenticatedUser, HttpError> {
+        rqctx
+            .request
+            .headers()
+            .get("x-user-id")
+            .and_then(|value| value.to_str().ok())
+            .map(|id| AuthenticatedUser { id: id.to_string() })
+            .ok_or_else(|| {
+                HttpError::for_client_error(
+                    None,
+                    StatusCode::UNAUTHORIZED,
+                    String::from("missing user"),
+                )
+            })
+    }
+
+    fn metadata(
+        _body_content_type: ApiEndpointBodyContentType,
+    ) -> ExtractorMetadata {
+        ExtractorMetadata::for_parameters::<UserHeaders>(
+            ApiEndpointParameterLocation::Header,
+        )
+    }
+}
+
+#[endpoint {
+    method = GET,
+    path = "/whoami",
+}]
+async fn whoami(
+    _rqctx: RequestContext<usize>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponseOk<String>, HttpError> {
+    Ok(HttpResponseOk(user.id))
+}
+
+fn api() -> ApiDescription<usize> {
+    let mut api = ApiDescription::new();
+    api.register(whoami).unwrap();
+    api
+}
+
+#[tokio::test]
+async fn test_custom_extractor() {
+    let testctx = common::test_setup("custom_extractor", api());
+    let client = &testctx.client_testctx;
+
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(client.url("/whoami"))
+        .header("x-user-id", "alice")
+        .body(Body::empty())
+        .unwrap();
+    let mut response = client
+        .make_request_with_request(request, StatusCode::OK)
+        .await
+        .unwrap();
+    let id: String = read_json(&mut response).await;
+    assert_eq!(id, "alice");
+
+    // Error case: the extractor's error is the response.
+    let error = client
+        .make_request(
+            Method::GET,
+            "/whoami",
+            None as Option<()>,
+            StatusCode::UNAUTHORIZED,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(error.message, "missing user");
+
+    testctx.teardown().await;
+}
+
+#[test]
+fn test_custom_extractor_openapi() {
+    let spec = api().openapi("Custom", "1.0").json().unwrap();
+    let parameter = &spec["paths"]["/whoami"]["get"]["parameters"][0];
+    assert_eq!(parameter["in"], "header");
+    assert_eq!(parameter["name"], "x-user-id");
+    assert_eq!(parameter["required"], true);
+    assert_eq!(
+        parameter["description"],
+        "identifies the user making the request"
+    );
+}
@@ -0,0 +1,91 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for response types defined outside of Dropshot.
+
+use dropshot::endpoint;
+use dropshot::test_util::TEST_HEADER_1;
+use dropshot::ApiDescription;
+use dropshot::ApiEndpointHeader;
+use dropshot::ApiEndpointResponse;
+use dropshot::ApiSchemaGenerator;
+use dropshot::HttpError;
+use dropshot::HttpHandlerResult;
+use dropshot::HttpResponse;
+use dropshot::RequestContext;
+use http::Method;
+use http::StatusCode;
+use hyper::Body;
+
+pub mod common;
+
+/// A job that's been started, with the URL at which to monitor its progress
+/// in the TEST_HEADER_1 header
+struct JobStarted {
+    monitor: String,
+}
+
+impl HttpResponse for JobStarted {
+    fn to_result(self) -> HttpHandlerResult {
+        Ok(hyper::Response::builder()
+            .status(StatusCode::ACCEPTED)
+            .header(TEST_HEADER_1, self.monitor)
+            .body(Body::empty())?)
+    }
+
+    fn response_metadata() -> ApiEndpointResponse {
+        ApiEndpointResponse {
+            success: Some(StatusCode::ACCEPTED),
+            description: Some(String::from("job started")),
+            headers: vec![ApiEndpointHeader {
+                name: String::from(TEST_HEADER_1),
+                description: Some(String::from("where to monitor the job")),
+                schema: ApiSchemaGenerator::Gen {
+                    name: || String::from("String"),
+                    schema: |gen| gen.subschema_for::<String>(),
+                },
+                required: true,
+            }],
+            ..Default::default()
+        }
+    }
+}
+
+#[endpoint {
+    method = POST,
+    path = "/jobs",
+}]
+async fn job_start(
+    _rqctx: RequestContext<usize>,
+) -> Result<JobStarted, HttpError> {
+    Ok(JobStarted { monitor: String::from("/jobs/1") })
+}
+
+fn api() -> ApiDescription<usize> {
+    let mut api = ApiDescription::new();
+    api.register(job_start).unwrap();
+    api
+}
+
+#[tokio::test]
+async fn test_custom_response() {
+    let testctx = common::test_setup("custom_response", api());
+    let client = &testctx.client_testctx;
+
+    let response = client
+        .make_request_no_body(Method::POST, "/jobs", StatusCode::ACCEPTED)
+        .await
+        .unwrap();
+    assert_eq!(response.headers()[TEST_HEADER_1], "/jobs/1");
+
+    testctx.teardown().await;
+}
+
+#[test]
+fn test_custom_response_openapi() {
+    let spec = api().openapi("test", "1.0.0").json().unwrap();
+    let response = &spec["paths"]["/jobs"]["post"]["responses"]["202"];
+    let monitor = &response["headers"][TEST_HEADER_1];
+    assert_eq!(monitor["description"], "where to monitor the job");
+    assert_eq!(monitor["required"], true);
+    assert_eq!(monitor["schema"]["type"], "string");
+}
@@ -16,6 +16,7 @@
 use dropshot::channel;
 use dropshot::endpoint;
 use dropshot::http_response_found;
+use dropshot::http_response_permanent_redirect;
 use dropshot::http_response_see_other;
 use dropshot::http_response_temporary_redirect;
 use dropshot::test_util::object_delete;
@@ -24,14 +25,18 @@ use dropshot::test_util::read_string;
 use dropshot::test_util::TEST_HEADER_1;
 use dropshot::test_util::TEST_HEADER_2;
 use dropshot::ApiDescription;
+use dropshot::FormBody;
+use dropshot::Header;
 use dropshot::HttpError;
 use dropshot::HttpResponseDeleted;
 use dropshot::HttpResponseFound;
 use dropshot::HttpResponseHeaders;
 use dropshot::HttpResponseOk;
+use dropshot::HttpResponsePermanentRedirect;
 use dropshot::HttpResponseSeeOther;
 use dropshot::HttpResponseTemporaryRedirect;
 use dropshot::HttpResponseUpdatedNoContent;
+use dropshot::Multipart;
 use dropshot::Path;
 use dropshot::Query;
 use dropshot::RawRequest;
@@ -48,6 +53,7 @@ use futures::TryStreamExt;
 use http::StatusCode;
 use hyper::Body;
 use hyper::Method;
+use hyper::Request;
 use hyper::Response;
 use schemars::JsonSchema;
 use serde::Deserialize;
@@ -65,22 +71,29 @@ fn demo_api() -> ApiDescription<usize> {
     let mut api = ApiDescription::<usize>::new();
     api.register(demo_handler_args_1).unwrap();
     api.register(demo_handler_args_2query).unwrap();
+    api.register(demo_handler_args_query_repeated).unwrap();
     api.register(demo_handler_args_2json).unwrap();
     api.register(demo_handler_args_2json_nested).unwrap();
     api.register(demo_handler_args_2urlencoded).unwrap();
+    api.register(demo_handler_form_body).unwrap();
     api.register(demo_handler_args_3).unwrap();
     api.register(demo_handler_path_param_string).unwrap();
     api.register(demo_handler_path_param_uuid).unwrap();
     api.register(demo_handler_path_param_u32).unwrap();
     api.register(demo_handler_untyped_body).unwrap();
+    api.register(demo_handler_large_body).unwrap();
     api.register(demo_handler_streaming_body).unwrap();
+    api.register(demo_handler_multipart).unwrap();
     api.register(demo_handler_raw_request).unwrap();
     api.register(demo_handler_delete).unwrap();
+    api.register(demo_handler_delete_body).unwrap();
     api.register(demo_handler_headers).unwrap();
+    api.register(demo_handler_header_params).unwrap();
     api.register(demo_handler_302_bogus).unwrap();
     api.register(demo_handler_302_found).unwrap();
     api.register(demo_handler_303_see_other).unwrap();
     api.register(demo_handler_307_temporary_redirect).unwrap();
+    api.register(demo_handler_308_permanent_redirect).unwrap();
     api.register(demo_handler_websocket).unwrap();
     api.register(demo_handler_request_compat).unwrap();
     api.register(demo_handler_request_addresses).unwrap();
@@ -213,6 +226,92 @@ async fn test_demo2query() {
     testctx.teardown().await;
 }
 
+// The "demo_query_repeated" handler consumes a query string with a repeated
+// parameter and a flattened struct.
+#[tokio::test]
+async fn test_demo_query_repeated() {
+    let api = demo_api();
+    let testctx = common::test_setup("demo_query_repeated", api);
+
+    // Test case: repeated parameter and flattened field
+    let mut response = testctx
+        .client_testctx
+        .make_request(
+            Method::GET,
+            "/testing/demo_query_repeated?id=3&name=foo&id=1",
+            None as Option<()>,
+            StatusCode::OK,
+        )
+        .await
+        .expect("expected success");
+    let json: DemoQueryRepeatedArgs = read_json(&mut response).await;
+    assert_eq!(json.id, vec![3, 1]);
+    assert_eq!(json.filter.name, Some("foo".to_string()));
+
+    // Test case: single value
+    let mut response = testctx
+        .client_testctx
+        .make_request(
+            Method::GET,
+            "/testing/demo_query_repeated?id=7",
+            None as Option<()>,
+            StatusCode::OK,
+        )
+        .await
+        .expect("expected success");
+    let json: DemoQueryRepeatedArgs = read_json(&mut response).await;
+    assert_eq!(json.id, vec![7]);
+    assert_eq!(json.filter.name, None);
+
+    // Test case: no values
+    let mut response = testctx
+        .client_testctx
+        .make_request(
+            Method::GET,
+            "/testing/demo_query_repeated",
+            None as Option<()>,
+            StatusCode::OK,
+        )
+        .await
+        .expect("expected success");
+    let json: DemoQueryRepeatedArgs = read_json(&mut response).await;
+    assert!(json.id.is_empty());
+
+    // Test case: one of the values is bad
+    let error = testctx
+        .client_testctx
+        .make_request(
+            Method::GET,
+            "/testing/demo_query_repeated?id=1&id=foo",
+            None as Option<()>,
+            StatusCode::BAD_REQUEST,
+        )
+        .await
+        .expect_err("expected failure");
+    assert_eq!(
+        error.message,
+        "unable to parse query string: invalid digit found in string"
+    );
+
+    // Test case: flattened field name duplicated
+    let error = testctx
+        .client_testctx
+        .make_request(
+            Method::GET,
+            "/testing/demo_query_repeated?name=foo&name=bar",
+            None as Option<()>,
+            StatusCode::BAD_REQUEST,
+        )
+        .await
+        .expect_err("expected failure");
+    assert_eq!(
+        error.message,
+        "unable to parse query string: duplicate field `name`"
+    );
+
+    testctx.teardown().await;
+}
+
 // The "demo2json" handler consumes only a JSON object.  Here we make sure such
 // handlers work and also exercise various error cases associated with bad JSON
 // handling.
@@ -425,6 +524,56 @@ async fn test_demo2urlencoded() {
     );
 }
 
+// `FormBody` accepts URL-encoded bodies without the endpoint specifying a
+// content type.
+#[tokio::test]
+async fn test_form_body() {
+    let api = demo_api();
+    let testctx = common::test_setup("form_body", api);
+
+    let input = DemoJsonBody { test1: "bar".to_string(), test2: Some(10) };
+    let mut response = testctx
+        .client_testctx
+        .make_request_url_encoded(
+            Method::POST,
+            "/testing/form_body",
+            Some(input),
+            StatusCode::OK,
+        )
+        .await
+        .expect("expected success");
+    let json: DemoJsonBody = read_json(&mut response).await;
+    assert_eq!(json.test1, "bar");
+    assert_eq!(json.test2, Some(10));
+
+    // Error case: wrong content type
+    let input = DemoJsonBody { test1: "qux".to_string(), test2: None };
+    let error = testctx
+        .client_testctx
+        .make_request(
+            Method::POST,
+            "/testing/form_body",
+            Some(input),
+            StatusCode::BAD_REQUEST,
+        )
+        .await
+        .expect_err("expected failure");
+    assert_eq!(
+        error.message,
+        "expected content type \"application/x-www-form-urlencoded\", got \
+         \"application/json\""
+    );
+
+    // The body is documented with the URL-encoded content type.
+    let spec = demo_api().openapi("Demo", "1.0").json().unwrap();
+    let content =
+        &spec["paths"]["/testing/form_body"]["post"]["requestBody"]["content"];
+    assert!(content["application/x-www-form-urlencoded"].is_object());
+    assert!(content["application/json"].is_null());
+
+    testctx.teardown().await;
+}
+
 // The "demo3" handler takes both query arguments and a JSON body.  This test
 // makes sure that both sets of parameters are received by the handler function
 // and at least one error case from each of those sources is exercised.  We
@@ -645,6 +794,46 @@ async fn test_demo_path_param_u32() {
     testctx.teardown().await;
 }
 
+// Test an endpoint that overrides the server's limit on request body size.
+#[tokio::test]
+async fn test_large_body() {
+    let api = demo_api();
+    let testctx = common::test_setup("test_large_body", api);
+    let client = &testctx.client_testctx;
+
+    // Success case: larger than the server's limit, but within the endpoint's.
+    let body = "x".repeat(1500);
+    let mut response = client
+        .make_request(
+            Method::PUT,
+            "/testing/large_body",
+            Some(body),
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+    let json: usize = read_json(&mut response).await;
+    assert_eq!(json, 1500);
+
+    // Error case: larger than the endpoint's limit.
+    let body = "x".repeat(2100);
+    let error = client
+        .make_request(
+            Method::PUT,
+            "/testing/large_body",
+            Some(body),
+            StatusCode::BAD_REQUEST,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.message,
+        "request body exceeded maximum size of 2048 bytes"
+    );
+
+    testctx.teardown().await;
+}
+
 // Test `UntypedBody`.
 #[tokio::test]
 async fn test_untyped_body() {
@@ -727,6 +916,22 @@ async fn test_untyped_body() {
     let json: DemoUntyped = read_json(&mut response).await;
     assert_eq!(json.nbytes, 4);
     assert_eq!(json.as_utf8, Some(String::from("tμv")));
+    assert_eq!(json.content_type, None);
+
+    // Success case: the Content-Type is passed through.
+    let request = Request::builder()
+        .method(Method::PUT)
+        .uri(client.url("/testing/untyped_body"))
+        .header(http::header::CONTENT_TYPE, "application/x-custom")
+        .body(Body::from("{}"))
+        .unwrap();
+    let mut response = client
+        .make_request_with_request(request, StatusCode::OK)
+        .await
+        .unwrap();
+    let json: DemoUntyped = read_json(&mut response).await;
+    assert_eq!(json.nbytes, 2);
+    assert_eq!(json.content_type.as_deref(), Some("application/x-custom"));
 
     testctx.teardown().await;
 }
@@ -782,6 +987,105 @@ async fn test_streaming_body() {
     );
 }
 
+// Test `Multipart`.
+#[tokio::test]
+async fn test_multipart() {
+    let api = demo_api();
+    let testctx = common::test_setup("test_multipart", api);
+    let client = &testctx.client_testctx;
+
+    let multipart_request = |content_type: &str, body: &str| {
+        Request::builder()
+            .method(Method::POST)
+            .uri(client.url("/testing/multipart"))
+            .header(http::header::CONTENT_TYPE, content_type)
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let content_type = "multipart/form-data; boundary=demo";
+
+    // Success case: a field and a file upload
+    let body = "--demo\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\
+        \r\n\
+        hello\r\n\
+        --demo\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        0123456789\r\n\
+        --demo--\r\n";
+    let mut response = client
+        .make_request_with_request(
+            multipart_request(content_type, body),
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+    let json: Vec<DemoMultipartPart> = read_json(&mut response).await;
+    assert_eq!(json.len(), 2);
+    assert_eq!(json[0].name.as_deref(), Some("title"));
+    assert_eq!(json[0].file_name, None);
+    assert_eq!(json[0].nbytes, 5);
+    assert_eq!(json[1].name.as_deref(), Some("file"));
+    assert_eq!(json[1].file_name.as_deref(), Some("a.txt"));
+    assert_eq!(json[1].nbytes, 10);
+
+    // Error case: a part larger than the handler's limit
+    let body = "--demo\r\n\
+        Content-Disposition: form-data; name=\"big\"\r\n\
+        \r\n\
+        0123456789abcdefg\r\n\
+        --demo--\r\n";
+    let error = client
+        .make_request_with_request(
+            multipart_request(content_type, body),
+            StatusCode::PAYLOAD_TOO_LARGE,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.message,
+        "multipart part exceeded maximum size of 16 bytes"
+    );
+
+    // Error case: a body with no closing delimiter
+    let body = "--demo\r\n\r\nhello";
+    let error = client
+        .make_request_with_request(
+            multipart_request(content_type, body),
+            StatusCode::BAD_REQUEST,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.message,
+        "unable to parse multipart body: unexpected end of body"
+    );
+
+    // Error case: wrong content type
+    let error = client
+        .make_request_with_request(
+            multipart_request(CONTENT_TYPE_JSON, "{}"),
+            StatusCode::BAD_REQUEST,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.message,
+        "expected content type \"multipart/form-data\", got \
+         \"application/json\""
+    );
+
+    // The body is documented with the multipart content type.
+    let spec = demo_api().openapi("Demo", "1.0").json().unwrap();
+    let content =
+        &spec["paths"]["/testing/multipart"]["post"]["requestBody"]["content"];
+    assert!(content["multipart/form-data"].is_object());
+
+    testctx.teardown().await;
+}
+
 // Test `RawRequest`.
 #[tokio::test]
 async fn test_raw_request() {
@@ -820,6 +1124,31 @@ async fn test_delete_request() {
     testctx.teardown().await;
 }
 
+// Test delete request with a body.  Request bodies are accepted on any method,
+// not just PUT and POST.
+#[tokio::test]
+async fn test_delete_request_body() {
+    let api = demo_api();
+    let testctx = common::test_setup("test_delete_request_body", api);
+
+    let input = DemoJsonBody { test1: "bar".to_string(), test2: Some(15) };
+    let mut response = testctx
+        .client_testctx
+        .make_request(
+            Method::DELETE,
+            "/testing/delete_body",
+            Some(input),
+            StatusCode::OK,
+        )
+        .await
+        .expect("expected success");
+    let json: DemoJsonBody = read_json(&mut response).await;
+    assert_eq!(json.test1, "bar");
+    assert_eq!(json.test2, Some(15));
+
+    testctx.teardown().await;
+}
+
 // Test response headers
 #[tokio::test]
 async fn test_header_request() {
@@ -853,6 +1182,86 @@ async fn test_header_request() {
     assert_eq!(headers, vec!["hi", "howdy"]);
 }
 
+// Test the `Header` extractor
+#[tokio::test]
+async fn test_header_params() {
+    let api = demo_api();
+    let testctx = common::test_setup("test_header_params", api);
+    let client = &testctx.client_testctx;
+
+    // Error case: the required header is missing.
+    let error = client
+        .make_request_error(
+            Method::GET,
+            "/testing/header_params",
+            StatusCode::BAD_REQUEST,
+        )
+        .await;
+    assert_eq!(
+        error.message,
+        "unable to parse headers: missing field `x-demo-count`"
+    );
+
+    // Error case: the header has the wrong type.
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(client.url("/testing/header_params"))
+        .header("X-Demo-Count", "many")
+        .body(Body::empty())
+        .unwrap();
+    let error = client
+        .make_request_with_request(request, StatusCode::BAD_REQUEST)
+        .await
+        .unwrap_err();
+    assert!(
+        error.message.starts_with("unable to parse headers:"),
+        "{}",
+        error.message
+    );
+
+    // Success case, with and without the optional header.
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(client.url("/testing/header_params"))
+        .header("X-Demo-Count", "3")
+        .body(Body::empty())
+        .unwrap();
+    let mut response = client
+        .make_request_with_request(request, StatusCode::OK)
+        .await
+        .unwrap();
+    let json: DemoHeaders = read_json(&mut response).await;
+    assert_eq!(json.x_demo_count, 3);
+    assert_eq!(json.x_demo_name, None);
+
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(client.url("/testing/header_params"))
+        .header("X-Demo-Count", "3")
+        .header("X-Demo-Name", "tile")
+        .body(Body::empty())
+        .unwrap();
+    let mut response = client
+        .make_request_with_request(request, StatusCode::OK)
+        .await
+        .unwrap();
+    let json: DemoHeaders = read_json(&mut response).await;
+    assert_eq!(json.x_demo_name.as_deref(), Some("tile"));
+
+    // The headers are documented as header parameters.
+    let spec = demo_api().openapi("Demo", "1.0").json().unwrap();
+    let parameters =
+        &spec["paths"]["/testing/header_params"]["get"]["parameters"];
+    assert_eq!(parameters[0]["in"], "header");
+    assert_eq!(parameters[0]["name"], "x-demo-count");
+    assert_eq!(parameters[0]["required"], true);
+    assert_eq!(parameters[1]["in"], "header");
+    assert_eq!(parameters[1]["name"], "x-demo-name");
+    assert_eq!(parameters[1]["required"], false);
+
+    testctx.teardown().await;
+}
+
 // Test 302 "Found" response with an invalid header value
 #[tokio::test]
 async fn test_302_bogus() {
@@ -944,6 +1353,31 @@ async fn test_307_temporary_redirect() {
     assert_eq!(read_string(&mut response).await, "");
 }
 
+// Test 308 "Permanent Redirect" response
+#[tokio::test]
+async fn test_308_permanent_redirect() {
+    let api = demo_api();
+    let testctx = common::test_setup("test_308_permanent_redirect", api);
+    let mut response = testctx
+        .client_testctx
+        .make_request(
+            Method::GET,
+            "/testing/308_permanent_redirect",
+            None as Option<()>,
+            StatusCode::PERMANENT_REDIRECT,
+        )
+        .await
+        .expect("expected success");
+    let headers = response
+        .headers()
+        .get_all(http::header::LOCATION)
+        .iter()
+        .map(|v| v.to_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(headers, vec!["/path4"]);
+    assert_eq!(read_string(&mut response).await, "");
+}
+
 // The "test_demo_websocket" handler upgrades to a websocket and exchanges
 // greetings with the client.
 #[tokio::test]
@@ -1042,6 +1476,28 @@ async fn demo_handler_args_2query(
     http_echo(&query.into_inner())
 }
 
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DemoQueryRepeatedArgs {
+    #[serde(default)]
+    pub id: Vec<u32>,
+    #[serde(flatten)]
+    pub filter: DemoQueryFilter,
+}
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DemoQueryFilter {
+    pub name: Option<String>,
+}
+#[endpoint {
+    method = GET,
+    path = "/testing/demo_query_repeated",
+}]
+async fn demo_handler_args_query_repeated(
+    _rqctx: RequestCtx,
+    query: Query<DemoQueryRepeatedArgs>,
+) -> Result<Response<Body>, HttpError> {
+    http_echo(&query.into_inner())
+}
+
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct DemoJsonBody {
     pub test1: String,
@@ -1085,6 +1541,17 @@ async fn demo_handler_args_2urlencoded(
     http_echo(&body.into_inner())
 }
 
+#[endpoint {
+    method = POST,
+    path = "/testing/form_body",
+}]
+async fn demo_handler_form_body(
+    _rqctx: RequestCtx,
+    body: FormBody<DemoJsonBody>,
+) -> Result<Response<Body>, HttpError> {
+    http_echo(&body.into_inner())
+}
+
 #[derive(Deserialize, Serialize, JsonSchema)]
 pub struct DemoJsonAndQuery {
     pub query: DemoQueryArgs,
@@ -1156,6 +1623,7 @@ async fn demo_handler_path_param_u32(
 pub struct DemoUntyped {
     pub nbytes: usize,
     pub as_utf8: Option<String>,
+    pub content_type: Option<String>,
 }
 #[derive(Deserialize, JsonSchema)]
 pub struct DemoUntypedQuery {
@@ -1176,8 +1644,21 @@ async fn demo_handler_untyped_body(
     } else {
         None
     };
+    let content_type = body.content_type().map(String::from);
 
-    Ok(HttpResponseOk(DemoUntyped { nbytes, as_utf8 }))
+    Ok(HttpResponseOk(DemoUntyped { nbytes, as_utf8, content_type }))
+}
+
+#[endpoint {
+    method = PUT,
+    path = "/testing/large_body",
+    request_body_max_bytes = 2048,
+}]
+async fn demo_handler_large_body(
+    _rqctx: RequestCtx,
+    body: TypedBody<String>,
+) -> Result<HttpResponseOk<usize>, HttpError> {
+    Ok(HttpResponseOk(body.into_inner().len()))
 }
 
 #[derive(Deserialize, Serialize, JsonSchema)]
@@ -1200,6 +1681,35 @@ async fn demo_handler_streaming_body(
     Ok(HttpResponseOk(DemoStreaming { nbytes }))
 }
 
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct DemoMultipartPart {
+    pub name: Option<String>,
+    pub file_name: Option<String>,
+    pub nbytes: usize,
+}
+#[endpoint {
+    method = POST,
+    path = "/testing/multipart"
+}]
+async fn demo_handler_multipart(
+    _rqctx: RequestContext<usize>,
+    multipart: Multipart,
+) -> Result<HttpResponseOk<Vec<DemoMultipartPart>>, HttpError> {
+    let mut multipart = multipart.part_max_bytes(16);
+    let mut parts = Vec::new();
+    while let Some(mut part) = multipart.next_part().await? {
+        let name = part.name().map(String::from);
+        let file_name = part.file_name().map(String::from);
+        let mut nbytes = 0;
+        while let Some(chunk) = part.chunk().await? {
+            nbytes += chunk.len();
+        }
+        parts.push(DemoMultipartPart { name, file_name, nbytes });
+    }
+
+    Ok(HttpResponseOk(parts))
+}
+
 #[derive(Deserialize, Serialize, JsonSchema)]
 pub struct DemoRaw {
     pub nbytes: usize,
@@ -1251,6 +1761,17 @@ async fn demo_handler_delete(
     Ok(HttpResponseDeleted())
 }
 
+#[endpoint {
+    method = DELETE,
+    path = "/testing/delete_body",
+}]
+async fn demo_handler_delete_body(
+    _rqctx: RequestCtx,
+    json: TypedBody<DemoJsonBody>,
+) -> Result<Response<Body>, HttpError> {
+    http_echo(&json.into_inner())
+}
+
 #[endpoint {
     method = GET,
     path = "/testing/headers",
@@ -1270,6 +1791,23 @@ async fn demo_handler_headers(
     Ok(response)
 }
 
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct DemoHeaders {
+    pub x_demo_count: u32,
+    pub x_demo_name: Option<String>,
+}
+#[endpoint {
+    method = GET,
+    path = "/testing/header_params",
+}]
+async fn demo_handler_header_params(
+    _rqctx: RequestCtx,
+    headers: Header<DemoHeaders>,
+) -> Result<Response<Body>, HttpError> {
+    http_echo(&headers.into_inner())
+}
+
 #[endpoint {
     method = GET,
     path = "/testing/302_bogus",
@@ -1310,6 +1848,16 @@ async fn demo_handler_307_temporary_redirect(
     Ok(http_response_temporary_redirect(String::from("/path3")).unwrap())
 }
 
+#[endpoint {
+    method = GET,
+    path = "/testing/308_permanent_redirect",
+}]
+async fn demo_handler_308_permanent_redirect(
+    _rqctx: RequestCtx,
+) -> Result<HttpResponsePermanentRedirect, HttpError> {
+    Ok(http_response_permanent_redirect(String::from("/path4")).unwrap())
+}
+
 #[channel {
     protocol = WEBSOCKETS,
     path = "/testing/websocket"
@@ -0,0 +1,163 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for reading the matched endpoint's metadata from
+//! `RequestContext::endpoint`, and for the deprecation headers derived from
+//! it.
+
+use dropshot::endpoint;
+use dropshot::test_util::read_json;
+use dropshot::ApiDescription;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::Path;
+use dropshot::RequestContext;
+use http::Method;
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+pub mod common;
+
+#[derive(Deserialize, JsonSchema)]
+struct ProjectPath {
+    #[allow(dead_code)]
+    project: String,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct Metadata {
+    operation_id: String,
+    method: String,
+    path: String,
+    tags: Vec<String>,
+    deprecated: bool,
+    attributes: BTreeMap<String, String>,
+}
+
+#[endpoint {
+    method = GET,
+    path = "/projects/{project}",
+    aliases = [ "/legacy/projects/{project}" ],
+    tags = [ "projects" ],
+    attributes = { audit = "read", team = "storage" },
+}]
+async fn project_metadata(
+    rqctx: RequestContext<usize>,
+    _path: Path<ProjectPath>,
+) -> Result<HttpResponseOk<Metadata>, HttpError> {
+    let endpoint = &rqctx.endpoint;
+    Ok(HttpResponseOk(Metadata {
+        operation_id: endpoint.operation_id.clone(),
+        method: endpoint.method.to_string(),
+        path: endpoint.path.clone(),
+        tags: endpoint.tags.clone(),
+        deprecated: endpoint.deprecated,
+        attributes: endpoint.attributes.clone(),
+    }))
+}
+
+#[tokio::test]
+async fn test_endpoint_metadata() {
+    let mut api = ApiDescription::new();
+    api.register(project_metadata).unwrap();
+    let testctx = common::test_setup("endpoint_metadata", api);
+    let client = &testctx.client_testctx;
+
+    let mut response = client
+        .make_request_no_body(Method::GET, "/projects/p1", StatusCode::OK)
+        .await
+        .unwrap();
+    let metadata: Metadata = read_json(&mut response).await;
+    assert_eq!(metadata.operation_id, "project_metadata");
+    assert_eq!(metadata.method, "GET");
+    assert_eq!(metadata.path, "/projects/{project}");
+    assert_eq!(metadata.tags, ["projects"]);
+    assert!(!metadata.deprecated);
+    assert_eq!(metadata.attributes["audit"], "read");
+    assert_eq!(metadata.attributes["team"], "storage");
+
+    // An alias reports its own path template and operation id but shares the
+    // canonical endpoint's attributes.
+    let mut response = client
+        .make_request_no_body(
+            Method::GET,
+            "/legacy/projects/p1",
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+    let metadata: Metadata = read_json(&mut response).await;
+    assert_eq!(metadata.operation_id, "project_metadata_alias1");
+    assert_eq!(metadata.path, "/legacy/projects/{project}");
+    assert!(metadata.deprecated);
+    assert_eq!(metadata.attributes["audit"], "read");
+
+    testctx.teardown().await;
+}
+
+#[endpoint {
+    method = GET,
+    path = "/old-projects",
+    deprecated_since = "2023-06-01T00:00:00Z",
+    sunset = "2024-01-01T00:00:00Z",
+}]
+async fn old_projects(
+    _rqctx: RequestContext<usize>,
+) -> Result<HttpResponseOk<()>, HttpError> {
+    Ok(HttpResponseOk(()))
+}
+
+#[endpoint {
+    method = GET,
+    path = "/old-projects/{project}",
+    deprecated_since = "2023-06-01T00:00:00Z",
+    sunset = "2024-01-01T00:00:00Z",
+}]
+async fn old_project(
+    _rqctx: RequestContext<usize>,
+    _path: Path<ProjectPath>,
+) -> Result<HttpResponseOk<()>, HttpError> {
+    Err(HttpError::for_not_found(None, String::from("no such project")))
+}
+
+#[tokio::test]
+async fn test_deprecation_headers() {
+    let mut api = ApiDescription::new();
+    api.register(project_metadata).unwrap();
+    api.register(old_projects).unwrap();
+    api.register(old_project).unwrap();
+    let testctx = common::test_setup("deprecation_headers", api);
+    let client = &testctx.client_testctx;
+
+    let response = client
+        .make_request_no_body(Method::GET, "/old-projects", StatusCode::OK)
+        .await
+        .unwrap();
+    let headers = response.headers();
+    assert_eq!(headers["deprecation"], "@1685577600");
+    assert_eq!(headers["sunset"], "Mon, 01 Jan 2024 00:00:00 GMT");
+
+    // Error responses carry them too.
+    let response =
+        hyper::Client::new().get(client.url("/old-projects/p1")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let headers = response.headers();
+    assert_eq!(headers["deprecation"], "@1685577600");
+    assert_eq!(headers["sunset"], "Mon, 01 Jan 2024 00:00:00 GMT");
+
+    // Endpoints that are merely deprecated don't send either header.
+    let response = client
+        .make_request_no_body(
+            Method::GET,
+            "/legacy/projects/p1",
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+    assert!(response.headers().get("deprecation").is_none());
+    assert!(response.headers().get("sunset").is_none());
+
+    testctx.teardown().await;
+}
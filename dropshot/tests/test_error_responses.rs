@@ -0,0 +1,81 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for documenting the errors an endpoint may return.
+
+use dropshot::endpoint;
+use dropshot::ApiDescription;
+use dropshot::ApiEndpoint;
+use dropshot::HttpError;
+use dropshot::HttpResponseDeleted;
+use dropshot::HttpResponseOk;
+use dropshot::Method;
+use dropshot::RequestContext;
+use http::StatusCode;
+
+#[endpoint {
+    method = GET,
+    path = "/widget",
+    errors = [404, 409, 404],
+}]
+async fn widget_get(
+    _rqctx: RequestContext<()>,
+) -> Result<HttpResponseOk<String>, HttpError> {
+    Err(HttpError::for_not_found(None, String::from("no widget")))
+}
+
+#[endpoint {
+    method = DELETE,
+    path = "/widget",
+}]
+async fn widget_delete(
+    _rqctx: RequestContext<()>,
+) -> Result<HttpResponseDeleted, HttpError> {
+    Ok(HttpResponseDeleted())
+}
+
+#[test]
+fn test_error_responses_openapi() {
+    let mut api = ApiDescription::new();
+    api.register(widget_get).unwrap();
+    api.register(widget_delete).unwrap();
+    let spec = api.openapi("test", "1.0.0").json().unwrap();
+
+    // Declared errors are listed individually, after the success response,
+    // with the error body's schema.
+    let responses =
+        spec["paths"]["/widget"]["get"]["responses"].as_object().unwrap();
+    let codes = responses.keys().map(String::as_str).collect::<Vec<_>>();
+    assert_eq!(codes, ["200", "404", "409", "4XX", "5XX"]);
+    assert_eq!(responses["404"]["description"], "Not Found");
+    assert_eq!(responses["409"]["description"], "Conflict");
+    assert_eq!(
+        responses["409"]["content"]["application/json"]["schema"]["$ref"],
+        "#/components/schemas/Error"
+    );
+    assert_eq!(responses["4XX"]["$ref"], "#/components/responses/Error");
+
+    // Endpoints that don't declare errors are unchanged.
+    let responses =
+        spec["paths"]["/widget"]["delete"]["responses"].as_object().unwrap();
+    let codes = responses.keys().map(String::as_str).collect::<Vec<_>>();
+    assert_eq!(codes, ["204", "4XX", "5XX"]);
+}
+
+async fn widget_put(
+    _rqctx: RequestContext<()>,
+) -> Result<HttpResponseDeleted, HttpError> {
+    Ok(HttpResponseDeleted())
+}
+
+#[test]
+#[should_panic(expected = "not an error status: 302 Found")]
+fn test_error_responses_not_error() {
+    ApiEndpoint::<()>::new(
+        String::from("widget_put"),
+        widget_put,
+        Method::PUT,
+        "application/json",
+        "/widget",
+    )
+    .error(StatusCode::FOUND);
+}
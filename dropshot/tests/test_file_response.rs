@@ -0,0 +1,139 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for sending files.
+
+use dropshot::endpoint;
+use dropshot::test_util::read_string;
+use dropshot::ApiDescription;
+use dropshot::HttpError;
+use dropshot::HttpResponseAttachment;
+use dropshot::HttpResponseFile;
+use dropshot::Path;
+use dropshot::RangeRequest;
+use dropshot::RequestContext;
+use http::Method;
+use http::StatusCode;
+use hyper::Body;
+use hyper::Request;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+pub mod common;
+
+const CONTENTS: &str = "the quick brown fox jumps over the lazy dog";
+
+#[derive(Deserialize, JsonSchema)]
+struct FilePath {
+    name: String,
+}
+
+#[endpoint {
+    method = GET,
+    path = "/files/{name}",
+}]
+async fn file_get(
+    _rqctx: RequestContext<usize>,
+    path: Path<FilePath>,
+    range: RangeRequest,
+) -> Result<HttpResponseFile, HttpError> {
+    let path = std::env::temp_dir().join(path.into_inner().name);
+    HttpResponseFile::open(path).await?.with_range(&range)
+}
+
+#[endpoint {
+    method = GET,
+    path = "/downloads/{name}",
+}]
+async fn file_download(
+    _rqctx: RequestContext<usize>,
+    path: Path<FilePath>,
+) -> Result<HttpResponseAttachment<HttpResponseFile>, HttpError> {
+    let path = std::env::temp_dir().join(path.into_inner().name);
+    let file = HttpResponseFile::open(path).await?;
+    Ok(HttpResponseAttachment::new("狐狸 \"fox\".txt", file))
+}
+
+#[tokio::test]
+async fn test_file_response() {
+    let name =
+        format!("dropshot-test-file-response-{}.txt", std::process::id());
+    let file_path = std::env::temp_dir().join(&name);
+    std::fs::write(&file_path, CONTENTS).unwrap();
+
+    let mut api = ApiDescription::new();
+    api.register(file_get).unwrap();
+    api.register(file_download).unwrap();
+    let testctx = common::test_setup("file_response", api);
+    let client = &testctx.client_testctx;
+    let url = format!("/files/{}", name);
+    let request = |range: Option<&str>| {
+        let mut builder =
+            Request::builder().method(Method::GET).uri(client.url(&url));
+        if let Some(range) = range {
+            builder = builder.header(http::header::RANGE, range);
+        }
+        builder.body(Body::empty()).unwrap()
+    };
+
+    let mut response = client
+        .make_request_with_request(request(None), StatusCode::OK)
+        .await
+        .unwrap();
+    let headers = response.headers();
+    assert_eq!(headers[http::header::CONTENT_TYPE], "text/plain");
+    assert_eq!(
+        headers[http::header::CONTENT_LENGTH],
+        CONTENTS.len().to_string().as_str()
+    );
+    assert_eq!(headers[http::header::ACCEPT_RANGES], "bytes");
+    let last_modified = headers[http::header::LAST_MODIFIED].to_str().unwrap();
+    chrono::DateTime::parse_from_rfc2822(last_modified).unwrap();
+    assert_eq!(read_string(&mut response).await, CONTENTS);
+
+    let mut response = client
+        .make_request_with_request(
+            request(Some("bytes=4-8")),
+            StatusCode::PARTIAL_CONTENT,
+        )
+        .await
+        .unwrap();
+    let headers = response.headers();
+    assert_eq!(headers[http::header::CONTENT_RANGE], "bytes 4-8/43");
+    assert_eq!(headers[http::header::CONTENT_LENGTH], "5");
+    assert_eq!(read_string(&mut response).await, "quick");
+
+    client
+        .make_request_with_request(
+            request(Some("bytes=100-")),
+            StatusCode::RANGE_NOT_SATISFIABLE,
+        )
+        .await
+        .unwrap_err();
+
+    // A download is labelled with its file name, however unusual.
+    let mut response = client
+        .make_request_no_body(
+            Method::GET,
+            &format!("/downloads/{}", name),
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers()[http::header::CONTENT_DISPOSITION],
+        "attachment; filename=\"__ _fox_.txt\"; \
+         filename*=UTF-8''%E7%8B%90%E7%8B%B8%20%22fox%22.txt"
+    );
+    assert_eq!(read_string(&mut response).await, CONTENTS);
+
+    client
+        .make_request_error(
+            Method::GET,
+            "/files/dropshot-test-file-response-missing.txt",
+            StatusCode::NOT_FOUND,
+        )
+        .await;
+
+    testctx.teardown().await;
+    std::fs::remove_file(&file_path).unwrap();
+}
@@ -0,0 +1,89 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for serving HTTP/2.
+
+use dropshot::endpoint;
+use dropshot::test_util::TestContext;
+use dropshot::ApiDescription;
+use dropshot::ConfigDropshot;
+use dropshot::ConfigHttp2;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::RequestContext;
+use slog::o;
+
+pub mod common;
+
+/// Reports the version of HTTP with which the request was made.
+#[endpoint {
+    method = GET,
+    path = "/version",
+}]
+async fn api_version(
+    rqctx: RequestContext<usize>,
+) -> Result<HttpResponseOk<String>, HttpError> {
+    Ok(HttpResponseOk(format!("{:?}", rqctx.request.version())))
+}
+
+fn test_setup(name: &str, http2: ConfigHttp2) -> TestContext<usize> {
+    let mut api = ApiDescription::new();
+    api.register(api_version).unwrap();
+    let config = ConfigDropshot { http2, ..Default::default() };
+    let logctx = common::create_log_context(name);
+    let log = logctx.log.new(o!());
+    TestContext::new(api, 0, &config, Some(logctx), log)
+}
+
+/// Makes a request over cleartext HTTP/2 with prior knowledge.
+async fn get_version_h2c(
+    testctx: &TestContext<usize>,
+) -> Result<String, hyper::Error> {
+    let client = hyper::Client::builder().http2_only(true).build_http();
+    let uri = testctx.client_testctx.url("/version");
+    let response = client.get(uri).await?;
+    assert_eq!(response.version(), http::Version::HTTP_2);
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    Ok(serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_http2_prior_knowledge() {
+    let testctx = test_setup(
+        "http2_prior_knowledge",
+        ConfigHttp2 {
+            max_concurrent_streams: Some(10),
+            initial_stream_window_size: Some(1 << 20),
+            initial_connection_window_size: Some(1 << 22),
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(get_version_h2c(&testctx).await.unwrap(), "HTTP/2.0");
+
+    // HTTP/1.1 clients are served on the same port.
+    let mut response = testctx
+        .client_testctx
+        .make_request_no_body(
+            http::Method::GET,
+            "/version",
+            http::StatusCode::OK,
+        )
+        .await
+        .unwrap();
+    let version: String = dropshot::test_util::read_json(&mut response).await;
+    assert_eq!(version, "HTTP/1.1");
+
+    testctx.teardown().await;
+}
+
+#[tokio::test]
+async fn test_http2_disabled() {
+    let testctx = test_setup(
+        "http2_disabled",
+        ConfigHttp2 { enabled: false, ..Default::default() },
+    );
+
+    get_version_h2c(&testctx).await.unwrap_err();
+
+    testctx.teardown().await;
+}
@@ -0,0 +1,98 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for listing the requests a server is currently handling.
+
+use dropshot::endpoint;
+use dropshot::test_util::read_json;
+use dropshot::test_util::TestContext;
+use dropshot::ApiDescription;
+use dropshot::ConfigDropshot;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::InFlightRequest;
+use dropshot::RequestContext;
+use http::Method;
+use http::StatusCode;
+use slog::o;
+use tokio::sync::Notify;
+
+pub mod common;
+
+struct ExampleContext {
+    /// notified when the slow handler has started
+    entered: Notify,
+    /// notified to let the slow handler finish
+    release: Notify,
+}
+
+#[endpoint {
+    method = GET,
+    path = "/slow",
+}]
+async fn slow(
+    rqctx: RequestContext<ExampleContext>,
+) -> Result<HttpResponseOk<()>, HttpError> {
+    rqctx.context().entered.notify_one();
+    rqctx.context().release.notified().await;
+    Ok(HttpResponseOk(()))
+}
+
+#[endpoint {
+    method = GET,
+    path = "/admin/requests",
+}]
+async fn admin_requests(
+    rqctx: RequestContext<ExampleContext>,
+) -> Result<HttpResponseOk<Vec<InFlightRequest>>, HttpError> {
+    Ok(HttpResponseOk(rqctx.server.in_flight_requests()))
+}
+
+#[tokio::test]
+async fn test_in_flight_requests() {
+    let mut api = ApiDescription::new();
+    api.register(slow).unwrap();
+    api.register(admin_requests).unwrap();
+    let context =
+        ExampleContext { entered: Notify::new(), release: Notify::new() };
+    let logctx = common::create_log_context("in_flight_requests");
+    let log = logctx.log.new(o!());
+    let testctx = TestContext::new(
+        api,
+        context,
+        &ConfigDropshot::default(),
+        Some(logctx),
+        log,
+    );
+    assert!(testctx.server.in_flight_requests().is_empty());
+
+    // Start a request that won't finish until we say so.
+    let client = hyper::Client::new();
+    let uri = testctx.client_testctx.url("/slow");
+    let slow_request = tokio::spawn(async move { client.get(uri).await });
+    testctx.server.app_private().entered.notified().await;
+
+    let in_flight = testctx.server.in_flight_requests();
+    assert_eq!(in_flight.len(), 1);
+    assert_eq!(in_flight[0].method, "GET");
+    assert_eq!(in_flight[0].uri, "/slow");
+    assert_eq!(in_flight[0].operation_id.as_deref(), Some("slow"));
+
+    // Handlers can list in-flight requests, too, which include their own.
+    let mut response = testctx
+        .client_testctx
+        .make_request_no_body(Method::GET, "/admin/requests", StatusCode::OK)
+        .await
+        .unwrap();
+    let listed: Vec<serde_json::Value> = read_json(&mut response).await;
+    assert_eq!(listed.len(), 2);
+    assert_eq!(listed[0]["request_id"], in_flight[0].request_id.as_str());
+    assert_eq!(listed[1]["operation_id"], "admin_requests");
+
+    // Once the request finishes, it's no longer in flight.
+    testctx.server.app_private().release.notify_one();
+    let response = slow_request.await.unwrap().unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(testctx.server.in_flight_requests().is_empty());
+
+    testctx.teardown().await;
+}
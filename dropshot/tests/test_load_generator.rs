@@ -0,0 +1,100 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for driving load against a test server with
+//! `test_util::LoadGenerator`.
+
+use dropshot::endpoint;
+use dropshot::test_util::LoadGenerator;
+use dropshot::ApiDescription;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::RequestContext;
+use dropshot::TypedBody;
+use http::Method;
+use http::StatusCode;
+use std::time::Duration;
+
+pub mod common;
+
+#[endpoint {
+    method = GET,
+    path = "/ping",
+}]
+async fn api_ping(
+    _rqctx: RequestContext<usize>,
+) -> Result<HttpResponseOk<()>, HttpError> {
+    Ok(HttpResponseOk(()))
+}
+
+#[endpoint {
+    method = PUT,
+    path = "/echo",
+}]
+async fn api_echo(
+    _rqctx: RequestContext<usize>,
+    body: TypedBody<String>,
+) -> Result<HttpResponseOk<String>, HttpError> {
+    Ok(HttpResponseOk(body.into_inner()))
+}
+
+fn api() -> ApiDescription<usize> {
+    let mut api = ApiDescription::new();
+    api.register(api_ping).unwrap();
+    api.register(api_echo).unwrap();
+    api
+}
+
+#[tokio::test]
+async fn test_load_generator_requests() {
+    let testctx = common::test_setup("load_generator_requests", api());
+
+    let report = LoadGenerator::new(Method::GET, "/ping")
+        .concurrency(4)
+        .requests(100)
+        .run(&testctx.client_testctx)
+        .await;
+    assert_eq!(report.responses(), 100);
+    assert_eq!(report.failures(), 0);
+    assert_eq!(report.errors(), 0);
+    assert_eq!(report.statuses.get(&StatusCode::OK), Some(&100));
+
+    let p50 = report.latency_percentile(50.0).unwrap();
+    let p99 = report.latency_percentile(99.0).unwrap();
+    let max = report.latency_percentile(100.0).unwrap();
+    assert!(p50 <= p99 && p99 <= max);
+    assert!(report.to_string().starts_with("100 responses (0 errors)"));
+
+    // Bad requests count as errors, not failures.
+    let report = LoadGenerator::new(Method::PUT, "/echo")
+        .header("content-type", "application/json")
+        .body("not json")
+        .concurrency(2)
+        .requests(10)
+        .run(&testctx.client_testctx)
+        .await;
+    assert_eq!(report.responses(), 10);
+    assert_eq!(report.errors(), 10);
+    assert_eq!(report.failures(), 0);
+    assert_eq!(report.statuses.get(&StatusCode::BAD_REQUEST), Some(&10));
+
+    testctx.teardown().await;
+}
+
+#[tokio::test]
+async fn test_load_generator_duration() {
+    let testctx = common::test_setup("load_generator_duration", api());
+
+    let report = LoadGenerator::new(Method::PUT, "/echo")
+        .header("content-type", "application/json")
+        .body("\"hello\"")
+        .concurrency(2)
+        .duration(Duration::from_millis(200))
+        .run(&testctx.client_testctx)
+        .await;
+    assert!(report.responses() > 0);
+    assert_eq!(report.errors(), 0);
+    assert_eq!(report.failures(), 0);
+    assert!(report.elapsed >= Duration::from_millis(200));
+
+    testctx.teardown().await;
+}
@@ -0,0 +1,129 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for MessagePack request and response bodies.
+
+#![cfg(feature = "msgpack")]
+
+use dropshot::endpoint;
+use dropshot::test_util::ClientTestContext;
+use dropshot::ApiDescription;
+use dropshot::HttpError;
+use dropshot::HttpErrorResponseBody;
+use dropshot::HttpResponseOk;
+use dropshot::MsgPack;
+use dropshot::MsgPackBody;
+use dropshot::RequestContext;
+use dropshot::CONTENT_TYPE_JSON;
+use dropshot::CONTENT_TYPE_MSGPACK;
+use http::header::CONTENT_TYPE;
+use http::Method;
+use http::StatusCode;
+use hyper::Body;
+use hyper::Request;
+use hyper::Response;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+pub mod common;
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
+struct Reading {
+    sensor: String,
+    value: i64,
+}
+
+#[endpoint {
+    method = POST,
+    path = "/readings",
+}]
+async fn readings_post(
+    _rqctx: RequestContext<usize>,
+    body: MsgPackBody<Reading>,
+) -> Result<HttpResponseOk<MsgPack<Reading>>, HttpError> {
+    let mut reading = body.into_inner();
+    reading.value += 1;
+    Ok(HttpResponseOk(MsgPack(reading)))
+}
+
+fn api() -> ApiDescription<usize> {
+    let mut api = ApiDescription::new();
+    api.register(readings_post).unwrap();
+    api
+}
+
+async fn post_reading(
+    client: &ClientTestContext,
+    content_type: &str,
+    body: Vec<u8>,
+    expected_status: StatusCode,
+) -> Result<Response<Body>, HttpErrorResponseBody> {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(client.url("/readings"))
+        .header(CONTENT_TYPE, content_type)
+        .body(Body::from(body))
+        .unwrap();
+    client.make_request_with_request(request, expected_status).await
+}
+
+#[tokio::test]
+async fn test_msgpack_body() {
+    let testctx = common::test_setup("msgpack_body", api());
+    let client = &testctx.client_testctx;
+
+    // Clients may encode structs as either arrays or maps.
+    let reading = Reading { sensor: String::from("t1"), value: 7 };
+    for body in [
+        rmp_serde::to_vec(&reading).unwrap(),
+        rmp_serde::to_vec_named(&reading).unwrap(),
+    ] {
+        let mut response =
+            post_reading(client, CONTENT_TYPE_MSGPACK, body, StatusCode::OK)
+                .await
+                .unwrap();
+        assert_eq!(response.headers()[CONTENT_TYPE], CONTENT_TYPE_MSGPACK);
+        let bytes = hyper::body::to_bytes(response.body_mut()).await.unwrap();
+        let echoed: Reading = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(echoed, Reading { sensor: String::from("t1"), value: 8 });
+    }
+
+    // Error case: not a valid encoding of the body type
+    let error = post_reading(
+        client,
+        CONTENT_TYPE_MSGPACK,
+        rmp_serde::to_vec(&42).unwrap(),
+        StatusCode::BAD_REQUEST,
+    )
+    .await
+    .unwrap_err();
+    assert!(error.message.starts_with("unable to parse MessagePack body:"));
+
+    // Error case: wrong content type
+    let error = post_reading(
+        client,
+        CONTENT_TYPE_JSON,
+        br#"{"sensor":"t1","value":7}"#.to_vec(),
+        StatusCode::BAD_REQUEST,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        error.message,
+        "expected content type \"application/msgpack\", got \
+         \"application/json\""
+    );
+
+    testctx.teardown().await;
+}
+
+#[test]
+fn test_msgpack_openapi() {
+    let spec = api().openapi("MessagePack", "1.0").json().unwrap();
+    let post = &spec["paths"]["/readings"]["post"];
+    let request = &post["requestBody"]["content"]["application/msgpack"];
+    assert_eq!(request["schema"]["$ref"], "#/components/schemas/Reading");
+    let response = &post["responses"]["200"]["content"]["application/msgpack"];
+    assert_eq!(response["schema"]["$ref"], "#/components/schemas/Reading");
+    assert!(spec["components"]["schemas"]["Reading"].is_object());
+}
@@ -0,0 +1,162 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for endpoints that offer several body encodings.
+
+#![cfg(all(feature = "cbor", feature = "msgpack"))]
+
+use dropshot::endpoint;
+use dropshot::test_util::read_json;
+use dropshot::ApiDescription;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::Negotiated;
+use dropshot::RequestContext;
+use dropshot::TypedBody;
+use dropshot::CONTENT_TYPE_CBOR;
+use dropshot::CONTENT_TYPE_JSON;
+use dropshot::CONTENT_TYPE_MSGPACK;
+use http::header::ACCEPT;
+use http::header::CONTENT_TYPE;
+use http::Method;
+use http::StatusCode;
+use hyper::Body;
+use hyper::Request;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+pub mod common;
+
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
+struct Reading {
+    sensor: String,
+    value: i64,
+}
+
+#[endpoint {
+    method = POST,
+    path = "/readings",
+    encodings = [ "application/json", "application/cbor", "application/msgpack" ],
+}]
+async fn readings_post(
+    rqctx: RequestContext<usize>,
+    body: TypedBody<Reading>,
+) -> Result<HttpResponseOk<Negotiated<Reading>>, HttpError> {
+    Ok(HttpResponseOk(Negotiated::new(&rqctx, body.into_inner())))
+}
+
+#[endpoint {
+    method = GET,
+    path = "/readings/latest",
+}]
+async fn readings_latest(
+    rqctx: RequestContext<usize>,
+) -> Result<HttpResponseOk<Negotiated<Reading>>, HttpError> {
+    let reading = Reading { sensor: String::from("t0"), value: 21 };
+    Ok(HttpResponseOk(Negotiated::new(&rqctx, reading)))
+}
+
+fn api() -> ApiDescription<usize> {
+    let mut api = ApiDescription::new();
+    api.register(readings_post).unwrap();
+    api.register(readings_latest).unwrap();
+    api
+}
+
+#[tokio::test]
+async fn test_negotiation() {
+    let testctx = common::test_setup("negotiation", api());
+    let client = &testctx.client_testctx;
+    let reading = Reading { sensor: String::from("t1"), value: 7 };
+
+    let mut cbor = Vec::new();
+    ciborium::ser::into_writer(&reading, &mut cbor).unwrap();
+    let bodies = vec![
+        (CONTENT_TYPE_JSON, serde_json::to_vec(&reading).unwrap()),
+        (CONTENT_TYPE_CBOR, cbor),
+        (CONTENT_TYPE_MSGPACK, rmp_serde::to_vec_named(&reading).unwrap()),
+    ];
+
+    // Every combination of request and response encodings works.
+    for (content_type, body) in &bodies {
+        for accept in
+            [CONTENT_TYPE_JSON, CONTENT_TYPE_CBOR, CONTENT_TYPE_MSGPACK]
+        {
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri(client.url("/readings"))
+                .header(CONTENT_TYPE, *content_type)
+                .header(ACCEPT, accept)
+                .body(Body::from(body.clone()))
+                .unwrap();
+            let mut response = client
+                .make_request_with_request(request, StatusCode::OK)
+                .await
+                .unwrap();
+            assert_eq!(response.headers()[CONTENT_TYPE], accept);
+            let bytes =
+                hyper::body::to_bytes(response.body_mut()).await.unwrap();
+            let echoed: Reading = match accept {
+                CONTENT_TYPE_JSON => serde_json::from_slice(&bytes).unwrap(),
+                CONTENT_TYPE_CBOR => {
+                    ciborium::de::from_reader(&bytes[..]).unwrap()
+                }
+                _ => rmp_serde::from_slice(&bytes).unwrap(),
+            };
+            assert_eq!(echoed, reading);
+        }
+    }
+
+    // Error case: an encoding the endpoint doesn't offer
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(client.url("/readings"))
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from("sensor=t1&value=7"))
+        .unwrap();
+    let error = client
+        .make_request_with_request(request, StatusCode::BAD_REQUEST)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.message,
+        "expected content type \"application/json\", got \
+         \"application/x-www-form-urlencoded\""
+    );
+
+    // An endpoint that offers no encodings always responds with JSON.
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(client.url("/readings/latest"))
+        .header(ACCEPT, CONTENT_TYPE_CBOR)
+        .body(Body::empty())
+        .unwrap();
+    let mut response = client
+        .make_request_with_request(request, StatusCode::OK)
+        .await
+        .unwrap();
+    let latest: Reading = read_json(&mut response).await;
+    assert_eq!(latest.value, 21);
+
+    testctx.teardown().await;
+}
+
+#[test]
+fn test_negotiation_openapi() {
+    let spec = api().openapi("Negotiation", "1.0").json().unwrap();
+    let post = &spec["paths"]["/readings"]["post"];
+    let request = post["requestBody"]["content"].as_object().unwrap();
+    let response = post["responses"]["200"]["content"].as_object().unwrap();
+    for content in [request, response] {
+        let mut content_types = content.keys().collect::<Vec<_>>();
+        content_types.sort();
+        assert_eq!(
+            content_types,
+            vec![CONTENT_TYPE_CBOR, CONTENT_TYPE_JSON, CONTENT_TYPE_MSGPACK]
+        );
+    }
+
+    let get = &spec["paths"]["/readings/latest"]["get"];
+    let response = get["responses"]["200"]["content"].as_object().unwrap();
+    assert_eq!(response.keys().collect::<Vec<_>>(), vec![CONTENT_TYPE_JSON]);
+}
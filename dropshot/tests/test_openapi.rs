@@ -6,8 +6,9 @@ use dropshot::{
     HttpResponseAccepted, HttpResponseCreated, HttpResponseDeleted,
     HttpResponseFound, HttpResponseHeaders, HttpResponseOk,
     HttpResponseSeeOther, HttpResponseTemporaryRedirect,
-    HttpResponseUpdatedNoContent, PaginationParams, Path, Query,
-    RequestContext, ResultsPage, TagConfig, TagDetails, TypedBody, UntypedBody,
+    HttpResponseUpdatedNoContent, OpenApiVersion, PaginationParams, Path,
+    Query, RequestContext, ResultsPage, TagConfig, TagDetails, TypedBody,
+    UntypedBody,
 };
 use hyper::Body;
 use schemars::JsonSchema;
@@ -508,6 +509,72 @@ fn test_openapi() -> Result<(), String> {
     Ok(())
 }
 
+#[test]
+fn test_openapi_yaml() -> Result<(), String> {
+    let api = make_api(None)?;
+    let mut output = Cursor::new(Vec::new());
+
+    api.openapi("test", "threeve").write_yaml(&mut output).unwrap();
+    let actual: serde_json::Value =
+        serde_yaml::from_slice(output.get_ref()).unwrap();
+
+    // The YAML document describes the same API as the JSON one.
+    let expected = api.openapi("test", "threeve").json().unwrap();
+    assert_eq!(actual, expected);
+    Ok(())
+}
+
+#[test]
+fn test_openapi_3_1() -> Result<(), String> {
+    fn contains_key(value: &serde_json::Value, key: &str) -> bool {
+        match value {
+            serde_json::Value::Object(object) => {
+                object.iter().any(|(k, v)| k == key || contains_key(v, key))
+            }
+            serde_json::Value::Array(values) => {
+                values.iter().any(|v| contains_key(v, key))
+            }
+            _ => false,
+        }
+    }
+
+    let api = make_api(None)?;
+    let spec = api
+        .openapi("test", "threeve")
+        .version(OpenApiVersion::V3_1)
+        .json()
+        .unwrap();
+
+    assert_eq!(spec["openapi"], "3.1.0");
+    assert!(!contains_key(&spec, "nullable"));
+    assert_eq!(
+        spec["components"]["schemas"]["BodyParamNested"]["properties"]["maybe"],
+        serde_json::json!({ "type": ["boolean", "null"] })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_openapi_write_file() -> Result<(), String> {
+    let api = make_api(None)?;
+    let definition = api.openapi("test", "threeve");
+    let expected = definition.json().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+
+    let json_path = dir.path().join("openapi.json");
+    definition.write_file(&json_path).unwrap();
+    let contents = std::fs::read(&json_path).unwrap();
+    let actual: serde_json::Value = serde_json::from_slice(&contents).unwrap();
+    assert_eq!(actual, expected);
+
+    let yaml_path = dir.path().join("openapi.yaml");
+    definition.write_file(&yaml_path).unwrap();
+    let contents = std::fs::read(&yaml_path).unwrap();
+    let actual: serde_json::Value = serde_yaml::from_slice(&contents).unwrap();
+    assert_eq!(actual, expected);
+    Ok(())
+}
+
 #[test]
 fn test_openapi_fuller() -> Result<(), String> {
     let mut tag_definitions = HashMap::new();
@@ -532,6 +599,8 @@ fn test_openapi_fuller() -> Result<(), String> {
         .contact_name("old mate")
         .license_name("CDDL")
         .terms_of_service("no hat, no cane? no service!")
+        .server("https://api.example.com/v1", "production")
+        .server_url("/v1")
         .write(&mut output);
     let actual = from_utf8(output.get_ref()).unwrap();
 
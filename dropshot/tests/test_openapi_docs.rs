@@ -0,0 +1,104 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for serving an API's own OpenAPI document and documentation.
+
+use dropshot::endpoint;
+use dropshot::test_util::read_json;
+use dropshot::ApiDescription;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::RequestContext;
+use http::Method;
+use http::StatusCode;
+
+pub mod common;
+
+#[endpoint {
+    method = GET,
+    path = "/before",
+}]
+async fn before(
+    _rqctx: RequestContext<usize>,
+) -> Result<HttpResponseOk<()>, HttpError> {
+    Ok(HttpResponseOk(()))
+}
+
+#[endpoint {
+    method = GET,
+    path = "/after",
+}]
+async fn after(
+    _rqctx: RequestContext<usize>,
+) -> Result<HttpResponseOk<()>, HttpError> {
+    Ok(HttpResponseOk(()))
+}
+
+#[tokio::test]
+async fn test_openapi_endpoint() {
+    let mut api = ApiDescription::new();
+    api.register(before).unwrap();
+    api.register_openapi("/openapi.json", "self-describing", "1.2.3").unwrap();
+    // Endpoints registered later are described too.
+    api.register(after).unwrap();
+    let expected = api.openapi("self-describing", "1.2.3").json().unwrap();
+
+    let testctx = common::test_setup("openapi_endpoint", api);
+    let client = &testctx.client_testctx;
+    let mut response = client
+        .make_request_no_body(Method::GET, "/openapi.json", StatusCode::OK)
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers()[http::header::CONTENT_TYPE],
+        "application/json"
+    );
+    let actual: serde_json::Value = read_json(&mut response).await;
+    assert_eq!(actual, expected);
+    let paths = actual["paths"].as_object().unwrap();
+    assert!(paths.contains_key("/before"));
+    assert!(paths.contains_key("/after"));
+    assert!(!paths.contains_key("/openapi.json"));
+
+    testctx.teardown().await;
+}
+
+#[cfg(feature = "redoc")]
+#[tokio::test]
+async fn test_redoc_endpoint() {
+    let mut api = ApiDescription::new();
+    api.register_openapi("/openapi.json", "documented", "1.0.0").unwrap();
+    api.register_redoc("/docs", "documented", "/openapi.json", b"/* redoc */")
+        .unwrap();
+
+    let testctx = common::test_setup("redoc_endpoint", api);
+    let client = &testctx.client_testctx;
+    let mut response = client
+        .make_request_no_body(Method::GET, "/docs", StatusCode::OK)
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers()[http::header::CONTENT_TYPE],
+        "text/html; charset=utf-8"
+    );
+    let page = dropshot::test_util::read_string(&mut response).await;
+    assert!(page.contains("<redoc spec-url=\"/openapi.json\"></redoc>"));
+    assert!(page.contains("<script src=\"/docs/redoc.standalone.js\">"));
+
+    // The script is served from the API itself, not a CDN.
+    let mut response = client
+        .make_request_no_body(
+            Method::GET,
+            "/docs/redoc.standalone.js",
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers()[http::header::CONTENT_TYPE],
+        "text/javascript; charset=utf-8"
+    );
+    let script = dropshot::test_util::read_string(&mut response).await;
+    assert_eq!(script, "/* redoc */");
+
+    testctx.teardown().await;
+}
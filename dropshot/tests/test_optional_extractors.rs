@@ -0,0 +1,131 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for optional extractors.
+
+use dropshot::endpoint;
+use dropshot::test_util::read_json;
+use dropshot::ApiDescription;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::Query;
+use dropshot::RequestContext;
+use dropshot::TypedBody;
+use http::Method;
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+pub mod common;
+
+#[derive(Deserialize, JsonSchema)]
+struct Filter {
+    name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct Widget {
+    size: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
+struct Received {
+    name: Option<String>,
+    size: Option<u32>,
+}
+
+#[endpoint {
+    method = POST,
+    path = "/widgets",
+}]
+async fn widget_post(
+    _rqctx: RequestContext<usize>,
+    filter: Option<Query<Filter>>,
+    body: Option<TypedBody<Widget>>,
+) -> Result<HttpResponseOk<Received>, HttpError> {
+    Ok(HttpResponseOk(Received {
+        name: filter.map(|filter| filter.into_inner().name),
+        size: body.map(|body| body.into_inner().size),
+    }))
+}
+
+fn api() -> ApiDescription<usize> {
+    let mut api = ApiDescription::new();
+    api.register(widget_post).unwrap();
+    api
+}
+
+#[tokio::test]
+async fn test_optional_extractors() {
+    let testctx = common::test_setup("optional_extractors", api());
+    let client = &testctx.client_testctx;
+
+    let cases = [
+        ("/widgets", None, Received { name: None, size: None }),
+        (
+            "/widgets?name=knob",
+            None,
+            Received { name: Some("knob".to_string()), size: None },
+        ),
+        (
+            "/widgets",
+            Some(Widget { size: 3 }),
+            Received { name: None, size: Some(3) },
+        ),
+        (
+            "/widgets?name=knob",
+            Some(Widget { size: 3 }),
+            Received { name: Some("knob".to_string()), size: Some(3) },
+        ),
+    ];
+    for (uri, body, expected) in cases {
+        let mut response = client
+            .make_request(Method::POST, uri, body, StatusCode::OK)
+            .await
+            .unwrap();
+        let received: Received = read_json(&mut response).await;
+        assert_eq!(received, expected);
+    }
+
+    // A body sent with chunked encoding, which has no Content-Length, is
+    // still a body.
+    let chunks = vec![Ok::<_, std::io::Error>("{\"size\": 4}")];
+    let request = hyper::Request::builder()
+        .method(Method::POST)
+        .uri(client.url("/widgets"))
+        .body(hyper::Body::wrap_stream(futures::stream::iter(chunks)))
+        .unwrap();
+    let mut response = client
+        .make_request_with_request(request, StatusCode::OK)
+        .await
+        .unwrap();
+    let received: Received = read_json(&mut response).await;
+    assert_eq!(received, Received { name: None, size: Some(4) });
+
+    // Error case: a query string that's present must still be valid.
+    let error = client
+        .make_request(
+            Method::POST,
+            "/widgets?nmae=knob",
+            None as Option<()>,
+            StatusCode::BAD_REQUEST,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.message,
+        "unable to parse query string: missing field `name`"
+    );
+
+    testctx.teardown().await;
+}
+
+#[test]
+fn test_optional_extractors_openapi() {
+    let spec = api().openapi("Optional", "1.0").json().unwrap();
+    let post = &spec["paths"]["/widgets"]["post"];
+    assert_eq!(post["parameters"][0]["name"], "name");
+    assert_ne!(post["parameters"][0]["required"], true);
+    assert!(post["requestBody"]["content"]["application/json"].is_object());
+    assert_ne!(post["requestBody"]["required"], true);
+}
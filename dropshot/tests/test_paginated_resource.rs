@@ -0,0 +1,151 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for `#[derive(PaginatedResource)]`.
+
+use dropshot::endpoint;
+use dropshot::test_util::objects_list_page;
+use dropshot::ApiDescription;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::PaginatedResource;
+use dropshot::PaginationOrder;
+use dropshot::PaginationParams;
+use dropshot::Query;
+use dropshot::RequestContext;
+use dropshot::ResultsPage;
+use dropshot::WhichPage;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+pub mod common;
+
+#[derive(
+    Clone, Debug, Deserialize, JsonSchema, PaginatedResource, Serialize,
+)]
+#[paginate(sort_by = "name")]
+#[paginate(sort_by = "size", then = "name")]
+struct File {
+    name: String,
+    size: u64,
+}
+
+fn files() -> Vec<File> {
+    [("a", 3), ("b", 1), ("c", 3), ("d", 2), ("e", 1)]
+        .iter()
+        .map(|(name, size)| File { name: name.to_string(), size: *size })
+        .collect()
+}
+
+/// Returns whether `key` comes after `last_seen` in a scan in `order`.
+fn is_after<K: Ord>(order: PaginationOrder, key: &K, last_seen: &K) -> bool {
+    match order {
+        PaginationOrder::Ascending => key > last_seen,
+        PaginationOrder::Descending => key < last_seen,
+    }
+}
+
+#[endpoint {
+    method = GET,
+    path = "/files",
+}]
+async fn files_list(
+    rqctx: RequestContext<usize>,
+    query: Query<PaginationParams<FileScanParams, FilePageSelector>>,
+) -> Result<HttpResponseOk<ResultsPage<File>>, HttpError> {
+    let pag_params = query.into_inner();
+    let limit = rqctx.page_limit(&pag_params)?.get() as usize;
+    let scan_params = File::scan_params(&pag_params);
+
+    let mut files = files();
+    files.sort_by_key(|file| match scan_params.sort_by {
+        FileSortBy::Name => (0, file.name.clone()),
+        FileSortBy::SizeName => (file.size, file.name.clone()),
+    });
+    if scan_params.order == PaginationOrder::Descending {
+        files.reverse();
+    }
+    let files = files
+        .into_iter()
+        .filter(|file| match &pag_params.page {
+            WhichPage::First(_) => true,
+            WhichPage::Next(FilePageSelector::Name { order, last_seen }) => {
+                is_after(*order, &file.name, last_seen)
+            }
+            WhichPage::Next(FilePageSelector::SizeName {
+                order,
+                last_seen,
+            }) => is_after(*order, &(file.size, file.name.clone()), last_seen),
+        })
+        .take(limit)
+        .collect();
+    Ok(HttpResponseOk(File::results_page(files, &pag_params)?))
+}
+
+fn api() -> ApiDescription<usize> {
+    let mut api = ApiDescription::new();
+    api.register(files_list).unwrap();
+    api
+}
+
+#[tokio::test]
+async fn test_paginated_resource() {
+    let testctx = common::test_setup("paginated_resource", api());
+    let client = &testctx.client_testctx;
+
+    // Collects the names of all the files listed from `path`, two at a time.
+    let list = |path: &'static str| async move {
+        let mut names = Vec::new();
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let mut page = objects_list_page::<File>(
+            client,
+            &format!("{}{}limit=2", path, separator),
+        )
+        .await;
+        loop {
+            names.extend(page.items.iter().map(|file| file.name.clone()));
+            let Some(token) = page.next_page else { break };
+            page = objects_list_page::<File>(
+                client,
+                &format!("/files?page_token={}&limit=2", token),
+            )
+            .await;
+        }
+        names
+    };
+
+    // The first sort is the default.
+    assert_eq!(list("/files").await, ["a", "b", "c", "d", "e"]);
+    assert_eq!(
+        list("/files?order=descending").await,
+        ["e", "d", "c", "b", "a"]
+    );
+    // Ties in the first field of a sort are broken by the next.
+    assert_eq!(
+        list("/files?sort_by=size_name").await,
+        ["b", "e", "d", "a", "c"]
+    );
+    assert_eq!(
+        list("/files?sort_by=size_name&order=descending").await,
+        ["c", "a", "d", "e", "b"]
+    );
+
+    testctx.teardown().await;
+}
+
+#[test]
+fn test_paginated_resource_openapi() {
+    let spec = api().openapi("test", "1.0.0").json().unwrap();
+    let mut parameters = spec["paths"]["/files"]["get"]["parameters"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|parameter| parameter["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    parameters.sort_unstable();
+    assert_eq!(parameters, ["limit", "order", "page_token", "sort_by"]);
+    assert_eq!(
+        spec["components"]["schemas"]["FileSortBy"]["enum"],
+        serde_json::json!(["name", "size_name"])
+    );
+}
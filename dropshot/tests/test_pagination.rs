@@ -6,9 +6,12 @@ use chrono::Utc;
 use dropshot::endpoint;
 use dropshot::test_util::iter_collection;
 use dropshot::test_util::object_get;
+use dropshot::test_util::objects_list_all;
 use dropshot::test_util::objects_list_page;
+use dropshot::test_util::read_json;
 use dropshot::test_util::ClientTestContext;
 use dropshot::test_util::LogContext;
+use dropshot::test_util::MockClock;
 use dropshot::ApiDescription;
 use dropshot::ConfigLogging;
 use dropshot::ConfigLoggingIfExists;
@@ -16,6 +19,10 @@ use dropshot::ConfigLoggingLevel;
 use dropshot::EmptyScanParams;
 use dropshot::HttpError;
 use dropshot::HttpResponseOk;
+use dropshot::HttpResponseOkPage;
+use dropshot::OffsetPaginationParams;
+use dropshot::PageTokenEncoding;
+use dropshot::PageTokenKey;
 use dropshot::PaginationOrder;
 use dropshot::PaginationParams;
 use dropshot::Query;
@@ -32,6 +39,7 @@ use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde::Serialize;
 use std::collections::BTreeSet;
+use std::convert::TryFrom;
 use std::env::current_exe;
 use std::fmt::Debug;
 use std::net::Ipv4Addr;
@@ -145,6 +153,9 @@ fn page_selector_for(n: &u16, _p: &EmptyScanParams) -> IntegersPageSelector {
 fn paginate_api() -> ApiDescription<usize> {
     let mut api = ApiDescription::new();
     api.register(api_integers).unwrap();
+    api.register(api_integers_linked).unwrap();
+    api.register(api_integers_offset).unwrap();
+    api.register(api_integers_spawned).unwrap();
     api.register(api_empty).unwrap();
     api.register(api_with_extra_params).unwrap();
     api.register(api_with_required_params).unwrap();
@@ -189,6 +200,85 @@ async fn api_integers(
     )?))
 }
 
+/// "/intapi-linked": the same collection as "/intapi", with each page linking
+/// to the next in a Link header.
+#[endpoint {
+    method = GET,
+    path = "/intapi-linked",
+}]
+async fn api_integers_linked(
+    rqctx: RequestContext<usize>,
+    query: Query<PaginationParams<EmptyScanParams, IntegersPageSelector>>,
+) -> Result<HttpResponseOkPage<u16>, HttpError> {
+    let pag_params = query.into_inner();
+    let limit = rqctx.page_limit(&pag_params)?.get() as u16;
+
+    let start = match &pag_params.page {
+        WhichPage::First(..) => 0,
+        WhichPage::Next(IntegersPageSelector { last_seen }) => *last_seen,
+    };
+    // Stop after a few pages so that the last page has no link.
+    let items = range_u16(start, limit).into_iter().filter(|n| *n <= 5);
+
+    let page = ResultsPage::new(
+        items.collect(),
+        &EmptyScanParams {},
+        page_selector_for,
+    )?;
+    Ok(HttpResponseOkPage::new(&rqctx, page))
+}
+
+/// "/intapi-spawned": the same collection as "/intapi", with the results page
+/// constructed on a task that the handler spawns within its page token scope
+#[endpoint {
+    method = GET,
+    path = "/intapi-spawned",
+}]
+async fn api_integers_spawned(
+    rqctx: RequestContext<usize>,
+    query: Query<PaginationParams<EmptyScanParams, IntegersPageSelector>>,
+) -> Result<HttpResponseOk<ResultsPage<u16>>, HttpError> {
+    let pag_params = query.into_inner();
+    let limit = rqctx.page_limit(&pag_params)?.get() as u16;
+
+    let start = match &pag_params.page {
+        WhichPage::First(..) => 0,
+        WhichPage::Next(IntegersPageSelector { last_seen }) => *last_seen,
+    };
+
+    let scope = rqctx.page_token_scope();
+    let page = tokio::spawn(scope.run(async move {
+        ResultsPage::new(
+            range_u16(start, limit),
+            &EmptyScanParams {},
+            page_selector_for,
+        )
+    }))
+    .await
+    .unwrap()?;
+    Ok(HttpResponseOk(page))
+}
+
+/// "/intapi-offset": the same collection as "/intapi", paginated by offset
+#[endpoint {
+    method = GET,
+    path = "/intapi-offset",
+}]
+async fn api_integers_offset(
+    rqctx: RequestContext<usize>,
+    query: Query<OffsetPaginationParams<EmptyScanParams>>,
+) -> Result<HttpResponseOk<ResultsPage<u16>>, HttpError> {
+    let pag_params = query.into_inner();
+    let limit = rqctx.offset_page_limit(&pag_params)?;
+
+    let items = match u16::try_from(pag_params.offset) {
+        Ok(offset) => range_u16(offset, limit.get() as u16),
+        Err(_) => Vec::new(),
+    };
+    let page = ResultsPage::new_offset(items, &pag_params, limit);
+    Ok(HttpResponseOk(page.with_total_count(u64::from(std::u16::MAX) - 1)))
+}
+
 #[tokio::test]
 async fn test_paginate_errors() {
     let api = paginate_api();
@@ -235,6 +325,302 @@ async fn test_paginate_errors() {
     testctx.teardown().await;
 }
 
+#[tokio::test]
+async fn test_paginate_link_header() {
+    let api = paginate_api();
+    let testctx = common::test_setup("link_header", api);
+    let client = &testctx.client_testctx;
+
+    // Follow the links (rather than the tokens in the bodies) to the end.
+    let mut path = String::from("/intapi-linked?limit=2");
+    let mut items = Vec::new();
+    loop {
+        let mut response = client
+            .make_request_no_body(Method::GET, &path, StatusCode::OK)
+            .await
+            .unwrap();
+        let page: ResultsPage<u16> = read_json(&mut response).await;
+        items.extend(page.items);
+        let Some(link) = response.headers().get(http::header::LINK) else {
+            assert!(page.next_page.is_none());
+            break;
+        };
+        let link = link.to_str().unwrap();
+        let url = link
+            .strip_prefix('<')
+            .and_then(|link| link.strip_suffix(">; rel=\"next\""))
+            .unwrap();
+        let next_page_prefix =
+            client.url("/intapi-linked?limit=2&page_token=").to_string();
+        assert!(url.starts_with(&next_page_prefix));
+        assert!(page.next_page.is_some());
+        let url: hyper::Uri = url.parse().unwrap();
+        path = url.path_and_query().unwrap().to_string();
+    }
+    assert_eq!(items, [1, 2, 3, 4, 5]);
+
+    testctx.teardown().await;
+}
+
+#[tokio::test]
+async fn test_paginate_list_all() {
+    let api = paginate_api();
+    let testctx = common::test_setup("list_all", api);
+    let client = &testctx.client_testctx;
+
+    let items = objects_list_all::<u16>(client, "/intapi-linked").await;
+    assert_eq!(items, [1, 2, 3, 4, 5]);
+    let items = objects_list_all::<u16>(client, "/intapi-linked?limit=2").await;
+    assert_eq!(items, [1, 2, 3, 4, 5]);
+
+    testctx.teardown().await;
+}
+
+#[tokio::test]
+async fn test_paginate_offset() {
+    let api = paginate_api();
+    let testctx = common::test_setup("offset", api);
+    let client = &testctx.client_testctx;
+
+    let page =
+        objects_list_page::<u16>(&client, "/intapi-offset?limit=3").await;
+    assert_sequence_from(&page.items, 1, 3);
+    assert_eq!(page.next_page.as_deref(), Some("3"));
+    assert_eq!(page.total_count, Some(65534));
+
+    let page =
+        objects_list_page::<u16>(&client, "/intapi-offset?offset=3&limit=3")
+            .await;
+    assert_sequence_from(&page.items, 4, 3);
+    assert_eq!(page.next_page.as_deref(), Some("6"));
+
+    // A page that isn't full is the last one.
+    let page = objects_list_page::<u16>(
+        &client,
+        "/intapi-offset?offset=65532&limit=5",
+    )
+    .await;
+    assert_sequence_from(&page.items, 65533, 2);
+    assert_eq!(page.next_page, None);
+
+    assert_error(
+        client,
+        "/intapi-offset?offset=-1",
+        "unable to parse query string: invalid digit found in string",
+    )
+    .await;
+
+    // Offset pagination is documented as ordinary parameters, not as
+    // token-based pagination.
+    let spec = paginate_api().openapi("test", "1.0.0").json().unwrap();
+    let operation = &spec["paths"]["/intapi-offset"]["get"];
+    let mut parameters = operation["parameters"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|parameter| parameter["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    parameters.sort_unstable();
+    assert_eq!(parameters, ["limit", "offset"]);
+    assert!(operation.get("x-dropshot-pagination").is_none());
+
+    testctx.teardown().await;
+}
+
+#[tokio::test]
+async fn test_paginate_signed_tokens() {
+    let key = PageTokenKey::new([7u8; 32]).unwrap();
+    let api = paginate_api().page_token_key(key);
+    let testctx = common::test_setup("signed_tokens", api);
+    let client = &testctx.client_testctx;
+
+    // Signed tokens work just like unsigned ones.
+    let page = objects_list_page::<u16>(&client, "/intapi?limit=3").await;
+    assert_sequence_from(&page.items, 1, 3);
+    let token = page.next_page.unwrap();
+    let page = objects_list_page::<u16>(
+        &client,
+        &format!("/intapi?page_token={}&limit=3", token),
+    )
+    .await;
+    assert_sequence_from(&page.items, 4, 3);
+    let other_token = page.next_page.unwrap();
+
+    // Tokens without a valid signature are rejected.
+    let (unsigned, _) = token.rsplit_once('.').unwrap();
+    let (_, other_signature) = other_token.rsplit_once('.').unwrap();
+    let forged = format!("{}.{}", unsigned, other_signature);
+    for bad_token in [unsigned, forged.as_str()] {
+        assert_error(
+            client,
+            &format!("/intapi?page_token={}", bad_token),
+            "unable to parse query string: failed to parse pagination token: \
+             invalid signature",
+        )
+        .await;
+    }
+
+    // Tokens created on a task that the handler spawns within its page token
+    // scope are signed too.
+    let page =
+        objects_list_page::<u16>(&client, "/intapi-spawned?limit=3").await;
+    assert_sequence_from(&page.items, 1, 3);
+    let token = page.next_page.unwrap();
+    let page = objects_list_page::<u16>(
+        &client,
+        &format!("/intapi?page_token={}&limit=3", token),
+    )
+    .await;
+    assert_sequence_from(&page.items, 4, 3);
+
+    testctx.teardown().await;
+}
+
+#[tokio::test]
+async fn test_paginate_token_version() {
+    let testctx_v1 = common::test_setup("token_version_v1", paginate_api());
+    let api = paginate_api().page_token_version(2);
+    let testctx_v2 = common::test_setup("token_version_v2", api);
+
+    // Tokens are accepted by servers with the same version of the API.
+    let page =
+        objects_list_page::<u16>(&testctx_v2.client_testctx, "/intapi?limit=3")
+            .await;
+    let token = page.next_page.unwrap();
+    let page = objects_list_page::<u16>(
+        &testctx_v2.client_testctx,
+        &format!("/intapi?page_token={}&limit=3", token),
+    )
+    .await;
+    assert_sequence_from(&page.items, 4, 3);
+
+    // Tokens from other versions are rejected.
+    let page =
+        objects_list_page::<u16>(&testctx_v1.client_testctx, "/intapi?limit=3")
+            .await;
+    let old_token = page.next_page.unwrap();
+    assert_error(
+        &testctx_v2.client_testctx,
+        &format!("/intapi?page_token={}", old_token),
+        "unable to parse query string: failed to parse pagination token: \
+         token is for another version of the API; restart the scan",
+    )
+    .await;
+    assert_error(
+        &testctx_v1.client_testctx,
+        &format!("/intapi?page_token={}", token),
+        "unable to parse query string: failed to parse pagination token: \
+         token is for another version of the API; restart the scan",
+    )
+    .await;
+
+    testctx_v1.teardown().await;
+    testctx_v2.teardown().await;
+}
+
+#[tokio::test]
+async fn test_paginate_token_size_and_encoding() {
+    let testctx_default = common::test_setup("token_padded", paginate_api());
+    let api = paginate_api()
+        .page_token_encoding(PageTokenEncoding::Base64UrlNoPad)
+        .page_token_max_length(64);
+    let testctx_nopad = common::test_setup("token_unpadded", api);
+    let api = paginate_api().page_token_max_length(16);
+    let testctx_small = common::test_setup("token_small", api);
+
+    let page = objects_list_page::<u16>(
+        &testctx_default.client_testctx,
+        "/intapi?limit=10",
+    )
+    .await;
+    let padded_token = page.next_page.unwrap();
+    assert!(padded_token.ends_with('='));
+    let page = objects_list_page::<u16>(
+        &testctx_nopad.client_testctx,
+        "/intapi?limit=10",
+    )
+    .await;
+    let unpadded_token = page.next_page.unwrap();
+    assert!(!unpadded_token.contains('='));
+
+    // Servers accept tokens in either encoding.
+    for token in [&padded_token, &unpadded_token] {
+        let path = format!("/intapi?page_token={}&limit=3", token);
+        for testctx in [&testctx_default, &testctx_nopad] {
+            let page =
+                objects_list_page::<u16>(&testctx.client_testctx, &path).await;
+            assert_sequence_from(&page.items, 11, 3);
+        }
+    }
+
+    // Tokens longer than the configured maximum are rejected.
+    let long_token = format!("{}{}", unpadded_token, "A".repeat(16));
+    assert_error(
+        &testctx_nopad.client_testctx,
+        &format!("/intapi?page_token={}", long_token),
+        "unable to parse query string: failed to parse pagination token: too \
+         large",
+    )
+    .await;
+    let error = testctx_default
+        .client_testctx
+        .make_request_error(
+            Method::GET,
+            "/intapi?page_token=not-base64!",
+            StatusCode::BAD_REQUEST,
+        )
+        .await;
+    assert!(error.message.starts_with(
+        "unable to parse query string: failed to parse pagination token: "
+    ));
+
+    // A server that would hand out a token over its own limit fails the
+    // request instead.
+    let error = testctx_small
+        .client_testctx
+        .make_request_error(
+            Method::GET,
+            "/intapi?limit=3",
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )
+        .await;
+    assert_eq!(error.message, "Internal Server Error");
+
+    testctx_default.teardown().await;
+    testctx_nopad.teardown().await;
+    testctx_small.teardown().await;
+}
+
+#[tokio::test]
+async fn test_paginate_token_lifetime() {
+    let clock = MockClock::new("2023-01-01T00:00:00Z".parse().unwrap());
+    let api = paginate_api()
+        .clock(clock.clone())
+        .page_token_lifetime(Duration::from_secs(60));
+    let testctx = common::test_setup("token_lifetime", api);
+    let client = &testctx.client_testctx;
+
+    let page = objects_list_page::<u16>(&client, "/intapi?limit=3").await;
+    let token = page.next_page.unwrap();
+    let path = format!("/intapi?page_token={}&limit=3", token);
+
+    // Tokens work until their lifetime has passed.
+    clock.advance(Duration::from_secs(60));
+    let page = objects_list_page::<u16>(&client, &path).await;
+    assert_sequence_from(&page.items, 4, 3);
+
+    clock.advance(Duration::from_secs(1));
+    assert_error(
+        client,
+        &path,
+        "unable to parse query string: failed to parse pagination token: \
+         token has expired; restart the scan",
+    )
+    .await;
+
+    testctx.teardown().await;
+}
+
 #[tokio::test]
 async fn test_paginate_basic() {
     let api = paginate_api();
@@ -889,6 +1275,34 @@ async fn test_example_basic() {
     exctx.cleanup_successful();
 }
 
+/// Tests the "pagination-keyset" example, which lists the same 999 projects in
+/// either order.
+#[tokio::test]
+async fn test_example_keyset() {
+    let mut exctx = start_example("pagination-keyset", 12233).await;
+    let client = &exctx.client;
+
+    let ascending =
+        assert_collection_iter::<ExampleProject>(&client, "/projects", "")
+            .await;
+    assert_eq!(ascending.len(), 999);
+    assert_eq!(ascending[0].name, "project001");
+    assert_eq!(ascending[ascending.len() - 1].name, "project999");
+
+    let descending = assert_collection_iter::<ExampleProject>(
+        &client,
+        "/projects",
+        "order=descending",
+    )
+    .await;
+    assert_eq!(
+        descending,
+        ascending.iter().rev().cloned().collect::<Vec<ExampleProject>>()
+    );
+
+    exctx.cleanup_successful();
+}
+
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
 struct ExampleProjectMtime {
     name: String,
@@ -0,0 +1,204 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for conditional requests.
+
+use dropshot::endpoint;
+use dropshot::test_util::read_string;
+use dropshot::ApiDescription;
+use dropshot::EntityTag;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::HttpResponseTagged;
+use dropshot::Preconditions;
+use dropshot::RequestContext;
+use http::Method;
+use http::StatusCode;
+use hyper::Body;
+use hyper::Request;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde::Serializer;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+pub mod common;
+
+#[endpoint {
+    method = GET,
+    path = "/document",
+}]
+async fn document_get(
+    _rqctx: RequestContext<usize>,
+    preconditions: Preconditions,
+) -> Result<HttpResponseOk<String>, HttpError> {
+    preconditions.evaluate(Some(&EntityTag::strong("v2")), None)?;
+    Ok(HttpResponseOk(String::from("version 2")))
+}
+
+#[endpoint {
+    method = PUT,
+    path = "/document",
+}]
+async fn document_put(
+    _rqctx: RequestContext<usize>,
+    preconditions: Preconditions,
+) -> Result<HttpResponseOk<String>, HttpError> {
+    preconditions.evaluate(Some(&EntityTag::strong("v2")), None)?;
+    Ok(HttpResponseOk(String::from("version 3")))
+}
+
+/// A response body that counts how many times it's been serialized
+#[derive(JsonSchema)]
+struct Catalog(Vec<String>);
+
+static CATALOG_SERIALIZED: AtomicUsize = AtomicUsize::new(0);
+
+impl Serialize for Catalog {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        CATALOG_SERIALIZED.fetch_add(1, Ordering::SeqCst);
+        self.0.serialize(s)
+    }
+}
+
+#[endpoint {
+    method = GET,
+    path = "/catalog",
+}]
+async fn catalog_get(
+    _rqctx: RequestContext<usize>,
+) -> Result<HttpResponseTagged<HttpResponseOk<Catalog>>, HttpError> {
+    Ok(HttpResponseTagged::new(
+        EntityTag::strong("7"),
+        HttpResponseOk(Catalog(vec![String::from("widget")])),
+    ))
+}
+
+fn request(
+    testctx: &dropshot::test_util::TestContext<usize>,
+    method: Method,
+    header: &str,
+    value: &str,
+) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(testctx.client_testctx.url("/document"))
+        .header(header, value)
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_preconditions() {
+    let mut api = ApiDescription::new();
+    api.register(document_get).unwrap();
+    api.register(document_put).unwrap();
+    let testctx = common::test_setup("preconditions", api);
+    let client = &testctx.client_testctx;
+
+    // A cached copy that's current gets a 304 with no body, carrying the
+    // current entity tag.
+    let mut response = client
+        .make_request_with_request(
+            request(&testctx, Method::GET, "if-none-match", "W/\"v2\""),
+            StatusCode::NOT_MODIFIED,
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.headers()[http::header::ETAG], "\"v2\"");
+    assert_eq!(read_string(&mut response).await, "");
+
+    client
+        .make_request_with_request(
+            request(&testctx, Method::GET, "if-none-match", "\"v1\""),
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+
+    // An update based on a stale copy fails.
+    let error = client
+        .make_request_with_request(
+            request(&testctx, Method::PUT, "if-match", "\"v1\""),
+            StatusCode::PRECONDITION_FAILED,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(error.message, "Precondition Failed");
+
+    client
+        .make_request_with_request(
+            request(&testctx, Method::PUT, "if-match", "\"v1\", \"v2\""),
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+
+    let error = client
+        .make_request_with_request(
+            request(&testctx, Method::PUT, "if-match", "v2"),
+            StatusCode::BAD_REQUEST,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.message,
+        "invalid if-match header: malformed entity tag: v2"
+    );
+
+    testctx.teardown().await;
+}
+
+#[tokio::test]
+async fn test_tagged_response() {
+    let mut api = ApiDescription::new();
+    api.register(catalog_get).unwrap();
+    let testctx = common::test_setup("tagged_response", api);
+    let client = &testctx.client_testctx;
+    let request = |if_none_match: &str| {
+        Request::builder()
+            .method(Method::GET)
+            .uri(client.url("/catalog"))
+            .header(http::header::IF_NONE_MATCH, if_none_match)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let mut response = client
+        .make_request_no_body(Method::GET, "/catalog", StatusCode::OK)
+        .await
+        .unwrap();
+    assert_eq!(response.headers()[http::header::ETAG], "\"7\"");
+    assert_eq!(read_string(&mut response).await, "[\"widget\"]");
+    assert_eq!(CATALOG_SERIALIZED.load(Ordering::SeqCst), 1);
+
+    // A client with the current version gets a 304 carrying the tag, and the
+    // body isn't even serialized.
+    let mut response = client
+        .make_request_with_request(
+            request("\"6\", W/\"7\""),
+            StatusCode::NOT_MODIFIED,
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.headers()[http::header::ETAG], "\"7\"");
+    assert_eq!(read_string(&mut response).await, "");
+    assert_eq!(CATALOG_SERIALIZED.load(Ordering::SeqCst), 1);
+
+    let mut response = client
+        .make_request_with_request(request("\"6\""), StatusCode::OK)
+        .await
+        .unwrap();
+    assert_eq!(read_string(&mut response).await, "[\"widget\"]");
+    assert_eq!(CATALOG_SERIALIZED.load(Ordering::SeqCst), 2);
+
+    // The ETag header is documented.
+    let mut api = ApiDescription::<usize>::new();
+    api.register(catalog_get).unwrap();
+    let spec = api.openapi("test", "1.0.0").json().unwrap();
+    let etag = &spec["paths"]["/catalog"]["get"]["responses"]["200"]["headers"]
+        ["ETag"];
+    assert_eq!(etag["required"], true);
+    assert_eq!(etag["schema"]["type"], "string");
+
+    testctx.teardown().await;
+}
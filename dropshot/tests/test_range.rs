@@ -0,0 +1,116 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for range requests.
+
+use dropshot::endpoint;
+use dropshot::test_util::read_string;
+use dropshot::ApiDescription;
+use dropshot::FreeformBody;
+use dropshot::HttpError;
+use dropshot::HttpResponsePartialContent;
+use dropshot::RangeRequest;
+use dropshot::RequestContext;
+use http::Method;
+use http::StatusCode;
+use hyper::Body;
+use hyper::Request;
+
+pub mod common;
+
+const BLOB: &str = "abcdefghijklmnopqrstuvwxyz";
+
+#[endpoint {
+    method = GET,
+    path = "/blob",
+}]
+async fn blob_get(
+    _rqctx: RequestContext<usize>,
+    range: RangeRequest,
+) -> Result<HttpResponsePartialContent<FreeformBody>, HttpError> {
+    let len = BLOB.len() as u64;
+    Ok(match range.single_range(len)? {
+        None => HttpResponsePartialContent::full(Body::from(BLOB).into()),
+        Some(r) => HttpResponsePartialContent::partial(
+            Body::from(&BLOB[r.start as usize..r.end as usize]).into(),
+            r,
+            len,
+        )?,
+    })
+}
+
+#[tokio::test]
+async fn test_range() {
+    let mut api = ApiDescription::new();
+    api.register(blob_get).unwrap();
+    let testctx = common::test_setup("range", api);
+    let client = &testctx.client_testctx;
+    let request = |range: Option<&str>| {
+        let mut builder =
+            Request::builder().method(Method::GET).uri(client.url("/blob"));
+        if let Some(range) = range {
+            builder = builder.header(http::header::RANGE, range);
+        }
+        builder.body(Body::empty()).unwrap()
+    };
+
+    let mut response = client
+        .make_request_with_request(request(None), StatusCode::OK)
+        .await
+        .unwrap();
+    assert_eq!(response.headers()[http::header::ACCEPT_RANGES], "bytes");
+    assert!(response.headers().get(http::header::CONTENT_RANGE).is_none());
+    assert_eq!(read_string(&mut response).await, BLOB);
+
+    let mut response = client
+        .make_request_with_request(
+            request(Some("bytes=2-5")),
+            StatusCode::PARTIAL_CONTENT,
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.headers()[http::header::CONTENT_RANGE], "bytes 2-5/26");
+    assert_eq!(read_string(&mut response).await, "cdef");
+
+    let mut response = client
+        .make_request_with_request(
+            request(Some("bytes=-3")),
+            StatusCode::PARTIAL_CONTENT,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers()[http::header::CONTENT_RANGE],
+        "bytes 23-25/26"
+    );
+    assert_eq!(read_string(&mut response).await, "xyz");
+
+    client
+        .make_request_with_request(
+            request(Some("bytes=100-")),
+            StatusCode::RANGE_NOT_SATISFIABLE,
+        )
+        .await
+        .unwrap_err();
+
+    testctx.teardown().await;
+}
+
+#[test]
+fn test_partial_content() {
+    let body = || FreeformBody::from(Body::from("bc"));
+    assert!(HttpResponsePartialContent::partial(body(), 1..3, 4).is_ok());
+    for (start, end) in [(1, 1), (3, 1), (1, 5)] {
+        let error = HttpResponsePartialContent::partial(body(), start..end, 4)
+            .err()
+            .unwrap();
+        assert_eq!(error.status_code, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    // Both statuses are documented.
+    let mut api = ApiDescription::new();
+    api.register(blob_get).unwrap();
+    let spec = api.openapi("test", "1.0.0").json().unwrap();
+    let responses = &spec["paths"]["/blob"]["get"]["responses"];
+    assert!(responses.get("200").is_some());
+    assert!(responses.get("206").is_some());
+}
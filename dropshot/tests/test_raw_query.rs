@@ -0,0 +1,59 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for the raw query string extractor.
+
+use dropshot::endpoint;
+use dropshot::test_util::read_json;
+use dropshot::ApiDescription;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::Query;
+use dropshot::RawQuery;
+use dropshot::RequestContext;
+use http::Method;
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+pub mod common;
+
+#[derive(Deserialize, JsonSchema)]
+struct DownloadArgs {
+    name: String,
+}
+
+#[endpoint {
+    method = GET,
+    path = "/download",
+}]
+async fn download(
+    _rqctx: RequestContext<usize>,
+    query: Query<DownloadArgs>,
+    raw_query: RawQuery,
+) -> Result<HttpResponseOk<(String, Option<String>)>, HttpError> {
+    Ok(HttpResponseOk((query.into_inner().name, raw_query.into_inner())))
+}
+
+#[tokio::test]
+async fn test_raw_query() {
+    let mut api = ApiDescription::new();
+    api.register(download).unwrap();
+    let testctx = common::test_setup("raw_query", api);
+    let client = &testctx.client_testctx;
+
+    // The raw query string keeps its encoding and parameter order.
+    let mut response = client
+        .make_request(
+            Method::GET,
+            "/download?sig=a%2Bb&name=my%20file+v2&expires=10",
+            None as Option<()>,
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+    let (name, raw): (String, Option<String>) = read_json(&mut response).await;
+    assert_eq!(name, "my file v2");
+    assert_eq!(raw.as_deref(), Some("sig=a%2Bb&name=my%20file+v2&expires=10"));
+
+    testctx.teardown().await;
+}
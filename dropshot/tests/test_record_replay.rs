@@ -0,0 +1,142 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for recording requests with `test_util::Recorder` and replaying
+//! them against another server with `Recording::replay`.
+
+use camino::Utf8PathBuf;
+use dropshot::endpoint;
+use dropshot::test_util::RecordedBody;
+use dropshot::test_util::Recorder;
+use dropshot::test_util::Recording;
+use dropshot::test_util::ReplayRules;
+use dropshot::test_util::TestContext;
+use dropshot::ApiDescription;
+use dropshot::ConfigDropshot;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::Path;
+use dropshot::RequestContext;
+use http::Method;
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use slog::o;
+use std::convert::TryFrom;
+use uuid::Uuid;
+
+pub mod common;
+
+#[derive(Deserialize, JsonSchema)]
+struct WidgetPath {
+    name: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Widget {
+    name: String,
+    /// differs for every response
+    id: Uuid,
+    /// the server's context, which differs between servers
+    generation: usize,
+}
+
+#[endpoint {
+    method = GET,
+    path = "/widgets/{name}",
+}]
+async fn widget_get(
+    rqctx: RequestContext<usize>,
+    path: Path<WidgetPath>,
+) -> Result<HttpResponseOk<Widget>, HttpError> {
+    let name = path.into_inner().name;
+    if name == "missing" {
+        return Err(HttpError::for_not_found(None, String::from("no widget")));
+    }
+    Ok(HttpResponseOk(Widget {
+        name,
+        id: Uuid::new_v4(),
+        generation: *rqctx.context(),
+    }))
+}
+
+fn start_server(test_name: &str, generation: usize) -> TestContext<usize> {
+    let mut api = ApiDescription::new();
+    api.register(widget_get).unwrap();
+    let logctx = common::create_log_context(test_name);
+    let log = logctx.log.new(o!());
+    TestContext::new(
+        api,
+        generation,
+        &ConfigDropshot::default(),
+        Some(logctx),
+        log,
+    )
+}
+
+#[tokio::test]
+async fn test_record_replay() {
+    // Record a couple of requests.
+    let mut testctx = start_server("record_replay_record", 0);
+    let recorder = Recorder::new();
+    testctx.client_testctx.record(&recorder);
+    testctx
+        .client_testctx
+        .make_request_no_body(Method::GET, "/widgets/gizmo", StatusCode::OK)
+        .await
+        .unwrap();
+    testctx
+        .client_testctx
+        .make_request_error(
+            Method::GET,
+            "/widgets/missing",
+            StatusCode::NOT_FOUND,
+        )
+        .await;
+    testctx.teardown().await;
+
+    let recording = recorder.recording();
+    assert_eq!(recording.exchanges.len(), 2);
+    let exchange = &recording.exchanges[0];
+    assert_eq!(exchange.request.method, "GET");
+    assert_eq!(exchange.request.path, "/widgets/gizmo");
+    assert_eq!(exchange.request.body, RecordedBody::Empty);
+    assert_eq!(exchange.response.status, 200);
+    match &exchange.response.body {
+        RecordedBody::Json(body) => assert_eq!(body["name"], "gizmo"),
+        body => panic!("unexpected body: {:?}", body),
+    }
+
+    // The recording survives a round trip through a file.
+    let dir = tempfile::tempdir().unwrap();
+    let path =
+        Utf8PathBuf::try_from(dir.path().join("recording.json")).unwrap();
+    recording.save(&path).unwrap();
+    let recording = Recording::load(&path).unwrap();
+    assert_eq!(recording, recorder.recording());
+
+    // Replaying against an equivalent server finds only the field that
+    // differs for every response, unless that field is ignored.
+    let testctx = start_server("record_replay_same", 0);
+    let differences =
+        recording.replay(&testctx.client_testctx, &ReplayRules::new()).await;
+    assert_eq!(differences.len(), 1);
+    assert_eq!(differences[0].index, 0);
+    assert_eq!(differences[0].problems.len(), 1);
+    assert!(differences[0].problems[0].starts_with("body/id: expected "));
+
+    let rules = ReplayRules::new().ignore_field("/id");
+    let differences = recording.replay(&testctx.client_testctx, &rules).await;
+    assert!(differences.is_empty(), "{:#?}", differences);
+    testctx.teardown().await;
+
+    // Replaying against a server that behaves differently reports how.
+    let testctx = start_server("record_replay_different", 1);
+    let differences = recording.replay(&testctx.client_testctx, &rules).await;
+    assert_eq!(differences.len(), 1);
+    assert_eq!(
+        differences[0].to_string(),
+        "request 0 (GET /widgets/gizmo): body/generation: expected 0, found 1"
+    );
+    testctx.teardown().await;
+}
@@ -0,0 +1,189 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for generating requests from endpoint descriptions with
+//! `test_util::RequestGenerator`.
+
+use dropshot::endpoint;
+use dropshot::test_util::RequestGenerator;
+use dropshot::ApiDescription;
+use dropshot::HttpError;
+use dropshot::HttpResponseCreated;
+use dropshot::HttpResponseOk;
+use dropshot::Path;
+use dropshot::Query;
+use dropshot::RequestContext;
+use dropshot::TypedBody;
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+pub mod common;
+
+#[derive(Deserialize, JsonSchema)]
+struct WidgetPath {
+    id: u32,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct WidgetQuery {
+    verbosity: Option<u8>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct Widget {
+    name: String,
+    count: u16,
+}
+
+#[endpoint {
+    method = GET,
+    path = "/widgets/{id}",
+}]
+async fn widget_get(
+    _rqctx: RequestContext<usize>,
+    path: Path<WidgetPath>,
+    _query: Query<WidgetQuery>,
+) -> Result<HttpResponseOk<Widget>, HttpError> {
+    let id = path.into_inner().id;
+    Ok(HttpResponseOk(Widget { name: format!("widget-{}", id), count: 0 }))
+}
+
+#[endpoint {
+    method = POST,
+    path = "/widgets",
+}]
+async fn widget_create(
+    _rqctx: RequestContext<usize>,
+    body: TypedBody<Widget>,
+) -> Result<HttpResponseCreated<Widget>, HttpError> {
+    Ok(HttpResponseCreated(body.into_inner()))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct CounterPath {
+    value: u8,
+}
+
+/// Mishandles the largest value its parameter allows.
+#[endpoint {
+    method = PUT,
+    path = "/counters/{value}",
+}]
+async fn counter_put(
+    _rqctx: RequestContext<usize>,
+    path: Path<CounterPath>,
+) -> Result<HttpResponseOk<u16>, HttpError> {
+    let value = path.into_inner().value;
+    if value == u8::MAX {
+        return Err(HttpError::for_internal_error(String::from("overflow")));
+    }
+    Ok(HttpResponseOk(u16::from(value) + 1))
+}
+
+/// A body whose schema no value can satisfy
+#[derive(Deserialize, JsonSchema)]
+struct Impossible {
+    #[allow(dead_code)]
+    #[schemars(length(min = 5, max = 2))]
+    name: String,
+    #[allow(dead_code)]
+    #[schemars(length(min = 3, max = 1))]
+    tags: Vec<String>,
+}
+
+#[endpoint {
+    method = POST,
+    path = "/impossible",
+}]
+async fn impossible_create(
+    _rqctx: RequestContext<usize>,
+    _body: TypedBody<Impossible>,
+) -> Result<HttpResponseOk<()>, HttpError> {
+    Ok(HttpResponseOk(()))
+}
+
+#[test]
+fn test_request_generator_generate() {
+    let mut api = ApiDescription::new();
+    api.register(widget_get).unwrap();
+    api.register(widget_create).unwrap();
+
+    let generator = RequestGenerator::new(&api).cases_per_endpoint(4);
+    let requests = generator.generate();
+    let valid = requests.iter().filter(|r| r.invalid_because.is_none());
+    assert_eq!(valid.count(), 8);
+
+    // Each parameter gets an out-of-range value, and the body is made invalid
+    // both syntactically and by omitting a required property.
+    let mut invalid = requests
+        .iter()
+        .filter_map(|r| {
+            r.invalid_because.as_ref().map(|_| r.operation_id.as_str())
+        })
+        .collect::<Vec<_>>();
+    invalid.sort_unstable();
+    assert_eq!(
+        invalid,
+        ["widget_create", "widget_create", "widget_get", "widget_get"]
+    );
+
+    // The same seed always produces the same requests.
+    let again = generator.generate();
+    assert_eq!(
+        requests.iter().map(|r| &r.path_and_query).collect::<Vec<_>>(),
+        again.iter().map(|r| &r.path_and_query).collect::<Vec<_>>(),
+    );
+    assert_eq!(
+        requests.iter().map(|r| &r.body).collect::<Vec<_>>(),
+        again.iter().map(|r| &r.body).collect::<Vec<_>>(),
+    );
+}
+
+#[tokio::test]
+async fn test_request_generator_check() {
+    let mut api = ApiDescription::new();
+    api.register(widget_get).unwrap();
+    api.register(widget_create).unwrap();
+
+    let generator = RequestGenerator::new(&api);
+    let testctx = common::test_setup("request_generator_check", api);
+    let violations = generator.check(&testctx.client_testctx).await;
+    assert!(violations.is_empty(), "{:#?}", violations);
+    testctx.teardown().await;
+}
+
+#[tokio::test]
+async fn test_request_generator_finds_server_errors() {
+    let mut api = ApiDescription::new();
+    api.register(counter_put).unwrap();
+
+    let generator = RequestGenerator::new(&api).cases_per_endpoint(64);
+    let testctx =
+        common::test_setup("request_generator_finds_server_errors", api);
+    let violations = generator.check(&testctx.client_testctx).await;
+    assert!(!violations.is_empty());
+    for violation in &violations {
+        assert_eq!(violation.request.path_and_query, "/counters/255");
+        assert_eq!(violation.status, Some(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(violation.request.invalid_because.is_none());
+    }
+    testctx.teardown().await;
+}
+
+#[test]
+fn test_request_generator_contradictory_bounds() {
+    let mut api = ApiDescription::new();
+    api.register(impossible_create).unwrap();
+
+    // A maximum below the minimum yields values of the minimum size.
+    let requests = RequestGenerator::new(&api).generate();
+    let body = requests
+        .iter()
+        .find(|r| r.invalid_because.is_none())
+        .and_then(|r| r.body.as_ref())
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(body["name"].as_str().unwrap().len(), 5);
+    assert_eq!(body["tags"].as_array().unwrap().len(), 3);
+}
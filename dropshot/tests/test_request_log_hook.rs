@@ -0,0 +1,129 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for enriching per-request loggers with
+//! `ApiDescription::request_log_hook`.
+
+use dropshot::endpoint;
+use dropshot::test_util::TestContext;
+use dropshot::ApiDescription;
+use dropshot::HttpError;
+use dropshot::HttpResponseUpdatedNoContent;
+use dropshot::RequestContext;
+use http::Method;
+use http::Request;
+use http::StatusCode;
+use hyper::Body;
+use slog::info;
+use slog::o;
+use slog::Drain;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// A log message and the key-value pairs attached to it.
+#[derive(Debug)]
+struct CapturedRecord {
+    msg: String,
+    kvs: Vec<(String, String)>,
+}
+
+/// Drain that keeps every record in memory so that the test can inspect it.
+struct CaptureDrain(Arc<Mutex<Vec<CapturedRecord>>>);
+
+struct CaptureSerializer(Vec<(String, String)>);
+
+impl slog::Serializer for CaptureSerializer {
+    fn emit_arguments(
+        &mut self,
+        key: slog::Key,
+        val: &std::fmt::Arguments<'_>,
+    ) -> slog::Result {
+        self.0.push((key.to_string(), val.to_string()));
+        Ok(())
+    }
+}
+
+impl Drain for CaptureDrain {
+    type Ok = ();
+    type Err = slog::Never;
+
+    fn log(
+        &self,
+        record: &slog::Record<'_>,
+        values: &slog::OwnedKVList,
+    ) -> Result<(), slog::Never> {
+        use slog::KV;
+
+        let mut serializer = CaptureSerializer(Vec::new());
+        record.kv().serialize(record, &mut serializer).unwrap();
+        values.serialize(record, &mut serializer).unwrap();
+        self.0.lock().unwrap().push(CapturedRecord {
+            msg: record.msg().to_string(),
+            kvs: serializer.0,
+        });
+        Ok(())
+    }
+}
+
+#[endpoint {
+    method = PUT,
+    path = "/projects",
+}]
+async fn api_project_put(
+    rqctx: RequestContext<usize>,
+) -> Result<HttpResponseUpdatedNoContent, HttpError> {
+    info!(rqctx.log, "updating project");
+    Ok(HttpResponseUpdatedNoContent())
+}
+
+#[tokio::test]
+async fn test_request_log_hook() {
+    let mut api = ApiDescription::new().request_log_hook(|rqctx| {
+        let tenant = rqctx
+            .request
+            .headers()
+            .get("x-tenant-id")
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("unknown")
+            .to_string();
+        rqctx.log.new(o!("tenant_id" => tenant))
+    });
+    api.register(api_project_put).unwrap();
+
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let log = slog::Logger::root(
+        CaptureDrain(Arc::clone(&records)).fuse(),
+        o!("test" => "request_log_hook"),
+    );
+    let testctx =
+        TestContext::new(api, 0_usize, &Default::default(), None, log);
+    let client = &testctx.client_testctx;
+
+    client
+        .make_request_with_request(
+            Request::builder()
+                .method(Method::PUT)
+                .uri(client.url("/projects"))
+                .header("x-tenant-id", "acme")
+                .body(Body::empty())
+                .unwrap(),
+            StatusCode::NO_CONTENT,
+        )
+        .await
+        .unwrap();
+    testctx.teardown().await;
+
+    let records = records.lock().unwrap();
+    let record = records
+        .iter()
+        .find(|record| record.msg == "updating project")
+        .expect("handler log message was not emitted");
+    let value = |key: &str| {
+        record.kvs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    };
+
+    // The handler's message carries the values added by the hook as well as
+    // those Dropshot adds to every request's logger.
+    assert_eq!(value("tenant_id"), Some("acme"));
+    assert!(value("req_id").is_some());
+    assert_eq!(value("test"), Some("request_log_hook"));
+}
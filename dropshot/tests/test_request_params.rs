@@ -0,0 +1,124 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for `#[derive(RequestParams)]`.
+
+use dropshot::endpoint;
+use dropshot::test_util::read_json;
+use dropshot::ApiDescription;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::RequestContext;
+use dropshot::RequestParams;
+use http::Method;
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+pub mod common;
+
+#[derive(RequestParams)]
+struct WidgetParams {
+    /// identifies the widget
+    #[dropshot(path)]
+    widget_id: u32,
+    #[dropshot(query)]
+    #[serde(rename = "show-parts")]
+    show_parts: Option<bool>,
+    #[dropshot(query)]
+    #[serde(default)]
+    limit: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
+struct Widget {
+    id: u32,
+    show_parts: Option<bool>,
+    limit: u32,
+}
+
+#[endpoint {
+    method = GET,
+    path = "/widgets/{widget_id}",
+}]
+async fn widget_get(
+    _rqctx: RequestContext<usize>,
+    params: WidgetParams,
+) -> Result<HttpResponseOk<Widget>, HttpError> {
+    Ok(HttpResponseOk(Widget {
+        id: params.widget_id,
+        show_parts: params.show_parts,
+        limit: params.limit,
+    }))
+}
+
+fn api() -> ApiDescription<usize> {
+    let mut api = ApiDescription::new();
+    api.register(widget_get).unwrap();
+    api
+}
+
+#[tokio::test]
+async fn test_request_params() {
+    let testctx = common::test_setup("request_params", api());
+    let client = &testctx.client_testctx;
+
+    let mut response = client
+        .make_request(
+            Method::GET,
+            "/widgets/12?show-parts=true&limit=3",
+            None as Option<()>,
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+    let widget: Widget = read_json(&mut response).await;
+    assert_eq!(widget, Widget { id: 12, show_parts: Some(true), limit: 3 });
+
+    let mut response = client
+        .make_request(
+            Method::GET,
+            "/widgets/12",
+            None as Option<()>,
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+    let widget: Widget = read_json(&mut response).await;
+    assert_eq!(widget, Widget { id: 12, show_parts: None, limit: 0 });
+
+    client
+        .make_request_error(
+            Method::GET,
+            "/widgets/twelve",
+            StatusCode::BAD_REQUEST,
+        )
+        .await;
+
+    testctx.teardown().await;
+}
+
+#[test]
+fn test_request_params_openapi() {
+    let spec = api().openapi("test", "1.0.0").json().unwrap();
+    let parameters = spec["paths"]["/widgets/{widget_id}"]["get"]["parameters"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| {
+            (
+                p["in"].as_str().unwrap().to_string(),
+                p["name"].as_str().unwrap().to_string(),
+                p["required"].as_bool().unwrap_or(false),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        parameters,
+        vec![
+            (String::from("path"), String::from("widget_id"), true),
+            (String::from("query"), String::from("limit"), false),
+            (String::from("query"), String::from("show-parts"), false),
+        ]
+    );
+}
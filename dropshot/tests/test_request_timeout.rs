@@ -0,0 +1,91 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for request timeouts.
+
+use dropshot::endpoint;
+use dropshot::test_util::TestContext;
+use dropshot::ApiDescription;
+use dropshot::ConfigDropshot;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::RequestContext;
+use http::Method;
+use http::StatusCode;
+use slog::o;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+pub mod common;
+
+struct ExampleContext {
+    /// notified when a handler that never finishes is cancelled
+    cancelled: Notify,
+}
+
+/// Notifies the context when dropped, i.e., when the handler holding it is
+/// cancelled.
+struct CancelGuard<'a>(&'a ExampleContext);
+
+impl Drop for CancelGuard<'_> {
+    fn drop(&mut self) {
+        self.0.cancelled.notify_one();
+    }
+}
+
+#[endpoint {
+    method = GET,
+    path = "/stuck",
+}]
+async fn api_stuck(
+    rqctx: RequestContext<ExampleContext>,
+) -> Result<HttpResponseOk<()>, HttpError> {
+    let _guard = CancelGuard(rqctx.context());
+    std::future::pending().await
+}
+
+#[endpoint {
+    method = GET,
+    path = "/patient",
+    request_timeout_ms = 60000,
+}]
+async fn api_patient(
+    _rqctx: RequestContext<ExampleContext>,
+) -> Result<HttpResponseOk<()>, HttpError> {
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    Ok(HttpResponseOk(()))
+}
+
+#[tokio::test]
+async fn test_request_timeout() {
+    let mut api = ApiDescription::new();
+    api.register(api_stuck).unwrap();
+    api.register(api_patient).unwrap();
+
+    let config =
+        ConfigDropshot { request_timeout_ms: Some(100), ..Default::default() };
+    let logctx = common::create_log_context("request_timeout");
+    let log = logctx.log.new(o!());
+    let context = ExampleContext { cancelled: Notify::new() };
+    let testctx = TestContext::new(api, context, &config, Some(logctx), log);
+    let client = &testctx.client_testctx;
+
+    // A handler that runs past the server's timeout is cancelled and the
+    // client is told the service is unavailable.
+    let error = client
+        .make_request_error(
+            Method::GET,
+            "/stuck",
+            StatusCode::SERVICE_UNAVAILABLE,
+        )
+        .await;
+    assert_eq!(error.message, "Service Unavailable");
+    testctx.server.app_private().cancelled.notified().await;
+
+    // An endpoint may allow itself longer than the server's timeout.
+    client
+        .make_request_no_body(Method::GET, "/patient", StatusCode::OK)
+        .await
+        .expect("expected success");
+
+    testctx.teardown().await;
+}
@@ -0,0 +1,90 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for endpoints declaring the media type of freeform responses.
+
+use dropshot::endpoint;
+use dropshot::test_util::read_string;
+use dropshot::ApiDescription;
+use dropshot::FreeformBody;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::RequestContext;
+use http::Method;
+use http::StatusCode;
+use hyper::Body;
+
+pub mod common;
+
+#[endpoint {
+    method = GET,
+    path = "/motd",
+    response_content_type = "text/plain; charset=utf-8",
+}]
+async fn motd_get(
+    _rqctx: RequestContext<usize>,
+) -> Result<HttpResponseOk<FreeformBody>, HttpError> {
+    Ok(HttpResponseOk(Body::from("hello\n").into()))
+}
+
+#[endpoint {
+    method = GET,
+    path = "/greeting",
+    response_content_type = "text/plain",
+}]
+async fn greeting_get(
+    _rqctx: RequestContext<usize>,
+) -> Result<HttpResponseOk<String>, HttpError> {
+    Ok(HttpResponseOk(String::from("hello")))
+}
+
+fn api() -> ApiDescription<usize> {
+    let mut api = ApiDescription::new();
+    api.register(motd_get).unwrap();
+    api.register(greeting_get).unwrap();
+    api
+}
+
+#[tokio::test]
+async fn test_response_content_type() {
+    let testctx = common::test_setup("response_content_type", api());
+    let client = &testctx.client_testctx;
+
+    let mut response = client
+        .make_request_no_body(Method::GET, "/motd", StatusCode::OK)
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers()[http::header::CONTENT_TYPE],
+        "text/plain; charset=utf-8"
+    );
+    assert_eq!(read_string(&mut response).await, "hello\n");
+
+    // Bodies that Dropshot serializes keep their own media type.
+    let response = client
+        .make_request_no_body(Method::GET, "/greeting", StatusCode::OK)
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers()[http::header::CONTENT_TYPE],
+        "application/json"
+    );
+
+    testctx.teardown().await;
+}
+
+#[test]
+fn test_response_content_type_openapi() {
+    let spec = api().openapi("test", "1.0.0").json().unwrap();
+    let content = spec["paths"]["/motd"]["get"]["responses"]["200"]["content"]
+        .as_object()
+        .unwrap();
+    let types = content.keys().map(String::as_str).collect::<Vec<_>>();
+    assert_eq!(types, ["text/plain; charset=utf-8"]);
+
+    let content = spec["paths"]["/greeting"]["get"]["responses"]["200"]
+        ["content"]
+        .as_object()
+        .unwrap();
+    let types = content.keys().map(String::as_str).collect::<Vec<_>>();
+    assert_eq!(types, ["application/json"]);
+}
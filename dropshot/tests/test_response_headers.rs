@@ -0,0 +1,251 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for typed response headers.
+
+use dropshot::endpoint;
+use dropshot::test_util::read_json;
+use dropshot::test_util::TEST_HEADER_1;
+use dropshot::test_util::TEST_HEADER_2;
+use dropshot::ApiDescription;
+use dropshot::CacheControl;
+use dropshot::EntityTag;
+use dropshot::HttpError;
+use dropshot::HttpResponseCached;
+use dropshot::HttpResponseCreated;
+use dropshot::HttpResponseCreatedAt;
+use dropshot::HttpResponseHeaders;
+use dropshot::HttpResponseOk;
+use dropshot::HttpResponsePartialContent;
+use dropshot::HttpResponseTagged;
+use dropshot::Path;
+use dropshot::RequestContext;
+use dropshot::TypedBody;
+use http::Method;
+use http::StatusCode;
+use hyper::Body;
+use hyper::Request;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use std::time::Duration;
+
+pub mod common;
+
+#[derive(JsonSchema, Serialize)]
+struct CountHeaders {
+    /// number of items
+    #[serde(rename = "x-dropshot-test-header-1")]
+    count: u32,
+    /// whether more items exist
+    #[serde(rename = "x-dropshot-test-header-2")]
+    more: Option<bool>,
+}
+
+#[endpoint {
+    method = GET,
+    path = "/items",
+}]
+async fn items_get(
+    _rqctx: RequestContext<usize>,
+) -> Result<
+    HttpResponseHeaders<HttpResponseOk<Vec<u32>>, CountHeaders>,
+    HttpError,
+> {
+    Ok(HttpResponseHeaders::new(
+        HttpResponseOk(vec![1, 2, 3]),
+        CountHeaders { count: 3, more: None },
+    ))
+}
+
+#[endpoint {
+    method = GET,
+    path = "/blob",
+}]
+async fn blob_get(
+    _rqctx: RequestContext<usize>,
+) -> Result<
+    HttpResponseHeaders<
+        HttpResponsePartialContent<dropshot::FreeformBody>,
+        CountHeaders,
+    >,
+    HttpError,
+> {
+    Ok(HttpResponseHeaders::new(
+        HttpResponsePartialContent::partial(Body::from("bc").into(), 1..3, 4)?,
+        CountHeaders { count: 2, more: Some(true) },
+    ))
+}
+
+#[derive(Deserialize, JsonSchema, Serialize)]
+struct ItemPath {
+    name: String,
+}
+
+#[endpoint {
+    method = GET,
+    path = "/items/{name}",
+}]
+async fn item_get(
+    _rqctx: RequestContext<usize>,
+    path: Path<ItemPath>,
+) -> Result<HttpResponseOk<String>, HttpError> {
+    Ok(HttpResponseOk(path.into_inner().name))
+}
+
+#[endpoint {
+    method = POST,
+    path = "/items",
+}]
+async fn item_create(
+    rqctx: RequestContext<usize>,
+    body: TypedBody<String>,
+) -> Result<HttpResponseCreatedAt<String>, HttpError> {
+    let name = body.into_inner();
+    let operation_id = if name.is_empty() { "item_view" } else { "item_get" };
+    HttpResponseCreated(name.clone()).with_location(
+        &rqctx,
+        operation_id,
+        &ItemPath { name },
+    )
+}
+
+#[endpoint {
+    method = GET,
+    path = "/regions",
+}]
+async fn regions_get(
+    _rqctx: RequestContext<usize>,
+) -> Result<
+    HttpResponseCached<HttpResponseTagged<HttpResponseOk<Vec<String>>>>,
+    HttpError,
+> {
+    Ok(HttpResponseCached::new(
+        CacheControl::new().public().max_age(Duration::from_secs(3600)),
+        HttpResponseTagged::new(
+            EntityTag::strong("1"),
+            HttpResponseOk(vec![String::from("us-east-1")]),
+        ),
+    ))
+}
+
+fn api() -> ApiDescription<usize> {
+    let mut api = ApiDescription::new();
+    api.register(items_get).unwrap();
+    api.register(blob_get).unwrap();
+    api.register(item_get).unwrap();
+    api.register(item_create).unwrap();
+    api.register(regions_get).unwrap();
+    api
+}
+
+#[tokio::test]
+async fn test_response_headers() {
+    let testctx = common::test_setup("response_headers", api());
+    let client = &testctx.client_testctx;
+
+    // Numbers are formatted, and an absent optional header is omitted.
+    let mut response = client
+        .make_request_no_body(Method::GET, "/items", StatusCode::OK)
+        .await
+        .unwrap();
+    assert_eq!(response.headers()[TEST_HEADER_1], "3");
+    assert!(response.headers().get(TEST_HEADER_2).is_none());
+    let items: Vec<u32> = read_json(&mut response).await;
+    assert_eq!(items, vec![1, 2, 3]);
+
+    // Headers can be added to responses other than the fixed wrappers.
+    let response = client
+        .make_request_no_body(Method::GET, "/blob", StatusCode::PARTIAL_CONTENT)
+        .await
+        .unwrap();
+    assert_eq!(response.headers()[TEST_HEADER_1], "2");
+    assert_eq!(response.headers()[TEST_HEADER_2], "true");
+    assert_eq!(response.headers()[http::header::CONTENT_RANGE], "bytes 1-2/4");
+
+    // The Location of a created resource is built from its endpoint's route.
+    let response = client
+        .make_request(
+            Method::POST,
+            "/items",
+            Some("new item"),
+            StatusCode::CREATED,
+        )
+        .await
+        .unwrap();
+    let location = response.headers()[http::header::LOCATION].clone();
+    assert_eq!(location, "/items/new%20item");
+    let mut response = client
+        .make_request_no_body(
+            Method::GET,
+            location.to_str().unwrap(),
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+    let name: String = read_json(&mut response).await;
+    assert_eq!(name, "new item");
+
+    // Naming an endpoint that doesn't exist is a server bug.
+    client
+        .make_request(
+            Method::POST,
+            "/items",
+            Some(""),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )
+        .await
+        .unwrap_err();
+
+    // Cache-Control is sent with both full responses and revalidations.
+    let response = client
+        .make_request_no_body(Method::GET, "/regions", StatusCode::OK)
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers()[http::header::CACHE_CONTROL],
+        "public, max-age=3600"
+    );
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(client.url("/regions"))
+        .header(http::header::IF_NONE_MATCH, "\"1\"")
+        .body(Body::empty())
+        .unwrap();
+    let response = client
+        .make_request_with_request(request, StatusCode::NOT_MODIFIED)
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers()[http::header::CACHE_CONTROL],
+        "public, max-age=3600"
+    );
+
+    testctx.teardown().await;
+}
+
+#[test]
+fn test_response_headers_openapi() {
+    let spec = api().openapi("test", "1.0.0").json().unwrap();
+    for path in ["/items", "/blob"] {
+        let headers =
+            &spec["paths"][path]["get"]["responses"]["200"]["headers"];
+        let count = &headers[TEST_HEADER_1];
+        assert_eq!(count["description"], "number of items");
+        assert_eq!(count["required"], true);
+        assert_eq!(count["schema"]["type"], "integer");
+        let more = &headers[TEST_HEADER_2];
+        assert_eq!(more["description"], "whether more items exist");
+        assert_eq!(more["schema"]["type"], "boolean");
+        assert_ne!(more["required"], true);
+    }
+
+    let location = &spec["paths"]["/items"]["post"]["responses"]["201"]
+        ["headers"]["location"];
+    assert_eq!(location["required"], true);
+    assert_eq!(location["schema"]["type"], "string");
+
+    let cache_control = &spec["paths"]["/regions"]["get"]["responses"]["200"]
+        ["headers"]["Cache-Control"];
+    assert_eq!(cache_control["required"], true);
+    assert_eq!(cache_control["schema"]["type"], "string");
+}
@@ -0,0 +1,111 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for responses with a status chosen by the handler.
+
+use dropshot::endpoint;
+use dropshot::test_util::read_json;
+use dropshot::ApiDescription;
+use dropshot::HttpError;
+use dropshot::HttpResponseWithStatus;
+use dropshot::Path;
+use dropshot::RequestContext;
+use dropshot::ResponseStatuses;
+use http::Method;
+use http::StatusCode;
+use hyper::Body;
+use hyper::Request;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+pub mod common;
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct Widget {
+    name: String,
+}
+
+struct CreatedOrConflict;
+impl ResponseStatuses for CreatedOrConflict {
+    const STATUS_CODES: &'static [StatusCode] =
+        &[StatusCode::CREATED, StatusCode::CONFLICT];
+    const DESCRIPTION: &'static str =
+        "the new widget, or the existing one with the same name";
+}
+
+#[endpoint {
+    method = PUT,
+    path = "/widgets/{name}",
+}]
+async fn widget_put(
+    _rqctx: RequestContext<usize>,
+    path: Path<Widget>,
+) -> Result<HttpResponseWithStatus<Widget, CreatedOrConflict>, HttpError> {
+    let widget = path.into_inner();
+    match widget.name.as_str() {
+        "existing" => HttpResponseWithStatus::new(StatusCode::CONFLICT, widget),
+        "teapot" => {
+            HttpResponseWithStatus::new(StatusCode::IM_A_TEAPOT, widget)
+        }
+        _ => HttpResponseWithStatus::new(StatusCode::CREATED, widget),
+    }
+}
+
+fn api() -> ApiDescription<usize> {
+    let mut api = ApiDescription::new();
+    api.register(widget_put).unwrap();
+    api
+}
+
+#[tokio::test]
+async fn test_response_status() {
+    let testctx = common::test_setup("response_status", api());
+    let client = &testctx.client_testctx;
+
+    let mut response = client
+        .make_request_no_body(Method::PUT, "/widgets/new", StatusCode::CREATED)
+        .await
+        .unwrap();
+    let widget: Widget = read_json(&mut response).await;
+    assert_eq!(widget.name, "new");
+
+    // The test client expects 4xx responses to carry an error, so make this
+    // request directly.
+    let request = Request::builder()
+        .method(Method::PUT)
+        .uri(client.url("/widgets/existing"))
+        .body(Body::empty())
+        .unwrap();
+    let mut response = hyper::Client::new().request(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let widget: Widget = read_json(&mut response).await;
+    assert_eq!(widget.name, "existing");
+
+    // A status that wasn't declared is a bug in the handler.
+    client
+        .make_request_error(
+            Method::PUT,
+            "/widgets/teapot",
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )
+        .await;
+
+    testctx.teardown().await;
+}
+
+#[test]
+fn test_response_status_openapi() {
+    let spec = api().openapi("test", "1.0.0").json().unwrap();
+    let responses = &spec["paths"]["/widgets/{name}"]["put"]["responses"];
+    for status in ["201", "409"] {
+        assert_eq!(
+            responses[status]["description"],
+            "the new widget, or the existing one with the same name"
+        );
+        assert_eq!(
+            responses[status]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/Widget"
+        );
+    }
+    assert_eq!(responses["4XX"]["$ref"], "#/components/responses/Error");
+}
@@ -0,0 +1,174 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for shutting down a server while it's handling requests.
+
+use dropshot::endpoint;
+use dropshot::ApiDescription;
+use dropshot::ConfigDropshot;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::HttpServer;
+use dropshot::HttpServerStarter;
+use dropshot::RequestContext;
+use slog::o;
+use slog::Logger;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+pub mod common;
+
+struct ExampleContext {
+    /// notified when the slow handler has started
+    entered: Notify,
+    /// notified to let the slow handler finish
+    release: Arc<Notify>,
+}
+
+#[endpoint {
+    method = GET,
+    path = "/slow",
+}]
+async fn slow(
+    rqctx: RequestContext<ExampleContext>,
+) -> Result<HttpResponseOk<()>, HttpError> {
+    rqctx.context().entered.notify_one();
+    rqctx.context().release.notified().await;
+    Ok(HttpResponseOk(()))
+}
+
+fn make_server(log: &Logger) -> HttpServer<ExampleContext> {
+    let mut api = ApiDescription::new();
+    api.register(slow).unwrap();
+    let context = ExampleContext {
+        entered: Notify::new(),
+        release: Arc::new(Notify::new()),
+    };
+    HttpServerStarter::new(&ConfigDropshot::default(), api, context, log)
+        .unwrap()
+        .start()
+}
+
+/// Starts a request to the slow endpoint, returning once its handler is
+/// running.
+async fn start_slow_request(
+    server: &HttpServer<ExampleContext>,
+) -> tokio::task::JoinHandle<Result<hyper::Response<hyper::Body>, hyper::Error>>
+{
+    let client = hyper::Client::new();
+    let uri = format!("http://{}/slow", server.local_addr()).parse().unwrap();
+    let request = tokio::spawn(async move { client.get(uri).await });
+    server.app_private().entered.notified().await;
+    request
+}
+
+#[tokio::test]
+async fn test_shutdown_drains_requests() {
+    let logctx = common::create_log_context("shutdown_drains_requests");
+    let log = logctx.log.new(o!());
+    let server = make_server(&log);
+    let request = start_slow_request(&server).await;
+
+    // The request in progress completes normally as long as it finishes
+    // within the drain timeout.
+    let release = Arc::clone(&server.app_private().release);
+    let shutdown = server.wait_for_shutdown();
+    let closing = tokio::spawn(server.shutdown(Duration::from_secs(60)));
+    release.notify_one();
+    let response = request.await.unwrap().unwrap();
+    assert_eq!(response.status(), http::StatusCode::OK);
+    closing.await.unwrap().unwrap();
+    shutdown.await.unwrap();
+
+    logctx.cleanup_successful();
+}
+
+#[tokio::test]
+async fn test_shutdown_aborts_stragglers() {
+    let logctx = common::create_log_context("shutdown_aborts_stragglers");
+    let log = logctx.log.new(o!());
+    let server = make_server(&log);
+    let local_addr = server.local_addr();
+    let request = start_slow_request(&server).await;
+
+    // The request is never released, so its connection is aborted once the
+    // drain timeout expires.
+    let shutdown = server.wait_for_shutdown();
+    server.shutdown(Duration::from_millis(100)).await.unwrap();
+    shutdown.await.unwrap();
+    request.await.unwrap().unwrap_err();
+
+    // The server no longer accepts connections.
+    let uri = format!("http://{}/slow", local_addr).parse().unwrap();
+    let error = hyper::Client::new().get(uri).await.unwrap_err();
+    assert!(error.is_connect());
+
+    logctx.cleanup_successful();
+}
+
+#[tokio::test]
+async fn test_shutdown_handle() {
+    let logctx = common::create_log_context("shutdown_handle");
+    let log = logctx.log.new(o!());
+    let server = make_server(&log);
+    let request = start_slow_request(&server).await;
+
+    // Shutting down through a handle lets the request in progress complete,
+    // while whoever owns the server just waits for it to stop.
+    let handle = server.shutdown_handle();
+    let release = Arc::clone(&server.app_private().release);
+    let running = tokio::spawn(server);
+    handle.clone().shutdown();
+    release.notify_one();
+    let response = request.await.unwrap().unwrap();
+    assert_eq!(response.status(), http::StatusCode::OK);
+    running.await.unwrap().unwrap();
+    handle.wait_for_shutdown().await.unwrap();
+
+    // Shutting down again is harmless.
+    handle.shutdown();
+
+    logctx.cleanup_successful();
+}
+
+#[tokio::test]
+async fn test_shutdown_handle_abort() {
+    let logctx = common::create_log_context("shutdown_handle_abort");
+    let log = logctx.log.new(o!());
+    let server = make_server(&log);
+    let request = start_slow_request(&server).await;
+
+    let handle = server.shutdown_handle();
+    handle.abort();
+    request.await.unwrap().unwrap_err();
+    server.close().await.unwrap();
+
+    logctx.cleanup_successful();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_shutdown_on_signals() {
+    let logctx = common::create_log_context("shutdown_on_signals");
+    let log = logctx.log.new(o!());
+    let server = make_server(&log);
+    let request = start_slow_request(&server).await;
+
+    // The first signal starts a graceful shutdown, so the request in progress
+    // carries on.  The second aborts it.
+    let signals = server.shutdown_handle().shutdown_on_signals().unwrap();
+    let shutdown = server.wait_for_shutdown();
+    let raise = |signal| {
+        // Safety: kill(2) has no memory safety requirements.
+        assert_eq!(unsafe { libc::kill(libc::getpid(), signal) }, 0);
+    };
+    raise(libc::SIGTERM);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!request.is_finished());
+    raise(libc::SIGINT);
+    request.await.unwrap().unwrap_err();
+    shutdown.await.unwrap();
+    signals.await.unwrap();
+
+    logctx.cleanup_successful();
+}
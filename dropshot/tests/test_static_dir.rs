@@ -0,0 +1,99 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for serving a static directory.
+
+use dropshot::test_util::read_string;
+use dropshot::ApiDescription;
+use http::Method;
+use http::StatusCode;
+use hyper::Body;
+use hyper::Request;
+
+pub mod common;
+
+#[tokio::test]
+async fn test_static_dir() {
+    let outside = tempfile::tempdir().unwrap();
+    std::fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("index.html"), "<h1>console</h1>").unwrap();
+    std::fs::create_dir(dir.path().join("css")).unwrap();
+    std::fs::write(dir.path().join("css/site.css"), "h1 {}").unwrap();
+    std::fs::create_dir(dir.path().join("empty")).unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(
+        outside.path().join("secret.txt"),
+        dir.path().join("secret.txt"),
+    )
+    .unwrap();
+
+    let mut api = ApiDescription::new();
+    api.register_static_dir("/console", dir.path()).unwrap();
+    assert!(api
+        .register_static_dir("/other", dir.path().join("index.html"))
+        .is_err());
+    let testctx = common::test_setup("static_dir", api);
+    let client = &testctx.client_testctx;
+
+    // A directory is served its index file.
+    for path in ["/console", "/console/"] {
+        let mut response = client
+            .make_request_no_body(Method::GET, path, StatusCode::OK)
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[http::header::CONTENT_TYPE], "text/html");
+        assert_eq!(read_string(&mut response).await, "<h1>console</h1>");
+    }
+
+    let mut response = client
+        .make_request_no_body(
+            Method::GET,
+            "/console/css/site.css",
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.headers()[http::header::CONTENT_TYPE], "text/css");
+    let etag = response.headers()[http::header::ETAG].clone();
+    assert!(etag.to_str().unwrap().starts_with("W/\""));
+    let last_modified = response.headers()[http::header::LAST_MODIFIED].clone();
+    assert_eq!(read_string(&mut response).await, "h1 {}");
+
+    // A client with a current copy gets a 304 carrying the same validators.
+    for (header, value) in [
+        (http::header::IF_NONE_MATCH, &etag),
+        (http::header::IF_MODIFIED_SINCE, &last_modified),
+    ] {
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(client.url("/console/css/site.css"))
+            .header(header, value)
+            .body(Body::empty())
+            .unwrap();
+        let mut response = client
+            .make_request_with_request(request, StatusCode::NOT_MODIFIED)
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[http::header::ETAG], etag);
+        assert_eq!(
+            response.headers()[http::header::LAST_MODIFIED],
+            last_modified
+        );
+        assert_eq!(read_string(&mut response).await, "");
+    }
+
+    // Nothing outside the directory is reachable.
+    for path in [
+        "/console/missing.js",
+        "/console/empty",
+        "/console/css/%2e%2e/%2e%2e/secret.txt",
+        "/console/css%2f..%2f..%2fsecret.txt",
+        "/console/secret.txt",
+    ] {
+        client
+            .make_request_error(Method::GET, path, StatusCode::NOT_FOUND)
+            .await;
+    }
+
+    testctx.teardown().await;
+}
@@ -2,8 +2,16 @@
 
 //! Test cases for streaming requests.
 
-use dropshot::{endpoint, ApiDescription, HttpError, RequestContext};
-use http::{Method, Response, StatusCode};
+use bytes::Bytes;
+use dropshot::test_util::read_ndjson;
+use dropshot::{
+    endpoint, ApiDescription, ChecksumAlgorithm, FreeformBody, HttpError,
+    HttpResponseNdjson, HttpResponseOk, RequestContext, StreamingBody,
+    HEADER_CONTENT_DIGEST,
+};
+use futures::StreamExt;
+use futures::TryStreamExt;
+use http::{Method, Request, Response, StatusCode};
 use hyper::{body::HttpBody, Body};
 use hyper_staticfile::FileBytesStream;
 use tokio::io::{AsyncSeekExt, AsyncWriteExt};
@@ -16,6 +24,11 @@ fn api() -> ApiDescription<usize> {
     let mut api = ApiDescription::new();
     api.register(api_streaming).unwrap();
     api.register(api_not_streaming).unwrap();
+    api.register(api_async_read).unwrap();
+    api.register(api_from_stream).unwrap();
+    api.register(api_ndjson).unwrap();
+    api.register(api_ndjson_error).unwrap();
+    api.register(api_checksum_upload).unwrap();
     api
 }
 
@@ -64,6 +77,85 @@ async fn api_not_streaming(
         .body(serde_json::to_string("not-streaming").unwrap().into())?)
 }
 
+#[endpoint {
+    method = GET,
+    path = "/async-read",
+}]
+async fn api_async_read(
+    _rqctx: RequestContext<usize>,
+) -> Result<HttpResponseOk<FreeformBody>, HttpError> {
+    let (mut writer, reader) = tokio::io::duplex(BUF_SIZE);
+
+    // Produce the body incrementally; the writer can only get ahead of the
+    // client by the size of the pipe.
+    tokio::spawn(async move {
+        let mut buf = [0; BUF_SIZE];
+        for i in 0..BUF_COUNT {
+            buf.fill((i & 255) as u8);
+            writer.write_all(&buf).await.unwrap();
+        }
+    });
+
+    Ok(HttpResponseOk(FreeformBody::from_async_read(reader)))
+}
+
+#[endpoint {
+    method = GET,
+    path = "/from-stream",
+}]
+async fn api_from_stream(
+    _rqctx: RequestContext<usize>,
+) -> Result<HttpResponseOk<FreeformBody>, HttpError> {
+    let chunks = futures::stream::iter(0..BUF_COUNT).map(|i| {
+        Ok::<_, std::io::Error>(Bytes::from(vec![(i & 255) as u8; BUF_SIZE]))
+    });
+    Ok(HttpResponseOk(FreeformBody::from_stream(chunks)))
+}
+
+#[endpoint {
+    method = GET,
+    path = "/ndjson",
+}]
+async fn api_ndjson(
+    _rqctx: RequestContext<usize>,
+) -> Result<HttpResponseNdjson<usize>, HttpError> {
+    let items =
+        futures::stream::iter(0..BUF_COUNT).map(Ok::<_, std::io::Error>);
+    Ok(HttpResponseNdjson::new(items))
+}
+
+#[endpoint {
+    method = GET,
+    path = "/ndjson-error",
+}]
+async fn api_ndjson_error(
+    _rqctx: RequestContext<usize>,
+) -> Result<HttpResponseNdjson<usize>, HttpError> {
+    let items = futures::stream::iter(0..BUF_COUNT).map(|i| {
+        if i < BUF_COUNT / 2 {
+            Ok(i)
+        } else {
+            Err(std::io::Error::new(std::io::ErrorKind::Other, "scan failed"))
+        }
+    });
+    Ok(HttpResponseNdjson::new(items))
+}
+
+#[endpoint {
+    method = PUT,
+    path = "/checksum-upload",
+}]
+async fn api_checksum_upload(
+    _rqctx: RequestContext<usize>,
+    body: StreamingBody,
+) -> Result<HttpResponseOk<usize>, HttpError> {
+    let nbytes = body
+        .into_stream_with_checksum(ChecksumAlgorithm::Sha256)
+        .try_fold(0, |nbytes, chunk| async move { Ok(nbytes + chunk.len()) })
+        .await?;
+    Ok(HttpResponseOk(nbytes))
+}
+
 fn check_has_transfer_encoding(
     response: &Response<Body>,
     expected_value: Option<&str>,
@@ -156,3 +248,119 @@ async fn test_non_streaming_servers_do_not_use_transfer_encoding() {
     check_has_transfer_encoding(&response, None);
     testctx.teardown().await;
 }
+
+#[tokio::test]
+async fn test_streaming_from_async_read() {
+    let api = api();
+    let testctx = common::test_setup("streaming_from_async_read", api);
+    let client = &testctx.client_testctx;
+
+    let mut response = client
+        .make_request_no_body(Method::GET, "/async-read", StatusCode::OK)
+        .await
+        .expect("Expected GET request to succeed");
+    check_has_transfer_encoding(&response, Some("chunked"));
+
+    let body_bytes = hyper::body::to_bytes(response.body_mut())
+        .await
+        .expect("Error reading body");
+    assert_eq!(BUF_SIZE * BUF_COUNT, body_bytes.len());
+    for (i, chunk) in body_bytes.chunks(BUF_SIZE).enumerate() {
+        assert!(chunk.iter().all(|b| *b == (i & 255) as u8));
+    }
+
+    testctx.teardown().await;
+}
+
+#[tokio::test]
+async fn test_streaming_from_stream() {
+    let api = api();
+    let testctx = common::test_setup("streaming_from_stream", api);
+    let client = &testctx.client_testctx;
+
+    let mut response = client
+        .make_request_no_body(Method::GET, "/from-stream", StatusCode::OK)
+        .await
+        .expect("Expected GET request to succeed");
+    check_has_transfer_encoding(&response, Some("chunked"));
+
+    let body_bytes = hyper::body::to_bytes(response.body_mut())
+        .await
+        .expect("Error reading body");
+    assert_eq!(BUF_SIZE * BUF_COUNT, body_bytes.len());
+    for (i, chunk) in body_bytes.chunks(BUF_SIZE).enumerate() {
+        assert!(chunk.iter().all(|b| *b == (i & 255) as u8));
+    }
+
+    testctx.teardown().await;
+}
+
+#[tokio::test]
+async fn test_streaming_ndjson() {
+    let api = api();
+    let testctx = common::test_setup("streaming_ndjson", api);
+    let client = &testctx.client_testctx;
+
+    let mut response = client
+        .make_request_no_body(Method::GET, "/ndjson", StatusCode::OK)
+        .await
+        .expect("Expected GET request to succeed");
+    check_has_transfer_encoding(&response, Some("chunked"));
+    let items: Vec<usize> = read_ndjson(&mut response).await;
+    assert_eq!(items, (0..BUF_COUNT).collect::<Vec<_>>());
+
+    // An error partway through aborts the response rather than ending it
+    // cleanly.
+    let mut response = client
+        .make_request_no_body(Method::GET, "/ndjson-error", StatusCode::OK)
+        .await
+        .expect("Expected GET request to succeed");
+    assert!(hyper::body::to_bytes(response.body_mut()).await.is_err());
+
+    testctx.teardown().await;
+}
+
+#[tokio::test]
+async fn test_streaming_upload_checksum() {
+    let api = api();
+    let testctx = common::test_setup("streaming_upload_checksum", api);
+    let client = &testctx.client_testctx;
+
+    // Digest of "hello world"
+    let good_digest = "sha-256=:uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=:";
+    let upload = |digest: Option<&str>, expected_status| {
+        let mut request = Request::builder()
+            .method(Method::PUT)
+            .uri(client.url("/checksum-upload"));
+        if let Some(digest) = digest {
+            request = request.header(HEADER_CONTENT_DIGEST, digest);
+        }
+        client.make_request_with_request(
+            request.body(Body::from("hello world")).unwrap(),
+            expected_status,
+        )
+    };
+
+    let mut response = upload(Some(good_digest), StatusCode::OK).await.unwrap();
+    let nbytes: usize = dropshot::test_util::read_json(&mut response).await;
+    assert_eq!(nbytes, 11);
+
+    let error = upload(
+        Some("sha-256=:47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=:"),
+        StatusCode::BAD_REQUEST,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        error.message,
+        "request body does not match its sha-256 checksum"
+    );
+
+    let error = upload(None, StatusCode::BAD_REQUEST).await.unwrap_err();
+    assert_eq!(
+        error.message,
+        "request has no sha-256 checksum in content-digest"
+    );
+
+    testctx.teardown().await;
+}
@@ -0,0 +1,147 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for rejecting unknown query parameters.
+
+use dropshot::endpoint;
+use dropshot::test_util::read_json;
+use dropshot::ApiDescription;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::Query;
+use dropshot::RequestContext;
+use http::Method;
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+pub mod common;
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct ListArgs {
+    limit: Option<u32>,
+    name: Option<String>,
+}
+
+#[endpoint {
+    method = GET,
+    path = "/strict",
+    strict_query = true,
+}]
+async fn list_strict(
+    _rqctx: RequestContext<usize>,
+    query: Query<ListArgs>,
+) -> Result<HttpResponseOk<ListArgs>, HttpError> {
+    Ok(HttpResponseOk(query.into_inner()))
+}
+
+#[endpoint {
+    method = GET,
+    path = "/lenient",
+    strict_query = false,
+}]
+async fn list_lenient(
+    _rqctx: RequestContext<usize>,
+    query: Query<ListArgs>,
+) -> Result<HttpResponseOk<ListArgs>, HttpError> {
+    Ok(HttpResponseOk(query.into_inner()))
+}
+
+#[endpoint {
+    method = GET,
+    path = "/default",
+}]
+async fn list_default(
+    _rqctx: RequestContext<usize>,
+    query: Query<ListArgs>,
+) -> Result<HttpResponseOk<ListArgs>, HttpError> {
+    Ok(HttpResponseOk(query.into_inner()))
+}
+
+fn api(mut api: ApiDescription<usize>) -> ApiDescription<usize> {
+    api.register(list_strict).unwrap();
+    api.register(list_lenient).unwrap();
+    api.register(list_default).unwrap();
+    api
+}
+
+#[tokio::test]
+async fn test_strict_query() {
+    let testctx =
+        common::test_setup("strict_query", api(ApiDescription::new()));
+    let client = &testctx.client_testctx;
+
+    // Known parameters are accepted.
+    let mut response = client
+        .make_request(
+            Method::GET,
+            "/strict?limit=10&name=foo",
+            None as Option<()>,
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+    let args: ListArgs = read_json(&mut response).await;
+    assert_eq!(args.limit, Some(10));
+    assert_eq!(args.name.as_deref(), Some("foo"));
+
+    // Error case: unknown parameters are listed once each.
+    let error = client
+        .make_request(
+            Method::GET,
+            "/strict?limt=10&name=foo&sort=asc&limt=20",
+            None as Option<()>,
+            StatusCode::BAD_REQUEST,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(error.message, "unknown query parameters: limt, sort");
+
+    // Unknown parameters are otherwise ignored.
+    for path in ["/lenient?limt=10", "/default?limt=10"] {
+        let mut response = client
+            .make_request(Method::GET, path, None as Option<()>, StatusCode::OK)
+            .await
+            .unwrap();
+        let args: ListArgs = read_json(&mut response).await;
+        assert_eq!(args.limit, None);
+    }
+
+    testctx.teardown().await;
+}
+
+#[tokio::test]
+async fn test_strict_query_global() {
+    let testctx = common::test_setup(
+        "strict_query_global",
+        api(ApiDescription::new().strict_query(true)),
+    );
+    let client = &testctx.client_testctx;
+
+    // Endpoints that don't say otherwise inherit the description's setting.
+    let error = client
+        .make_request(
+            Method::GET,
+            "/default?limt=10",
+            None as Option<()>,
+            StatusCode::BAD_REQUEST,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(error.message, "unknown query parameters: limt");
+
+    // An endpoint may opt out.
+    let mut response = client
+        .make_request(
+            Method::GET,
+            "/lenient?limt=10&limit=5",
+            None as Option<()>,
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+    let args: ListArgs = read_json(&mut response).await;
+    assert_eq!(args.limit, Some(5));
+
+    testctx.teardown().await;
+}
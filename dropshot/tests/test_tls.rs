@@ -3,7 +3,10 @@
 //! Test cases for TLS support. This validates various behaviors of our TLS
 //! mode, including certificate loading and supported modes.
 
-use dropshot::{ConfigDropshot, ConfigTls, HttpResponseOk, HttpServerStarter};
+use dropshot::{
+    ConfigDropshot, ConfigTls, ConfigTlsClientAuth, HttpResponseOk,
+    HttpServerStarter,
+};
 use slog::{o, Logger};
 use std::convert::TryFrom;
 use std::path::Path;
@@ -71,11 +74,19 @@ fn make_server(
 ) -> HttpServerStarter<i32> {
     let config = ConfigDropshot {
         bind_address: "127.0.0.1:0".parse().unwrap(),
+        additional_bind_addresses: Vec::new(),
+        unix_socket: None,
         request_body_max_bytes: 1024,
+        request_timeout_ms: None,
         tls: Some(ConfigTls::AsFile {
             cert_file: cert_file.to_path_buf(),
             key_file: key_file.to_path_buf(),
         }),
+        tls_client_auth: None,
+        http2: Default::default(),
+        load_shedding: None,
+        external_url: None,
+        trusted_proxies: Vec::new(),
     };
     HttpServerStarter::new(&config, dropshot::ApiDescription::new(), 0, log)
         .unwrap()
@@ -143,6 +154,69 @@ async fn test_tls_certificate_loading() {
     logctx.cleanup_successful();
 }
 
+#[tokio::test]
+async fn test_tls_der_certificates() {
+    let logctx = create_log_context("test_tls_der_certificates");
+    let log = logctx.log.new(o!());
+
+    // Generate key for the server, used directly in DER form.
+    let (certs, key) = common::generate_tls_key();
+    let config = ConfigDropshot {
+        bind_address: "127.0.0.1:0".parse().unwrap(),
+        additional_bind_addresses: Vec::new(),
+        unix_socket: None,
+        request_body_max_bytes: 1024,
+        request_timeout_ms: None,
+        tls: Some(ConfigTls::AsDer {
+            certs: certs.iter().map(|cert| cert.0.clone()).collect(),
+            key: key.0.clone(),
+        }),
+        tls_client_auth: None,
+        http2: Default::default(),
+        load_shedding: None,
+        external_url: None,
+        trusted_proxies: Vec::new(),
+    };
+    let server = HttpServerStarter::new(
+        &config,
+        dropshot::ApiDescription::new(),
+        0,
+        &log,
+    )
+    .unwrap()
+    .start();
+    let port = server.local_addr().port();
+
+    let uri: hyper::Uri =
+        format!("https://localhost:{}/", port).parse().unwrap();
+    let request = hyper::Request::builder()
+        .method(http::method::Method::GET)
+        .uri(&uri)
+        .body(hyper::Body::empty())
+        .unwrap();
+    let client = make_https_client(make_pki_verifier(&certs));
+    client.request(request).await.unwrap();
+
+    // An empty certificate chain is rejected when the server is created.
+    let config = ConfigDropshot {
+        tls: Some(ConfigTls::AsDer { certs: vec![], key: key.0 }),
+        ..config
+    };
+    let error = HttpServerStarter::new(
+        &config,
+        dropshot::ApiDescription::new(),
+        0,
+        &log,
+    )
+    .map(|_| ())
+    .unwrap_err();
+    assert!(error.to_string().contains("expected at least one certificate"));
+
+    server.close().await.unwrap();
+
+    logctx.cleanup_successful();
+}
+
 #[tokio::test]
 async fn test_tls_only() {
     let logctx = create_log_context("test_tls_only");
@@ -279,6 +353,17 @@ async fn test_tls_refresh_certificates() {
     let https_client = make_https_client(make_cert_verifier(new_certs.clone()));
     https_client.request(https_request_maker()).await.unwrap();
 
+    // A configuration that can't be loaded is reported, and the server keeps
+    // using the certificate chain it had.
+    let bad_config = ConfigTls::AsDer {
+        certs: new_certs.iter().map(|cert| cert.0.clone()).collect(),
+        key: vec![1, 2, 3],
+    };
+    let error = server.refresh_tls(&bad_config).await.unwrap_err();
+    assert!(error.contains("invalid certificate or key"), "{}", error);
+    let https_client = make_https_client(make_cert_verifier(new_certs.clone()));
+    https_client.request(https_request_maker()).await.unwrap();
+
     server.close().await.unwrap();
     logctx.cleanup_successful();
 }
@@ -370,11 +455,19 @@ async fn test_server_is_https() {
 
     let config = ConfigDropshot {
         bind_address: "127.0.0.1:0".parse().unwrap(),
+        additional_bind_addresses: Vec::new(),
+        unix_socket: None,
         request_body_max_bytes: 1024,
+        request_timeout_ms: None,
         tls: Some(ConfigTls::AsFile {
             cert_file: cert_file.path().to_path_buf(),
             key_file: key_file.path().to_path_buf(),
         }),
+        tls_client_auth: None,
+        http2: Default::default(),
+        load_shedding: None,
+        external_url: None,
+        trusted_proxies: Vec::new(),
     };
     let mut api = dropshot::ApiDescription::new();
     api.register(tls_check_handler).unwrap();
@@ -437,3 +530,202 @@ async fn test_server_is_http() {
         .await
         .expect_err("expected failure");
 }
+
+#[dropshot::endpoint {
+    method = GET,
+    path = "/client_cert",
+}]
+async fn client_cert_handler(
+    _rqctx: dropshot::RequestContext<usize>,
+    client_cert: dropshot::ClientCert,
+) -> Result<HttpResponseOk<String>, dropshot::HttpError> {
+    Ok(HttpResponseOk(client_cert.subject().to_string()))
+}
+
+#[dropshot::endpoint {
+    method = GET,
+    path = "/client_cert_optional",
+}]
+async fn client_cert_optional_handler(
+    rqctx: dropshot::RequestContext<usize>,
+    client_cert: Option<dropshot::ClientCert>,
+) -> Result<HttpResponseOk<bool>, dropshot::HttpError> {
+    assert_eq!(client_cert.is_some(), rqctx.request.client_cert().is_some());
+    Ok(HttpResponseOk(client_cert.is_some()))
+}
+
+#[tokio::test]
+async fn test_client_cert_absent() {
+    let mut api = dropshot::ApiDescription::new();
+    api.register(client_cert_handler).unwrap();
+    api.register(client_cert_optional_handler).unwrap();
+
+    let testctx = common::test_setup("test_client_cert_absent", api);
+    let client = &testctx.client_testctx;
+
+    // Without TLS, there's never a client certificate.
+    let error = client
+        .make_request_error(
+            hyper::Method::GET,
+            "/client_cert",
+            hyper::StatusCode::UNAUTHORIZED,
+        )
+        .await;
+    assert_eq!(error.message, "a client certificate is required");
+
+    let mut response = client
+        .make_request(
+            hyper::Method::GET,
+            "/client_cert_optional",
+            None as Option<()>,
+            hyper::StatusCode::OK,
+        )
+        .await
+        .unwrap();
+    let present: bool = dropshot::test_util::read_json(&mut response).await;
+    assert!(!present);
+
+    testctx.teardown().await;
+}
+
+fn make_mtls_client(
+    server_certs: &Vec<rustls::Certificate>,
+    client: Option<&common::TestCertificateChain>,
+) -> hyper::Client<
+    hyper_rustls::HttpsConnector<hyper::client::connect::HttpConnector>,
+> {
+    let builder = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(make_pki_verifier(
+            server_certs,
+        )));
+    let tls_config = match client {
+        // The client presents its own certificate and the intermediate one,
+        // which the server verifies up to the trusted root.
+        Some(client) => builder
+            .with_client_auth_cert(
+                client.cert_chain()[..2].to_vec(),
+                client.end_cert_private_key(),
+            )
+            .unwrap(),
+        None => builder.with_no_client_auth(),
+    };
+    let https_connector = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_tls_config(tls_config)
+        .https_only()
+        .enable_http1()
+        .build();
+    hyper::Client::builder().build(https_connector)
+}
+
+fn make_mtls_server(
+    log: &Logger,
+    server_certs: &Vec<rustls::Certificate>,
+    server_key: &rustls::PrivateKey,
+    client_ca_file: &Path,
+    optional: bool,
+) -> dropshot::HttpServer<usize> {
+    let config = ConfigDropshot {
+        bind_address: "127.0.0.1:0".parse().unwrap(),
+        request_body_max_bytes: 1024,
+        tls: Some(ConfigTls::AsDer {
+            certs: server_certs.iter().map(|cert| cert.0.clone()).collect(),
+            key: server_key.0.clone(),
+        }),
+        tls_client_auth: Some(ConfigTlsClientAuth {
+            ca_file: client_ca_file.to_path_buf(),
+            optional,
+        }),
+        ..Default::default()
+    };
+    let mut api = dropshot::ApiDescription::new();
+    api.register(client_cert_handler).unwrap();
+    api.register(client_cert_optional_handler).unwrap();
+    HttpServerStarter::new(&config, api, 0, log).unwrap().start()
+}
+
+async fn get_client_cert(
+    client: &hyper::Client<
+        hyper_rustls::HttpsConnector<hyper::client::connect::HttpConnector>,
+    >,
+    port: u16,
+    path: &str,
+) -> Result<serde_json::Value, hyper::Error> {
+    let request = hyper::Request::builder()
+        .method(http::method::Method::GET)
+        .uri(format!("https://localhost:{}{}", port, path))
+        .body(hyper::Body::empty())
+        .unwrap();
+    let response = client.request(request).await?;
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    Ok(serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_client_cert_required() {
+    let logctx = create_log_context("test_client_cert_required");
+    let log = logctx.log.new(o!());
+
+    // Generate keys for the server, and the CA that issues client
+    // certificates.
+    let (certs, key) = common::generate_tls_key();
+    let client_ca = common::TestCertificateChain::new();
+    let client_root = client_ca.cert_chain()[2..].to_vec();
+    let (ca_file, _) = common::tls_key_to_file(&client_root, &key);
+
+    let server = make_mtls_server(&log, &certs, &key, ca_file.path(), false);
+    let port = server.local_addr().port();
+
+    // A client with a certificate from the trusted CA is identified.
+    let client = make_mtls_client(&certs, Some(&client_ca));
+    let subject = get_client_cert(&client, port, "/client_cert").await.unwrap();
+    assert_eq!(subject, "CN=rcgen self signed cert");
+
+    // A client without a certificate can't connect.
+    let client = make_mtls_client(&certs, None);
+    get_client_cert(&client, port, "/client_cert_optional").await.unwrap_err();
+
+    // Nor can a client whose certificate comes from another CA.
+    let other_ca = common::TestCertificateChain::new();
+    let client = make_mtls_client(&certs, Some(&other_ca));
+    get_client_cert(&client, port, "/client_cert_optional").await.unwrap_err();
+
+    server.close().await.unwrap();
+
+    logctx.cleanup_successful();
+}
+
+#[tokio::test]
+async fn test_client_cert_optional() {
+    let logctx = create_log_context("test_client_cert_optional");
+    let log = logctx.log.new(o!());
+
+    let (certs, key) = common::generate_tls_key();
+    let client_ca = common::TestCertificateChain::new();
+    let client_root = client_ca.cert_chain()[2..].to_vec();
+    let (ca_file, _) = common::tls_key_to_file(&client_root, &key);
+
+    let server = make_mtls_server(&log, &certs, &key, ca_file.path(), true);
+    let port = server.local_addr().port();
+
+    // Clients may connect with or without a certificate.
+    let client = make_mtls_client(&certs, Some(&client_ca));
+    let present =
+        get_client_cert(&client, port, "/client_cert_optional").await.unwrap();
+    assert_eq!(present.as_bool(), Some(true));
+
+    let client = make_mtls_client(&certs, None);
+    let present =
+        get_client_cert(&client, port, "/client_cert_optional").await.unwrap();
+    assert_eq!(present.as_bool(), Some(false));
+
+    // A certificate that is presented must still be valid.
+    let other_ca = common::TestCertificateChain::new();
+    let client = make_mtls_client(&certs, Some(&other_ca));
+    get_client_cert(&client, port, "/client_cert_optional").await.unwrap_err();
+
+    server.close().await.unwrap();
+
+    logctx.cleanup_successful();
+}
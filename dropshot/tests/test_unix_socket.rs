@@ -0,0 +1,146 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for serving on a Unix domain socket.
+
+#![cfg(unix)]
+
+use dropshot::endpoint;
+use dropshot::ApiDescription;
+use dropshot::ConfigDropshot;
+use dropshot::ConfigUnixSocket;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::HttpServer;
+use dropshot::HttpServerStarter;
+use dropshot::RequestContext;
+use slog::o;
+use slog::Logger;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+pub mod common;
+
+/// Reports the remote address of the request.
+#[endpoint {
+    method = GET,
+    path = "/remote",
+}]
+async fn api_remote(
+    rqctx: RequestContext<usize>,
+) -> Result<HttpResponseOk<String>, HttpError> {
+    Ok(HttpResponseOk(rqctx.request.remote_addr().to_string()))
+}
+
+fn make_server(
+    log: &Logger,
+    unix_socket: ConfigUnixSocket,
+) -> Result<HttpServer<usize>, String> {
+    let mut api = ApiDescription::new();
+    api.register(api_remote).unwrap();
+    let config =
+        ConfigDropshot { unix_socket: Some(unix_socket), ..Default::default() };
+    HttpServerStarter::new(&config, api, 0, log)
+        .map(HttpServerStarter::start)
+        .map_err(|error| error.to_string())
+}
+
+fn make_server_error(log: &Logger, unix_socket: ConfigUnixSocket) -> String {
+    match make_server(log, unix_socket) {
+        Ok(_) => panic!("expected the server to fail to start"),
+        Err(error) => error,
+    }
+}
+
+async fn get_remote(path: &Path) -> String {
+    let stream = tokio::net::UnixStream::connect(path).await.unwrap();
+    let (mut sender, connection) =
+        hyper::client::conn::handshake(stream).await.unwrap();
+    tokio::spawn(connection);
+    let request = hyper::Request::builder()
+        .uri("/remote")
+        .header(http::header::HOST, "localhost")
+        .body(hyper::Body::empty())
+        .unwrap();
+    let response = sender.send_request(request).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_unix_socket() {
+    let logctx = common::create_log_context("unix_socket");
+    let log = logctx.log.new(o!());
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("api.sock");
+
+    let server = make_server(
+        &log,
+        ConfigUnixSocket {
+            path: path.clone(),
+            mode: Some(0o600),
+            remove_existing: false,
+        },
+    )
+    .unwrap();
+    let metadata = std::fs::metadata(&path).unwrap();
+    assert!(metadata.file_type().is_socket());
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+    // The private directory the socket was bound in is gone.
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    assert_eq!(get_remote(&path).await, "0.0.0.0:0");
+
+    // The socket file is removed when the server shuts down.
+    server.close().await.unwrap();
+    assert!(!path.exists());
+
+    logctx.cleanup_successful();
+}
+
+#[tokio::test]
+async fn test_unix_socket_existing() {
+    let logctx = common::create_log_context("unix_socket_existing");
+    let log = logctx.log.new(o!());
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("api.sock");
+    let unix_socket = ConfigUnixSocket {
+        path: path.clone(),
+        mode: None,
+        remove_existing: false,
+    };
+
+    // Leave a socket behind, as a server that didn't shut down would.
+    let stale = std::os::unix::net::UnixListener::bind(&path).unwrap();
+    drop(stale);
+
+    let error = make_server_error(&log, unix_socket.clone());
+    assert!(error.starts_with("failed to bind"), "{}", error);
+    let error = make_server_error(
+        &log,
+        ConfigUnixSocket { mode: Some(0o600), ..unix_socket.clone() },
+    );
+    assert!(error.starts_with("failed to bind"), "{}", error);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    // With remove_existing, the stale socket is replaced.
+    let server = make_server(
+        &log,
+        ConfigUnixSocket { remove_existing: true, ..unix_socket.clone() },
+    )
+    .unwrap();
+    assert_eq!(get_remote(&path).await, "0.0.0.0:0");
+    server.close().await.unwrap();
+
+    // Files other than sockets are never removed.
+    std::fs::write(&path, "precious").unwrap();
+    let error = make_server_error(
+        &log,
+        ConfigUnixSocket { remove_existing: true, ..unix_socket },
+    );
+    assert!(error.ends_with("exists and is not a socket"), "{}", error);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "precious");
+
+    logctx.cleanup_successful();
+}
@@ -0,0 +1,176 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Test cases for validating extracted parameters.
+
+use dropshot::endpoint;
+use dropshot::test_util::read_json;
+use dropshot::ApiDescription;
+use dropshot::HttpError;
+use dropshot::HttpResponseOk;
+use dropshot::Path;
+use dropshot::Query;
+use dropshot::RequestContext;
+use dropshot::TypedBody;
+use dropshot::Validate;
+use dropshot::Validated;
+use dropshot::ValidationErrors;
+use http::Method;
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+pub mod common;
+
+#[derive(Deserialize, JsonSchema)]
+struct ProjectPath {
+    project: String,
+}
+
+impl Validate for ProjectPath {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        if !self.project.chars().all(|c| c.is_ascii_lowercase()) {
+            errors.add("project", "must contain only lowercase letters");
+        }
+        errors.into_result()
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct ListArgs {
+    limit: u32,
+}
+
+impl Validate for ListArgs {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        if self.limit == 0 || self.limit > 100 {
+            errors.add("limit", "must be between 1 and 100");
+        }
+        errors.into_result()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct Range {
+    start: u32,
+    end: u32,
+}
+
+impl Validate for Range {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        if self.end < self.start {
+            errors.add("end", "must not be less than start");
+            errors.add("start", "must not be greater than end");
+        }
+        errors.into_result()
+    }
+}
+
+#[endpoint {
+    method = POST,
+    path = "/projects/{project}/ranges",
+}]
+async fn range_create(
+    _rqctx: RequestContext<usize>,
+    _path: Validated<Path<ProjectPath>>,
+    _query: Validated<Query<ListArgs>>,
+    body: Validated<TypedBody<Range>>,
+) -> Result<HttpResponseOk<Range>, HttpError> {
+    Ok(HttpResponseOk(body.into_inner().into_inner()))
+}
+
+fn api() -> ApiDescription<usize> {
+    let mut api = ApiDescription::new();
+    api.register(range_create).unwrap();
+    api
+}
+
+#[tokio::test]
+async fn test_validation() {
+    let testctx = common::test_setup("validation", api());
+    let client = &testctx.client_testctx;
+
+    // Valid values are passed to the handler.
+    let mut response = client
+        .make_request(
+            Method::POST,
+            "/projects/alpha/ranges?limit=10",
+            Some(Range { start: 1, end: 2 }),
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+    let range: Range = read_json(&mut response).await;
+    assert_eq!((range.start, range.end), (1, 2));
+
+    // Error case: each extractor's value is validated.
+    let cases = [
+        (
+            "/projects/Alpha/ranges?limit=10",
+            Range { start: 1, end: 2 },
+            "request failed validation: project: must contain only \
+             lowercase letters",
+            vec![("project", vec!["must contain only lowercase letters"])],
+        ),
+        (
+            "/projects/alpha/ranges?limit=0",
+            Range { start: 1, end: 2 },
+            "request failed validation: limit: must be between 1 and 100",
+            vec![("limit", vec!["must be between 1 and 100"])],
+        ),
+        (
+            "/projects/alpha/ranges?limit=10",
+            Range { start: 2, end: 1 },
+            "request failed validation: end: must not be less than start; \
+             start: must not be greater than end",
+            vec![
+                ("end", vec!["must not be less than start"]),
+                ("start", vec!["must not be greater than end"]),
+            ],
+        ),
+    ];
+    for (uri, body, message, field_errors) in cases {
+        let error = client
+            .make_request(
+                Method::POST,
+                uri,
+                Some(body),
+                StatusCode::BAD_REQUEST,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(error.message, message);
+        let expected = field_errors
+            .into_iter()
+            .map(|(name, messages)| {
+                (
+                    name.to_string(),
+                    messages.into_iter().map(String::from).collect(),
+                )
+            })
+            .collect::<BTreeMap<String, Vec<String>>>();
+        assert_eq!(error.field_errors, expected);
+    }
+
+    // Error case: values that can't be deserialized aren't validated.
+    let error = client
+        .make_request(
+            Method::POST,
+            "/projects/alpha/ranges?limit=ten",
+            Some(Range { start: 1, end: 2 }),
+            StatusCode::BAD_REQUEST,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.message,
+        "unable to parse query string: invalid digit found in string"
+    );
+    assert!(error.field_errors.is_empty());
+
+    testctx.teardown().await;
+}
@@ -14,11 +14,14 @@ use quote::{quote_spanned, ToTokens};
 use serde::Deserialize;
 use serde_tokenstream::from_tokenstream;
 use serde_tokenstream::Error;
+use std::collections::BTreeMap;
 use std::ops::DerefMut;
 use syn::spanned::Spanned;
 
 use syn_parsing::ItemFnForSignature;
 
+mod paginated_resource;
+mod request_params;
 mod syn_parsing;
 
 #[allow(non_snake_case)]
@@ -50,12 +53,30 @@ struct EndpointMetadata {
     method: MethodType,
     path: String,
     #[serde(default)]
+    aliases: Vec<String>,
+    operation_id: Option<String>,
+    #[serde(default)]
     tags: Vec<String>,
     #[serde(default)]
     unpublished: bool,
     #[serde(default)]
     deprecated: bool,
+    deprecated_since: Option<String>,
+    sunset: Option<String>,
     content_type: Option<String>,
+    response_content_type: Option<String>,
+    #[serde(default)]
+    encodings: Vec<String>,
+    strict_query: Option<bool>,
+    max_concurrency: Option<usize>,
+    request_body_max_bytes: Option<usize>,
+    request_timeout_ms: Option<u64>,
+    #[serde(default)]
+    errors: Vec<u16>,
+    #[serde(default)]
+    security: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    attributes: BTreeMap<String, String>,
     _dropshot_crate: Option<String>,
 }
 
@@ -69,6 +90,7 @@ enum ChannelProtocol {
 struct ChannelMetadata {
     protocol: ChannelProtocol,
     path: String,
+    operation_id: Option<String>,
     #[serde(default)]
     tags: Vec<String>,
     #[serde(default)]
@@ -102,14 +124,49 @@ const USAGE: &str = "Endpoint handlers must have the following signature:
 ///     method = { DELETE | GET | OPTIONS | PATCH | POST | PUT },
 ///     path = "/path/name/with/{named}/{variables}",
 ///
+///     // Optional additional paths served by the same handler; each is
+///     // documented as a deprecated operation
+///     aliases = [ "/old/path/with/{named}/{variables}" ],
+///     // Optional operation ID for the OpenAPI document (defaults to the
+///     // function's name); must be unique within the API
+///     operation_id = "my_operation",
 ///     // Optional tags for the operation's description
 ///     tags = [ "all", "your", "OpenAPI", "tags" ],
 ///     // Specifies the media type used to encode the request body
 ///     content_type = { "application/json" | "application/x-www-form-urlencoded" }
+///     // Specifies the media type of a `FreeformBody` response
+///     response_content_type = "text/plain",
+///     // Media types in which the request and response bodies may also be
+///     // encoded, chosen by the request's `Content-Type` and `Accept` headers
+///     encodings = [ "application/json", "application/cbor", "application/msgpack" ],
 ///     // A value of `true` marks the operation as deprecated
 ///     deprecated = { true | false },
+///     // Marks the operation as deprecated as of an RFC 3339 timestamp, sent
+///     // to clients in a `Deprecation` response header
+///     deprecated_since = "2023-06-01T00:00:00Z",
+///     // Announces when the operation will stop working, sent to clients in
+///     // a `Sunset` response header
+///     sunset = "2024-01-01T00:00:00Z",
 ///     // A value of `true` causes the operation to be omitted from the API description
 ///     unpublished = { true | false },
+///     // A value of `true` rejects requests with query parameters that the
+///     // `Query` extractor doesn't accept
+///     strict_query = { true | false },
+///     // Limits how many requests the endpoint handles at once; excess
+///     // requests receive a 503
+///     max_concurrency = N,
+///     // Overrides the server's `request_body_max_bytes` for this endpoint
+///     request_body_max_bytes = N,
+///     // Overrides the server's `request_timeout_ms` for this endpoint
+///     request_timeout_ms = N,
+///     // Error statuses documented as the operation's own responses
+///     errors = [ 404, 409 ],
+///     // Security schemes declared with `ApiDescription::security_scheme`
+///     // that callers must satisfy, each with the scopes it requires
+///     security = { oauth = [ "read", "write" ], api_key = [] },
+///     // Custom attributes made available to handlers and hooks through
+///     // `RequestContext::endpoint`
+///     attributes = { name = "value" },
 /// }]
 /// ```
 ///
@@ -168,6 +225,7 @@ fn do_channel(
     let ChannelMetadata {
         protocol,
         path,
+        operation_id,
         tags,
         unpublished,
         deprecated,
@@ -241,10 +299,23 @@ fn do_channel(
             let metadata = EndpointMetadata {
                 method: MethodType::GET,
                 path,
+                aliases: vec![],
+                operation_id,
                 tags,
                 unpublished,
                 deprecated,
+                deprecated_since: None,
+                sunset: None,
                 content_type: Some("application/json".to_string()),
+                response_content_type: None,
+                encodings: vec![],
+                strict_query: None,
+                max_concurrency: None,
+                request_body_max_bytes: None,
+                request_timeout_ms: None,
+                errors: vec![],
+                security: BTreeMap::new(),
+                attributes: BTreeMap::new(),
                 _dropshot_crate,
             };
             do_endpoint_inner(metadata, attr, new_item)
@@ -252,6 +323,60 @@ fn do_channel(
     }
 }
 
+/// Derives [`SharedExtractor`](../dropshot/trait.SharedExtractor.html) for a
+/// struct that combines path and query parameters, so that a handler can
+/// accept them as a single argument.  Each field must be marked with either
+/// `#[dropshot(path)]` or `#[dropshot(query)]`.
+///
+/// ```ignore
+/// #[derive(RequestParams)]
+/// struct WidgetParams {
+///     #[dropshot(path)]
+///     widget_id: Uuid,
+///     #[dropshot(query)]
+///     verbose: Option<bool>,
+/// }
+/// ```
+///
+/// The path fields are extracted as with
+/// [`Path`](../dropshot/struct.Path.html) and the query fields as with
+/// [`Query`](../dropshot/struct.Query.html), so the same `serde` and
+/// `schemars` attributes apply, and the crate must depend on both `serde` and
+/// `schemars`.  Attributes of these two kinds on the struct itself apply to
+/// both sets of fields.
+#[proc_macro_derive(RequestParams, attributes(dropshot, serde, schemars))]
+pub fn derive_request_params(
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    do_output(request_params::do_derive_request_params(item.into()))
+}
+
+/// Derives [`PaginatedResource`](../dropshot/trait.PaginatedResource.html)
+/// for the item type of a paginated collection, along with its scan
+/// parameters and page selector.  Each `#[paginate(...)]` attribute describes
+/// one way to sort the collection, by the field named by `sort_by` and then by
+/// any named by `then`.
+///
+/// ```ignore
+/// #[derive(Clone, JsonSchema, PaginatedResource, Serialize)]
+/// #[paginate(sort_by = "name")]
+/// #[paginate(sort_by = "mtime", then = "name")]
+/// struct Project {
+///     name: String,
+///     mtime: DateTime<Utc>,
+/// }
+/// ```
+///
+/// This generates `ProjectSortBy`, `ProjectScanParams`, and
+/// `ProjectPageSelector`; see the trait's documentation for details.  The
+/// crate must depend on both `serde` and `schemars`.
+#[proc_macro_derive(PaginatedResource, attributes(paginate))]
+pub fn derive_paginated_resource(
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    do_output(paginated_resource::do_derive_paginated_resource(item.into()))
+}
+
 fn do_output(
     res: Result<(proc_macro2::TokenStream, Vec<Error>), Error>,
 ) -> proc_macro::TokenStream {
@@ -290,6 +415,47 @@ fn do_endpoint_inner(
             "invalid content type for endpoint",
         ));
     }
+    if metadata.encodings.iter().any(|encoding| {
+        !matches!(
+            encoding.as_str(),
+            "application/json" | "application/cbor" | "application/msgpack"
+        )
+    }) {
+        return Err(Error::new_spanned(&attr, "invalid encoding for endpoint"));
+    }
+    if let Some(response_content_type) = &metadata.response_content_type {
+        if !matches!(
+            response_content_type.split_once('/'),
+            Some((kind, subtype)) if !kind.is_empty() && !subtype.is_empty()
+        ) {
+            return Err(Error::new_spanned(
+                &attr,
+                "invalid response content type for endpoint",
+            ));
+        }
+    }
+    if metadata.operation_id.as_deref() == Some("") {
+        return Err(Error::new_spanned(
+            &attr,
+            "operation_id must not be empty",
+        ));
+    }
+    if metadata.errors.iter().any(|status| !(400..600).contains(status)) {
+        return Err(Error::new_spanned(
+            &attr,
+            "errors must be 4xx or 5xx statuses",
+        ));
+    }
+    let deprecated_since = metadata
+        .deprecated_since
+        .as_deref()
+        .map(|time| parse_timestamp(&attr, "deprecated_since", time))
+        .transpose()?;
+    let sunset = metadata
+        .sunset
+        .as_deref()
+        .map(|time| parse_timestamp(&attr, "sunset", time))
+        .transpose()?;
 
     let mut errors = Vec::new();
 
@@ -335,6 +501,8 @@ fn do_endpoint_inner(
 
     let name = &ast.sig.ident;
     let name_str = name.to_string();
+    let operation_id =
+        metadata.operation_id.unwrap_or_else(|| name_str.clone());
     let method_ident = format_ident!("{}", method);
     let visibility = &ast.vis;
 
@@ -372,6 +540,14 @@ fn do_endpoint_inner(
         })
         .collect::<Vec<_>>();
 
+    let aliases = metadata
+        .aliases
+        .iter()
+        .map(|alias| {
+            quote! { .alias(#alias) }
+        })
+        .collect::<Vec<_>>();
+
     let visible = metadata.unpublished.then(|| {
         quote! { .visible(false) }
     });
@@ -380,8 +556,80 @@ fn do_endpoint_inner(
         quote! { .deprecated(true) }
     });
 
+    // These were validated above, so parsing them again can't fail.
+    let deprecated_since = deprecated_since.map(|time| {
+        quote! { .deprecated_since(#time.parse().unwrap()) }
+    });
+
+    let sunset = sunset.map(|time| {
+        quote! { .sunset(#time.parse().unwrap()) }
+    });
+
+    let encodings = metadata
+        .encodings
+        .iter()
+        .map(|encoding| {
+            quote! { .encoding(#encoding) }
+        })
+        .collect::<Vec<_>>();
+
+    let response_content_type =
+        metadata.response_content_type.map(|response_content_type| {
+            quote! { .response_content_type(#response_content_type) }
+        });
+
+    let strict_query = metadata.strict_query.map(|strict_query| {
+        quote! { .strict_query(#strict_query) }
+    });
+
+    let max_concurrency = metadata.max_concurrency.map(|max_concurrency| {
+        quote! { .max_concurrency(#max_concurrency) }
+    });
+
+    let request_body_max_bytes =
+        metadata.request_body_max_bytes.map(|request_body_max_bytes| {
+            quote! { .request_body_max_bytes(#request_body_max_bytes) }
+        });
+
+    let request_timeout =
+        metadata.request_timeout_ms.map(|request_timeout_ms| {
+            quote! {
+                .request_timeout(
+                    std::time::Duration::from_millis(#request_timeout_ms)
+                )
+            }
+        });
+
+    let security = metadata
+        .security
+        .iter()
+        .map(|(name, scopes)| {
+            quote! {
+                .security_requirement(#name, vec![#(#scopes.to_string()),*])
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let attributes = metadata
+        .attributes
+        .iter()
+        .map(|(name, value)| {
+            quote! { .attribute(#name, #value) }
+        })
+        .collect::<Vec<_>>();
+
     let dropshot = get_crate(metadata._dropshot_crate);
 
+    let error_statuses = metadata
+        .errors
+        .iter()
+        .map(|status| {
+            quote! {
+                .error(#dropshot::StatusCode::from_u16(#status).unwrap())
+            }
+        })
+        .collect::<Vec<_>>();
+
     let first_arg = match ast.sig.inputs.first() {
         Some(syn::FnArg::Typed(syn::PatType {
             attrs: _,
@@ -571,7 +819,7 @@ fn do_endpoint_inner(
     let construct = if errors.is_empty() {
         quote! {
             #dropshot::ApiEndpoint::new(
-                #name_str.to_string(),
+                #operation_id.to_string(),
                 #name,
                 #dropshot::Method::#method_ident,
                 #content_type,
@@ -579,9 +827,21 @@ fn do_endpoint_inner(
             )
             #summary
             #description
+            #(#aliases)*
             #(#tags)*
             #visible
             #deprecated
+            #deprecated_since
+            #sunset
+            #(#encodings)*
+            #response_content_type
+            #strict_query
+            #max_concurrency
+            #request_body_max_bytes
+            #request_timeout
+            #(#error_statuses)*
+            #(#security)*
+            #(#attributes)*
         }
     } else {
         quote! {
@@ -648,6 +908,26 @@ fn get_crate(var: Option<String>) -> proc_macro2::TokenStream {
     syn::Ident::new(DROPSHOT, proc_macro2::Span::call_site()).to_token_stream()
 }
 
+/// Checks that `time`, the value of the attribute `name`, is an RFC 3339
+/// timestamp, returning it in UTC.
+fn parse_timestamp(
+    attr: &proc_macro2::TokenStream,
+    name: &str,
+    time: &str,
+) -> Result<String, Error> {
+    chrono::DateTime::parse_from_rfc3339(time)
+        .map(|time| {
+            time.with_timezone(&chrono::Utc)
+                .to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)
+        })
+        .map_err(|e| {
+            Error::new_spanned(
+                attr,
+                format!("{} must be an RFC 3339 timestamp: {}", name, e),
+            )
+        })
+}
+
 #[allow(dead_code)]
 fn to_compile_errors(errors: Vec<syn::Error>) -> proc_macro2::TokenStream {
     let compile_errors = errors.iter().map(syn::Error::to_compile_error);
@@ -1232,14 +1512,14 @@ mod tests {
     }
 
     #[test]
-    fn test_endpoint_with_doc() {
+    fn test_endpoint_with_operation_id() {
         let (item, errors) = do_endpoint(
             quote! {
                 method = GET,
-                path = "/a/b/c"
+                path = "/a/b/c",
+                operation_id = "stuff_get",
             },
             quote! {
-                /** handle "xyz" requests */
                 async fn handler_xyz(
                     _rqctx: RequestContext<()>,
                 ) -> Result<HttpResponseOk<()>, HttpError> {
@@ -1284,11 +1564,11 @@ mod tests {
             };
 
             #[allow(non_camel_case_types, missing_docs)]
-            #[doc = "API Endpoint: handler_xyz\nhandle \"xyz\" requests"]
+            #[doc = "API Endpoint: handler_xyz"]
             struct handler_xyz {}
 
             #[allow(non_upper_case_globals, missing_docs)]
-            #[doc = "API Endpoint: handler_xyz\nhandle \"xyz\" requests"]
+            #[doc = "API Endpoint: handler_xyz"]
             const handler_xyz: handler_xyz = handler_xyz {};
 
             impl From<handler_xyz>
@@ -1297,7 +1577,6 @@ mod tests {
                 as dropshot::RequestContextArgument>::Context>
             {
                 fn from(_: handler_xyz) -> Self {
-                    #[doc = r#" handle "xyz" requests "#]
                     async fn handler_xyz(
                         _rqctx: RequestContext<()>,
                     ) -> Result<HttpResponseOk<()>, HttpError> {
@@ -1312,13 +1591,12 @@ mod tests {
                     };
 
                     dropshot::ApiEndpoint::new(
-                        "handler_xyz".to_string(),
+                        "stuff_get".to_string(),
                         handler_xyz,
                         dropshot::Method::GET,
                         "application/json",
                         "/a/b/c",
                     )
-                    .summary("handle \"xyz\" requests")
                 }
             }
         };
@@ -1328,51 +1606,643 @@ mod tests {
     }
 
     #[test]
-    fn test_endpoint_invalid_item() {
-        let ret = do_endpoint(
+    fn test_endpoint_with_aliases() {
+        let (item, errors) = do_endpoint(
             quote! {
                 method = GET,
-                path = "/a/b/c"
+                path = "/a/b/c",
+                aliases = ["/x/b/c", "/y/b/c"],
             },
             quote! {
-                const POTATO = "potato";
+                async fn handler_xyz(
+                    _rqctx: RequestContext<()>,
+                ) -> Result<HttpResponseOk<()>, HttpError> {
+                    Ok(())
+                }
             },
-        );
+        )
+        .unwrap();
+        let expected = quote! {
+            const _: fn() = || {
+                struct NeedRequestContext(<RequestContext<()> as dropshot::RequestContextArgument>::Context) ;
+            };
+            const _: fn() = || {
+                trait ResultTrait {
+                    type T;
+                    type E;
+                }
+                impl<TT, EE> ResultTrait for Result<TT, EE>
+                where
+                    TT: dropshot::HttpResponse,
+                {
+                    type T = TT;
+                    type E = EE;
+                }
+                struct NeedHttpResponse(
+                    <Result<HttpResponseOk<()>, HttpError> as ResultTrait>::T,
+                );
+                trait TypeEq {
+                    type This: ?Sized;
+                }
+                impl<T: ?Sized> TypeEq for T {
+                    type This = Self;
+                }
+                fn validate_result_error_type<T>()
+                where
+                    T: ?Sized + TypeEq<This = dropshot::HttpError>,
+                {
+                }
+                validate_result_error_type::<
+                    <Result<HttpResponseOk<()>, HttpError> as ResultTrait>::E,
+                >();
+            };
 
-        let msg = format!("{}", ret.err().unwrap());
-        assert_eq!("expected `fn`", msg);
+            #[allow(non_camel_case_types, missing_docs)]
+            #[doc = "API Endpoint: handler_xyz"]
+            struct handler_xyz {}
+
+            #[allow(non_upper_case_globals, missing_docs)]
+            #[doc = "API Endpoint: handler_xyz"]
+            const handler_xyz: handler_xyz = handler_xyz {};
+
+            impl From<handler_xyz>
+                for dropshot::ApiEndpoint<
+                    <RequestContext<()>
+                as dropshot::RequestContextArgument>::Context>
+            {
+                fn from(_: handler_xyz) -> Self {
+                    async fn handler_xyz(
+                        _rqctx: RequestContext<()>,
+                    ) -> Result<HttpResponseOk<()>, HttpError> {
+                        Ok(())
+                    }
+
+                    const _: fn() = || {
+                        fn future_endpoint_must_be_send<T: ::std::marker::Send>(_t: T) {}
+                        fn check_future_bounds(arg0: RequestContext<()>) {
+                            future_endpoint_must_be_send(handler_xyz(arg0));
+                        }
+                    };
+
+                    dropshot::ApiEndpoint::new(
+                        "handler_xyz".to_string(),
+                        handler_xyz,
+                        dropshot::Method::GET,
+                        "application/json",
+                        "/a/b/c",
+                    )
+                    .alias("/x/b/c")
+                    .alias("/y/b/c")
+                }
+            }
+        };
+
+        assert!(errors.is_empty());
+        assert_eq!(expected.to_string(), item.to_string());
     }
 
     #[test]
-    fn test_endpoint_bad_string() {
-        let ret = do_endpoint(
+    fn test_endpoint_with_attributes() {
+        let (item, errors) = do_endpoint(
             quote! {
                 method = GET,
-                path = /a/b/c
+                path = "/a/b/c",
+                attributes = { audit = "read", metric = "abc" },
             },
             quote! {
-                const POTATO = "potato";
+                async fn handler_xyz(
+                    _rqctx: RequestContext<()>,
+                ) -> Result<HttpResponseOk<()>, HttpError> {
+                    Ok(())
+                }
             },
-        );
+        )
+        .unwrap();
+        let expected = quote! {
+            const _: fn() = || {
+                struct NeedRequestContext(<RequestContext<()> as dropshot::RequestContextArgument>::Context) ;
+            };
+            const _: fn() = || {
+                trait ResultTrait {
+                    type T;
+                    type E;
+                }
+                impl<TT, EE> ResultTrait for Result<TT, EE>
+                where
+                    TT: dropshot::HttpResponse,
+                {
+                    type T = TT;
+                    type E = EE;
+                }
+                struct NeedHttpResponse(
+                    <Result<HttpResponseOk<()>, HttpError> as ResultTrait>::T,
+                );
+                trait TypeEq {
+                    type This: ?Sized;
+                }
+                impl<T: ?Sized> TypeEq for T {
+                    type This = Self;
+                }
+                fn validate_result_error_type<T>()
+                where
+                    T: ?Sized + TypeEq<This = dropshot::HttpError>,
+                {
+                }
+                validate_result_error_type::<
+                    <Result<HttpResponseOk<()>, HttpError> as ResultTrait>::E,
+                >();
+            };
 
-        let msg = format!("{}", ret.err().unwrap());
-        assert_eq!("expected a string, but found `/`", msg);
-    }
+            #[allow(non_camel_case_types, missing_docs)]
+            #[doc = "API Endpoint: handler_xyz"]
+            struct handler_xyz {}
 
-    #[test]
-    fn test_endpoint_bad_metadata() {
-        let ret = do_endpoint(
-            quote! {
-                methud = GET,
-                path = "/a/b/c"
-            },
-            quote! {
-                const POTATO = "potato";
-            },
-        );
+            #[allow(non_upper_case_globals, missing_docs)]
+            #[doc = "API Endpoint: handler_xyz"]
+            const handler_xyz: handler_xyz = handler_xyz {};
 
-        let msg = format!("{}", ret.err().unwrap());
-        assert_eq!("extraneous member `methud`", msg);
+            impl From<handler_xyz>
+                for dropshot::ApiEndpoint<
+                    <RequestContext<()>
+                as dropshot::RequestContextArgument>::Context>
+            {
+                fn from(_: handler_xyz) -> Self {
+                    async fn handler_xyz(
+                        _rqctx: RequestContext<()>,
+                    ) -> Result<HttpResponseOk<()>, HttpError> {
+                        Ok(())
+                    }
+
+                    const _: fn() = || {
+                        fn future_endpoint_must_be_send<T: ::std::marker::Send>(_t: T) {}
+                        fn check_future_bounds(arg0: RequestContext<()>) {
+                            future_endpoint_must_be_send(handler_xyz(arg0));
+                        }
+                    };
+
+                    dropshot::ApiEndpoint::new(
+                        "handler_xyz".to_string(),
+                        handler_xyz,
+                        dropshot::Method::GET,
+                        "application/json",
+                        "/a/b/c",
+                    )
+                    .attribute("audit", "read")
+                    .attribute("metric", "abc")
+                }
+            }
+        };
+
+        assert!(errors.is_empty());
+        assert_eq!(expected.to_string(), item.to_string());
+    }
+
+    #[test]
+    fn test_endpoint_with_sunset() {
+        let (item, errors) = do_endpoint(
+            quote! {
+                method = GET,
+                path = "/a/b/c",
+                deprecated_since = "2023-06-01T00:00:00Z",
+                sunset = "2024-01-01T01:00:00+01:00",
+            },
+            quote! {
+                async fn handler_xyz(
+                    _rqctx: RequestContext<()>,
+                ) -> Result<HttpResponseOk<()>, HttpError> {
+                    Ok(())
+                }
+            },
+        )
+        .unwrap();
+        let expected = quote! {
+            const _: fn() = || {
+                struct NeedRequestContext(<RequestContext<()> as dropshot::RequestContextArgument>::Context) ;
+            };
+            const _: fn() = || {
+                trait ResultTrait {
+                    type T;
+                    type E;
+                }
+                impl<TT, EE> ResultTrait for Result<TT, EE>
+                where
+                    TT: dropshot::HttpResponse,
+                {
+                    type T = TT;
+                    type E = EE;
+                }
+                struct NeedHttpResponse(
+                    <Result<HttpResponseOk<()>, HttpError> as ResultTrait>::T,
+                );
+                trait TypeEq {
+                    type This: ?Sized;
+                }
+                impl<T: ?Sized> TypeEq for T {
+                    type This = Self;
+                }
+                fn validate_result_error_type<T>()
+                where
+                    T: ?Sized + TypeEq<This = dropshot::HttpError>,
+                {
+                }
+                validate_result_error_type::<
+                    <Result<HttpResponseOk<()>, HttpError> as ResultTrait>::E,
+                >();
+            };
+
+            #[allow(non_camel_case_types, missing_docs)]
+            #[doc = "API Endpoint: handler_xyz"]
+            struct handler_xyz {}
+
+            #[allow(non_upper_case_globals, missing_docs)]
+            #[doc = "API Endpoint: handler_xyz"]
+            const handler_xyz: handler_xyz = handler_xyz {};
+
+            impl From<handler_xyz>
+                for dropshot::ApiEndpoint<
+                    <RequestContext<()>
+                as dropshot::RequestContextArgument>::Context>
+            {
+                fn from(_: handler_xyz) -> Self {
+                    async fn handler_xyz(
+                        _rqctx: RequestContext<()>,
+                    ) -> Result<HttpResponseOk<()>, HttpError> {
+                        Ok(())
+                    }
+
+                    const _: fn() = || {
+                        fn future_endpoint_must_be_send<T: ::std::marker::Send>(_t: T) {}
+                        fn check_future_bounds(arg0: RequestContext<()>) {
+                            future_endpoint_must_be_send(handler_xyz(arg0));
+                        }
+                    };
+
+                    dropshot::ApiEndpoint::new(
+                        "handler_xyz".to_string(),
+                        handler_xyz,
+                        dropshot::Method::GET,
+                        "application/json",
+                        "/a/b/c",
+                    )
+                    .deprecated_since("2023-06-01T00:00:00Z".parse().unwrap())
+                    .sunset("2024-01-01T00:00:00Z".parse().unwrap())
+                }
+            }
+        };
+
+        assert!(errors.is_empty());
+        assert_eq!(expected.to_string(), item.to_string());
+    }
+
+    #[test]
+    fn test_endpoint_with_security() {
+        let (item, errors) = do_endpoint(
+            quote! {
+                method = GET,
+                path = "/a/b/c",
+                security = { oauth = ["read", "write"], api_key = [] },
+            },
+            quote! {
+                async fn handler_xyz(
+                    _rqctx: RequestContext<()>,
+                ) -> Result<HttpResponseOk<()>, HttpError> {
+                    Ok(())
+                }
+            },
+        )
+        .unwrap();
+        let expected = quote! {
+            const _: fn() = || {
+                struct NeedRequestContext(<RequestContext<()> as dropshot::RequestContextArgument>::Context) ;
+            };
+            const _: fn() = || {
+                trait ResultTrait {
+                    type T;
+                    type E;
+                }
+                impl<TT, EE> ResultTrait for Result<TT, EE>
+                where
+                    TT: dropshot::HttpResponse,
+                {
+                    type T = TT;
+                    type E = EE;
+                }
+                struct NeedHttpResponse(
+                    <Result<HttpResponseOk<()>, HttpError> as ResultTrait>::T,
+                );
+                trait TypeEq {
+                    type This: ?Sized;
+                }
+                impl<T: ?Sized> TypeEq for T {
+                    type This = Self;
+                }
+                fn validate_result_error_type<T>()
+                where
+                    T: ?Sized + TypeEq<This = dropshot::HttpError>,
+                {
+                }
+                validate_result_error_type::<
+                    <Result<HttpResponseOk<()>, HttpError> as ResultTrait>::E,
+                >();
+            };
+
+            #[allow(non_camel_case_types, missing_docs)]
+            #[doc = "API Endpoint: handler_xyz"]
+            struct handler_xyz {}
+
+            #[allow(non_upper_case_globals, missing_docs)]
+            #[doc = "API Endpoint: handler_xyz"]
+            const handler_xyz: handler_xyz = handler_xyz {};
+
+            impl From<handler_xyz>
+                for dropshot::ApiEndpoint<
+                    <RequestContext<()>
+                as dropshot::RequestContextArgument>::Context>
+            {
+                fn from(_: handler_xyz) -> Self {
+                    async fn handler_xyz(
+                        _rqctx: RequestContext<()>,
+                    ) -> Result<HttpResponseOk<()>, HttpError> {
+                        Ok(())
+                    }
+
+                    const _: fn() = || {
+                        fn future_endpoint_must_be_send<T: ::std::marker::Send>(_t: T) {}
+                        fn check_future_bounds(arg0: RequestContext<()>) {
+                            future_endpoint_must_be_send(handler_xyz(arg0));
+                        }
+                    };
+
+                    dropshot::ApiEndpoint::new(
+                        "handler_xyz".to_string(),
+                        handler_xyz,
+                        dropshot::Method::GET,
+                        "application/json",
+                        "/a/b/c",
+                    )
+                    .security_requirement("api_key", vec![])
+                    .security_requirement(
+                        "oauth",
+                        vec!["read".to_string(), "write".to_string()]
+                    )
+                }
+            }
+        };
+
+        assert!(errors.is_empty());
+        assert_eq!(expected.to_string(), item.to_string());
+    }
+
+    #[test]
+    fn test_endpoint_with_doc() {
+        let (item, errors) = do_endpoint(
+            quote! {
+                method = GET,
+                path = "/a/b/c"
+            },
+            quote! {
+                /** handle "xyz" requests */
+                async fn handler_xyz(
+                    _rqctx: RequestContext<()>,
+                ) -> Result<HttpResponseOk<()>, HttpError> {
+                    Ok(())
+                }
+            },
+        )
+        .unwrap();
+        let expected = quote! {
+            const _: fn() = || {
+                struct NeedRequestContext(<RequestContext<()> as dropshot::RequestContextArgument>::Context) ;
+            };
+            const _: fn() = || {
+                trait ResultTrait {
+                    type T;
+                    type E;
+                }
+                impl<TT, EE> ResultTrait for Result<TT, EE>
+                where
+                    TT: dropshot::HttpResponse,
+                {
+                    type T = TT;
+                    type E = EE;
+                }
+                struct NeedHttpResponse(
+                    <Result<HttpResponseOk<()>, HttpError> as ResultTrait>::T,
+                );
+                trait TypeEq {
+                    type This: ?Sized;
+                }
+                impl<T: ?Sized> TypeEq for T {
+                    type This = Self;
+                }
+                fn validate_result_error_type<T>()
+                where
+                    T: ?Sized + TypeEq<This = dropshot::HttpError>,
+                {
+                }
+                validate_result_error_type::<
+                    <Result<HttpResponseOk<()>, HttpError> as ResultTrait>::E,
+                >();
+            };
+
+            #[allow(non_camel_case_types, missing_docs)]
+            #[doc = "API Endpoint: handler_xyz\nhandle \"xyz\" requests"]
+            struct handler_xyz {}
+
+            #[allow(non_upper_case_globals, missing_docs)]
+            #[doc = "API Endpoint: handler_xyz\nhandle \"xyz\" requests"]
+            const handler_xyz: handler_xyz = handler_xyz {};
+
+            impl From<handler_xyz>
+                for dropshot::ApiEndpoint<
+                    <RequestContext<()>
+                as dropshot::RequestContextArgument>::Context>
+            {
+                fn from(_: handler_xyz) -> Self {
+                    #[doc = r#" handle "xyz" requests "#]
+                    async fn handler_xyz(
+                        _rqctx: RequestContext<()>,
+                    ) -> Result<HttpResponseOk<()>, HttpError> {
+                        Ok(())
+                    }
+
+                    const _: fn() = || {
+                        fn future_endpoint_must_be_send<T: ::std::marker::Send>(_t: T) {}
+                        fn check_future_bounds(arg0: RequestContext<()>) {
+                            future_endpoint_must_be_send(handler_xyz(arg0));
+                        }
+                    };
+
+                    dropshot::ApiEndpoint::new(
+                        "handler_xyz".to_string(),
+                        handler_xyz,
+                        dropshot::Method::GET,
+                        "application/json",
+                        "/a/b/c",
+                    )
+                    .summary("handle \"xyz\" requests")
+                }
+            }
+        };
+
+        assert!(errors.is_empty());
+        assert_eq!(expected.to_string(), item.to_string());
+    }
+
+    #[test]
+    fn test_endpoint_invalid_item() {
+        let ret = do_endpoint(
+            quote! {
+                method = GET,
+                path = "/a/b/c"
+            },
+            quote! {
+                const POTATO = "potato";
+            },
+        );
+
+        let msg = format!("{}", ret.err().unwrap());
+        assert_eq!("expected `fn`", msg);
+    }
+
+    #[test]
+    fn test_endpoint_bad_string() {
+        let ret = do_endpoint(
+            quote! {
+                method = GET,
+                path = /a/b/c
+            },
+            quote! {
+                const POTATO = "potato";
+            },
+        );
+
+        let msg = format!("{}", ret.err().unwrap());
+        assert_eq!("expected a string, but found `/`", msg);
+    }
+
+    #[test]
+    fn test_endpoint_bad_metadata() {
+        let ret = do_endpoint(
+            quote! {
+                methud = GET,
+                path = "/a/b/c"
+            },
+            quote! {
+                const POTATO = "potato";
+            },
+        );
+
+        let msg = format!("{}", ret.err().unwrap());
+        assert_eq!("extraneous member `methud`", msg);
+    }
+
+    #[test]
+    fn test_endpoint_bad_encoding() {
+        let ret = do_endpoint(
+            quote! {
+                method = POST,
+                path = "/a/b/c",
+                encodings = ["application/json", "text/plain"]
+            },
+            quote! {
+                pub async fn handler_xyz(
+                    _rqctx: RequestContext<()>,
+                ) -> Result<HttpResponseOk<()>, HttpError> {
+                    Ok(())
+                }
+            },
+        );
+
+        let msg = format!("{}", ret.err().unwrap());
+        assert_eq!("invalid encoding for endpoint", msg);
+    }
+
+    #[test]
+    fn test_endpoint_bad_sunset() {
+        let ret = do_endpoint(
+            quote! {
+                method = GET,
+                path = "/a/b/c",
+                sunset = "2024-01-01",
+            },
+            quote! {
+                pub async fn handler_xyz(
+                    _rqctx: RequestContext<()>,
+                ) -> Result<HttpResponseOk<()>, HttpError> {
+                    Ok(())
+                }
+            },
+        );
+
+        let msg = format!("{}", ret.err().unwrap());
+        assert_eq!(
+            "sunset must be an RFC 3339 timestamp: premature end of input",
+            msg
+        );
+    }
+
+    #[test]
+    fn test_endpoint_bad_response_content_type() {
+        let ret = do_endpoint(
+            quote! {
+                method = GET,
+                path = "/a/b/c",
+                response_content_type = "text"
+            },
+            quote! {
+                pub async fn handler_xyz(
+                    _rqctx: RequestContext<()>,
+                ) -> Result<HttpResponseOk<()>, HttpError> {
+                    Ok(())
+                }
+            },
+        );
+
+        let msg = format!("{}", ret.err().unwrap());
+        assert_eq!("invalid response content type for endpoint", msg);
+    }
+
+    #[test]
+    fn test_endpoint_bad_errors() {
+        let ret = do_endpoint(
+            quote! {
+                method = GET,
+                path = "/a/b/c",
+                errors = [404, 200]
+            },
+            quote! {
+                pub async fn handler_xyz(
+                    _rqctx: RequestContext<()>,
+                ) -> Result<HttpResponseOk<()>, HttpError> {
+                    Ok(())
+                }
+            },
+        );
+
+        let msg = format!("{}", ret.err().unwrap());
+        assert_eq!("errors must be 4xx or 5xx statuses", msg);
+    }
+
+    #[test]
+    fn test_endpoint_empty_operation_id() {
+        let ret = do_endpoint(
+            quote! {
+                method = GET,
+                path = "/a/b/c",
+                operation_id = ""
+            },
+            quote! {
+                pub async fn handler_xyz(
+                    _rqctx: RequestContext<()>,
+                ) -> Result<HttpResponseOk<()>, HttpError> {
+                    Ok(())
+                }
+            },
+        );
+
+        let msg = format!("{}", ret.err().unwrap());
+        assert_eq!("operation_id must not be empty", msg);
     }
 
     #[test]
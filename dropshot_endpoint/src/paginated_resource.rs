@@ -0,0 +1,430 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Implementation of `#[derive(PaginatedResource)]`
+
+use quote::format_ident;
+use quote::quote;
+use serde_tokenstream::Error;
+
+use crate::get_crate;
+
+const USAGE: &str = "PaginatedResource may only be derived for structs with \
+    named fields and at least one #[paginate(sort_by = \"field\")] attribute";
+
+/// One of the sorts described by a `#[paginate(...)]` attribute
+struct Sort {
+    /// fields by which items are sorted, most significant first
+    fields: Vec<syn::Ident>,
+}
+
+impl Sort {
+    /// Returns the name of the sort's variant in the generated enums.
+    fn variant(&self) -> syn::Ident {
+        let name = self
+            .fields
+            .iter()
+            .flat_map(|field| {
+                let field = field.to_string();
+                let field = field.trim_start_matches("r#").to_string();
+                field
+                    .split('_')
+                    .filter(|word| !word.is_empty())
+                    .map(|word| {
+                        let mut chars = word.chars();
+                        chars
+                            .next()
+                            .map(|first| first.to_ascii_uppercase())
+                            .into_iter()
+                            .chain(chars)
+                            .collect::<String>()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<String>();
+        format_ident!("{}", name)
+    }
+
+    /// Returns the name of the sort in querystrings and page tokens.
+    fn wire_name(&self) -> String {
+        self.fields
+            .iter()
+            .map(|field| field.to_string().trim_start_matches("r#").to_string())
+            .collect::<Vec<_>>()
+            .join("_")
+    }
+}
+
+pub(crate) fn do_derive_paginated_resource(
+    item: proc_macro2::TokenStream,
+) -> Result<(proc_macro2::TokenStream, Vec<Error>), Error> {
+    let input: syn::DeriveInput = syn::parse2(item)?;
+    let dropshot = get_crate(None);
+    let name = &input.ident;
+    let vis = &input.vis;
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(&input.generics, USAGE));
+    }
+    let fields = match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => return Err(Error::new_spanned(&input.ident, USAGE)),
+    };
+
+    let mut errors = Vec::new();
+    let mut sorts = Vec::<Sort>::new();
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("paginate")) {
+        let mut sort_by = None;
+        let mut then = Vec::new();
+        let result = attr.parse_nested_meta(|meta| {
+            let field = if meta.path.is_ident("sort_by") {
+                &mut sort_by
+            } else if meta.path.is_ident("then") {
+                then.push(None);
+                then.last_mut().unwrap()
+            } else {
+                return Err(meta.error("expected `sort_by` or `then`"));
+            };
+            let value: syn::LitStr = meta.value()?.parse()?;
+            let ident = fields
+                .iter()
+                .filter_map(|f| f.ident.as_ref())
+                .find(|ident| {
+                    ident.to_string().trim_start_matches("r#") == value.value()
+                })
+                .ok_or_else(|| {
+                    syn::Error::new_spanned(
+                        &value,
+                        format!("no field named `{}`", value.value()),
+                    )
+                })?;
+            *field = Some(ident.clone());
+            Ok(())
+        });
+        match (result, sort_by) {
+            (Err(error), _) => errors.push(error),
+            (Ok(()), None) => errors.push(Error::new_spanned(
+                attr,
+                "#[paginate] requires `sort_by`",
+            )),
+            (Ok(()), Some(sort_by)) => {
+                let sort = Sort {
+                    fields: std::iter::once(sort_by)
+                        .chain(then.into_iter().flatten())
+                        .collect(),
+                };
+                if sorts.iter().any(|s| s.variant() == sort.variant()) {
+                    errors.push(Error::new_spanned(attr, "duplicate sort"));
+                } else {
+                    sorts.push(sort);
+                }
+            }
+        }
+    }
+    if sorts.is_empty() {
+        return Err(Error::new_spanned(&input.ident, USAGE));
+    }
+
+    let sort_by_name = format_ident!("{}SortBy", name);
+    let scan_params_name = format_ident!("{}ScanParams", name);
+    let page_selector_name = format_ident!("{}PageSelector", name);
+
+    let types = fields
+        .iter()
+        .filter_map(|f| f.ident.as_ref().map(|ident| (ident, &f.ty)))
+        .collect::<Vec<_>>();
+    let key_type = |sort: &Sort| {
+        let types = sort.fields.iter().map(|field| {
+            let (_, ty) =
+                types.iter().find(|(ident, _)| *ident == field).unwrap();
+            ty
+        });
+        if sort.fields.len() == 1 {
+            quote! { #(#types)* }
+        } else {
+            quote! { (#(#types,)*) }
+        }
+    };
+    let key_value = |sort: &Sort| {
+        let values = sort.fields.iter().map(|field| {
+            quote! { ::std::clone::Clone::clone(&item.#field) }
+        });
+        if sort.fields.len() == 1 {
+            quote! { #(#values)* }
+        } else {
+            quote! { (#(#values,)*) }
+        }
+    };
+
+    let variants = sorts.iter().map(Sort::variant).collect::<Vec<_>>();
+    let wire_names = sorts.iter().map(Sort::wire_name).collect::<Vec<_>>();
+    let key_types = sorts.iter().map(key_type).collect::<Vec<_>>();
+    let key_values = sorts.iter().map(key_value).collect::<Vec<_>>();
+    let default_variant = &variants[0];
+    let sort_by_doc =
+        format!("Sorts by which a list of {} can be ordered", name);
+    let scan_params_doc =
+        format!("Parameters of the first request to list {} items", name);
+    let page_selector_doc =
+        format!("Position of a scan through a list of {} items", name);
+
+    let stream = quote! {
+        #[doc = #sort_by_doc]
+        #[derive(
+            Clone,
+            Copy,
+            Debug,
+            Eq,
+            PartialEq,
+            ::serde::Deserialize,
+            ::serde::Serialize,
+            ::schemars::JsonSchema,
+        )]
+        #vis enum #sort_by_name {
+            #(
+                #[serde(rename = #wire_names)]
+                #variants,
+            )*
+        }
+
+        impl ::std::default::Default for #sort_by_name {
+            fn default() -> Self {
+                #sort_by_name::#default_variant
+            }
+        }
+
+        #[doc = #scan_params_doc]
+        #[derive(
+            Clone,
+            Copy,
+            Debug,
+            PartialEq,
+            ::serde::Deserialize,
+            ::serde::Serialize,
+            ::schemars::JsonSchema,
+        )]
+        #vis struct #scan_params_name {
+            /// how to sort the items
+            #[serde(default)]
+            pub sort_by: #sort_by_name,
+            /// order in which to list the items
+            #[serde(default)]
+            pub order: #dropshot::PaginationOrder,
+        }
+
+        impl #dropshot::KeysetScanParams for #scan_params_name {
+            fn order(&self) -> #dropshot::PaginationOrder {
+                self.order
+            }
+        }
+
+        #[doc = #page_selector_doc]
+        #[derive(Clone, ::serde::Deserialize, ::serde::Serialize)]
+        #[serde(tag = "sort_by")]
+        #vis enum #page_selector_name {
+            #(
+                #[serde(rename = #wire_names)]
+                #variants {
+                    order: #dropshot::PaginationOrder,
+                    last_seen: #key_types,
+                },
+            )*
+        }
+
+        impl #dropshot::PaginatedResource for #name {
+            type ScanParams = #scan_params_name;
+            type PageSelector = #page_selector_name;
+
+            fn page_selector_for(
+                item: &Self,
+                scan_params: &#scan_params_name,
+            ) -> #page_selector_name {
+                let order = scan_params.order;
+                match scan_params.sort_by {
+                    #(
+                        #sort_by_name::#variants =>
+                            #page_selector_name::#variants {
+                                order,
+                                last_seen: #key_values,
+                            },
+                    )*
+                }
+            }
+
+            fn scan_params_for(
+                page_selector: &#page_selector_name,
+            ) -> #scan_params_name {
+                match page_selector {
+                    #(
+                        #page_selector_name::#variants { order, .. } =>
+                            #scan_params_name {
+                                sort_by: #sort_by_name::#variants,
+                                order: *order,
+                            },
+                    )*
+                }
+            }
+        }
+    };
+
+    Ok((stream, errors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::do_derive_paginated_resource;
+    use quote::quote;
+
+    #[test]
+    fn test_derive_paginated_resource() {
+        let (item, errors) = do_derive_paginated_resource(quote! {
+            #[paginate(sort_by = "name")]
+            #[paginate(sort_by = "time_modified", then = "name")]
+            pub struct Project {
+                name: String,
+                time_modified: u64,
+            }
+        })
+        .unwrap();
+        assert!(errors.is_empty());
+
+        let expected = quote! {
+            #[doc = "Sorts by which a list of Project can be ordered"]
+            #[derive(
+                Clone,
+                Copy,
+                Debug,
+                Eq,
+                PartialEq,
+                ::serde::Deserialize,
+                ::serde::Serialize,
+                ::schemars::JsonSchema,
+            )]
+            pub enum ProjectSortBy {
+                #[serde(rename = "name")]
+                Name,
+                #[serde(rename = "time_modified_name")]
+                TimeModifiedName,
+            }
+
+            impl ::std::default::Default for ProjectSortBy {
+                fn default() -> Self {
+                    ProjectSortBy::Name
+                }
+            }
+
+            #[doc = "Parameters of the first request to list Project items"]
+            #[derive(
+                Clone,
+                Copy,
+                Debug,
+                PartialEq,
+                ::serde::Deserialize,
+                ::serde::Serialize,
+                ::schemars::JsonSchema,
+            )]
+            pub struct ProjectScanParams {
+                /// how to sort the items
+                #[serde(default)]
+                pub sort_by: ProjectSortBy,
+                /// order in which to list the items
+                #[serde(default)]
+                pub order: dropshot::PaginationOrder,
+            }
+
+            impl dropshot::KeysetScanParams for ProjectScanParams {
+                fn order(&self) -> dropshot::PaginationOrder {
+                    self.order
+                }
+            }
+
+            #[doc = "Position of a scan through a list of Project items"]
+            #[derive(Clone, ::serde::Deserialize, ::serde::Serialize)]
+            #[serde(tag = "sort_by")]
+            pub enum ProjectPageSelector {
+                #[serde(rename = "name")]
+                Name {
+                    order: dropshot::PaginationOrder,
+                    last_seen: String,
+                },
+                #[serde(rename = "time_modified_name")]
+                TimeModifiedName {
+                    order: dropshot::PaginationOrder,
+                    last_seen: (u64, String,),
+                },
+            }
+
+            impl dropshot::PaginatedResource for Project {
+                type ScanParams = ProjectScanParams;
+                type PageSelector = ProjectPageSelector;
+
+                fn page_selector_for(
+                    item: &Self,
+                    scan_params: &ProjectScanParams,
+                ) -> ProjectPageSelector {
+                    let order = scan_params.order;
+                    match scan_params.sort_by {
+                        ProjectSortBy::Name => ProjectPageSelector::Name {
+                            order,
+                            last_seen: ::std::clone::Clone::clone(&item.name),
+                        },
+                        ProjectSortBy::TimeModifiedName =>
+                            ProjectPageSelector::TimeModifiedName {
+                                order,
+                                last_seen: (
+                                    ::std::clone::Clone::clone(
+                                        &item.time_modified
+                                    ),
+                                    ::std::clone::Clone::clone(&item.name),
+                                ),
+                            },
+                    }
+                }
+
+                fn scan_params_for(
+                    page_selector: &ProjectPageSelector,
+                ) -> ProjectScanParams {
+                    match page_selector {
+                        ProjectPageSelector::Name { order, .. } =>
+                            ProjectScanParams {
+                                sort_by: ProjectSortBy::Name,
+                                order: *order,
+                            },
+                        ProjectPageSelector::TimeModifiedName { order, .. } =>
+                            ProjectScanParams {
+                                sort_by: ProjectSortBy::TimeModifiedName,
+                                order: *order,
+                            },
+                    }
+                }
+            }
+        };
+        assert_eq!(item.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn test_derive_paginated_resource_errors() {
+        let (_, errors) = do_derive_paginated_resource(quote! {
+            #[paginate(sort_by = "name")]
+            #[paginate(sort_by = "size")]
+            #[paginate(then = "name")]
+            #[paginate(sort_by = "name", order = "ascending")]
+            #[paginate(sort_by = "name")]
+            struct Project {
+                name: String,
+            }
+        })
+        .unwrap();
+        assert_eq!(errors.len(), 4);
+
+        let error = do_derive_paginated_resource(quote! {
+            struct Project {
+                name: String,
+            }
+        })
+        .unwrap_err();
+        assert!(error.to_string().starts_with("PaginatedResource may only"));
+    }
+}
@@ -0,0 +1,280 @@
+// Copyright 2023 Oxide Computer Company
+
+//! Implementation of `#[derive(RequestParams)]`
+
+use quote::format_ident;
+use quote::quote;
+use serde_tokenstream::Error;
+
+use crate::get_crate;
+
+const USAGE: &str = "RequestParams may only be derived for structs with \
+    named fields, each marked #[dropshot(path)] or #[dropshot(query)]";
+
+#[derive(Clone, Copy, PartialEq)]
+enum ParamSource {
+    Path,
+    Query,
+}
+
+pub(crate) fn do_derive_request_params(
+    item: proc_macro2::TokenStream,
+) -> Result<(proc_macro2::TokenStream, Vec<Error>), Error> {
+    let input: syn::DeriveInput = syn::parse2(item)?;
+    let dropshot = get_crate(None);
+    let name = &input.ident;
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(&input.generics, USAGE));
+    }
+    let fields = match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => return Err(Error::new_spanned(&input.ident, USAGE)),
+    };
+
+    // serde and schemars attributes on the struct apply to both halves.
+    let container_attrs = input
+        .attrs
+        .iter()
+        .filter(|attr| {
+            attr.path().is_ident("serde") || attr.path().is_ident("schemars")
+        })
+        .collect::<Vec<_>>();
+
+    let mut errors = Vec::new();
+    let mut path_fields = Vec::new();
+    let mut query_fields = Vec::new();
+    for field in fields {
+        let mut source = None;
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("dropshot"))
+        {
+            match attr.parse_args::<syn::Ident>() {
+                Ok(ident) if ident == "path" => {
+                    source = Some(ParamSource::Path)
+                }
+                Ok(ident) if ident == "query" => {
+                    source = Some(ParamSource::Query)
+                }
+                _ => errors.push(Error::new_spanned(
+                    attr,
+                    "expected #[dropshot(path)] or #[dropshot(query)]",
+                )),
+            }
+        }
+
+        // Carry over the field's other attributes (serde renames,
+        // documentation, and so on) along with the field itself.
+        let mut field = field.clone();
+        field.attrs.retain(|attr| !attr.path().is_ident("dropshot"));
+        field.vis = syn::Visibility::Inherited;
+        match source {
+            Some(ParamSource::Path) => path_fields.push(field),
+            Some(ParamSource::Query) => query_fields.push(field),
+            None => errors.push(Error::new_spanned(
+                &field,
+                "field must be marked #[dropshot(path)] or #[dropshot(query)]",
+            )),
+        }
+    }
+
+    // Each half is deserialized into a hidden struct by the corresponding
+    // extractor, and the fields are then moved into the caller's struct.
+    let half = |source: ParamSource, fields: &[syn::Field]| {
+        let (struct_name, var, extractor) = match source {
+            ParamSource::Path => (
+                format_ident!("__{}PathParams", name),
+                format_ident!("__path"),
+                quote! { #dropshot::Path },
+            ),
+            ParamSource::Query => (
+                format_ident!("__{}QueryParams", name),
+                format_ident!("__query"),
+                quote! { #dropshot::Query },
+            ),
+        };
+        if fields.is_empty() {
+            return (quote! {}, quote! {}, quote! {}, Vec::new());
+        }
+        let extractor = quote! { #extractor<#struct_name> };
+        let definition = quote! {
+            #[derive(::serde::Deserialize, ::schemars::JsonSchema)]
+            #( #container_attrs )*
+            #[allow(non_camel_case_types)]
+            struct #struct_name {
+                #( #fields, )*
+            }
+        };
+        let extract = quote! {
+            let #var =
+                <#extractor as #dropshot::SharedExtractor>::from_request(rqctx)
+                    .await?
+                    .into_inner();
+        };
+        let metadata = quote! {
+            let mut half = <#extractor as #dropshot::SharedExtractor>::metadata(
+                body_content_type.clone(),
+            );
+            if half.extension_mode != #dropshot::ExtensionMode::None {
+                metadata.extension_mode = half.extension_mode;
+            }
+            metadata.parameters.append(&mut half.parameters);
+        };
+        let moves = fields
+            .iter()
+            .map(|field| {
+                let ident = &field.ident;
+                quote! { #ident: #var.#ident }
+            })
+            .collect::<Vec<_>>();
+        (definition, extract, metadata, moves)
+    };
+    let (path_definition, path_extract, path_metadata, path_moves) =
+        half(ParamSource::Path, &path_fields);
+    let (query_definition, query_extract, query_metadata, query_moves) =
+        half(ParamSource::Query, &query_fields);
+
+    let stream = quote! {
+        const _: () = {
+            #path_definition
+            #query_definition
+
+            #[#dropshot::__private::async_trait]
+            impl #dropshot::SharedExtractor for #name {
+                async fn from_request<Context: #dropshot::ServerContext>(
+                    rqctx: &#dropshot::RequestContext<Context>,
+                ) -> Result<Self, #dropshot::HttpError> {
+                    #path_extract
+                    #query_extract
+                    Ok(#name {
+                        #( #path_moves, )*
+                        #( #query_moves, )*
+                    })
+                }
+
+                fn metadata(
+                    body_content_type: #dropshot::ApiEndpointBodyContentType,
+                ) -> #dropshot::ExtractorMetadata {
+                    let mut metadata = #dropshot::ExtractorMetadata::default();
+                    #path_metadata
+                    #query_metadata
+                    let _ = body_content_type;
+                    metadata
+                }
+            }
+        };
+    };
+
+    Ok((stream, errors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::do_derive_request_params;
+    use quote::quote;
+
+    #[test]
+    fn test_derive_request_params() {
+        let (item, errors) = do_derive_request_params(quote! {
+            #[serde(rename_all = "kebab-case")]
+            struct Params {
+                /// the widget
+                #[dropshot(path)]
+                pub widget_id: u32,
+                #[dropshot(query)]
+                verbose: Option<bool>,
+            }
+        })
+        .unwrap();
+        assert!(errors.is_empty());
+
+        let expected = quote! {
+            const _: () = {
+                #[derive(::serde::Deserialize, ::schemars::JsonSchema)]
+                #[serde(rename_all = "kebab-case")]
+                #[allow(non_camel_case_types)]
+                struct __ParamsPathParams {
+                    /// the widget
+                    widget_id: u32,
+                }
+                #[derive(::serde::Deserialize, ::schemars::JsonSchema)]
+                #[serde(rename_all = "kebab-case")]
+                #[allow(non_camel_case_types)]
+                struct __ParamsQueryParams {
+                    verbose: Option<bool>,
+                }
+
+                #[dropshot::__private::async_trait]
+                impl dropshot::SharedExtractor for Params {
+                    async fn from_request<Context: dropshot::ServerContext>(
+                        rqctx: &dropshot::RequestContext<Context>,
+                    ) -> Result<Self, dropshot::HttpError> {
+                        let __path = <dropshot::Path<__ParamsPathParams>
+                            as dropshot::SharedExtractor>::from_request(rqctx)
+                            .await?
+                            .into_inner();
+                        let __query = <dropshot::Query<__ParamsQueryParams>
+                            as dropshot::SharedExtractor>::from_request(rqctx)
+                            .await?
+                            .into_inner();
+                        Ok(Params {
+                            widget_id: __path.widget_id,
+                            verbose: __query.verbose,
+                        })
+                    }
+
+                    fn metadata(
+                        body_content_type: dropshot::ApiEndpointBodyContentType,
+                    ) -> dropshot::ExtractorMetadata {
+                        let mut metadata =
+                            dropshot::ExtractorMetadata::default();
+                        let mut half = <dropshot::Path<__ParamsPathParams>
+                            as dropshot::SharedExtractor>::metadata(
+                                body_content_type.clone(),
+                            );
+                        if half.extension_mode
+                            != dropshot::ExtensionMode::None
+                        {
+                            metadata.extension_mode = half.extension_mode;
+                        }
+                        metadata.parameters.append(&mut half.parameters);
+                        let mut half = <dropshot::Query<__ParamsQueryParams>
+                            as dropshot::SharedExtractor>::metadata(
+                                body_content_type.clone(),
+                            );
+                        if half.extension_mode
+                            != dropshot::ExtensionMode::None
+                        {
+                            metadata.extension_mode = half.extension_mode;
+                        }
+                        metadata.parameters.append(&mut half.parameters);
+                        let _ = body_content_type;
+                        metadata
+                    }
+                }
+            };
+        };
+        assert_eq!(item.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn test_derive_request_params_errors() {
+        let (_, errors) = do_derive_request_params(quote! {
+            struct Params {
+                unmarked: u32,
+                #[dropshot(body)]
+                wrong: u32,
+            }
+        })
+        .unwrap();
+        assert_eq!(errors.len(), 3);
+
+        let error = do_derive_request_params(quote! {
+            struct Params(u32);
+        })
+        .unwrap_err();
+        assert!(error.to_string().starts_with("RequestParams may only"));
+    }
+}